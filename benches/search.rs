@@ -0,0 +1,160 @@
+//! Search and indexing paths on synthetic repositories of increasing size.
+//! Run with `cargo bench --bench search`; the tools print their results as they run.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use indiefuture_cli::chunking;
+use indiefuture_cli::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance};
+use indiefuture_cli::subtasks::SubTaskType;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use serde_json::json;
+use std::path::Path;
+
+/// Files in each synthetic repository
+const REPO_SIZES: [usize; 3] = [100, 1_000, 5_000];
+
+/// Functions in each synthetic source file for chunking
+const SOURCE_SIZES: [usize; 3] = [10, 100, 1_000];
+
+/// Fragments in context memory for relevance scoring
+const MEMORY_SIZES: [usize; 3] = [10, 100, 1_000];
+
+/// A Rust file with `functions` small functions, one struct and one impl block
+fn rust_source(seed: usize, functions: usize) -> String {
+    let mut source = format!(
+        "use std::collections::HashMap;\n\npub struct Item{seed} {{\n    name: String,\n}}\n\n\
+         impl Item{seed} {{\n    pub fn name(&self) -> &str {{\n        &self.name\n    }}\n}}\n"
+    );
+    for i in 0..functions {
+        source.push_str(&format!(
+            "\n/// Handles case {i}\npub fn handle_{seed}_{i}(input: &HashMap<String, u32>) -> u32 {{\n    \
+             let total: u32 = input.values().sum();\n    total + {i}\n}}\n"
+        ));
+    }
+    source
+}
+
+/// A workspace of `files` Rust sources spread over nested modules; every tenth file
+/// defines `parse_config` so searches have a few hits
+fn synthetic_repo(files: usize) -> TempWorkspace {
+    let workspace = TempWorkspace::new().unwrap();
+    for i in 0..files {
+        let mut source = rust_source(i, 5);
+        if i % 10 == 0 {
+            source.push_str("\npub fn parse_config(text: &str) -> Config {\n    todo!()\n}\n");
+        }
+        workspace
+            .write(&format!("src/module_{}/file_{}.rs", i / 50, i), &source)
+            .unwrap();
+    }
+    workspace
+        .write("README.md", "# Synthetic\n\nNot a source file.\n")
+        .unwrap();
+    workspace
+}
+
+fn memory_with(fragments: usize) -> ContextMemory {
+    let mut memory = ContextMemory::default();
+    for i in 0..fragments {
+        memory.add_frag(MemoryFragment {
+            source: if i % 3 == 0 {
+                "grep_search"
+            } else {
+                "file_read"
+            }
+            .to_string(),
+            content: rust_source(i, 3),
+            provenance: Provenance::FileRead,
+            metadata: Some(MemoryMetadata {
+                file_type: Some("file".to_string()),
+                path: Some(format!("src/module_{}/file_{}.rs", i / 50, i)),
+                timestamp: None,
+                tags: vec![format!("module_{}", i / 50)],
+            }),
+        });
+    }
+    memory
+}
+
+fn run_tool(
+    runtime: &tokio::runtime::Runtime,
+    workspace: &TempWorkspace,
+    subtask: SubTaskType,
+) -> bool {
+    let ai = FakeAiClient::new();
+    runtime.block_on(workspace.run(subtask, &ai)).0.is_some()
+}
+
+fn grep(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("grep_tool");
+    group.sample_size(10);
+    for files in REPO_SIZES {
+        let workspace = synthetic_repo(files);
+        group.bench_with_input(BenchmarkId::from_parameter(files), &files, |b, _| {
+            b.iter(|| {
+                let grep = SubTaskType::GrepTool(
+                    serde_json::from_value(json!({
+                        "pattern": "fn parse_config",
+                        "include": "src/**/*.rs",
+                        "max_results": 5
+                    }))
+                    .unwrap(),
+                );
+                run_tool(&runtime, &workspace, grep)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn glob(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("glob_tool");
+    group.sample_size(10);
+    for files in REPO_SIZES {
+        let workspace = synthetic_repo(files);
+        group.bench_with_input(BenchmarkId::from_parameter(files), &files, |b, _| {
+            b.iter(|| {
+                let glob = SubTaskType::GlobTool(
+                    serde_json::from_value(json!({ "pattern": "src/**/*.rs", "max_results": 5 }))
+                        .unwrap(),
+                );
+                run_tool(&runtime, &workspace, glob)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn chunking(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunk_source");
+    for functions in SOURCE_SIZES {
+        let source = rust_source(0, functions);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(functions),
+            &source,
+            |b, source| b.iter(|| chunking::chunk_source(Path::new("lib.rs"), source)),
+        );
+    }
+    group.finish();
+}
+
+fn relevance(c: &mut Criterion) {
+    let mut group = c.benchmark_group("relevant_fragments");
+    for fragments in MEMORY_SIZES {
+        let memory = memory_with(fragments);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(fragments),
+            &memory,
+            |b, memory| {
+                b.iter(|| {
+                    memory.relevant_fragments("update handle_3_1 in module_0 to use the config", 5)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, grep, glob, chunking, relevance);
+criterion_main!(benches);
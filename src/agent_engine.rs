@@ -1,14 +1,61 @@
 use crate::memory::ContextMemory;
-use crate::memory::MemoryFragment;
+use crate::memory::{MemoryFragment, Provenance, USER_ANSWER_SOURCE};
+use crate::model_router::ModelRouter;
+use crate::model_switch::{self, ModelSwitch};
+use crate::mutants;
+use crate::subtasks::subtask::EditSetToolInputs;
 use crate::subtasks::subtask::SubTask;
 use crate::subtasks::subtask::SubTaskType;
-use cliclack::{self, confirm, log, spinner};
+use cliclack::{self, input};
 use tokio::sync::Mutex;
 
+use crate::ai::reasoning;
+use crate::analysis_cache::AnalysisCache;
+use crate::approval::ApprovalMode;
+use crate::background::{self, BackgroundJobs, Job};
+use crate::change_summary::{self, ChangeHistory};
+use crate::command_preview;
+use crate::context_limit;
+use crate::crash;
+use crate::dirty_tree::{self, AutoStash, DirtyTreePolicy};
+use crate::embeddings::{self, EmbeddingIndex};
+use crate::error::{AgentError, AgentResult};
+use crate::error_lookup;
+use crate::formatting;
+use crate::git;
+use crate::hunk_review;
+use crate::hybrid_search;
+use crate::interrupt;
+use crate::journal::{self, ApprovalSource, Journal, JournalEntry};
+use crate::keys;
+use crate::knowledge::ProjectKnowledge;
+use crate::notifications::{self, RunStats};
+use crate::output;
+use crate::platform;
+use crate::preferences::UserPreferences;
+use crate::profiling::{RunProfiler, SubtaskProfile};
+use crate::project_context;
+use crate::remote_approval::RemoteApprover;
+use crate::replanning;
+use crate::report::TurnReport;
+use crate::result_validation;
+use crate::shell_session;
+use crate::storage::Storage;
+use crate::theme::{self, Role};
+use crate::tool_env;
+use crate::usage::UsageTracker;
+use crate::utils;
+use crate::verification;
+use crate::workspace_lock::WorkspaceLock;
 use crate::AiClient;
 use crate::Settings;
-use std::env::args;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Recorded when a question could not be put to the user
+const NO_ANSWER: &str =
+    "No answer was given; proceed with your best judgement and state the assumption.";
 
 #[derive(Default)]
 pub struct AgentEngine {
@@ -16,35 +63,433 @@ pub struct AgentEngine {
     pub active_subtasks: Vec<SubtaskSlot>,
 
     pub context_memory: ContextMemory,
-    // pub user_confirmation_callback: Option<Box<dyn Fn(&SubTask) -> bool + Send + Sync>>,
+
+    pub approval_mode: ApprovalMode,
+    pub pending_mutations: Vec<SubtaskSlot>, // deferred in plan mode until the batched approval
+    pub remote_approver: Option<Arc<RemoteApprover>>, // answers prompts from another device
+    pub desktop_notifications: bool,         // alert the user when a prompt is waiting for them
+    pub profiler: Option<Arc<RunProfiler>>,  // set with --profile-run
+    pub turn_report: TurnReport,             // what the latest turn did, for `/report`
+    pub dirty_tree: DirtyTreePolicy,         // checked before the first edit or command of a run
+    pub auto_stash: Option<AutoStash>,       // the user's changes, set aside until the run ends
+    pub background: Option<Arc<BackgroundJobs>>, // embeds changed files and compacts memory
+    pub explain_commands: bool, // explain commands in plain words before asking to run them
+    pub read_only: bool,        // skip every subtask that is not read-only, as `ask` does
+    pub correction_asked: bool, // asked this turn what to do differently after a decline
+
+                                // pub user_confirmation_callback: Option<Box<dyn Fn(&SubTask) -> bool + Send + Sync>>,
 }
 
 #[derive(Clone, Debug)]
 pub struct SubtaskSlot {
     depth: usize,
     subtask: SubTaskType,
+    approved: bool, // already approved as part of a batch
+    priority: Priority,
+    ran: Option<RanAhead>, // set when it already ran alongside an earlier step
+}
+
+/// A step run alongside an earlier one, waiting for its turn to be stored
+#[derive(Clone, Debug)]
+struct RanAhead {
+    output: Option<SubtaskOutput>,
+    fragments: Vec<MemoryFragment>, // what it added to its copy of context memory while running
+}
+
+impl RanAhead {
+    /// Add its fragments to `context_memory`, as if it had run now, and return its output
+    async fn store(self, context_memory: &Mutex<ContextMemory>) -> Option<SubtaskOutput> {
+        let mut memory = context_memory.lock().await;
+        for fragment in self.fragments {
+            memory.add_frag(fragment);
+        }
+        self.output
+    }
+}
+
+/// How urgently a queued subtask should run; equal priorities keep stack order
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Selects queued subtasks to cancel, labelled so the user can see what was dropped
+#[derive(Clone)]
+pub struct SubtaskFilter {
+    pub label: String,
+    predicate: Arc<dyn Fn(&SubTaskType) -> bool + Send + Sync>,
+}
+
+impl SubtaskFilter {
+    pub fn new(
+        label: impl Into<String>,
+        predicate: impl Fn(&SubTaskType) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    /// Every queued subtask run by the tool called `tool_name`
+    pub fn tool(tool_name: &str) -> Self {
+        let tool_name = tool_name.to_string();
+        Self::new(format!("{} subtasks", tool_name), move |subtask| {
+            subtask.tool_name() == tool_name
+        })
+    }
+
+    pub fn matches(&self, subtask: &SubTaskType) -> bool {
+        (self.predicate)(subtask)
+    }
+}
+
+/// A concrete subtask offered as one resolution of an ambiguous step
+#[derive(Clone, Debug)]
+pub struct SubtaskChoice {
+    pub subtask: SubTaskType,
+    pub parameters: String, // tool call arguments, shown so the user can tell choices apart
+}
+
+impl std::fmt::Debug for SubtaskFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubtaskFilter")
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
 }
 
 impl AgentEngine {
     pub fn push_subtask(&mut self, new_subtask: SubTaskType) {
+        self.push_subtask_with_priority(new_subtask, Priority::Normal);
+    }
+
+    pub fn push_subtask_with_priority(&mut self, new_subtask: SubTaskType, priority: Priority) {
         let current_depth = self.current_subtask_depth;
 
-        self.active_subtasks.push(SubtaskSlot {
+        self.queue_slot(SubtaskSlot {
             depth: current_depth,
             subtask: new_subtask,
+            approved: false,
+            priority,
+            ran: None,
         });
     }
 
+    /// The stack pops from the end, so keep it sorted with the most urgent slots last
+    fn queue_slot(&mut self, slot: SubtaskSlot) {
+        let index = self
+            .active_subtasks
+            .partition_point(|queued| queued.priority <= slot.priority);
+        self.active_subtasks.insert(index, slot);
+    }
+
+    /// Drop queued and plan-mode deferred subtasks matching `filter`; returns how many
+    pub fn cancel_matching(&mut self, filter: &SubtaskFilter) -> usize {
+        let before = self.active_subtasks.len() + self.pending_mutations.len();
+        self.active_subtasks
+            .retain(|slot| !filter.matches(&slot.subtask));
+        self.pending_mutations
+            .retain(|slot| !filter.matches(&slot.subtask));
+        before - self.active_subtasks.len() - self.pending_mutations.len()
+    }
+
+    /// Replace the remaining steps at the current depth and below with `plan`, run in order.
+    /// Shallower slots, such as a parent task waiting to retry with more context, are kept.
+    /// Returns how many queued subtasks were dropped.
+    pub fn replace_queue(&mut self, plan: Vec<SubTaskType>) -> usize {
+        let before = self.active_subtasks.len();
+        let current_depth = self.current_subtask_depth;
+        self.active_subtasks
+            .retain(|slot| slot.depth < current_depth);
+        let dropped = before - self.active_subtasks.len();
+
+        for subtask in plan.into_iter().rev() {
+            self.push_subtask(subtask);
+        }
+        dropped
+    }
+
+    /// Ask once for every mutation deferred in plan mode; approved ones are queued to run
+    pub async fn review_pending_mutations(&mut self) -> bool {
+        let pending = std::mem::take(&mut self.pending_mutations);
+
+        let listing = pending
+            .iter()
+            .enumerate()
+            .map(|(i, slot)| {
+                let mut line = format!(
+                    "{}. {} {}",
+                    i + 1,
+                    slot.subtask.icon(),
+                    slot.subtask.description()
+                );
+                if let Some(preview) = slot.subtask.preview() {
+                    line.push_str(&format!("\n{}", styled_preview(&slot.subtask, &preview)));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        cliclack::log::info(format!("📋 Planned changes:\n{}", listing)).expect("Failed to log");
+
+        let question = format!("Apply all {} planned changes?", pending.len());
+        let approved = match &self.remote_approver {
+            Some(remote) => {
+                cliclack::log::info(format!(
+                    "📱 Waiting for remote approval at {}",
+                    remote.url()
+                ))
+                .expect("Failed to log");
+                remote.request(&question, Some(listing)).await
+            }
+            None => {
+                self.notify_waiting(&question);
+                keys::approve(&question, Some(&listing), true)
+            }
+        };
+
+        if !approved {
+            cliclack::log::info(format!("⨯ Discarded {} planned changes", pending.len()))
+                .expect("Failed to log");
+            return false;
+        }
+
+        // The stack pops from the end, so push in reverse to keep the planned order
+        for slot in pending.into_iter().rev() {
+            self.queue_slot(SubtaskSlot {
+                approved: true,
+                ..slot
+            });
+        }
+        true
+    }
+
+    /// Apply the dirty tree policy before the first mutation of a run. Returns false when
+    /// the run must stop instead.
+    fn check_working_tree(&mut self, root: &Path) -> bool {
+        if self.dirty_tree == DirtyTreePolicy::Off {
+            return true;
+        }
+
+        // Outside a git repository there is nothing to keep apart
+        let files = match dirty_tree::uncommitted_files(root) {
+            Ok(files) if !files.is_empty() => files,
+            _ => return true,
+        };
+
+        let mut listed = files
+            .iter()
+            .take(10)
+            .map(|file| format!("  {}", file))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if files.len() > 10 {
+            listed.push_str(&format!("\n  ... and {} more", files.len() - 10));
+        }
+
+        match self.dirty_tree {
+            DirtyTreePolicy::Block => {
+                cliclack::log::error(format!(
+                    "The working tree has uncommitted changes:\n{}\nCommit or stash them before the agent edits, or set DIRTY_TREE=warn or stash.",
+                    listed
+                ))
+                .expect("Failed to log");
+                false
+            }
+            DirtyTreePolicy::Stash => match AutoStash::push(root) {
+                Ok(stash) => {
+                    cliclack::log::info(format!(
+                        "📦 Stashed {} uncommitted file(s); they are restored after this run",
+                        files.len()
+                    ))
+                    .expect("Failed to log");
+                    self.auto_stash = stash;
+                    true
+                }
+                Err(e) => {
+                    cliclack::log::error(format!(
+                        "Failed to stash uncommitted changes, not editing: {}",
+                        e
+                    ))
+                    .expect("Failed to log");
+                    false
+                }
+            },
+            _ => {
+                cliclack::log::warning(format!(
+                    "The working tree has uncommitted changes; the agent's edits will be mixed in with them:\n{}",
+                    listed
+                ))
+                .expect("Failed to log");
+                true
+            }
+        }
+    }
+
+    /// Take the queued steps that can run alongside `slot`, at most `limit` with it: the
+    /// consecutive read-only ones at its depth that run without confirmation. Steps queued
+    /// after an edit or command wait for it, as that is where the run stops.
+    fn parallel_peers(&mut self, slot: &SubtaskSlot, limit: usize) -> Vec<SubtaskSlot> {
+        let mut peers = Vec::new();
+        if !slot.subtask.runs_in_parallel() {
+            return peers;
+        }
+        while peers.len() + 1 < limit {
+            match self.active_subtasks.last() {
+                Some(peer)
+                    if peer.depth == slot.depth
+                        && peer.ran.is_none()
+                        && peer.subtask.runs_in_parallel()
+                        && (peer.approved
+                            || !self.approval_mode.requires_confirmation(&peer.subtask)) =>
+                {
+                    peers.extend(self.active_subtasks.pop());
+                }
+                _ => break,
+            }
+        }
+        peers
+    }
+
+    /// Steps still queued in this run, next first, then those awaiting approval
+    pub fn queued_steps(&self) -> Vec<String> {
+        let mut steps: Vec<String> = self
+            .active_subtasks
+            .iter()
+            .rev()
+            .map(|slot| slot.subtask.description())
+            .collect();
+        steps.extend(
+            self.pending_mutations
+                .iter()
+                .map(|slot| format!("{} (awaiting approval)", slot.subtask.description())),
+        );
+        steps
+    }
+
+    /// Explain what the run has done and what remains, then let the user continue, add
+    /// guidance, or stop. Returns false when the run should stop.
+    async fn pause_and_explain(
+        &mut self,
+        shared_state: &SharedState,
+        context_memory: &Mutex<ContextMemory>,
+    ) -> bool {
+        let done: Vec<String> = self
+            .turn_report
+            .steps
+            .iter()
+            .map(|step| {
+                format!(
+                    "{}{}",
+                    step.description,
+                    if step.succeeded { "" } else { " (failed)" }
+                )
+            })
+            .collect();
+        let remaining = self.queued_steps();
+
+        let spin = output::spinner();
+        spin.start("Explaining where things stand...");
+        match interrupt::explain_progress(
+            shared_state.ai_client.as_ref(),
+            &self.turn_report.prompt,
+            &done,
+            &remaining,
+            context_memory,
+        )
+        .await
+        {
+            Ok(explanation) => {
+                spin.stop("⏸ Paused");
+                cliclack::log::info(explanation).expect("Failed to log");
+            }
+            Err(e) => spin.error(format!("Failed to explain progress: {}", e)),
+        }
+
+        let choice = cliclack::select("Continue the run?")
+            .item(
+                "continue",
+                "Continue",
+                format!("{} step(s) left", remaining.len()),
+            )
+            .item(
+                "guide",
+                "Add guidance and continue",
+                "the note is added to context",
+            )
+            .item("stop", "Stop here", "drop the remaining steps")
+            .interact()
+            .unwrap_or("continue");
+
+        match choice {
+            "stop" => {
+                self.active_subtasks.clear();
+                self.pending_mutations.clear();
+                cliclack::log::info("⨯ Run stopped").expect("Failed to log");
+                false
+            }
+            "guide" => {
+                let note: String = input("What should the agent know or do differently?")
+                    .required(false)
+                    .interact()
+                    .unwrap_or_default();
+                if !note.trim().is_empty() {
+                    context_memory.lock().await.add_frag(MemoryFragment {
+                        source: interrupt::USER_NOTE_SOURCE.to_string(),
+                        provenance: Provenance::UserProvided,
+                        content: format!("Guidance from the user mid-run: {}", note.trim()),
+                        metadata: None,
+                    });
+                }
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// Put back the changes stashed before this run, if any
+    fn restore_auto_stash(&mut self) {
+        let Some(stash) = self.auto_stash.take() else {
+            return;
+        };
+
+        match stash.restore() {
+            Ok(()) => cliclack::log::info("📦 Restored your stashed changes").expect("Failed to log"),
+            Err(e) => cliclack::log::warning(format!(
+                "Could not restore your stashed changes ({}). They are kept as {}; run `git stash pop` once you've reviewed the agent's edits.",
+                e,
+                stash.entry().unwrap_or_else(|_| "a stash".to_string())
+            ))
+            .expect("Failed to log"),
+        }
+    }
+
+    /// Start recording a new turn for `/report`
+    pub fn begin_turn(&mut self, prompt: &str) {
+        self.turn_report = TurnReport::new(prompt);
+        self.correction_asked = false;
+        crash::begin_turn(prompt);
+    }
+
     pub fn increment_subtask_depth(&mut self) {
         self.current_subtask_depth += 1;
 
-        println!("increment_subtask_depth {}", self.current_subtask_depth);
+        if output::is_verbose() {
+            println!("increment_subtask_depth {}", self.current_subtask_depth);
+        }
     }
 
     pub fn set_subtask_depth(&mut self, new_depth: usize) {
         self.current_subtask_depth = new_depth;
 
-        println!("set task depth {}", new_depth);
+        if output::is_verbose() {
+            println!("set task depth {}", new_depth);
+        }
     }
 
     /*  pub fn set_user_confirmation_callback(
@@ -54,26 +499,496 @@ impl AgentEngine {
         self.user_confirmation_callback = Some(callback);
     }*/
 
-    /// Ask for user confirmation before executing a subtask
-    pub async fn ask_user_confirmation(&self, subtask_type: SubTaskType) -> bool {
+    /// Show "step n/total" with the queued steps collapsed and a time estimate.
+    /// The total grows as tasks expand into subtasks, so it is recomputed every step.
+    fn log_progress(&self, step: usize, busy: Duration, current: &SubTaskType) {
+        let remaining = self.active_subtasks.len() + self.pending_mutations.len();
+        let total = step + remaining;
+
+        let mut line = format!(
+            "{} {} {}",
+            theme::paint(&format!("Step {}/{}:", step, total), Role::Accent),
+            current.icon(),
+            utils::truncate_string(&current.description(), 80)
+        );
+
+        // Executed steps so far give the average; the first step has nothing to go on
+        let completed = (step - 1) as u32;
+        if completed > 0 && remaining > 0 {
+            let estimate = busy / completed * remaining as u32;
+            line.push_str(&format!(" (~{}s left)", estimate.as_secs().max(1)));
+        }
+
+        // The stack pops from the end, so the next steps are at the back
+        let upcoming: Vec<String> = self
+            .active_subtasks
+            .iter()
+            .rev()
+            .take(3)
+            .map(|slot| utils::truncate_string(&slot.subtask.description(), 60))
+            .collect();
+        if !upcoming.is_empty() {
+            let mut next = format!("next: {}", upcoming.join(" › "));
+            if remaining > upcoming.len() {
+                next.push_str(&format!(" (+{} more)", remaining - upcoming.len()));
+            }
+            line.push_str(&format!("\n  {}", theme::paint(&next, Role::Muted)));
+        }
+
+        cliclack::log::step(line).expect("Failed to log");
+    }
+
+    /// Let a user who switched away know the run is blocked on them
+    fn notify_waiting(&self, operation: &str) {
+        if self.desktop_notifications {
+            notifications::desktop_notify("Agent is waiting for approval", operation);
+        }
+    }
+
+    /// Build and test the workspace after edits. On failure, pushes a fix task and returns true
+    /// until `verify_max_fix_attempts` is used up, then records the run as failed.
+    async fn verify_edits(
+        &mut self,
+        shared_state: &SharedState,
+        context_memory: &Mutex<ContextMemory>,
+        settings: &Settings,
+        fix_attempts: &mut usize,
+        mutants_reviewed: &mut bool,
+        stats: &mut RunStats,
+    ) -> bool {
+        let root = &shared_state.workspace_root;
+        let Some(verify) = verification::detect(root, settings.verify_command.as_deref()) else {
+            cliclack::log::remark("No build or test toolchain detected; skipping verification")
+                .expect("Failed to log");
+            return false;
+        };
+
+        let spin = output::spinner();
+        spin.start(format!("Verifying edits: {}", verify.command));
+
+        let outcome = match verification::run(root, &verify).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                spin.error(format!("Verification could not run: {}", e));
+                return false;
+            }
+        };
+
+        if outcome.success {
+            spin.stop(format!("✓ Verification passed ({})", verify.toolchain));
+            // Once per run, so strengthened tests are verified but not mutated again
+            if settings.mutation_review && !*mutants_reviewed {
+                *mutants_reviewed = true;
+                return self
+                    .review_mutants(root, context_memory, &stats.files_changed)
+                    .await;
+            }
+            return false;
+        }
+        spin.error(format!("⨯ Verification failed ({})", verify.toolchain));
+
+        if *fix_attempts >= settings.verify_max_fix_attempts {
+            cliclack::log::warning(format!(
+                "Edits still fail verification after {} fix attempt(s); last output:\n{}",
+                fix_attempts, outcome.output
+            ))
+            .expect("Failed to log");
+            stats.failed += 1;
+            return false;
+        }
+        *fix_attempts += 1;
+
+        context_memory.lock().await.add_frag(MemoryFragment {
+            source: "verification".to_string(),
+            provenance: Provenance::ToolOutput,
+            content: format!(
+                "Output of `{}` after the last edits:\n{}",
+                verify.command, outcome.output
+            ),
+            metadata: None,
+        });
+        // Explanations of the error codes go into context before the fix is planned
+        for code in error_lookup::reported_codes(&outcome.output)
+            .into_iter()
+            .take(error_lookup::MAX_CODES_PER_FAILURE)
+        {
+            match error_lookup::explain_code(&code).await {
+                Ok(explanation) => context_memory
+                    .lock()
+                    .await
+                    .add_frag(error_lookup::explanation_fragment(&code, &explanation)),
+                Err(e) => log::info!("No local explanation for {}: {}", code, e),
+            }
+        }
+        self.push_subtask(SubTaskType::Task(format!(
+            "The edits just made fail verification (`{}`). Fix the errors shown in the verification output in context memory. This is fix attempt {} of {}.",
+            verify.command, fix_attempts, settings.verify_max_fix_attempts
+        )));
+        true
+    }
+
+    /// Run cargo-mutants on the files this run changed and queue a task strengthening the
+    /// tests for the mutants they missed; true when one was queued
+    async fn review_mutants(
+        &mut self,
+        root: &Path,
+        context_memory: &Mutex<ContextMemory>,
+        changed: &[String],
+    ) -> bool {
+        let files = mutants::mutable_files(changed);
+        if files.is_empty() {
+            return false;
+        }
+
+        let spin = output::spinner();
+        spin.start(format!("Mutation testing {} changed file(s)", files.len()));
+        let missed = match mutants::run(root, &files).await {
+            Ok(missed) => missed,
+            Err(e) => {
+                spin.error(format!("Mutation testing could not run: {}", e));
+                return false;
+            }
+        };
+        if missed.is_empty() {
+            spin.stop("✓ The tests caught every mutant");
+            return false;
+        }
+        spin.stop(format!("🧬 {} mutant(s) survived the tests", missed.len()));
+
+        context_memory
+            .lock()
+            .await
+            .add_frag(mutants::fragment(&missed));
+        self.push_subtask(SubTaskType::Task(mutants::strengthen_task(&missed)));
+        true
+    }
+
+    /// Apply a cancellation or replacement of queued subtasks
+    fn revise_queue(&mut self, output: SubtaskOutput) {
+        match output {
+            SubtaskOutput::CancelMatching(filter) => {
+                let cancelled = self.cancel_matching(&filter);
+                cliclack::log::info(format!(
+                    "⨯ Cancelled {} queued subtask(s): {}",
+                    cancelled, filter.label
+                ))
+                .expect("Failed to log");
+            }
+            SubtaskOutput::ReplaceQueue(plan) => {
+                let steps = plan.len();
+                let dropped = self.replace_queue(plan);
+                cliclack::log::info(format!(
+                    "🔁 Revised plan: {} step(s) replace {} queued",
+                    steps, dropped
+                ))
+                .expect("Failed to log");
+            }
+            _ => {}
+        }
+    }
+
+    /// Make room after the model rejected a step's prompt as longer than its context window.
+    /// The first rejection halves context memory by compacting it; after another, the user
+    /// picks which of the largest fragments to remove. Returns whether to retry the step.
+    async fn make_room_for_context(
+        &self,
+        shared_state: &SharedState,
+        context_memory: &Mutex<ContextMemory>,
+        compacted: &mut bool,
+    ) -> bool {
+        let model = shared_state.ai_client.model_name();
+
+        if !*compacted {
+            *compacted = true;
+            let (budget, pinned) = {
+                let memory = context_memory.lock().await;
+                (
+                    memory.pinned_len() + memory.content_len() / 2,
+                    memory.pinned_len(),
+                )
+            };
+
+            let spin = output::spinner();
+            spin.start(format!(
+                "The prompt was too long for {}; compacting context memory...",
+                model
+            ));
+            let handoff =
+                model_switch::fit_memory(shared_state.ai_client.as_ref(), context_memory, budget)
+                    .await;
+            // The summary request may itself be too long; that is not the step's failure
+            context_limit::take();
+            let dropped = match handoff {
+                Ok(handoff) => handoff.dropped.len(),
+                Err(e) => {
+                    log::warn!("Failed to summarize context memory: {}", e);
+                    context_memory
+                        .lock()
+                        .await
+                        .fit_to(budget.saturating_sub(pinned))
+                        .len()
+                }
+            };
+            spin.stop(format!(
+                "Compacted context memory{}; retrying the step",
+                if dropped > 0 {
+                    format!(" and dropped {} of its oldest fragments", dropped)
+                } else {
+                    String::new()
+                }
+            ));
+            return true;
+        }
+
+        let candidates = context_memory
+            .lock()
+            .await
+            .largest_fragments(context_limit::REMOVAL_CANDIDATES);
+        if candidates.is_empty() {
+            cliclack::log::error(format!(
+                "The step is too long for {} even without context memory; try a model with a larger window (/model)",
+                model
+            ))
+            .expect("Failed to log");
+            return false;
+        }
+
+        cliclack::log::warning(format!(
+            "The prompt is still too long for {} after compacting context memory. These fragments take the most room:",
+            model
+        ))
+        .expect("Failed to log");
+        let removed = context_limit::choose_removals(&candidates);
+        if removed.is_empty() {
+            cliclack::log::info("Nothing removed; skipping the step").expect("Failed to log");
+            return false;
+        }
+
+        let mut memory = context_memory.lock().await;
+        for index in removed {
+            memory.remove(&candidates[index].0);
+        }
+        true
+    }
+
+    /// Let a quick model revise or abort the queued steps in light of what a step just found
+    async fn checkpoint_plan(
+        &mut self,
+        shared_state: &SharedState,
+        completed: &SubTaskType,
+        observation: &str,
+    ) {
+        // Steps at this depth and below belong to the plan being followed
+        let current_depth = self.current_subtask_depth;
+        let remaining: Vec<&SubTaskType> = self
+            .active_subtasks
+            .iter()
+            .rev()
+            .filter(|slot| slot.depth >= current_depth)
+            .map(|slot| &slot.subtask)
+            .collect();
+        if remaining.is_empty() {
+            return;
+        }
+
+        let decision = replanning::checkpoint(
+            shared_state.checkpoint_client(),
+            completed,
+            observation,
+            &remaining,
+        )
+        .await;
+        match decision {
+            Ok(decision) => {
+                if let Some(output) = decision.into_output() {
+                    self.revise_queue(output);
+                }
+            }
+            Err(e) => log::warn!("Plan checkpoint failed, continuing with the plan: {}", e),
+        }
+    }
+
+    /// Ask a question a subtask needs answered, offering `options` plus a free-form answer.
+    /// Returns None when nobody is at the terminal or the user skips it.
+    fn ask_user(&self, question: &str, options: &[String]) -> Option<String> {
+        if self.approval_mode == ApprovalMode::Auto || self.remote_approver.is_some() {
+            return None;
+        }
+        self.notify_waiting(question);
+
+        if !options.is_empty() {
+            let other = options.len();
+            let choice = options
+                .iter()
+                .enumerate()
+                .fold(cliclack::select(question), |prompt, (i, option)| {
+                    prompt.item(i, option, "")
+                })
+                .item(other, "Something else…", "type your own answer")
+                .interact()
+                .ok()?;
+            if choice < other {
+                return Some(options[choice].clone());
+            }
+        }
+
+        let answer: String = input(if options.is_empty() {
+            question
+        } else {
+            "Your answer"
+        })
+        .required(false)
+        .interact()
+        .ok()?;
+        let answer = answer.trim();
+        (!answer.is_empty()).then(|| answer.to_string())
+    }
+
+    /// Let the user pick between alternative subtasks for a step. Without anyone at the
+    /// terminal every alternative runs, as the planner's calls always did.
+    fn choose_subtasks(&self, question: &str, choices: &[SubtaskChoice]) -> Vec<SubTaskType> {
+        let all: Vec<SubTaskType> = choices.iter().map(|c| c.subtask.clone()).collect();
+        if self.approval_mode == ApprovalMode::Auto || self.remote_approver.is_some() {
+            return all;
+        }
+        self.notify_waiting(question);
+
+        let run_all = choices.len();
+        let skip = run_all + 1;
+        let choice = choices
+            .iter()
+            .enumerate()
+            .fold(cliclack::select(question), |prompt, (i, choice)| {
+                prompt.item(
+                    i,
+                    format!("{} {}", choice.subtask.icon(), choice.subtask.description()),
+                    &choice.parameters,
+                )
+            })
+            .item(run_all, "All of them", "run every option in turn")
+            .item(skip, "None", "skip this step")
+            .interact()
+            .unwrap_or(skip);
+
+        match choice {
+            i if i < run_all => vec![all[i].clone()],
+            i if i == run_all => all,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Ask a yes/no follow-up question; declined whenever nobody is at the terminal
+    fn offer_follow_up(&self, question: &str) -> bool {
+        if self.approval_mode == ApprovalMode::Auto || self.remote_approver.is_some() {
+            return false;
+        }
+
+        keys::approve(question, None, false)
+    }
+
+    /// Ask for user confirmation before executing a subtask. Returns the subtask to run,
+    /// which for an edit set reviewed hunk by hunk holds only the kept changes, or None
+    /// when the user declines.
+    pub async fn ask_user_confirmation(
+        &self,
+        subtask_type: SubTaskType,
+        ai_client: &dyn AiClient,
+    ) -> Option<SubTaskType> {
         // Display the subtask description
-        cliclack::log::info(&format!(
+        cliclack::log::info(format!(
             "{} Operation: {}",
             subtask_type.icon(),
             subtask_type.description()
         ))
         .expect("Failed to log");
 
+        let capabilities = subtask_type.capabilities();
+        if !capabilities.is_empty() {
+            cliclack::log::remark(format!(
+                "Needs: {}",
+                capabilities
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+            .expect("Failed to log");
+        }
+
+        // Commands come with their risk and, unless turned off, a plain explanation
+        let preview = match &subtask_type {
+            SubTaskType::Bash(inputs) if self.explain_commands => {
+                let spin = output::spinner();
+                spin.start("Explaining the command...");
+                let preview = command_preview::preview(ai_client, &inputs.command, true).await;
+                spin.clear();
+                Some(preview.render())
+            }
+            _ => subtask_type.preview(),
+        };
+        if let Some(preview) = &preview {
+            cliclack::log::info(styled_preview(&subtask_type, preview)).expect("Failed to log");
+        }
+
+        if let Some(remote) = &self.remote_approver {
+            cliclack::log::info(format!(
+                "📱 Waiting for remote approval at {}",
+                remote.url()
+            ))
+            .expect("Failed to log");
+            return remote
+                .request(&subtask_type.description(), preview)
+                .await
+                .then_some(subtask_type);
+        }
+
+        self.notify_waiting(&subtask_type.description());
+
+        if let SubTaskType::EditSetTool(inputs) = &subtask_type {
+            return Self::confirm_edit_set(inputs);
+        }
+
         // Create a temporary SubTask object for the callback
-        let _subtask = SubTask::new(subtask_type, None);
+        let _subtask = SubTask::new(subtask_type.clone(), None);
 
         // If a custom callback is provided, use it
 
         // Otherwise use the default confirmation prompt
-        confirm("Execute this operation?")
-            .initial_value(true)
+        keys::approve("Execute this operation?", preview.as_deref(), true).then_some(subtask_type)
+    }
+
+    /// Apply an edit set whole, or step through its hunks and keep only the accepted ones
+    fn confirm_edit_set(inputs: &EditSetToolInputs) -> Option<SubTaskType> {
+        let choice = cliclack::select("Apply these edits?")
+            .item("all", "Apply all", "")
+            .item(
+                "review",
+                "Review hunk by hunk",
+                "accept, reject, edit or split each change",
+            )
+            .item("decline", "Decline", "")
             .interact()
-            .unwrap_or(false)
+            .unwrap_or("decline");
+
+        match choice {
+            "all" => Some(SubTaskType::EditSetTool(inputs.clone())),
+            "review" => {
+                // Approval happens in the workspace, so the current directory is its root
+                let reviewed = std::env::current_dir()
+                    .map_err(Into::into)
+                    .and_then(|root| hunk_review::review(inputs, &root));
+                match reviewed {
+                    Ok(Some(reviewed)) => Some(SubTaskType::EditSetTool(reviewed)),
+                    Ok(None) => None,
+                    Err(e) => {
+                        cliclack::log::warning(format!("Hunk review failed: {}", e))
+                            .expect("Failed to log");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
     }
 
     pub async fn perform_subtask(
@@ -81,18 +996,25 @@ impl AgentEngine {
         subtask_type: SubTaskType,
         context_memory: Arc<Mutex<ContextMemory>>,
         shared_state: Arc<SharedState>,
-        settings: Arc<Settings>,
-    ) -> SubtaskOutput {
+        _settings: Arc<Settings>,
+    ) -> Option<SubtaskOutput> {
         // Get the appropriate tool for this subtask type
         let tool = subtask_type.get_tool();
 
-        let ai_client = &shared_state.ai_client;
+        let ai_client = shared_state.client_for(&subtask_type);
 
-        // Execute the subtask
-        match tool.handle_subtask(ai_client, context_memory).await {
-            Some(output) => output,
-            None => SubtaskOutput::SubtaskComplete(),
-        }
+        // Commands waiting for input are only asked about when someone can answer
+        shell_session::set_attended(
+            self.approval_mode != ApprovalMode::Auto && self.remote_approver.is_none(),
+        );
+
+        // Execute the subtask; None means the tool failed or had nothing to report
+        crash::set_active_subtask(Some(&subtask_type.description()));
+        let output = tool
+            .handle_subtask(ai_client, context_memory, &shared_state.workspace_root)
+            .await;
+        crash::set_active_subtask(None);
+        output
     }
 
     /*
@@ -106,95 +1028,361 @@ impl AgentEngine {
         shared_state: Arc<SharedState>,
         context_memory: Arc<Mutex<ContextMemory>>,
         settings: Arc<Settings>,
-    ) {
+    ) -> RunStats {
+        let mut stats = RunStats::default();
+        let mut busy = Duration::ZERO; // time spent executing, excluding prompts
+        let mut unverified_edits = false;
+        let mut fix_attempts = 0;
+        let mut mutants_reviewed = false;
+        let mut tree_checked = false;
+        let mut context_compacted = false; // for the step being retried after a too-long prompt
+        let mut validation_retries = 0; // for the step being retried after a rejected result
+
         // execute_command("task", &task_description, settings.clone()).await?;
 
         //need to handle differently if we are popping up to next depth or not !
 
         loop {
+            // Ctrl+C pauses between steps to explain the state of the run
+            if interrupt::take() && !self.pause_and_explain(&shared_state, &context_memory).await {
+                stats.declined = true;
+                break;
+            }
+
             if let Some(next_subtask) = self.active_subtasks.last() {
                 if next_subtask.depth != self.current_subtask_depth {
                     self.set_subtask_depth(next_subtask.depth);
                 }
             }
 
+            let Some(mut next_subtask) = self.active_subtasks.pop().clone() else {
+                //returns the last element !
 
- 
-
-
+                // In plan mode, mutations wait here until planning has finished
+                if !self.pending_mutations.is_empty() {
+                    let review_started = Instant::now();
+                    let approved = self.review_pending_mutations().await;
+                    if let Some(profiler) = &self.profiler {
+                        profiler.record(SubtaskProfile {
+                            label: "Batched approval".to_string(),
+                            kind: "approval".to_string(),
+                            ai: Duration::ZERO,
+                            tool: Duration::ZERO,
+                            user_wait: review_started.elapsed(),
+                        });
+                    }
+                    if approved {
+                        continue;
+                    }
+                }
 
+                // Check the plan's edits before reporting success, looping back to fix failures
+                if unverified_edits && !stats.declined && settings.verify_after_edits {
+                    unverified_edits = false;
+                    if self
+                        .verify_edits(
+                            &shared_state,
+                            &context_memory,
+                            &settings,
+                            &mut fix_attempts,
+                            &mut mutants_reviewed,
+                            &mut stats,
+                        )
+                        .await
+                    {
+                        continue;
+                    }
+                }
+                break;
+            };
 
-            let Some(next_subtask) = self.active_subtasks.pop() .clone() else {
+            if self.read_only && !next_subtask.subtask.is_read_only() {
+                cliclack::log::warning(format!(
+                    "⨯ Skipped in a read-only turn: {}",
+                    next_subtask.subtask.description()
+                ))
+                .expect("Failed to log");
+                continue;
+            }
 
- 
+            if !next_subtask.approved && self.approval_mode.defers(&next_subtask.subtask) {
+                cliclack::log::info(format!("⏸ Planned: {}", next_subtask.subtask.description()))
+                    .expect("Failed to log");
+                self.pending_mutations.push(next_subtask);
+                continue;
+            }
 
+            // Keep the user's uncommitted work out of the agent's diffs
+            if next_subtask.subtask.is_mutating() && !tree_checked {
+                tree_checked = true;
+                if !self.check_working_tree(&shared_state.workspace_root) {
+                    stats.declined = true;
+                    self.active_subtasks.clear();
+                    break;
+                }
+            }
 
-                //returns the last element !
-                break;
+            self.log_progress(stats.operations + 1, busy, &next_subtask.subtask);
+
+            let approval = if next_subtask.approved {
+                ApprovalSource::Batch
+            } else if self
+                .approval_mode
+                .requires_confirmation(&next_subtask.subtask)
+            {
+                match self.remote_approver {
+                    Some(_) => ApprovalSource::Remote,
+                    None => ApprovalSource::User,
+                }
+            } else {
+                ApprovalSource::Automatic
             };
 
-            let confirmed = match next_subtask.subtask.requires_user_permission() {
-                true => {
-                    self.ask_user_confirmation(next_subtask.subtask.clone())
-                        .await
+            let wait_started = Instant::now();
+            let confirmed = match approval {
+                ApprovalSource::User | ApprovalSource::Remote => {
+                    self.ask_user_confirmation(
+                        next_subtask.subtask.clone(),
+                        shared_state.ai_client.as_ref(),
+                    )
+                    .await
                 }
-                false => true,
+                _ => Some(next_subtask.subtask.clone()),
             };
+            let user_wait = wait_started.elapsed();
 
             match confirmed {
-                true => {
-                    cliclack::log::info("✓ Operation approved").expect("Failed to log");
+                Some(subtask) => {
+                    next_subtask.subtask = subtask;
 
-                    let spin = spinner();
-                    spin.start("Processing task... ");
+                    cliclack::log::info("✓ Operation approved").expect("Failed to log");
 
                     cliclack::log::info(format!(" TASK {:?}", next_subtask.subtask))
                         .expect("Failed to log");
 
-                    let subtask_output = self
-                        .perform_subtask(
+                    let step_started = Instant::now();
+                    let ai_before = self.profiler.as_ref().map(|p| p.ai_time());
+                    // Only a rejection of this step's own prompts counts
+                    context_limit::take();
+                    let peers = match approval {
+                        ApprovalSource::Automatic | ApprovalSource::Batch => {
+                            self.parallel_peers(&next_subtask, settings.parallel_tools)
+                        }
+                        _ => Vec::new(),
+                    };
+                    let mut subtask_output = if let Some(ran) = next_subtask.ran.take() {
+                        ran.store(&context_memory).await
+                    } else if !peers.is_empty() {
+                        // Each sees the context so far, pinned fragments included, in a copy of
+                        // its own; what it adds there is stored when its turn comes
+                        let (seed, seeded) = {
+                            let memory = context_memory.lock().await;
+                            (memory.clone(), memory.fragment_count())
+                        };
+                        let memories: Vec<Arc<Mutex<ContextMemory>>> = (0..=peers.len())
+                            .map(|_| Arc::new(Mutex::new(seed.clone())))
+                            .collect();
+                        let batch = std::iter::once(&next_subtask)
+                            .chain(&peers)
+                            .zip(&memories)
+                            .map(|(slot, memory)| {
+                                self.perform_subtask(
+                                    slot.subtask.clone(),
+                                    Arc::clone(memory),
+                                    Arc::clone(&shared_state),
+                                    Arc::clone(&settings),
+                                )
+                            });
+                        let label = format!("Running {} steps at once... ", peers.len() + 1);
+                        let spin = output::spinner();
+                        spin.start(&label);
+                        let outputs =
+                            reasoning::spin_while(&spin, &label, futures::future::join_all(batch))
+                                .await;
+                        spin.stop("Tasks analyzed ✓");
+
+                        let mut ran = Vec::new();
+                        for (output, memory) in outputs.into_iter().zip(memories) {
+                            let fragments = memory
+                                .lock()
+                                .await
+                                .get_fragments()
+                                .get(seeded..)
+                                .map(<[_]>::to_vec)
+                                .unwrap_or_default();
+                            ran.push(RanAhead { output, fragments });
+                        }
+                        // The others wait at the top of the stack, to be stored in planned order
+                        let first = ran.remove(0);
+                        for (mut peer, ran) in peers.into_iter().zip(ran).rev() {
+                            peer.ran = Some(ran);
+                            self.active_subtasks.push(peer);
+                        }
+                        first.store(&context_memory).await
+                    } else {
+                        let perform = self.perform_subtask(
                             next_subtask.subtask.clone(),
                             Arc::clone(&context_memory),
                             Arc::clone(&shared_state),
                             Arc::clone(&settings),
+                        );
+                        // Interactive commands own the terminal, so no spinner draws over them
+                        if next_subtask.subtask.is_interactive() {
+                            perform.await
+                        } else {
+                            let spin = output::spinner();
+                            spin.start("Processing task... ");
+                            let output =
+                                reasoning::spin_while(&spin, "Processing task... ", perform).await;
+                            spin.stop("Task analyzed ✓");
+                            output
+                        }
+                    };
+
+                    let step_time = step_started.elapsed();
+                    busy += step_time;
+
+                    // A prompt too long for the model is retried with compacted memory, then
+                    // with whatever the user chooses to remove
+                    if context_limit::take() {
+                        if self
+                            .make_room_for_context(
+                                &shared_state,
+                                &context_memory,
+                                &mut context_compacted,
+                            )
+                            .await
+                        {
+                            self.queue_slot(next_subtask);
+                            continue;
+                        }
+                    } else {
+                        context_compacted = false;
+                    }
+
+                    // Results failing the tool's validators are retried rather than stored
+                    let mut rejection = None;
+                    if let Some(SubtaskOutput::AddToContextMemory(fragment)) = &subtask_output {
+                        rejection = result_validation::validate(
+                            next_subtask.subtask.tool_name(),
+                            &fragment.content,
+                            shared_state.ai_client.as_ref(),
                         )
                         .await;
+                    }
+                    if let Some(reason) = &rejection {
+                        if validation_retries < settings.result_validation_retries {
+                            validation_retries += 1;
+                            cliclack::log::warning(format!(
+                                "⨯ Result rejected ({}); retrying, attempt {} of {}",
+                                reason, validation_retries, settings.result_validation_retries
+                            ))
+                            .expect("Failed to log");
+                            self.queue_slot(next_subtask);
+                            continue;
+                        }
+                        cliclack::log::warning(format!(
+                            "⨯ Result rejected ({}); not stored in context",
+                            reason
+                        ))
+                        .expect("Failed to log");
+                        subtask_output = None;
+                    }
+                    validation_retries = 0;
+
+                    if let (Some(profiler), Some(ai_before)) = (&self.profiler, ai_before) {
+                        let ai = profiler.ai_time().saturating_sub(ai_before);
+                        profiler.record(SubtaskProfile {
+                            label: next_subtask.subtask.description(),
+                            kind: next_subtask.subtask.tool_name().to_string(),
+                            ai,
+                            tool: step_time.saturating_sub(ai),
+                            user_wait,
+                        });
+                    }
+                    stats.operations += 1;
+                    if subtask_output.is_none() {
+                        stats.failed += 1;
+                    }
 
-                    spin.stop("Task analyzed ✓");
-
-
-
-
-
-
-
-
-
-
-
-
-                    let task_is_completed = match   next_subtask.subtask .clone() {
-
-                    	SubTaskType::Task( _ ) => false, // for now 
-                    	_ => true 
-                    }; 
-
-                    if !task_is_completed {  //re push self to stack 
-                    	 cliclack::log::info("⨯ Task not yet complete - retrying with more context ").expect("Failed to log");
-                    	self.push_subtask(next_subtask.subtask.clone() );
+                    if next_subtask.subtask.is_mutating() {
+                        if let Err(e) = shared_state
+                            .journal_operation(
+                                &next_subtask.subtask,
+                                approval,
+                                subtask_output.is_some(),
+                            )
+                            .await
+                        {
+                            log::warn!("Failed to journal operation: {}", e);
+                        }
                     }
 
+                    self.turn_report
+                        .record_step(&next_subtask.subtask, subtask_output.as_ref());
+                    if let Some(explanation) = context_memory.lock().await.take_explanation() {
+                        self.turn_report.explanation = Some(explanation);
+                    }
 
+                    // What the step turned up, for the plan checkpoint below
+                    let observation = match &subtask_output {
+                        None if rejection.is_some() => rejection
+                            .map(|reason| format!("The step's result was rejected: {}", reason)),
+                        None => Some("The step failed or produced no result.".to_string()),
+                        Some(SubtaskOutput::AddToContextMemory(fragment)) => {
+                            Some(fragment.content.clone())
+                        }
+                        _ => None,
+                    };
 
+                    let subtask_output = subtask_output.unwrap_or(SubtaskOutput::SubtaskComplete());
 
+                    let touched_files = next_subtask.subtask.touched_files();
+                    if !touched_files.is_empty() {
+                        unverified_edits = true;
+                        if settings.auto_format {
+                            format_touched_files(&shared_state.workspace_root, &touched_files)
+                                .await;
+                        }
+                        shared_state.record_touched_files(&touched_files);
+                        if let Some(jobs) = &self.background {
+                            for file in &touched_files {
+                                jobs.enqueue(Job::EmbedFile(file.clone())).await;
+                            }
+                        }
+                        stats.merge(RunStats {
+                            files_changed: touched_files,
+                            ..RunStats::default()
+                        });
+                    }
 
+                    // Tasks are retried with more context only while the user is in the loop to stop them
+                    let task_is_completed = match next_subtask.subtask.clone() {
+                        SubTaskType::Task(_) => !self
+                            .approval_mode
+                            .requires_confirmation(&next_subtask.subtask),
+                        _ => true,
+                    };
+
+                    if !task_is_completed {
+                        //re push self to stack
+                        cliclack::log::info(
+                            "⨯ Task not yet complete - retrying with more context ",
+                        )
+                        .expect("Failed to log");
+                        self.push_subtask(next_subtask.subtask.clone());
+                    }
 
                     match subtask_output {
                         SubtaskOutput::AddToContextMemory(ref memory_fragment) => {
-                            context_memory
-                                .lock()
-                                .await
-                                .add_frag(memory_fragment.clone());
+                            let mut memory = context_memory.lock().await;
+                            memory.add_frag(memory_fragment.clone());
+                            let oversized = memory.content_len() > background::COMPACT_AFTER_CHARS;
+                            drop(memory);
+
+                            if let Some(jobs) = self.background.as_ref().filter(|_| oversized) {
+                                jobs.enqueue(Job::CompactMemory).await;
+                            }
                         }
 
                         SubtaskOutput::PushSubtasks(ref new_tasks_array) => {
@@ -219,44 +1407,499 @@ impl AgentEngine {
                             }
                         }
 
-                        _ => {
-                            // ???
+                        SubtaskOutput::PushPrioritized(ref new_tasks_array, priority) => {
+                            for new_subtask in new_tasks_array {
+                                self.push_subtask_with_priority(new_subtask.clone(), priority);
+                            }
                         }
-                    }
 
+                        SubtaskOutput::AskUser(ref question, ref options) => {
+                            let answer = self.ask_user(question, options);
+                            context_memory.lock().await.add_frag(MemoryFragment {
+                                source: USER_ANSWER_SOURCE.to_string(),
+                                provenance: Provenance::UserProvided,
+                                content: format!(
+                                    "Q: {}\nA: {}",
+                                    question,
+                                    answer.as_deref().unwrap_or(NO_ANSWER)
+                                ),
+                                metadata: None,
+                            });
+
+                            // The subtask resumes with the answer in context memory
+                            if task_is_completed {
+                                self.push_subtask(next_subtask.subtask.clone());
+                            }
+                        }
 
+                        SubtaskOutput::ChooseSubtask(ref question, ref choices) => {
+                            for new_subtask in self.choose_subtasks(question, choices) {
+                                self.push_subtask(new_subtask);
+                            }
+                        }
 
+                        SubtaskOutput::Progress(percent, ref message) => {
+                            cliclack::log::step(format!("⏳ {}% {}", percent.min(100), message))
+                                .expect("Failed to log");
+                            if percent < 100 && task_is_completed {
+                                self.push_subtask(next_subtask.subtask.clone());
+                            }
+                        }
 
+                        SubtaskOutput::CancelMatching(_) | SubtaskOutput::ReplaceQueue(_) => {
+                            self.revise_queue(subtask_output);
+                        }
 
- 
+                        SubtaskOutput::RememberFact(ref fact) => {
+                            match shared_state.remember_fact(fact, &context_memory).await {
+                                Ok(()) => cliclack::log::info(format!("📌 Remembered: {}", fact))
+                                    .expect("Failed to log"),
+                                Err(e) => cliclack::log::warning(format!(
+                                    "Failed to remember fact: {}",
+                                    e
+                                ))
+                                .expect("Failed to log"),
+                            }
+                        }
 
-                   // cliclack::log::info(format!(" {:?}", subtask_output)).expect("Failed to log");
-                }
-                false => {
+                        SubtaskOutput::SemanticSearch(ref query, limit) => {
+                            match shared_state
+                                .semantic_search(query, limit, &context_memory)
+                                .await
+                            {
+                                Ok(0) => cliclack::log::warning(
+                                    "No indexed code matches; files are indexed as they change",
+                                )
+                                .expect("Failed to log"),
+                                Ok(found) => cliclack::log::info(format!(
+                                    "🧭 Found {} matching chunks",
+                                    found
+                                ))
+                                .expect("Failed to log"),
+                                Err(e) => {
+                                    cliclack::log::warning(format!("Semantic search failed: {}", e))
+                                        .expect("Failed to log")
+                                }
+                            }
+                        }
+
+                        SubtaskOutput::HybridSearch(ref query, limit) => {
+                            match shared_state
+                                .hybrid_search(query, limit, &context_memory)
+                                .await
+                            {
+                                Ok(0) => cliclack::log::warning(format!(
+                                    "Nothing in the workspace matches '{}'",
+                                    query
+                                ))
+                                .expect("Failed to log"),
+                                Ok(found) => cliclack::log::info(format!(
+                                    "🔦 Found {} matching places",
+                                    found
+                                ))
+                                .expect("Failed to log"),
+                                Err(e) => {
+                                    cliclack::log::warning(format!("Hybrid search failed: {}", e))
+                                        .expect("Failed to log")
+                                }
+                            }
+                        }
+
+                        SubtaskOutput::OfferFollowUp(ref question, ref follow_up)
+                            if self.offer_follow_up(question) =>
+                        {
+                            for new_subtask in follow_up {
+                                self.push_subtask(new_subtask.clone());
+                            }
+                        }
 
+                        _ => {
+                            // ???
+                        }
+                    }
 
-                 
+                    if let Some(observation) = observation.filter(|_| settings.replan_checkpoints) {
+                        self.checkpoint_plan(&shared_state, &next_subtask.subtask, &observation)
+                            .await;
+                    }
 
+                    // cliclack::log::info(format!(" {:?}", subtask_output)).expect("Failed to log");
+                }
+                None => {
                     cliclack::log::info("⨯ Operation declined").expect("Failed to log");
+                    stats.declined = true;
+
+                    // Nobody is at the terminal to explain a remote decline, and one
+                    // explanation per turn is enough
+                    if self.remote_approver.is_some() || self.correction_asked {
+                        break;
+                    }
+                    self.correction_asked = true;
+
+                    // Learn from the decline so recurring corrections become preferences
+                    let correction: String =
+                        input("What should the agent do differently? (optional)")
+                            .placeholder("e.g. use anyhow for errors, never auto-commit")
+                            .required(false)
+                            .interact()
+                            .unwrap_or_default();
+
+                    if !correction.trim().is_empty() {
+                        if let Err(e) = shared_state
+                            .record_correction(&correction, &context_memory)
+                            .await
+                        {
+                            cliclack::log::warning(format!("Failed to record correction: {}", e))
+                                .expect("Failed to log");
+                        }
+                    }
+
                     break;
                 }
             }
 
             //handle this next subtask
         }
+
+        self.restore_auto_stash();
+
+        // A plan pinned by a model handoff is done with once its run is
+        context_memory
+            .lock()
+            .await
+            .unpin(model_switch::CURRENT_PLAN_SOURCE);
+        stats
+    }
+}
+
+/// Run the project's formatter over files the agent wrote, so diffs carry no style noise
+async fn format_touched_files(root: &Path, files: &[String]) {
+    for file in files {
+        let path = root.join(file);
+        let Some(formatter) = formatting::detect(root, &path) else {
+            continue;
+        };
+
+        match formatting::format_file(root, &path, &formatter).await {
+            Ok(()) => cliclack::log::remark(format!("Formatted {} with {}", file, formatter.name))
+                .expect("Failed to log"),
+            Err(e) => cliclack::log::warning(format!("Left {} unformatted: {}", file, e))
+                .expect("Failed to log"),
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum SubtaskOutput {
     PushSubtasksIncrementDepth(Vec<SubTaskType>), // add subtasks in a deeper depth to try and grow context -- once those are all popped off and handled, we have more context to try again !
     PushSubtasks(Vec<SubTaskType>),
     AddToContextMemory(MemoryFragment),
 
+    RememberFact(String), // persist a durable project fact in the workspace knowledge store
+
+    SemanticSearch(String, usize), // query and most results; add the matching chunks as cited fragments
+    HybridSearch(String, usize), // query and most results; add one fragment ranking keyword and semantic hits
+
+    OfferFollowUp(String, Vec<SubTaskType>), // subtasks to push only if the user accepts the question
+
+    PushPrioritized(Vec<SubTaskType>, Priority), // queue ahead of (or behind) normal subtasks
+    CancelMatching(SubtaskFilter),               // drop queued subtasks that are no longer wanted
+    ReplaceQueue(Vec<SubTaskType>), // re-planned remaining steps, replacing those at this depth
+
+    AskUser(String, Vec<String>), // question and suggested answers; the subtask re-runs once answered
+    Progress(u8, String), // percent done and status; the subtask re-runs until it reaches 100
+    ChooseSubtask(String, Vec<SubtaskChoice>), // alternatives for one step; the user picks which run
+
     SubtaskComplete(), //we have enough context to do an AI Query or to move on
                        //SubtaskFailed, // we are giving up . when would this happen ?
 }
 
+/// A preview as shown in the terminal, with the diffs of pending edits in the theme's colors
+fn styled_preview(subtask: &SubTaskType, preview: &str) -> String {
+    match subtask {
+        SubTaskType::EditSetTool(_) | SubTaskType::WriteFileTool(_) => theme::diff(preview),
+        _ => preview.to_string(),
+    }
+}
+
 pub struct SharedState {
     pub ai_client: Box<dyn AiClient>,
+    pub checkpoint_client: Option<Box<dyn AiClient>>, // cheaper model for plan checkpoints
+    pub model_switch: Option<ModelSwitch>, // changes the model behind ai_client; None if fixed
+    pub model_router: ModelRouter, // models some kinds of subtasks run on instead of ai_client
+    pub storage: Storage,
+    pub workspace_root: PathBuf,
+    pub workspace_id: String, // this checkout, for its journal and caches
+    pub project_id: String, // machine-independent id so knowledge and the index are shared across teammates
+    pub session_id: String,
+    pub workspace_lock: Option<WorkspaceLock>,
+    pub usage: Arc<UsageTracker>, // tokens and cost of this session's model requests
+}
+
+impl SharedState {
+    /// State for a fresh session in `workspace_root` on `ai_client` alone, with no other
+    /// models, no workspace lock and no usage yet
+    pub fn new(ai_client: Box<dyn AiClient>, storage: Storage, workspace_root: &Path) -> Self {
+        Self {
+            ai_client,
+            checkpoint_client: None,
+            model_switch: None,
+            model_router: ModelRouter::default(),
+            storage,
+            workspace_root: workspace_root.to_path_buf(),
+            workspace_id: utils::workspace_id(workspace_root),
+            project_id: utils::project_id(workspace_root),
+            session_id: utils::generate_id(),
+            workspace_lock: None,
+            usage: Arc::new(UsageTracker::new()),
+        }
+    }
+
+    /// Client for plan checkpoints, falling back to the main model
+    pub fn checkpoint_client(&self) -> &dyn AiClient {
+        self.checkpoint_client
+            .as_deref()
+            .unwrap_or(self.ai_client.as_ref())
+    }
+
+    /// Client `subtask` runs on: the model it is routed to, or the main model
+    pub fn client_for(&self, subtask: &SubTaskType) -> &dyn AiClient {
+        self.model_router
+            .client_for(subtask, self.ai_client.as_ref())
+    }
+
+    /// Open the project knowledge store for the current workspace
+    pub fn project_knowledge(&self) -> ProjectKnowledge {
+        ProjectKnowledge::open(&self.storage, &self.project_id)
+    }
+
+    /// Persist a project fact and refresh the pinned knowledge fragment
+    pub async fn remember_fact(
+        &self,
+        fact: &str,
+        context_memory: &Mutex<ContextMemory>,
+    ) -> AgentResult<()> {
+        let knowledge = self.project_knowledge();
+        knowledge.remember(fact).await?;
+        knowledge.pin_into(context_memory).await
+    }
+
+    /// Add a fragment for each indexed chunk most similar to `query`, citing its lines.
+    /// Returns how many were added.
+    pub async fn semantic_search(
+        &self,
+        query: &str,
+        limit: usize,
+        context_memory: &Mutex<ContextMemory>,
+    ) -> AgentResult<usize> {
+        let hits = self
+            .embedding_index()
+            .search(self.ai_client.as_ref(), query, limit)
+            .await?;
+        let fragments: Vec<MemoryFragment> = hits
+            .iter()
+            .filter_map(|hit| embeddings::hit_fragment(&self.workspace_root, hit))
+            .collect();
+
+        let found = fragments.len();
+        let mut memory = context_memory.lock().await;
+        for fragment in fragments {
+            memory.add_frag(fragment);
+        }
+        Ok(found)
+    }
+
+    /// Grep for the query's keywords and search the embedding index at once, and add one
+    /// fragment ranking what either found. Returns the number of results. Without a usable
+    /// index the keyword results stand alone.
+    pub async fn hybrid_search(
+        &self,
+        query: &str,
+        limit: usize,
+        context_memory: &Mutex<ContextMemory>,
+    ) -> AgentResult<usize> {
+        let keywords = hybrid_search::keywords(query);
+        let grep = {
+            let workspace_root = self.workspace_root.clone();
+            let keywords = keywords.clone();
+            tokio::task::spawn_blocking(move || {
+                hybrid_search::keyword_hits(&workspace_root, &keywords, limit)
+            })
+        };
+        let index = self.embedding_index();
+        let semantic = index.search(self.ai_client.as_ref(), query, limit);
+        let (keyword_hits, semantic_hits) = tokio::join!(grep, semantic);
+
+        let keyword_hits = keyword_hits
+            .map_err(|e| AgentError::TaskExecution(format!("Keyword search failed: {}", e)))?;
+        let semantic_hits = semantic_hits.unwrap_or_else(|e| {
+            log::warn!(
+                "Semantic search failed, ranking keyword matches only: {}",
+                e
+            );
+            Vec::new()
+        });
+
+        let hits = hybrid_search::fuse(keyword_hits, semantic_hits, limit);
+        let Some((fragment, found)) =
+            hybrid_search::results_fragment(&self.workspace_root, query, &keywords, &hits)
+        else {
+            return Ok(0);
+        };
+        context_memory.lock().await.add_frag(fragment);
+        Ok(found)
+    }
+
+    /// Open the current user's preference store
+    pub fn user_preferences(&self) -> UserPreferences {
+        UserPreferences::open(&self.storage)
+    }
+
+    /// Record a user correction and refresh the pinned preferences fragment
+    pub async fn record_correction(
+        &self,
+        correction: &str,
+        context_memory: &Mutex<ContextMemory>,
+    ) -> AgentResult<()> {
+        let preferences = self.user_preferences();
+        preferences.record_correction(correction).await?;
+        preferences.pin_into(context_memory).await
+    }
+
+    /// Open the per-commit cache of project analysis for the current workspace
+    pub fn analysis_cache(&self) -> AnalysisCache {
+        AnalysisCache::open(&self.storage, &self.workspace_id)
+    }
+
+    /// Open the project's index of file embeddings by the current embedding model
+    pub fn embedding_index(&self) -> EmbeddingIndex {
+        EmbeddingIndex::open(
+            &self.storage,
+            &self.project_id,
+            &self.ai_client.embedding_model(),
+        )
+    }
+
+    /// Open the record of changes the agent made in the current workspace
+    pub fn change_history(&self) -> ChangeHistory {
+        ChangeHistory::open(&self.storage, &self.workspace_id)
+    }
+
+    /// Summarize what a turn changed, store it with the workspace's change history and
+    /// refresh the pinned recent changes fragment
+    pub async fn summarize_changes(
+        &self,
+        report: &TurnReport,
+        context_memory: &Mutex<ContextMemory>,
+    ) -> AgentResult<String> {
+        let summary =
+            change_summary::generate(self.ai_client.as_ref(), &self.workspace_root, report).await?;
+
+        let history = self.change_history();
+        history
+            .record(&report.prompt, &report.files_changed, &summary)
+            .await?;
+        history.pin_into(context_memory).await?;
+
+        Ok(summary)
+    }
+
+    /// Open the operation journal for the current workspace
+    pub fn journal(&self) -> Journal {
+        Journal::open(&self.storage, &self.workspace_id)
+    }
+
+    /// Append an executed mutating operation to the workspace journal
+    pub async fn journal_operation(
+        &self,
+        subtask: &SubTaskType,
+        approval: ApprovalSource,
+        succeeded: bool,
+    ) -> AgentResult<()> {
+        let command = match subtask {
+            SubTaskType::Bash(inputs) => Some(tool_env::mask(&inputs.command)),
+            _ => None,
+        };
+
+        let entry = JournalEntry {
+            id: utils::generate_id(),
+            timestamp: utils::current_timestamp(),
+            session_id: self.session_id.clone(),
+            user: self.storage.user().to_string(),
+            operation: tool_env::mask(&subtask.description()),
+            command,
+            files: subtask.touched_files(),
+            diff_hash: subtask.change_text().as_deref().map(journal::hash_change),
+            approval,
+            succeeded,
+        };
+
+        crash::record(format!(
+            "{} ({} approval): {}",
+            if succeeded { "succeeded" } else { "failed" },
+            entry.approval,
+            entry.operation
+        ));
+        self.journal().append(&entry).await
+    }
+
+    /// Record files this session edited and warn when another session edited them too
+    pub fn record_touched_files(&self, files: &[String]) {
+        let Some(lock) = &self.workspace_lock else {
+            return;
+        };
+
+        let files: Vec<String> = files
+            .iter()
+            .map(|file| {
+                let path = self.workspace_root.join(file);
+                utils::path_to_string(&path.canonicalize().unwrap_or(path))
+            })
+            .collect();
+
+        let overlaps = match lock.record_touched(&files) {
+            Ok(overlaps) => overlaps,
+            Err(e) => {
+                log::warn!("Failed to update workspace lock: {}", e);
+                return;
+            }
+        };
+
+        for overlap in overlaps {
+            // Show the combined working-tree changes so both sessions' edits are visible
+            let diff = git::run_git(&self.workspace_root, &["diff", "--", &overlap.file])
+                .unwrap_or_default();
+
+            cliclack::log::warning(format!(
+                "{} was also edited by another session ({}, pid {}). Combined changes:\n{}",
+                overlap.file,
+                overlap.other.user,
+                overlap.other.pid,
+                if diff.is_empty() {
+                    "(no uncommitted diff)".to_string()
+                } else {
+                    theme::diff(&diff)
+                }
+            ))
+            .expect("Failed to log");
+        }
+    }
+
+    /// Pin the project's AGENTS.md, long-term knowledge and preferences into context memory
+    pub async fn load_pinned_context(
+        &self,
+        context_memory: &Mutex<ContextMemory>,
+    ) -> AgentResult<()> {
+        {
+            let mut memory = context_memory.lock().await;
+            memory.pin_frag(platform::environment_fragment(&self.workspace_root));
+            match project_context::agent_instructions(&self.workspace_root) {
+                Some(fragment) => memory.pin_frag(fragment),
+                None => memory.unpin(project_context::AGENT_INSTRUCTIONS_SOURCE),
+            }
+        }
+        self.project_knowledge().pin_into(context_memory).await?;
+        self.change_history().pin_into(context_memory).await?;
+        self.user_preferences().pin_into(context_memory).await
+    }
 }
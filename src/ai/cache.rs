@@ -0,0 +1,194 @@
+use crate::ai::reasoning::ReasoningEffort;
+use crate::ai::{AiClient, ChatCompletionResponse, GenerationProfile, Message, TextStream};
+use crate::error::AgentResult;
+use crate::storage::{Storage, StorageTree};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// The user tree cached responses are kept in; they can hold anything sent as context
+pub const CACHE_TREE: &str = "response_cache";
+
+/// A stored response and when it was stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse<T> {
+    created: i64, // unix seconds
+    response: T,
+}
+
+/// A client that answers a request it has answered before from storage, instead of calling
+/// the model again. Requests match when the provider, model, sampling options, messages and
+/// functions are all the same; failed calls and broken streams are not stored.
+pub struct CachingAiClient {
+    inner: Box<dyn AiClient>,
+    tree: StorageTree,
+    ttl: Duration,
+    options: String, // the profile and reasoning effort set on this clone, part of the key
+}
+
+impl CachingAiClient {
+    pub fn new(inner: Box<dyn AiClient>, storage: &Storage, ttl: Duration) -> Self {
+        Self {
+            inner,
+            tree: storage.user_tree(CACHE_TREE),
+            ttl,
+            options: String::new(),
+        }
+    }
+
+    fn with_inner(&self, inner: Box<dyn AiClient>, option: String) -> Self {
+        Self {
+            inner,
+            tree: self.tree.clone(),
+            ttl: self.ttl,
+            options: format!("{}{}", self.options, option),
+        }
+    }
+
+    /// The key of a request: a sha256 of everything that decides its response
+    pub fn key(&self, call: &str, request: &Value) -> String {
+        let identity = json!({
+            "call": call,
+            "provider": self.inner.provider_name(),
+            "model": self.inner.model_name(),
+            "options": self.options,
+            "request": request,
+        });
+        let digest = Sha256::digest(identity.to_string().as_bytes());
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    async fn lookup<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let cached: CachedResponse<T> = match self.tree.get(key).await {
+            Ok(cached) => cached?,
+            Err(e) => {
+                log::warn!("Failed to read the response cache: {}", e);
+                return None;
+            }
+        };
+        let age = chrono::Utc::now().timestamp() - cached.created;
+        if age > self.ttl.as_secs() as i64 {
+            return None;
+        }
+        log::debug!("Answered from the response cache ({}s old)", age);
+        Some(cached.response)
+    }
+
+    async fn store<T: Serialize>(tree: &StorageTree, key: &str, response: T) {
+        let cached = CachedResponse {
+            created: chrono::Utc::now().timestamp(),
+            response,
+        };
+        if let Err(e) = tree.put(key, &cached).await {
+            log::warn!("Failed to write the response cache: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl AiClient for CachingAiClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        let key = self.key("text", &json!(messages));
+        if let Some(text) = self.lookup(&key).await {
+            return Ok(text);
+        }
+        let text = self.inner.generate_text(messages).await?;
+        Self::store(&self.tree, &key, &text).await;
+        Ok(text)
+    }
+
+    /// A cached answer arrives as one piece; a new one is stored once it has fully arrived
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        // Streamed and whole answers are the same text
+        let key = self.key("text", &json!(messages));
+        if let Some(text) = self.lookup::<String>(&key).await {
+            return Ok(stream::once(async move { Ok(text) }).boxed());
+        }
+
+        let tokens = self.inner.stream_text(messages).await?;
+        let store = Some((self.tree.clone(), key));
+        Ok(stream::unfold(
+            (tokens, String::new(), store),
+            |(mut tokens, mut text, store)| async move {
+                match tokens.next().await {
+                    Some(Ok(token)) => {
+                        text.push_str(&token);
+                        Some((Ok(token), (tokens, text, store)))
+                    }
+                    // A broken-off answer is never stored
+                    Some(Err(e)) => Some((Err(e), (tokens, text, None))),
+                    None => {
+                        if let Some((tree, key)) = store {
+                            Self::store(&tree, &key, &text).await;
+                        }
+                        None
+                    }
+                }
+            },
+        )
+        .boxed())
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        messages: Vec<Message>,
+        functions: Value,
+        force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        let key = self.key(
+            "functions",
+            &json!({
+                "messages": messages,
+                "functions": functions,
+                "force_message_only": force_message_only,
+            }),
+        );
+        if let Some(response) = self.lookup(&key).await {
+            return Ok(response);
+        }
+        let response = self
+            .inner
+            .chat_completion_with_functions(messages, functions, force_message_only)
+            .await?;
+        Self::store(&self.tree, &key, &response).await;
+        Ok(response)
+    }
+
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        self.inner.generate_embeddings(text).await
+    }
+
+    fn provider_name(&self) -> String {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn embedding_model(&self) -> String {
+        self.inner.embedding_model()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(self.with_inner(self.inner.clone_box(), String::new()))
+    }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(self.with_inner(
+            self.inner.with_profile(profile),
+            format!("profile={:?};", profile),
+        ))
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        Box::new(self.with_inner(
+            self.inner.with_reasoning_effort(effort),
+            format!("effort={:?};", effort),
+        ))
+    }
+}
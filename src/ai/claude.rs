@@ -1,4 +1,10 @@
-use crate::ai::{AiClient, ChatCompletionResponse, FunctionCall, Message, MessageRole};
+use crate::ai::openai::{GptFunctionCall, GptToolCall};
+use crate::ai::reasoning::{self, ReasoningEffort, Thinking};
+use crate::ai::{
+    sse, AiClient, ChatCompletionResponse, GenerationParams, GenerationProfile, Message,
+    MessageRole, TextStream, TokenUsage,
+};
+use crate::context_limit;
 use crate::error::{AgentError, AgentResult};
 use async_trait::async_trait;
 use reqwest::{header, Client};
@@ -9,20 +15,29 @@ use std::time::Duration;
 #[derive(Debug, Clone)]
 pub struct ClaudeClient {
     client: Client,
-    api_key: String,
     model: String,
+    generation: Option<GenerationParams>, // a temperature of 0.7 when unset
+    reasoning_effort: Option<ReasoningEffort>, // enables extended thinking with this budget
 }
 
+/// Tokens left for the answer itself on top of any thinking budget
+const MAX_ANSWER_TOKENS: u32 = 4000;
+
 #[derive(Debug, Serialize)]
 struct ClaudeCompletionRequest {
     model: String,
     messages: Vec<ClaudeMessage>,
-    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>, // must be left unset with extended thinking
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,24 +60,51 @@ struct ClaudeCompletionResponse {
     content: Vec<ClaudeResponseContent>,
     #[serde(default)]
     tool_calls: Option<Vec<ClaudeToolCall>>,
+    #[serde(default)]
+    usage: Option<ClaudeUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeUsage {
+    input_tokens: u64,
+    output_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
 struct ClaudeResponseContent {
     #[serde(rename = "type")]
     content_type: String,
-    text: String,
+    #[serde(default)]
+    text: String, // empty on thinking blocks
 }
 
 #[derive(Debug, Deserialize)]
 struct ClaudeToolCall {
-    id: String,
-    #[serde(rename = "type")]
-    tool_type: String,
     name: String,
     input: Value,
 }
 
+/// Claude takes system prompts as a separate field; join them in order
+fn split_system_messages(messages: Vec<Message>) -> (Option<String>, Vec<Message>) {
+    let (system, rest): (Vec<Message>, Vec<Message>) = messages
+        .into_iter()
+        .partition(|m| m.role == MessageRole::System);
+
+    let system_message = if system.is_empty() {
+        None
+    } else {
+        Some(
+            system
+                .into_iter()
+                .map(|m| m.content)
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        )
+    };
+
+    (system_message, rest)
+}
+
 impl ClaudeClient {
     pub fn new(api_key: &str, model: &str) -> AgentResult<Self> {
         let mut headers = header::HeaderMap::new();
@@ -88,28 +130,35 @@ impl ClaudeClient {
 
         Ok(Self {
             client,
-            api_key: api_key.to_string(),
             model: model.to_string(),
+            generation: None,
+            reasoning_effort: None,
         })
     }
-}
 
-#[async_trait]
-impl AiClient for ClaudeClient {
-    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
-        // Extract system message if present
-        let mut system_message = None;
-        let filtered_messages: Vec<Message> = messages
-            .into_iter()
-            .filter(|m| {
-                if m.role == MessageRole::System {
-                    system_message = Some(m.content.clone());
-                    false
-                } else {
-                    true
-                }
+    /// Only the temperature is sent; the API advises against also setting top_p.
+    /// Extended thinking does not allow one at all.
+    fn temperature(&self) -> Option<f32> {
+        match self.reasoning_effort {
+            Some(_) => None,
+            None => Some(self.generation.map_or(0.7, |params| params.temperature)),
+        }
+    }
+
+    /// The extended thinking block of a request, when a reasoning effort is set
+    fn thinking(&self) -> Option<Value> {
+        self.reasoning_effort.map(|effort| {
+            json!({
+                "type": "enabled",
+                "budget_tokens": effort.thinking_budget()
             })
-            .collect();
+        })
+    }
+
+    /// A request for a plain text answer to `messages`
+    fn text_request(&self, messages: Vec<Message>, stream: bool) -> ClaudeCompletionRequest {
+        // Extract system message if present
+        let (system_message, filtered_messages) = split_system_messages(messages);
 
         let api_messages: Vec<ClaudeMessage> = filtered_messages
             .into_iter()
@@ -123,15 +172,74 @@ impl AiClient for ClaudeClient {
             })
             .collect();
 
-        let request = ClaudeCompletionRequest {
+        ClaudeCompletionRequest {
             model: self.model.clone(),
             messages: api_messages,
-            temperature: 0.7,
-            max_tokens: Some(4000),
+            temperature: self.temperature(),
+            max_tokens: Some(self.max_tokens()),
+            thinking: self.thinking(),
             system: system_message,
             tools: None,
-        };
+            stream,
+        }
+    }
 
+    /// Room for the thinking budget as well as the answer
+    fn max_tokens(&self) -> u32 {
+        MAX_ANSWER_TOKENS
+            + self
+                .reasoning_effort
+                .map_or(0, |effort| effort.thinking_budget())
+    }
+}
+
+/// The answer text a streamed message event adds; thinking deltas are dropped
+pub fn stream_token(data: &str) -> AgentResult<Option<String>> {
+    let event: Value = serde_json::from_str(data)
+        .map_err(|e| AgentError::AiApi(format!("Failed to parse Claude stream event: {}", e)))?;
+    match event["type"].as_str() {
+        Some("error") => Err(AgentError::AiApi(format!(
+            "Claude API stream failed: {}",
+            event["error"]["message"]
+                .as_str()
+                .unwrap_or("unknown error")
+        ))),
+        Some("content_block_delta") if event["delta"]["type"] == "text_delta" => Ok(event["delta"]
+            ["text"]
+            .as_str()
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)),
+        _ => Ok(None),
+    }
+}
+
+/// The answer text of a response; thinking blocks are dropped so they never reach memory
+fn answer_text(content: &[ClaudeResponseContent]) -> String {
+    let thinking_blocks = content
+        .iter()
+        .filter(|c| c.content_type.ends_with("thinking"))
+        .count();
+    if thinking_blocks > 0 {
+        log::debug!(
+            "Dropped {} thinking blocks from Claude's response",
+            thinking_blocks
+        );
+    }
+
+    let text: String = content
+        .iter()
+        .filter(|c| c.content_type == "text")
+        .map(|c| c.text.as_str())
+        .collect();
+    reasoning::strip_thinking(&text)
+}
+
+#[async_trait]
+impl AiClient for ClaudeClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        let request = self.text_request(messages, false);
+
+        let _thinking = self.reasoning_effort.map(|_| Thinking::start());
         let response = self
             .client
             .post("https://api.anthropic.com/v1/messages")
@@ -143,6 +251,9 @@ impl AiClient for ClaudeClient {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
+            if context_limit::is_exceeded(status.as_u16(), &error_text) {
+                return Err(context_limit::exceeded("Claude", &error_text));
+            }
             return Err(AgentError::AiApi(format!(
                 "Claude API returned error status: {}, body: {}",
                 status, error_text
@@ -155,12 +266,7 @@ impl AiClient for ClaudeClient {
             .map_err(|e| AgentError::AiApi(format!("Failed to parse Claude response: {}", e)))?;
 
         // Concatenate all text content from response
-        let content: String = response_data
-            .content
-            .iter()
-            .filter(|c| c.content_type == "text")
-            .map(|c| c.text.clone())
-            .collect();
+        let content = answer_text(&response_data.content);
 
         if content.is_empty() {
             return Err(AgentError::AiApi(
@@ -171,25 +277,39 @@ impl AiClient for ClaudeClient {
         Ok(content)
     }
 
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        let request = self.text_request(messages, true);
+        let response = self
+            .client
+            .post("https://api.anthropic.com/v1/messages")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("Claude API request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            if context_limit::is_exceeded(status.as_u16(), &error_text) {
+                return Err(context_limit::exceeded("Claude", &error_text));
+            }
+            return Err(AgentError::AiApi(format!(
+                "Claude API returned error status: {}, body: {}",
+                status, error_text
+            )));
+        }
+
+        Ok(sse::text_stream(response, stream_token))
+    }
+
     async fn chat_completion_with_functions(
         &self,
         messages: Vec<Message>,
         functions: Value,
-        force_message_only: bool,
+        _force_message_only: bool,
     ) -> AgentResult<ChatCompletionResponse> {
         // Extract system message if present
-        let mut system_message = None;
-        let filtered_messages: Vec<Message> = messages
-            .into_iter()
-            .filter(|m| {
-                if m.role == MessageRole::System {
-                    system_message = Some(m.content.clone());
-                    false
-                } else {
-                    true
-                }
-            })
-            .collect();
+        let (system_message, filtered_messages) = split_system_messages(messages);
 
         let api_messages: Vec<ClaudeMessage> = filtered_messages
             .into_iter()
@@ -231,12 +351,15 @@ impl AiClient for ClaudeClient {
         let request = ClaudeCompletionRequest {
             model: self.model.clone(),
             messages: api_messages,
-            temperature: 0.7,
-            max_tokens: Some(4000),
+            temperature: self.temperature(),
+            max_tokens: Some(self.max_tokens()),
+            thinking: self.thinking(),
             system: system_message,
             tools,
+            stream: false,
         };
 
+        let _thinking = self.reasoning_effort.map(|_| Thinking::start());
         let response = self
             .client
             .post("https://api.anthropic.com/v1/messages")
@@ -248,6 +371,9 @@ impl AiClient for ClaudeClient {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
+            if context_limit::is_exceeded(status.as_u16(), &error_text) {
+                return Err(context_limit::exceeded("Claude", &error_text));
+            }
             return Err(AgentError::AiApi(format!(
                 "Claude API returned error status: {}, body: {}",
                 status, error_text
@@ -260,12 +386,11 @@ impl AiClient for ClaudeClient {
             .map_err(|e| AgentError::AiApi(format!("Failed to parse Claude response: {}", e)))?;
 
         // Extract content
-        let content: String = response_data
-            .content
-            .iter()
-            .filter(|c| c.content_type == "text")
-            .map(|c| c.text.clone())
-            .collect();
+        let content = answer_text(&response_data.content);
+        let usage = response_data.usage.map(|usage| TokenUsage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+        });
 
         // Process tool calls if present
         if let Some(tool_calls) = response_data.tool_calls {
@@ -279,8 +404,14 @@ impl AiClient for ClaudeClient {
 
                 return Ok(ChatCompletionResponse {
                     content: Some(content),
-
-                    tool_calls: None, // FOR NOW !! FIX !! !!!!!!!!!!
+                    usage,
+                    tool_calls: Some(vec![GptToolCall {
+                        r#type: "function".to_string(),
+                        function: GptFunctionCall {
+                            name: tool_call.name.clone(),
+                            arguments: Value::String(arguments),
+                        },
+                    }]),
                 });
             }
         }
@@ -289,6 +420,7 @@ impl AiClient for ClaudeClient {
         Ok(ChatCompletionResponse {
             content: Some(content),
             tool_calls: None,
+            usage,
         })
     }
 
@@ -311,4 +443,18 @@ impl AiClient for ClaudeClient {
     fn clone_box(&self) -> Box<dyn AiClient> {
         Box::new(self.clone())
     }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self {
+            generation: Some(profile.params()),
+            ..self.clone()
+        })
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        Box::new(Self {
+            reasoning_effort: Some(effort),
+            ..self.clone()
+        })
+    }
 }
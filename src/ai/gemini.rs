@@ -0,0 +1,405 @@
+use crate::ai::openai::{GptFunctionCall, GptToolCall};
+use crate::ai::reasoning::{self, ReasoningEffort, Thinking};
+use crate::ai::{
+    sse, AiClient, ChatCompletionResponse, GenerationParams, GenerationProfile, Message,
+    MessageRole, TextStream, TokenUsage,
+};
+use crate::context_limit;
+use crate::error::{AgentError, AgentResult};
+use async_trait::async_trait;
+use reqwest::{header, Client};
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+
+const API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Gemini has no embeddings from its chat models; this one embeds code for semantic search
+const EMBEDDING_MODEL: &str = "text-embedding-004";
+
+/// Schema keywords Gemini accepts in function parameters; it rejects requests with any other
+const SCHEMA_KEYWORDS: [&str; 13] = [
+    "type",
+    "format",
+    "description",
+    "nullable",
+    "enum",
+    "properties",
+    "required",
+    "items",
+    "minItems",
+    "maxItems",
+    "minimum",
+    "maximum",
+    "anyOf",
+];
+
+#[derive(Debug, Clone)]
+pub struct GeminiClient {
+    client: Client,
+    model: String,
+    generation: Option<GenerationParams>, // the model's defaults when unset
+    reasoning_effort: Option<ReasoningEffort>, // sets the thinking budget of 2.5 models
+}
+
+impl GeminiClient {
+    pub fn new(api_key: &str, model: &str) -> AgentResult<Self> {
+        let mut headers = header::HeaderMap::new();
+        let mut key_value = header::HeaderValue::from_str(api_key)
+            .map_err(|e| AgentError::AiApi(format!("Invalid API key format: {}", e)))?;
+        key_value.set_sensitive(true);
+        headers.insert("x-goog-api-key", key_value);
+
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(120))
+            .build()
+            .map_err(|e| AgentError::AiApi(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            model: model.to_string(),
+            generation: None,
+            reasoning_effort: None,
+        })
+    }
+
+    fn generation_config(&self) -> Value {
+        let mut config = Map::new();
+        if let Some(params) = self.generation {
+            config.insert("temperature".to_string(), json!(params.temperature));
+            config.insert("topP".to_string(), json!(params.top_p));
+        }
+        if let Some(effort) = self.reasoning_effort {
+            config.insert(
+                "thinkingConfig".to_string(),
+                json!({ "thinkingBudget": effort.thinking_budget() }),
+            );
+        }
+        Value::Object(config)
+    }
+
+    /// A request for `messages`, with the functions Gemini must call when there are any
+    fn request(&self, messages: Vec<Message>, functions: Option<&Value>) -> Value {
+        let mut request = contents(messages);
+        request["generationConfig"] = self.generation_config();
+        if let Some(functions) = functions {
+            request["tools"] =
+                json!([{ "functionDeclarations": function_declarations(functions) }]);
+            request["toolConfig"] = json!({ "functionCallingConfig": { "mode": "ANY" } });
+        }
+        request
+    }
+
+    async fn post(&self, method: &str, request: &Value) -> AgentResult<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/models/{}:{}", API_URL, self.model, method))
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("Gemini API request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            if context_limit::is_exceeded(status.as_u16(), &error_text) {
+                return Err(context_limit::exceeded("Gemini", &error_text));
+            }
+            return Err(AgentError::AiApi(format!(
+                "Gemini API returned error status: {}, body: {}",
+                status, error_text
+            )));
+        }
+        Ok(response)
+    }
+
+    async fn generate(&self, request: &Value) -> AgentResult<Value> {
+        let _thinking = self.reasoning_effort.map(|_| Thinking::start());
+        self.post("generateContent", request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("Failed to parse Gemini response: {}", e)))
+    }
+}
+
+/// The `contents` and `systemInstruction` of a request. Gemini calls the assistant `model`,
+/// takes system prompts separately and wants turns to alternate, so consecutive messages from
+/// one side are joined.
+pub fn contents(messages: Vec<Message>) -> Value {
+    let mut system = Vec::new();
+    let mut contents: Vec<(&str, Vec<String>)> = Vec::new();
+    for message in messages {
+        let role = match message.role {
+            MessageRole::System | MessageRole::Developer => {
+                system.push(message.content);
+                continue;
+            }
+            MessageRole::User => "user",
+            MessageRole::Assistant => "model",
+        };
+        match contents.last_mut() {
+            Some((last, texts)) if *last == role => texts.push(message.content),
+            _ => contents.push((role, vec![message.content])),
+        }
+    }
+
+    // A request needs at least one turn
+    if contents.is_empty() {
+        contents.push(("user", std::mem::take(&mut system)));
+    }
+
+    let mut request = json!({
+        "contents": contents
+            .into_iter()
+            .map(|(role, texts)| json!({ "role": role, "parts": [{ "text": texts.join("\n\n") }] }))
+            .collect::<Vec<_>>(),
+    });
+    if !system.is_empty() {
+        request["systemInstruction"] = json!({ "parts": [{ "text": system.join("\n\n") }] });
+    }
+    request
+}
+
+/// OpenAI-style functions as Gemini function declarations, with their parameter schemas cut
+/// down to what Gemini accepts
+pub fn function_declarations(functions: &Value) -> Vec<Value> {
+    functions
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|function| {
+            let mut declaration = json!({
+                "name": function["name"].as_str().unwrap_or("unknown"),
+                "description": function["description"].as_str().unwrap_or(""),
+            });
+            // Gemini rejects objects without properties, so functions without arguments
+            // leave their parameters out
+            let parameters = gemini_schema(&function["parameters"]);
+            if parameters["properties"]
+                .as_object()
+                .is_some_and(|properties| !properties.is_empty())
+            {
+                declaration["parameters"] = parameters;
+            }
+            declaration
+        })
+        .collect()
+}
+
+/// `schema` with only the keywords Gemini knows. A type list such as `["string", "null"]`
+/// becomes a nullable type.
+fn gemini_schema(schema: &Value) -> Value {
+    let Some(object) = schema.as_object() else {
+        return schema.clone();
+    };
+
+    let mut cleaned = Map::new();
+    for (key, value) in object {
+        // Gemini has no `oneOf`; the options of a tagged enum exclude each other anyway
+        let key = if key == "oneOf" {
+            "anyOf"
+        } else {
+            key.as_str()
+        };
+        if !SCHEMA_KEYWORDS.contains(&key) {
+            continue;
+        }
+        let value = match (key, value) {
+            ("type", Value::Array(types)) => {
+                if types.iter().any(|t| t == "null") {
+                    cleaned.insert("nullable".to_string(), Value::Bool(true));
+                }
+                match types.iter().find(|t| *t != "null") {
+                    Some(t) => t.clone(),
+                    None => continue,
+                }
+            }
+            ("properties", Value::Object(properties)) => Value::Object(
+                properties
+                    .iter()
+                    .map(|(name, property)| (name.clone(), gemini_schema(property)))
+                    .collect(),
+            ),
+            ("anyOf", Value::Array(options)) => {
+                Value::Array(options.iter().map(gemini_schema).collect())
+            }
+            ("items", items) => gemini_schema(items),
+            _ => value.clone(),
+        };
+        cleaned.insert(key.to_string(), value);
+    }
+    Value::Object(cleaned)
+}
+
+/// The answer text of a response; thought summaries are dropped so they never reach memory
+pub fn answer_text(response: &Value) -> String {
+    let text: String = parts(response)
+        .iter()
+        .filter(|part| part["thought"] != true)
+        .filter_map(|part| part["text"].as_str())
+        .collect();
+    reasoning::strip_thinking(&text)
+}
+
+/// The function calls of a response, as OpenAI returns them
+pub fn tool_calls(response: &Value) -> Vec<GptToolCall> {
+    parts(response)
+        .iter()
+        .filter_map(|part| {
+            let call = part.get("functionCall")?;
+            Some(GptToolCall {
+                r#type: "function".to_string(),
+                function: GptFunctionCall {
+                    name: call["name"].as_str()?.to_string(),
+                    arguments: Value::String(call.get("args").unwrap_or(&json!({})).to_string()),
+                },
+            })
+        })
+        .collect()
+}
+
+/// The tokens a response was billed for, from its `usageMetadata`
+pub fn usage(response: &Value) -> Option<TokenUsage> {
+    let metadata = response.get("usageMetadata")?;
+    Some(TokenUsage {
+        prompt_tokens: metadata["promptTokenCount"].as_u64().unwrap_or(0),
+        completion_tokens: metadata["candidatesTokenCount"].as_u64().unwrap_or(0)
+            + metadata["thoughtsTokenCount"].as_u64().unwrap_or(0),
+    })
+}
+
+fn parts(response: &Value) -> &[Value] {
+    response["candidates"][0]["content"]["parts"]
+        .as_array()
+        .map_or(&[], Vec::as_slice)
+}
+
+/// The answer text a streamed response chunk adds
+pub fn stream_token(data: &str) -> AgentResult<Option<String>> {
+    let chunk: Value = serde_json::from_str(data)
+        .map_err(|e| AgentError::AiApi(format!("Failed to parse Gemini stream chunk: {}", e)))?;
+    if let Some(message) = chunk["error"]["message"].as_str() {
+        return Err(AgentError::AiApi(format!(
+            "Gemini API stream failed: {}",
+            message
+        )));
+    }
+    let text = answer_text(&chunk);
+    Ok((!text.is_empty()).then_some(text))
+}
+
+#[async_trait]
+impl AiClient for GeminiClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        let response = self.generate(&self.request(messages, None)).await?;
+        let content = answer_text(&response);
+        if content.is_empty() {
+            return Err(AgentError::AiApi(format!(
+                "Gemini API returned no content (finish reason: {})",
+                response["candidates"][0]["finishReason"]
+                    .as_str()
+                    .unwrap_or("unknown")
+            )));
+        }
+        Ok(content)
+    }
+
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        let response = self
+            .post(
+                "streamGenerateContent?alt=sse",
+                &self.request(messages, None),
+            )
+            .await?;
+        Ok(sse::text_stream(response, stream_token))
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        messages: Vec<Message>,
+        functions: Value,
+        force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        let functions = (!force_message_only).then_some(&functions);
+        let response = self.generate(&self.request(messages, functions)).await?;
+
+        let content = answer_text(&response);
+        let tool_calls = tool_calls(&response);
+        Ok(ChatCompletionResponse {
+            content: (!content.is_empty()).then_some(content),
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            usage: usage(&response),
+        })
+    }
+
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        let request = json!({
+            "model": format!("models/{}", EMBEDDING_MODEL),
+            "content": { "parts": [{ "text": text }] },
+        });
+        let response = self
+            .client
+            .post(format!(
+                "{}/models/{}:embedContent",
+                API_URL, EMBEDDING_MODEL
+            ))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("Gemini API request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AgentError::AiApi(format!(
+                "Gemini API returned error status: {}, body: {}",
+                status, error_text
+            )));
+        }
+
+        let response: Value = response
+            .json()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("Failed to parse Gemini embedding: {}", e)))?;
+        response["embedding"]["values"]
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_f64().map(|value| value as f32))
+                    .collect()
+            })
+            .ok_or_else(|| AgentError::AiApi("Gemini API returned no embedding".to_string()))
+    }
+
+    fn provider_name(&self) -> String {
+        "gemini".to_string()
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn embedding_model(&self) -> String {
+        format!("gemini:{}", EMBEDDING_MODEL)
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(self.clone())
+    }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self {
+            generation: Some(profile.params()),
+            ..self.clone()
+        })
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        Box::new(Self {
+            reasoning_effort: Some(effort),
+            ..self.clone()
+        })
+    }
+}
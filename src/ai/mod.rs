@@ -1,12 +1,25 @@
+pub mod cache;
+#[cfg(feature = "claude")]
 pub mod claude;
+#[cfg(feature = "gemini")]
+pub mod gemini;
+#[cfg(feature = "ollama")]
+pub mod ollama;
 pub mod openai;
+pub mod reasoning;
+pub mod retry;
+pub mod salvage;
+pub mod sse;
 //pub mod prompt;
 
 use crate::ai::openai::GptToolCall;
+use crate::ai::reasoning::ReasoningEffort;
 use crate::error::AgentResult;
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MessageRole {
@@ -16,14 +29,15 @@ pub enum MessageRole {
     Assistant,
 }
 
-impl ToString for MessageRole {
-    fn to_string(&self) -> String {
-        match self {
-            MessageRole::Developer => "developer".to_string(),
-            MessageRole::System => "system".to_string(),
-            MessageRole::User => "user".to_string(),
-            MessageRole::Assistant => "assistant".to_string(),
-        }
+impl fmt::Display for MessageRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            MessageRole::Developer => "developer",
+            MessageRole::System => "system",
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+        };
+        write!(f, "{}", role)
     }
 }
 
@@ -41,10 +55,58 @@ pub struct FunctionCall {
     pub arguments: String,
 }
 
+/// Pieces of a response in the order the model writes them
+pub type TextStream = BoxStream<'static, AgentResult<String>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
     pub content: Option<String>,
     pub tool_calls: Option<Vec<GptToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>, // None when the provider does not report it
+}
+
+/// Tokens a request was billed for, as the provider reported them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// Sampling preset the planner can request for one step, instead of a raw temperature.
+/// Variants are commented rather than documented so the schema stays a plain string enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationProfile {
+    Deterministic, // code edits, commands and exact arguments
+    Balanced,      // explanations and analysis
+    Creative,      // brainstorming alternatives, names and approaches
+}
+
+/// Vetted sampling parameters behind a `GenerationProfile`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationParams {
+    pub temperature: f32,
+    pub top_p: f32,
+}
+
+impl GenerationProfile {
+    pub fn params(&self) -> GenerationParams {
+        match self {
+            Self::Deterministic => GenerationParams {
+                temperature: 0.0,
+                top_p: 1.0,
+            },
+            Self::Balanced => GenerationParams {
+                temperature: 0.4,
+                top_p: 1.0,
+            },
+            Self::Creative => GenerationParams {
+                temperature: 0.9,
+                top_p: 0.95,
+            },
+        }
+    }
 }
 
 #[async_trait]
@@ -52,12 +114,19 @@ pub trait AiClient: Send + Sync {
     /// Generate text from a conversation history
     async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String>;
 
+    /// Generate text as a stream of tokens, so it can be shown while the model writes it.
+    /// Providers without streaming yield the whole response as one piece.
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        let text = self.generate_text(messages).await?;
+        Ok(Box::pin(stream::once(async move { Ok(text) })))
+    }
+
     /// Generate chat completion with functions
     async fn chat_completion_with_functions(
         &self,
-        messages: Vec<Message>,
-        functions: serde_json::Value,
-        force_message_only: bool,
+        _messages: Vec<Message>,
+        _functions: serde_json::Value,
+        _force_message_only: bool,
     ) -> AgentResult<ChatCompletionResponse> {
         // Default implementation to support backward compatibility
         // Derived implementations should override this
@@ -75,28 +144,70 @@ pub trait AiClient: Send + Sync {
     /// Get the model name being used
     fn model_name(&self) -> String;
 
+    /// The model that `generate_embeddings` uses, as `provider:model`. Embeddings of different
+    /// models cannot be compared.
+    fn embedding_model(&self) -> String {
+        self.provider_name()
+    }
+
     /// Create a clone of this client
     fn clone_box(&self) -> Box<dyn AiClient>;
+
+    /// A clone that samples with `profile`'s parameters. Providers without sampling
+    /// controls ignore the profile.
+    fn with_profile(&self, _profile: GenerationProfile) -> Box<dyn AiClient> {
+        self.clone_box()
+    }
+
+    /// A clone that gives reasoning models `effort`. Other models and providers ignore it.
+    fn with_reasoning_effort(&self, _effort: ReasoningEffort) -> Box<dyn AiClient> {
+        self.clone_box()
+    }
 }
 
-/// Factory function to create an AI client based on configuration
+/// Factory function to create an AI client based on configuration. Its calls are retried
+/// when they fail with a transient error.
 pub fn create_ai_client(
     provider: &str,
     model: &str,
     api_key: &str,
 ) -> AgentResult<Box<dyn AiClient>> {
-    match provider {
-        "openai" => {
-            let client = openai::OpenAiClient::new(api_key, model)?;
-            Ok(Box::new(client))
-        }
-        "claude" => {
-            let client = claude::ClaudeClient::new(api_key, model)?;
-            Ok(Box::new(client))
+    let client: Box<dyn AiClient> = match provider {
+        provider if !compiled_in(provider) => return Err(not_compiled_in(provider)),
+        "openai" => Box::new(openai::OpenAiClient::new(api_key, model)?),
+        #[cfg(feature = "gemini")]
+        "gemini" => Box::new(gemini::GeminiClient::new(api_key, model)?),
+        // Local models need no API key
+        #[cfg(feature = "ollama")]
+        "ollama" => Box::new(ollama::OllamaClient::new(model)?),
+        #[cfg(feature = "claude")]
+        "claude" => Box::new(claude::ClaudeClient::new(api_key, model)?),
+        _ => {
+            return Err(crate::error::AgentError::AiApi(format!(
+                "Unsupported AI provider: {}",
+                provider
+            )))
         }
-        _ => Err(crate::error::AgentError::AiApi(format!(
-            "Unsupported AI provider: {}",
-            provider
-        ))),
+    };
+    Ok(Box::new(retry::RetryingAiClient::new(client)))
+}
+
+/// Whether the client for `provider` is part of this build; unknown providers are left
+/// to the caller to reject
+#[allow(clippy::match_like_matches_macro)] // the arms are constants that vary with features
+pub fn compiled_in(provider: &str) -> bool {
+    match provider {
+        "claude" => cfg!(feature = "claude"),
+        "gemini" => cfg!(feature = "gemini"),
+        "ollama" => cfg!(feature = "ollama"),
+        _ => true,
     }
 }
+
+/// Error for a known provider whose cargo feature was left out of this build
+pub fn not_compiled_in(provider: &str) -> crate::error::AgentError {
+    crate::error::AgentError::Configuration(format!(
+        "The {} provider is not compiled into this build; rebuild with `--features {}`",
+        provider, provider
+    ))
+}
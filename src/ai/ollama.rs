@@ -0,0 +1,311 @@
+use crate::ai::openai::{GptFunctionCall, GptToolCall};
+use crate::ai::reasoning;
+use crate::ai::{
+    sse, AiClient, ChatCompletionResponse, GenerationParams, GenerationProfile, Message,
+    MessageRole, TextStream, TokenUsage,
+};
+use crate::error::{AgentError, AgentResult};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Where the Ollama server listens and which model embeds text; set from the settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OllamaConfig {
+    pub url: String,
+    pub embedding_model: String,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:11434".to_string(),
+            embedding_model: "nomic-embed-text".to_string(),
+        }
+    }
+}
+
+static CONFIG: RwLock<Option<OllamaConfig>> = RwLock::new(None);
+
+pub fn configure(config: OllamaConfig) {
+    *CONFIG.write().unwrap_or_else(|e| e.into_inner()) = Some(config);
+}
+
+fn config() -> OllamaConfig {
+    CONFIG
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Local models served by Ollama. Function calls are made in JSON mode, so they work with
+/// models that have no native tool support.
+#[derive(Debug, Clone)]
+pub struct OllamaClient {
+    client: Client,
+    config: OllamaConfig,
+    model: String,
+    generation: Option<GenerationParams>, // the model's defaults when unset
+}
+
+impl OllamaClient {
+    pub fn new(model: &str) -> AgentResult<Self> {
+        let client = Client::builder()
+            // Local models load on the first request and generate slowly on a CPU
+            .timeout(Duration::from_secs(600))
+            .build()
+            .map_err(|e| AgentError::AiApi(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            config: config(),
+            model: model.to_string(),
+            generation: None,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.config.url.trim_end_matches('/'), path)
+    }
+
+    fn chat_request(&self, messages: Vec<Message>, stream: bool) -> Value {
+        let messages: Vec<Value> = messages
+            .into_iter()
+            .map(|m| {
+                // Ollama has no developer role
+                let role = match m.role {
+                    MessageRole::Developer => MessageRole::System,
+                    role => role,
+                };
+                json!({ "role": role.to_string(), "content": m.content })
+            })
+            .collect();
+
+        let mut request = json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": stream,
+        });
+        if let Some(params) = self.generation {
+            request["options"] = json!({
+                "temperature": params.temperature,
+                "top_p": params.top_p,
+            });
+        }
+        request
+    }
+
+    async fn post(&self, path: &str, request: &Value) -> AgentResult<reqwest::Response> {
+        let response = self
+            .client
+            .post(self.url(path))
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| {
+                AgentError::AiApi(format!(
+                    "Ollama request failed ({}); is `ollama serve` running? {}",
+                    self.config.url, e
+                ))
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AgentError::AiApi(format!(
+                "Ollama returned error status: {}, body: {}",
+                status, error_text
+            )));
+        }
+        Ok(response)
+    }
+
+    async fn chat(&self, request: &Value) -> AgentResult<(String, Option<TokenUsage>)> {
+        let response: Value = self
+            .post("/api/chat", request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("Failed to parse Ollama response: {}", e)))?;
+
+        let answer = response["message"]["content"]
+            .as_str()
+            .map(reasoning::strip_thinking)
+            .ok_or_else(|| AgentError::AiApi("Ollama returned no message".to_string()))?;
+        Ok((answer, usage(&response)))
+    }
+}
+
+/// The tokens a chat response took, from its evaluation counts
+pub fn usage(response: &Value) -> Option<TokenUsage> {
+    Some(TokenUsage {
+        prompt_tokens: response.get("prompt_eval_count")?.as_u64()?,
+        completion_tokens: response["eval_count"].as_u64().unwrap_or(0),
+    })
+}
+
+/// The text a streamed chat line adds
+pub fn stream_token(line: &str) -> AgentResult<Option<String>> {
+    let event: Value = serde_json::from_str(line)
+        .map_err(|e| AgentError::AiApi(format!("Failed to parse Ollama stream line: {}", e)))?;
+    if let Some(error) = event["error"].as_str() {
+        return Err(AgentError::AiApi(format!(
+            "Ollama stream failed: {}",
+            error
+        )));
+    }
+    Ok(event["message"]["content"]
+        .as_str()
+        .filter(|token| !token.is_empty())
+        .map(str::to_string))
+}
+
+/// Instructions asking for function calls as a JSON object, listing the functions
+pub fn function_prompt(functions: &Value) -> String {
+    format!(
+        "Respond only with a JSON object calling one or more of the functions below, in the form \
+         {{\"tool_calls\": [{{\"name\": \"<function name>\", \"arguments\": {{...}}}}]}}. \
+         The arguments must match the function's parameters schema.\n\nFunctions:\n{}",
+        serde_json::to_string_pretty(functions).unwrap_or_default()
+    )
+}
+
+/// The function calls in a JSON-mode answer, as OpenAI returns them. Models also answer with
+/// a bare call or a list of calls; `None` when the answer holds no call to a known function.
+pub fn parse_tool_calls(answer: &str, functions: &Value) -> Option<Vec<GptToolCall>> {
+    let json: Value = serde_json::from_str(answer.trim()).ok()?;
+    let calls = match &json {
+        Value::Array(calls) => calls.clone(),
+        Value::Object(object) => match object.get("tool_calls") {
+            Some(Value::Array(calls)) => calls.clone(),
+            _ => vec![json.clone()],
+        },
+        _ => return None,
+    };
+
+    let known = |name: &str| {
+        functions
+            .as_array()
+            .is_some_and(|functions| functions.iter().any(|f| f["name"] == name))
+    };
+    let tool_calls: Vec<GptToolCall> = calls
+        .iter()
+        .filter_map(|call| {
+            // Some models nest the call under `function`, as OpenAI's responses do
+            let call = call.get("function").unwrap_or(call);
+            let name = call["name"].as_str().filter(|name| known(name))?;
+            let arguments = match &call["arguments"] {
+                Value::String(arguments) => arguments.clone(),
+                Value::Null => "{}".to_string(),
+                arguments => arguments.to_string(),
+            };
+            Some(GptToolCall {
+                r#type: "function".to_string(),
+                function: GptFunctionCall {
+                    name: name.to_string(),
+                    arguments: Value::String(arguments),
+                },
+            })
+        })
+        .collect();
+
+    (!tool_calls.is_empty()).then_some(tool_calls)
+}
+
+#[async_trait]
+impl AiClient for OllamaClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        Ok(self.chat(&self.chat_request(messages, false)).await?.0)
+    }
+
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        let response = self
+            .post("/api/chat", &self.chat_request(messages, true))
+            .await?;
+        Ok(sse::json_lines_stream(response, stream_token))
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        mut messages: Vec<Message>,
+        functions: Value,
+        force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        if force_message_only {
+            let (answer, usage) = self.chat(&self.chat_request(messages, false)).await?;
+            return Ok(ChatCompletionResponse {
+                content: Some(answer),
+                tool_calls: None,
+                usage,
+            });
+        }
+
+        messages.push(Message {
+            role: MessageRole::System,
+            content: function_prompt(&functions),
+            name: None,
+        });
+        let mut request = self.chat_request(messages, false);
+        request["format"] = json!("json");
+
+        let (answer, usage) = self.chat(&request).await?;
+        let tool_calls = parse_tool_calls(&answer, &functions);
+        if tool_calls.is_none() {
+            log::warn!("Ollama answered without a function call: {}", answer);
+        }
+        Ok(ChatCompletionResponse {
+            content: tool_calls.is_none().then_some(answer),
+            tool_calls,
+            usage,
+        })
+    }
+
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        let request = json!({
+            "model": self.config.embedding_model,
+            "input": text,
+        });
+        let response: Value = self
+            .post("/api/embed", &request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("Failed to parse Ollama embedding: {}", e)))?;
+
+        response["embeddings"][0]
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_f64().map(|value| value as f32))
+                    .collect()
+            })
+            .ok_or_else(|| AgentError::AiApi("Ollama returned no embedding".to_string()))
+    }
+
+    fn provider_name(&self) -> String {
+        "ollama".to_string()
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn embedding_model(&self) -> String {
+        format!("ollama:{}", self.config.embedding_model)
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(self.clone())
+    }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self {
+            generation: Some(profile.params()),
+            ..self.clone()
+        })
+    }
+}
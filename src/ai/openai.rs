@@ -1,17 +1,106 @@
-use crate::ai::{AiClient, ChatCompletionResponse, FunctionCall, Message, MessageRole};
+use crate::ai::reasoning::{self, ReasoningEffort, Thinking};
+use crate::ai::{
+    sse, AiClient, ChatCompletionResponse, GenerationParams, GenerationProfile, Message,
+    TextStream, TokenUsage,
+};
+use crate::context_limit;
 use crate::error::{AgentError, AgentResult};
+use crate::output;
 use async_trait::async_trait;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::sync::RwLock;
 use std::time::Duration;
 
+/// The model code and questions are embedded with for semantic search
+const EMBEDDING_MODEL: &str = "text-embedding-ada-002";
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Where requests are sent. With an `api_version` the endpoint is Azure OpenAI, which
+/// addresses models by deployment name and takes the key in an `api-key` header.
+#[derive(Debug, Clone, Default)]
+pub struct OpenAiConfig {
+    pub base_url: Option<String>, // an Azure resource, or an OpenAI-compatible API; api.openai.com if unset
+    pub api_version: Option<String>, // Azure's `api-version`, e.g. 2024-10-21
+    pub deployment: Option<String>, // Azure deployment of the chat model; named after the model if unset
+    pub embedding_deployment: Option<String>, // Azure deployment of the embedding model
+}
+
+impl OpenAiConfig {
+    pub fn is_azure(&self) -> bool {
+        self.api_version.is_some()
+    }
+
+    fn base_url(&self) -> &str {
+        self.base_url
+            .as_deref()
+            .unwrap_or(DEFAULT_BASE_URL)
+            .trim_end_matches('/')
+    }
+
+    fn azure_url(&self, deployment: &str, operation: &str) -> String {
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            self.base_url(),
+            deployment,
+            operation,
+            self.api_version.as_deref().unwrap_or_default()
+        )
+    }
+
+    /// The chat completions URL for `model`
+    pub fn chat_url(&self, model: &str) -> String {
+        if self.is_azure() {
+            self.azure_url(
+                self.deployment.as_deref().unwrap_or(model),
+                "chat/completions",
+            )
+        } else {
+            format!("{}/chat/completions", self.base_url())
+        }
+    }
+
+    pub fn embeddings_url(&self) -> String {
+        if self.is_azure() {
+            self.azure_url(self.embedding_model(), "embeddings")
+        } else {
+            format!("{}/embeddings", self.base_url())
+        }
+    }
+
+    /// The embedding model, or on Azure its deployment
+    pub fn embedding_model(&self) -> &str {
+        match &self.embedding_deployment {
+            Some(deployment) if self.is_azure() => deployment,
+            _ => EMBEDDING_MODEL,
+        }
+    }
+}
+
+static CONFIG: RwLock<Option<OpenAiConfig>> = RwLock::new(None);
+
+pub fn configure(config: OpenAiConfig) {
+    *CONFIG.write().unwrap_or_else(|e| e.into_inner()) = Some(config);
+}
+
+fn config() -> OpenAiConfig {
+    CONFIG
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone)]
 pub struct OpenAiClient {
     client: Client,
-    api_key: String,
+    config: OpenAiConfig,
     model: String,
+    generation: Option<GenerationParams>, // the API's defaults when unset
+    reasoning_effort: Option<ReasoningEffort>, // for o-series models; the API's default when unset
 }
 /*
 #[derive(Debug, Serialize)]
@@ -37,6 +126,8 @@ struct OpenAiMessage {
 #[derive(Debug, Deserialize)]
 struct OpenAiCompletionResponse {
     choices: Vec<OpenAiChoice>,
+    #[serde(default)]
+    usage: Option<TokenUsage>, // OpenAI's field names match ours
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,16 +154,9 @@ struct OpenAiResponseMessage {
     #[serde(default)]
     function_call: Option<OpenAiFunctionCall>,*/
     pub content: Option<String>,
-    pub role: Option<String>,
     pub tool_calls: Option<Vec<GptToolCall>>,
 }
 
-#[derive(Debug, Deserialize)]
-struct OpenAiFunctionCall {
-    name: String,
-    arguments: String,
-}
-
 #[derive(Debug, Serialize)]
 struct OpenAiEmbeddingRequest {
     model: String,
@@ -91,29 +175,87 @@ struct OpenAiEmbedding {
 
 impl OpenAiClient {
     pub fn new(api_key: &str, model: &str) -> AgentResult<Self> {
+        let config = config();
+        if config.is_azure() && config.base_url.is_none() {
+            return Err(AgentError::Configuration(
+                "OPENAI_API_VERSION is set for Azure OpenAI, but OPENAI_BASE_URL is not"
+                    .to_string(),
+            ));
+        }
+
         let mut headers = header::HeaderMap::new();
         headers.insert(
             header::CONTENT_TYPE,
             header::HeaderValue::from_static("application/json"),
         );
 
-        let mut auth_value = header::HeaderValue::from_str(&format!("Bearer {}", api_key))
+        // Azure takes the key as is, in a header of its own
+        let (auth_name, auth_value) = if config.is_azure() {
+            (
+                header::HeaderName::from_static("api-key"),
+                api_key.to_string(),
+            )
+        } else {
+            (header::AUTHORIZATION, format!("Bearer {}", api_key))
+        };
+        let mut auth_value = header::HeaderValue::from_str(&auth_value)
             .map_err(|e| AgentError::AiApi(format!("Invalid API key format: {}", e)))?;
         auth_value.set_sensitive(true);
-        headers.insert(header::AUTHORIZATION, auth_value);
+        headers.insert(auth_name, auth_value);
 
         let client = Client::builder()
             .default_headers(headers)
-            .timeout(Duration::from_secs(60))
+            // Reasoning models can think for minutes before answering
+            .timeout(Duration::from_secs(
+                if reasoning::is_reasoning_model(model) {
+                    300
+                } else {
+                    60
+                },
+            ))
             .build()
             .map_err(|e| AgentError::AiApi(format!("Failed to create HTTP client: {}", e)))?;
 
         Ok(Self {
             client,
-            api_key: api_key.to_string(),
+            config,
             model: model.to_string(),
+            generation: None,
+            reasoning_effort: None,
         })
     }
+
+    /// Add the sampling parameters of the requested profile, if any, to a request body.
+    /// Reasoning models take a reasoning effort instead and reject sampling parameters.
+    fn apply_generation(&self, request_body: &mut Value) {
+        if reasoning::is_reasoning_model(&self.model) {
+            if let Some(effort) = self.reasoning_effort {
+                request_body["reasoning_effort"] = json!(effort.to_string());
+            }
+        } else if let Some(params) = self.generation {
+            request_body["temperature"] = json!(params.temperature);
+            request_body["top_p"] = json!(params.top_p);
+        }
+    }
+}
+
+/// The text a streamed chat completion event adds; the stream ends with `[DONE]`
+pub fn stream_token(data: &str) -> AgentResult<Option<String>> {
+    if data.trim() == "[DONE]" {
+        return Ok(None);
+    }
+    let event: Value = serde_json::from_str(data)
+        .map_err(|e| AgentError::AiApi(format!("Failed to parse OpenAI stream event: {}", e)))?;
+    if let Some(error) = event.get("error") {
+        return Err(AgentError::AiApi(format!(
+            "OpenAI API stream failed: {}",
+            error["message"].as_str().unwrap_or("unknown error")
+        )));
+    }
+    Ok(event["choices"][0]["delta"]["content"]
+        .as_str()
+        .filter(|token| !token.is_empty())
+        .map(str::to_string))
 }
 
 #[async_trait]
@@ -128,7 +270,7 @@ impl AiClient for OpenAiClient {
             })
             .collect();
 
-        let request_body = json!({
+        let mut request_body = json!({
             "model": self.model,
             "messages": api_messages,
 
@@ -137,10 +279,12 @@ impl AiClient for OpenAiClient {
 
 
         });
+        self.apply_generation(&mut request_body);
 
+        let _thinking = reasoning::is_reasoning_model(&self.model).then(Thinking::start);
         let response = self
             .client
-            .post("https://api.openai.com/v1/chat/completions")
+            .post(self.config.chat_url(&self.model))
             .json(&request_body)
             .send()
             .await
@@ -149,6 +293,9 @@ impl AiClient for OpenAiClient {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
+            if context_limit::is_exceeded(status.as_u16(), &error_text) {
+                return Err(context_limit::exceeded("OpenAI", &error_text));
+            }
             return Err(AgentError::AiApi(format!(
                 "OpenAI API returned error status: {}, body: {}",
                 status, error_text
@@ -166,11 +313,59 @@ impl AiClient for OpenAiClient {
             ));
         }
 
-        Ok(response_data.choices[0]
-            .message
-            .content
-            .clone()
-            .unwrap_or_default())
+        Ok(reasoning::strip_thinking(
+            response_data.choices[0]
+                .message
+                .content
+                .as_deref()
+                .unwrap_or_default(),
+        ))
+    }
+
+    /// Reasoning models answer only once they are done thinking, so they are not streamed
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        if reasoning::is_reasoning_model(&self.model) {
+            let text = self.generate_text(messages).await?;
+            return Ok(Box::pin(futures::stream::once(async move { Ok(text) })));
+        }
+
+        let api_messages: Vec<OpenAiMessage> = messages
+            .into_iter()
+            .map(|m| OpenAiMessage {
+                role: m.role.to_string(),
+                content: m.content,
+                name: m.name,
+            })
+            .collect();
+
+        let mut request_body = json!({
+            "model": self.model,
+            "messages": api_messages,
+            "stream": true,
+        });
+        self.apply_generation(&mut request_body);
+
+        let response = self
+            .client
+            .post(self.config.chat_url(&self.model))
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| AgentError::AiApi(format!("OpenAI API request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            if context_limit::is_exceeded(status.as_u16(), &error_text) {
+                return Err(context_limit::exceeded("OpenAI", &error_text));
+            }
+            return Err(AgentError::AiApi(format!(
+                "OpenAI API returned error status: {}, body: {}",
+                status, error_text
+            )));
+        }
+
+        Ok(sse::text_stream(response, stream_token))
     }
 
     async fn chat_completion_with_functions(
@@ -205,10 +400,10 @@ impl AiClient for OpenAiClient {
 
         let enable_function_calling = !force_message_only; // for now
 
-        let request_body = match enable_function_calling {
+        let mut request_body = match enable_function_calling {
             true => json!({
 
-                 "model": "gpt-4o" ,
+                 "model": self.model,
 
                 "messages": api_messages,
              //   "temperature": 0.7,  // Lower temperature for more deterministic responses
@@ -222,9 +417,7 @@ impl AiClient for OpenAiClient {
             }),
 
             false => json!({
-              //  "model": self.model,
-
-                "model": "gpt-4o" ,
+                "model": self.model,
 
                 "messages": api_messages,
 
@@ -234,22 +427,29 @@ impl AiClient for OpenAiClient {
 
             }),
         };
+        self.apply_generation(&mut request_body);
 
         //println!("request_body {:?}", request_body);
 
+        let _thinking = reasoning::is_reasoning_model(&self.model).then(Thinking::start);
         let response = self
             .client
-            .post("https://api.openai.com/v1/chat/completions")
+            .post(self.config.chat_url(&self.model))
             .json(&request_body)
             .send()
             .await
             .map_err(|e| AgentError::AiApi(format!("OpenAI API request failed: {}", e)))?;
 
-        println!("response {:?}", response);
+        if output::is_verbose() {
+            println!("response {:?}", response);
+        }
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
+            if context_limit::is_exceeded(status.as_u16(), &error_text) {
+                return Err(context_limit::exceeded("OpenAI", &error_text));
+            }
             return Err(AgentError::AiApi(format!(
                 "OpenAI API returned error status: {}, body: {}",
                 status, error_text
@@ -261,7 +461,9 @@ impl AiClient for OpenAiClient {
             .await
             .map_err(|e| AgentError::AiApi(format!("Failed to parse OpenAI response: {}", e)))?;
 
-        println!("response_data {:?}", response_data);
+        if output::is_verbose() {
+            println!("response_data {:?}", response_data);
+        }
 
         if response_data.choices.is_empty() {
             return Err(AgentError::AiApi(
@@ -270,16 +472,19 @@ impl AiClient for OpenAiClient {
         }
 
         let message = &response_data.choices[0].message;
+        let content = message.content.as_deref().map(reasoning::strip_thinking);
 
         let result = if let Some(tool_calls) = &message.tool_calls {
             ChatCompletionResponse {
-                content: message.content.clone(),
+                content,
                 tool_calls: Some(tool_calls.to_vec()),
+                usage: response_data.usage,
             }
         } else {
             ChatCompletionResponse {
-                content: message.content.clone(),
+                content,
                 tool_calls: None,
+                usage: response_data.usage,
             }
         };
 
@@ -288,13 +493,13 @@ impl AiClient for OpenAiClient {
 
     async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
         let request = OpenAiEmbeddingRequest {
-            model: "text-embedding-ada-002".to_string(), // Standard embedding model
+            model: self.config.embedding_model().to_string(),
             input: text.to_string(),
         };
 
         let response = self
             .client
-            .post("https://api.openai.com/v1/embeddings")
+            .post(self.config.embeddings_url())
             .json(&request)
             .send()
             .await
@@ -331,9 +536,27 @@ impl AiClient for OpenAiClient {
         self.model.clone()
     }
 
+    fn embedding_model(&self) -> String {
+        format!("openai:{}", self.config.embedding_model())
+    }
+
     fn clone_box(&self) -> Box<dyn AiClient> {
         Box::new(self.clone())
     }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self {
+            generation: Some(profile.params()),
+            ..self.clone()
+        })
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        Box::new(Self {
+            reasoning_effort: Some(effort),
+            ..self.clone()
+        })
+    }
 }
 
 #[derive(Serialize, Debug)]
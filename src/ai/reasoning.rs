@@ -0,0 +1,156 @@
+use crate::error::AgentError;
+use crate::output;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How much hidden reasoning a reasoning model may do before it answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub const ALL: [ReasoningEffort; 3] = [
+        ReasoningEffort::Low,
+        ReasoningEffort::Medium,
+        ReasoningEffort::High,
+    ];
+
+    /// Tokens Claude's extended thinking may spend at this effort
+    pub fn thinking_budget(&self) -> u32 {
+        match self {
+            Self::Low => 2048,
+            Self::Medium => 8192,
+            Self::High => 16384,
+        }
+    }
+}
+
+impl fmt::Display for ReasoningEffort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ReasoningEffort {
+    type Err = AgentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|effort| effort.to_string() == s.trim())
+            .ok_or_else(|| {
+                AgentError::Configuration(format!(
+                    "Unknown reasoning effort '{}', expected one of: {}",
+                    s,
+                    Self::ALL
+                        .iter()
+                        .map(|effort| effort.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+/// OpenAI's o-series models (o1, o3-mini, o4-mini, ...), which reason before answering and
+/// reject sampling parameters
+pub fn is_reasoning_model(model: &str) -> bool {
+    let mut chars = model.trim().chars();
+    chars.next() == Some('o') && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Remove `<think>` and `<thinking>` blocks some models write before their answer, so
+/// their chain of thought never reaches context memory. An unclosed block drops the rest.
+pub fn strip_thinking(text: &str) -> String {
+    let mut rest = text;
+    let mut answer = String::new();
+
+    while let Some((start, tag)) = ["<think>", "<thinking>"]
+        .iter()
+        .filter_map(|tag| rest.find(tag).map(|start| (start, *tag)))
+        .min()
+    {
+        answer.push_str(&rest[..start]);
+        let close = tag.replace('<', "</");
+        rest = match rest[start..].find(&close) {
+            Some(end) => &rest[start + end + close.len()..],
+            None => "",
+        };
+    }
+    answer.push_str(rest);
+
+    if answer.len() == text.len() {
+        answer
+    } else {
+        answer.trim().to_string()
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static IN_FLIGHT: Mutex<Vec<(u64, Instant)>> = Mutex::new(Vec::new());
+
+/// Marks a reasoning request as in flight until it is dropped
+pub struct Thinking {
+    id: u64,
+}
+
+impl Thinking {
+    pub fn start() -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        IN_FLIGHT.lock().unwrap().push((id, Instant::now()));
+        Self { id }
+    }
+}
+
+impl Drop for Thinking {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap().retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// How long the oldest reasoning request still in flight has been thinking
+pub fn thinking_for() -> Option<Duration> {
+    IN_FLIGHT
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, started)| started.elapsed())
+        .max()
+}
+
+/// Await `future` under `spin`, which shows "Thinking… Ns" while a reasoning model works
+/// and `message` otherwise
+pub async fn spin_while<F: Future>(spin: &output::Spinner, message: &str, future: F) -> F::Output {
+    tokio::pin!(future);
+    let mut ticks = tokio::time::interval(Duration::from_millis(500));
+    let mut thinking = false;
+
+    loop {
+        tokio::select! {
+            output = &mut future => return output,
+            _ = ticks.tick() => match thinking_for() {
+                Some(elapsed) => {
+                    thinking = true;
+                    spin.set_message(format!("Thinking… {}s", elapsed.as_secs()));
+                }
+                None if thinking => {
+                    thinking = false;
+                    spin.set_message(message);
+                }
+                None => {}
+            },
+        }
+    }
+}
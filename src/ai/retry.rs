@@ -0,0 +1,176 @@
+use crate::ai::reasoning::ReasoningEffort;
+use crate::ai::{AiClient, ChatCompletionResponse, GenerationProfile, Message, TextStream};
+use crate::error::{AgentError, AgentResult};
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// What providers put before the status code in errors for failed responses
+const STATUS_PREFIX: &str = "error status: ";
+
+/// How failed API calls are retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,  // calls made in all, including the first
+    pub base_delay: Duration, // wait before the first retry, doubled for each one after
+    pub max_delay: Duration,  // longest wait between two calls
+    pub jitter: bool,         // wait a random part of the delay, so clients do not retry in step
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait before retry number `retry`, counting from 1. With jitter the wait is between
+    /// half the delay and the whole of it.
+    pub fn delay(&self, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16) as u32;
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        let random = (uuid::Uuid::new_v4().as_u128() % 1000) as f64 / 1000.0;
+        delay.mul_f64(0.5 + random / 2.0)
+    }
+}
+
+/// The policy from the settings; the default until configured
+static POLICY: RwLock<Option<RetryPolicy>> = RwLock::new(None);
+
+pub fn configure(policy: RetryPolicy) {
+    *POLICY.write().unwrap_or_else(|e| e.into_inner()) = Some(policy);
+}
+
+pub fn policy() -> RetryPolicy {
+    POLICY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .unwrap_or_default()
+}
+
+/// Whether a call that failed with `error` may succeed when made again: rate limits, server
+/// errors and requests that never got an answer. Bad requests and overlong prompts would only
+/// fail again.
+pub fn is_transient(error: &AgentError) -> bool {
+    match error {
+        AgentError::Http(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+        AgentError::AiApi(message) => match message.split_once(STATUS_PREFIX) {
+            Some((_, rest)) => {
+                let status = rest.get(..3).unwrap_or_default();
+                matches!(status, "408" | "429") || status.starts_with('5')
+            }
+            None => message.contains("request failed"),
+        },
+        _ => false,
+    }
+}
+
+/// `call` made again while it fails with a transient error, up to the configured attempts
+pub async fn with_retries<T, F, Fut>(what: &str, mut call: F) -> AgentResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AgentResult<T>>,
+{
+    let policy = policy();
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Err(e) if is_transient(&e) && attempt < policy.max_attempts => {
+                let delay = policy.delay(attempt);
+                log::warn!(
+                    "{} failed ({}); retrying in {:.1}s, attempt {} of {}",
+                    what,
+                    e,
+                    delay.as_secs_f64(),
+                    attempt + 1,
+                    policy.max_attempts
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A client whose calls are retried under the configured policy. Streams are retried until
+/// they start; a stream that breaks off midway is continued by `salvage` instead.
+pub struct RetryingAiClient {
+    inner: Box<dyn AiClient>,
+}
+
+impl RetryingAiClient {
+    pub fn new(inner: Box<dyn AiClient>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl AiClient for RetryingAiClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        with_retries("Text generation", || {
+            self.inner.generate_text(messages.clone())
+        })
+        .await
+    }
+
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        with_retries("Streaming", || self.inner.stream_text(messages.clone())).await
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        messages: Vec<Message>,
+        functions: serde_json::Value,
+        force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        with_retries("Chat completion", || {
+            self.inner.chat_completion_with_functions(
+                messages.clone(),
+                functions.clone(),
+                force_message_only,
+            )
+        })
+        .await
+    }
+
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        with_retries("Embedding", || self.inner.generate_embeddings(text)).await
+    }
+
+    fn provider_name(&self) -> String {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn embedding_model(&self) -> String {
+        self.inner.embedding_model()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(Self::new(self.inner.clone_box()))
+    }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self::new(self.inner.with_profile(profile)))
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        Box::new(Self::new(self.inner.with_reasoning_effort(effort)))
+    }
+}
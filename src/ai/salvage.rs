@@ -0,0 +1,179 @@
+use crate::ai::{AiClient, Message, MessageRole, TextStream};
+use crate::error::{AgentError, AgentResult};
+use futures::stream::{self, StreamExt};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Continuation requests made for one stream before its error is passed on
+static ATTEMPTS: AtomicUsize = AtomicUsize::new(2);
+
+/// Characters of a continuation held back to drop any text it repeats
+const OVERLAP_WINDOW: usize = 200;
+
+/// Shorter repeats are taken to be coincidence, not a restart
+const MIN_OVERLAP: usize = 16;
+
+/// The end of the partial text quoted in a continuation request
+const QUOTED_TAIL: usize = 300;
+
+pub fn configure(attempts: usize) {
+    ATTEMPTS.store(attempts, Ordering::SeqCst);
+}
+
+pub fn attempts() -> usize {
+    ATTEMPTS.load(Ordering::SeqCst)
+}
+
+/// Whether a stream that failed with `error` may be continued. Rejected prompts would only be
+/// rejected again.
+pub fn is_salvageable(error: &AgentError) -> bool {
+    matches!(error, AgentError::AiApi(_) | AgentError::Http(_))
+}
+
+/// `messages` followed by the text written so far and a request to go on from where it stopped
+pub fn continuation(messages: &[Message], partial: &str) -> Vec<Message> {
+    let mut messages = messages.to_vec();
+    if partial.is_empty() {
+        return messages;
+    }
+
+    let tail_start = partial
+        .char_indices()
+        .map(|(index, _)| index)
+        .find(|index| partial.len() - index <= QUOTED_TAIL)
+        .unwrap_or(partial.len());
+    messages.push(Message {
+        role: MessageRole::Assistant,
+        content: partial.to_string(),
+        name: None,
+    });
+    messages.push(Message {
+        role: MessageRole::User,
+        content: format!(
+            "Your response was cut off. Continue it exactly where it stopped, without repeating \
+             anything or adding an introduction. It ended with:\n\n{}",
+            &partial[tail_start..]
+        ),
+        name: None,
+    });
+    messages
+}
+
+/// `text` without the beginning it repeats from the end of `written`
+pub fn trim_overlap<'a>(written: &str, text: &'a str) -> &'a str {
+    let overlap = text
+        .char_indices()
+        .map(|(index, c)| index + c.len_utf8())
+        .rev()
+        .find(|end| *end >= MIN_OVERLAP && written.ends_with(&text[..*end]))
+        .unwrap_or(0);
+    &text[overlap..]
+}
+
+struct Salvage {
+    client: Box<dyn AiClient>,
+    messages: Vec<Message>,
+    tokens: TextStream,
+    written: String,      // text passed on so far
+    held: Option<String>, // the start of a continuation, until its overlap is known
+    pending: VecDeque<AgentResult<String>>,
+    attempts: usize,
+    done: bool,
+}
+
+impl Salvage {
+    /// Pass on the held start of a continuation, minus what it repeats
+    fn release(&mut self) {
+        if let Some(held) = self.held.take() {
+            let text = trim_overlap(&self.written, &held).to_string();
+            self.pass(text);
+        }
+    }
+
+    fn pass(&mut self, text: String) {
+        if !text.is_empty() {
+            self.written.push_str(&text);
+            self.pending.push_back(Ok(text));
+        }
+    }
+
+    fn fail(&mut self, error: AgentError) {
+        self.pending.push_back(Err(error));
+        self.done = true;
+    }
+
+    /// Ask for the rest of the response after `error` broke the stream off
+    async fn resume(&mut self, mut error: AgentError) {
+        loop {
+            if !is_salvageable(&error) || self.attempts >= attempts() {
+                return self.fail(error);
+            }
+            self.attempts += 1;
+            log::warn!(
+                "Response stream broke off after {} characters ({}); continuing, attempt {} of {}",
+                self.written.len(),
+                error,
+                self.attempts,
+                attempts()
+            );
+
+            let messages = continuation(&self.messages, &self.written);
+            match self.client.stream_text(messages).await {
+                Ok(tokens) => {
+                    self.tokens = tokens;
+                    self.held = (!self.written.is_empty()).then(String::new);
+                    return;
+                }
+                Err(e) => error = e,
+            }
+        }
+    }
+}
+
+/// `client`'s streamed answer to `messages`. When the stream breaks off, the text so far is
+/// kept and the rest requested as a continuation, up to the configured number of attempts.
+pub async fn stream_text(client: &dyn AiClient, messages: Vec<Message>) -> AgentResult<TextStream> {
+    let tokens = client.stream_text(messages.clone()).await?;
+    let salvage = Salvage {
+        client: client.clone_box(),
+        messages,
+        tokens,
+        written: String::new(),
+        held: None,
+        pending: VecDeque::new(),
+        attempts: 0,
+        done: false,
+    };
+
+    Ok(stream::unfold(salvage, |mut salvage| async move {
+        loop {
+            if let Some(item) = salvage.pending.pop_front() {
+                return Some((item, salvage));
+            }
+            if salvage.done {
+                return None;
+            }
+
+            match salvage.tokens.next().await {
+                Some(Ok(token)) => match &mut salvage.held {
+                    Some(held) => {
+                        held.push_str(&token);
+                        if held.len() >= OVERLAP_WINDOW {
+                            salvage.release();
+                        }
+                    }
+                    None => salvage.pass(token),
+                },
+                Some(Err(error)) => {
+                    salvage.release();
+                    salvage.resume(error).await;
+                }
+                None => {
+                    salvage.release();
+                    salvage.done = true;
+                }
+            }
+        }
+    })
+    .boxed())
+}
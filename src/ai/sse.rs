@@ -0,0 +1,98 @@
+use crate::ai::TextStream;
+use crate::error::{AgentError, AgentResult};
+use futures::stream::{self, StreamExt};
+
+/// Splits a byte stream into lines, however the bytes of a line or character are spread
+/// across chunks
+#[derive(Debug, Default)]
+pub struct Lines {
+    pending: Vec<u8>, // bytes after the last complete line
+}
+
+impl Lines {
+    /// Every line `chunk` completes, without its line ending
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            lines.push(
+                String::from_utf8_lossy(&line)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_string(),
+            );
+        }
+        lines
+    }
+}
+
+/// Splits a server-sent event stream into the data of each event
+#[derive(Debug, Default)]
+pub struct SseEvents {
+    lines: Lines,
+    data: Vec<String>, // data lines of the event being read
+}
+
+impl SseEvents {
+    /// The data of every event `chunk` completes
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut events = Vec::new();
+
+        for line in self.lines.push(chunk) {
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+            // Event names, ids and `:` comments carry nothing the clients use
+        }
+
+        events
+    }
+}
+
+/// The tokens of a streamed response, taken from each event's data by `token`, which
+/// returns `None` for events without text
+pub fn text_stream(
+    response: reqwest::Response,
+    token: fn(&str) -> AgentResult<Option<String>>,
+) -> TextStream {
+    let mut events = SseEvents::default();
+    token_stream(response, move |chunk| events.push(chunk), token)
+}
+
+/// The tokens of a response streamed as one JSON object per line, as Ollama does
+pub fn json_lines_stream(
+    response: reqwest::Response,
+    token: fn(&str) -> AgentResult<Option<String>>,
+) -> TextStream {
+    let mut lines = Lines::default();
+    token_stream(response, move |chunk| lines.push(chunk), token)
+}
+
+fn token_stream(
+    response: reqwest::Response,
+    mut split: impl FnMut(&[u8]) -> Vec<String> + Send + 'static,
+    token: fn(&str) -> AgentResult<Option<String>>,
+) -> TextStream {
+    response
+        .bytes_stream()
+        .map(move |chunk| match chunk {
+            Ok(bytes) => split(&bytes)
+                .iter()
+                .filter(|item| !item.trim().is_empty())
+                .filter_map(|item| token(item).transpose())
+                .collect(),
+            Err(e) => vec![Err(AgentError::AiApi(format!(
+                "The response stream broke off: {}",
+                e
+            )))],
+        })
+        .flat_map(stream::iter)
+        .boxed()
+}
@@ -0,0 +1,88 @@
+use crate::chunking::SourceLanguage;
+use crate::dirty_tree;
+use crate::error::AgentResult;
+use crate::git;
+use crate::project_context::{self, SymbolIndex};
+use crate::storage::{Storage, StorageTree};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Analysis derived from one commit, shared by every session that starts on it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitAnalysis {
+    pub commit: String,
+    pub symbols: SymbolIndex,
+    pub created_at: String,
+}
+
+/// Per-workspace cache of derived project analysis, keyed by commit. Entries are never
+/// changed once written; when HEAD moves, the entries of other commits are dropped.
+/// Embeddings are cached by file content in the embedding index instead, so they carry over
+/// between commits.
+pub struct AnalysisCache {
+    tree: StorageTree,
+}
+
+impl AnalysisCache {
+    pub fn open(storage: &Storage, workspace_id: &str) -> Self {
+        Self {
+            tree: storage.workspace_tree("analysis_cache", workspace_id),
+        }
+    }
+
+    pub async fn get(&self, commit: &str) -> AgentResult<Option<CommitAnalysis>> {
+        self.tree.get(commit).await
+    }
+
+    /// The symbol index of the workspace, from the cache when a session already indexed
+    /// HEAD. Indexes of source files with uncommitted changes match no commit, so they are
+    /// built fresh and not cached.
+    pub async fn symbol_index(&self, root: &Path) -> AgentResult<SymbolIndex> {
+        let commit = clean_head(root);
+        if let Some(commit) = &commit {
+            if let Some(cached) = self.get(commit).await? {
+                log::info!("Using the cached analysis of {}", commit);
+                return Ok(cached.symbols);
+            }
+        }
+
+        let walk_root = root.to_path_buf();
+        let symbols =
+            tokio::task::spawn_blocking(move || project_context::index_symbols(&walk_root))
+                .await
+                .unwrap_or_default();
+
+        if let Some(commit) = commit {
+            self.store(CommitAnalysis {
+                commit,
+                symbols: symbols.clone(),
+                created_at: utils::current_timestamp(),
+            })
+            .await?;
+        }
+        Ok(symbols)
+    }
+
+    /// Cache `analysis`, dropping the entries of other commits
+    async fn store(&self, analysis: CommitAnalysis) -> AgentResult<()> {
+        for stale in self.tree.values::<CommitAnalysis>().await? {
+            if stale.commit != analysis.commit {
+                self.tree.remove(&stale.commit).await?;
+            }
+        }
+        self.tree.put(&analysis.commit, &analysis).await
+    }
+}
+
+/// HEAD's commit hash, unless source files have uncommitted changes or this is not a git
+/// repository
+fn clean_head(root: &Path) -> Option<String> {
+    let commit = git::run_git(root, &["rev-parse", "--verify", "HEAD"]).ok()?;
+    let source_changed = dirty_tree::uncommitted_files(root)
+        .ok()?
+        .iter()
+        .any(|path| SourceLanguage::from_path(Path::new(path)).is_some());
+
+    (!source_changed && !commit.is_empty()).then_some(commit)
+}
@@ -0,0 +1,80 @@
+use crate::error::AgentResult;
+use crate::explanation::Explanation;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+static ANSWERS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Save every explanation under `dir` for this session, relative to the workspace unless
+/// absolute; only the first call has an effect. Nothing is saved until this is called.
+pub fn configure(dir: PathBuf) {
+    let _ = ANSWERS_DIR.set(dir);
+}
+
+/// Where answers for `workspace_root` are saved, if saving is on
+pub fn dir(workspace_root: &Path) -> Option<PathBuf> {
+    ANSWERS_DIR.get().map(|dir| workspace_root.join(dir))
+}
+
+/// Write `explanation` to a new timestamped markdown file, returning its path
+pub fn save(
+    workspace_root: &Path,
+    query: &str,
+    explanation: &Explanation,
+) -> AgentResult<Option<PathBuf>> {
+    let Some(dir) = dir(workspace_root) else {
+        return Ok(None);
+    };
+    std::fs::create_dir_all(&dir)?;
+
+    let stamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut path = dir.join(format!("{}.md", stamp));
+    let mut attempt = 1;
+    while path.exists() {
+        attempt += 1;
+        path = dir.join(format!("{}_{}.md", stamp, attempt));
+    }
+
+    std::fs::write(&path, explanation.to_markdown(query))?;
+    Ok(Some(path))
+}
+
+/// The most recently saved answer, if any
+pub fn latest(workspace_root: &Path) -> AgentResult<Option<PathBuf>> {
+    let Some(dir) = dir(workspace_root) else {
+        return Ok(None);
+    };
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+
+    let mut answers = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "md") {
+            answers.push((std::fs::metadata(&path)?.modified()?, path));
+        }
+    }
+
+    // Names break ties between answers saved within the same second
+    Ok(answers.into_iter().max().map(|(_, path)| path))
+}
+
+/// Show `path` in `$PAGER` (`less` or `more` by default), printing it if no pager runs
+pub fn open_in_pager(path: &Path) -> AgentResult<()> {
+    let pager = std::env::var("PAGER")
+        .unwrap_or_else(|_| if cfg!(windows) { "more" } else { "less" }.to_string());
+    let mut words = pager.split_whitespace();
+
+    if let Some(program) = words.next() {
+        if let Ok(status) = Command::new(program).args(words).arg(path).status() {
+            if status.success() {
+                return Ok(());
+            }
+        }
+    }
+
+    println!("{}", std::fs::read_to_string(path)?);
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use crate::error::AgentError;
+use crate::subtasks::SubTaskType;
+use std::fmt;
+use std::str::FromStr;
+
+/// How much the user is asked before subtasks run, chosen per session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApprovalMode {
+    /// Run everything without asking
+    Auto,
+    /// Run read-only tools freely and collect mutations for one batched approval
+    Plan,
+    /// Ask before planning steps, commands and edits
+    #[default]
+    ConfirmWrites,
+    /// Ask before every subtask
+    ConfirmAll,
+}
+
+impl ApprovalMode {
+    pub const ALL: [ApprovalMode; 4] = [
+        ApprovalMode::Auto,
+        ApprovalMode::Plan,
+        ApprovalMode::ConfirmWrites,
+        ApprovalMode::ConfirmAll,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Auto => "run everything without asking",
+            Self::Plan => "run read-only tools, then approve all changes at once",
+            Self::ConfirmWrites => "ask before planning, commands and edits",
+            Self::ConfirmAll => "ask before every operation",
+        }
+    }
+
+    /// Whether the user must confirm this subtask right before it runs
+    pub fn requires_confirmation(&self, subtask: &SubTaskType) -> bool {
+        match self {
+            Self::Auto | Self::Plan => false,
+            Self::ConfirmWrites => subtask.requires_user_permission(),
+            Self::ConfirmAll => true,
+        }
+    }
+
+    /// Whether this subtask waits for the batched approval at the end of planning
+    pub fn defers(&self, subtask: &SubTaskType) -> bool {
+        *self == Self::Plan && !subtask.is_read_only()
+    }
+}
+
+impl fmt::Display for ApprovalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Auto => "auto",
+            Self::Plan => "plan",
+            Self::ConfirmWrites => "confirm-writes",
+            Self::ConfirmAll => "confirm-all",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ApprovalMode {
+    type Err = AgentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.to_string() == s.trim())
+            .ok_or_else(|| {
+                AgentError::Configuration(format!(
+                    "Unknown approval mode '{}', expected one of: {}",
+                    s,
+                    Self::ALL
+                        .iter()
+                        .map(|mode| mode.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
@@ -0,0 +1,78 @@
+use crate::agent_engine::{AgentEngine, SharedState};
+use crate::approval::ApprovalMode;
+use crate::config::Settings;
+use crate::error::{AgentError, AgentResult};
+use crate::intent;
+use crate::memory::{ContextMemory, MemoryFragment, Provenance};
+use crate::subtasks::SubTaskType;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Source of the pinned fragment telling the planner the turn may only read
+pub const READ_ONLY_TURN_SOURCE: &str = "read_only_turn";
+
+/// Planner instructions naming every tool a read-only turn may use
+fn read_only_turn_instructions() -> String {
+    let tools = SubTaskType::all_tools()
+        .iter()
+        .filter(|tool| tool.is_read_only())
+        .filter(|tool| !matches!(tool, SubTaskType::Task(_) | SubTaskType::ExplainTool(_)))
+        .map(|tool| tool.tool_name())
+        .collect::<Vec<_>>();
+
+    format!(
+        "This turn is read-only: plan only with {}, and finish with ExplainTool. Do not edit \
+         files, run commands or record facts.",
+        tools.join(", ")
+    )
+}
+
+/// Answer `question` in one read-only turn: only search, read and explain tools run, nothing
+/// is confirmed, and the answer is printed. Tools that would change anything are skipped.
+pub async fn run_ask(
+    question: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    settings: Arc<Settings>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
+    if let Err(e) = shared_state.load_pinned_context(&context_memory).await {
+        log::warn!("Failed to load long-term memory: {}", e);
+    }
+    context_memory.lock().await.pin_frag(MemoryFragment {
+        source: READ_ONLY_TURN_SOURCE.to_string(),
+        provenance: Provenance::UserProvided,
+        content: read_only_turn_instructions(),
+        metadata: None,
+    });
+
+    let mut engine = agent_engine.lock().await;
+    engine.approval_mode = ApprovalMode::Auto;
+    engine.read_only = true;
+    engine.begin_turn(question);
+
+    // General-knowledge questions need no tools at all
+    match intent::answer_without_tools(shared_state.ai_client.as_ref(), question, &context_memory)
+        .await
+    {
+        Ok(Some(answer)) => {
+            println!("{}", answer);
+            return Ok(());
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Intent check failed, planning instead: {}", e),
+    }
+
+    // ExplainTool prints the answer as it is generated
+    engine.push_subtask(SubTaskType::Task(question.to_string()));
+    engine
+        .handle_subtasks(shared_state, context_memory, settings)
+        .await;
+
+    match engine.turn_report.explanation {
+        Some(_) => Ok(()),
+        None => Err(AgentError::Cli(
+            "No answer was produced; try rephrasing the question".to_string(),
+        )),
+    }
+}
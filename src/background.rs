@@ -0,0 +1,270 @@
+use crate::agent_engine::SharedState;
+use crate::ai::{AiClient, Message, MessageRole};
+use crate::error::AgentResult;
+use crate::file_rank;
+use crate::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance};
+use crate::project_context;
+use crate::storage::StorageTree;
+use crate::utils;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+/// Context memory larger than this is compacted in the background
+pub const COMPACT_AFTER_CHARS: usize = 60_000;
+
+/// Fragments left as they are when compacting, newest first
+pub const KEEP_RECENT_FRAGMENTS: usize = 8;
+
+/// Source of the fragment that replaces compacted ones
+pub const COMPACTED_SOURCE: &str = "compacted_memory";
+
+/// Non-interactive work done between prompts, persisted until it has run
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "path", rename_all = "snake_case")]
+pub enum Job {
+    PrefetchRepoMap,   // gather the project overview into context memory
+    EmbedFile(String), // refresh the embeddings of a changed file
+    CompactMemory,     // summarize older context fragments into one
+}
+
+impl Job {
+    pub fn description(&self) -> String {
+        match self {
+            Self::PrefetchRepoMap => "Prefetch the repo map".to_string(),
+            Self::EmbedFile(path) => format!("Embed {}", path),
+            Self::CompactMemory => "Compact context memory".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedJob {
+    key: String, // its storage key, to remove it once it has run
+    job: Job,
+    queued_at: String,
+}
+
+/// Runs background jobs on bounded tokio tasks, starting at most `per_minute` of them a
+/// minute, so they never hold up the interactive loop. Queued jobs are stored with the
+/// workspace and resumed by the next session if this one ends first.
+pub struct BackgroundJobs {
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    queue: StorageTree,
+    permits: Arc<Semaphore>,
+    interval: Duration,                      // between job starts
+    next_start: Mutex<Instant>,              // when the next job may start
+    pending: std::sync::Mutex<HashSet<Job>>, // queued or running, so duplicates are skipped
+}
+
+impl BackgroundJobs {
+    pub fn new(
+        shared_state: Arc<SharedState>,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        max_concurrent: usize,
+        per_minute: u32,
+    ) -> Arc<Self> {
+        let queue = shared_state
+            .storage
+            .workspace_tree("background_jobs", &shared_state.workspace_id);
+
+        Arc::new(Self {
+            shared_state,
+            context_memory,
+            queue,
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            interval: Duration::from_secs(60) / per_minute.max(1),
+            next_start: Mutex::new(Instant::now()),
+            pending: std::sync::Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Queue a job unless an identical one is already waiting or running
+    pub async fn enqueue(self: &Arc<Self>, job: Job) {
+        if !self.pending.lock().unwrap().insert(job.clone()) {
+            return;
+        }
+
+        // Timestamp keys keep jobs in the order they were queued
+        let key = format!(
+            "{:020}-{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            utils::generate_id()
+        );
+        let entry = QueuedJob {
+            key: key.clone(),
+            job: job.clone(),
+            queued_at: utils::current_timestamp(),
+        };
+        if let Err(e) = self.queue.put(&key, &entry).await {
+            log::warn!("Failed to persist background job: {}", e);
+        }
+
+        self.spawn(key, job);
+    }
+
+    /// Start the jobs an earlier session queued but did not finish
+    pub async fn resume(self: &Arc<Self>) -> AgentResult<usize> {
+        let mut resumed = 0;
+
+        for entry in self.queue.values::<QueuedJob>().await? {
+            if self.pending.lock().unwrap().insert(entry.job.clone()) {
+                self.spawn(entry.key, entry.job);
+                resumed += 1;
+            } else {
+                self.queue.remove(&entry.key).await?;
+            }
+        }
+        Ok(resumed)
+    }
+
+    /// Jobs queued or running right now
+    pub fn pending(&self) -> Vec<Job> {
+        self.pending.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn spawn(self: &Arc<Self>, key: String, job: Job) {
+        let jobs = Arc::clone(self);
+        tokio::spawn(async move {
+            let Ok(_permit) = Arc::clone(&jobs.permits).acquire_owned().await else {
+                return;
+            };
+            jobs.wait_for_slot().await;
+
+            if let Err(e) = jobs.run(&job).await {
+                log::warn!("Background job '{}' failed: {}", job.description(), e);
+            }
+
+            // Failed jobs are dropped too, so one bad job is not retried every session
+            if let Err(e) = jobs.queue.remove(&key).await {
+                log::warn!("Failed to remove finished background job: {}", e);
+            }
+            jobs.pending.lock().unwrap().remove(&job);
+        });
+    }
+
+    /// Space job starts `interval` apart
+    async fn wait_for_slot(&self) {
+        let start = {
+            let mut next_start = self.next_start.lock().await;
+            let start = (*next_start).max(Instant::now());
+            *next_start = start + self.interval;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+
+    async fn run(&self, job: &Job) -> AgentResult<()> {
+        match job {
+            Job::PrefetchRepoMap => {
+                let root = self.shared_state.workspace_root.clone();
+                let symbols = self
+                    .shared_state
+                    .analysis_cache()
+                    .symbol_index(&root)
+                    .await?;
+                // Searches list the files the index found most important first
+                if !symbols.ranking.is_empty() {
+                    file_rank::configure(symbols.ranking.clone());
+                }
+                let fragments = tokio::task::spawn_blocking(move || {
+                    project_context::gather_with(&root, &symbols)
+                })
+                .await
+                .unwrap_or_default();
+
+                let mut memory = self.context_memory.lock().await;
+                for fragment in fragments {
+                    memory.add_frag(fragment);
+                }
+                Ok(())
+            }
+            Job::EmbedFile(path) => {
+                self.shared_state
+                    .embedding_index()
+                    .refresh(
+                        self.shared_state.ai_client.as_ref(),
+                        &self.shared_state.workspace_root,
+                        path,
+                    )
+                    .await?;
+                Ok(())
+            }
+            Job::CompactMemory => self.compact_memory().await,
+        }
+    }
+
+    /// Fold older fragments into one summary; memory stays unlocked while the model writes it
+    async fn compact_memory(&self) -> AgentResult<()> {
+        let folded = {
+            let memory = self.context_memory.lock().await;
+            if memory.content_len() <= COMPACT_AFTER_CHARS {
+                return Ok(());
+            }
+            memory.compactable(KEEP_RECENT_FRAGMENTS)
+        };
+        if folded.len() < 2 {
+            return Ok(());
+        }
+
+        let summary = summarize_fragments(
+            self.shared_state.ai_client.as_ref(),
+            &folded,
+            COMPACT_AFTER_CHARS * 2,
+        )
+        .await?;
+
+        let compacted = self.context_memory.lock().await.compact(&folded, summary);
+        if compacted {
+            log::info!("Compacted {} context fragments", folded.len());
+        }
+        Ok(())
+    }
+}
+
+/// Have the model fold `fragments` into one summary fragment, reading at most `max_chars`
+/// of them
+pub async fn summarize_fragments(
+    ai_client: &dyn AiClient,
+    fragments: &[MemoryFragment],
+    max_chars: usize,
+) -> AgentResult<MemoryFragment> {
+    let notes = fragments
+        .iter()
+        .map(|fragment| format!("[{}]\n{}", fragment.source, fragment.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: "You compress a coding agent's working notes. Keep file paths, symbol \
+                names, commands and their outcomes, errors, and decisions; drop repetition \
+                and raw listings. Reply with the condensed notes only."
+                .to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: utils::truncate_string(&notes, max_chars),
+            name: None,
+        },
+    ];
+    let summary = ai_client.generate_text(messages).await?;
+
+    Ok(MemoryFragment {
+        source: COMPACTED_SOURCE.to_string(),
+        provenance: Provenance::ModelGenerated,
+        content: summary.trim().to_string(),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("summary".to_string()),
+            path: None,
+            timestamp: Some(Utc::now().timestamp()),
+            tags: vec!["compacted".to_string()],
+        }),
+    })
+}
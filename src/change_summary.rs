@@ -0,0 +1,161 @@
+use crate::ai::{AiClient, Message, MessageRole};
+use crate::error::AgentResult;
+use crate::git;
+use crate::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance};
+use crate::report::TurnReport;
+use crate::storage::{Storage, StorageTree};
+use crate::tool_env;
+use crate::utils;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::sync::Mutex;
+
+/// Source name used for the pinned fragment holding recent change summaries
+pub const RECENT_CHANGES_SOURCE: &str = "recent_changes";
+
+/// Summaries pinned into context; older ones stay in storage
+const MAX_PINNED_SUMMARIES: usize = 5;
+
+/// The diff is truncated so large turns still fit in one prompt
+const MAX_DIFF_CHARS: usize = 12000;
+
+/// What the agent changed in one turn and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeSummary {
+    pub id: String,
+    pub prompt: String,
+    pub files: Vec<String>,
+    pub summary: String,
+    pub created_at: String,
+}
+
+/// Per-workspace record of the changes the agent made, newest last
+pub struct ChangeHistory {
+    tree: StorageTree,
+}
+
+impl ChangeHistory {
+    pub fn open(storage: &Storage, workspace_id: &str) -> Self {
+        Self {
+            tree: storage.workspace_tree("change_summaries", workspace_id),
+        }
+    }
+
+    pub async fn record(&self, prompt: &str, files: &[String], summary: &str) -> AgentResult<()> {
+        let entry = ChangeSummary {
+            id: utils::generate_id(),
+            prompt: prompt.to_string(),
+            files: files.to_vec(),
+            summary: summary.to_string(),
+            created_at: utils::current_timestamp(),
+        };
+
+        // Timestamp keys keep entries in the order they were made
+        let key = format!(
+            "{:020}-{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            entry.id
+        );
+        self.tree.put(&key, &entry).await
+    }
+
+    /// The most recent `count` summaries, oldest first
+    pub async fn recent(&self, count: usize) -> AgentResult<Vec<ChangeSummary>> {
+        let summaries: Vec<ChangeSummary> = self.tree.values().await?;
+        let skip = summaries.len().saturating_sub(count);
+        Ok(summaries.into_iter().skip(skip).collect())
+    }
+
+    /// Render the latest summaries as a single fragment suitable for pinning in context memory
+    pub async fn to_memory_fragment(&self) -> AgentResult<Option<MemoryFragment>> {
+        let summaries = self.recent(MAX_PINNED_SUMMARIES).await?;
+        if summaries.is_empty() {
+            return Ok(None);
+        }
+
+        let mut content = String::from("Changes the agent made in earlier turns:\n");
+        for summary in &summaries {
+            content.push_str(&format!(
+                "\n[{}] {}\n{}\n",
+                summary.created_at,
+                utils::truncate_string(&summary.prompt, 120),
+                summary.summary
+            ));
+        }
+
+        Ok(Some(MemoryFragment {
+            source: RECENT_CHANGES_SOURCE.to_string(),
+            provenance: Provenance::ModelGenerated,
+            content,
+            metadata: Some(MemoryMetadata {
+                file_type: Some("change_summary".to_string()),
+                path: None,
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec!["pinned".to_string(), "change_summary".to_string()],
+            }),
+        }))
+    }
+
+    /// Refresh the pinned recent changes fragment in context memory
+    pub async fn pin_into(&self, context_memory: &Mutex<ContextMemory>) -> AgentResult<()> {
+        let fragment = self.to_memory_fragment().await?;
+        let mut memory = context_memory.lock().await;
+
+        match fragment {
+            Some(fragment) => memory.pin_frag(fragment),
+            None => memory.unpin(RECENT_CHANGES_SOURCE),
+        }
+        Ok(())
+    }
+}
+
+/// Summarize what a turn changed: the files, the key hunks and why, from the request,
+/// the executed steps and the working-tree diff of the changed files
+pub async fn generate(
+    ai_client: &dyn AiClient,
+    workspace_root: &Path,
+    report: &TurnReport,
+) -> AgentResult<String> {
+    let mut args = vec!["diff", "--"];
+    args.extend(report.files_changed.iter().map(String::as_str));
+    // Outside a git repository the steps are all there is to go on
+    let diff = git::run_git(workspace_root, &args).unwrap_or_default();
+
+    let steps = report
+        .steps
+        .iter()
+        .map(|step| format!("- {}", step.description))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: "You summarize changes a coding agent just made, for the user who asked \
+                for them. Write short Markdown: one bullet per changed file naming the key \
+                hunks (functions, types or config touched), then one or two sentences on why, \
+                tied to the request. Do not describe anything the diff and steps do not show."
+                .to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: format!(
+                "Request:\n{}\n\nSteps taken:\n{}\n\nChanged files: {}\n\nDiff:\n{}",
+                report.prompt,
+                steps,
+                report.files_changed.join(", "),
+                if diff.is_empty() {
+                    "(not available)".to_string()
+                } else {
+                    utils::truncate_string(&tool_env::mask(&diff), MAX_DIFF_CHARS)
+                }
+            ),
+            name: None,
+        },
+    ];
+
+    let summary = ai_client.generate_text(messages).await?;
+    Ok(summary.trim().to_string())
+}
@@ -0,0 +1,271 @@
+use crate::ai::{AiClient, Message, MessageRole};
+use crate::embeddings::EmbeddingIndex;
+use crate::error::{AgentError, AgentResult};
+use crate::git::CommitSummary;
+use crate::subtasks::subtask::FileEditToolInputs;
+use crate::utils;
+use chrono::Utc;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+/// Category a commit falls under in the changelog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Feature,
+    Fix,
+    Performance,
+    Refactor,
+    Docs,
+    Tests,
+    Chore,
+    Other,
+}
+
+impl ChangeKind {
+    /// Classify a commit from a conventional-commit prefix or its leading verb
+    pub fn from_subject(subject: &str) -> Self {
+        let subject = subject.trim();
+
+        // Drop a leading "[ticket]" marker
+        let subject = match subject.strip_prefix('[') {
+            Some(rest) => rest
+                .split_once(']')
+                .map(|(_, s)| s.trim())
+                .unwrap_or(subject),
+            None => subject,
+        };
+
+        if let Some((prefix, _)) = subject.split_once(':') {
+            let prefix = prefix
+                .split('(')
+                .next()
+                .unwrap_or(prefix)
+                .trim_end_matches('!')
+                .to_lowercase();
+
+            match prefix.as_str() {
+                "feat" | "feature" => return Self::Feature,
+                "fix" | "bugfix" => return Self::Fix,
+                "perf" => return Self::Performance,
+                "refactor" | "style" => return Self::Refactor,
+                "docs" => return Self::Docs,
+                "test" | "tests" => return Self::Tests,
+                "chore" | "build" | "ci" => return Self::Chore,
+                _ => {}
+            }
+        }
+
+        let verb = subject
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match verb.as_str() {
+            "add" | "adds" | "added" | "implement" | "introduce" | "support" => Self::Feature,
+            "fix" | "fixes" | "fixed" | "resolve" | "handle" => Self::Fix,
+            "speed" | "optimize" | "cache" => Self::Performance,
+            "refactor" | "remove" | "rename" | "move" | "simplify" | "extract" => Self::Refactor,
+            "document" | "docs" => Self::Docs,
+            "test" | "tests" => Self::Tests,
+            "bump" | "update" | "upgrade" => Self::Chore,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn heading(&self) -> &'static str {
+        match self {
+            Self::Feature => "Features",
+            Self::Fix => "Bug Fixes",
+            Self::Performance => "Performance",
+            Self::Refactor => "Refactoring",
+            Self::Docs => "Documentation",
+            Self::Tests => "Tests",
+            Self::Chore => "Maintenance",
+            Self::Other => "Other Changes",
+        }
+    }
+}
+
+/// Group commits by kind, in changelog order
+pub fn group_commits(commits: &[CommitSummary]) -> Vec<(ChangeKind, Vec<&CommitSummary>)> {
+    let mut groups: Vec<(ChangeKind, Vec<&CommitSummary>)> = Vec::new();
+
+    for commit in commits {
+        let kind = ChangeKind::from_subject(&commit.subject);
+        match groups.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, group)) => group.push(commit),
+            None => groups.push((kind, vec![commit])),
+        }
+    }
+
+    groups.sort_by_key(|(kind, _)| *kind);
+    groups
+}
+
+/// Top-level areas of the codebase a set of files belongs to, e.g. `src/cli`
+fn touched_areas<'a>(files: impl Iterator<Item = &'a String>) -> BTreeSet<String> {
+    files
+        .map(|file| {
+            let parts: Vec<&str> = file.split('/').collect();
+            match parts.len() {
+                1 => parts[0].to_string(),
+                _ => parts[..parts.len() - 1]
+                    .iter()
+                    .take(2)
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            }
+        })
+        .collect()
+}
+
+/// Symbols the search index holds for each of the touched files, so the outline can name the
+/// affected modules and functions. Files missing from the index are left out.
+pub async fn indexed_symbols(
+    index: &EmbeddingIndex,
+    commits: &[CommitSummary],
+) -> BTreeMap<String, Vec<String>> {
+    let files: BTreeSet<&String> = commits.iter().flat_map(|c| c.files.iter()).collect();
+    let mut symbols = BTreeMap::new();
+
+    for file in files {
+        let Ok(Some(embedded)) = index.get(file).await else {
+            continue;
+        };
+        let mut names: Vec<String> = Vec::new();
+        for symbol in embedded.chunks.into_iter().filter_map(|chunk| chunk.symbol) {
+            if !names.contains(&symbol) {
+                names.push(symbol);
+            }
+        }
+        if !names.is_empty() {
+            symbols.insert(file.clone(), names);
+        }
+    }
+    symbols
+}
+
+/// Plain-text outline of the grouped commits, used as the generation prompt, naming the
+/// indexed symbols of the files each group touched
+pub fn outline(
+    groups: &[(ChangeKind, Vec<&CommitSummary>)],
+    symbols: &BTreeMap<String, Vec<String>>,
+) -> String {
+    let mut outline = String::new();
+
+    for (kind, commits) in groups {
+        let files: BTreeSet<&String> = commits.iter().flat_map(|c| c.files.iter()).collect();
+        let areas = touched_areas(files.iter().copied());
+
+        outline.push_str(&format!("{}:\n", kind.heading()));
+        for commit in commits {
+            outline.push_str(&format!(
+                "- {} ({}) files: {}\n",
+                commit.subject,
+                &commit.hash[..7.min(commit.hash.len())],
+                commit.files.join(", ")
+            ));
+        }
+        outline.push_str(&format!(
+            "  areas touched: {}\n",
+            areas.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+        for file in files {
+            if let Some(names) = symbols.get(file) {
+                outline.push_str(&format!("  {} defines: {}\n", file, names.join(", ")));
+            }
+        }
+        outline.push('\n');
+    }
+
+    outline
+}
+
+/// Ask the model to turn the commit outline into a changelog section
+pub async fn generate_section(
+    ai_client: &dyn AiClient,
+    index: &EmbeddingIndex,
+    since: Option<&str>,
+    commits: &[CommitSummary],
+) -> AgentResult<String> {
+    if commits.is_empty() {
+        return Err(AgentError::TaskExecution(
+            "No commits to summarize".to_string(),
+        ));
+    }
+
+    let groups = group_commits(commits);
+    let symbols = indexed_symbols(index, commits).await;
+
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: "You write concise, user-facing changelog entries in Markdown. \
+                Use one `### <Category>` heading per group provided, in the same order, \
+                with one bullet per user-visible change. Merge commits that describe the \
+                same change, omit purely internal noise, and mention the affected area \
+                when it helps the reader. Output only the headings and bullets."
+                .to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: format!("Commits grouped by type:\n\n{}", outline(&groups, &symbols)),
+            name: None,
+        },
+    ];
+
+    let body = ai_client.generate_text(messages).await?;
+
+    let title = match since {
+        Some(since) => format!("## Unreleased (since {})", since),
+        None => "## Unreleased".to_string(),
+    };
+
+    Ok(format!(
+        "{} - {}\n\n{}\n",
+        title,
+        Utc::now().format("%Y-%m-%d"),
+        body.trim()
+    ))
+}
+
+/// Edit inputs that add `section` to the top of the changelog, creating it if needed
+pub fn changelog_edit(workspace_root: &Path, section: &str) -> AgentResult<FileEditToolInputs> {
+    let path = workspace_root.join(CHANGELOG_FILE);
+    let file_path = utils::path_to_string(&path);
+
+    if !path.exists() {
+        return Ok(FileEditToolInputs {
+            file_path,
+            old_string: String::new(),
+            new_string: format!("# Changelog\n\n{}", section),
+        });
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+
+    // Insert below the title when there is one, otherwise above everything
+    let anchor = content
+        .lines()
+        .next()
+        .filter(|line| line.starts_with("# ") && content.matches(*line).count() == 1);
+
+    let (old_string, new_string) = match anchor {
+        Some(title) => (
+            title.to_string(),
+            format!("{}\n\n{}", title, section.trim_end()),
+        ),
+        None => (content.clone(), format!("{}\n{}", section, content)),
+    };
+
+    Ok(FileEditToolInputs {
+        file_path,
+        old_string,
+        new_string,
+    })
+}
@@ -0,0 +1,406 @@
+use std::ops::Range;
+use std::path::Path;
+use tree_sitter::{Language, Node, Parser};
+
+/// Definitions longer than this are split into their members when possible
+const MAX_CHUNK_LINES: usize = 120;
+
+/// Lines of preceding context repeated at the start of each chunk
+const OVERLAP_LINES: usize = 3;
+
+/// Window size used for unsupported languages and code between definitions
+const FALLBACK_CHUNK_LINES: usize = 60;
+
+/// A contiguous span of a source file, ideally one complete definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeChunk {
+    pub start_line: usize, // 1-based, inclusive, including overlap
+    pub end_line: usize,   // 1-based, inclusive
+    pub symbol: Option<String>,
+    pub kind: String,
+    pub content: String,
+}
+
+/// Languages with a tree-sitter grammar available for chunking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceLanguage {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl SourceLanguage {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    fn language(&self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    /// Node kinds that form a chunk of their own
+    fn is_definition(&self, kind: &str) -> bool {
+        match self {
+            Self::Rust => matches!(
+                kind,
+                "function_item"
+                    | "struct_item"
+                    | "enum_item"
+                    | "union_item"
+                    | "impl_item"
+                    | "trait_item"
+                    | "mod_item"
+                    | "macro_definition"
+                    | "const_item"
+                    | "static_item"
+                    | "type_item"
+            ),
+            Self::Python => matches!(
+                kind,
+                "function_definition" | "class_definition" | "decorated_definition"
+            ),
+            Self::JavaScript | Self::TypeScript | Self::Tsx => matches!(
+                kind,
+                "function_declaration"
+                    | "generator_function_declaration"
+                    | "class_declaration"
+                    | "abstract_class_declaration"
+                    | "method_definition"
+                    | "interface_declaration"
+                    | "type_alias_declaration"
+                    | "enum_declaration"
+                    | "lexical_declaration"
+                    | "export_statement"
+            ),
+            Self::Go => matches!(
+                kind,
+                "function_declaration" | "method_declaration" | "type_declaration"
+            ),
+        }
+    }
+}
+
+/// Split a source file into chunks at function/class boundaries.
+/// Falls back to fixed-size line windows when the language is unsupported or unparsable.
+pub fn chunk_source(path: &Path, content: &str) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    let Some(language) = SourceLanguage::from_path(path) else {
+        return fixed_size_chunks(&lines, 1, lines.len());
+    };
+
+    let mut parser = Parser::new();
+    if parser.set_language(&language.language()).is_err() {
+        return fixed_size_chunks(&lines, 1, lines.len());
+    }
+
+    let Some(tree) = parser.parse(content, None) else {
+        return fixed_size_chunks(&lines, 1, lines.len());
+    };
+
+    let mut spans = Vec::new();
+    collect_definitions(
+        language,
+        tree.root_node(),
+        content.as_bytes(),
+        None,
+        false,
+        &mut spans,
+    );
+
+    let mut chunks: Vec<CodeChunk> = spans
+        .into_iter()
+        .map(|span| {
+            let start = extend_over_comments(&lines, span.start_line);
+            let start = start.saturating_sub(OVERLAP_LINES).max(1);
+            make_chunk(&lines, start, span.end_line, span.symbol, span.kind)
+        })
+        .collect();
+
+    // Code between definitions (imports, statements) still needs to be searchable
+    let mut covered = vec![false; lines.len() + 1];
+    for chunk in &chunks {
+        covered[chunk.start_line..=chunk.end_line.min(lines.len())].fill(true);
+    }
+
+    let mut gap_start = None;
+    for line in 1..=lines.len() + 1 {
+        let is_gap = line <= lines.len() && !covered[line];
+        match (is_gap, gap_start) {
+            (true, None) => gap_start = Some(line),
+            (false, Some(start)) => {
+                if lines[start - 1..line - 1]
+                    .iter()
+                    .any(|l| !l.trim().is_empty())
+                {
+                    chunks.extend(fixed_size_chunks(&lines, start, line - 1));
+                }
+                gap_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    chunks.sort_by_key(|chunk| (chunk.start_line, chunk.end_line));
+    chunks
+}
+
+/// Find the definition of `symbol`, matching either its full or last path segment.
+/// Members of containers are searched regardless of the container's size.
+pub fn find_symbol(path: &Path, content: &str, symbol: &str) -> Option<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    symbol_spans(path, content)?
+        .into_iter()
+        .find(|span| span.matches(symbol))
+        .map(|span| {
+            let start = extend_over_comments(&lines, span.start_line);
+            make_chunk(&lines, start, span.end_line, span.symbol, span.kind)
+        })
+}
+
+/// Every definition in the file, members of containers included, without leading comments
+pub fn definitions(path: &Path, content: &str) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    symbol_spans(path, content)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|span| {
+            make_chunk(
+                &lines,
+                span.start_line,
+                span.end_line,
+                span.symbol,
+                span.kind,
+            )
+        })
+        .collect()
+}
+
+/// Byte ranges of every definition matching `symbol`, excluding leading doc comments
+pub fn symbol_byte_ranges(path: &Path, content: &str, symbol: &str) -> Vec<Range<usize>> {
+    symbol_spans(path, content)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|span| span.matches(symbol))
+        .map(|span| span.byte_range)
+        .collect()
+}
+
+/// Every definition in the file, including members of containers
+fn symbol_spans(path: &Path, content: &str) -> Option<Vec<DefinitionSpan>> {
+    let language = SourceLanguage::from_path(path)?;
+
+    let mut parser = Parser::new();
+    parser.set_language(&language.language()).ok()?;
+    let tree = parser.parse(content, None)?;
+
+    let mut spans = Vec::new();
+    collect_definitions(
+        language,
+        tree.root_node(),
+        content.as_bytes(),
+        None,
+        true,
+        &mut spans,
+    );
+
+    Some(spans)
+}
+
+struct DefinitionSpan {
+    start_line: usize,
+    end_line: usize,
+    byte_range: Range<usize>,
+    symbol: Option<String>,
+    kind: String,
+}
+
+impl DefinitionSpan {
+    fn matches(&self, symbol: &str) -> bool {
+        self.symbol
+            .as_deref()
+            .is_some_and(|name| name == symbol || name.rsplit("::").next() == Some(symbol))
+    }
+}
+
+fn collect_definitions(
+    language: SourceLanguage,
+    node: Node,
+    source: &[u8],
+    parent: Option<&str>,
+    all_members: bool, // record every container and its members instead of splitting by size
+    spans: &mut Vec<DefinitionSpan>,
+) {
+    let mut cursor = node.walk();
+
+    for child in node.named_children(&mut cursor) {
+        if !language.is_definition(child.kind()) {
+            continue;
+        }
+
+        let start_line = child.start_position().row + 1;
+        let end_line = child.end_position().row + 1;
+        let name = definition_name(child, source);
+        let symbol = match (parent, name) {
+            (Some(parent), Some(name)) => Some(format!("{}::{}", parent, name)),
+            (None, name) => name,
+            (Some(_), None) => None,
+        };
+
+        let span = DefinitionSpan {
+            start_line,
+            end_line,
+            byte_range: child.byte_range(),
+            symbol,
+            kind: child.kind().to_string(),
+        };
+
+        if all_members {
+            let parent = span.symbol.clone();
+            spans.push(span);
+            if let Some(body) = container_body(child) {
+                collect_definitions(language, body, source, parent.as_deref(), true, spans);
+            }
+            continue;
+        }
+
+        // Split large containers (impl blocks, classes) into their members
+        if end_line - start_line + 1 > MAX_CHUNK_LINES {
+            if let Some(body) = container_body(child) {
+                let before = spans.len();
+                collect_definitions(language, body, source, span.symbol.as_deref(), false, spans);
+                if spans.len() > before {
+                    continue;
+                }
+            }
+        }
+
+        spans.push(span);
+    }
+}
+
+/// The node holding the members of a container definition, if it is one
+fn container_body(node: Node) -> Option<Node> {
+    // Wrappers like `export class ...` or decorated Python classes hold the definition inside
+    if matches!(node.kind(), "export_statement" | "decorated_definition") {
+        let inner = node
+            .child_by_field_name("declaration")
+            .or_else(|| node.child_by_field_name("definition"))?;
+        return container_body(inner);
+    }
+
+    node.child_by_field_name("body")
+}
+
+fn definition_name(node: Node, source: &[u8]) -> Option<String> {
+    let text = |n: Node| n.utf8_text(source).ok().map(str::to_string);
+
+    match node.kind() {
+        "impl_item" => {
+            let type_name = text(node.child_by_field_name("type")?)?;
+            match node.child_by_field_name("trait").and_then(text) {
+                Some(trait_name) => Some(format!("{} for {}", trait_name, type_name)),
+                None => Some(type_name),
+            }
+        }
+        "export_statement" | "decorated_definition" => {
+            let inner = node
+                .child_by_field_name("declaration")
+                .or_else(|| node.child_by_field_name("definition"))?;
+            definition_name(inner, source)
+        }
+        // `const foo = () => ...` and Go `type Foo struct` name their first declarator
+        "lexical_declaration" | "type_declaration" => {
+            let mut cursor = node.walk();
+            let declarator = node
+                .named_children(&mut cursor)
+                .find(|c| matches!(c.kind(), "variable_declarator" | "type_spec"))?;
+            text(declarator.child_by_field_name("name")?)
+        }
+        _ => text(node.child_by_field_name("name")?),
+    }
+}
+
+/// Move a start line up to include doc comments and attributes directly above it
+fn extend_over_comments(lines: &[&str], start_line: usize) -> usize {
+    let mut start = start_line;
+
+    while start > 1 {
+        let previous = lines[start - 2].trim_start();
+        let is_annotation = ["//", "#", "/*", "*", "@"]
+            .iter()
+            .any(|prefix| previous.starts_with(prefix));
+
+        if !is_annotation {
+            break;
+        }
+        start -= 1;
+    }
+
+    start
+}
+
+fn make_chunk(
+    lines: &[&str],
+    start_line: usize,
+    end_line: usize,
+    symbol: Option<String>,
+    kind: String,
+) -> CodeChunk {
+    let end_line = end_line.min(lines.len()).max(start_line);
+
+    CodeChunk {
+        start_line,
+        end_line,
+        symbol,
+        kind,
+        content: lines
+            .get(start_line - 1..end_line)
+            .unwrap_or_default()
+            .join("\n"),
+    }
+}
+
+/// Overlapping fixed-size windows over lines `start..=end` (1-based)
+fn fixed_size_chunks(lines: &[&str], start: usize, end: usize) -> Vec<CodeChunk> {
+    let mut chunks = Vec::new();
+    let mut window_start = start;
+
+    while window_start <= end {
+        let window_end = (window_start + FALLBACK_CHUNK_LINES - 1).min(end);
+        chunks.push(make_chunk(
+            lines,
+            window_start,
+            window_end,
+            None,
+            "lines".to_string(),
+        ));
+
+        if window_end == end {
+            break;
+        }
+        window_start = window_end + 1 - OVERLAP_LINES;
+    }
+
+    chunks
+}
@@ -0,0 +1,170 @@
+use crate::ai::{Message, MessageRole};
+use crate::error::AgentResult;
+use crate::memory::{ContextMemory, MemoryFragment, Provenance};
+use crate::AiClient;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Source of the fragment recording which questions were asked about a request
+pub const CLARIFICATION_SOURCE: &str = "clarification";
+
+/// Most questions asked before planning a single request
+const MAX_QUESTIONS: usize = 3;
+
+const CLARIFY_PROMPT: &str = r#"
+You are the step before planning in a command-line coding agent working in the user's project.
+Decide whether the user's request is clear enough to plan, given the pinned project context.
+
+Most requests are clear: answer with no questions. Only ask when a reasonable plan depends on
+something you cannot infer, e.g. "clean this up" (which files? what kind of cleanup?) or
+"make it faster" (which part? what trade-offs are acceptable?).
+
+When you do ask, give at most 3 short, targeted questions, each with 2-4 likely answers as
+options where that makes sense. Answer by calling the Clarify function.
+"#;
+
+/// A question to put to the user, with suggested answers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Question {
+    pub question: String,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// Questions asked about one request, kept in context memory so re-runs of the task ask each
+/// question only once
+#[derive(Debug, Clone, Default)]
+pub struct Clarification {
+    pub questions: Vec<Question>,
+}
+
+impl Clarification {
+    /// The clarification already decided for `request`, if any
+    pub fn recorded(memory: &ContextMemory, request: &str) -> Option<Self> {
+        let header = header(request);
+        memory
+            .get_fragments()
+            .iter()
+            .rev()
+            .filter(|f| f.source == CLARIFICATION_SOURCE)
+            .find_map(|f| f.content.strip_prefix(header.as_str()))
+            .and_then(|questions| serde_json::from_str(questions).ok())
+            .map(|questions| Self { questions })
+    }
+
+    /// Remember the questions decided for `request`, even when there are none
+    pub fn record(&self, memory: &mut ContextMemory, request: &str) {
+        memory.add_frag(MemoryFragment {
+            source: CLARIFICATION_SOURCE.to_string(),
+            provenance: Provenance::ModelGenerated,
+            content: format!(
+                "{}{}",
+                header(request),
+                serde_json::to_string(&self.questions).unwrap_or_default()
+            ),
+            metadata: None,
+        });
+    }
+
+    /// The first question the user has not answered yet
+    pub fn next_unanswered<'a>(&'a self, memory: &ContextMemory) -> Option<&'a Question> {
+        self.questions
+            .iter()
+            .find(|q| memory.answer_to(&q.question).is_none())
+    }
+
+    /// The questions and answers, for the planning prompt
+    pub fn answers(&self, memory: &ContextMemory) -> Option<String> {
+        let answered: Vec<String> = self
+            .questions
+            .iter()
+            .filter_map(|q| {
+                memory
+                    .answer_to(&q.question)
+                    .map(|answer| format!("Q: {}\nA: {}", q.question, answer))
+            })
+            .collect();
+        (!answered.is_empty()).then(|| answered.join("\n"))
+    }
+}
+
+/// Ask the model which questions, if any, `request` needs answered before it can be planned
+pub async fn clarify(
+    ai_client: &dyn AiClient,
+    request: &str,
+    pinned_context: &str,
+) -> AgentResult<Clarification> {
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: CLARIFY_PROMPT.to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::System,
+            content: format!("PINNED CONTEXT:\n\n{}", pinned_context),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: request.to_string(),
+            name: None,
+        },
+    ];
+
+    let response = ai_client
+        .chat_completion_with_functions(messages, clarify_function(), false)
+        .await?;
+    let Some(call) = response.tool_calls.into_iter().flatten().next() else {
+        return Ok(Clarification::default());
+    };
+
+    let arguments = match call.function.arguments {
+        serde_json::Value::String(raw) => serde_json::from_str(&raw)?,
+        arguments => arguments,
+    };
+    let mut questions: Vec<Question> =
+        serde_json::from_value(arguments["questions"].clone()).unwrap_or_default();
+    questions.retain(|q| !q.question.trim().is_empty());
+    questions.truncate(MAX_QUESTIONS);
+
+    Ok(Clarification { questions })
+}
+
+fn header(request: &str) -> String {
+    format!("Clarifying questions for: {}\n", request)
+}
+
+fn clarify_function() -> serde_json::Value {
+    json!([
+        {
+            "name": "Clarify",
+            "description": "List the questions to ask before planning, or none if the request is clear",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "questions": {
+                        "type": "array",
+                        "description": "Empty when the request is clear enough to plan",
+                        "maxItems": MAX_QUESTIONS,
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "question": {
+                                    "type": "string"
+                                },
+                                "options": {
+                                    "type": "array",
+                                    "description": "Likely answers the user can pick from",
+                                    "items": { "type": "string" }
+                                }
+                            },
+                            "required": ["question"]
+                        }
+                    }
+                },
+                "required": ["questions"]
+            }
+        }
+    ])
+}
@@ -0,0 +1,201 @@
+use crate::error::{AgentError, AgentResult};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What the binary was asked to do on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    /// No subcommand: start the interactive prompt
+    Interactive,
+
+    /// `run-workflow <name> [--param value ...]`
+    RunWorkflow {
+        name: String,
+        params: HashMap<String, String>,
+    },
+
+    /// `pr-desc [--base <branch>] [--push]`
+    PrDesc { base: Option<String>, push: bool },
+
+    /// `ask "<question>"`: one read-only turn, printing the answer
+    Ask { question: String },
+
+    /// `update [--check]`: install the latest release, or only report whether there is one.
+    /// Binaries are checked against the release's own checksum, not a signature.
+    Update { check: bool },
+
+    /// `resume [<bundle>]`: start the interactive prompt with the task a crash interrupted,
+    /// from the given crash bundle or the latest one
+    Resume { bundle: Option<PathBuf> },
+
+    /// `eval <suite> [--case <name>] [--compare <results.json>]`: score the agent on a suite
+    /// of canned prompts, optionally against an earlier run
+    Eval {
+        suite: String,
+        case: Option<String>,
+        compare: Option<PathBuf>,
+    },
+}
+
+/// Parsed command line: a subcommand plus options that apply to every subcommand
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliArgs {
+    pub command: CliCommand,
+    pub force: bool,          // start even if another session holds the workspace
+    pub mode: Option<String>, // approval mode overriding the configured one
+    pub remote_approve: bool, // answer permission prompts from another device
+    pub profile_run: bool,    // print where time went per subtask when the run ends
+    pub plain: bool,          // plain-text output for screen readers and simple terminals
+}
+
+/// Parse command line arguments, excluding the program name
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> AgentResult<CliArgs> {
+    let mut force = false;
+    let mut mode = None;
+    let mut remote_approve = false;
+    let mut profile_run = false;
+    let mut plain = false;
+    let mut rest = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--force" => force = true,
+            "--remote-approve" => remote_approve = true,
+            "--profile-run" => profile_run = true,
+            "--plain" => plain = true,
+            "--mode" => {
+                mode = Some(
+                    args.next()
+                        .ok_or_else(|| AgentError::Cli("Missing value for --mode".to_string()))?,
+                )
+            }
+            _ => match arg.strip_prefix("--mode=") {
+                Some(value) => mode = Some(value.to_string()),
+                None => rest.push(arg),
+            },
+        }
+    }
+
+    Ok(CliArgs {
+        command: parse_command(rest)?,
+        force,
+        mode,
+        remote_approve,
+        profile_run,
+        plain,
+    })
+}
+
+fn parse_command(args: Vec<String>) -> AgentResult<CliCommand> {
+    let mut args = args.into_iter();
+
+    let Some(subcommand) = args.next() else {
+        return Ok(CliCommand::Interactive);
+    };
+
+    match subcommand.as_str() {
+        "run-workflow" => {
+            let name = args.next().ok_or_else(|| {
+                AgentError::Cli("Usage: run-workflow <name> [--param value ...]".to_string())
+            })?;
+
+            Ok(CliCommand::RunWorkflow {
+                name,
+                params: parse_params(args)?,
+            })
+        }
+        "pr-desc" => {
+            let mut base = None;
+            let mut push = false;
+
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--push" => push = true,
+                    "--base" => {
+                        base = Some(args.next().ok_or_else(|| {
+                            AgentError::Cli("Missing value for --base".to_string())
+                        })?)
+                    }
+                    _ => match arg.strip_prefix("--base=") {
+                        Some(value) => base = Some(value.to_string()),
+                        None => {
+                            return Err(AgentError::Cli(format!("Unexpected argument: {}", arg)))
+                        }
+                    },
+                }
+            }
+
+            Ok(CliCommand::PrDesc { base, push })
+        }
+        "ask" => {
+            // Unquoted questions arrive as separate words
+            let question = args.collect::<Vec<_>>().join(" ");
+            if question.trim().is_empty() {
+                return Err(AgentError::Cli("Usage: ask \"<question>\"".to_string()));
+            }
+            Ok(CliCommand::Ask { question })
+        }
+        "update" => {
+            let mut check = false;
+            for arg in args {
+                match arg.as_str() {
+                    "--check" => check = true,
+                    _ => return Err(AgentError::Cli(format!("Unexpected argument: {}", arg))),
+                }
+            }
+            Ok(CliCommand::Update { check })
+        }
+        "resume" => {
+            let bundle = args.next().map(PathBuf::from);
+            if let Some(arg) = args.next() {
+                return Err(AgentError::Cli(format!("Unexpected argument: {}", arg)));
+            }
+            Ok(CliCommand::Resume { bundle })
+        }
+        "eval" => {
+            let suite = args.next().ok_or_else(|| {
+                AgentError::Cli(
+                    "Usage: eval <suite> [--case <name>] [--compare <results.json>]".to_string(),
+                )
+            })?;
+            let mut params = parse_params(args)?;
+            let case = params.remove("case");
+            let compare = params.remove("compare").map(PathBuf::from);
+            if let Some(key) = params.keys().next() {
+                return Err(AgentError::Cli(format!("Unexpected argument: --{}", key)));
+            }
+            Ok(CliCommand::Eval {
+                suite,
+                case,
+                compare,
+            })
+        }
+        other => Err(AgentError::Cli(format!("Unknown subcommand: {}", other))),
+    }
+}
+
+/// Parse `--key value` and `--key=value` pairs
+fn parse_params<I: Iterator<Item = String>>(mut args: I) -> AgentResult<HashMap<String, String>> {
+    let mut params = HashMap::new();
+
+    while let Some(arg) = args.next() {
+        let Some(key) = arg.strip_prefix("--") else {
+            return Err(AgentError::Cli(format!("Unexpected argument: {}", arg)));
+        };
+
+        match key.split_once('=') {
+            Some((key, value)) => {
+                params.insert(key.to_string(), value.to_string());
+            }
+            None => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| AgentError::Cli(format!("Missing value for --{}", key)))?;
+                params.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    Ok(params)
+}
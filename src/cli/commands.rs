@@ -1,244 +1,831 @@
-use crate::agent_engine::AgentEngine;
-use cliclack::{self, spinner, confirm, log};
-use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use crate::agent_engine::{AgentEngine, SharedState};
+use crate::answers;
+use crate::approval::ApprovalMode;
+use crate::changelog;
+use crate::diagnostics::{self, Severity};
+use crate::error::{AgentError, AgentResult};
+use crate::experiments;
+use crate::git;
+use crate::keys;
+use crate::memory::{ContextMemory, MemoryFragment, Provenance};
+use crate::model_switch::{self, CURRENT_PLAN_SOURCE};
+use crate::mutants;
+use crate::output;
+use crate::platform;
+use crate::scrollback::{self, Multiplexer, WATCHED_PANE_SOURCE};
+use crate::subtasks::SubTaskType;
+use crate::theme::{self, ColorTheme};
+use crate::workspaces::{self, WORKSPACES_SOURCE};
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
+use tokio::sync::Mutex;
 
-use crate::ai::{create_ai_client, Message, MessageRole};
-use crate::codebase::CodebaseScanner;
-use crate::config::Settings;
-use crate::error::{AgentError, AgentResult};
-use crate::memory::{MemoryManager, ShortTermMemory, VectorStore};
-use crate::task::{
-    SubTask, SubTaskType,    
-    // Legacy imports
-      Task,  TaskExecutor, TaskStatus
-};
-
-/// Execute a CLI command
-/*
-pub async fn execute_command(command: &str, args: &str, settings: Arc<Settings>) -> AgentResult<()> {
+/// Split a `/command args...` line into the command name and its arguments
+pub fn parse_slash_command(input: &str) -> Option<(&str, &str)> {
+    let rest = input.trim().strip_prefix('/')?;
+    let (command, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+    if command.is_empty() {
+        return None;
+    }
+
+    Some((command, args.trim()))
+}
+
+/// Execute a slash command entered at the task prompt.
+/// Commands may queue subtasks on the engine; they run once the command returns.
+pub async fn execute_command(
+    command: &str,
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
     match command {
-        "task" => execute_task(args, settings).await,
-        "scan" => scan_codebase(args, settings).await,
-        "config" => show_config(settings).await,
-        _ => Err(AgentError::Cli(format!("Unknown command: {}", command))),
-    }
-}*/
-
-/// Add a subtask to the queue
-/*pub fn add_queued_subtask(subtask: SubTask) -> AgentResult<()> {
-    let queue_manager = SubTaskQueueManager::global();
-    queue_manager.add_queued_subtask(subtask);
-    Ok(())
-}*/
-
-/// Execute a complex task by breaking it down and handling subtasks
-pub async fn ask_confirmation_for_subtask(
-
-    mut agent_engine : &mut AgentEngine , 
-
-    subtask: SubTaskType,
-
-     settings: Arc<Settings>
-
-     ) -> AgentResult<()> {
-    // Initialize components
-   
-   
-    
-    
-    // Set up the user confirmation callback
-    agent_engine.ask_user_confirmation(Box::new(|subtask: &SubTask| {
-        // Get confirmation from the user
-        println!("\n");
-        
-        log::info(&format!("{} AI wants to execute subtask:", 
-            subtask.subtask_type.icon()
-        )).expect("Failed to log");
-        
-        log::info(&format!("  {}", subtask.subtask_type.description())).expect("Failed to log");
-        
-        let result = confirm("Allow this operation?")
-            .initial_value(true)
-            .interact()
-            .unwrap_or(false);
-            
-        if result {
-            log::info("✓ Operation approved").expect("Failed to log");
+        "remember" => remember(args, shared_state, context_memory).await,
+        "forget" => forget(args, shared_state, context_memory).await,
+        "prefer" => prefer(args, shared_state, context_memory).await,
+        "unprefer" => unprefer(args, shared_state, context_memory).await,
+        "changelog" => changelog(args, shared_state, agent_engine).await,
+        "history" => history(args, shared_state).await,
+        "mode" => mode(args, agent_engine).await,
+        "theme" => theme(args),
+        "model" => model(args, shared_state, context_memory, agent_engine).await,
+        "memory" => memory(args, context_memory).await,
+        "last" => last(shared_state),
+        "report" => report(args, shared_state, agent_engine).await,
+        "workspace" => workspace(args, shared_state, context_memory).await,
+        "fix-errors" => fix_errors(args, shared_state, context_memory, agent_engine).await,
+        "mutants" => mutants(args, shared_state, context_memory, agent_engine).await,
+        "capture" => capture(args, context_memory).await,
+        "watch" => watch(args, context_memory).await,
+        "experiments" => experiments(),
+        "cost" => cost(&shared_state),
+        _ => Err(AgentError::Cli(format!("Unknown command: /{}", command))),
+    }
+}
+
+/// Record a project fact, or list known facts when called without arguments
+async fn remember(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+) -> AgentResult<()> {
+    if args.is_empty() {
+        let facts = shared_state.project_knowledge().facts().await?;
+
+        if facts.is_empty() {
+            cliclack::log::info("No project facts remembered yet. Use /remember <fact>.")?;
         } else {
-            log::info("⨯ Operation declined").expect("Failed to log");
+            let listing = facts
+                .iter()
+                .map(|f| format!("[{}] {}", f.short_id(), f.fact))
+                .collect::<Vec<_>>()
+                .join("\n");
+            cliclack::log::info(format!("📌 Project knowledge:\n{}", listing))?;
         }
-        result
-    }))  ;
-    
-    // Process user input to generate subtasks
-    log::info("🔄 Analyzing task...").expect("Failed to log");
-    let spin = spinner();
-    spin.start("Processing task...");
-    
-    // Process the user input and generate subtasks
-    agent_engine.process_user_input(task_description).await?;
-    
-    spin.stop("Task analyzed ✓");
-    
-  /*
-
-    let queue_size = queue_manager.queue_length();
-    
-    if queue_size > 0 {
-        log::info(&format!("🔍 Generated {} subtasks", queue_size)).expect("Failed to log");
-        
-        // List the subtasks in the queue
-        let subtasks = queue_manager.list_subtasks();
-        println!();
-        log::info("📋 Subtasks:").expect("Failed to log");
-        
-        for (i, subtask) in subtasks.iter().enumerate() {
-            log::info(&format!(" {}. {} {}", 
-                style(i + 1).bold(), 
-                subtask.subtask_type.icon(),
-                subtask.subtask_type.description()
-            )).expect("Failed to log");
+
+        return Ok(());
+    }
+
+    shared_state.remember_fact(args, &context_memory).await?;
+    cliclack::log::success(format!("📌 Remembered: {}", args))?;
+
+    Ok(())
+}
+
+/// Remove the fact with the given id, or after confirmation those whose text matches
+async fn forget(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+) -> AgentResult<()> {
+    if args.is_empty() {
+        return Err(AgentError::Cli(
+            "Usage: /forget <fact id or text>".to_string(),
+        ));
+    }
+
+    let knowledge = shared_state.project_knowledge();
+    let mut removed = knowledge.forget(args).await?;
+
+    if removed.is_empty() {
+        let matches = knowledge.matching(args).await?;
+        if matches.is_empty() {
+            cliclack::log::warning(format!("No remembered facts match '{}'", args))?;
+            return Ok(());
+        }
+
+        let listing = matches
+            .iter()
+            .map(|f| format!("[{}] {}", f.short_id(), f.fact))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let question = format!(
+            "Forget {} fact(s) mentioning '{}'?\n{}",
+            matches.len(),
+            args,
+            listing
+        );
+        if !keys::approve(&question, None, false) {
+            return Ok(());
         }
-        
-        // Ask for confirmation before executing tasks
-        println!();
-        let confirmed = confirm("Do you want to execute these subtasks?")
-            .initial_value(true)
-            .interact()
-            .unwrap_or(false);
-            
-        if confirmed {
-            // Process all subtasks in the queue
-            log::info("▶️ Executing subtasks...").expect("Failed to log");
-            subtask_executor.process_all_subtasks().await?;
-            
-            // Display the results
-            println!();
-            log::info("🎉 Task execution complete!").expect("Failed to log");
+        knowledge.remove(&matches).await?;
+        removed = matches;
+    }
+
+    knowledge.pin_into(&context_memory).await?;
+    for fact in removed {
+        cliclack::log::success(format!("🗑️ Forgot [{}] {}", fact.short_id(), fact.fact))?;
+    }
+
+    Ok(())
+}
+
+/// Record an explicit user preference, or list preferences when called without arguments
+async fn prefer(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+) -> AgentResult<()> {
+    let preferences = shared_state.user_preferences();
+
+    if args.is_empty() {
+        let all = preferences.all().await?;
+
+        if all.is_empty() {
+            cliclack::log::info("No preferences learned yet. Use /prefer <preference>.")?;
         } else {
-            // User cancelled task execution
-            log::info("⨯ Task execution cancelled by user").expect("Failed to log");
+            let listing = all
+                .iter()
+                .map(|p| {
+                    let status = if p.is_active() { "active" } else { "seen" };
+                    format!("[{} x{}] {}", status, p.occurrences, p.text)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            cliclack::log::info(format!("🧭 User preferences:\n{}", listing))?;
         }
+
+        return Ok(());
+    }
+
+    preferences.prefer(args).await?;
+    preferences.pin_into(&context_memory).await?;
+    cliclack::log::success(format!("🧭 Preference saved: {}", args))?;
+
+    Ok(())
+}
+
+/// Remove preferences matching a text fragment
+async fn unprefer(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+) -> AgentResult<()> {
+    if args.is_empty() {
+        return Err(AgentError::Cli("Usage: /unprefer <text>".to_string()));
+    }
+
+    let preferences = shared_state.user_preferences();
+    let removed = preferences.forget(args).await?;
+    preferences.pin_into(&context_memory).await?;
+
+    if removed.is_empty() {
+        cliclack::log::warning(format!("No preferences match '{}'", args))?;
     } else {
-        log::info("⚠️ No subtasks were generated").expect("Failed to log");
-    }*/
-    
+        for preference in removed {
+            cliclack::log::success(format!("🗑️ Removed preference: {}", preference.text))?;
+        }
+    }
+
     Ok(())
 }
 
+/// Draft a changelog section for commits since the last tag (or the given ref)
+/// and queue the CHANGELOG.md edit for approval
+async fn changelog(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
+    let root = &shared_state.workspace_root;
 
-/*
-/// Scan the codebase to build the vector index
-async fn scan_codebase(path_str: &str, settings: Arc<Settings>) -> AgentResult<()> {
-    let scan_path = if path_str.is_empty() {
-        settings.default_scan_path.clone()
+    let since = match args {
+        "" => git::last_tag(root),
+        since => Some(since.to_string()),
+    };
+
+    let commits = git::commits_since(root, since.as_deref())?;
+    if commits.is_empty() {
+        cliclack::log::warning(format!(
+            "No commits since {}",
+            since.as_deref().unwrap_or("the start of history")
+        ))?;
+        return Ok(());
+    }
+
+    cliclack::log::info(format!(
+        "📝 Summarizing {} commits since {}",
+        commits.len(),
+        since.as_deref().unwrap_or("the start of history")
+    ))?;
+
+    let section = changelog::generate_section(
+        shared_state.ai_client.as_ref(),
+        &shared_state.embedding_index(),
+        since.as_deref(),
+        &commits,
+    )
+    .await?;
+    cliclack::log::info(format!("Proposed changelog section:\n{}", section))?;
+
+    let edit = changelog::changelog_edit(root, &section)?;
+    agent_engine
+        .lock()
+        .await
+        .push_subtask(SubTaskType::FileEditTool(edit));
+
+    Ok(())
+}
+
+/// Show recent journaled operations, or export the full journal as JSON lines
+async fn history(args: &str, shared_state: Arc<SharedState>) -> AgentResult<()> {
+    let journal = shared_state.journal();
+
+    if let Some(path) = args.strip_prefix("export") {
+        let path = match path.trim() {
+            "" => "indiefuture-audit.jsonl",
+            path => path,
+        };
+
+        let entries = journal.entries().await?;
+        let mut lines = String::new();
+        for entry in &entries {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        std::fs::write(path, lines)?;
+
+        cliclack::log::success(format!("Exported {} entries to {}", entries.len(), path))?;
+        return Ok(());
+    }
+
+    let count = match args {
+        "" => 20,
+        count => count.parse().map_err(|_| {
+            AgentError::Cli("Usage: /history [count] | /history export [path]".to_string())
+        })?,
+    };
+
+    let entries = journal.recent(count).await?;
+    if entries.is_empty() {
+        cliclack::log::info("No operations recorded in this workspace yet.")?;
     } else {
-        PathBuf::from(path_str)
+        let listing = entries
+            .iter()
+            .map(|entry| entry.summary())
+            .collect::<Vec<_>>()
+            .join("\n");
+        cliclack::log::info(format!("📜 Operation history:\n{}", listing))?;
+    }
+
+    Ok(())
+}
+
+/// Reopen the latest saved explanation in a pager
+fn last(shared_state: Arc<SharedState>) -> AgentResult<()> {
+    if answers::dir(&shared_state.workspace_root).is_none() {
+        cliclack::log::info("Saving explanations is turned off (ANSWERS_DIR=off).")?;
+        return Ok(());
+    }
+
+    match answers::latest(&shared_state.workspace_root)? {
+        Some(path) => answers::open_in_pager(&path),
+        None => {
+            cliclack::log::info("No explanations saved in this workspace yet.")?;
+            Ok(())
+        }
+    }
+}
+
+/// Write the latest turn's plan, tool outputs, diffs and explanation to a markdown file
+async fn report(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
+    let report = agent_engine.lock().await.turn_report.clone();
+    if report.is_empty() {
+        cliclack::log::info("Nothing to report yet; run a task first.")?;
+        return Ok(());
+    }
+
+    let path = match args {
+        "" => "indiefuture-report.md",
+        path => path,
     };
-    
-    if !scan_path.exists() {
-        return Err(AgentError::Cli(format!(
-            "Path does not exist: {:?}",
-            scan_path
-        )));
+    std::fs::write(path, report.to_markdown(&shared_state.workspace_root))?;
+
+    cliclack::log::success(format!(
+        "📄 Wrote a report of {} step(s) to {}",
+        report.steps.len(),
+        path
+    ))?;
+    Ok(())
+}
+
+/// Show or change the session's approval mode
+async fn mode(args: &str, agent_engine: Arc<Mutex<AgentEngine>>) -> AgentResult<()> {
+    let mut engine = agent_engine.lock().await;
+
+    if args.is_empty() {
+        let listing = ApprovalMode::ALL
+            .iter()
+            .map(|mode| {
+                let marker = if *mode == engine.approval_mode {
+                    "●"
+                } else {
+                    "○"
+                };
+                format!("{} {} - {}", marker, mode, mode.description())
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        cliclack::log::info(format!("Approval mode:\n{}", listing))?;
+        return Ok(());
+    }
+
+    engine.approval_mode = args.parse()?;
+    cliclack::log::success(format!(
+        "Approval mode set to {} ({})",
+        engine.approval_mode,
+        engine.approval_mode.description()
+    ))?;
+
+    Ok(())
+}
+
+/// Show or change the color theme for the rest of the session
+fn theme(args: &str) -> AgentResult<()> {
+    if args.is_empty() {
+        let current = theme::current();
+        let listing = ColorTheme::ALL
+            .iter()
+            .map(|theme| {
+                let marker = if *theme == current { "●" } else { "○" };
+                format!("{} {} - {}", marker, theme, theme.description())
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        cliclack::log::info(format!("Color theme:\n{}", listing))?;
+        return Ok(());
+    }
+
+    let selected: ColorTheme = args.parse()?;
+    theme::configure(selected);
+    cliclack::log::success(format!(
+        "Color theme set to {} ({})",
+        selected,
+        selected.description()
+    ))?;
+    Ok(())
+}
+
+/// Show the model in use, or switch models. Context memory is refreshed and fitted into the
+/// new model's window, so the session carries on where it was.
+async fn model(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
+    let Some(switch) = &shared_state.model_switch else {
+        return Err(AgentError::Cli(
+            "This session's model can't be switched".to_string(),
+        ));
+    };
+    let previous = switch.current().model_name();
+
+    if args.is_empty() {
+        let routes: String = shared_state
+            .model_router
+            .routes()
+            .iter()
+            .map(|(tool, model)| format!("\n  {} runs on {}", tool, model))
+            .collect();
+        cliclack::log::info(format!(
+            "Model: {} ({} token context window){}",
+            previous,
+            model_switch::context_window(&previous),
+            routes
+        ))?;
+        return Ok(());
     }
-    
-    // Initialize vector store
-    let vector_store = VectorStore::new(settings.vector_store_path.to_str().unwrap_or("."), &settings.collection_name).await;
-    
-    // Initialize short-term memory
-    let short_term_memory = ShortTermMemory::new(None);
-    
-    // Create memory manager
-    let memory_manager = Arc::new(MemoryManager::new(short_term_memory, vector_store));
-    
-    // Create codebase scanner
-    let codebase_scanner = Arc::new(CodebaseScanner::new(
-        &scan_path,
-        settings.ignore_patterns.clone(),
-        settings.supported_extensions.clone(),
+
+    switch.switch(args)?;
+    cliclack::log::success(format!(
+        "Switched from {} to {} ({} token context window)",
+        previous,
+        args,
+        model_switch::context_window(args)
+    ))?;
+
+    // Essentials are re-pinned first so they are counted against the new window
+    shared_state.load_pinned_context(&context_memory).await?;
+    let steps = agent_engine.lock().await.queued_steps();
+    {
+        let mut memory = context_memory.lock().await;
+        match model_switch::plan_fragment(&steps) {
+            Some(fragment) => memory.pin_frag(fragment),
+            None => memory.unpin(CURRENT_PLAN_SOURCE),
+        }
+    }
+
+    let budget = model_switch::memory_budget(args);
+    let used = {
+        let memory = context_memory.lock().await;
+        memory.content_len() + memory.pinned_len()
+    };
+    if used <= budget {
+        return Ok(());
+    }
+
+    let spin = output::spinner();
+    spin.start(format!("Fitting context memory into {}'s window...", args));
+    let handoff =
+        match model_switch::fit_memory(shared_state.ai_client.as_ref(), &context_memory, budget)
+            .await
+        {
+            Ok(handoff) => handoff,
+            Err(e) => {
+                spin.error(format!("Failed to compact context memory: {}", e));
+                return Err(e);
+            }
+        };
+    spin.stop(format!(
+        "Compacted {} context fragments into a summary",
+        handoff.compacted
     ));
-    
-    // Show status while scanning
-    println!("Scanning codebase at {:?}...", scan_path);
-    
-    // Scan the directory
-    let files = codebase_scanner.scan_directory(&scan_path).await?;
-    
-    println!("Scanned {} files", files.len());
-    
-    // Create a progress bar for storing embeddings
-    let total_files = files.len() as u64;
-    let pb = ProgressBar::new(total_files);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .expect("Failed to set progress bar style")
-            .progress_chars("#>-"),
-    );
-    
-    // Store files in memory
-    for file in files {
-        use crate::memory::MemoryMetadata;
-        
-        // Only process if the file has content and is not too large
-        if !file.content.is_empty() && file.content.len() < 100000 {
-            let metadata = MemoryMetadata {
-                source: "codebase".to_string(),
-                file_path: Some(file.path.clone()),
-                language: file.language.clone(),
-                tags: vec!["code".to_string()],
-                task_id: None,
+
+    if !handoff.dropped.is_empty() {
+        let listing = handoff
+            .dropped
+            .iter()
+            .map(
+                |fragment| match fragment.metadata.as_ref().and_then(|m| m.path.as_deref()) {
+                    Some(path) => format!("- {} ({})", fragment.source, path),
+                    None => format!("- {}", fragment.source),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+        cliclack::log::warning(format!(
+            "Dropped {} context fragments that did not fit:\n{}",
+            handoff.dropped.len(),
+            listing
+        ))?;
+    }
+    if handoff.pinned_over_budget {
+        cliclack::log::warning(
+            "Pinned context alone is larger than this model's window; consider /forget or \
+             /unprefer to trim it",
+        )?;
+    }
+
+    Ok(())
+}
+
+/// List the fragments in context memory with their provenance and trust, optionally only
+/// those of one provenance
+async fn memory(args: &str, context_memory: Arc<Mutex<ContextMemory>>) -> AgentResult<()> {
+    let filter: Option<Provenance> = match args {
+        "" => None,
+        provenance => Some(provenance.parse()?),
+    };
+
+    let memory = context_memory.lock().await;
+    let line = |marker: &str, fragment: &MemoryFragment| {
+        let path = fragment
+            .metadata
+            .as_ref()
+            .and_then(|m| m.path.as_deref())
+            .map(|path| format!(" ({})", path))
+            .unwrap_or_default();
+        format!(
+            "{} {}{} - {} - {} chars",
+            marker,
+            fragment.source,
+            path,
+            fragment.provenance_label(),
+            fragment.content.len()
+        )
+    };
+    let listing: Vec<String> = memory
+        .get_pinned_fragments()
+        .iter()
+        .map(|fragment| ("📌", fragment))
+        .chain(
+            memory
+                .get_fragments()
+                .iter()
+                .map(|fragment| ("•", fragment)),
+        )
+        .filter(|(_, fragment)| filter.is_none_or(|provenance| fragment.provenance == provenance))
+        .map(|(marker, fragment)| line(marker, fragment))
+        .collect();
+
+    if listing.is_empty() {
+        cliclack::log::info("No matching context fragments")?;
+    } else {
+        cliclack::log::info(format!(
+            "Context memory ({} fragments, 📌 pinned):\n{}",
+            listing.len(),
+            listing.join("\n")
+        ))?;
+    }
+    Ok(())
+}
+
+/// List the session's workspaces, or add, remove or allow edits in another repository
+async fn workspace(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+) -> AgentResult<()> {
+    const USAGE: &str =
+        "Usage: /workspace [add <path> [--edit] | remove <name> | edit <name> on|off]";
+    let words: Vec<&str> = args.split_whitespace().collect();
+
+    match words.as_slice() {
+        [] => {
+            let mut listing = vec![format!(
+                "● {} (current, editable)",
+                platform::display_path(&shared_state.workspace_root)
+            )];
+            listing.extend(workspaces::list().iter().map(|w| {
+                let access = if w.editable { "editable" } else { "read-only" };
+                format!(
+                    "○ {}: {} ({})",
+                    w.name,
+                    platform::display_path(&w.root),
+                    access
+                )
+            }));
+            cliclack::log::info(format!("Workspaces:\n{}", listing.join("\n")))?;
+            return Ok(());
+        }
+        ["add", path] | ["add", path, "--edit"] => {
+            let editable = words.len() == 3;
+            let added = workspaces::add(&shared_state.workspace_root, Path::new(path), editable)?;
+            cliclack::log::success(format!(
+                "Added {} as {} ({}); refer to its files as {}:<path>",
+                platform::display_path(&added.root),
+                added.name,
+                if editable { "editable" } else { "read-only" },
+                added.name
+            ))?;
+        }
+        ["remove", name] => {
+            let removed = workspaces::remove(name)
+                .ok_or_else(|| AgentError::Cli(format!("No workspace named {}", name)))?;
+            cliclack::log::success(format!("Removed workspace {}", removed.name))?;
+        }
+        ["edit", name, setting @ ("on" | "off")] => {
+            let changed = workspaces::set_editable(name, *setting == "on")
+                .ok_or_else(|| AgentError::Cli(format!("No workspace named {}", name)))?;
+            let access = if changed.editable {
+                "allowed"
+            } else {
+                "not allowed"
             };
-            
-            if memory_manager.vector_store.is_available() {
-                let _ = memory_manager.add_memory(&file.content, metadata).await;
-            }
-            
-            pb.inc(1);
-            pb.set_message(file.path.clone());
+            cliclack::log::success(format!("Edits in {} are {}", changed.name, access))?;
         }
+        _ => return Err(AgentError::Cli(USAGE.to_string())),
+    }
+
+    let mut memory = context_memory.lock().await;
+    match workspaces::fragment() {
+        Some(fragment) => memory.pin_frag(fragment),
+        None => memory.unpin(WORKSPACES_SOURCE),
     }
-    
-    pb.finish_with_message("All files indexed");
-    cliclack::log::info("Codebase scanning complete!").expect("Failed to log");
-    
-    Ok(())
-}
-
-/// Display and edit configuration
-async fn show_config(settings: Arc<Settings>) -> AgentResult<()> {
-    cliclack::log::info("Current Configuration:").expect("Failed to log");
-    println!();
-    cliclack::log::info(&format!("AI Provider: {}", settings.default_ai_provider)).expect("Failed to log");
-    cliclack::log::info(&format!("Default Model: {}", settings.default_model)).expect("Failed to log");
-    cliclack::log::info(&format!("Vector Store Path: {:?}", settings.vector_store_path)).expect("Failed to log");
-    cliclack::log::info(&format!("Sled Path: {:?}", settings.sled_path)).expect("Failed to log");
-    cliclack::log::info(&format!("Default Scan Path: {:?}", settings.default_scan_path)).expect("Failed to log");
-    cliclack::log::info(&format!("Max Concurrent Tasks: {}", settings.max_concurrent_tasks)).expect("Failed to log");
-    cliclack::log::info(&format!(
-        "Default Timeout: {} seconds",
-        settings.default_timeout_seconds
-    )).expect("Failed to log");
-    println!();
-    cliclack::log::info(&format!("OpenAI API Key: {}", 
-        if settings.openai_api_key.is_some() { "[Set]" } else { "[Not Set]" }
-    )).expect("Failed to log");
-    
-    cliclack::log::info(&format!("Claude API Key: {}", 
-        if settings.claude_api_key.is_some() { "[Set]" } else { "[Not Set]" }
-    )).expect("Failed to log");
-    println!();
-    
-    // In a more complete implementation, we would allow editing the configuration here
-    cliclack::log::info("To modify settings, edit the .env file or set environment variables.").expect("Failed to log");
-    
-    Ok(())
-}
-
-*/
\ No newline at end of file
+    Ok(())
+}
+
+/// Build or lint the project and queue a fix for each file with errors, located by the
+/// diagnostics; `/fix-errors warnings` takes on warnings too
+async fn fix_errors(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
+    let worst_fixed = match args {
+        "" => Severity::Error,
+        "warnings" => Severity::Warning,
+        _ => return Err(AgentError::Cli("Usage: /fix-errors [warnings]".to_string())),
+    };
+
+    let spin = output::spinner();
+    spin.start("Collecting diagnostics");
+    let diagnostics: Vec<_> = match diagnostics::collect(&shared_state.workspace_root).await {
+        Ok(diagnostics) => diagnostics
+            .into_iter()
+            .filter(|d| d.severity <= worst_fixed)
+            .collect(),
+        Err(e) => {
+            spin.error("Could not collect diagnostics");
+            return Err(e);
+        }
+    };
+    if diagnostics.is_empty() {
+        spin.stop("✓ Nothing to fix");
+        return Ok(());
+    }
+
+    let files = diagnostics::by_file(&diagnostics);
+    spin.stop(format!(
+        "🩹 {} diagnostics in {} files",
+        diagnostics.len(),
+        files.len()
+    ));
+    context_memory
+        .lock()
+        .await
+        .add_frag(diagnostics::fragment(&diagnostics));
+
+    // The queue is a stack, so the first file is queued last
+    let mut engine = agent_engine.lock().await;
+    for (file, file_diagnostics) in files.iter().rev() {
+        engine.push_subtask(SubTaskType::Task(diagnostics::fix_task(
+            file,
+            file_diagnostics,
+        )));
+    }
+    Ok(())
+}
+
+/// Run cargo-mutants on the given files, or the uncommitted changes, and queue a task
+/// strengthening the tests for the mutants they missed
+async fn mutants(
+    args: &str,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
+    let root = &shared_state.workspace_root;
+    let files: Vec<String> = if args.is_empty() {
+        git::run_git(root, &["diff", "--name-only", "HEAD"])?
+            .lines()
+            .map(str::to_string)
+            .collect()
+    } else {
+        args.split_whitespace().map(str::to_string).collect()
+    };
+    let files = mutants::mutable_files(&files);
+    if files.is_empty() {
+        return Err(AgentError::Cli(
+            "No changed Rust source files; usage: /mutants [file...]".to_string(),
+        ));
+    }
+
+    let spin = output::spinner();
+    spin.start(format!("Mutation testing {}", files.join(", ")));
+    let missed = match mutants::run(root, &files).await {
+        Ok(missed) => missed,
+        Err(e) => {
+            spin.error("Mutation testing could not run");
+            return Err(e);
+        }
+    };
+    if missed.is_empty() {
+        spin.stop("✓ The tests caught every mutant");
+        return Ok(());
+    }
+    spin.stop(format!("🧬 {} mutant(s) survived the tests", missed.len()));
+
+    context_memory
+        .lock()
+        .await
+        .add_frag(mutants::fragment(&missed));
+    agent_engine
+        .lock()
+        .await
+        .push_subtask(SubTaskType::Task(mutants::strengthen_task(&missed)));
+    Ok(())
+}
+
+/// Add the last lines of the user's terminal scrollback to context, so output they just saw
+/// can be asked about without pasting it
+async fn capture(args: &str, context_memory: Arc<Mutex<ContextMemory>>) -> AgentResult<()> {
+    const USAGE: &str = "Usage: /capture [lines] [tmux pane, e.g. {last}]";
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let (lines, pane) = match words.as_slice() {
+        [] => (None, None),
+        [word] => match word.parse() {
+            Ok(count) => (Some(count), None),
+            Err(_) => (None, Some(*word)),
+        },
+        [count, pane] => (
+            Some(
+                count
+                    .parse()
+                    .map_err(|_| AgentError::Cli(USAGE.to_string()))?,
+            ),
+            Some(*pane),
+        ),
+        _ => return Err(AgentError::Cli(USAGE.to_string())),
+    };
+    let lines = lines.unwrap_or(scrollback::DEFAULT_CAPTURE_LINES);
+
+    let multiplexer = Multiplexer::detect().ok_or_else(|| {
+        AgentError::Cli(
+            "Scrollback can only be captured inside tmux or screen; paste the output instead"
+                .to_string(),
+        )
+    })?;
+    let text = scrollback::capture(multiplexer, lines, pane).await?;
+    if text.trim().is_empty() {
+        cliclack::log::warning("The scrollback is empty")?;
+        return Ok(());
+    }
+
+    context_memory
+        .lock()
+        .await
+        .add_frag(scrollback::fragment(&text, multiplexer));
+    cliclack::log::success(format!(
+        "📋 Captured {} lines of {} scrollback",
+        text.lines().count(),
+        multiplexer
+    ))?;
+    Ok(())
+}
+
+/// Let the agent read a tmux pane the user drives, such as a dev server or REPL, or stop it
+async fn watch(args: &str, context_memory: Arc<Mutex<ContextMemory>>) -> AgentResult<()> {
+    match args {
+        "" => match scrollback::watched() {
+            Some(pane) => cliclack::log::info(format!(
+                "👀 Watching tmux pane {} ({})",
+                pane.id, pane.command
+            ))?,
+            None => cliclack::log::info("No pane is watched. Usage: /watch <tmux pane> | off")?,
+        },
+        "off" => match scrollback::unwatch() {
+            Some(pane) => cliclack::log::success(format!("Stopped watching pane {}", pane.id))?,
+            None => cliclack::log::info("No pane is watched")?,
+        },
+        target => {
+            let pane = scrollback::watch(target).await?;
+            cliclack::log::success(format!(
+                "👀 The agent can now read pane {} ({}); it never types into it",
+                pane.id, pane.command
+            ))?;
+        }
+    }
+
+    let mut memory = context_memory.lock().await;
+    match scrollback::watched_fragment() {
+        Some(fragment) => memory.pin_frag(fragment),
+        None => memory.unpin(WATCHED_PANE_SOURCE),
+    }
+    Ok(())
+}
+
+/// Show the prompt variants this session uses and how each variant's turns have gone
+fn experiments() -> AgentResult<()> {
+    let (Some(assignment), Some(path)) = (experiments::assignment(), experiments::results_path())
+    else {
+        cliclack::log::info(
+            "No prompt experiment is running. Add prompt_variants to ~/.indiefuture/config.yaml and set PROMPT_EXPERIMENTS=true.",
+        )?;
+        return Ok(());
+    };
+
+    let using = assignment
+        .iter()
+        .map(|(slot, variant)| format!("{}: {}", slot, variant))
+        .collect::<Vec<_>>()
+        .join(", ");
+    cliclack::log::info(format!("🧪 This session uses {}", using))?;
+
+    let outcomes = experiments::load(&path)?;
+    if outcomes.is_empty() {
+        cliclack::log::info("No turns recorded yet")?;
+    } else {
+        cliclack::log::info(format!(
+            "{} turn(s) recorded in {}\n{}",
+            outcomes.len(),
+            path.display(),
+            experiments::render(&experiments::summarize(&outcomes))
+        ))?;
+    }
+    Ok(())
+}
+
+/// Show the tokens and cost of this session's model requests so far
+fn cost(shared_state: &SharedState) -> AgentResult<()> {
+    cliclack::log::info(format!("💰 {}", shared_state.usage.summary()))?;
+    Ok(())
+}
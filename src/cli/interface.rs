@@ -3,14 +3,23 @@ use crate::memory::ContextMemory;
 use crate::subtasks::SubTaskType;
 
 use crate::agent_engine::AgentEngine;
-use cliclack::{self, input, intro, outro, select};
-use console::style;
-use tokio::sync::Mutex;
-//use crate::cli::commands::execute_command;
+use crate::ai::{reasoning, AiClient};
+use crate::background::{BackgroundJobs, Job};
+use crate::cli::commands::{execute_command, parse_slash_command};
 use crate::config::Settings;
+use crate::embeddings::{self, MigrationPolicy};
 use crate::error::AgentResult;
-use std::io;
+use crate::experiments::{self, TurnOutcome};
+use crate::intent;
+use crate::interrupt;
+use crate::notifications::{self, RunSummary};
+use crate::output;
+use crate::project_context::AutoContext;
+use crate::voice;
+use cliclack::{self, input, intro, outro, select};
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
 
 /// Main CLI entry point
 pub async fn run_cli(
@@ -20,24 +29,83 @@ pub async fn run_cli(
     settings: Arc<Settings>,
 
     agent_engine: Arc<Mutex<AgentEngine>>,
+
+    mut resumed_task: Option<String>, // prefilled as the first task, after a crash
 ) -> AgentResult<()> {
     // Welcome message
     intro("IndieFuture Agent CLI").expect("Failed to show intro");
     cliclack::log::info("Your AI-powered assistant for complex tasks")
         .expect("Failed to show info");
 
+    // Ctrl+C during a run pauses it to explain where things stand
+    interrupt::listen();
+
+    // Load durable project facts and user preferences from previous sessions as pinned context
+    if let Err(e) = shared_state.load_pinned_context(&context_memory).await {
+        log::warn!("Failed to load long-term memory: {}", e);
+    }
+
+    // Embedding, compaction and prefetching run on their own tasks, never blocking prompts
+    let background = BackgroundJobs::new(
+        Arc::clone(&shared_state),
+        Arc::clone(&context_memory),
+        settings.background_jobs,
+        settings.background_jobs_per_minute,
+    );
+    match background.resume().await {
+        Ok(0) => {}
+        Ok(count) => log::info!("Resumed {} background jobs from an earlier session", count),
+        Err(e) => log::warn!("Failed to resume background jobs: {}", e),
+    }
+    agent_engine.lock().await.background = Some(Arc::clone(&background));
+
+    // Vectors of different embedding models cannot be compared, so an older index is rebuilt
+    // or queried with its own model
+    match settings.embedding_migration.parse::<MigrationPolicy>() {
+        Ok(policy) => {
+            if let Err(e) = migrate_embeddings(&shared_state, &settings, &background, policy).await
+            {
+                log::warn!("Failed to check the embedding index: {}", e);
+            }
+        }
+        Err(e) => log::warn!("{}", e),
+    }
+
+    // Give the first question a project overview, gathered while the user is typing
+    match settings.auto_context.parse::<AutoContext>() {
+        Ok(AutoContext::Off) => {}
+        Ok(mode) => {
+            let is_new = shared_state
+                .project_knowledge()
+                .facts()
+                .await
+                .map(|facts| facts.is_empty())
+                .unwrap_or(true);
+
+            if mode == AutoContext::Always || is_new {
+                background.enqueue(Job::PrefetchRepoMap).await;
+            }
+        }
+        Err(e) => log::warn!("{}", e),
+    }
+
     // Main loop
     loop {
-        let select_result = select("What would you like to do?")
-            .item(
-                "task",
-                "Execute a task",
-                "Break down and execute a complex task",
-            )
-            // .item("scan", "Scan codebase", "Scan and index your codebase for future tasks")
-            .item("config", "Configure", "View or modify settings")
-            .item("quit", "Quit", "Exit the application")
-            .interact();
+        let mut prompt = None;
+
+        let select_result = if resumed_task.is_some() {
+            Ok("task")
+        } else {
+            select("What would you like to do?")
+                .item(
+                    "task",
+                    "Execute a task",
+                    "Break down and execute a complex task",
+                )
+                .item("config", "Configure", "View or modify settings")
+                .item("quit", "Quit", "Exit the application")
+                .interact()
+        };
 
         let selected = match select_result {
             Ok(val) => val.to_owned(),
@@ -46,20 +114,75 @@ pub async fn run_cli(
 
         match selected.as_str() {
             "task" => {
-                let input_result = input("What task would you like to execute?")
-                    .placeholder("Describe your task in detail...")
-                    .interact();
-
-                let task_description = match input_result {
-                    Ok(val) => val,
-                    Err(_) => String::new(),
-                };
-
-                if !task_description.is_empty() {
-                    agent_engine
-                        .lock()
-                        .await
-                        .push_subtask(SubTaskType::Task(task_description.clone()));
+                let mut task_input = input("What task would you like to execute?")
+                    .placeholder("Describe your task in detail, or enter a /command...");
+                if let Some(task) = resumed_task.take() {
+                    task_input = task_input.default_input(&task);
+                }
+                let input_result = task_input.interact();
+
+                let mut task_description: String = input_result.unwrap_or_default();
+
+                // Spoken prompts land in the prompt box to be corrected before they run
+                if task_description.trim() == "/voice" {
+                    task_description = match voice::listen(&settings).await {
+                        Ok(transcript) => input("What task would you like to execute?")
+                            .default_input(&transcript)
+                            .interact()
+                            .unwrap_or_default(),
+                        Err(e) => {
+                            cliclack::log::error(e.to_string()).expect("Failed to log");
+                            String::new()
+                        }
+                    };
+                }
+
+                if let Some((command, args)) = parse_slash_command(&task_description) {
+                    if let Err(e) = execute_command(
+                        command,
+                        args,
+                        Arc::clone(&shared_state),
+                        Arc::clone(&context_memory),
+                        Arc::clone(&agent_engine),
+                    )
+                    .await
+                    {
+                        cliclack::log::error(e.to_string()).expect("Failed to log");
+                    }
+                } else if !task_description.is_empty() {
+                    agent_engine.lock().await.begin_turn(&task_description);
+
+                    // General-knowledge questions are answered directly, skipping tool planning
+                    let spin = output::spinner();
+                    spin.start("Thinking...");
+                    let direct_answer = reasoning::spin_while(
+                        &spin,
+                        "Thinking...",
+                        intent::answer_without_tools(
+                            shared_state.ai_client.as_ref(),
+                            &task_description,
+                            &context_memory,
+                        ),
+                    )
+                    .await;
+                    spin.stop("");
+
+                    match direct_answer {
+                        Ok(Some(answer)) => {
+                            cliclack::log::info(&answer).expect("Failed to log");
+                            agent_engine.lock().await.turn_report.explanation = Some(answer);
+                        }
+                        result => {
+                            if let Err(e) = result {
+                                log::warn!("Intent check failed, planning instead: {}", e);
+                            }
+                            agent_engine
+                                .lock()
+                                .await
+                                .push_subtask(SubTaskType::Task(task_description.clone()));
+                        }
+                    }
+                    prompt = Some(task_description);
                     // execute_command("task", &task_description, settings.clone()).await?;
                 }
             }
@@ -69,14 +192,16 @@ pub async fn run_cli(
             "config" => {
                 // execute_command("config", "", settings.clone()).await?;
             }
-            "quit" | _ => {
+            _ => {
                 outro("Goodbye!").expect("Failed to show outro");
                 break;
             }
         }
 
         // handle ALL subtasks until the entire stack (queue) is empty and then we loop again
-        agent_engine
+        let started = Instant::now();
+        let cost_before = shared_state.usage.cost_usd();
+        let stats = agent_engine
             .lock()
             .await
             .handle_subtasks(
@@ -84,8 +209,133 @@ pub async fn run_cli(
                 Arc::clone(&context_memory),
                 Arc::clone(&settings),
             )
-            .await
+            .await;
+
+        // Explain what an editing turn changed instead of leaving only the raw diffs
+        if prompt.is_some() && settings.change_summaries && !stats.files_changed.is_empty() {
+            let report = agent_engine.lock().await.turn_report.clone();
+            let spin = output::spinner();
+            spin.start("Summarizing changes...");
+            match shared_state
+                .summarize_changes(&report, &context_memory)
+                .await
+            {
+                Ok(summary) => {
+                    spin.stop("📝 Changes summary");
+                    cliclack::log::info(&summary).expect("Failed to log");
+                    agent_engine.lock().await.turn_report.changes_summary = Some(summary);
+                }
+                Err(e) => spin.error(format!("Failed to summarize changes: {}", e)),
+            }
+        }
+
+        // Under a prompt experiment, how the turn went is kept to compare the variants
+        if let (Some(_), Some(variants), Some(path)) = (
+            &prompt,
+            experiments::assignment(),
+            experiments::results_path(),
+        ) {
+            let outcome = TurnOutcome::new(
+                &shared_state.session_id,
+                &shared_state.ai_client.model_name(),
+                variants,
+                &stats,
+                started,
+            );
+            if let Err(e) = experiments::record(&path, &outcome) {
+                log::warn!("Failed to record the experiment outcome: {}", e);
+            }
+        }
+
+        if let Some(prompt) = prompt {
+            notifications::notify_run(
+                &settings,
+                RunSummary::new(
+                    &prompt,
+                    &stats,
+                    started,
+                    // The turn's share of the session cost, including the intent check
+                    shared_state
+                        .usage
+                        .cost_usd()
+                        .zip(cost_before)
+                        .map(|(after, before)| after - before),
+                    &shared_state.session_id,
+                    shared_state.storage.user(),
+                ),
+            )
+            .await;
+        }
     }
 
     Ok(())
 }
+
+/// Deal with indexes of this workspace built with another embedding model than the current
+/// one: re-embed their files in the background, or keep querying them with their own model
+async fn migrate_embeddings(
+    shared_state: &SharedState,
+    settings: &Settings,
+    background: &Arc<BackgroundJobs>,
+    policy: MigrationPolicy,
+) -> AgentResult<()> {
+    let index = shared_state.embedding_index();
+    let others = index.other_indexes().await?;
+    if others.is_empty() {
+        return Ok(());
+    }
+
+    let reembed = match policy {
+        MigrationPolicy::Reembed => true,
+        MigrationPolicy::Dual => false,
+        MigrationPolicy::Ask => {
+            let models = others
+                .iter()
+                .map(|header| {
+                    format!(
+                        "{} ({} dimensions)",
+                        header.embedding_model, header.dimensions
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            select(format!(
+                "This workspace was indexed with {}, but embeddings now come from {}. Set EMBEDDING_MIGRATION to stop being asked.",
+                models,
+                index.embedding_model()
+            ))
+            .item("reembed", "Re-embed", "rebuild the index with the current model in the background")
+            .item("dual", "Query both", "keep the old index and query it with its own model")
+            .interact()
+            .unwrap_or("dual")
+                == "reembed"
+        }
+    };
+
+    if reembed {
+        for header in &others {
+            for path in index.indexed_paths(&header.embedding_model).await? {
+                background.enqueue(Job::EmbedFile(path)).await;
+            }
+            let dropped = index.drop_index(&header.embedding_model).await?;
+            cliclack::log::info(format!(
+                "🔁 Re-embedding {} files indexed with {}",
+                dropped, header.embedding_model
+            ))?;
+        }
+        return Ok(());
+    }
+
+    let mut embedders: Vec<Arc<dyn AiClient>> = Vec::new();
+    for header in &others {
+        match embeddings::embedder_for(&header.embedding_model, settings) {
+            Some(embedder) => embedders.push(Arc::from(embedder)),
+            None => cliclack::log::warning(format!(
+                "The index built with {} cannot be queried: its model is unavailable. Set EMBEDDING_MIGRATION=reembed to rebuild it.",
+                header.embedding_model
+            ))?,
+        }
+    }
+    embeddings::configure_previous_embedders(embedders);
+    Ok(())
+}
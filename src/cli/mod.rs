@@ -1,5 +1,7 @@
-//pub mod commands;
+pub mod args;
+pub mod commands;
 pub mod interface;
 
-//pub use commands::execute_command;
+pub use args::{parse_args, CliArgs, CliCommand};
+pub use commands::execute_command;
 pub use interface::run_cli;
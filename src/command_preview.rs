@@ -0,0 +1,264 @@
+use crate::ai::{AiClient, GenerationProfile, Message, MessageRole};
+use crate::error::AgentResult;
+use crate::tool_env;
+use crate::utils;
+use std::fmt;
+
+/// Longest explanation shown; the model is asked for one short sentence
+const MAX_EXPLANATION_CHARS: usize = 200;
+
+/// How much damage a command could do if it is not what the user expects. Every command
+/// is confirmed under the default approval policy; this says how closely to look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskLevel {
+    Low,    // reads, builds or tests
+    Medium, // changes files, git state or installed packages, or uses the network
+    High,   // deletes or overwrites irrecoverably, rewrites history or escalates privileges
+}
+
+impl RiskLevel {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Low => "🟢",
+            Self::Medium => "🟡",
+            Self::High => "🔴",
+        }
+    }
+}
+
+impl fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A command's risk level and the part of it that decided the level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Risk {
+    pub level: RiskLevel,
+    pub reason: &'static str,
+}
+
+impl Risk {
+    fn new(level: RiskLevel, reason: &'static str) -> Self {
+        Self { level, reason }
+    }
+}
+
+/// Assess `command` by its riskiest part; commands chained with `&&`, `;` or pipes are
+/// as risky as the worst of them
+pub fn assess(command: &str) -> Risk {
+    // Redirections between output streams or into /dev/null write no files
+    let command = [
+        " 2>&1",
+        " >&2",
+        " >/dev/null",
+        " > /dev/null",
+        " 2>/dev/null",
+    ]
+    .iter()
+    .fold(command.to_lowercase(), |command, stream| {
+        command.replace(stream, "")
+    });
+    let segments: Vec<Vec<&str>> = command
+        .split(['\n', ';', '|', '&'])
+        .map(|segment| segment.split_whitespace().collect::<Vec<_>>())
+        .filter(|words| !words.is_empty())
+        .collect();
+
+    let mut risk = segments
+        .iter()
+        .map(|words| assess_segment(words))
+        .max_by_key(|risk| risk.level)
+        .unwrap_or(Risk::new(RiskLevel::Low, "does nothing"));
+
+    // A download piped straight into a shell runs code nobody has read
+    let downloads = segments
+        .iter()
+        .any(|words| matches!(program(words), "curl" | "wget"));
+    let pipes_to_shell = segments
+        .iter()
+        .skip(1)
+        .any(|words| matches!(program(words), "sh" | "bash" | "zsh" | "python" | "python3"));
+    if downloads && pipes_to_shell {
+        risk = Risk::new(RiskLevel::High, "runs a downloaded script");
+    }
+    if risk.level == RiskLevel::Low && command.contains('>') {
+        risk = Risk::new(RiskLevel::Medium, "writes files through redirection");
+    }
+    risk
+}
+
+/// The program a segment runs, past `env VAR=value` style prefixes and paths
+fn program<'a>(words: &[&'a str]) -> &'a str {
+    words
+        .iter()
+        .find(|word| !word.contains('=') && **word != "env" && **word != "time")
+        .map(|word| word.rsplit('/').next().unwrap_or(word))
+        .unwrap_or("")
+}
+
+fn assess_segment(words: &[&str]) -> Risk {
+    use RiskLevel::*;
+
+    let program = program(words);
+    let args: Vec<&str> = words
+        .iter()
+        .skip_while(|word| word.rsplit('/').next() != Some(program))
+        .skip(1)
+        .copied()
+        .collect();
+    let has = |flag: &str| args.contains(&flag);
+    let has_short = |letter: char| {
+        args.iter()
+            .any(|arg| arg.starts_with('-') && !arg.starts_with("--") && arg.contains(letter))
+    };
+    let subcommand = args
+        .iter()
+        .find(|arg| !arg.starts_with('-'))
+        .copied()
+        .unwrap_or("");
+
+    match program {
+        "sudo" | "su" | "doas" => Risk::new(High, "runs with elevated privileges"),
+        "dd" | "mkfs" | "fdisk" | "parted" | "shred" | "shutdown" | "reboot" => {
+            Risk::new(High, "can destroy data or stop the machine")
+        }
+        "rm" if has_short('r') || has_short('f') || has("--recursive") || has("--force") => {
+            Risk::new(High, "deletes files recursively or without asking")
+        }
+        "rm" | "rmdir" | "mv" | "unlink" => Risk::new(Medium, "moves or deletes files"),
+        "chmod" | "chown" if has_short('r') || has("--recursive") => {
+            Risk::new(High, "changes permissions recursively")
+        }
+        "sed" if has_short('i') || has("--in-place") => Risk::new(Medium, "edits files in place"),
+        "chmod" | "chown" | "cp" | "mkdir" | "touch" | "ln" | "tee" => {
+            Risk::new(Medium, "changes files")
+        }
+        "kill" | "killall" | "pkill" => Risk::new(Medium, "stops running processes"),
+        "find" if has("-delete") || has("-exec") => {
+            Risk::new(High, "deletes or runs commands on every match")
+        }
+        "git" => match subcommand {
+            "push" if has("--force") || has("-f") || has("--force-with-lease") => {
+                Risk::new(High, "overwrites remote history")
+            }
+            "reset" if has("--hard") => Risk::new(High, "discards uncommitted changes"),
+            "clean" if has_short('f') => Risk::new(High, "deletes untracked files"),
+            "checkout" | "restore" if has(".") || has("--") => {
+                Risk::new(High, "discards uncommitted changes")
+            }
+            "branch" if has("-d") || has("--delete") => Risk::new(Medium, "deletes a branch"),
+            "status" | "log" | "diff" | "show" | "branch" | "blame" | "grep" | "ls-files"
+            | "rev-parse" | "describe" | "shortlog" | "remote" | "tag" | "" => {
+                Risk::new(Low, "only reads the repository")
+            }
+            "push" | "pull" | "fetch" | "clone" => {
+                Risk::new(Medium, "talks to a remote repository")
+            }
+            _ => Risk::new(Medium, "changes git state"),
+        },
+        "npm" | "pnpm" | "yarn" | "pip" | "pip3" | "cargo" | "go" | "gem" | "bundle" | "poetry"
+        | "uv" | "brew" | "apt" | "apt-get" | "dnf" | "pacman" => match subcommand {
+            "install" | "i" | "add" | "remove" | "uninstall" | "rm" | "update" | "upgrade"
+            | "get" | "sync" => Risk::new(Medium, "installs or removes packages"),
+            "publish" | "yank" | "login" => {
+                Risk::new(High, "publishes or changes a package registry")
+            }
+            "test" | "build" | "check" | "clippy" | "vet" | "lint" | "list" | "ls" | "show"
+            | "outdated" | "audit" | "doc" | "tree" | "metadata" => {
+                Risk::new(Low, "builds, tests or inspects the project")
+            }
+            _ => Risk::new(Medium, "runs a package manager"),
+        },
+        "curl" | "wget" | "ssh" | "scp" | "rsync" | "docker" | "kubectl" => {
+            Risk::new(Medium, "uses the network or outside services")
+        }
+        "ls" | "cat" | "head" | "tail" | "less" | "grep" | "rg" | "find" | "pwd" | "echo"
+        | "printf" | "wc" | "which" | "whereis" | "tree" | "du" | "df" | "file" | "stat"
+        | "sort" | "uniq" | "cut" | "diff" | "sed" | "awk" | "jq" | "date" | "uname" | "whoami"
+        | "cd" | "true" | "false" | "test" | "[" | "basename" | "dirname" | "realpath" => {
+            Risk::new(Low, "only reads")
+        }
+        "pytest" | "tsc" | "jest" | "vitest" | "rustc" | "eslint" | "prettier" | "mypy"
+        | "ruff" => Risk::new(Low, "builds, tests or inspects the project"),
+        _ => Risk::new(Medium, "runs a program with unknown effects"),
+    }
+}
+
+/// What the user sees before approving a command
+#[derive(Debug, Clone)]
+pub struct CommandPreview {
+    pub risk: Risk,
+    pub explanation: Option<String>, // None when the model could not be asked
+}
+
+impl CommandPreview {
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "{} {} risk: {}",
+            self.risk.level.icon(),
+            self.risk.level,
+            self.risk.reason
+        );
+        if let Some(explanation) = &self.explanation {
+            text.push_str(&format!("\n{}", explanation));
+        }
+        text
+    }
+}
+
+/// Assess `command` and, when `explain` is set, ask `ai_client` what it does. A failed
+/// explanation leaves only the risk.
+pub async fn preview(ai_client: &dyn AiClient, command: &str, explain: bool) -> CommandPreview {
+    let explanation = if explain {
+        match explain_command(ai_client, command).await {
+            Ok(explanation) => Some(explanation),
+            Err(e) => {
+                log::warn!("Failed to explain the command: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    CommandPreview {
+        risk: assess(command),
+        explanation,
+    }
+}
+
+/// One plain sentence on what `command` does, for users who do not read shell
+pub async fn explain_command(ai_client: &dyn AiClient, command: &str) -> AgentResult<String> {
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: "You explain shell commands to people who do not read shell. Answer with \
+                one short plain sentence saying what the command does and what it changes, if \
+                anything. No preamble, no Markdown, do not repeat the command."
+                .to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: tool_env::mask(command),
+            name: None,
+        },
+    ];
+
+    let explanation = ai_client
+        .with_profile(GenerationProfile::Deterministic)
+        .generate_text(messages)
+        .await?;
+    let line = explanation.lines().find(|line| !line.trim().is_empty());
+    Ok(utils::truncate_string(
+        line.unwrap_or_default().trim(),
+        MAX_EXPLANATION_CHARS,
+    ))
+}
@@ -1,3 +1,7 @@
+pub mod project;
 pub mod settings;
+pub mod user;
 
+pub use project::ProjectSettings;
 pub use settings::Settings;
+pub use user::UserSettings;
@@ -0,0 +1,47 @@
+use crate::error::{AgentError, AgentResult};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Per-project settings, kept in the workspace next to the code they apply to
+pub const PROJECT_SETTINGS_FILE: &str = ".indiefuture/project.yaml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectSettings {
+    /// Variables set for shell commands and verification runs, masked in anything recorded
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Names of variables passed through from the agent's own environment, so secrets need
+    /// not be written in a file that is committed; masked like `env`
+    #[serde(default)]
+    pub pass_env: Vec<String>,
+}
+
+impl ProjectSettings {
+    /// Load the workspace's project settings; a missing file means defaults
+    pub fn load(workspace_root: &Path) -> AgentResult<Self> {
+        let path = workspace_root.join(PROJECT_SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        serde_yaml::from_str(&contents).map_err(|e| {
+            AgentError::Configuration(format!("Invalid project settings {:?}: {}", path, e))
+        })
+    }
+
+    /// Variables for commands: `env`, and those of `pass_env` that are set, which win
+    pub fn env_vars(&self) -> BTreeMap<String, String> {
+        let mut vars = self.env.clone();
+        for name in &self.pass_env {
+            match std::env::var(name) {
+                Ok(value) => {
+                    vars.insert(name.clone(), value);
+                }
+                Err(_) => log::warn!("{} is listed in pass_env but not set", name),
+            }
+        }
+        vars
+    }
+}
@@ -1,27 +1,98 @@
+use crate::ai;
 use crate::error::{AgentError, AgentResult};
 use dotenvy::dotenv;
 use serde::{Deserialize, Serialize};
 use std::env;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     // AI API settings
     pub openai_api_key: Option<String>,
     pub claude_api_key: Option<String>,
+    pub gemini_api_key: Option<String>,
     pub default_ai_provider: String,
     pub default_model: String,
+    pub reasoning_effort: Option<String>, // low, medium or high for o-series models and Claude extended thinking
+    pub ollama_url: String,               // the Ollama server, for DEFAULT_AI_PROVIDER=ollama
+    pub ollama_embedding_model: String,   // local model that embeds code for semantic search
+    pub openai_base_url: Option<String>,  // an Azure OpenAI resource or OpenAI-compatible API
+    pub openai_api_version: Option<String>, // Azure's api-version; setting it selects Azure
+    pub openai_deployment: Option<String>, // Azure deployment of the chat model
+    pub openai_embedding_deployment: Option<String>, // Azure deployment of the embedding model
+    pub stream_salvage_attempts: usize, // continuations requested when a streamed answer breaks off
+    pub response_cache: bool, // answer repeated identical requests from storage instead of the model
+    pub response_cache_ttl_hours: u64, // cached responses older than this are requested again
+    pub ai_retry_attempts: usize, // calls made to the AI API, including the first, while it fails transiently
+    pub ai_retry_base_delay_ms: u64, // wait before the first retry, doubled for each one after
+    pub ai_retry_max_delay_ms: u64, // longest wait between two retries
+    pub ai_retry_jitter: bool, // wait a random part of each delay so concurrent sessions spread out
 
     // Storage settings
     pub vector_store_path: PathBuf,
+    pub embedding_migration: String, // ask, reembed or dual when the embedding model changed
+    pub semantic_memory: bool, // rank context memory for planning by embedding similarity, not keywords
     pub sled_path: PathBuf,
     pub collection_name: String,
+    pub locks_path: PathBuf, // session lockfiles, one directory per workspace
+    pub crash_reports_path: PathBuf, // diagnostic bundles written when the agent crashes
+
+    // Memory backend settings
+    pub memory_backend: String, // "local" (sled) or "postgres" for a team-shared store
+    pub memory_backend_url: Option<String>,
+    pub memory_user: String, // keeps per-user data separate in a shared backend
 
     // Task settings
+    pub approval_mode: String, // auto, plan, confirm-writes or confirm-all
+    pub remote_approval_bind: String, // address the approval server listens on with --remote-approve
+    pub remote_approval_url: Option<String>, // public base URL for approval links, if behind a proxy
+    pub remote_approval_notify_url: Option<String>, // ntfy topic or webhook notified of prompts
+    pub remote_approval_timeout_seconds: u64, // unanswered prompts are denied after this
+    pub shell: Option<String>,               // shell for commands, e.g. "bash -l", "zsh" or "fish"
+    pub shell_login: bool, // source the user's profile so PATH matches their terminal
+    pub shell_session: bool, // keep one shell per session so `cd`, exports and virtualenvs persist
+    pub explain_commands: bool, // explain each command and its risk before asking to run it
     pub max_concurrent_tasks: usize,
+    pub background_jobs: usize, // background jobs (embedding, memory compaction) run at once
+    pub background_jobs_per_minute: u32, // background jobs started per minute, to stay under API rate limits
     pub default_timeout_seconds: u64,
+    pub auto_format: bool, // run the project's configured formatter on files the agent writes
+    pub verify_after_edits: bool, // build and test after a plan changes files
+    pub verify_command: Option<String>, // overrides the command detected from project files
+    pub verify_max_fix_attempts: usize, // fix plans tried before reporting a failed verification
+    pub result_validation_retries: usize, // reruns of a step whose result fails its tool's validators
+    pub parallel_tools: usize, // consecutive read-only steps run at once; 1 runs every step alone
+    pub mutation_review: bool, // run cargo-mutants on changed files once verification passes
+    pub replan_checkpoints: bool, // after each step, check whether the rest of the plan still holds
+    pub replan_model: Option<String>, // cheaper model for those checkpoints; the default model if unset
+    pub model_routes: Option<String>, // models per tool, e.g. "TaskTool=gpt-4o-mini,ProtoSubtask=gpt-4o-mini"
+    pub eval_judge_model: Option<String>, // model that scores eval runs; the default model if unset
+    pub answers_dir: Option<PathBuf>, // where explanations are saved, relative to the workspace; None to not save
+    pub crate_docs_cache: Option<PathBuf>, // where crates.io lookups are cached; None to not cache
+    pub change_summaries: bool, // summarize what each editing turn changed and why, and remember it
+    pub prompt_experiments: bool, // split sessions between the configured prompt variants
+    pub experiments_path: PathBuf, // outcomes of turns under each variant, one JSON object per line
+    pub dirty_tree: String, // off, warn, block or stash uncommitted changes before the agent edits
+
+    // Notification settings
+    pub notification_webhook_url: Option<String>, // receives a JSON summary after each turn or batch task
+    pub desktop_notifications: bool, // notify on long runs finishing and on pending approvals
+    pub desktop_notify_after_seconds: u64, // runs shorter than this finish without a notification
+
+    // Voice input settings
+    pub voice_record_command: Option<String>, // records WAV to {file}; sox, arecord or ffmpeg if unset
+    pub voice_transcribe_command: Option<String>, // local whisper printing the text of {file}; the API if unset
+    pub voice_model: String,                      // transcription model of the API
+    pub voice_language: Option<String>, // ISO-639-1 code; detected from the speech if unset
+
+    // Display settings
+    pub color_theme: String, // default, light-terminal, high-contrast or no-emoji
+
+    // Update settings
+    pub update_repository: String, // GitHub owner/name whose releases `indiefuture update` installs
 
     // Codebase settings
+    pub auto_context: String, // gather a project overview at startup: new, always or off
     pub default_scan_path: PathBuf,
     pub ignore_patterns: Vec<String>,
     pub supported_extensions: Vec<String>,
@@ -35,13 +106,75 @@ impl Default for Settings {
         Self {
             openai_api_key: None,
             claude_api_key: None,
+            gemini_api_key: None,
             default_ai_provider: "openai".to_string(),
             default_model: "gpt-4o".to_string(),
+            reasoning_effort: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            ollama_embedding_model: "nomic-embed-text".to_string(),
+            openai_base_url: None,
+            openai_api_version: None,
+            openai_deployment: None,
+            openai_embedding_deployment: None,
+            stream_salvage_attempts: 2,
+            response_cache: false,
+            response_cache_ttl_hours: 24,
+            ai_retry_attempts: 3,
+            ai_retry_base_delay_ms: 500,
+            ai_retry_max_delay_ms: 30_000,
+            ai_retry_jitter: true,
             vector_store_path: default_data_dir.join("vector_store"),
+            embedding_migration: "ask".to_string(),
+            semantic_memory: true,
             sled_path: default_data_dir.join("sled_db"),
             collection_name: "code_embeddings".to_string(),
+            locks_path: default_data_dir.join("locks"),
+            crash_reports_path: default_data_dir.join("crashes"),
+            memory_backend: "local".to_string(),
+            memory_backend_url: None,
+            memory_user: env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or_else(|_| "default".to_string()),
+            approval_mode: "confirm-writes".to_string(),
+            remote_approval_bind: "127.0.0.1:8787".to_string(),
+            remote_approval_url: None,
+            remote_approval_notify_url: None,
+            remote_approval_timeout_seconds: 3600,
+            notification_webhook_url: None,
+            desktop_notifications: false,
+            desktop_notify_after_seconds: 30,
+            voice_record_command: None,
+            voice_transcribe_command: None,
+            voice_model: "whisper-1".to_string(),
+            voice_language: None,
+            color_theme: "default".to_string(),
+            update_repository: "indiefuture/indiefuture_agent_cli".to_string(),
+            shell: None,
+            shell_login: false,
+            shell_session: false,
+            explain_commands: true,
             max_concurrent_tasks: 5,
+            background_jobs: 2,
+            background_jobs_per_minute: 30,
             default_timeout_seconds: 30,
+            auto_format: true,
+            verify_after_edits: true,
+            verify_command: None,
+            verify_max_fix_attempts: 2,
+            result_validation_retries: 1,
+            parallel_tools: 4,
+            mutation_review: false,
+            replan_checkpoints: true,
+            replan_model: None,
+            model_routes: None,
+            eval_judge_model: None,
+            answers_dir: Some(PathBuf::from(".indiefuture/answers")),
+            crate_docs_cache: Some(default_data_dir.join("crate_docs")),
+            change_summaries: true,
+            prompt_experiments: false,
+            experiments_path: default_data_dir.join("experiments.jsonl"),
+            dirty_tree: "warn".to_string(),
+            auto_context: "new".to_string(),
             default_scan_path: PathBuf::from("."),
             ignore_patterns: vec![
                 ".git".to_string(),
@@ -84,6 +217,10 @@ impl Settings {
             settings.claude_api_key = Some(key);
         }
 
+        if let Ok(key) = env::var("GEMINI_API_KEY") {
+            settings.gemini_api_key = Some(key);
+        }
+
         if let Ok(provider) = env::var("DEFAULT_AI_PROVIDER") {
             settings.default_ai_provider = provider;
         }
@@ -92,24 +229,282 @@ impl Settings {
             settings.default_model = model;
         }
 
+        if let Ok(effort) = env::var("REASONING_EFFORT") {
+            settings.reasoning_effort = Some(effort);
+        }
+
+        if let Ok(url) = env::var("OLLAMA_URL") {
+            settings.ollama_url = url;
+        }
+
+        if let Ok(model) = env::var("OLLAMA_EMBEDDING_MODEL") {
+            settings.ollama_embedding_model = model;
+        }
+
+        if let Ok(url) = env::var("OPENAI_BASE_URL") {
+            settings.openai_base_url = Some(url);
+        }
+
+        if let Ok(version) = env::var("OPENAI_API_VERSION") {
+            settings.openai_api_version = Some(version);
+        }
+
+        if let Ok(deployment) = env::var("OPENAI_DEPLOYMENT") {
+            settings.openai_deployment = Some(deployment);
+        }
+
+        if let Ok(deployment) = env::var("OPENAI_EMBEDDING_DEPLOYMENT") {
+            settings.openai_embedding_deployment = Some(deployment);
+        }
+
+        if let Ok(attempts) = env::var("STREAM_SALVAGE_ATTEMPTS") {
+            if let Ok(attempts) = attempts.parse::<usize>() {
+                settings.stream_salvage_attempts = attempts;
+            }
+        }
+
+        if let Ok(enabled) = env::var("RESPONSE_CACHE") {
+            settings.response_cache = matches!(enabled.as_str(), "1" | "true" | "yes");
+        }
+
+        if let Ok(hours) = env::var("RESPONSE_CACHE_TTL_HOURS") {
+            if let Ok(hours) = hours.parse::<u64>() {
+                settings.response_cache_ttl_hours = hours;
+            }
+        }
+
+        if let Ok(attempts) = env::var("AI_RETRY_ATTEMPTS") {
+            if let Ok(attempts) = attempts.parse::<usize>() {
+                settings.ai_retry_attempts = attempts.max(1);
+            }
+        }
+
+        if let Ok(delay) = env::var("AI_RETRY_BASE_DELAY_MS") {
+            if let Ok(delay) = delay.parse::<u64>() {
+                settings.ai_retry_base_delay_ms = delay;
+            }
+        }
+
+        if let Ok(delay) = env::var("AI_RETRY_MAX_DELAY_MS") {
+            if let Ok(delay) = delay.parse::<u64>() {
+                settings.ai_retry_max_delay_ms = delay;
+            }
+        }
+
+        if let Ok(enabled) = env::var("AI_RETRY_JITTER") {
+            settings.ai_retry_jitter = !matches!(enabled.as_str(), "0" | "false" | "no");
+        }
+
         if let Ok(path) = env::var("VECTOR_STORE_PATH") {
             settings.vector_store_path = PathBuf::from(path);
         }
 
+        if let Ok(policy) = env::var("EMBEDDING_MIGRATION") {
+            settings.embedding_migration = policy;
+        }
+
+        if let Ok(enabled) = env::var("SEMANTIC_MEMORY") {
+            settings.semantic_memory = !matches!(enabled.as_str(), "0" | "false" | "no");
+        }
+
         if let Ok(path) = env::var("SLED_PATH") {
             settings.sled_path = PathBuf::from(path);
         }
 
+        if let Ok(path) = env::var("CRASH_REPORTS_PATH") {
+            settings.crash_reports_path = PathBuf::from(path);
+        }
+
         if let Ok(name) = env::var("COLLECTION_NAME") {
             settings.collection_name = name;
         }
 
+        if let Ok(backend) = env::var("MEMORY_BACKEND") {
+            settings.memory_backend = backend;
+        }
+
+        if let Ok(url) = env::var("MEMORY_BACKEND_URL") {
+            settings.memory_backend_url = Some(url);
+        }
+
+        if let Ok(user) = env::var("MEMORY_USER") {
+            settings.memory_user = user;
+        }
+
+        if let Ok(mode) = env::var("APPROVAL_MODE") {
+            settings.approval_mode = mode;
+        }
+
+        if let Ok(bind) = env::var("REMOTE_APPROVAL_BIND") {
+            settings.remote_approval_bind = bind;
+        }
+
+        if let Ok(url) = env::var("REMOTE_APPROVAL_URL") {
+            settings.remote_approval_url = Some(url);
+        }
+
+        if let Ok(url) = env::var("REMOTE_APPROVAL_NOTIFY_URL") {
+            settings.remote_approval_notify_url = Some(url);
+        }
+
+        if let Ok(timeout) = env::var("REMOTE_APPROVAL_TIMEOUT_SECONDS") {
+            if let Ok(timeout) = timeout.parse::<u64>() {
+                settings.remote_approval_timeout_seconds = timeout;
+            }
+        }
+
+        if let Ok(url) = env::var("NOTIFICATION_WEBHOOK_URL") {
+            settings.notification_webhook_url = Some(url);
+        }
+
+        if let Ok(enabled) = env::var("DESKTOP_NOTIFICATIONS") {
+            settings.desktop_notifications = matches!(enabled.as_str(), "1" | "true" | "yes");
+        }
+
+        if let Ok(seconds) = env::var("DESKTOP_NOTIFY_AFTER_SECONDS") {
+            if let Ok(seconds) = seconds.parse::<u64>() {
+                settings.desktop_notify_after_seconds = seconds;
+            }
+        }
+
+        if let Ok(command) = env::var("VOICE_RECORD_COMMAND") {
+            settings.voice_record_command = Some(command);
+        }
+
+        if let Ok(command) = env::var("VOICE_TRANSCRIBE_COMMAND") {
+            settings.voice_transcribe_command = Some(command);
+        }
+
+        if let Ok(model) = env::var("VOICE_MODEL") {
+            settings.voice_model = model;
+        }
+
+        if let Ok(language) = env::var("VOICE_LANGUAGE") {
+            settings.voice_language = Some(language);
+        }
+
+        if let Ok(theme) = env::var("COLOR_THEME") {
+            settings.color_theme = theme;
+        }
+
+        if let Ok(repository) = env::var("UPDATE_REPOSITORY") {
+            settings.update_repository = repository;
+        }
+
+        if let Ok(enabled) = env::var("AUTO_FORMAT") {
+            settings.auto_format = !matches!(enabled.as_str(), "0" | "false" | "no");
+        }
+
+        if let Ok(enabled) = env::var("VERIFY_AFTER_EDITS") {
+            settings.verify_after_edits = !matches!(enabled.as_str(), "0" | "false" | "no");
+        }
+
+        if let Ok(command) = env::var("VERIFY_COMMAND") {
+            settings.verify_command = Some(command);
+        }
+
+        if let Ok(attempts) = env::var("VERIFY_MAX_FIX_ATTEMPTS") {
+            if let Ok(attempts) = attempts.parse::<usize>() {
+                settings.verify_max_fix_attempts = attempts;
+            }
+        }
+
+        if let Ok(retries) = env::var("RESULT_VALIDATION_RETRIES") {
+            if let Ok(retries) = retries.parse::<usize>() {
+                settings.result_validation_retries = retries;
+            }
+        }
+
+        if let Ok(limit) = env::var("PARALLEL_TOOLS") {
+            if let Ok(limit) = limit.parse::<usize>() {
+                settings.parallel_tools = limit.max(1);
+            }
+        }
+
+        if let Ok(enabled) = env::var("MUTATION_REVIEW") {
+            settings.mutation_review = matches!(enabled.as_str(), "1" | "true" | "yes");
+        }
+
+        if let Ok(enabled) = env::var("REPLAN_CHECKPOINTS") {
+            settings.replan_checkpoints = !matches!(enabled.as_str(), "0" | "false" | "no");
+        }
+
+        if let Ok(model) = env::var("REPLAN_MODEL") {
+            settings.replan_model = Some(model);
+        }
+
+        if let Ok(routes) = env::var("MODEL_ROUTES") {
+            settings.model_routes = Some(routes);
+        }
+
+        if let Ok(model) = env::var("EVAL_JUDGE_MODEL") {
+            settings.eval_judge_model = Some(model);
+        }
+
+        if let Ok(dir) = env::var("ANSWERS_DIR") {
+            settings.answers_dir =
+                (!matches!(dir.as_str(), "" | "off" | "false" | "no")).then(|| PathBuf::from(dir));
+        }
+
+        if let Ok(dir) = env::var("CRATE_DOCS_CACHE") {
+            settings.crate_docs_cache =
+                (!matches!(dir.as_str(), "" | "off" | "false" | "no")).then(|| PathBuf::from(dir));
+        }
+
+        if let Ok(enabled) = env::var("CHANGE_SUMMARIES") {
+            settings.change_summaries = !matches!(enabled.as_str(), "0" | "false" | "no");
+        }
+
+        if let Ok(enabled) = env::var("PROMPT_EXPERIMENTS") {
+            settings.prompt_experiments = matches!(enabled.as_str(), "1" | "true" | "yes");
+        }
+
+        if let Ok(path) = env::var("EXPERIMENTS_PATH") {
+            settings.experiments_path = PathBuf::from(path);
+        }
+
+        if let Ok(policy) = env::var("DIRTY_TREE") {
+            settings.dirty_tree = policy;
+        }
+
+        if let Ok(auto_context) = env::var("AUTO_CONTEXT") {
+            settings.auto_context = auto_context;
+        }
+
+        if let Ok(shell) = env::var("AGENT_SHELL") {
+            settings.shell = Some(shell);
+        }
+
+        if let Ok(login) = env::var("AGENT_SHELL_LOGIN") {
+            settings.shell_login = matches!(login.as_str(), "1" | "true" | "yes");
+        }
+
+        if let Ok(session) = env::var("SHELL_SESSION") {
+            settings.shell_session = matches!(session.as_str(), "1" | "true" | "yes");
+        }
+
+        if let Ok(enabled) = env::var("EXPLAIN_COMMANDS") {
+            settings.explain_commands = !matches!(enabled.as_str(), "0" | "false" | "no");
+        }
+
         if let Ok(max_tasks) = env::var("MAX_CONCURRENT_TASKS") {
             if let Ok(max_tasks) = max_tasks.parse::<usize>() {
                 settings.max_concurrent_tasks = max_tasks;
             }
         }
 
+        if let Ok(jobs) = env::var("BACKGROUND_JOBS") {
+            if let Ok(jobs) = jobs.parse::<usize>() {
+                settings.background_jobs = jobs;
+            }
+        }
+
+        if let Ok(per_minute) = env::var("BACKGROUND_JOBS_PER_MINUTE") {
+            if let Ok(per_minute) = per_minute.parse::<u32>() {
+                settings.background_jobs_per_minute = per_minute;
+            }
+        }
+
         if let Ok(timeout) = env::var("DEFAULT_TIMEOUT_SECONDS") {
             if let Ok(timeout) = timeout.parse::<u64>() {
                 settings.default_timeout_seconds = timeout;
@@ -129,7 +524,26 @@ impl Settings {
         Ok(settings)
     }
 
+    /// The API key of the default provider; empty when it needs none
+    pub fn api_key(&self) -> &str {
+        self.api_key_for(&self.default_ai_provider)
+    }
+
+    /// The API key of `provider`, empty for providers without one
+    pub fn api_key_for(&self, provider: &str) -> &str {
+        match provider {
+            "claude" => self.claude_api_key.as_deref(),
+            "gemini" => self.gemini_api_key.as_deref(),
+            _ => self.openai_api_key.as_deref(),
+        }
+        .unwrap_or("")
+    }
+
     pub fn validate(&self) -> AgentResult<()> {
+        if !ai::compiled_in(&self.default_ai_provider) {
+            return Err(ai::not_compiled_in(&self.default_ai_provider));
+        }
+
         match self.default_ai_provider.as_str() {
             "openai" => {
                 if self.openai_api_key.is_none() {
@@ -145,6 +559,14 @@ impl Settings {
                     ));
                 }
             }
+            "gemini" => {
+                if self.gemini_api_key.is_none() {
+                    return Err(AgentError::Configuration(
+                        "Gemini API key is required when using Gemini provider".to_string(),
+                    ));
+                }
+            }
+            "ollama" => {}
             provider => {
                 return Err(AgentError::Configuration(format!(
                     "Unsupported AI provider: {}",
@@ -0,0 +1,60 @@
+use crate::error::{AgentError, AgentResult};
+use crate::experiments::{self, PromptVariants};
+use crate::keys::KeyBindings;
+use crate::result_validation::{self, Validators};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Personal settings that follow the user across workspaces, in the data directory
+pub const USER_SETTINGS_FILE: &str = ".indiefuture/config.yaml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UserSettings {
+    /// Keys of the approval prompts, for terminals and habits the defaults clash with
+    #[serde(default)]
+    pub keys: KeyBindings,
+    /// Checks each tool's results must pass before they are stored in context memory,
+    /// written as `- regex: ...` maps rather than YAML tags
+    #[serde(
+        default,
+        deserialize_with = "serde_yaml::with::singleton_map_recursive::deserialize"
+    )]
+    pub validators: Validators,
+    /// Alternate planning prompts by tool and variant name, tried when `PROMPT_EXPERIMENTS`
+    /// is on
+    #[serde(default)]
+    pub prompt_variants: PromptVariants,
+}
+
+impl UserSettings {
+    pub fn path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(USER_SETTINGS_FILE))
+    }
+
+    /// Load the user settings; a missing file means defaults
+    pub fn load() -> AgentResult<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => {
+                let contents = std::fs::read_to_string(&path)?;
+                Self::parse(&contents).map_err(|e| match e {
+                    AgentError::Configuration(message) => AgentError::Configuration(format!(
+                        "Invalid user settings {:?}: {}",
+                        path, message
+                    )),
+                    other => other,
+                })
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Settings from YAML text, with the key bindings, validators and prompt variants checked
+    pub fn parse(contents: &str) -> AgentResult<Self> {
+        let settings: Self =
+            serde_yaml::from_str(contents).map_err(|e| AgentError::Configuration(e.to_string()))?;
+        settings.keys.validate()?;
+        result_validation::check(&settings.validators)?;
+        experiments::check(&settings.prompt_variants)?;
+        Ok(settings)
+    }
+}
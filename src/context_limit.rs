@@ -0,0 +1,125 @@
+use crate::error::AgentError;
+use crate::interrupt;
+use crate::keys::{self, Action};
+use crate::memory::MemoryFragment;
+use crate::model_switch;
+use console::{Key, Term};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Largest fragments offered for removal when a prompt still does not fit after compaction
+pub const REMOVAL_CANDIDATES: usize = 5;
+
+/// Set when a provider rejects a prompt as too long, until the engine makes room
+static EXCEEDED: AtomicBool = AtomicBool::new(false);
+
+/// Whether an error response says the prompt was longer than the model's context window
+pub fn is_exceeded(status: u16, body: &str) -> bool {
+    let body = body.to_lowercase();
+    matches!(status, 400 | 413)
+        && [
+            "context_length_exceeded",
+            "maximum context length",
+            "context window",
+            "prompt is too long",
+            "too many tokens",
+            "exceeds the maximum number of tokens",
+        ]
+        .iter()
+        .any(|phrase| body.contains(phrase))
+}
+
+/// The error for a prompt `provider` rejected as too long, noted so the running step can be
+/// retried with less context
+pub fn exceeded(provider: &str, body: &str) -> AgentError {
+    EXCEEDED.store(true, Ordering::SeqCst);
+
+    // Both providers explain the limit in `error.message`
+    let message = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| json["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string());
+    AgentError::ContextLengthExceeded(format!("{}: {}", provider, message))
+}
+
+/// Whether a prompt was rejected as too long since the last call, clearing the flag
+pub fn take() -> bool {
+    EXCEEDED.swap(false, Ordering::SeqCst)
+}
+
+/// One line describing a fragment and its size
+pub fn fragment_label(fragment: &MemoryFragment, pinned: bool) -> String {
+    let path = fragment
+        .metadata
+        .as_ref()
+        .and_then(|m| m.path.as_deref())
+        .map(|path| format!(" ({})", path))
+        .unwrap_or_default();
+    format!(
+        "{}{}{} - {} chars, ~{} tokens",
+        if pinned { "📌 " } else { "" },
+        fragment.source,
+        path,
+        fragment.content.len(),
+        fragment.content.len() / model_switch::CHARS_PER_TOKEN
+    )
+}
+
+/// Let the user remove any of `candidates` with one key each: its number removes it, and the
+/// reject key or Enter finishes. Returns the indices of the removed candidates.
+pub fn choose_removals(candidates: &[(MemoryFragment, bool)]) -> Vec<usize> {
+    let labels: Vec<String> = candidates
+        .iter()
+        .map(|(fragment, pinned)| fragment_label(fragment, *pinned))
+        .collect();
+
+    let term = Term::stderr();
+    if !term.is_term() {
+        let mut prompt = cliclack::multiselect("Remove which context fragments?").required(false);
+        for (index, label) in labels.iter().enumerate() {
+            prompt = prompt.item(index, label, "");
+        }
+        return prompt.interact().unwrap_or_default();
+    }
+
+    let bindings = keys::bindings();
+    let listing = labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| format!("[{}] {}", index + 1, label))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let _ = cliclack::log::step(format!(
+        "Press a number to remove that fragment, [{}] or [enter] when done:\n{}",
+        bindings.key_name(Action::Reject),
+        listing
+    ));
+
+    let mut removed = Vec::new();
+    loop {
+        let Ok(key) = term.read_key_raw() else {
+            return removed;
+        };
+        match (&key, bindings.action(&key)) {
+            (_, Some(Action::Interrupt)) => {
+                interrupt::request();
+                return removed;
+            }
+            (Key::Enter | Key::Escape, _) | (_, Some(Action::Reject)) => return removed,
+            (Key::Char(c), _) => {
+                let Some(index) = c
+                    .to_digit(10)
+                    .and_then(|digit| (digit as usize).checked_sub(1))
+                    .filter(|index| *index < candidates.len() && !removed.contains(index))
+                else {
+                    continue;
+                };
+                let _ = cliclack::log::success(format!("Removed {}", labels[index]));
+                removed.push(index);
+                if removed.len() == candidates.len() {
+                    return removed;
+                }
+            }
+            _ => {}
+        }
+    }
+}
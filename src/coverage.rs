@@ -0,0 +1,327 @@
+use crate::chunking;
+use crate::error::{AgentError, AgentResult};
+use crate::memory::{MemoryFragment, MemoryMetadata, Provenance};
+use crate::platform;
+use crate::tool_env;
+use crate::utils;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Source of the fragment holding the last coverage report
+pub const COVERAGE_SOURCE: &str = "coverage";
+/// Uncovered functions listed per file
+const MAX_FUNCTIONS_PER_FILE: usize = 15;
+/// Node kinds of functions and methods in the supported languages
+const FUNCTION_KINDS: &[&str] = &[
+    "function_item",
+    "function_definition",
+    "function_declaration",
+    "generator_function_declaration",
+    "method_definition",
+    "method_declaration",
+];
+
+/// Line hits of one source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub file: String,                // relative to the workspace
+    pub lines: BTreeMap<usize, u64>, // hits of each instrumented line
+}
+
+impl FileCoverage {
+    /// Instrumented lines that ran at least once
+    pub fn covered(&self) -> usize {
+        self.lines.values().filter(|hits| **hits > 0).count()
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.lines.is_empty() {
+            return 100.0;
+        }
+        self.covered() as f64 * 100.0 / self.lines.len() as f64
+    }
+}
+
+/// A function none of whose instrumented lines ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncoveredFunction {
+    pub symbol: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Coverage from an LCOV tracefile, which both `cargo llvm-cov --lcov` and
+/// `cargo tarpaulin --out Lcov` write. Files outside `root` are skipped.
+pub fn parse_lcov(text: &str, root: &Path) -> Vec<FileCoverage> {
+    let mut files: BTreeMap<String, BTreeMap<usize, u64>> = BTreeMap::new();
+    let mut current: Option<String> = None;
+
+    for line in text.lines().map(str::trim) {
+        if let Some(path) = line.strip_prefix("SF:") {
+            current = relative(root, Path::new(path));
+        } else if let Some(record) = line.strip_prefix("DA:") {
+            let (Some(file), Some((number, hits))) = (&current, record.split_once(',')) else {
+                continue;
+            };
+            // Some generators append a checksum after the hit count
+            let hits = hits.split(',').next().unwrap_or(hits);
+            if let (Ok(number), Ok(hits)) = (number.parse::<usize>(), hits.parse::<u64>()) {
+                // Generic code instantiated several times is reported once per instance
+                *files
+                    .entry(file.clone())
+                    .or_default()
+                    .entry(number)
+                    .or_default() += hits;
+            }
+        } else if line == "end_of_record" {
+            current = None;
+        }
+    }
+
+    into_coverage(files)
+}
+
+/// Coverage from tarpaulin's `tarpaulin-report.json`, whose paths are lists of components
+pub fn parse_tarpaulin(json: &str, root: &Path) -> Vec<FileCoverage> {
+    let Ok(report) = serde_json::from_str::<Value>(json) else {
+        return Vec::new();
+    };
+    let mut files: BTreeMap<String, BTreeMap<usize, u64>> = BTreeMap::new();
+
+    for file in report["files"].as_array().into_iter().flatten() {
+        let path: PathBuf = file["path"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        let Some(relative) = relative(root, &path) else {
+            continue;
+        };
+        let lines = files.entry(relative).or_default();
+        for trace in file["traces"].as_array().into_iter().flatten() {
+            let Some(number) = trace["line"].as_u64() else {
+                continue;
+            };
+            let hits = trace["stats"]["Line"].as_u64().unwrap_or(0);
+            *lines.entry(number as usize).or_default() += hits;
+        }
+    }
+
+    into_coverage(files)
+}
+
+fn into_coverage(files: BTreeMap<String, BTreeMap<usize, u64>>) -> Vec<FileCoverage> {
+    files
+        .into_iter()
+        .filter(|(_, lines)| !lines.is_empty())
+        .map(|(file, lines)| FileCoverage { file, lines })
+        .collect()
+}
+
+/// `path` relative to the workspace, or None for dependencies and the standard library
+fn relative(root: &Path, path: &Path) -> Option<String> {
+    if path.is_relative() {
+        return Some(platform::display_path(path));
+    }
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    path.strip_prefix(root)
+        .or_else(|_| path.strip_prefix(&canonical))
+        .ok()
+        .map(platform::display_path)
+}
+
+/// A coverage tool, and where it leaves its report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Measurer {
+    LlvmCov,
+    Tarpaulin,
+}
+
+impl Measurer {
+    fn command(&self) -> &'static str {
+        match self {
+            Self::LlvmCov => "cargo llvm-cov --workspace --lcov --output-path target/lcov.info",
+            Self::Tarpaulin => "cargo tarpaulin --workspace --out Json --output-dir target",
+        }
+    }
+
+    fn report_path(&self) -> &'static str {
+        match self {
+            Self::LlvmCov => "target/lcov.info",
+            Self::Tarpaulin => "target/tarpaulin-report.json",
+        }
+    }
+
+    fn parse(&self, report: &str, root: &Path) -> Vec<FileCoverage> {
+        match self {
+            Self::LlvmCov => parse_lcov(report, root),
+            Self::Tarpaulin => parse_tarpaulin(report, root),
+        }
+    }
+
+    /// The first installed tool, preferring llvm-cov, which works on every platform
+    async fn detect(root: &Path) -> Option<Self> {
+        for (measurer, probe) in [
+            (Self::LlvmCov, "cargo llvm-cov --version"),
+            (Self::Tarpaulin, "cargo tarpaulin --version"),
+        ] {
+            let installed = platform::shell_command(probe)
+                .current_dir(root)
+                .output()
+                .await
+                .is_ok_and(|output| output.status.success());
+            if installed {
+                return Some(measurer);
+            }
+        }
+        None
+    }
+}
+
+/// Run the test suite of the Cargo project at `root` under coverage and read the report,
+/// one entry per source file in file order
+pub async fn measure(root: &Path) -> AgentResult<Vec<FileCoverage>> {
+    if !root.join("Cargo.toml").is_file() {
+        return Err(AgentError::ToolExecution(
+            "Coverage is only measured for Cargo projects".to_string(),
+        ));
+    }
+    let measurer = Measurer::detect(root).await.ok_or_else(|| {
+        AgentError::ToolExecution(
+            "Neither cargo-llvm-cov nor cargo-tarpaulin is installed; install one with \
+            `cargo install cargo-llvm-cov`"
+                .to_string(),
+        )
+    })?;
+
+    let output = platform::shell_command(measurer.command())
+        .current_dir(root)
+        .output()
+        .await
+        .map_err(|e| {
+            AgentError::ToolExecution(format!("Failed to run '{}': {}", measurer.command(), e))
+        })?;
+    if !output.status.success() {
+        let stderr = tool_env::mask(&String::from_utf8_lossy(&output.stderr));
+        return Err(AgentError::ToolExecution(format!(
+            "'{}' failed; coverage needs a passing test suite:\n{}",
+            measurer.command(),
+            utils::truncate_string(stderr.trim(), 2_000)
+        )));
+    }
+
+    let report = std::fs::read_to_string(root.join(measurer.report_path())).map_err(|e| {
+        AgentError::ToolExecution(format!(
+            "Cannot read the coverage report {}: {}",
+            measurer.report_path(),
+            e
+        ))
+    })?;
+    Ok(measurer.parse(&report, root))
+}
+
+/// Files with uncovered lines, lowest coverage first
+pub fn lowest_coverage(files: &[FileCoverage], count: usize) -> Vec<&FileCoverage> {
+    let mut partial: Vec<&FileCoverage> = files.iter().filter(|f| f.percent() < 100.0).collect();
+    partial.sort_by(|a, b| {
+        a.percent()
+            .total_cmp(&b.percent())
+            .then_with(|| a.file.cmp(&b.file))
+    });
+    partial.truncate(count);
+    partial
+}
+
+/// Functions in `file` none of whose instrumented lines ran. Functions without
+/// instrumented lines, such as trait declarations, are not listed.
+pub fn uncovered_functions(root: &Path, file: &FileCoverage) -> Vec<UncoveredFunction> {
+    let path = root.join(&file.file);
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+
+    chunking::definitions(&path, &content)
+        .into_iter()
+        .filter(|chunk| FUNCTION_KINDS.contains(&chunk.kind.as_str()))
+        .filter_map(|chunk| {
+            let mut hits = file
+                .lines
+                .range(chunk.start_line..=chunk.end_line)
+                .peekable();
+            hits.peek()?;
+            if !hits.all(|(_, hits)| *hits == 0) {
+                return None;
+            }
+            Some(UncoveredFunction {
+                symbol: chunk.symbol?,
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+            })
+        })
+        .collect()
+}
+
+/// Context fragment with the total coverage and the uncovered functions of the
+/// `max_files` least covered files
+pub fn fragment(root: &Path, files: &[FileCoverage], max_files: usize) -> MemoryFragment {
+    let instrumented: usize = files.iter().map(|f| f.lines.len()).sum();
+    let covered: usize = files.iter().map(FileCoverage::covered).sum();
+    let total = if instrumented == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / instrumented as f64
+    };
+
+    let lowest = lowest_coverage(files, max_files);
+    let mut lines = vec![format!(
+        "Line coverage {:.1}% ({} of {} lines in {} files).",
+        total,
+        covered,
+        instrumented,
+        files.len()
+    )];
+    if lowest.is_empty() {
+        lines.push("Every instrumented line is covered.".to_string());
+    } else {
+        lines.push("Least covered files:".to_string());
+    }
+    for file in &lowest {
+        lines.push(format!(
+            "- {}: {:.1}% ({} of {} lines)",
+            file.file,
+            file.percent(),
+            file.covered(),
+            file.lines.len()
+        ));
+        let uncovered = uncovered_functions(root, file);
+        for function in uncovered.iter().take(MAX_FUNCTIONS_PER_FILE) {
+            lines.push(format!(
+                "  uncovered: {} (lines {}-{})",
+                function.symbol, function.start_line, function.end_line
+            ));
+        }
+        if uncovered.len() > MAX_FUNCTIONS_PER_FILE {
+            lines.push(format!(
+                "  ... and {} more uncovered functions",
+                uncovered.len() - MAX_FUNCTIONS_PER_FILE
+            ));
+        }
+    }
+
+    MemoryFragment {
+        source: COVERAGE_SOURCE.to_string(),
+        provenance: Provenance::ToolOutput,
+        content: lines.join("\n"),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("coverage".to_string()),
+            path: None,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            tags: lowest
+                .iter()
+                .map(|file| format!("file:{}", file.file))
+                .collect(),
+        }),
+    }
+}
@@ -0,0 +1,272 @@
+use crate::config::Settings;
+use crate::error::AgentResult;
+use crate::tool_env;
+use crate::utils;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Events kept for a crash report, oldest dropped first
+const MAX_EVENTS: usize = 50;
+
+/// Replaces secrets and the user's name in a saved bundle
+const REDACTED: &str = "<redacted>";
+
+/// What the session was doing, kept up to date so a crash can report it
+#[derive(Debug)]
+struct Activity {
+    task: Option<String>,
+    active_subtask: Option<String>,
+    events: VecDeque<String>,
+}
+
+static ACTIVITY: Mutex<Activity> = Mutex::new(Activity {
+    task: None,
+    active_subtask: None,
+    events: VecDeque::new(),
+});
+
+/// Where bundles go and the settings they include; set by `install`
+static REPORTS: OnceLock<(PathBuf, Value)> = OnceLock::new();
+
+/// Set by the first panic, so a panic while reporting one does not loop
+static CRASHED: AtomicBool = AtomicBool::new(false);
+
+/// Everything saved about a crash. It holds the user's request and step descriptions but no
+/// file contents or secrets, and the home directory is written as `~`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashBundle {
+    pub version: String,
+    pub timestamp: String,
+    pub os: String,
+    pub message: String,          // what the panic said
+    pub location: Option<String>, // file:line of the panic
+    pub task: Option<String>,     // the request of the turn that crashed
+    pub active_subtask: Option<String>,
+    pub events: Vec<String>, // most recent last
+    pub config: Value,       // settings without secrets
+    pub backtrace: String,
+}
+
+impl CrashBundle {
+    /// A bundle of the current activity for a panic saying `message`
+    pub fn capture(message: &str, location: Option<String>, backtrace: &str) -> Self {
+        // The panic may have come from a thread holding the lock
+        let (task, active_subtask, events) = match ACTIVITY.try_lock() {
+            Ok(activity) => (
+                activity.task.clone(),
+                activity.active_subtask.clone(),
+                activity.events.iter().cloned().collect(),
+            ),
+            Err(_) => (None, None, Vec::new()),
+        };
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            message: anonymize(message),
+            location,
+            task,
+            active_subtask,
+            events,
+            config: REPORTS
+                .get()
+                .map(|(_, config)| config.clone())
+                .unwrap_or(Value::Null),
+            backtrace: anonymize(backtrace),
+        }
+    }
+
+    /// Write the bundle to `dir` as `crash-<time>.json`, returning its path
+    pub fn save(&self, dir: &Path) -> AgentResult<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "crash-{}.json",
+            Utc::now().format("%Y%m%d-%H%M%S-%3f")
+        ));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> AgentResult<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// What the crashed session was doing, shown when resuming it
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Resuming after the crash of {}", self.timestamp);
+        if let Some(subtask) = &self.active_subtask {
+            summary.push_str(&format!("\nIt was working on: {}", subtask));
+        }
+        let skip = self.events.len().saturating_sub(5);
+        if skip < self.events.len() {
+            summary.push_str("\nLast steps:");
+            for event in &self.events[skip..] {
+                summary.push_str(&format!("\n  {}", event));
+            }
+        }
+        summary
+    }
+
+    /// What to tell the user instead of a raw panic dump
+    pub fn recovery_message(&self, saved: Option<&Path>) -> String {
+        let mut message = format!(
+            "indiefuture stopped after an internal error: {}\n",
+            self.message
+        );
+        if let Some(subtask) = &self.active_subtask {
+            message.push_str(&format!("It was working on: {}\n", subtask));
+        }
+        message
+            .push_str("Changes already made are on disk; check `git status` before continuing.\n");
+        match saved {
+            Some(path) => message.push_str(&format!(
+                "A diagnostic bundle without secrets or file contents was saved to {}\n\
+                 Please attach it to a bug report.\n",
+                path.display()
+            )),
+            None => message.push_str("The diagnostic bundle could not be saved.\n"),
+        }
+        if self.task.is_some() {
+            message.push_str("Pick up where it left off with: indiefuture resume\n");
+        }
+        message
+    }
+}
+
+/// Save a bundle and print a recovery message on any panic, then exit. Ending the process
+/// keeps a crashed background task from leaving the session running half-broken.
+pub fn install(settings: &Settings) {
+    let _ = REPORTS.set((
+        settings.crash_reports_path.clone(),
+        redacted_settings(settings),
+    ));
+
+    std::panic::set_hook(Box::new(|info| {
+        if CRASHED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()));
+
+        let bundle =
+            CrashBundle::capture(&message, location, &Backtrace::force_capture().to_string());
+        let saved = REPORTS.get().and_then(|(dir, _)| bundle.save(dir).ok());
+
+        // A spinner or prompt may have hidden the cursor
+        let _ = console::Term::stderr().show_cursor();
+        eprintln!("\n{}", bundle.recovery_message(saved.as_deref()));
+        std::process::exit(101);
+    }));
+}
+
+/// Start a new turn for `task`
+pub fn begin_turn(task: &str) {
+    let mut activity = ACTIVITY.lock().unwrap_or_else(|e| e.into_inner());
+    activity.task = Some(anonymize(task));
+    activity.active_subtask = None;
+    push_event(
+        &mut activity,
+        format!("turn: {}", utils::truncate_string(task, 200)),
+    );
+}
+
+/// The subtask being executed, or `None` between subtasks
+pub fn set_active_subtask(description: Option<&str>) {
+    let mut activity = ACTIVITY.lock().unwrap_or_else(|e| e.into_inner());
+    match description {
+        Some(description) => {
+            activity.active_subtask = Some(anonymize(description));
+            push_event(&mut activity, format!("started: {}", description));
+        }
+        None => {
+            if let Some(finished) = activity.active_subtask.take() {
+                push_event(&mut activity, format!("finished: {}", finished));
+            }
+        }
+    }
+}
+
+/// Note something that happened, for the next crash report
+pub fn record(event: impl Into<String>) {
+    let mut activity = ACTIVITY.lock().unwrap_or_else(|e| e.into_inner());
+    push_event(&mut activity, event.into());
+}
+
+fn push_event(activity: &mut Activity, event: String) {
+    if activity.events.len() == MAX_EVENTS {
+        activity.events.pop_front();
+    }
+    activity.events.push_back(format!(
+        "{} {}",
+        Utc::now().format("%H:%M:%S"),
+        anonymize(&event)
+    ));
+}
+
+/// `text` with project secrets masked and the home directory written as `~`
+pub fn anonymize(text: &str) -> String {
+    let text = tool_env::mask(text);
+    match dirs::home_dir() {
+        Some(home) if home.as_os_str().len() > 1 => {
+            text.replace(home.to_string_lossy().as_ref(), "~")
+        }
+        _ => text,
+    }
+}
+
+/// The settings with API keys, URLs (which may carry credentials) and the user name replaced
+pub fn redacted_settings(settings: &Settings) -> Value {
+    let Ok(Value::Object(fields)) = serde_json::to_value(settings) else {
+        return Value::Null;
+    };
+
+    fields
+        .into_iter()
+        .map(|(name, value)| {
+            let secret = ["key", "url", "token", "secret", "password", "user"]
+                .iter()
+                .any(|word| name.split('_').any(|part| part == *word));
+            let value = match value {
+                Value::Null => Value::Null,
+                _ if secret => Value::String(REDACTED.to_string()),
+                Value::String(text) => Value::String(anonymize(&text)),
+                other => other,
+            };
+            (name, value)
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// The most recent bundle in `dir`, if any
+pub fn latest(dir: &Path) -> AgentResult<Option<PathBuf>> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+    let mut bundles: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("crash-") && name.ends_with(".json"))
+        })
+        .collect();
+    // Timestamped names sort chronologically
+    bundles.sort();
+    Ok(bundles.pop())
+}
@@ -0,0 +1,278 @@
+use crate::crate_source;
+use crate::error::{AgentError, AgentResult};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const CRATES_IO_API: &str = "https://crates.io/api/v1/crates";
+/// crates.io refuses requests without an identifying user agent
+const USER_AGENT: &str = concat!("indiefuture-cli/", env!("CARGO_PKG_VERSION"));
+/// Characters of README shown at once
+const MAX_README_CHARS: usize = 6_000;
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Cache fetched metadata under `dir`; only the first call has an effect. Nothing is cached
+/// until this is called.
+pub fn configure(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir);
+}
+
+/// What crates.io knows about one version of a crate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrateDocs {
+    pub name: String,
+    pub version: String,
+    pub latest_version: Option<String>, // newest stable release, to spot outdated pins
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub rust_version: Option<String>, // minimum supported Rust version
+    pub yanked: bool,
+    pub features: Vec<String>,
+    pub repository: Option<String>,
+    pub documentation: String, // docs.rs unless the crate names its own docs
+    pub readme: Option<String>, // plain text
+    pub fetched_at: String,
+}
+
+impl CrateDocs {
+    /// Metadata followed by the README, or only the README paragraphs mentioning `query`
+    pub fn render(&self, query: Option<&str>) -> String {
+        let mut lines = vec![format!("{} {}", self.name, self.version)];
+        if let Some(description) = &self.description {
+            lines.push(description.clone());
+        }
+        if self.yanked {
+            lines.push("This version is yanked.".to_string());
+        }
+        if let Some(latest) = self.latest_version.as_ref().filter(|v| **v != self.version) {
+            lines.push(format!("Latest stable version: {}", latest));
+        }
+        if let Some(license) = &self.license {
+            lines.push(format!("License: {}", license));
+        }
+        if let Some(rust_version) = &self.rust_version {
+            lines.push(format!("Minimum Rust version: {}", rust_version));
+        }
+        if !self.features.is_empty() {
+            lines.push(format!("Features: {}", self.features.join(", ")));
+        }
+        lines.push(format!("Documentation: {}", self.documentation));
+        if let Some(repository) = &self.repository {
+            lines.push(format!("Repository: {}", repository));
+        }
+
+        let readme = self.readme.as_deref().unwrap_or_default();
+        let excerpt = match query {
+            Some(query) => readme_snippets(readme, query),
+            None => readme.to_string(),
+        };
+        if !excerpt.is_empty() {
+            lines.push(String::new());
+            lines.push(utils::truncate_string(&excerpt, MAX_README_CHARS));
+        } else if let Some(query) = query {
+            lines.push(format!("The README does not mention '{}'.", query));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Metadata and README of `name` at `version`, the version Cargo.lock in `workspace_root`
+/// pins, or the latest stable release. Cached versions are not fetched again.
+pub async fn lookup(
+    workspace_root: &Path,
+    name: &str,
+    version: Option<&str>,
+) -> AgentResult<CrateDocs> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AgentError::ToolExecution(format!(
+            "{} is not a crate name",
+            name
+        )));
+    }
+    let pinned = version.map(str::to_string).or_else(|| {
+        let lockfile = std::fs::read_to_string(workspace_root.join("Cargo.lock")).ok()?;
+        crate_source::locked_versions(&lockfile, name)
+            .into_iter()
+            .next()
+    });
+    if let Some(version) = &pinned {
+        if let Some(cached) = cached(name, version) {
+            log::info!("Using cached crates.io metadata for {} {}", name, version);
+            return Ok(cached);
+        }
+    }
+
+    let docs = fetch(name, pinned.as_deref()).await?;
+    if let Err(e) = store(&docs) {
+        log::warn!("Failed to cache crates.io metadata: {}", e);
+    }
+    Ok(docs)
+}
+
+async fn fetch(name: &str, version: Option<&str>) -> AgentResult<CrateDocs> {
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(20))
+        .build()?;
+
+    let info: serde_json::Value = client
+        .get(format!("{}/{}", CRATES_IO_API, name))
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| AgentError::ToolExecution(format!("crates.io has no crate {}: {}", name, e)))?
+        .json()
+        .await?;
+    let krate = &info["crate"];
+    let text = |value: &serde_json::Value| value.as_str().map(str::to_string);
+
+    let latest_version = text(&krate["max_stable_version"]).or_else(|| text(&krate["max_version"]));
+    let version = match version {
+        Some(version) => version.to_string(),
+        None => latest_version.clone().ok_or_else(|| {
+            AgentError::ToolExecution(format!("crates.io lists no versions of {}", name))
+        })?,
+    };
+    let release = info["versions"]
+        .as_array()
+        .and_then(|versions| versions.iter().find(|v| v["num"] == version.as_str()))
+        .ok_or_else(|| {
+            AgentError::ToolExecution(format!("crates.io has no version {} of {}", version, name))
+        })?;
+
+    let mut features: Vec<String> = release["features"]
+        .as_object()
+        .map(|features| features.keys().cloned().collect())
+        .unwrap_or_default();
+    features.sort();
+
+    // A crate without a README is still worth describing
+    let readme = match client
+        .get(format!("{}/{}/{}/readme", CRATES_IO_API, name, version))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(response) => Some(html_to_text(&response.text().await?)),
+        Err(e) => {
+            log::info!("No README for {} {}: {}", name, version, e);
+            None
+        }
+    };
+
+    Ok(CrateDocs {
+        name: text(&krate["name"]).unwrap_or_else(|| name.to_string()),
+        documentation: text(&krate["documentation"])
+            .unwrap_or_else(|| format!("https://docs.rs/{}/{}", name, version)),
+        version,
+        latest_version,
+        description: text(&krate["description"]).map(|d| d.trim().to_string()),
+        license: text(&release["license"]),
+        rust_version: text(&release["rust_version"]),
+        yanked: release["yanked"].as_bool().unwrap_or(false),
+        features,
+        repository: text(&krate["repository"]),
+        readme,
+        fetched_at: utils::current_timestamp(),
+    })
+}
+
+fn cache_path(name: &str, version: &str) -> Option<PathBuf> {
+    CACHE_DIR
+        .get()
+        .map(|dir| dir.join(format!("{}-{}.json", name, version)))
+}
+
+/// Cached metadata of `name` at `version`, if it was fetched before
+pub fn cached(name: &str, version: &str) -> Option<CrateDocs> {
+    let contents = std::fs::read_to_string(cache_path(name, version)?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Cache `docs`; published versions never change, so entries do not expire
+pub fn store(docs: &CrateDocs) -> AgentResult<()> {
+    let Some(path) = cache_path(&docs.name, &docs.version) else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(docs)?)?;
+    Ok(())
+}
+
+/// README paragraphs mentioning `query`, ignoring case, with the heading above each
+pub fn readme_snippets(readme: &str, query: &str) -> String {
+    let query = query.to_lowercase();
+    let mut heading = "";
+    let mut snippets: Vec<String> = Vec::new();
+
+    for paragraph in readme.split("\n\n").map(str::trim) {
+        if paragraph.is_empty() {
+            continue;
+        }
+        if !paragraph.contains('\n') && paragraph.len() < 80 && !paragraph.ends_with('.') {
+            heading = paragraph;
+        }
+        if paragraph.to_lowercase().contains(&query) {
+            if !heading.is_empty() && heading != paragraph {
+                snippets.push(format!("{}\n{}", heading, paragraph));
+            } else {
+                snippets.push(paragraph.to_string());
+            }
+        }
+    }
+    snippets.join("\n\n")
+}
+
+/// Plain text of a rendered README: tags dropped, entities decoded, blank runs collapsed
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = rest[start + 1..start + end].to_lowercase();
+        // Block elements end a paragraph; list items start a line
+        if [
+            "p", "/p", "h1", "h2", "h3", "h4", "/h1", "/h2", "/h3", "/h4", "pre", "/pre",
+        ]
+        .iter()
+        .any(|block| tag == *block || tag.starts_with(&format!("{} ", block)))
+        {
+            text.push_str("\n\n");
+        } else if tag == "li" || tag.starts_with("li ") {
+            text.push_str("\n- ");
+        } else if tag.starts_with("br") {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    let mut collapsed = String::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if !collapsed.is_empty() {
+            collapsed.push_str("\n\n");
+        }
+        collapsed.push_str(paragraph);
+    }
+    collapsed
+}
@@ -0,0 +1,313 @@
+use crate::error::{AgentError, AgentResult};
+use crate::fs_guard::{self, PathAccess};
+use crate::platform;
+use crate::text_file;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Files listed when no file or pattern is given
+const MAX_LISTED_FILES: usize = 200;
+/// Lines of a dependency file read at once
+const MAX_READ_LINES: usize = 2_000;
+/// Matching lines returned by one search
+const MAX_SEARCH_MATCHES: usize = 100;
+
+/// Where a dependency's source is unpacked on this machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateSource {
+    pub name: String,
+    pub version: Option<String>, // None for path dependencies
+    pub root: PathBuf,
+}
+
+impl CrateSource {
+    /// `name@version`, or just the name for path dependencies
+    pub fn label(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// A dependency declared in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,    // the name the code uses
+    pub package: String, // the crate's published name, different when renamed
+    pub path: Option<String>,
+}
+
+/// Find the source of the dependency `name` of the project in `workspace_root`: path
+/// dependencies where they point, then `vendor/`, then the cargo registry, at the version
+/// Cargo.lock pins
+pub fn locate(workspace_root: &Path, name: &str) -> AgentResult<CrateSource> {
+    let manifest = std::fs::read_to_string(workspace_root.join("Cargo.toml"))
+        .map_err(|e| AgentError::ToolExecution(format!("Cannot read Cargo.toml: {}", e)))?;
+    let normalized = name.replace('-', "_");
+    let dependency = dependencies(&manifest)
+        .into_iter()
+        .find(|d| {
+            d.name.replace('-', "_") == normalized || d.package.replace('-', "_") == normalized
+        })
+        .ok_or_else(|| {
+            AgentError::ToolExecution(format!("{} is not a dependency in Cargo.toml", name))
+        })?;
+
+    if let Some(path) = &dependency.path {
+        return Ok(CrateSource {
+            name: dependency.package,
+            version: None,
+            root: workspace_root.join(path),
+        });
+    }
+
+    let lockfile = std::fs::read_to_string(workspace_root.join("Cargo.lock")).unwrap_or_default();
+    let locked = locked_versions(&lockfile, &dependency.package);
+
+    let vendor = workspace_root.join("vendor");
+    let mut candidates: Vec<PathBuf> = locked
+        .iter()
+        .map(|version| vendor.join(format!("{}-{}", dependency.package, version)))
+        .collect();
+    candidates.push(vendor.join(&dependency.package));
+    for registry in registry_dirs() {
+        candidates.extend(
+            locked
+                .iter()
+                .map(|version| registry.join(format!("{}-{}", dependency.package, version))),
+        );
+    }
+
+    if let Some(root) = candidates.into_iter().find(|dir| dir.is_dir()) {
+        let version = locked
+            .iter()
+            .find(|version| root.ends_with(format!("{}-{}", dependency.package, version)))
+            .or(locked.first())
+            .cloned();
+        return Ok(CrateSource {
+            name: dependency.package,
+            version,
+            root,
+        });
+    }
+
+    // Without a lockfile, the newest unpacked version is the best guess
+    let newest = registry_dirs()
+        .iter()
+        .filter_map(|registry| std::fs::read_dir(registry).ok())
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let version = file_name.strip_prefix(&format!("{}-", dependency.package))?;
+            version
+                .starts_with(|c: char| c.is_ascii_digit())
+                .then(|| (version_key(version), version.to_string(), entry.path()))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0));
+
+    match newest {
+        Some((_, version, root)) if locked.is_empty() => Ok(CrateSource {
+            name: dependency.package,
+            version: Some(version),
+            root,
+        }),
+        _ => Err(AgentError::ToolExecution(format!(
+            "The source of {} is not downloaded; run `cargo fetch` first",
+            dependency.package
+        ))),
+    }
+}
+
+/// Dependencies declared in any dependency table of `manifest`, including target-specific,
+/// dev and build dependencies
+pub fn dependencies(manifest: &str) -> Vec<Dependency> {
+    let mut found: Vec<Dependency> = Vec::new();
+    let mut in_table = false;
+    let mut current: Option<usize> = None; // index of a `[dependencies.name]` table
+
+    for line in manifest.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_end_matches(']').trim();
+            current = None;
+            in_table = header.ends_with("dependencies");
+            if let Some((table, name)) = header.rsplit_once('.') {
+                if table.ends_with("dependencies") {
+                    found.push(Dependency {
+                        name: name.trim_matches('"').to_string(),
+                        package: name.trim_matches('"').to_string(),
+                        path: None,
+                    });
+                    current = Some(found.len() - 1);
+                }
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        if let Some(index) = current {
+            match key {
+                "package" => found[index].package = unquote(value),
+                "path" => found[index].path = Some(unquote(value)),
+                _ => {}
+            }
+        } else if in_table && !key.is_empty() {
+            found.push(Dependency {
+                name: key.to_string(),
+                package: inline_field(value, "package").unwrap_or_else(|| key.to_string()),
+                path: inline_field(value, "path"),
+            });
+        }
+    }
+    found
+}
+
+/// Versions of `package` pinned in a Cargo.lock, newest first
+pub fn locked_versions(lockfile: &str, package: &str) -> Vec<String> {
+    let mut versions: Vec<String> = lockfile
+        .split("[[package]]")
+        .filter(|block| {
+            block
+                .lines()
+                .any(|line| line.trim() == format!("name = \"{}\"", package))
+        })
+        .filter_map(|block| {
+            block
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("version = "))
+                .map(unquote)
+        })
+        .collect();
+    versions.sort_by_key(|version| std::cmp::Reverse(version_key(version)));
+    versions
+}
+
+/// The file or directory `path` inside the dependency, refusing anything outside it
+pub fn resolve(source: &CrateSource, path: &str) -> AgentResult<PathBuf> {
+    let resolved = fs_guard::resolve(&source.root, Path::new(path), PathAccess::Read)?;
+    if !resolved.starts_with(source.root.canonicalize()?) {
+        return Err(AgentError::ToolExecution(format!(
+            "{} is outside the source of {}",
+            path,
+            source.label()
+        )));
+    }
+    Ok(resolved)
+}
+
+/// The dependency's manifest, readme and source files, relative to its root
+pub fn list_files(source: &CrateSource) -> Vec<String> {
+    source_files(&source.root)
+        .take(MAX_LISTED_FILES)
+        .map(|path| relative(&source.root, &path))
+        .collect()
+}
+
+/// Up to MAX_READ_LINES lines of a dependency file, from `offset`, with line numbers
+pub fn read(source: &CrateSource, path: &str, offset: usize) -> AgentResult<String> {
+    let (text, _) = text_file::read_text(&resolve(source, path)?)?;
+    let total = text.lines().count();
+    let mut content: String = text
+        .lines()
+        .enumerate()
+        .skip(offset)
+        .take(MAX_READ_LINES)
+        .map(|(i, line)| format!("{:>5} {}\n", i + 1, line))
+        .collect();
+    if offset + MAX_READ_LINES < total {
+        content.push_str(&format!(
+            "... {} more lines; read again from offset {}\n",
+            total - offset - MAX_READ_LINES,
+            offset + MAX_READ_LINES
+        ));
+    }
+    Ok(content)
+}
+
+/// Lines of the dependency's source files matching `regex`, as `path:line: text`
+pub fn search(source: &CrateSource, regex: &regex::Regex) -> Vec<String> {
+    source_files(&source.root)
+        .filter_map(|path| {
+            let (text, _) = text_file::read_text(&path).ok()?;
+            let file = relative(&source.root, &path);
+            Some(
+                text.lines()
+                    .enumerate()
+                    .filter(|(_, line)| regex.is_match(line))
+                    .map(|(i, line)| format!("{}:{}: {}", file, i + 1, line.trim()))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .take(MAX_SEARCH_MATCHES)
+        .collect()
+}
+
+/// Manifest, readme and Rust files under `root`, in a stable order
+fn source_files(root: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "target")
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.ends_with(".rs") || name == "Cargo.toml" || name.starts_with("README")
+        })
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    platform::display_path(path.strip_prefix(root).unwrap_or(path))
+}
+
+/// Unpacked registry sources, e.g. `~/.cargo/registry/src/index.crates.io-*`
+fn registry_dirs() -> Vec<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+    let Some(cargo_home) = cargo_home else {
+        return Vec::new();
+    };
+
+    let mut registries: Vec<PathBuf> = std::fs::read_dir(cargo_home.join("registry").join("src"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    registries.sort();
+    registries
+}
+
+/// Numeric parts of a version, for ordering `1.10.0` after `1.9.3`
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// Value of `field` in an inline table such as `{ version = "1", path = "../x" }`
+fn inline_field(value: &str, field: &str) -> Option<String> {
+    let inner = value.trim().strip_prefix('{')?.trim_end_matches('}');
+    inner.split(',').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key.trim() == field).then(|| unquote(value))
+    })
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .trim()
+        .trim_matches('"')
+        .trim_matches('\'')
+        .to_string()
+}
@@ -0,0 +1,324 @@
+use crate::error::{AgentError, AgentResult};
+use crate::memory::{MemoryFragment, MemoryMetadata, Provenance};
+use crate::platform;
+use crate::tool_env;
+use crate::utils;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Source of the fragment listing the diagnostics of the last check
+pub const DIAGNOSTICS_SOURCE: &str = "diagnostics";
+/// Characters of a diagnostic's message kept; rustc notes can run long
+const MAX_MESSAGE_CHARS: usize = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// One compiler or linter finding, located in a workspace file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>, // E0308, TS2345, no-unused-vars
+    pub message: String,
+    pub file: String, // relative to the workspace
+    pub line: usize,
+    pub column: usize,
+    pub suggestion: Option<String>, // replacement text the tool proposes for the span
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{} {}",
+            self.file, self.line, self.column, self.severity
+        )?;
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (suggested replacement: `{}`)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Diagnostics from `cargo build --message-format=json` output, one JSON object per line.
+/// Findings in dependencies and the summary lines are skipped.
+pub fn parse_cargo(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|event| event["reason"] == "compiler-message")
+        .filter_map(|event| {
+            let message = &event["message"];
+            let severity = match message["level"].as_str()? {
+                "error" => Severity::Error,
+                "warning" => Severity::Warning,
+                _ => return None,
+            };
+            let span = message["spans"]
+                .as_array()?
+                .iter()
+                .find(|span| span["is_primary"] == true)?;
+            let file = span["file_name"].as_str()?;
+            if Path::new(file).is_absolute() {
+                return None;
+            }
+
+            // Machine-applicable fixes sit on the children's spans
+            let suggestion = std::iter::once(span)
+                .chain(
+                    message["children"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .flat_map(|child| child["spans"].as_array().into_iter().flatten()),
+                )
+                .find_map(|span| span["suggested_replacement"].as_str())
+                .map(str::to_string);
+
+            Some(Diagnostic {
+                severity,
+                code: message["code"]["code"].as_str().map(str::to_string),
+                message: utils::truncate_string(message["message"].as_str()?, MAX_MESSAGE_CHARS),
+                file: file.to_string(),
+                line: span["line_start"].as_u64()? as usize,
+                column: span["column_start"].as_u64().unwrap_or(1) as usize,
+                suggestion,
+            })
+        })
+        .collect()
+}
+
+/// Diagnostics from `tsc --pretty false`, which has no JSON output: lines like
+/// `src/a.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.`
+pub fn parse_tsc(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (location, rest) = line.split_once("): ")?;
+            let (file, position) = location.rsplit_once('(')?;
+            let (line_number, column) = position.split_once(',')?;
+            let (kind, message) = rest.split_once(": ")?;
+            let (severity, code) = kind.split_once(' ')?;
+            let severity = match severity {
+                "error" => Severity::Error,
+                "warning" => Severity::Warning,
+                _ => return None,
+            };
+
+            Some(Diagnostic {
+                severity,
+                code: Some(code.to_string()),
+                message: utils::truncate_string(message, MAX_MESSAGE_CHARS),
+                file: file.trim().to_string(),
+                line: line_number.parse().ok()?,
+                column: column.parse().ok()?,
+                suggestion: None,
+            })
+        })
+        .collect()
+}
+
+/// Diagnostics from `eslint --format json`, with paths made relative to `root`
+pub fn parse_eslint(output: &str, root: &Path) -> Vec<Diagnostic> {
+    let Ok(Value::Array(files)) = serde_json::from_str::<Value>(output) else {
+        return Vec::new();
+    };
+
+    files
+        .iter()
+        .flat_map(|file| {
+            let path = file["filePath"].as_str().unwrap_or_default();
+            let relative = Path::new(path)
+                .strip_prefix(root)
+                .map(platform::display_path)
+                .unwrap_or_else(|_| path.to_string());
+            file["messages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(move |message| {
+                    let severity = match message["severity"].as_u64()? {
+                        2 => Severity::Error,
+                        1 => Severity::Warning,
+                        _ => return None,
+                    };
+                    let suggestion = message["fix"]["text"]
+                        .as_str()
+                        .or_else(|| message["suggestions"][0]["fix"]["text"].as_str())
+                        .map(str::to_string);
+
+                    Some(Diagnostic {
+                        severity,
+                        code: message["ruleId"].as_str().map(str::to_string),
+                        message: utils::truncate_string(
+                            message["message"].as_str()?,
+                            MAX_MESSAGE_CHARS,
+                        ),
+                        file: relative.clone(),
+                        line: message["line"].as_u64().unwrap_or(1) as usize,
+                        column: message["column"].as_u64().unwrap_or(1) as usize,
+                        suggestion,
+                    })
+                })
+        })
+        .collect()
+}
+
+/// A command reporting diagnostics, and how to read its output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Checker {
+    Cargo,
+    Tsc,
+    Eslint,
+}
+
+impl Checker {
+    fn command(&self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo build --all-targets --message-format=json",
+            Self::Tsc => "npx tsc --noEmit --pretty false",
+            Self::Eslint => "npx eslint --format json .",
+        }
+    }
+
+    fn parse(&self, output: &str, root: &Path) -> Vec<Diagnostic> {
+        match self {
+            Self::Cargo => parse_cargo(output),
+            Self::Tsc => parse_tsc(output),
+            Self::Eslint => parse_eslint(output, root),
+        }
+    }
+}
+
+/// The checkers that apply to the project at `root`
+fn checkers(root: &Path) -> Vec<Checker> {
+    let has = |name: &str| root.join(name).is_file();
+    if has("Cargo.toml") {
+        return vec![Checker::Cargo];
+    }
+
+    let mut checkers = Vec::new();
+    if has("tsconfig.json") {
+        checkers.push(Checker::Tsc);
+    }
+    let eslint_configs = [
+        "eslint.config.js",
+        "eslint.config.mjs",
+        "eslint.config.cjs",
+        ".eslintrc.js",
+        ".eslintrc.cjs",
+        ".eslintrc.json",
+        ".eslintrc.yml",
+        ".eslintrc",
+    ];
+    if eslint_configs.iter().any(|config| has(config)) {
+        checkers.push(Checker::Eslint);
+    }
+    checkers
+}
+
+/// Build or lint the project at `root` and collect its diagnostics, errors first then by
+/// file and line
+pub async fn collect(root: &Path) -> AgentResult<Vec<Diagnostic>> {
+    let checkers = checkers(root);
+    if checkers.is_empty() {
+        return Err(AgentError::ToolExecution(
+            "No cargo, tsc or eslint setup found to report diagnostics".to_string(),
+        ));
+    }
+
+    let mut diagnostics = Vec::new();
+    for checker in checkers {
+        // Both tools exit with a failure status when they report errors
+        let output = platform::shell_command(checker.command())
+            .current_dir(root)
+            .output()
+            .await
+            .map_err(|e| {
+                AgentError::ToolExecution(format!("Failed to run '{}': {}", checker.command(), e))
+            })?;
+        let stdout = tool_env::mask(&String::from_utf8_lossy(&output.stdout));
+        diagnostics.extend(checker.parse(&stdout, root));
+    }
+
+    diagnostics.sort_by(|a, b| {
+        (a.severity, &a.file, a.line, a.column).cmp(&(b.severity, &b.file, b.line, b.column))
+    });
+    diagnostics.dedup();
+    Ok(diagnostics)
+}
+
+/// Diagnostics grouped by file, in file order
+pub fn by_file(diagnostics: &[Diagnostic]) -> BTreeMap<&str, Vec<&Diagnostic>> {
+    let mut files: BTreeMap<&str, Vec<&Diagnostic>> = BTreeMap::new();
+    for diagnostic in diagnostics {
+        files.entry(&diagnostic.file).or_default().push(diagnostic);
+    }
+    files
+}
+
+/// Context fragment listing `diagnostics`, one per line
+pub fn fragment(diagnostics: &[Diagnostic]) -> MemoryFragment {
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let listing = diagnostics
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    MemoryFragment {
+        source: DIAGNOSTICS_SOURCE.to_string(),
+        provenance: Provenance::ToolOutput,
+        content: format!(
+            "{} errors and {} warnings:\n{}",
+            errors,
+            diagnostics.len() - errors,
+            listing
+        ),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("diagnostics".to_string()),
+            path: None,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            tags: by_file(diagnostics)
+                .keys()
+                .map(|file| format!("file:{}", file))
+                .collect(),
+        }),
+    }
+}
+
+/// A task fixing the diagnostics of one file, with the locations and suggestions spelled out
+/// so the planner edits in place instead of searching
+pub fn fix_task(file: &str, diagnostics: &[&Diagnostic]) -> String {
+    let listing = diagnostics
+        .iter()
+        .map(|d| format!("- {}", d))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Fix these diagnostics in {}. Read the file around each line, then edit only {}; \
+        apply a suggested replacement when it is right.\n{}",
+        file, file, listing
+    )
+}
@@ -0,0 +1,132 @@
+use crate::error::{AgentError, AgentResult};
+use crate::git;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Message on stashes the agent makes, so they are recognisable in `git stash list`
+const STASH_MESSAGE: &str = "indiefuture: auto-stash before agent edits";
+
+/// What to do when the working tree has uncommitted changes before the agent edits it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirtyTreePolicy {
+    /// Don't check
+    Off,
+    /// Go ahead, but list the uncommitted files first
+    #[default]
+    Warn,
+    /// Refuse to run edits and commands until the tree is clean
+    Block,
+    /// Stash the changes for the turn and restore them afterwards
+    Stash,
+}
+
+impl DirtyTreePolicy {
+    pub const ALL: [DirtyTreePolicy; 4] = [
+        DirtyTreePolicy::Off,
+        DirtyTreePolicy::Warn,
+        DirtyTreePolicy::Block,
+        DirtyTreePolicy::Stash,
+    ];
+}
+
+impl fmt::Display for DirtyTreePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::Warn => "warn",
+            Self::Block => "block",
+            Self::Stash => "stash",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DirtyTreePolicy {
+    type Err = AgentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.to_string() == s.trim())
+            .ok_or_else(|| {
+                AgentError::Configuration(format!(
+                    "Unknown dirty tree policy '{}', expected one of: {}",
+                    s,
+                    Self::ALL
+                        .iter()
+                        .map(|policy| policy.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+/// Uncommitted changes the agent stashed, restored once its turn is over
+#[derive(Debug, Clone)]
+pub struct AutoStash {
+    root: PathBuf,
+    commit: String, // the stash commit, so a stash made in between is never popped instead
+}
+
+impl AutoStash {
+    /// Stash tracked and untracked changes; None when there was nothing to stash
+    pub fn push(root: &Path) -> AgentResult<Option<Self>> {
+        let before = latest_stash(root);
+        git::run_git(
+            root,
+            &["stash", "push", "--include-untracked", "-m", STASH_MESSAGE],
+        )?;
+
+        Ok(latest_stash(root)
+            .filter(|commit| Some(commit) != before.as_ref())
+            .map(|commit| Self {
+                root: root.to_path_buf(),
+                commit,
+            }))
+    }
+
+    /// Pop the stash back onto the working tree. On a conflict git keeps the stash, so
+    /// nothing is lost and the user can resolve it with `git stash pop` later.
+    pub fn restore(&self) -> AgentResult<()> {
+        let entry = self.entry()?;
+        git::run_git(&self.root, &["stash", "pop", &entry])?;
+        Ok(())
+    }
+
+    /// The `stash@{n}` name of this stash right now
+    pub fn entry(&self) -> AgentResult<String> {
+        let stashes = git::run_git(&self.root, &["stash", "list", "--format=%H"])?;
+        stashes
+            .lines()
+            .position(|commit| commit == self.commit)
+            .map(|index| format!("stash@{{{}}}", index))
+            .ok_or_else(|| {
+                AgentError::ToolExecution(format!(
+                    "The agent's stash {} is no longer in the stash list",
+                    self.commit
+                ))
+            })
+    }
+}
+
+/// Uncommitted files in the working tree, including untracked ones. Errors outside a git
+/// repository.
+pub fn uncommitted_files(root: &Path) -> AgentResult<Vec<String>> {
+    let status = git::run_git(root, &["status", "--porcelain", "--untracked-files=all"])?;
+
+    // Each line is a status code and the path; the output is trimmed, so the code's
+    // leading space may be gone from the first line
+    Ok(status
+        .lines()
+        .filter_map(|line| line.trim_start().split_once(' '))
+        .map(|(_, path)| path.trim_start().to_string())
+        .collect())
+}
+
+fn latest_stash(root: &Path) -> Option<String> {
+    git::run_git(root, &["rev-parse", "--quiet", "--verify", "refs/stash"])
+        .ok()
+        .filter(|commit| !commit.is_empty())
+}
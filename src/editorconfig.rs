@@ -0,0 +1,178 @@
+use ec4rs::property::{Charset, FinalNewline, IndentSize, IndentStyle, TabWidth};
+use std::path::Path;
+
+const BOM: char = '\u{feff}';
+
+/// How a file's `.editorconfig` sections say it should be indented and terminated
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorStyle {
+    pub indent: Option<Indent>,
+    pub final_newline: Option<bool>,
+    pub bom: bool, // charset = utf-8-bom
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Tabs { width: usize },
+    Spaces(usize),
+}
+
+impl EditorStyle {
+    /// Style for `path` from the `.editorconfig` files above it; empty when there are none
+    pub fn for_path(path: &Path) -> Self {
+        let Ok(mut properties) = ec4rs::properties_of(path) else {
+            return Self::default();
+        };
+        properties.use_fallbacks();
+
+        let tab_width = match properties.get::<TabWidth>() {
+            Ok(TabWidth::Value(width)) if width > 0 => Some(width),
+            _ => None,
+        };
+        let indent_size = match properties.get::<IndentSize>() {
+            Ok(IndentSize::Value(size)) if size > 0 => Some(size),
+            Ok(IndentSize::UseTabWidth) => tab_width,
+            _ => None,
+        };
+
+        let indent = match properties.get::<IndentStyle>() {
+            Ok(IndentStyle::Tabs) => Some(Indent::Tabs {
+                width: tab_width.or(indent_size).unwrap_or(4),
+            }),
+            Ok(IndentStyle::Spaces) => indent_size.map(Indent::Spaces),
+            Err(_) => None,
+        };
+
+        Self {
+            indent,
+            final_newline: match properties.get::<FinalNewline>() {
+                Ok(FinalNewline::Value(value)) => Some(value),
+                Err(_) => None,
+            },
+            bom: properties.get::<Charset>() == Ok(Charset::Utf8Bom),
+        }
+    }
+
+    /// Re-indent a generated block to the configured style. Indentation that already fits
+    /// is left alone, since a block inserted at depth may legitimately start indented.
+    pub fn indent_block(&self, text: &str) -> String {
+        let Some(indent) = self.indent else {
+            return text.to_string();
+        };
+
+        let widths: Vec<usize> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| leading(line).len())
+            .filter(|&width| width > 0)
+            .collect();
+        let uses_tabs = text.lines().any(|line| leading(line).starts_with('\t'));
+
+        // Width of one indent level as the model wrote it, when it must change
+        let unit = match indent {
+            Indent::Spaces(size) if uses_tabs => Some(Unit::Tab(size)),
+            Indent::Spaces(size) => {
+                let step = widths.iter().copied().reduce(gcd).unwrap_or(size);
+                (step % size != 0).then_some(Unit::Spaces(step, size))
+            }
+            Indent::Tabs { width } => {
+                let space_indented = text.lines().any(|line| leading(line).starts_with(' '));
+                // Prefer the configured width, so a lone deep line is not read as one level
+                let step = if widths.iter().all(|w| w % width == 0) {
+                    width
+                } else {
+                    widths.iter().copied().reduce(gcd).unwrap_or(width)
+                };
+                space_indented.then_some(Unit::ToTabs(step))
+            }
+        };
+        let Some(unit) = unit else {
+            return text.to_string();
+        };
+
+        let mut block = text
+            .lines()
+            .map(|line| {
+                let prefix = leading(line);
+                let rest = &line[prefix.len()..];
+                if rest.is_empty() {
+                    return String::new();
+                }
+                let width: usize = prefix
+                    .chars()
+                    .map(|c| if c == '\t' { unit.tab_width() } else { 1 })
+                    .sum();
+                format!("{}{}", unit.render(width), rest)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.ends_with('\n') {
+            block.push('\n');
+        }
+        block
+    }
+
+    /// Apply the style to the full content of a file being written from scratch
+    pub fn whole_file(&self, content: &str) -> String {
+        let mut content = self.indent_block(content.trim_start_matches(BOM));
+
+        match self.final_newline {
+            Some(true) if !content.is_empty() && !content.ends_with('\n') => content.push('\n'),
+            Some(false) => content.truncate(content.trim_end_matches('\n').len()),
+            _ => {}
+        }
+
+        if self.bom {
+            content.insert(0, BOM);
+        }
+        content
+    }
+}
+
+/// How to turn a line's indentation width, as written, into the configured style
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    /// Tabs written where spaces of this size are configured
+    Tab(usize),
+    /// Spaces in steps of `.0` where steps of `.1` are configured
+    Spaces(usize, usize),
+    /// Spaces in steps of `.0` where tabs are configured
+    ToTabs(usize),
+}
+
+impl Unit {
+    fn tab_width(&self) -> usize {
+        match self {
+            Self::Tab(size) => *size,
+            Self::Spaces(step, _) | Self::ToTabs(step) => *step,
+        }
+    }
+
+    fn render(&self, width: usize) -> String {
+        match *self {
+            Self::Tab(_) => " ".repeat(width),
+            Self::Spaces(step, size) => {
+                format!(
+                    "{}{}",
+                    " ".repeat(width / step * size),
+                    " ".repeat(width % step)
+                )
+            }
+            Self::ToTabs(step) => {
+                format!("{}{}", "\t".repeat(width / step), " ".repeat(width % step))
+            }
+        }
+    }
+}
+
+fn leading(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
@@ -0,0 +1,439 @@
+use crate::editorconfig::EditorStyle;
+use crate::error::{AgentError, AgentResult};
+use crate::fs_guard::{self, PathAccess};
+use crate::text_file::{self, TextFormat};
+use serde::{Deserialize, Serialize};
+use similar::{DiffTag, TextDiff};
+use std::path::{Path, PathBuf};
+
+/// Where new text goes relative to existing file content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InsertPosition {
+    /// On the lines following the line containing the anchor
+    After(String),
+    /// On the lines preceding the line containing the anchor
+    Before(String),
+    /// At the end of the file
+    End,
+}
+
+/// Insert `text` as whole lines at `position`, returning the new content.
+/// Anchors must match exactly once so the insertion point is unambiguous.
+pub fn apply_insert(content: &str, position: &InsertPosition, text: &str) -> AgentResult<String> {
+    let text = text.trim_end_matches('\n');
+
+    let at = match position {
+        InsertPosition::End => {
+            let mut updated = content.to_string();
+            if !updated.is_empty() && !updated.ends_with('\n') {
+                updated.push('\n');
+            }
+            updated.push_str(text);
+            updated.push('\n');
+            return Ok(updated);
+        }
+        InsertPosition::After(anchor) => {
+            let start = find_unique(content, anchor)?;
+            let anchor_end = start + anchor.len();
+            // A multi-line anchor may already end at a line break
+            let search_from = if anchor.ends_with('\n') {
+                anchor_end - 1
+            } else {
+                anchor_end
+            };
+            content[search_from..]
+                .find('\n')
+                .map(|offset| search_from + offset + 1)
+                .unwrap_or(content.len())
+        }
+        InsertPosition::Before(anchor) => {
+            let start = find_unique(content, anchor)?;
+            content[..start].rfind('\n').map(|i| i + 1).unwrap_or(0)
+        }
+    };
+
+    let (head, tail) = content.split_at(at);
+    let separator = if head.is_empty() || head.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+
+    Ok(format!("{}{}{}\n{}", head, separator, text, tail))
+}
+
+/// Byte offset of the single occurrence of `anchor` in `content`
+fn find_unique(content: &str, anchor: &str) -> AgentResult<usize> {
+    if anchor.is_empty() {
+        return Err(AgentError::ToolExecution(
+            "Anchor text is empty".to_string(),
+        ));
+    }
+
+    let mut matches = content.match_indices(anchor);
+
+    match (matches.next(), matches.next()) {
+        (Some((start, _)), None) => Ok(start),
+        (None, _) => Err(AgentError::ToolExecution(format!(
+            "Text not found in file: {}",
+            anchor
+        ))),
+        (Some(_), Some(_)) => Err(AgentError::ToolExecution(format!(
+            "Text appears {} times, provide more context to make it unique: {}",
+            content.matches(anchor).count(),
+            anchor
+        ))),
+    }
+}
+
+/// A single change to one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FileEdit {
+    /// Replace the only occurrence of `old_string`; an empty `old_string` writes a new file
+    Replace {
+        old_string: String,
+        new_string: String,
+    },
+    /// Insert lines without replacing anything
+    Insert {
+        position: InsertPosition,
+        content: String,
+    },
+}
+
+impl FileEdit {
+    /// The edit with its new text following the file's `.editorconfig` style
+    pub fn conformed(&self, style: &EditorStyle) -> FileEdit {
+        match self {
+            Self::Replace {
+                old_string,
+                new_string,
+            } => Self::Replace {
+                old_string: old_string.clone(),
+                new_string: if old_string.is_empty() {
+                    style.whole_file(new_string)
+                } else {
+                    style.indent_block(new_string)
+                },
+            },
+            Self::Insert { position, content } => Self::Insert {
+                position: position.clone(),
+                content: style.indent_block(content),
+            },
+        }
+    }
+
+    fn apply(&self, content: &str) -> AgentResult<String> {
+        match self {
+            Self::Replace {
+                old_string,
+                new_string,
+            } => {
+                if old_string.is_empty() {
+                    return Ok(new_string.clone());
+                }
+                find_unique(content, old_string)?;
+                Ok(content.replacen(old_string.as_str(), new_string, 1))
+            }
+            Self::Insert {
+                position,
+                content: text,
+            } => apply_insert(content, position, text),
+        }
+    }
+}
+
+/// The before and after state of one file touched by an edit set
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub path: PathBuf,            // as staged, for diffs and messages
+    pub resolved: PathBuf,        // where the file actually is, for reading and writing
+    pub original: Option<String>, // None when the file is being created
+    pub updated: String,
+    pub format: TextFormat, // line endings and encoding to write back
+}
+
+impl PlannedFile {
+    /// Unified diff of the planned change
+    pub fn diff(&self) -> String {
+        let name = self.path.display().to_string();
+        TextDiff::from_lines(self.original.as_deref().unwrap_or_default(), &self.updated)
+            .unified_diff()
+            .context_radius(3)
+            .header(&name, &name)
+            .to_string()
+    }
+
+    /// The planned change split into hunks of nearby changes, as `git add -p` shows them
+    pub fn hunks(&self) -> Vec<Hunk> {
+        let diff =
+            TextDiff::from_lines(self.original.as_deref().unwrap_or_default(), &self.updated);
+        let old_lines = diff.old_slices();
+        let new_lines = diff.new_slices();
+
+        diff.grouped_ops(3)
+            .iter()
+            .map(|group| Hunk {
+                changes: group
+                    .iter()
+                    .filter(|op| op.tag() != DiffTag::Equal)
+                    .map(|op| LineChange {
+                        old_start: op.old_range().start,
+                        old_lines: to_owned_lines(&old_lines[op.old_range()]),
+                        new_lines: to_owned_lines(&new_lines[op.new_range()]),
+                    })
+                    .collect(),
+            })
+            .filter(|hunk| !hunk.changes.is_empty())
+            .collect()
+    }
+}
+
+fn to_owned_lines(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+/// Consecutive lines of the original replaced by new ones; lines keep their line breaks
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineChange {
+    pub old_start: usize, // index of the first replaced line in the original
+    pub old_lines: Vec<String>,
+    pub new_lines: Vec<String>,
+}
+
+impl LineChange {
+    fn old_end(&self) -> usize {
+        self.old_start + self.old_lines.len()
+    }
+}
+
+/// Nearby changes to one file that are reviewed together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    pub changes: Vec<LineChange>,
+}
+
+impl Hunk {
+    /// One hunk per change, for hunks holding changes separated by unchanged lines
+    pub fn split(&self) -> Vec<Hunk> {
+        self.changes
+            .iter()
+            .map(|change| Hunk {
+                changes: vec![change.clone()],
+            })
+            .collect()
+    }
+
+    /// The hunk as a single change spanning the unchanged lines between its changes,
+    /// so its new text can be edited as one block
+    pub fn merged(&self, original: &str) -> Option<LineChange> {
+        let first = self.changes.first()?;
+        let last = self.changes.last()?;
+        let lines: Vec<&str> = original.split_inclusive('\n').collect();
+
+        let mut new_lines = Vec::new();
+        let mut cursor = first.old_start;
+        for change in &self.changes {
+            new_lines.extend(to_owned_lines(&lines[cursor..change.old_start]));
+            new_lines.extend(change.new_lines.iter().cloned());
+            cursor = change.old_end();
+        }
+
+        Some(LineChange {
+            old_start: first.old_start,
+            old_lines: to_owned_lines(&lines[first.old_start..last.old_end()]),
+            new_lines,
+        })
+    }
+
+    /// The hunk in unified diff style, with up to three unchanged lines around it
+    pub fn render(&self, original: &str) -> String {
+        let lines: Vec<&str> = original.split_inclusive('\n').collect();
+        let (Some(first), Some(last)) = (self.changes.first(), self.changes.last()) else {
+            return String::new();
+        };
+
+        let mut rendered = String::new();
+        let mut push = |prefix: char, line: &str| {
+            rendered.push(prefix);
+            rendered.push_str(line.trim_end_matches(['\r', '\n']));
+            rendered.push('\n');
+        };
+
+        let mut cursor = first.old_start.saturating_sub(3);
+        for change in &self.changes {
+            for line in &lines[cursor..change.old_start] {
+                push(' ', line);
+            }
+            for line in &change.old_lines {
+                push('-', line);
+            }
+            for line in &change.new_lines {
+                push('+', line);
+            }
+            cursor = change.old_end();
+        }
+        for line in &lines[cursor..(last.old_end() + 3).min(lines.len())] {
+            push(' ', line);
+        }
+
+        rendered
+    }
+}
+
+/// Apply only `changes` to `original`; changes must not overlap
+pub fn apply_changes(original: &str, changes: &[LineChange]) -> String {
+    let lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let mut changes: Vec<&LineChange> = changes.iter().collect();
+    changes.sort_by_key(|change| change.old_start);
+
+    let mut updated = String::new();
+    let mut cursor = 0;
+    for change in changes {
+        updated.extend(lines[cursor..change.old_start].iter().copied());
+        updated.extend(change.new_lines.iter().map(String::as_str));
+        cursor = change.old_end();
+    }
+    updated.extend(lines[cursor..].iter().copied());
+
+    updated
+}
+
+/// Edits to several files that are reviewed together and land all-or-nothing
+#[derive(Debug, Clone)]
+pub struct EditSet {
+    root: PathBuf, // workspace the staged paths are relative to
+    edits: Vec<(PathBuf, FileEdit)>,
+}
+
+impl EditSet {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            edits: Vec::new(),
+        }
+    }
+
+    /// Stage an edit; edits to the same file apply in the order they were staged
+    pub fn stage(&mut self, path: impl Into<PathBuf>, edit: FileEdit) {
+        self.edits.push((path.into(), edit));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Compute the final content of every touched file without writing anything.
+    /// Fails if any edit does not apply, so nothing is written for an invalid set.
+    pub fn plan(&self) -> AgentResult<Vec<PlannedFile>> {
+        let mut planned: Vec<PlannedFile> = Vec::new();
+
+        for (path, edit) in &self.edits {
+            let resolved = fs_guard::resolve(&self.root, path, PathAccess::Write)?;
+
+            let index = match planned.iter().position(|p| p.resolved == resolved) {
+                Some(index) => index,
+                None => {
+                    let (original, format) = if resolved.exists() {
+                        let (text, format) = text_file::read_text(&resolved)?;
+                        (Some(text), format)
+                    } else {
+                        (None, TextFormat::default())
+                    };
+                    planned.push(PlannedFile {
+                        path: path.clone(),
+                        resolved,
+                        updated: original.clone().unwrap_or_default(),
+                        original,
+                        format,
+                    });
+                    planned.len() - 1
+                }
+            };
+
+            let file = &mut planned[index];
+            if file.original.is_none() && file.updated.is_empty() {
+                if let FileEdit::Replace { old_string, .. } = edit {
+                    if !old_string.is_empty() {
+                        return Err(AgentError::ToolExecution(format!(
+                            "File does not exist: {}",
+                            path.display()
+                        )));
+                    }
+                }
+            }
+
+            let edit = edit.conformed(&EditorStyle::for_path(&file.resolved));
+            file.updated = edit.apply(&file.updated).map_err(|e| match e {
+                AgentError::ToolExecution(message) => {
+                    AgentError::ToolExecution(format!("{}: {}", path.display(), message))
+                }
+                other => other,
+            })?;
+        }
+
+        Ok(planned)
+    }
+
+    /// Combined diff of every file in the set, for review before applying
+    pub fn review(&self) -> AgentResult<String> {
+        let planned = self.plan()?;
+
+        let mut review = format!(
+            "{} edits across {} files\n\n",
+            self.edits.len(),
+            planned.len()
+        );
+        for file in &planned {
+            review.push_str(&file.diff());
+            review.push('\n');
+        }
+
+        Ok(review)
+    }
+
+    /// Write every file, restoring all of them if any write fails
+    pub fn apply(&self) -> AgentResult<Vec<PlannedFile>> {
+        let planned = self.plan()?;
+        let mut written: Vec<&PlannedFile> = Vec::new();
+
+        for file in &planned {
+            if let Err(e) = file.format.write(&file.resolved, &file.updated) {
+                let rollback_errors = Self::rollback(&written);
+
+                return Err(AgentError::ToolExecution(format!(
+                    "Failed to write {}: {}. Rolled back {} files{}",
+                    file.path.display(),
+                    e,
+                    written.len(),
+                    if rollback_errors.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (rollback failed for: {})", rollback_errors.join(", "))
+                    }
+                )));
+            }
+            written.push(file);
+        }
+
+        Ok(planned)
+    }
+
+    /// Restore original contents, returning the files that could not be restored
+    fn rollback(written: &[&PlannedFile]) -> Vec<String> {
+        written
+            .iter()
+            .filter_map(|file| {
+                let result = match &file.original {
+                    Some(original) => file.format.write(&file.resolved, original),
+                    None => std::fs::remove_file(&file.resolved).map_err(Into::into),
+                };
+                result.err().map(|_| file.path.display().to_string())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,462 @@
+use crate::ai::{self, AiClient};
+use crate::chunking;
+use crate::config::Settings;
+use crate::error::{AgentError, AgentResult};
+use crate::fs_guard::{self, PathAccess};
+use crate::journal;
+use crate::memory::{self, MemoryFragment, MemoryMetadata, Provenance};
+use crate::storage::{Storage, StorageTree};
+use crate::text_file;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// Chunks longer than this are cut before embedding to stay within the model's input limit
+const MAX_CHUNK_CHARS: usize = 8000;
+
+/// Chunk embeddings are kept per embedding model, in trees named after it
+const INDEX_TREE: &str = "file_embeddings";
+
+/// Headers of the project's indexes, by embedding model
+const HEADERS_TREE: &str = "embedding_indexes";
+
+/// The model of an index built before models were recorded; it cannot be queried
+pub const UNRECORDED_MODEL: &str = "unrecorded";
+
+/// Damps the weight of top ranks when merging the hits of several indexes
+const RANK_OFFSET: f32 = 60.0;
+
+/// The embedding of one chunk of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkEmbedding {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub symbol: Option<String>,
+    pub embedding: Vec<f32>,
+}
+
+/// Embeddings of a file's chunks, as of the content with `content_hash`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEmbeddings {
+    pub path: String,
+    pub content_hash: String,
+    pub chunks: Vec<ChunkEmbedding>,
+    pub updated_at: String,
+}
+
+/// What an index was built with; vectors are only comparable within one index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexHeader {
+    pub embedding_model: String, // as `provider:model`
+    pub dimensions: usize,
+    pub created_at: String,
+}
+
+/// A chunk similar to a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub symbol: Option<String>,
+    pub similarity: f32,         // cosine similarity within its own index
+    pub embedding_model: String, // the index it came from
+}
+
+/// What to do when the workspace was indexed with another embedding model than the current one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MigrationPolicy {
+    /// Ask at the start of an interactive session
+    #[default]
+    Ask,
+    /// Re-embed the files of the old index with the current model in the background
+    Reembed,
+    /// Keep the old index and query it with its own model alongside the new one
+    Dual,
+}
+
+impl MigrationPolicy {
+    pub const ALL: [MigrationPolicy; 3] = [
+        MigrationPolicy::Ask,
+        MigrationPolicy::Reembed,
+        MigrationPolicy::Dual,
+    ];
+}
+
+impl fmt::Display for MigrationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Ask => "ask",
+            Self::Reembed => "reembed",
+            Self::Dual => "dual",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MigrationPolicy {
+    type Err = AgentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.to_string() == s.trim())
+            .ok_or_else(|| {
+                AgentError::Configuration(format!(
+                    "Unknown embedding migration policy '{}', expected one of: {}",
+                    s,
+                    Self::ALL
+                        .iter()
+                        .map(|policy| policy.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+/// Clients embedding queries for the older indexes kept under the dual policy
+static PREVIOUS_EMBEDDERS: RwLock<Option<Vec<Arc<dyn AiClient>>>> = RwLock::new(None);
+
+pub fn configure_previous_embedders(embedders: Vec<Arc<dyn AiClient>>) {
+    *PREVIOUS_EMBEDDERS
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = Some(embedders);
+}
+
+fn previous_embedders() -> Vec<Arc<dyn AiClient>> {
+    PREVIOUS_EMBEDDERS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// A client embedding with `embedding_model`, to query an older index with, if its provider
+/// can still be reached with the configured keys
+pub fn embedder_for(embedding_model: &str, settings: &Settings) -> Option<Box<dyn AiClient>> {
+    let (provider, _) = embedding_model.split_once(':')?;
+    let client = ai::create_ai_client(
+        provider,
+        &settings.default_model,
+        settings.api_key_for(provider),
+    )
+    .ok()?;
+    (client.embedding_model() == embedding_model).then_some(client)
+}
+
+fn index_tree(storage: &Storage, project_id: &str, embedding_model: &str) -> StorageTree {
+    if embedding_model == UNRECORDED_MODEL {
+        storage.workspace_tree(INDEX_TREE, project_id)
+    } else {
+        storage.workspace_tree(&format!("{}/{}", INDEX_TREE, embedding_model), project_id)
+    }
+}
+
+/// Per-project index of file chunk embeddings by one embedding model, kept current in the
+/// background and shared by every checkout of the project
+pub struct EmbeddingIndex {
+    storage: Storage,
+    project_id: String,
+    embedding_model: String,
+    tree: StorageTree,
+    headers: StorageTree,
+}
+
+impl EmbeddingIndex {
+    pub fn open(storage: &Storage, project_id: &str, embedding_model: &str) -> Self {
+        Self {
+            storage: storage.clone(),
+            project_id: project_id.to_string(),
+            embedding_model: embedding_model.to_string(),
+            tree: index_tree(storage, project_id, embedding_model),
+            headers: storage.workspace_tree(HEADERS_TREE, project_id),
+        }
+    }
+
+    pub fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    /// The header of this index, once it holds an embedding
+    pub async fn header(&self) -> AgentResult<Option<IndexHeader>> {
+        self.headers.get(&self.embedding_model).await
+    }
+
+    pub async fn get(&self, path: &str) -> AgentResult<Option<FileEmbeddings>> {
+        self.tree.get(path).await
+    }
+
+    pub async fn all(&self) -> AgentResult<Vec<FileEmbeddings>> {
+        self.tree.values().await
+    }
+
+    /// This project's indexes built with other embedding models, including one from before
+    /// models were recorded
+    pub async fn other_indexes(&self) -> AgentResult<Vec<IndexHeader>> {
+        let mut others: Vec<IndexHeader> = self
+            .headers
+            .values::<IndexHeader>()
+            .await?
+            .into_iter()
+            .filter(|header| header.embedding_model != self.embedding_model)
+            .collect();
+
+        let unrecorded = index_tree(&self.storage, &self.project_id, UNRECORDED_MODEL)
+            .values::<FileEmbeddings>()
+            .await?;
+        if let Some(file) = unrecorded.iter().find(|file| !file.chunks.is_empty()) {
+            others.push(IndexHeader {
+                embedding_model: UNRECORDED_MODEL.to_string(),
+                dimensions: file.chunks[0].embedding.len(),
+                created_at: file.updated_at.clone(),
+            });
+        }
+        Ok(others)
+    }
+
+    /// The files in this project's index for `embedding_model`
+    pub async fn indexed_paths(&self, embedding_model: &str) -> AgentResult<Vec<String>> {
+        Ok(index_tree(&self.storage, &self.project_id, embedding_model)
+            .values::<FileEmbeddings>()
+            .await?
+            .into_iter()
+            .map(|file| file.path)
+            .collect())
+    }
+
+    /// Delete this project's index for `embedding_model`, returning how many files it held
+    pub async fn drop_index(&self, embedding_model: &str) -> AgentResult<usize> {
+        let tree = index_tree(&self.storage, &self.project_id, embedding_model);
+        let paths = self.indexed_paths(embedding_model).await?;
+        for path in &paths {
+            tree.remove(path).await?;
+        }
+        self.headers.remove(embedding_model).await?;
+        Ok(paths.len())
+    }
+
+    /// Record the dimensions of this index's vectors, refusing vectors of other dimensions,
+    /// which a model swapped behind the same name would produce
+    async fn check_dimensions(&self, dimensions: usize) -> AgentResult<()> {
+        match self.header().await? {
+            Some(header) if header.dimensions != dimensions => Err(AgentError::Embedding(format!(
+                "{} returned {}-dimensional embeddings, but its index holds {}-dimensional ones; \
+                 re-embed the workspace",
+                self.embedding_model, dimensions, header.dimensions
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                let header = IndexHeader {
+                    embedding_model: self.embedding_model.clone(),
+                    dimensions,
+                    created_at: utils::current_timestamp(),
+                };
+                self.headers.put(&self.embedding_model, &header).await
+            }
+        }
+    }
+
+    /// Re-embed `path` if its content changed since it was last embedded, dropping it from
+    /// the index once the file is gone. Returns whether the index changed.
+    pub async fn refresh(
+        &self,
+        ai_client: &dyn AiClient,
+        workspace_root: &Path,
+        path: &str,
+    ) -> AgentResult<bool> {
+        let embedding_model = ai_client.embedding_model();
+        if embedding_model != self.embedding_model {
+            return Err(AgentError::Embedding(format!(
+                "Cannot add {} embeddings to the {} index",
+                embedding_model, self.embedding_model
+            )));
+        }
+
+        let full_path = workspace_root.join(path);
+        let Ok(content) = std::fs::read_to_string(&full_path) else {
+            return self.tree.remove(path).await;
+        };
+
+        let content_hash = journal::hash_change(&content);
+        if let Some(existing) = self.get(path).await? {
+            if existing.content_hash == content_hash {
+                return Ok(false);
+            }
+        }
+
+        let mut chunks = Vec::new();
+        for chunk in chunking::chunk_source(&full_path, &content) {
+            if chunk.content.trim().is_empty() {
+                continue;
+            }
+
+            let input = utils::truncate_string(&chunk.content, MAX_CHUNK_CHARS);
+            chunks.push(ChunkEmbedding {
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                symbol: chunk.symbol,
+                embedding: ai_client.generate_embeddings(&input).await?,
+            });
+        }
+        if let Some(chunk) = chunks.first() {
+            self.check_dimensions(chunk.embedding.len()).await?;
+        }
+
+        let entry = FileEmbeddings {
+            path: path.to_string(),
+            content_hash,
+            chunks,
+            updated_at: utils::current_timestamp(),
+        };
+        self.tree.put(path, &entry).await?;
+        Ok(true)
+    }
+
+    /// The `limit` chunks of this index most similar to `query`, embedded by `ai_client`
+    async fn search_index(
+        &self,
+        ai_client: &dyn AiClient,
+        query: &str,
+        limit: usize,
+    ) -> AgentResult<Vec<SearchHit>> {
+        let query = ai_client.generate_embeddings(query).await?;
+        let mut hits: Vec<SearchHit> = self
+            .all()
+            .await?
+            .into_iter()
+            .flat_map(|file| {
+                let query = &query;
+                file.chunks.into_iter().filter_map(move |chunk| {
+                    // Vectors of other dimensions come from another model and mean nothing here
+                    (chunk.embedding.len() == query.len()).then(|| SearchHit {
+                        path: file.path.clone(),
+                        start_line: chunk.start_line,
+                        end_line: chunk.end_line,
+                        symbol: chunk.symbol,
+                        similarity: cosine_similarity(query, &chunk.embedding),
+                        embedding_model: self.embedding_model.clone(),
+                    })
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    /// The `limit` chunks most similar to `query`. Older indexes kept under the dual policy
+    /// are searched too, each with the query embedded by its own model; their hits are merged
+    /// by rank, since similarities from different models cannot be compared.
+    pub async fn search(
+        &self,
+        ai_client: &dyn AiClient,
+        query: &str,
+        limit: usize,
+    ) -> AgentResult<Vec<SearchHit>> {
+        let mut ranked = vec![self.search_index(ai_client, query, limit).await?];
+
+        let others = self.other_indexes().await?;
+        for embedder in previous_embedders() {
+            let model = embedder.embedding_model();
+            if !others.iter().any(|header| header.embedding_model == model) {
+                continue;
+            }
+            let index = EmbeddingIndex::open(&self.storage, &self.project_id, &model);
+            match index.search_index(embedder.as_ref(), query, limit).await {
+                Ok(hits) => ranked.push(hits),
+                Err(e) => log::warn!("Failed to search the {} index: {}", model, e),
+            }
+        }
+
+        Ok(merge_by_rank(ranked, limit))
+    }
+}
+
+/// Source of the fragments semantic search adds
+pub const SEMANTIC_SEARCH_SOURCE: &str = "semantic_search";
+
+/// A fragment holding the lines of `hit`, cited by path, line range and similarity so a
+/// later read can ask for exactly that window. `None` once the file or those lines are gone.
+pub fn hit_fragment(workspace_root: &Path, hit: &SearchHit) -> Option<MemoryFragment> {
+    let path = fs_guard::resolve(workspace_root, Path::new(&hit.path), PathAccess::Read).ok()?;
+    let (text, _) = text_file::read_text(&path).ok()?;
+    let excerpt: Vec<&str> = text
+        .lines()
+        .skip(hit.start_line.saturating_sub(1))
+        .take(hit.end_line + 1 - hit.start_line)
+        .collect();
+    if excerpt.is_empty() {
+        return None;
+    }
+
+    let end_line = hit.start_line + excerpt.len() - 1;
+    let symbol = hit
+        .symbol
+        .as_deref()
+        .map(|symbol| format!(", in {}", symbol))
+        .unwrap_or_default();
+    Some(MemoryFragment {
+        source: SEMANTIC_SEARCH_SOURCE.to_string(),
+        provenance: Provenance::FileRead,
+        content: format!(
+            "{}:{}-{} (similarity {:.2}{}; FileReadTool offset {} limit {} reads this window)\n{}",
+            hit.path,
+            hit.start_line,
+            end_line,
+            hit.similarity,
+            symbol,
+            hit.start_line - 1,
+            excerpt.len(),
+            excerpt.join("\n")
+        ),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("code_chunk".to_string()),
+            path: Some(hit.path.clone()),
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            tags: vec![
+                SEMANTIC_SEARCH_SOURCE.to_string(),
+                memory::lines_tag(hit.start_line, end_line),
+                format!("similarity:{:.2}", hit.similarity),
+            ],
+        }),
+    })
+}
+
+/// The hits of several searches, ordered by the sum of their reciprocal ranks. A chunk found
+/// by more than one search is listed once.
+pub fn merge_by_rank(ranked: Vec<Vec<SearchHit>>, limit: usize) -> Vec<SearchHit> {
+    if ranked.len() == 1 {
+        return ranked.into_iter().flatten().take(limit).collect();
+    }
+
+    let mut scores: HashMap<(String, usize), (f32, SearchHit)> = HashMap::new();
+    for hits in ranked {
+        for (rank, hit) in hits.into_iter().enumerate() {
+            let score = 1.0 / (RANK_OFFSET + rank as f32 + 1.0);
+            scores
+                .entry((hit.path.clone(), hit.start_line))
+                .and_modify(|(total, _)| *total += score)
+                .or_insert((score, hit));
+        }
+    }
+
+    let mut merged: Vec<(f32, SearchHit)> = scores.into_values().collect();
+    merged.sort_by(|a, b| b.0.total_cmp(&a.0));
+    merged.into_iter().take(limit).map(|(_, hit)| hit).collect()
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
@@ -20,6 +20,9 @@ pub enum AgentError {
     #[error("AI API error: {0}")]
     AiApi(String),
 
+    #[error("The request is longer than the model's context window ({0})")]
+    ContextLengthExceeded(String),
+
     #[error("Task execution error: {0}")]
     TaskExecution(String),
 
@@ -41,6 +44,9 @@ pub enum AgentError {
     #[error("Working context error: {0}")]
     WorkingContext(String),
 
+    #[error("Workflow error: {0}")]
+    Workflow(String),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
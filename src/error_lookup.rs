@@ -0,0 +1,221 @@
+use crate::crate_docs;
+use crate::error::{AgentError, AgentResult};
+use crate::memory::{MemoryFragment, Provenance};
+use crate::utils;
+use regex::Regex;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const SEARCH_URL: &str = "https://html.duckduckgo.com/html/";
+/// Characters of an explanation kept in context; `rustc --explain` texts are a few pages
+const MAX_EXPLANATION_CHARS: usize = 6_000;
+/// Search results kept for an error message
+const MAX_SEARCH_RESULTS: usize = 5;
+/// Distinct error codes looked up from one verification failure
+pub const MAX_CODES_PER_FAILURE: usize = 3;
+
+/// What an error lookup is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorQuery {
+    Code(String),    // a rustc error code such as E0507
+    Message(String), // a distinctive part of an error message
+}
+
+impl ErrorQuery {
+    /// A bare code, or a message; messages quoting a code (`error[E0507]: ...`) look up the code
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        match error_codes(text).into_iter().next() {
+            Some(code) => Self::Code(code),
+            None => Self::Message(text.to_string()),
+        }
+    }
+}
+
+fn code_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b[Ee](\d{4})\b").expect("valid error code pattern"))
+}
+
+/// Distinct rustc error codes in `text`, in order of appearance
+pub fn error_codes(text: &str) -> Vec<String> {
+    let mut codes: Vec<String> = Vec::new();
+    for capture in code_pattern().captures_iter(text) {
+        let code = format!("E{}", &capture[1]);
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+/// Distinct codes of the errors rustc reported in build output (`error[E0507]: ...`)
+pub fn reported_codes(output: &str) -> Vec<String> {
+    static REPORTED: OnceLock<Regex> = OnceLock::new();
+    let reported =
+        REPORTED.get_or_init(|| Regex::new(r"error\[(E\d{4})\]").expect("valid error pattern"));
+
+    let mut codes: Vec<String> = Vec::new();
+    for capture in reported.captures_iter(output) {
+        if !codes.iter().any(|code| *code == capture[1]) {
+            codes.push(capture[1].to_string());
+        }
+    }
+    codes
+}
+
+/// An explanation ready to be added to context memory: `rustc --explain` for codes, web
+/// search results for messages and for codes the local rustc cannot explain
+pub async fn lookup(query: &ErrorQuery) -> AgentResult<MemoryFragment> {
+    match query {
+        ErrorQuery::Code(code) => match explain_code(code).await {
+            Ok(explanation) => Ok(explanation_fragment(code, &explanation)),
+            Err(e) => {
+                log::warn!("{}; searching the web instead", e);
+                search_fragment(&format!("error {}", code)).await
+            }
+        },
+        ErrorQuery::Message(message) => search_fragment(message).await,
+    }
+}
+
+async fn search_fragment(message: &str) -> AgentResult<MemoryFragment> {
+    let results = search_web(message).await?;
+    if results.is_empty() {
+        return Err(AgentError::ToolExecution(format!(
+            "No search results for '{}'",
+            message
+        )));
+    }
+
+    let listing = results
+        .iter()
+        .map(|r| format!("- {} ({})\n  {}", r.title, r.url, r.snippet))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(MemoryFragment {
+        source: "error_lookup".to_string(),
+        provenance: Provenance::Web,
+        content: format!("Search results for the error '{}':\n{}", message, listing),
+        metadata: None,
+    })
+}
+
+/// Context fragment holding the explanation of `code`
+pub fn explanation_fragment(code: &str, explanation: &str) -> MemoryFragment {
+    MemoryFragment {
+        source: "error_lookup".to_string(),
+        provenance: Provenance::ToolOutput,
+        content: format!(
+            "`rustc --explain {}`:\n{}",
+            code,
+            utils::truncate_string(explanation, MAX_EXPLANATION_CHARS)
+        ),
+        metadata: None,
+    }
+}
+
+/// The explanation of `code` from the local rustc
+pub async fn explain_code(code: &str) -> AgentResult<String> {
+    let output = tokio::process::Command::new("rustc")
+        .args(["--explain", code])
+        .output()
+        .await
+        .map_err(|e| AgentError::ToolExecution(format!("Failed to run rustc: {}", e)))?;
+    if !output.status.success() {
+        return Err(AgentError::ToolExecution(format!(
+            "rustc has no explanation for {}: {}",
+            code,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// One web search result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// Search Stack Overflow and the rest of the web for `message`
+pub async fn search_web(message: &str) -> AgentResult<Vec<SearchResult>> {
+    let query = format!("rust {}", utils::truncate_string(message, 200));
+    let html = reqwest::Client::builder()
+        .user_agent(concat!("indiefuture-cli/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(20))
+        .build()?
+        .get(SEARCH_URL)
+        .query(&[("q", query.as_str())])
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(parse_search_results(&html))
+}
+
+/// Results of a DuckDuckGo HTML results page
+pub fn parse_search_results(html: &str) -> Vec<SearchResult> {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    static SNIPPET: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| {
+        Regex::new(r#"(?s)class="result__a"[^>]*href="([^"]+)"[^>]*>(.*?)</a>"#)
+            .expect("valid result link pattern")
+    });
+    let snippet = SNIPPET.get_or_init(|| {
+        Regex::new(r#"(?s)class="result__snippet"[^>]*>(.*?)</a>"#)
+            .expect("valid result snippet pattern")
+    });
+
+    let snippets: Vec<String> = snippet
+        .captures_iter(html)
+        .map(|capture| crate_docs::html_to_text(&capture[1]))
+        .collect();
+    link.captures_iter(html)
+        .enumerate()
+        .map(|(i, capture)| SearchResult {
+            title: crate_docs::html_to_text(&capture[2]),
+            url: result_url(&crate_docs::html_to_text(&capture[1])),
+            snippet: snippets.get(i).cloned().unwrap_or_default(),
+        })
+        .take(MAX_SEARCH_RESULTS)
+        .collect()
+}
+
+/// The target of a result link, which the results page wraps in a redirect
+fn result_url(href: &str) -> String {
+    let Some((_, target)) = href.split_once("uddg=") else {
+        return href.to_string();
+    };
+    let encoded = target.split('&').next().unwrap_or(target);
+    percent_decode(encoded)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,571 @@
+use crate::agent_engine::{AgentEngine, SharedState};
+use crate::ai::{AiClient, Message, MessageRole};
+use crate::approval::ApprovalMode;
+use crate::config::Settings;
+use crate::dirty_tree::DirtyTreePolicy;
+use crate::error::{AgentError, AgentResult};
+use crate::memory::ContextMemory;
+use crate::platform;
+use crate::report::TurnReport;
+use crate::storage::Storage;
+use crate::subtasks::SubTaskType;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use walkdir::WalkDir;
+
+/// Directory, relative to the workspace root, holding eval suites and their results
+pub const EVALS_DIR: &str = "evals";
+
+/// Characters of a case's diff and answer shown to the judge
+const JUDGED_LIMIT: usize = 8000;
+
+/// Directories of a fixture that are build output rather than source
+const SKIPPED_DIRS: [&str; 3] = ["target", "node_modules", ".git"];
+
+const JUDGE_PROMPT: &str = r#"
+You grade how well a command-line coding agent handled one request in a small test
+repository. You are given the request, the criteria for a good outcome, the steps the agent
+ran, the diff it made and its final answer.
+
+Score from 0 (ignored or failed the request) to 10 (fully met every criterion) and give one
+sentence of reasoning. Judge the outcome, not the wording. Answer by calling the Score
+function.
+"#;
+
+/// Canned prompts run against a fixture repository, loaded from `evals/<name>.yaml`
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalSuite {
+    pub name: String,
+    pub fixture: PathBuf, // relative to the suite file; copied afresh for every case
+    pub cases: Vec<EvalCase>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalCase {
+    pub name: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub expect: Expectations,
+}
+
+/// What a good outcome looks like; every expectation given is one scored check
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Expectations {
+    #[serde(default)]
+    pub files_found: Vec<String>, // named in a step, its output or the answer
+    #[serde(default)]
+    pub files_changed: Vec<String>, // edited by the run
+    #[serde(default)]
+    pub answer_contains: Vec<String>, // in the final answer, ignoring case
+    #[serde(default)]
+    pub command: Option<String>, // must succeed in the fixture afterwards, e.g. `cargo check`
+    #[serde(default)]
+    pub judge: Option<String>, // criteria the judge scores the outcome against
+}
+
+/// The score of one check, from 0 to 1, with what it found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub check: String,
+    pub score: f64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub score: f64, // mean of the checks, from 0 to 1
+    pub checks: Vec<CheckResult>,
+}
+
+/// A suite run, saved so later runs can be compared with it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub suite: String,
+    pub provider: String,
+    pub model: String,
+    pub timestamp: String,
+    pub score: f64, // mean case score, from 0 to 100
+    pub cases: Vec<CaseResult>,
+}
+
+impl EvalSuite {
+    /// Load `evals/<name>.yaml` (or `.yml`) from the workspace, or a suite file by path
+    pub fn load(workspace_root: &Path, name: &str) -> AgentResult<(Self, PathBuf)> {
+        let dir = workspace_root.join(EVALS_DIR);
+        let path = [
+            PathBuf::from(name),
+            dir.join(format!("{}.yaml", name)),
+            dir.join(format!("{}.yml", name)),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+        .ok_or_else(|| AgentError::Cli(format!("No eval suite named '{}' in {:?}", name, dir)))?;
+
+        let contents = std::fs::read_to_string(&path)?;
+        let suite = Self::parse(&contents)
+            .map_err(|e| AgentError::Cli(format!("Invalid eval suite {:?}: {}", path, e)))?;
+        let fixture = path.parent().unwrap_or(Path::new(".")).join(&suite.fixture);
+        Ok((suite, fixture))
+    }
+
+    pub fn parse(contents: &str) -> AgentResult<Self> {
+        let suite: Self =
+            serde_yaml::from_str(contents).map_err(|e| AgentError::Cli(e.to_string()))?;
+        if suite.cases.is_empty() {
+            return Err(AgentError::Cli("The suite has no cases".to_string()));
+        }
+        Ok(suite)
+    }
+}
+
+impl CaseResult {
+    pub fn new(name: &str, checks: Vec<CheckResult>) -> Self {
+        let score = if checks.is_empty() {
+            0.0
+        } else {
+            checks.iter().map(|c| c.score).sum::<f64>() / checks.len() as f64
+        };
+        Self {
+            name: name.to_string(),
+            score,
+            checks,
+        }
+    }
+}
+
+impl EvalReport {
+    pub fn new(suite: &str, ai_client: &dyn AiClient, cases: Vec<CaseResult>) -> Self {
+        let score = if cases.is_empty() {
+            0.0
+        } else {
+            100.0 * cases.iter().map(|c| c.score).sum::<f64>() / cases.len() as f64
+        };
+        Self {
+            suite: suite.to_string(),
+            provider: ai_client.provider_name(),
+            model: ai_client.model_name(),
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            score,
+            cases,
+        }
+    }
+
+    pub fn load(path: &Path) -> AgentResult<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Save to `evals/results/<suite>-<time>.json` in the workspace, returning the path
+    pub fn save(&self, workspace_root: &Path) -> AgentResult<PathBuf> {
+        let dir = workspace_root.join(EVALS_DIR).join("results");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!(
+            "{}-{}.json",
+            self.suite,
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    /// A table of case scores, with the change from `baseline` where it has the case
+    pub fn render(&self, baseline: Option<&EvalReport>) -> String {
+        let delta = |now: f64, before: Option<f64>| {
+            before
+                .map(|before| format!(" ({:+.1})", now - before))
+                .unwrap_or_default()
+        };
+
+        let mut table = format!(
+            "Eval {} with {}/{}\n",
+            self.suite, self.provider, self.model
+        );
+        for case in &self.cases {
+            let before = baseline
+                .and_then(|b| b.cases.iter().find(|c| c.name == case.name))
+                .map(|c| 100.0 * c.score);
+            table.push_str(&format!(
+                "  {:<32} {:>5.1}{}\n",
+                case.name,
+                100.0 * case.score,
+                delta(100.0 * case.score, before)
+            ));
+            for check in case.checks.iter().filter(|c| c.score < 1.0) {
+                table.push_str(&format!("      ⨯ {}: {}\n", check.check, check.detail));
+            }
+        }
+        table.push_str(&format!(
+            "Score: {:.1}{}",
+            self.score,
+            delta(self.score, baseline.map(|b| b.score))
+        ));
+        if let Some(baseline) = baseline {
+            table.push_str(&format!(
+                " vs {}/{} at {}",
+                baseline.provider, baseline.model, baseline.timestamp
+            ));
+        }
+        table
+    }
+}
+
+/// Run every case of `suite`, or only the one named `only`, each in a fresh copy of `fixture`.
+/// The judge uses `judge_client` so runs of different models are graded alike.
+pub async fn run_suite(
+    suite: &EvalSuite,
+    fixture: &Path,
+    only: Option<&str>,
+    template: &SharedState,
+    judge_client: &dyn AiClient,
+    settings: Arc<Settings>,
+) -> AgentResult<EvalReport> {
+    let cases: Vec<&EvalCase> = suite
+        .cases
+        .iter()
+        .filter(|case| only.is_none_or(|name| case.name == name))
+        .collect();
+    if cases.is_empty() {
+        return Err(AgentError::Cli(format!(
+            "No case named '{}' in suite {}",
+            only.unwrap_or_default(),
+            suite.name
+        )));
+    }
+
+    let mut results = Vec::new();
+    for (index, case) in cases.iter().enumerate() {
+        cliclack::log::step(format!("[{}/{}] {}", index + 1, cases.len(), case.name))?;
+        let result = match run_case(case, fixture, template, judge_client, settings.clone()).await {
+            Ok(result) => result,
+            Err(e) => CaseResult::new(
+                &case.name,
+                vec![CheckResult {
+                    check: "run".to_string(),
+                    score: 0.0,
+                    detail: e.to_string(),
+                }],
+            ),
+        };
+        cliclack::log::info(format!("{}: {:.1}", case.name, 100.0 * result.score))?;
+        results.push(result);
+    }
+
+    Ok(EvalReport::new(
+        &suite.name,
+        template.ai_client.as_ref(),
+        results,
+    ))
+}
+
+async fn run_case(
+    case: &EvalCase,
+    fixture: &Path,
+    template: &SharedState,
+    judge_client: &dyn AiClient,
+    settings: Arc<Settings>,
+) -> AgentResult<CaseResult> {
+    let workspace = std::env::temp_dir().join(format!("indiefuture-eval-{}", utils::generate_id()));
+    copy_fixture(fixture, &workspace)?;
+    let result = run_in(case, fixture, &workspace, template, judge_client, settings).await;
+    if let Err(e) = std::fs::remove_dir_all(&workspace) {
+        log::warn!("Failed to remove eval workspace {:?}: {}", workspace, e);
+    }
+    result
+}
+
+async fn run_in(
+    case: &EvalCase,
+    fixture: &Path,
+    workspace: &Path,
+    template: &SharedState,
+    judge_client: &dyn AiClient,
+    settings: Arc<Settings>,
+) -> AgentResult<CaseResult> {
+    let shared_state = Arc::new(SharedState {
+        checkpoint_client: template.checkpoint_client.as_ref().map(|c| c.clone_box()),
+        model_router: template.model_router.clone(),
+        usage: Arc::clone(&template.usage),
+        ..SharedState::new(
+            template.ai_client.clone_box(),
+            Storage::temporary(template.storage.user())?,
+            workspace,
+        )
+    });
+
+    // Nobody is asked anything, so every case runs the same way
+    let mut engine = AgentEngine {
+        approval_mode: ApprovalMode::Auto,
+        dirty_tree: DirtyTreePolicy::Off,
+        ..AgentEngine::default()
+    };
+    engine.begin_turn(&case.prompt);
+    engine.push_subtask(SubTaskType::Task(case.prompt.clone()));
+    engine
+        .handle_subtasks(
+            shared_state,
+            Arc::new(Mutex::new(ContextMemory::default())),
+            settings,
+        )
+        .await;
+    let report = engine.turn_report;
+
+    let mut checks = deterministic_checks(&case.expect, &report);
+    if let Some(command) = &case.expect.command {
+        checks.push(command_check(command, workspace).await);
+    }
+    if let Some(criteria) = &case.expect.judge {
+        let diff = diff_fixture(fixture, workspace, &report.files_changed);
+        checks.push(
+            match judge(judge_client, case, criteria, &report, &diff).await {
+                Ok(check) => check,
+                Err(e) => CheckResult {
+                    check: "judge".to_string(),
+                    score: 0.0,
+                    detail: format!("The judge failed: {}", e),
+                },
+            },
+        );
+    }
+    Ok(CaseResult::new(&case.name, checks))
+}
+
+/// The checks of `expect` that need only the turn's report
+pub fn deterministic_checks(expect: &Expectations, report: &TurnReport) -> Vec<CheckResult> {
+    let mut checks = Vec::new();
+    let transcript: String = report
+        .steps
+        .iter()
+        .flat_map(|step| [Some(&step.description), step.output.as_ref()])
+        .flatten()
+        .chain(report.explanation.as_ref())
+        .map(|text| format!("{}\n", text))
+        .collect();
+
+    for file in &expect.files_found {
+        let found = transcript.contains(file.as_str());
+        checks.push(CheckResult {
+            check: format!("found {}", file),
+            score: f64::from(u8::from(found)),
+            detail: if found { "found" } else { "never mentioned" }.to_string(),
+        });
+    }
+
+    for file in &expect.files_changed {
+        let changed = report
+            .files_changed
+            .iter()
+            .any(|changed| same_file(changed, file));
+        checks.push(CheckResult {
+            check: format!("changed {}", file),
+            score: f64::from(u8::from(changed)),
+            detail: if changed {
+                "changed".to_string()
+            } else {
+                format!("changed only {:?}", report.files_changed)
+            },
+        });
+    }
+
+    let answer = report
+        .explanation
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    for phrase in &expect.answer_contains {
+        let contained = answer.contains(&phrase.to_lowercase());
+        checks.push(CheckResult {
+            check: format!("answer mentions \"{}\"", phrase),
+            score: f64::from(u8::from(contained)),
+            detail: if contained {
+                "mentioned"
+            } else if report.explanation.is_none() {
+                "no answer was given"
+            } else {
+                "not in the answer"
+            }
+            .to_string(),
+        });
+    }
+    checks
+}
+
+/// Whether `changed`, as a tool reported it, is the fixture file `expected`
+fn same_file(changed: &str, expected: &str) -> bool {
+    let changed = changed.replace('\\', "/");
+    let expected = expected.trim_start_matches("./");
+    changed == expected || changed.ends_with(&format!("/{}", expected))
+}
+
+async fn command_check(command: &str, workspace: &Path) -> CheckResult {
+    let output = platform::shell_command(command)
+        .current_dir(workspace)
+        .output()
+        .await;
+    let (score, detail) = match output {
+        Ok(output) if output.status.success() => (1.0, "succeeded".to_string()),
+        Ok(output) => (
+            0.0,
+            format!(
+                "failed: {}",
+                utils::truncate_string(String::from_utf8_lossy(&output.stderr).trim(), 300)
+            ),
+        ),
+        Err(e) => (0.0, format!("could not run: {}", e)),
+    };
+    CheckResult {
+        check: format!("`{}`", command),
+        score,
+        detail,
+    }
+}
+
+/// Copy `fixture` to `workspace`, leaving out build output
+pub fn copy_fixture(fixture: &Path, workspace: &Path) -> AgentResult<()> {
+    if !fixture.is_dir() {
+        return Err(AgentError::Cli(format!(
+            "Eval fixture {:?} is not a directory",
+            fixture
+        )));
+    }
+
+    let entries = WalkDir::new(fixture).into_iter().filter_entry(|entry| {
+        !(entry.file_type().is_dir()
+            && SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+    });
+    for entry in entries {
+        let entry = entry.map_err(|e| AgentError::Io(e.into()))?;
+        let relative = entry.path().strip_prefix(fixture).unwrap_or(entry.path());
+        let target = workspace.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Unified diffs of the changed files against the fixture
+fn diff_fixture(fixture: &Path, workspace: &Path, changed: &[String]) -> String {
+    changed
+        .iter()
+        .filter_map(|file| {
+            let relative = Path::new(file)
+                .strip_prefix(workspace)
+                .unwrap_or(Path::new(file));
+            let before = std::fs::read_to_string(fixture.join(relative)).unwrap_or_default();
+            let after = std::fs::read_to_string(workspace.join(relative)).ok()?;
+            let name = platform::display_path(relative);
+            Some(
+                similar::TextDiff::from_lines(&before, &after)
+                    .unified_diff()
+                    .header(&format!("a/{}", name), &format!("b/{}", name))
+                    .to_string(),
+            )
+        })
+        .collect()
+}
+
+async fn judge(
+    ai_client: &dyn AiClient,
+    case: &EvalCase,
+    criteria: &str,
+    report: &TurnReport,
+    diff: &str,
+) -> AgentResult<CheckResult> {
+    let steps = report
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            format!(
+                "{}. {}{}",
+                i + 1,
+                step.description,
+                if step.succeeded { "" } else { " (failed)" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: JUDGE_PROMPT.to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: format!(
+                "REQUEST:\n{}\n\nCRITERIA:\n{}\n\nSTEPS:\n{}\n\nDIFF:\n{}\n\nANSWER:\n{}",
+                case.prompt,
+                criteria,
+                if steps.is_empty() { "(none)" } else { &steps },
+                if diff.is_empty() {
+                    "(no changes)".to_string()
+                } else {
+                    utils::truncate_string(diff, JUDGED_LIMIT)
+                },
+                utils::truncate_string(
+                    report.explanation.as_deref().unwrap_or("(no answer)"),
+                    JUDGED_LIMIT
+                )
+            ),
+            name: None,
+        },
+    ];
+
+    let response = ai_client
+        .chat_completion_with_functions(messages, score_function(), false)
+        .await?;
+    let call = response
+        .tool_calls
+        .into_iter()
+        .flatten()
+        .next()
+        .ok_or_else(|| AgentError::AiApi("The judge answered without a score".to_string()))?;
+    let arguments = match call.function.arguments {
+        Value::String(raw) => serde_json::from_str(&raw)?,
+        arguments => arguments,
+    };
+
+    let score = arguments["score"]
+        .as_f64()
+        .unwrap_or_default()
+        .clamp(0.0, 10.0);
+    Ok(CheckResult {
+        check: "judge".to_string(),
+        score: score / 10.0,
+        detail: arguments["reason"]
+            .as_str()
+            .unwrap_or("no reason given")
+            .to_string(),
+    })
+}
+
+fn score_function() -> Value {
+    json!([
+        {
+            "name": "Score",
+            "description": "Grade how well the agent met the criteria",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "score": {
+                        "type": "integer",
+                        "description": "From 0 (failed) to 10 (fully met every criterion)"
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "One sentence explaining the score"
+                    }
+                },
+                "required": ["score", "reason"]
+            }
+        }
+    ])
+}
@@ -0,0 +1,243 @@
+use crate::error::{AgentError, AgentResult};
+use crate::notifications::RunStats;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Instant;
+
+/// The name of the built-in prompt in assignments and results
+pub const CONTROL: &str = "control";
+
+/// Prompts that can be swapped for variants, by the tool that sends them
+pub const SLOTS: [&str; 2] = ["TaskTool", "ProtoSubtask"];
+
+/// Alternate system prompts by slot, then by variant name
+pub type PromptVariants = BTreeMap<String, BTreeMap<String, String>>;
+
+/// The variant of each slot this session uses, and where its outcomes are recorded
+#[derive(Debug, Clone)]
+struct Experiment {
+    variants: PromptVariants,
+    assignment: BTreeMap<String, String>,
+    results_path: PathBuf,
+}
+
+/// The running experiment; none unless opted into
+static EXPERIMENT: RwLock<Option<Experiment>> = RwLock::new(None);
+
+/// One turn's outcome under the variants its session used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnOutcome {
+    pub timestamp: String,
+    pub session_id: String,
+    pub model: String,
+    pub variants: BTreeMap<String, String>, // variant by slot
+    pub status: String,                     // completed, failed or declined
+    pub operations: usize,
+    pub failed_operations: usize,
+    pub files_changed: usize,
+    pub duration_seconds: f64,
+}
+
+/// How the turns that used one variant of a slot went
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantSummary {
+    pub slot: String,
+    pub variant: String,
+    pub turns: usize,
+    pub completed: usize,
+    pub mean_operations: f64,
+    pub mean_failed_operations: f64,
+    pub mean_duration_seconds: f64,
+}
+
+/// An error naming the first variant that cannot be used
+pub fn check(variants: &PromptVariants) -> AgentResult<()> {
+    for (slot, named) in variants {
+        if !SLOTS.contains(&slot.as_str()) {
+            return Err(AgentError::Configuration(format!(
+                "Unknown prompt slot '{}'; variants can replace the prompts of {}",
+                slot,
+                SLOTS.join(" and ")
+            )));
+        }
+        for (name, prompt) in named {
+            if name == CONTROL {
+                return Err(AgentError::Configuration(format!(
+                    "'{}' names the built-in {} prompt; give the variant another name",
+                    CONTROL, slot
+                )));
+            }
+            if prompt.trim().is_empty() {
+                return Err(AgentError::Configuration(format!(
+                    "The {} variant '{}' has an empty prompt",
+                    slot, name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The variant of each slot with variants that `session_id` uses. Sessions split evenly
+/// between the built-in prompt and the variants, and a session keeps its variants throughout.
+pub fn assign(variants: &PromptVariants, session_id: &str) -> BTreeMap<String, String> {
+    variants
+        .iter()
+        .filter(|(_, named)| !named.is_empty())
+        .map(|(slot, named)| {
+            let options: Vec<&str> = std::iter::once(CONTROL)
+                .chain(named.keys().map(String::as_str))
+                .collect();
+            let bucket = fnv1a(&format!("{}/{}", session_id, slot)) as usize % options.len();
+            (slot.clone(), options[bucket].to_string())
+        })
+        .collect()
+}
+
+/// A hash that stays the same across builds, so results from older sessions keep their meaning
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Split this session between `variants`, recording outcomes to `results_path`
+pub fn configure(variants: PromptVariants, session_id: &str, results_path: PathBuf) {
+    let assignment = assign(&variants, session_id);
+    *EXPERIMENT.write().unwrap_or_else(|e| e.into_inner()) = Some(Experiment {
+        variants,
+        assignment,
+        results_path,
+    });
+}
+
+/// The variant of each slot this session uses, or `None` outside an experiment
+pub fn assignment() -> Option<BTreeMap<String, String>> {
+    EXPERIMENT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|experiment| experiment.assignment.clone())
+}
+
+pub fn results_path() -> Option<PathBuf> {
+    EXPERIMENT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|experiment| experiment.results_path.clone())
+}
+
+/// The system prompt of `slot`: this session's variant, or `control`
+pub fn prompt(slot: &str, control: &str) -> String {
+    let experiment = EXPERIMENT.read().unwrap_or_else(|e| e.into_inner());
+    experiment
+        .as_ref()
+        .and_then(|experiment| {
+            let variant = experiment.assignment.get(slot)?;
+            experiment.variants.get(slot)?.get(variant)
+        })
+        .cloned()
+        .unwrap_or_else(|| control.to_string())
+}
+
+impl TurnOutcome {
+    pub fn new(
+        session_id: &str,
+        model: &str,
+        variants: BTreeMap<String, String>,
+        stats: &RunStats,
+        started: Instant,
+    ) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            session_id: session_id.to_string(),
+            model: model.to_string(),
+            variants,
+            status: stats.status().to_string(),
+            operations: stats.operations,
+            failed_operations: stats.failed,
+            files_changed: stats.files_changed.len(),
+            duration_seconds: started.elapsed().as_secs_f64(),
+        }
+    }
+}
+
+/// Append `outcome` to the results file, one JSON object per line
+pub fn record(path: &Path, outcome: &TurnOutcome) -> AgentResult<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(outcome)?)?;
+    Ok(())
+}
+
+/// Every outcome recorded at `path`; lines that do not parse are skipped
+pub fn load(path: &Path) -> AgentResult<Vec<TurnOutcome>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Outcomes grouped by slot and variant, the built-in prompt first in each slot
+pub fn summarize(outcomes: &[TurnOutcome]) -> Vec<VariantSummary> {
+    let mut groups: BTreeMap<(&str, bool, &str), Vec<&TurnOutcome>> = BTreeMap::new();
+    for outcome in outcomes {
+        for (slot, variant) in &outcome.variants {
+            groups
+                .entry((slot, variant != CONTROL, variant))
+                .or_default()
+                .push(outcome);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|((slot, _, variant), turns)| {
+            let mean = |value: fn(&TurnOutcome) -> f64| {
+                turns.iter().map(|turn| value(turn)).sum::<f64>() / turns.len() as f64
+            };
+            VariantSummary {
+                slot: slot.to_string(),
+                variant: variant.to_string(),
+                turns: turns.len(),
+                completed: turns.iter().filter(|t| t.status == "completed").count(),
+                mean_operations: mean(|t| t.operations as f64),
+                mean_failed_operations: mean(|t| t.failed_operations as f64),
+                mean_duration_seconds: mean(|t| t.duration_seconds),
+            }
+        })
+        .collect()
+}
+
+/// A table of the summaries, one line per variant
+pub fn render(summaries: &[VariantSummary]) -> String {
+    let mut table = format!(
+        "{:<14} {:<20} {:>6} {:>10} {:>6} {:>7} {:>8}",
+        "prompt", "variant", "turns", "completed", "steps", "failed", "seconds"
+    );
+    for summary in summaries {
+        table.push_str(&format!(
+            "\n{:<14} {:<20} {:>6} {:>9.0}% {:>6.1} {:>7.1} {:>8.1}",
+            summary.slot,
+            summary.variant,
+            summary.turns,
+            100.0 * summary.completed as f64 / summary.turns as f64,
+            summary.mean_operations,
+            summary.mean_failed_operations,
+            summary.mean_duration_seconds
+        ));
+    }
+    table
+}
@@ -0,0 +1,243 @@
+use crate::theme::{self, MarkdownLines, Role};
+use serde::Deserialize;
+use std::fmt;
+
+/// Line prefix the model uses to mark claims it could not check against the context
+pub const UNVERIFIED_MARKER: &str = "[unverified]";
+
+/// Appended to explanation prompts so answers carry a machine-readable self-assessment
+pub const ASSESSMENT_INSTRUCTIONS: &str = r#"
+Start any line containing a claim you could not confirm from the context with [unverified].
+
+End your response with a fenced json block assessing it:
+```json
+{"confidence": "high" | "medium" | "low", "assumptions": ["each assumption you made that the context did not confirm"]}
+```
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::High => write!(f, "high"),
+            Self::Medium => write!(f, "medium"),
+            Self::Low => write!(f, "low"),
+        }
+    }
+}
+
+/// How sure the model is about an explanation, and what it took on faith
+#[derive(Debug, Clone, Deserialize)]
+pub struct Assessment {
+    pub confidence: Confidence,
+    #[serde(default)]
+    pub assumptions: Vec<String>,
+}
+
+impl Assessment {
+    /// Whether it is worth offering to verify the explanation with more searching
+    pub fn needs_verification(&self) -> bool {
+        self.confidence != Confidence::High || !self.assumptions.is_empty()
+    }
+}
+
+/// An explanation split into its text and the trailing self-assessment, if the model gave one
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub text: String,
+    pub assessment: Option<Assessment>,
+}
+
+impl Explanation {
+    pub fn parse(response: &str) -> Self {
+        let Some(start) = response.rfind("```json") else {
+            return Self {
+                text: response.trim().to_string(),
+                assessment: None,
+            };
+        };
+
+        let body = &response[start + "```json".len()..];
+        let body = body.split("```").next().unwrap_or(body);
+
+        match serde_json::from_str::<Assessment>(body.trim()) {
+            Ok(assessment) => Self {
+                text: response[..start].trim().to_string(),
+                assessment: Some(assessment),
+            },
+            Err(_) => Self {
+                text: response.trim().to_string(),
+                assessment: None,
+            },
+        }
+    }
+
+    /// Explanation text with its markdown and unverified lines highlighted, then the assessment
+    pub fn render(&self) -> String {
+        let mut markdown = MarkdownLines::default();
+        let mut rendered = self
+            .text
+            .lines()
+            .map(|line| render_line(&mut markdown, line))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let assessment = self.render_assessment();
+        if !assessment.is_empty() {
+            rendered.push_str("\n\n");
+            rendered.push_str(&assessment);
+        }
+        rendered
+    }
+
+    /// The confidence and unverified assumptions; empty without an assessment
+    pub fn render_assessment(&self) -> String {
+        let Some(assessment) = &self.assessment else {
+            return String::new();
+        };
+
+        let confidence = format!("Confidence: {}", assessment.confidence);
+        let mut rendered = match assessment.confidence {
+            Confidence::High => theme::paint(&confidence, Role::Success),
+            Confidence::Medium => theme::paint(&confidence, Role::Warning),
+            Confidence::Low => theme::paint(&confidence, Role::Error),
+        };
+
+        if !assessment.assumptions.is_empty() {
+            rendered.push_str(&format!(
+                "\n{}",
+                theme::paint("Unverified assumptions:", Role::Warning)
+            ));
+            for assumption in &assessment.assumptions {
+                rendered.push_str(&format!("\n  - {}", assumption));
+            }
+        }
+
+        rendered
+    }
+
+    /// The full explanation as a markdown document headed by the question it answers
+    pub fn to_markdown(&self, query: &str) -> String {
+        let mut markdown = format!("# {}\n\n{}\n", query.trim(), self.text);
+
+        if let Some(assessment) = &self.assessment {
+            markdown.push_str(&format!("\n**Confidence:** {}\n", assessment.confidence));
+
+            if !assessment.assumptions.is_empty() {
+                markdown.push_str("\n**Unverified assumptions:**\n\n");
+                for assumption in &assessment.assumptions {
+                    markdown.push_str(&format!("- {}\n", assumption));
+                }
+            }
+        }
+
+        markdown
+    }
+
+    /// Prompt for a follow-up task that checks what the explanation could not confirm
+    pub fn verification_task(&self, query: &str) -> Option<String> {
+        let assessment = self.assessment.as_ref()?;
+        if !assessment.needs_verification() {
+            return None;
+        }
+
+        let mut claims: Vec<String> = self
+            .text
+            .lines()
+            .filter_map(|line| line.trim_start().strip_prefix(UNVERIFIED_MARKER))
+            .map(|claim| claim.trim().to_string())
+            .collect();
+        claims.extend(assessment.assumptions.iter().cloned());
+
+        Some(format!(
+            "Verify the earlier explanation of \"{}\" by searching and reading the relevant code, then explain again. Check these points:\n- {}",
+            query,
+            claims.join("\n- ")
+        ))
+    }
+}
+
+/// One line of explanation text with unverified claims highlighted, then its markdown
+fn render_line(markdown: &mut MarkdownLines, line: &str) -> String {
+    let line = match line.trim_start().strip_prefix(UNVERIFIED_MARKER) {
+        Some(claim) => format!(
+            "{} {}",
+            theme::paint("⚠ unverified:", Role::Warning),
+            theme::paint(claim.trim(), Role::Muted)
+        ),
+        None => line.to_string(),
+    };
+    markdown.line(&line)
+}
+
+/// Renders an explanation line by line while it streams in. The self-assessment block is
+/// held back until it closes, then dropped, since it is shown separately once complete.
+#[derive(Debug, Default)]
+pub struct ExplanationStream {
+    response: String,  // everything received so far
+    partial: String,   // the line being received
+    held: Vec<String>, // a `json` fence that may be the assessment
+    markdown: MarkdownLines,
+}
+
+impl ExplanationStream {
+    /// Rendered lines `token` completes, each ending in a newline
+    pub fn push(&mut self, token: &str) -> String {
+        self.response.push_str(token);
+        self.partial.push_str(token);
+
+        let mut rendered = String::new();
+        while let Some(end) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=end).collect();
+            rendered.push_str(&self.line(line.trim_end_matches(['\n', '\r'])));
+        }
+        rendered
+    }
+
+    /// The rendered rest of the response, and the explanation parsed from all of it
+    pub fn finish(mut self) -> (String, Explanation) {
+        let partial = std::mem::take(&mut self.partial);
+        let mut rendered = if partial.is_empty() {
+            String::new()
+        } else {
+            self.line(&partial)
+        };
+        // An unclosed fence was not the assessment after all
+        for line in std::mem::take(&mut self.held) {
+            rendered.push_str(&render_line(&mut self.markdown, &line));
+            rendered.push('\n');
+        }
+        (rendered, Explanation::parse(&self.response))
+    }
+
+    fn line(&mut self, line: &str) -> String {
+        let fence = line.trim_start().starts_with("```");
+        if self.held.is_empty() {
+            if fence && !self.markdown.in_fence() && line.trim_start().starts_with("```json") {
+                self.held.push(line.to_string());
+                return String::new();
+            }
+            return format!("{}\n", render_line(&mut self.markdown, line));
+        }
+
+        self.held.push(line.to_string());
+        if !fence {
+            return String::new();
+        }
+        let held = std::mem::take(&mut self.held);
+        let body = held[1..held.len() - 1].join("\n");
+        if serde_json::from_str::<Assessment>(&body).is_ok() {
+            return String::new();
+        }
+        held.iter()
+            .map(|line| format!("{}\n", render_line(&mut self.markdown, line)))
+            .collect()
+    }
+}
@@ -0,0 +1,191 @@
+use crate::git;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::RwLock;
+
+/// Files whose references are counted; larger workspaces rank the rest by path alone
+const MAX_RANKED_FILES: usize = 5000;
+
+/// Files larger than this are not scanned for references; they are rarely hand-written
+const MAX_RANKED_FILE_BYTES: u64 = 200_000;
+
+/// Recent commits whose changes count as churn
+const CHURN_COMMITS: &str = "300";
+
+/// File stems too generic to tell which file a reference means; their directory is used
+const GENERIC_STEMS: [&str; 3] = ["mod", "index", "__init__"];
+
+/// Files the rest of a project hangs off
+const ENTRY_POINTS: [&str; 8] = [
+    "main.rs", "lib.rs", "main.go", "main.py", "app.py", "index.ts", "index.js", "main.ts",
+];
+
+/// How much a file is likely to matter when reading a project, by workspace-relative path.
+/// Files without a score are ranked by their path alone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileRanking {
+    scores: BTreeMap<String, f64>,
+}
+
+impl FileRanking {
+    /// The importance of `path`; higher is more important
+    pub fn score(&self, path: &str) -> f64 {
+        self.scores
+            .get(path)
+            .copied()
+            .unwrap_or_else(|| path_weight(path))
+    }
+
+    /// Sort `items` most important first, keeping the order of equally important ones
+    pub fn sort_by_importance<T>(&self, items: &mut [T], path: impl Fn(&T) -> &str) {
+        items.sort_by(|a, b| self.score(path(b)).total_cmp(&self.score(path(a))));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+}
+
+static RANKING: RwLock<Option<FileRanking>> = RwLock::new(None);
+
+/// Rank files with `ranking` from now on, once the workspace has been indexed
+pub fn configure(ranking: FileRanking) {
+    *RANKING.write().unwrap_or_else(|e| e.into_inner()) = Some(ranking);
+}
+
+/// The workspace's ranking, or one by path alone before it has been indexed
+pub fn current() -> FileRanking {
+    RANKING
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Weight of a path by where it lives: entry points above source, source above tests,
+/// examples and docs, and vendored or generated code last
+pub fn path_weight(path: &str) -> f64 {
+    let path = path.replace('\\', "/").to_lowercase();
+    let components: Vec<&str> = path.split('/').collect();
+    let file_name = components.last().copied().unwrap_or_default();
+    let stem = file_name.split('.').next().unwrap_or_default();
+    let in_dir = |names: &[&str]| {
+        components[..components.len() - 1]
+            .iter()
+            .any(|component| names.contains(component))
+    };
+
+    if in_dir(&[
+        "vendor",
+        "third_party",
+        "generated",
+        "dist",
+        "build",
+        "node_modules",
+    ]) {
+        0.2
+    } else if in_dir(&[
+        "test",
+        "tests",
+        "spec",
+        "specs",
+        "__tests__",
+        "testdata",
+        "fixtures",
+    ]) || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+    {
+        0.4
+    } else if in_dir(&["examples", "example", "benches", "docs", "scripts"]) {
+        0.6
+    } else if ENTRY_POINTS.contains(&file_name) {
+        1.5
+    } else {
+        1.0
+    }
+}
+
+/// The name other files use to refer to `path`: its stem, or its directory's for `mod.rs`
+/// and the like
+fn reference_name(path: &str) -> Option<&str> {
+    let mut components = path.rsplit('/');
+    let stem = components.next()?.split('.').next()?;
+    let name = if GENERIC_STEMS.contains(&stem) {
+        components.next()?
+    } else {
+        stem
+    };
+    (name.len() >= 3).then_some(name)
+}
+
+/// How often each path changed in the last commits, empty outside a git repository
+fn churn(root: &Path) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    let Ok(log) = git::run_git(
+        root,
+        &[
+            "log",
+            "--relative",
+            "-n",
+            CHURN_COMMITS,
+            "--name-only",
+            "--format=",
+        ],
+    ) else {
+        return counts;
+    };
+    for path in log.lines().filter(|line| !line.is_empty()) {
+        *counts.entry(path.to_string()).or_default() += 1;
+    }
+    counts
+}
+
+/// Rank `paths`, workspace-relative with forward slashes, by how many other files refer to
+/// them, how often they changed lately, and where they live
+pub fn rank_files(root: &Path, paths: &[String]) -> FileRanking {
+    let mut by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+    for path in paths.iter().take(MAX_RANKED_FILES) {
+        if let Some(name) = reference_name(path) {
+            by_name.entry(name).or_default().push(path);
+        }
+    }
+
+    // A file referring to another by name, once however often it does
+    let mut references: HashMap<&str, usize> = HashMap::new();
+    for path in paths.iter().take(MAX_RANKED_FILES) {
+        let full_path = root.join(path);
+        if std::fs::metadata(&full_path).map_or(true, |m| m.len() > MAX_RANKED_FILE_BYTES) {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&full_path) else {
+            continue;
+        };
+        let identifiers: HashSet<&str> = content
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .collect();
+        for identifier in identifiers {
+            for referenced in by_name.get(identifier).into_iter().flatten() {
+                if *referenced != path.as_str() {
+                    *references.entry(referenced).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let churn = churn(root);
+    let scores = paths
+        .iter()
+        .map(|path| {
+            let references = references.get(path.as_str()).copied().unwrap_or(0);
+            let changes = churn.get(path).copied().unwrap_or(0);
+            let score = path_weight(path)
+                * (1.0 + (1.0 + references as f64).ln() + 0.5 * (1.0 + changes as f64).ln());
+            (path.clone(), score)
+        })
+        .collect();
+    FileRanking { scores }
+}
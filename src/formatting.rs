@@ -0,0 +1,170 @@
+use crate::error::{AgentError, AgentResult};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+const RUSTFMT_CONFIGS: &[&str] = &["rustfmt.toml", ".rustfmt.toml"];
+
+const PRETTIER_CONFIGS: &[&str] = &[
+    ".prettierrc",
+    ".prettierrc.json",
+    ".prettierrc.yaml",
+    ".prettierrc.yml",
+    ".prettierrc.js",
+    ".prettierrc.cjs",
+    ".prettierrc.mjs",
+    ".prettierrc.toml",
+    "prettier.config.js",
+    "prettier.config.cjs",
+    "prettier.config.mjs",
+];
+
+const PRETTIER_EXTENSIONS: &[&str] = &[
+    "js", "jsx", "mjs", "cjs", "ts", "tsx", "json", "css", "scss", "less", "html", "vue", "md",
+    "yaml", "yml",
+];
+
+/// A formatter command the project is set up for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formatter {
+    pub name: &'static str,
+    pub program: &'static str,
+    pub args: Vec<String>,
+}
+
+/// Choose the formatter for `file` from the configs found between it and `root`.
+/// Returns `None` when the project shows no sign of using one, so its files are left alone.
+pub fn detect(root: &Path, file: &Path) -> Option<Formatter> {
+    let extension = file.extension()?.to_str()?;
+    let find = |names: &[&str]| find_config(root, file, names);
+
+    match extension {
+        // rustfmt picks up rustfmt.toml itself; Cargo.toml supplies the edition
+        "rs" if find(RUSTFMT_CONFIGS).is_some() || find(&["Cargo.toml"]).is_some() => {
+            let edition = find(&["Cargo.toml"])
+                .and_then(|manifest| cargo_edition(&manifest))
+                .unwrap_or_else(|| "2021".to_string());
+            Some(Formatter {
+                name: "rustfmt",
+                program: "rustfmt",
+                args: vec!["--edition".to_string(), edition],
+            })
+        }
+        "go" if find(&["go.mod"]).is_some() => Some(Formatter {
+            name: "gofmt",
+            program: "gofmt",
+            args: vec!["-w".to_string()],
+        }),
+        "py" => {
+            let pyproject = std::fs::read_to_string(find(&["pyproject.toml"])?).ok()?;
+            if pyproject.contains("[tool.ruff") {
+                Some(Formatter {
+                    name: "ruff",
+                    program: "ruff",
+                    args: vec!["format".to_string(), "--quiet".to_string()],
+                })
+            } else if pyproject.contains("[tool.black]") {
+                Some(Formatter {
+                    name: "black",
+                    program: "black",
+                    args: vec!["--quiet".to_string()],
+                })
+            } else {
+                None
+            }
+        }
+        ext if PRETTIER_EXTENSIONS.contains(&ext) && uses_prettier(root, file) => Some(Formatter {
+            name: "prettier",
+            program: "npx",
+            args: vec![
+                "--no-install".to_string(),
+                "prettier".to_string(),
+                "--write".to_string(),
+                "--log-level".to_string(),
+                "warn".to_string(),
+            ],
+        }),
+        _ => None,
+    }
+}
+
+/// Format `file` in place with `formatter`, run from `root` so relative configs resolve
+pub async fn format_file(root: &Path, file: &Path, formatter: &Formatter) -> AgentResult<()> {
+    let output = Command::new(formatter.program)
+        .args(&formatter.args)
+        .arg(file)
+        .current_dir(root)
+        .output()
+        .await
+        .map_err(|e| {
+            AgentError::ToolExecution(format!("Failed to run {}: {}", formatter.name, e))
+        })?;
+
+    if !output.status.success() {
+        return Err(AgentError::ToolExecution(format!(
+            "{} failed on {}: {}",
+            formatter.name,
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Prettier is in use when it has a config, a `prettier` key in package.json, or is installed
+/// alongside an .editorconfig (which prettier reads for indentation)
+fn uses_prettier(root: &Path, file: &Path) -> bool {
+    if find_config(root, file, PRETTIER_CONFIGS).is_some() {
+        return true;
+    }
+
+    let Some(package) = find_config(root, file, &["package.json"]) else {
+        return false;
+    };
+    let has_key = std::fs::read_to_string(&package)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .is_some_and(|json| json.get("prettier").is_some());
+    let installed = package
+        .with_file_name("node_modules")
+        .join(".bin")
+        .join("prettier")
+        .exists();
+
+    has_key || (installed && find_config(root, file, &[".editorconfig"]).is_some())
+}
+
+/// Nearest file named one of `names` in the directories from `file` up to `root`
+fn find_config(root: &Path, file: &Path, names: &[&str]) -> Option<PathBuf> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let start = file.parent()?.canonicalize().ok()?;
+    if !start.starts_with(&root) {
+        return None;
+    }
+
+    for dir in start.ancestors() {
+        if let Some(config) = names
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|p| p.is_file())
+        {
+            return Some(config);
+        }
+        if dir == root {
+            break;
+        }
+    }
+    None
+}
+
+fn cargo_edition(manifest: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(manifest).ok()?;
+    content.lines().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix("edition")?
+            .trim()
+            .strip_prefix('=')?;
+        Some(value.trim().trim_matches('"').to_string())
+    })
+}
@@ -0,0 +1,171 @@
+use crate::error::{AgentError, AgentResult};
+use crate::workspaces;
+use std::fs::Metadata;
+use std::path::{Component, Path, PathBuf};
+
+/// What a filesystem tool is about to do with a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathAccess {
+    List,
+    Read,
+    Write,
+}
+
+impl PathAccess {
+    fn verb(&self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+}
+
+/// Resolve `path` for a filesystem tool working in `root`.
+/// Symlinks are never followed from inside the workspace to outside it, dangling symlinks are
+/// not written through, and devices, FIFOs and sockets are refused. Paths given outside the
+/// workspace directly are resolved as usual for reading, but never written. Returns the resolved path, which callers should
+/// use for the actual I/O so it cannot be redirected afterwards by `..` through a link.
+/// Paths in other workspaces added to the session are checked against their own root, and
+/// only written when edits are allowed there.
+pub fn resolve(root: &Path, path: &Path, access: PathAccess) -> AgentResult<PathBuf> {
+    let (root, relative) = match workspaces::find(path) {
+        Some((workspace, _)) if access == PathAccess::Write && !workspace.editable => {
+            return Err(refused(
+                path,
+                access,
+                &format!(
+                    "workspace {} is read-only; allow edits with /workspace edit {} on",
+                    workspace.name, workspace.name
+                ),
+            ));
+        }
+        Some((workspace, relative)) => (workspace.root, relative),
+        None => (root.to_path_buf(), path.to_path_buf()),
+    };
+    let root = root.canonicalize().map_err(|e| {
+        refused(
+            path,
+            access,
+            &format!("workspace root is unavailable: {}", e),
+        )
+    })?;
+    let joined = root.join(&relative);
+
+    // `..` after a symlink climbs from the link's target, so resolve before normalizing
+    let lexical = normalize(&joined);
+    let resolved = normalize(&resolve_existing_prefix(&joined, path, access)?);
+    if lexical.starts_with(&root) && !resolved.starts_with(&root) {
+        return Err(refused(
+            path,
+            access,
+            &format!(
+                "it resolves to {} outside the workspace through a symlink",
+                resolved.display()
+            ),
+        ));
+    }
+
+    // Absolute and `../` paths would otherwise write anywhere the user can
+    if access == PathAccess::Write && !resolved.starts_with(&root) {
+        return Err(refused(
+            path,
+            access,
+            "it is outside the workspace; add its repository with /workspace add <path> --edit",
+        ));
+    }
+
+    if let Ok(metadata) = std::fs::metadata(&resolved) {
+        if let Some(kind) = special_kind(&metadata) {
+            return Err(refused(
+                path,
+                access,
+                &format!("it is a {}, not a regular file", kind),
+            ));
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Name of the kind of special file `metadata` describes, if it is one
+#[cfg(unix)]
+pub fn special_kind(metadata: &Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    if file_type.is_fifo() {
+        Some("FIFO")
+    } else if file_type.is_socket() {
+        Some("socket")
+    } else if file_type.is_block_device() {
+        Some("block device")
+    } else if file_type.is_char_device() {
+        Some("character device")
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+pub fn special_kind(metadata: &Metadata) -> Option<&'static str> {
+    (!metadata.is_file() && !metadata.is_dir()).then_some("special file")
+}
+
+/// Canonicalize the longest part of `path` that exists and append the rest, so paths
+/// about to be created still have their symlinked parents resolved
+fn resolve_existing_prefix(
+    path: &Path,
+    original: &Path,
+    access: PathAccess,
+) -> AgentResult<PathBuf> {
+    let mut existing = path.to_path_buf();
+    let mut rest = Vec::new();
+
+    while existing.symlink_metadata().is_err() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                rest.push(name.to_os_string());
+                existing = parent.to_path_buf();
+            }
+            _ => return Ok(path.to_path_buf()),
+        }
+    }
+
+    // A dangling link would be created wherever it points when written through
+    let mut resolved = existing.canonicalize().map_err(|_| {
+        refused(
+            original,
+            access,
+            &format!("{} is a dangling symlink", existing.display()),
+        )
+    })?;
+    for name in rest.into_iter().rev() {
+        resolved.push(name);
+    }
+    Ok(resolved)
+}
+
+/// Remove `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn refused(path: &Path, access: PathAccess, reason: &str) -> AgentError {
+    AgentError::ToolExecution(format!(
+        "Refusing to {} {}: {}",
+        access.verb(),
+        path.display(),
+        reason
+    ))
+}
@@ -0,0 +1,123 @@
+use crate::error::{AgentError, AgentResult};
+use std::path::Path;
+use std::process::Command;
+
+/// A commit with the files it touched
+#[derive(Debug, Clone)]
+pub struct CommitSummary {
+    pub hash: String,
+    pub subject: String,
+    pub files: Vec<String>,
+}
+
+/// Run a git command in `root` and return its trimmed stdout
+pub fn run_git(root: &Path, args: &[&str]) -> AgentResult<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(AgentError::ToolExecution(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Most recent tag reachable from HEAD, if any
+pub fn last_tag(root: &Path) -> Option<String> {
+    run_git(root, &["describe", "--tags", "--abbrev=0"])
+        .ok()
+        .filter(|tag| !tag.is_empty())
+}
+
+/// Commits after `since` (or the whole history), newest first
+pub fn commits_since(root: &Path, since: Option<&str>) -> AgentResult<Vec<CommitSummary>> {
+    let range = since.map(|since| format!("{}..HEAD", since));
+
+    // A record separator keeps subjects and file lists apart without ambiguity
+    let mut args = vec!["log", "--name-only", "--format=%x1e%H%x1f%s"];
+    if let Some(range) = range.as_deref() {
+        args.push(range);
+    }
+
+    let log = run_git(root, &args)?;
+
+    Ok(log
+        .split('\u{1e}')
+        .filter(|record| !record.trim().is_empty())
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let (hash, subject) = lines.next()?.split_once('\u{1f}')?;
+
+            Some(CommitSummary {
+                hash: hash.to_string(),
+                subject: subject.to_string(),
+                files: lines
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+/// Lines added and removed in one file of a diff
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// The branch pull requests are opened against: origin's HEAD, else `main` or `master`
+pub fn default_branch(root: &Path) -> Option<String> {
+    if let Ok(head) = run_git(
+        root,
+        &["symbolic-ref", "--short", "refs/remotes/origin/HEAD"],
+    ) {
+        if !head.is_empty() {
+            return Some(head);
+        }
+    }
+
+    ["main", "master"]
+        .iter()
+        .find(|branch| run_git(root, &["rev-parse", "--verify", "--quiet", branch]).is_ok())
+        .map(|branch| branch.to_string())
+}
+
+/// Files changed on this branch since it diverged from `base`
+pub fn changed_files(root: &Path, base: &str) -> AgentResult<Vec<FileChange>> {
+    let range = format!("{}...HEAD", base);
+    let numstat = run_git(root, &["diff", "--numstat", &range])?;
+
+    Ok(numstat
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let added = fields.next()?;
+            let removed = fields.next()?;
+            let path = fields.next()?;
+
+            // Binary files report "-" for both counts
+            Some(FileChange {
+                path: path.to_string(),
+                added: added.parse().unwrap_or(0),
+                removed: removed.parse().unwrap_or(0),
+            })
+        })
+        .collect())
+}
+
+/// Diff of a single file on this branch since it diverged from `base`
+pub fn file_diff(root: &Path, base: &str, path: &str) -> AgentResult<String> {
+    let range = format!("{}...HEAD", base);
+    run_git(root, &["diff", &range, "--", path])
+}
@@ -0,0 +1,126 @@
+use crate::error::{AgentError, AgentResult};
+use glob::{MatchOptions, Pattern};
+use jwalk::WalkDir;
+use rayon::prelude::*;
+use std::path::{Component, Path, PathBuf};
+
+/// Matches collected before the walk stops, unless the caller asks for a different cap
+pub const DEFAULT_MATCH_CAP: usize = 5000;
+
+/// A path matching a glob pattern
+#[derive(Debug, Clone)]
+pub struct GlobEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Paths matching a pattern; `truncated` is set when the walk stopped at the match cap
+#[derive(Debug, Clone, Default)]
+pub struct GlobMatches {
+    pub entries: Vec<GlobEntry>,
+    pub truncated: bool,
+}
+
+/// Find paths under `base` matching `pattern`, walking directories in parallel.
+/// The walk starts at the pattern's literal prefix, is depth-limited when the pattern has no
+/// `**`, and stops as soon as `max_matches` paths have matched. Metadata for the matches is
+/// collected in parallel afterwards. Entries are sorted directories first, then by path.
+pub fn glob_paths(base: &Path, pattern: &str, max_matches: usize) -> AgentResult<GlobMatches> {
+    let full_pattern = base.join(pattern);
+    let full_pattern = full_pattern.to_string_lossy();
+    let matcher = Pattern::new(&full_pattern)
+        .map_err(|e| AgentError::ToolExecution(format!("Invalid glob pattern: {}", e)))?;
+
+    let options = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+
+    let (root, rest) = split_literal_prefix(base, pattern);
+    if rest.is_empty() {
+        // No wildcards at all: the pattern names a single path
+        return Ok(GlobMatches {
+            entries: collect_metadata(root.exists().then_some(root).into_iter().collect()),
+            truncated: false,
+        });
+    }
+    if !root.is_dir() {
+        return Ok(GlobMatches::default());
+    }
+
+    let mut walker = WalkDir::new(&root)
+        .skip_hidden(false)
+        .follow_links(false)
+        .sort(true)
+        .min_depth(1);
+    if !rest.iter().any(|component| component.contains("**")) {
+        walker = walker.max_depth(rest.len());
+    }
+
+    let mut matched = Vec::new();
+    let mut truncated = false;
+
+    for entry in walker.into_iter().flatten() {
+        let path = entry.path();
+        if !matcher.matches_path_with(&path, options) {
+            continue;
+        }
+        if matched.len() >= max_matches {
+            truncated = true;
+            break;
+        }
+        matched.push(path);
+    }
+
+    Ok(GlobMatches {
+        entries: collect_metadata(matched),
+        truncated,
+    })
+}
+
+/// Stat every path in parallel and sort directories first, then by path
+fn collect_metadata(paths: Vec<PathBuf>) -> Vec<GlobEntry> {
+    let mut entries: Vec<GlobEntry> = paths
+        .into_par_iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(&path).ok();
+            GlobEntry {
+                is_dir: metadata.as_ref().is_some_and(|m| m.is_dir()),
+                size: metadata.as_ref().map_or(0, |m| m.len()),
+                path,
+            }
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.path.cmp(&b.path)));
+    entries
+}
+
+/// Split `pattern` into the directory named by its leading wildcard-free components and
+/// the remaining components, so the walk can start as deep as possible.
+/// Uses the platform's separators, so `\` also separates components on Windows.
+fn split_literal_prefix<'a>(base: &Path, pattern: &'a str) -> (PathBuf, Vec<&'a str>) {
+    let mut root = base.to_path_buf();
+    let mut rest = Vec::new();
+
+    for component in Path::new(pattern).components() {
+        match component {
+            // An absolute pattern replaces the base, as `Path::join` would
+            Component::Prefix(_) | Component::RootDir => root.push(component),
+            // Kept so walked paths still match the pattern text
+            Component::CurDir => root.push(component),
+            other => {
+                let name = other.as_os_str().to_str().unwrap_or_default();
+                if rest.is_empty() && !name.contains(['*', '?', '[']) {
+                    root.push(name);
+                } else {
+                    rest.push(name);
+                }
+            }
+        }
+    }
+
+    (root, rest)
+}
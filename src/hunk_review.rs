@@ -0,0 +1,124 @@
+use crate::edits::{self, Hunk, LineChange};
+use crate::error::AgentResult;
+use crate::subtasks::subtask::{EditSetToolInputs, FileEditToolInputs};
+use crate::theme;
+use std::collections::VecDeque;
+use std::path::Path;
+
+/// What the user decided for one hunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Accept,
+    Reject,
+    Edit,
+    Split,
+    AcceptRest, // this hunk and the rest of the file
+    RejectRest,
+}
+
+/// Step through every hunk of an edit set, like `git add -p`, letting the user accept,
+/// reject, edit or split each one. Returns the edits that were kept, with each file
+/// rewritten to its reviewed content, or None when nothing was kept.
+pub fn review(
+    inputs: &EditSetToolInputs,
+    workspace_root: &Path,
+) -> AgentResult<Option<EditSetToolInputs>> {
+    let planned = inputs.to_edit_set(workspace_root).plan()?;
+    let mut reviewed = Vec::new();
+
+    for file in &planned {
+        let original = file.original.as_deref().unwrap_or_default();
+        let name = file.path.display().to_string();
+        let mut queue: VecDeque<Hunk> = file.hunks().into();
+        let mut kept: Vec<LineChange> = Vec::new();
+        let mut rest = None;
+        let mut number = 0;
+
+        while let Some(hunk) = queue.pop_front() {
+            number += 1;
+
+            let decision = match rest {
+                Some(decision) => decision,
+                None => {
+                    cliclack::log::info(format!(
+                        "{} - hunk {}/{}\n{}",
+                        name,
+                        number,
+                        number + queue.len(),
+                        theme::diff(&hunk.render(original))
+                    ))?;
+                    ask(&hunk)?
+                }
+            };
+
+            match decision {
+                Decision::Accept => kept.extend(hunk.changes),
+                Decision::Reject => {}
+                Decision::AcceptRest | Decision::RejectRest => {
+                    rest = Some(decision);
+                    if decision == Decision::AcceptRest {
+                        kept.extend(hunk.changes);
+                    }
+                }
+                Decision::Split => {
+                    number -= 1;
+                    for part in hunk.split().into_iter().rev() {
+                        queue.push_front(part);
+                    }
+                }
+                Decision::Edit => {
+                    let Some(mut change) = hunk.merged(original) else {
+                        continue;
+                    };
+                    let current = change.new_lines.concat();
+                    let text: String = cliclack::input("Replacement text for this hunk")
+                        .multiline()
+                        .default_input(&current)
+                        .required(false)
+                        .interact()?;
+
+                    // Keep the hunk ending at a line break when it did before
+                    let mut text = text;
+                    if current.ends_with('\n') && !text.is_empty() && !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    change.new_lines = text.split_inclusive('\n').map(str::to_string).collect();
+                    kept.push(change);
+                }
+            }
+        }
+
+        let updated = edits::apply_changes(original, &kept);
+        if kept.is_empty() || (file.original.is_some() && updated == original) {
+            continue;
+        }
+
+        reviewed.push(FileEditToolInputs {
+            file_path: name,
+            old_string: original.to_string(),
+            new_string: updated,
+        });
+    }
+
+    Ok((!reviewed.is_empty()).then_some(EditSetToolInputs { edits: reviewed }))
+}
+
+fn ask(hunk: &Hunk) -> AgentResult<Decision> {
+    let mut prompt = cliclack::select("Apply this hunk?")
+        .item(Decision::Accept, "Accept", "")
+        .item(Decision::Reject, "Reject", "")
+        .item(
+            Decision::Edit,
+            "Edit",
+            "change the new text before applying it",
+        );
+    if hunk.changes.len() > 1 {
+        prompt = prompt.item(Decision::Split, "Split", "decide on each change separately");
+    }
+    let decision = prompt
+        .item(Decision::AcceptRest, "Accept the rest of this file", "")
+        .item(Decision::RejectRest, "Reject the rest of this file", "")
+        .interact()?;
+
+    Ok(decision)
+}
@@ -0,0 +1,270 @@
+use crate::embeddings::{self, SearchHit};
+use crate::file_rank;
+use crate::fs_guard::{self, PathAccess};
+use crate::memory::{MemoryFragment, MemoryMetadata, Provenance};
+use crate::text_file;
+use crate::utils;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Source of the fragment hybrid search adds
+pub const HYBRID_SEARCH_SOURCE: &str = "hybrid_search";
+
+/// Keywords taken from a query at most
+const MAX_KEYWORDS: usize = 8;
+
+/// Files larger than this are not grepped; they are rarely source
+const MAX_GREP_FILE_BYTES: u64 = 512 * 1024;
+
+/// Lines of context kept around a keyword match, and the gap below which matches merge
+const MATCH_CONTEXT_LINES: usize = 3;
+
+/// Lines shown of one result; longer chunks are cut and can be read in full
+const MAX_RESULT_LINES: usize = 40;
+
+/// Words too common in questions to tell code apart
+const STOP_WORDS: [&str; 24] = [
+    "the", "and", "for", "with", "where", "what", "which", "when", "how", "that", "this", "from",
+    "into", "are", "does", "code", "function", "file", "files", "use", "used", "make", "get",
+    "set",
+];
+
+/// The words of a natural-language query worth grepping for, lowercased and deduplicated
+pub fn keywords(query: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() >= 3 && !STOP_WORDS.contains(&word.as_str()))
+        .filter(|word| seen.insert(word.clone()))
+        .take(MAX_KEYWORDS)
+        .collect()
+}
+
+/// Regions of the workspace's files that mention `keywords`, best first: those matching more
+/// distinct keywords, then more lines, then in more important files. Nearby matches form one
+/// region with some context.
+/// Respects .gitignore, so build output and dependencies are not searched.
+pub fn keyword_hits(workspace_root: &Path, keywords: &[String], limit: usize) -> Vec<SearchHit> {
+    if keywords.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<((usize, usize), SearchHit)> = Vec::new();
+    let walker = ignore::WalkBuilder::new(workspace_root)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file())
+            || entry
+                .metadata()
+                .map_or(true, |m| m.len() > MAX_GREP_FILE_BYTES)
+        {
+            continue;
+        }
+        let Ok(path) = fs_guard::resolve(workspace_root, entry.path(), PathAccess::Read) else {
+            continue;
+        };
+        // Binary files do not decode and are skipped
+        let Ok((text, _)) = text_file::read_text(&path) else {
+            continue;
+        };
+        let relative = utils::path_to_string(path.strip_prefix(workspace_root).unwrap_or(&path));
+        let line_count = text.lines().count();
+
+        // (region start, end, keywords seen, matching lines), with 1-based lines
+        let mut region: Option<(usize, usize, HashSet<usize>, usize)> = None;
+        let mut regions = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_lowercase = line.to_lowercase();
+            let matched: Vec<usize> = (0..keywords.len())
+                .filter(|&k| line_lowercase.contains(&keywords[k]))
+                .collect();
+            if matched.is_empty() {
+                continue;
+            }
+
+            let line_number = index + 1;
+            match &mut region {
+                Some((_, end, seen, lines)) if line_number <= *end + MATCH_CONTEXT_LINES => {
+                    *end = line_number;
+                    seen.extend(matched);
+                    *lines += 1;
+                }
+                _ => {
+                    regions.extend(region.take());
+                    region = Some((line_number, line_number, matched.into_iter().collect(), 1));
+                }
+            }
+        }
+        regions.extend(region);
+
+        for (start, end, seen, lines) in regions {
+            scored.push((
+                (seen.len(), lines),
+                SearchHit {
+                    path: relative.clone(),
+                    start_line: start.saturating_sub(MATCH_CONTEXT_LINES).max(1),
+                    end_line: (end + MATCH_CONTEXT_LINES).min(line_count),
+                    symbol: None,
+                    similarity: 0.0,
+                    embedding_model: String::new(),
+                },
+            ));
+        }
+    }
+
+    // Stable, so equally good regions in equally important files stay in path order
+    let ranking = file_rank::current();
+    scored.sort_by(|(a, a_hit), (b, b_hit)| {
+        b.cmp(a).then_with(|| {
+            ranking
+                .score(&b_hit.path)
+                .total_cmp(&ranking.score(&a_hit.path))
+        })
+    });
+    scored.into_iter().take(limit).map(|(_, hit)| hit).collect()
+}
+
+/// A place hybrid search found, and which searches found it
+#[derive(Debug, Clone)]
+pub struct HybridHit {
+    pub hit: SearchHit,
+    pub by_keywords: bool,
+    pub by_meaning: bool,
+}
+
+/// Keyword and semantic hits merged by reciprocal rank, best first. A keyword region inside a
+/// chunk semantic search found counts as that chunk, so each place is listed once.
+pub fn fuse(
+    keyword_hits: Vec<SearchHit>,
+    semantic_hits: Vec<SearchHit>,
+    limit: usize,
+) -> Vec<HybridHit> {
+    let mut seen = HashSet::new();
+    let keyword_hits: Vec<SearchHit> = keyword_hits
+        .into_iter()
+        .map(|hit| {
+            semantic_hits
+                .iter()
+                .find(|chunk| {
+                    chunk.path == hit.path
+                        && chunk.start_line <= hit.end_line
+                        && hit.start_line <= chunk.end_line
+                })
+                .cloned()
+                .unwrap_or(hit)
+        })
+        .filter(|hit| seen.insert((hit.path.clone(), hit.start_line)))
+        .collect();
+
+    let by_meaning: HashSet<(String, usize)> = semantic_hits
+        .iter()
+        .map(|hit| (hit.path.clone(), hit.start_line))
+        .collect();
+
+    // Semantic hits first, so a chunk both found keeps its similarity and symbol
+    embeddings::merge_by_rank(vec![semantic_hits, keyword_hits], limit)
+        .into_iter()
+        .map(|hit| {
+            let key = (hit.path.clone(), hit.start_line);
+            HybridHit {
+                by_keywords: seen.contains(&key),
+                by_meaning: by_meaning.contains(&key),
+                hit,
+            }
+        })
+        .collect()
+}
+
+/// One fragment listing `hits` in order, each citing its lines, saying which search found it
+/// and showing its text, and the number of results listed. `None` when there are no hits or
+/// their files are gone.
+pub fn results_fragment(
+    workspace_root: &Path,
+    query: &str,
+    keywords: &[String],
+    hits: &[HybridHit],
+) -> Option<(MemoryFragment, usize)> {
+    let mut results = Vec::new();
+    for HybridHit {
+        hit,
+        by_keywords,
+        by_meaning,
+    } in hits
+    {
+        let Ok(path) = fs_guard::resolve(workspace_root, Path::new(&hit.path), PathAccess::Read)
+        else {
+            continue;
+        };
+        let Ok((text, _)) = text_file::read_text(&path) else {
+            continue;
+        };
+        let excerpt: Vec<&str> = text
+            .lines()
+            .skip(hit.start_line.saturating_sub(1))
+            .take(hit.end_line + 1 - hit.start_line)
+            .collect();
+        if excerpt.is_empty() {
+            continue;
+        }
+
+        let end_line = hit.start_line + excerpt.len() - 1;
+        let found_by = match (*by_keywords, *by_meaning) {
+            (true, true) => format!("keywords and meaning, similarity {:.2}", hit.similarity),
+            (false, true) => format!("meaning, similarity {:.2}", hit.similarity),
+            _ => "keywords".to_string(),
+        };
+        let symbol = hit
+            .symbol
+            .as_deref()
+            .map(|symbol| format!(", in {}", symbol))
+            .unwrap_or_default();
+        let shown = excerpt.len().min(MAX_RESULT_LINES);
+        let cut = if shown < excerpt.len() {
+            format!("\n[{} more lines]", excerpt.len() - shown)
+        } else {
+            String::new()
+        };
+        results.push(format!(
+            "{}. {}:{}-{} (found by {}{}; FileReadTool offset {} limit {} reads this window)\n{}{}",
+            results.len() + 1,
+            hit.path,
+            hit.start_line,
+            end_line,
+            found_by,
+            symbol,
+            hit.start_line - 1,
+            excerpt.len(),
+            excerpt[..shown].join("\n"),
+            cut
+        ));
+    }
+    if results.is_empty() {
+        return None;
+    }
+
+    let keywords = if keywords.is_empty() {
+        "none".to_string()
+    } else {
+        keywords.join(", ")
+    };
+    let fragment = MemoryFragment {
+        source: HYBRID_SEARCH_SOURCE.to_string(),
+        provenance: Provenance::FileRead,
+        content: format!(
+            "Hybrid search for '{}' (keywords: {}), {} results, best first:\n\n{}",
+            query,
+            keywords,
+            results.len(),
+            results.join("\n\n")
+        ),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("search_results".to_string()),
+            path: None,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            tags: vec![HYBRID_SEARCH_SOURCE.to_string()],
+        }),
+    };
+    Some((fragment, results.len()))
+}
@@ -0,0 +1,78 @@
+use crate::ai::{Message, MessageRole};
+use crate::error::AgentResult;
+use crate::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance, TRUST_GUIDANCE};
+use crate::AiClient;
+use chrono::Utc;
+use tokio::sync::Mutex;
+
+/// Reply meaning the prompt needs the planner and tools
+const NEEDS_PROJECT: &str = "NEEDS_PROJECT_CONTEXT";
+
+const GATE_PROMPT: &str = r#"
+You are the first step of a command-line coding agent working in the user's local project.
+Decide whether the user's message can be fully answered from general programming knowledge,
+without reading, searching, running or changing anything in their project.
+
+- If it can (e.g. "what does a Mutex do?", "explain lifetimes", "difference between Vec and slice"),
+  answer it directly, concisely and accurately.
+- If answering needs their code, files, git history, commands or edits in any way, reply with
+  exactly NEEDS_PROJECT_CONTEXT and nothing else.
+"#;
+
+/// Answer general-knowledge questions without planning or tools.
+/// Returns `None` when the prompt needs the project, so it should go to the planner.
+pub async fn answer_without_tools(
+    ai_client: &dyn AiClient,
+    prompt: &str,
+    context_memory: &Mutex<ContextMemory>,
+) -> AgentResult<Option<String>> {
+    // Preferences still apply to direct answers
+    let pinned = context_memory
+        .lock()
+        .await
+        .get_pinned_fragments()
+        .iter()
+        .map(|frag| format!("[{}]\n{}", frag.provenance_label(), frag.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mut messages = vec![Message {
+        role: MessageRole::System,
+        content: GATE_PROMPT.to_string(),
+        name: None,
+    }];
+    if !pinned.is_empty() {
+        messages.push(Message {
+            role: MessageRole::System,
+            content: format!("{}\n\n{}", TRUST_GUIDANCE, pinned),
+            name: None,
+        });
+    }
+    messages.push(Message {
+        role: MessageRole::User,
+        content: prompt.to_string(),
+        name: None,
+    });
+
+    let reply = ai_client.generate_text(messages).await?;
+    let reply = reply.trim();
+
+    if reply.is_empty() || reply.contains(NEEDS_PROJECT) {
+        return Ok(None);
+    }
+
+    // Keep the exchange so follow-up tasks can refer to it
+    context_memory.lock().await.add_frag(MemoryFragment {
+        source: "direct_answer".to_string(),
+        provenance: Provenance::ModelGenerated,
+        content: format!("Q: {}\nA: {}", prompt, reply),
+        metadata: Some(MemoryMetadata {
+            file_type: None,
+            path: None,
+            timestamp: Some(Utc::now().timestamp()),
+            tags: vec!["direct_answer".to_string()],
+        }),
+    });
+
+    Ok(Some(reply.to_string()))
+}
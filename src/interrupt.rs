@@ -0,0 +1,112 @@
+use crate::ai::{AiClient, Message, MessageRole};
+use crate::error::AgentResult;
+use crate::memory::{ContextMemory, TRUST_GUIDANCE};
+use crate::utils;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use tokio::sync::Mutex;
+
+/// Source of fragments holding guidance the user gave while a run was paused
+pub const USER_NOTE_SOURCE: &str = "user_note";
+
+/// Context fragments the model sees when explaining the state of a run
+const CONTEXT_FRAGMENTS: usize = 10;
+
+/// Each fragment is cut to this many characters in that prompt
+const MAX_FRAGMENT_CHARS: usize = 2000;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static LISTENING: Once = Once::new();
+
+/// Listen for Ctrl+C for the rest of the session. The first press asks the running plan to
+/// pause and explain itself at the next step; a second press before then quits.
+pub fn listen() {
+    LISTENING.call_once(|| {
+        tokio::spawn(async {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if REQUESTED.swap(true, Ordering::SeqCst) {
+                    eprintln!("\nQuitting.");
+                    std::process::exit(130);
+                }
+                eprintln!(
+                    "\n⏸ Pausing after the current step to explain where things stand. Press Ctrl+C again to quit."
+                );
+            }
+        });
+    });
+}
+
+/// Ask the running plan to pause at the next step, as Ctrl+C does
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Whether a pause was requested, clearing the request
+pub fn take() -> bool {
+    REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Explain to the user what the run has done so far and what remains, from the steps taken,
+/// the queued steps and the context gathered along the way
+pub async fn explain_progress(
+    ai_client: &dyn AiClient,
+    prompt: &str,
+    done: &[String],
+    remaining: &[String],
+    context_memory: &Mutex<ContextMemory>,
+) -> AgentResult<String> {
+    let context = context_memory
+        .lock()
+        .await
+        .relevant_fragments(prompt, CONTEXT_FRAGMENTS)
+        .iter()
+        .map(|fragment| {
+            format!(
+                "[{}; {}]\n{}",
+                fragment.source,
+                fragment.provenance_label(),
+                utils::truncate_string(&fragment.content, MAX_FRAGMENT_CHARS)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let list = |steps: &[String]| {
+        if steps.is_empty() {
+            "(none)".to_string()
+        } else {
+            steps
+                .iter()
+                .map(|step| format!("- {}", step))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: "The user paused a coding agent in the middle of a task. Tell them where \
+                things stand in short Markdown: what has been done and found so far, what is \
+                still queued and why, and anything that looks wrong or needs their decision. \
+                Only use what the steps and context show.\n\n"
+                .to_string()
+                + TRUST_GUIDANCE,
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: format!(
+                "Request:\n{}\n\nSteps done:\n{}\n\nSteps remaining, next first:\n{}\n\nContext gathered:\n{}",
+                if prompt.is_empty() { "(unknown)" } else { prompt },
+                list(done),
+                list(remaining),
+                context
+            ),
+            name: None,
+        },
+    ];
+
+    let explanation = ai_client.generate_text(messages).await?;
+    Ok(explanation.trim().to_string())
+}
@@ -0,0 +1,104 @@
+use crate::error::AgentResult;
+use crate::storage::{Storage, StorageTree};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// How an operation came to be executed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ApprovalSource {
+    /// The user confirmed it at the prompt
+    User,
+    /// The user approved it from another device via a remote approval link
+    Remote,
+    /// The user approved it with other planned changes in one batch
+    Batch,
+    /// It ran without a prompt
+    Automatic,
+}
+
+impl fmt::Display for ApprovalSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User => write!(f, "user"),
+            Self::Remote => write!(f, "remote"),
+            Self::Batch => write!(f, "batch"),
+            Self::Automatic => write!(f, "automatic"),
+        }
+    }
+}
+
+/// One executed mutating operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    pub timestamp: String,
+    pub session_id: String,
+    pub user: String,
+    pub operation: String,       // tool name and description
+    pub command: Option<String>, // shell command, for command operations
+    pub files: Vec<String>,
+    pub diff_hash: Option<String>, // sha256 of the change, to match entries against commits
+    pub approval: ApprovalSource,
+    pub succeeded: bool,
+}
+
+/// Append-only log of mutating operations executed in a workspace
+pub struct Journal {
+    tree: StorageTree,
+}
+
+impl Journal {
+    pub fn open(storage: &Storage, workspace_id: &str) -> Self {
+        Self {
+            tree: storage.workspace_tree("journal", workspace_id),
+        }
+    }
+
+    /// Append an entry; keys sort chronologically so entries never overwrite each other
+    pub async fn append(&self, entry: &JournalEntry) -> AgentResult<()> {
+        let key = format!(
+            "{:020}-{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            entry.id
+        );
+        self.tree.put(&key, entry).await
+    }
+
+    /// All entries, oldest first
+    pub async fn entries(&self) -> AgentResult<Vec<JournalEntry>> {
+        self.tree.values().await
+    }
+
+    /// The most recent `count` entries, oldest first
+    pub async fn recent(&self, count: usize) -> AgentResult<Vec<JournalEntry>> {
+        let entries = self.entries().await?;
+        let skip = entries.len().saturating_sub(count);
+        Ok(entries.into_iter().skip(skip).collect())
+    }
+}
+
+impl JournalEntry {
+    /// One-line summary for `/history`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {} {} [{} approval, {}]{}",
+            self.timestamp,
+            if self.succeeded { "✓" } else { "⨯" },
+            self.operation,
+            self.approval,
+            self.user,
+            self.diff_hash
+                .as_ref()
+                .map(|hash| format!(" #{}", &hash[..12.min(hash.len())]))
+                .unwrap_or_default()
+        )
+    }
+}
+
+/// Hex sha256 of a change, stored instead of the change itself
+pub fn hash_change(change: &str) -> String {
+    let digest = Sha256::digest(change.as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
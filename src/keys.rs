@@ -0,0 +1,240 @@
+use crate::error::{AgentError, AgentResult};
+use crate::interrupt;
+use crate::output;
+use console::{Key, Term};
+use serde::Deserialize;
+use std::fmt;
+use std::process::Command;
+use std::sync::RwLock;
+
+/// Something a key does at an approval prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Approve,
+    Reject,
+    /// Decline and pause the run to explain where it stands, as Ctrl+C does
+    Interrupt,
+    /// Open what is being approved, such as a diff, in `$VISUAL` or `$EDITOR`
+    OpenEditor,
+    /// Show or hide tool calls and other details of the agent's work
+    ToggleVerbose,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Approve,
+        Action::Reject,
+        Action::Interrupt,
+        Action::OpenEditor,
+        Action::ToggleVerbose,
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Approve => "approve",
+            Self::Reject => "reject",
+            Self::Interrupt => "interrupt",
+            Self::OpenEditor => "open-editor",
+            Self::ToggleVerbose => "toggle-verbose",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The `keys` table of the user settings. Keys are a character (`y`), `ctrl-<letter>`, or
+/// one of `enter`, `esc`, `tab`, `space` and `backspace`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct KeyBindings {
+    pub approve: String,
+    pub reject: String,
+    pub interrupt: String,
+    pub open_editor: String,
+    pub toggle_verbose: String,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            approve: "y".to_string(),
+            reject: "n".to_string(),
+            interrupt: "ctrl-c".to_string(),
+            open_editor: "e".to_string(),
+            toggle_verbose: "v".to_string(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key_name(&self, action: Action) -> &str {
+        match action {
+            Action::Approve => &self.approve,
+            Action::Reject => &self.reject,
+            Action::Interrupt => &self.interrupt,
+            Action::OpenEditor => &self.open_editor,
+            Action::ToggleVerbose => &self.toggle_verbose,
+        }
+    }
+
+    /// Check every key parses and no key is bound to two actions
+    pub fn validate(&self) -> AgentResult<()> {
+        let mut bound: Vec<(Key, Action)> = Vec::new();
+        for action in Action::ALL {
+            let key = parse_key(self.key_name(action))?;
+            if let Some((_, other)) = bound.iter().find(|(k, _)| *k == key) {
+                return Err(AgentError::Configuration(format!(
+                    "Key '{}' is bound to both {} and {}",
+                    self.key_name(action),
+                    other,
+                    action
+                )));
+            }
+            bound.push((key, action));
+        }
+        Ok(())
+    }
+
+    /// The action bound to `key`. Ctrl+C always interrupts, since reading keys stops it
+    /// from reaching the agent as a signal.
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| parse_key(self.key_name(*action)).is_ok_and(|bound| bound == *key))
+            .or((*key == Key::CtrlC).then_some(Action::Interrupt))
+    }
+}
+
+/// The key `name` stands for, as console reads it in raw mode
+pub fn parse_key(name: &str) -> AgentResult<Key> {
+    let lower = name.trim().to_lowercase();
+    let key = match lower.as_str() {
+        "enter" | "return" => Key::Enter,
+        "esc" | "escape" => Key::Escape,
+        "tab" => Key::Tab,
+        "space" => Key::Char(' '),
+        "backspace" => Key::Backspace,
+        "ctrl-c" => Key::CtrlC,
+        other => match other
+            .strip_prefix("ctrl-")
+            .map(|rest| rest.chars().collect::<Vec<_>>())
+        {
+            Some(letters) => match letters[..] {
+                [letter] if letter.is_ascii_lowercase() => {
+                    Key::Char(((letter as u8) & 0x1f) as char)
+                }
+                _ => return Err(unknown_key(name)),
+            },
+            None => {
+                // Characters keep their case: `Y` needs Shift
+                let mut chars = name.trim().chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Key::Char(c),
+                    _ => return Err(unknown_key(name)),
+                }
+            }
+        },
+    };
+    Ok(key)
+}
+
+fn unknown_key(name: &str) -> AgentError {
+    AgentError::Configuration(format!(
+        "Unknown key '{}'; use a character, ctrl-<letter>, enter, esc, tab, space or backspace",
+        name
+    ))
+}
+
+/// Bindings from the user settings; the defaults until configured
+static BINDINGS: RwLock<Option<KeyBindings>> = RwLock::new(None);
+
+pub fn configure(bindings: KeyBindings) -> AgentResult<()> {
+    bindings.validate()?;
+    *BINDINGS.write().unwrap_or_else(|e| e.into_inner()) = Some(bindings);
+    Ok(())
+}
+
+pub fn bindings() -> KeyBindings {
+    BINDINGS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Ask `question` and wait for the approve or reject key. `preview` is what the
+/// open-editor key opens. Without a terminal to read keys from, a yes/no prompt is shown.
+pub fn approve(question: &str, preview: Option<&str>, default: bool) -> bool {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return cliclack::confirm(question)
+            .initial_value(default)
+            .interact()
+            .unwrap_or(false);
+    }
+
+    let bindings = bindings();
+    let hints = Action::ALL
+        .iter()
+        .filter(|action| preview.is_some() || **action != Action::OpenEditor)
+        .map(|action| format!("[{}] {}", bindings.key_name(*action), action))
+        .collect::<Vec<_>>()
+        .join("  ");
+    let _ = cliclack::log::step(format!("{}\n{}", question, hints));
+
+    loop {
+        let Ok(key) = term.read_key_raw() else {
+            return false;
+        };
+        match bindings.action(&key) {
+            Some(Action::Approve) => {
+                let _ = cliclack::log::success("Approved");
+                return true;
+            }
+            Some(Action::Reject) => {
+                let _ = cliclack::log::info("Declined");
+                return false;
+            }
+            Some(Action::Interrupt) => {
+                interrupt::request();
+                let _ = cliclack::log::info("Declined; pausing to explain where things stand");
+                return false;
+            }
+            Some(Action::OpenEditor) => {
+                if let Some(preview) = preview {
+                    if let Err(e) = open_in_editor(preview) {
+                        let _ = cliclack::log::warning(format!("Could not open an editor: {}", e));
+                    }
+                }
+            }
+            Some(Action::ToggleVerbose) => {
+                let verbose = output::toggle_verbose();
+                let _ = cliclack::log::info(format!(
+                    "Verbose output {}",
+                    if verbose { "on" } else { "off" }
+                ));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Show `text` in `$VISUAL` or `$EDITOR` (vi, or notepad on Windows); edits are discarded
+fn open_in_editor(text: &str) -> AgentResult<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| AgentError::Configuration("EDITOR is empty".to_string()))?;
+    let path =
+        std::env::temp_dir().join(format!("indiefuture-preview-{}.diff", uuid::Uuid::new_v4()));
+    std::fs::write(&path, text)?;
+
+    let status = Command::new(program).args(words).arg(&path).status();
+    let _ = std::fs::remove_file(&path);
+    status.map_err(|e| AgentError::Cli(format!("Failed to run {}: {}", editor, e)))?;
+    Ok(())
+}
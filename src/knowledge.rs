@@ -0,0 +1,147 @@
+use crate::error::AgentResult;
+use crate::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance};
+use crate::storage::{Storage, StorageTree};
+use crate::utils;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+/// Source name used for the pinned fragment holding project knowledge
+pub const PROJECT_KNOWLEDGE_SOURCE: &str = "project_knowledge";
+
+/// Shortest id prefix `/forget` accepts, the length of a short id
+pub const MIN_ID_PREFIX: usize = 8;
+
+/// A durable fact about a workspace, e.g. "tests are run with make check"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFact {
+    pub id: String,
+    pub fact: String,
+    pub created_at: String,
+}
+
+impl ProjectFact {
+    /// Short form of the id, enough to identify a fact in `/forget`
+    pub fn short_id(&self) -> &str {
+        &self.id[..MIN_ID_PREFIX.min(self.id.len())]
+    }
+}
+
+/// Per-workspace store of facts the agent or user wants kept across sessions
+pub struct ProjectKnowledge {
+    tree: StorageTree,
+}
+
+impl ProjectKnowledge {
+    pub fn open(storage: &Storage, workspace_id: &str) -> Self {
+        Self {
+            tree: storage.workspace_tree("project_knowledge", workspace_id),
+        }
+    }
+
+    /// Record a new fact, ignoring exact duplicates
+    pub async fn remember(&self, fact: &str) -> AgentResult<ProjectFact> {
+        let fact = fact.trim();
+
+        if let Some(existing) = self
+            .facts()
+            .await?
+            .into_iter()
+            .find(|f| f.fact.eq_ignore_ascii_case(fact))
+        {
+            return Ok(existing);
+        }
+
+        let entry = ProjectFact {
+            id: utils::generate_id(),
+            fact: fact.to_string(),
+            created_at: utils::current_timestamp(),
+        };
+        self.tree.put(&entry.id, &entry).await?;
+
+        Ok(entry)
+    }
+
+    /// Remove the fact with id `query`, or those whose id starts with it when the prefix is
+    /// at least as long as a short id, so a short query cannot match by accident
+    pub async fn forget(&self, query: &str) -> AgentResult<Vec<ProjectFact>> {
+        let query = query.trim().to_lowercase();
+        let matches: Vec<_> = self
+            .facts()
+            .await?
+            .into_iter()
+            .filter(|fact| {
+                fact.id == query || (query.len() >= MIN_ID_PREFIX && fact.id.starts_with(&query))
+            })
+            .collect();
+
+        self.remove(&matches).await?;
+        Ok(matches)
+    }
+
+    /// Facts whose text contains `query`, for the caller to confirm before removing them
+    pub async fn matching(&self, query: &str) -> AgentResult<Vec<ProjectFact>> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(self
+            .facts()
+            .await?
+            .into_iter()
+            .filter(|fact| fact.fact.to_lowercase().contains(&query))
+            .collect())
+    }
+
+    pub async fn remove(&self, facts: &[ProjectFact]) -> AgentResult<()> {
+        for fact in facts {
+            self.tree.remove(&fact.id).await?;
+        }
+        Ok(())
+    }
+
+    /// All known facts, oldest first
+    pub async fn facts(&self) -> AgentResult<Vec<ProjectFact>> {
+        let mut facts: Vec<ProjectFact> = self.tree.values().await?;
+        facts.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(facts)
+    }
+
+    /// Render all facts as a single fragment suitable for pinning in context memory
+    pub async fn to_memory_fragment(&self) -> AgentResult<Option<MemoryFragment>> {
+        let facts = self.facts().await?;
+        if facts.is_empty() {
+            return Ok(None);
+        }
+
+        let mut content = String::from("Known facts about this project from previous sessions:\n");
+        for fact in &facts {
+            content.push_str(&format!("- {}\n", fact.fact));
+        }
+
+        Ok(Some(MemoryFragment {
+            source: PROJECT_KNOWLEDGE_SOURCE.to_string(),
+            provenance: Provenance::UserProvided,
+            content,
+            metadata: Some(MemoryMetadata {
+                file_type: Some("project_knowledge".to_string()),
+                path: None,
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec!["pinned".to_string(), "project_knowledge".to_string()],
+            }),
+        }))
+    }
+
+    /// Refresh the pinned project knowledge fragment in context memory
+    pub async fn pin_into(&self, context_memory: &Mutex<ContextMemory>) -> AgentResult<()> {
+        let fragment = self.to_memory_fragment().await?;
+        let mut memory = context_memory.lock().await;
+
+        match fragment {
+            Some(fragment) => memory.pin_frag(fragment),
+            None => memory.unpin(PROJECT_KNOWLEDGE_SOURCE),
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,80 @@
 pub mod agent_engine;
 pub mod ai;
+pub mod analysis_cache;
+pub mod answers;
+pub mod approval;
+pub mod ask;
+pub mod background;
+pub mod change_summary;
+pub mod changelog;
+pub mod chunking;
+pub mod clarification;
 pub mod cli;
-//pub mod codebase;
+pub mod command_preview;
 pub mod config;
+pub mod context_limit;
+pub mod coverage;
+pub mod crash;
+pub mod crate_docs;
+pub mod crate_source;
+pub mod diagnostics;
+pub mod dirty_tree;
+pub mod editorconfig;
+pub mod edits;
+pub mod embeddings;
 pub mod error;
+pub mod error_lookup;
+pub mod eval;
+pub mod experiments;
+pub mod explanation;
+pub mod file_rank;
+pub mod formatting;
+pub mod fs_guard;
+pub mod git;
+pub mod glob_walk;
+pub mod hunk_review;
+pub mod hybrid_search;
+pub mod intent;
+pub mod interrupt;
+pub mod journal;
+pub mod keys;
+pub mod knowledge;
 pub mod memory;
-//pub mod task;
+pub mod model_router;
+pub mod model_switch;
+pub mod mutants;
+pub mod notifications;
+pub mod output;
+pub mod pagination;
+pub mod platform;
+pub mod pr_description;
+pub mod preferences;
+pub mod profiling;
+pub mod project_context;
+#[cfg(unix)]
+pub mod pty;
+pub mod remote_approval;
+pub mod replanning;
+pub mod report;
+pub mod result_validation;
+pub mod scrollback;
+pub mod self_update;
+pub mod shell_session;
+pub mod storage;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod text_file;
+pub mod theme;
+pub mod tokens;
+pub mod tool_env;
+pub mod usage;
 pub mod utils;
+pub mod vector_store;
+pub mod verification;
+pub mod voice;
+pub mod workflows;
+pub mod workspace_lock;
+pub mod workspaces;
 
 pub mod subtasks;
 
@@ -1,7 +1,47 @@
 use indiefuture_cli::agent_engine::{AgentEngine, SharedState};
-use indiefuture_cli::memory::ContextMemory;
-use indiefuture_cli::{ai::create_ai_client, config::Settings, error::AgentResult, run_cli};
+use indiefuture_cli::ai::cache::CachingAiClient;
+#[cfg(feature = "ollama")]
+use indiefuture_cli::ai::ollama::{self, OllamaConfig};
+use indiefuture_cli::ai::openai::{self, OpenAiConfig};
+use indiefuture_cli::ai::reasoning::ReasoningEffort;
+use indiefuture_cli::ai::retry::{self, RetryPolicy};
+use indiefuture_cli::ai::salvage;
+use indiefuture_cli::answers;
+use indiefuture_cli::approval::ApprovalMode;
+use indiefuture_cli::ask;
+use indiefuture_cli::cli::{parse_args, CliCommand};
+use indiefuture_cli::crash::{self, CrashBundle};
+use indiefuture_cli::crate_docs;
+use indiefuture_cli::dirty_tree::DirtyTreePolicy;
+use indiefuture_cli::error::AgentError;
+use indiefuture_cli::eval::{self, EvalReport, EvalSuite};
+use indiefuture_cli::experiments;
+use indiefuture_cli::git;
+use indiefuture_cli::keys;
+use indiefuture_cli::memory::{self, ContextMemory};
+use indiefuture_cli::model_router::{self, ModelRouter};
+use indiefuture_cli::model_switch::ModelSwitch;
+use indiefuture_cli::output;
+use indiefuture_cli::platform::Shell;
+use indiefuture_cli::pr_description;
+use indiefuture_cli::profiling::{ProfilingAiClient, RunProfiler};
+use indiefuture_cli::remote_approval::RemoteApprover;
+use indiefuture_cli::result_validation;
+use indiefuture_cli::self_update;
+use indiefuture_cli::shell_session;
+use indiefuture_cli::storage::Storage;
+use indiefuture_cli::theme;
+use indiefuture_cli::tool_env::ToolEnv;
+use indiefuture_cli::usage::{UsageTracker, UsageTrackingAiClient};
+use indiefuture_cli::utils;
+use indiefuture_cli::workflows::{self, Workflow};
+use indiefuture_cli::workspace_lock::WorkspaceLock;
+use indiefuture_cli::{
+    ai::create_ai_client, ai::AiClient, config::ProjectSettings, config::Settings,
+    config::UserSettings, error::AgentResult, run_cli,
+};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
 #[tokio::main]
@@ -9,26 +49,367 @@ async fn main() -> AgentResult<()> {
     // Initialize logging
     env_logger::init();
 
+    let args = parse_args(std::env::args().skip(1))?;
+    if args.plain {
+        output::enable_plain();
+    }
+
     // Load settings
     let settings = Settings::load()?;
+    theme::configure(settings.color_theme.parse()?);
+    let user_settings = UserSettings::load()?;
+    keys::configure(user_settings.keys)?;
+    result_validation::configure(user_settings.validators)?;
+    salvage::configure(settings.stream_salvage_attempts);
+    retry::configure(RetryPolicy {
+        max_attempts: settings.ai_retry_attempts,
+        base_delay: Duration::from_millis(settings.ai_retry_base_delay_ms),
+        max_delay: Duration::from_millis(settings.ai_retry_max_delay_ms),
+        jitter: settings.ai_retry_jitter,
+    });
+    #[cfg(feature = "ollama")]
+    ollama::configure(OllamaConfig {
+        url: settings.ollama_url.clone(),
+        embedding_model: settings.ollama_embedding_model.clone(),
+    });
+    openai::configure(OpenAiConfig {
+        base_url: settings.openai_base_url.clone(),
+        api_version: settings.openai_api_version.clone(),
+        deployment: settings.openai_deployment.clone(),
+        embedding_deployment: settings.openai_embedding_deployment.clone(),
+    });
+    if settings.semantic_memory {
+        memory::enable_semantic_ranking();
+    }
+
+    // A panic saves a diagnostic bundle and explains how to recover instead of dumping a trace
+    crash::install(&settings);
+
+    // Updating needs no workspace, model or storage. `--check` exits with status 1 when a
+    // newer release is available, so CI images can tell they are stale.
+    if let CliCommand::Update { check } = args.command {
+        if self_update::run(&settings, check).await? && check {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Commands run in the configured shell, or the platform's default one
+    match &settings.shell {
+        Some(shell) => Shell::configure(Shell::parse(shell, settings.shell_login)?),
+        None if settings.shell_login => Shell::configure(Shell {
+            login: true,
+            ..Shell::platform_default()
+        }),
+        None => {}
+    }
+    if settings.shell_session {
+        shell_session::enable();
+    }
 
-    let ai_client = create_ai_client(
+    // Reasoning models think harder at a higher effort, and take longer
+    let reasoning_effort: Option<ReasoningEffort> = settings
+        .reasoning_effort
+        .as_deref()
+        .map(str::parse)
+        .transpose()?;
+
+    // `/model` switches the model behind this client mid-session
+    let model_switch = ModelSwitch::new(
         &settings.default_ai_provider,
         &settings.default_model,
-        settings.openai_api_key.as_deref().unwrap_or(""),
+        settings.api_key(),
+        reasoning_effort,
     )?;
+    let ai_client: Box<dyn AiClient> = Box::new(model_switch.clone());
+
+    // Plan checkpoints may use a cheaper model than planning and editing
+    let checkpoint_client = match &settings.replan_model {
+        Some(model) => Some(create_ai_client(
+            &settings.default_ai_provider,
+            model,
+            settings.api_key(),
+        )?),
+        None => None,
+    };
+
+    // Planning may run on a fast cheap model while edits and explanations use the default one
+    let model_routes = model_router::parse_routes(settings.model_routes.as_deref().unwrap_or(""))?;
+    let model_router = ModelRouter::new(&model_routes, &settings, reasoning_effort)?;
+
+    // Model latency is measured by wrapping the client
+    let profiler = args.profile_run.then(|| Arc::new(RunProfiler::new()));
+    let profiled = |client: Box<dyn AiClient>| -> Box<dyn AiClient> {
+        match &profiler {
+            Some(profiler) => Box::new(ProfilingAiClient::new(client, profiler.clone())),
+            None => client,
+        }
+    };
+    let ai_client = profiled(ai_client);
+    let checkpoint_client = checkpoint_client.map(profiled);
+    let model_router = model_router.map(profiled);
+
+    // Tokens and cost are counted before the response cache, so cached answers cost nothing
+    let usage = Arc::new(UsageTracker::new());
+    let ai_client: Box<dyn AiClient> =
+        Box::new(UsageTrackingAiClient::new(ai_client, usage.clone()));
+    let tracked = |client| -> Box<dyn AiClient> {
+        Box::new(UsageTrackingAiClient::new(client, usage.clone()))
+    };
+    let checkpoint_client = checkpoint_client.map(tracked);
+    let model_router = model_router.map(tracked);
+
+    // The backend may be unreachable or locked by another session; fall back to in-memory storage
+    let storage = match Storage::connect(&settings).await {
+        Ok(storage) => storage,
+        Err(e) => {
+            log::warn!("{}; using temporary storage for this session", e);
+            Storage::temporary(&settings.memory_user)?
+        }
+    };
+
+    // Repeated identical requests may be answered from storage; evals measure the model instead
+    let (ai_client, checkpoint_client, model_router) = if settings.response_cache
+        && !matches!(args.command, CliCommand::Eval { .. })
+    {
+        let ttl = Duration::from_secs(settings.response_cache_ttl_hours * 3600);
+        let cached =
+            |client| -> Box<dyn AiClient> { Box::new(CachingAiClient::new(client, &storage, ttl)) };
+        (
+            cached(ai_client),
+            checkpoint_client.map(cached),
+            model_router.map(cached),
+        )
+    } else {
+        (ai_client, checkpoint_client, model_router)
+    };
+
+    let workspace_root = std::env::current_dir()?;
+
+    // Project variables are injected into commands and masked wherever output is recorded
+    let project_settings = ProjectSettings::load(&workspace_root)?;
+    ToolEnv::configure(
+        ToolEnv::new(project_settings.env_vars()).with_secrets(project_settings.pass_env.clone()),
+    );
+    if let Some(dir) = &settings.answers_dir {
+        answers::configure(dir.clone());
+    }
+    if let Some(dir) = &settings.crate_docs_cache {
+        crate_docs::configure(dir.clone());
+    }
+    let workspace_id = utils::workspace_id(&workspace_root);
+    let project_id = utils::project_id(&workspace_root);
+    let session_id = utils::generate_id();
+    if settings.prompt_experiments && !user_settings.prompt_variants.is_empty() {
+        experiments::configure(
+            user_settings.prompt_variants,
+            &session_id,
+            settings.experiments_path.clone(),
+        );
+    }
+
+    // Lets concurrent sessions in the same workspace notice each other
+    let workspace_lock = match WorkspaceLock::acquire(
+        &settings.locks_path,
+        &workspace_root,
+        &session_id,
+        &settings.memory_user,
+        // A read-only turn cannot get in another session's way
+        args.force
+            || matches!(
+                args.command,
+                CliCommand::Ask { .. } | CliCommand::Eval { .. }
+            ),
+    ) {
+        Ok(lock) => Some(lock),
+        Err(AgentError::Io(e)) => {
+            log::warn!("Failed to create workspace lock: {}", e);
+            None
+        }
+        Err(e) => return Err(e),
+    };
+
+    if args.force {
+        if let Some(lock) = &workspace_lock {
+            for other in lock.other_sessions() {
+                println!(
+                    "⚠️ Another agent session ({}, pid {}) is active in this workspace",
+                    other.user, other.pid
+                );
+            }
+        }
+    }
 
-    let shared_state = SharedState { ai_client };
+    let shared_state = SharedState {
+        ai_client,
+        checkpoint_client,
+        model_switch: Some(model_switch),
+        model_router,
+        storage,
+        workspace_root,
+        workspace_id,
+        project_id,
+        session_id,
+        workspace_lock,
+        usage: usage.clone(),
+    };
 
     let context_memory = Mutex::new(ContextMemory::default());
-    let agent_engine = Mutex::new(AgentEngine::default());
-
-    // Run CLI interface
-    run_cli(
-        Arc::new(shared_state), //contains ai data
-        Arc::new(context_memory),
-        Arc::new(settings),
-        Arc::new(agent_engine),
-    )
-    .await
+    let approval_mode: ApprovalMode = args
+        .mode
+        .as_deref()
+        .unwrap_or(&settings.approval_mode)
+        .parse()?;
+    let dirty_tree: DirtyTreePolicy = settings.dirty_tree.parse()?;
+
+    let remote_approver = if args.remote_approve {
+        let remote = RemoteApprover::start(
+            &settings.remote_approval_bind,
+            settings.remote_approval_url.clone(),
+            settings.remote_approval_notify_url.clone(),
+            Duration::from_secs(settings.remote_approval_timeout_seconds),
+        )
+        .await?;
+        indiefuture_cli::say!("📱 Approve pending operations at {}", remote.url());
+        Some(Arc::new(remote))
+    } else {
+        None
+    };
+
+    let agent_engine = Mutex::new(AgentEngine {
+        approval_mode,
+        dirty_tree,
+        remote_approver,
+        desktop_notifications: settings.desktop_notifications,
+        explain_commands: settings.explain_commands,
+        profiler: profiler.clone(),
+        ..AgentEngine::default()
+    });
+
+    let shared_state = Arc::new(shared_state); //contains ai data
+    let context_memory = Arc::new(context_memory);
+
+    let result = match args.command {
+        CliCommand::Interactive => {
+            // Run CLI interface
+            run_cli(
+                shared_state,
+                context_memory,
+                Arc::new(settings),
+                Arc::new(agent_engine),
+                None,
+            )
+            .await
+        }
+        CliCommand::Resume { bundle } => {
+            let path = match bundle {
+                Some(path) => path,
+                None => crash::latest(&settings.crash_reports_path)?.ok_or_else(|| {
+                    AgentError::Cli(format!(
+                        "No crash bundle found in {}",
+                        settings.crash_reports_path.display()
+                    ))
+                })?,
+            };
+            let crashed = CrashBundle::load(&path)?;
+            indiefuture_cli::say!("{}\n", crashed.summary());
+
+            // The interrupted task is offered as the first prompt, to edit before it runs
+            run_cli(
+                shared_state,
+                context_memory,
+                Arc::new(settings),
+                Arc::new(agent_engine),
+                crashed.task,
+            )
+            .await
+        }
+        CliCommand::RunWorkflow { name, params } => {
+            let workflow = Workflow::load(&shared_state.workspace_root, &name)?;
+
+            if let Err(e) = shared_state.load_pinned_context(&context_memory).await {
+                log::warn!("Failed to load long-term memory: {}", e);
+            }
+
+            workflows::run_workflow(
+                &workflow,
+                &params,
+                shared_state,
+                context_memory,
+                Arc::new(settings),
+                Arc::new(agent_engine),
+            )
+            .await
+        }
+        CliCommand::Ask { question } => {
+            ask::run_ask(
+                &question,
+                shared_state,
+                context_memory,
+                Arc::new(settings),
+                Arc::new(agent_engine),
+            )
+            .await
+        }
+        CliCommand::PrDesc { base, push } => {
+            let root = &shared_state.workspace_root;
+            let base = base.or_else(|| git::default_branch(root)).ok_or_else(|| {
+                AgentError::Cli("Could not detect the base branch; pass --base".to_string())
+            })?;
+
+            let description =
+                pr_description::generate(shared_state.ai_client.as_ref(), root, &base).await?;
+
+            if push {
+                pr_description::push_to_github(root, &description)?;
+                println!("Updated the pull request description");
+            } else {
+                println!("{}", description);
+            }
+
+            Ok(())
+        }
+        CliCommand::Eval {
+            suite,
+            case,
+            compare,
+        } => {
+            let root = &shared_state.workspace_root;
+            let (suite, fixture) = EvalSuite::load(root, &suite)?;
+            let baseline = compare.as_deref().map(EvalReport::load).transpose()?;
+
+            // A fixed judge keeps scores comparable when the model under test changes
+            let judge_client = match &settings.eval_judge_model {
+                Some(model) => {
+                    create_ai_client(&settings.default_ai_provider, model, settings.api_key())?
+                }
+                None => shared_state.ai_client.clone_box(),
+            };
+
+            let report = eval::run_suite(
+                &suite,
+                &fixture,
+                case.as_deref(),
+                &shared_state,
+                judge_client.as_ref(),
+                Arc::new(settings),
+            )
+            .await?;
+            let path = report.save(root)?;
+            println!("{}", report.render(baseline.as_ref()));
+            println!("Saved to {}", path.display());
+            Ok(())
+        }
+        // Handled before the session starts
+        CliCommand::Update { .. } => Ok(()),
+    };
+
+    if let Some(profiler) = profiler {
+        println!("\n{}", profiler.report());
+    }
+    if !usage.is_empty() {
+        println!("\n{}", usage.summary());
+    }
+
+    result
 }
@@ -0,0 +1,153 @@
+use crate::ai::reasoning::ReasoningEffort;
+use crate::ai::{self, AiClient};
+use crate::config::Settings;
+use crate::error::{AgentError, AgentResult};
+use crate::subtasks::SubTaskType;
+use std::collections::BTreeMap;
+
+/// Providers a route may name before its model, as in `claude:claude-3-5-haiku`. Other
+/// prefixes are part of the model name, like Ollama's `qwen2.5-coder:14b`.
+const PROVIDERS: [&str; 4] = ["openai", "claude", "gemini", "ollama"];
+
+/// The model one kind of subtask runs on instead of the session's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRoute {
+    pub tool: String,             // tool name, e.g. TaskTool, or ProtoSubtask
+    pub provider: Option<String>, // the session's provider if unset
+    pub model: String,
+}
+
+/// Parse routes written as `TaskTool=gpt-4o-mini,ExplainTool=claude:claude-sonnet-4`
+pub fn parse_routes(spec: &str) -> AgentResult<Vec<ModelRoute>> {
+    let routable: Vec<&str> = SubTaskType::all_tools()
+        .iter()
+        .map(SubTaskType::tool_name)
+        .chain(["ProtoSubtask"])
+        .collect();
+
+    spec.split(',')
+        .map(str::trim)
+        .filter(|route| !route.is_empty())
+        .map(|route| {
+            let (tool, model) = route
+                .split_once('=')
+                .map(|(tool, model)| (tool.trim(), model.trim()))
+                .filter(|(_, model)| !model.is_empty())
+                .ok_or_else(|| {
+                    AgentError::Configuration(format!(
+                        "Model route '{}' is not of the form Tool=model",
+                        route
+                    ))
+                })?;
+            if !routable.contains(&tool) {
+                return Err(AgentError::Configuration(format!(
+                    "Model route for unknown tool '{}'; expected one of {}",
+                    tool,
+                    routable.join(", ")
+                )));
+            }
+
+            let (provider, model) = match model.split_once(':') {
+                Some((provider, model)) if PROVIDERS.contains(&provider) => {
+                    (Some(provider.to_string()), model)
+                }
+                _ => (None, model),
+            };
+            Ok(ModelRoute {
+                tool: tool.to_string(),
+                provider,
+                model: model.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Clients for the kinds of subtasks routed to another model than the session's, so cheap
+/// models can plan while the configured model edits. Unrouted subtasks use the session's
+/// client, which `/model` switches; routed ones keep their model.
+#[derive(Default)]
+pub struct ModelRouter {
+    clients: BTreeMap<String, Box<dyn AiClient>>, // by tool name
+}
+
+impl ModelRouter {
+    /// Create a client for each route, with the keys in `settings`
+    pub fn new(
+        routes: &[ModelRoute],
+        settings: &Settings,
+        reasoning_effort: Option<ReasoningEffort>,
+    ) -> AgentResult<Self> {
+        let mut router = Self::default();
+        for route in routes {
+            let provider = route
+                .provider
+                .as_deref()
+                .unwrap_or(&settings.default_ai_provider);
+            let client =
+                ai::create_ai_client(provider, &route.model, settings.api_key_for(provider))?;
+            let client = match reasoning_effort {
+                Some(effort) => client.with_reasoning_effort(effort),
+                None => client,
+            };
+            router = router.route(&route.tool, client);
+        }
+        Ok(router)
+    }
+
+    /// Run subtasks of `tool` on `client`
+    pub fn route(mut self, tool: &str, client: Box<dyn AiClient>) -> Self {
+        self.clients.insert(tool.to_string(), client);
+        self
+    }
+
+    /// The same routes, with each client wrapped by `wrap`
+    pub fn map(self, wrap: impl Fn(Box<dyn AiClient>) -> Box<dyn AiClient>) -> Self {
+        Self {
+            clients: self
+                .clients
+                .into_iter()
+                .map(|(tool, client)| (tool, wrap(client)))
+                .collect(),
+        }
+    }
+
+    /// The client `subtask` runs on: its route's, or `default`
+    pub fn client_for<'a>(
+        &'a self,
+        subtask: &SubTaskType,
+        default: &'a dyn AiClient,
+    ) -> &'a dyn AiClient {
+        self.clients
+            .get(subtask.tool_name())
+            .map_or(default, |client| client.as_ref())
+    }
+
+    /// Routed tools and their `provider:model`
+    pub fn routes(&self) -> Vec<(String, String)> {
+        self.clients
+            .iter()
+            .map(|(tool, client)| {
+                (
+                    tool.clone(),
+                    format!("{}:{}", client.provider_name(), client.model_name()),
+                )
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+}
+
+impl Clone for ModelRouter {
+    fn clone(&self) -> Self {
+        Self {
+            clients: self
+                .clients
+                .iter()
+                .map(|(tool, client)| (tool.clone(), client.clone_box()))
+                .collect(),
+        }
+    }
+}
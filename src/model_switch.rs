@@ -0,0 +1,223 @@
+use crate::ai::reasoning::{self, ReasoningEffort};
+use crate::ai::{self, AiClient, ChatCompletionResponse, GenerationProfile, Message, TextStream};
+use crate::background;
+use crate::error::AgentResult;
+use crate::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+
+/// Rough characters per token, to compare memory sizes with context windows
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Context memory may fill this fraction of a window; prompts, tools and the answer need the rest
+const MEMORY_SHARE_DIVISOR: usize = 2;
+
+/// Source of the pinned fragment listing the steps a run still has queued
+pub const CURRENT_PLAN_SOURCE: &str = "current_plan";
+
+/// Context window of `model` in tokens. Unknown models are assumed to have 128k.
+pub fn context_window(model: &str) -> usize {
+    let model = model.trim().to_lowercase();
+
+    if model.starts_with("gpt-4.1") {
+        1_047_576
+    } else if model.starts_with("gemini") {
+        1_048_576
+    } else if model.starts_with("claude") || reasoning::is_reasoning_model(&model) {
+        200_000
+    } else if model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo") {
+        128_000
+    } else if model.starts_with("gpt-4-32k") {
+        32_768
+    } else if model.starts_with("gpt-4") {
+        8_192
+    } else if model.starts_with("gpt-3.5") {
+        16_385
+    } else {
+        128_000
+    }
+}
+
+/// Characters of context memory, pinned fragments included, that fit `model`'s prompts
+pub fn memory_budget(model: &str) -> usize {
+    context_window(model) * CHARS_PER_TOKEN / MEMORY_SHARE_DIVISOR
+}
+
+/// The session's AI client behind a switch, so `/model` can change models mid-session.
+/// Clones share the switch.
+#[derive(Clone)]
+pub struct ModelSwitch {
+    provider: String,
+    api_key: String,
+    reasoning_effort: Option<ReasoningEffort>,
+    current: Arc<RwLock<Box<dyn AiClient>>>,
+}
+
+impl ModelSwitch {
+    pub fn new(
+        provider: &str,
+        model: &str,
+        api_key: &str,
+        reasoning_effort: Option<ReasoningEffort>,
+    ) -> AgentResult<Self> {
+        Ok(Self {
+            provider: provider.to_string(),
+            api_key: api_key.to_string(),
+            reasoning_effort,
+            current: Arc::new(RwLock::new(create_client(
+                provider,
+                model,
+                api_key,
+                reasoning_effort,
+            )?)),
+        })
+    }
+
+    /// Use `model` from now on, with the same provider and reasoning effort
+    pub fn switch(&self, model: &str) -> AgentResult<()> {
+        let client = create_client(&self.provider, model, &self.api_key, self.reasoning_effort)?;
+        *self.current.write().unwrap() = client;
+        Ok(())
+    }
+
+    /// The client in use right now
+    pub fn current(&self) -> Box<dyn AiClient> {
+        self.current.read().unwrap().clone_box()
+    }
+}
+
+fn create_client(
+    provider: &str,
+    model: &str,
+    api_key: &str,
+    reasoning_effort: Option<ReasoningEffort>,
+) -> AgentResult<Box<dyn AiClient>> {
+    let client = ai::create_ai_client(provider, model, api_key)?;
+    Ok(match reasoning_effort {
+        Some(effort) => client.with_reasoning_effort(effort),
+        None => client,
+    })
+}
+
+#[async_trait]
+impl AiClient for ModelSwitch {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        self.current().generate_text(messages).await
+    }
+
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        self.current().stream_text(messages).await
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        messages: Vec<Message>,
+        functions: serde_json::Value,
+        force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        self.current()
+            .chat_completion_with_functions(messages, functions, force_message_only)
+            .await
+    }
+
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        self.current().generate_embeddings(text).await
+    }
+
+    fn provider_name(&self) -> String {
+        self.current().provider_name()
+    }
+
+    fn embedding_model(&self) -> String {
+        self.current().embedding_model()
+    }
+
+    fn model_name(&self) -> String {
+        self.current().model_name()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(self.clone())
+    }
+
+    /// Profiles and efforts apply to the model in use when they are asked for
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        self.current().with_profile(profile)
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        self.current().with_reasoning_effort(effort)
+    }
+}
+
+/// What fitting context memory into a new model's window took
+#[derive(Debug, Default)]
+pub struct Handoff {
+    pub compacted: usize,             // fragments folded into a summary
+    pub dropped: Vec<MemoryFragment>, // fragments dropped when the summary was not enough
+    pub pinned_over_budget: bool,     // pinned context alone does not fit
+}
+
+/// Fit context memory into `budget` characters, first by having `ai_client` summarize older
+/// fragments, then by dropping the oldest remaining ones
+pub async fn fit_memory(
+    ai_client: &dyn AiClient,
+    context_memory: &Mutex<ContextMemory>,
+    budget: usize,
+) -> AgentResult<Handoff> {
+    let (folded, unpinned_budget, pinned_over_budget) = {
+        let memory = context_memory.lock().await;
+        let unpinned_budget = budget.saturating_sub(memory.pinned_len());
+        let handoff_needed = memory.content_len() > unpinned_budget;
+        let folded = if handoff_needed {
+            memory.compactable(background::KEEP_RECENT_FRAGMENTS)
+        } else {
+            Vec::new()
+        };
+        (folded, unpinned_budget, memory.pinned_len() > budget)
+    };
+
+    let mut handoff = Handoff {
+        pinned_over_budget,
+        ..Handoff::default()
+    };
+
+    // The summary is read by the new model, so it gets no more than fits its window
+    if folded.len() >= 2 {
+        let summary = background::summarize_fragments(ai_client, &folded, unpinned_budget).await?;
+        if context_memory.lock().await.compact(&folded, summary) {
+            handoff.compacted = folded.len();
+        }
+    }
+
+    handoff.dropped = context_memory.lock().await.fit_to(unpinned_budget);
+    Ok(handoff)
+}
+
+/// A pinned fragment listing the steps a run still has queued, next first
+pub fn plan_fragment(steps: &[String]) -> Option<MemoryFragment> {
+    if steps.is_empty() {
+        return None;
+    }
+
+    Some(MemoryFragment {
+        source: CURRENT_PLAN_SOURCE.to_string(),
+        provenance: Provenance::ModelGenerated,
+        content: format!(
+            "Current plan, next step first:\n{}",
+            steps
+                .iter()
+                .map(|step| format!("- {}", step))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("plan".to_string()),
+            path: None,
+            timestamp: Some(Utc::now().timestamp()),
+            tags: vec!["pinned".to_string()],
+        }),
+    })
+}
@@ -0,0 +1,171 @@
+use crate::error::{AgentError, AgentResult};
+use crate::memory::{MemoryFragment, MemoryMetadata, Provenance};
+use crate::tool_env;
+use crate::utils;
+use std::fmt;
+use std::path::Path;
+
+/// Source of the fragment listing the mutants the tests did not catch
+pub const MUTANTS_SOURCE: &str = "mutants";
+/// Where cargo-mutants writes `mutants.out`, relative to the workspace
+const OUTPUT_DIR: &str = "target";
+/// Surviving mutants listed in context and in the task
+const MAX_LISTED_MUTANTS: usize = 30;
+
+/// A change to the code that the test suite still passed with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mutant {
+    pub file: String, // relative to the workspace
+    pub line: usize,
+    pub description: String, // e.g. `replace add -> i32 with 0`
+}
+
+impl fmt::Display for Mutant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.description)
+    }
+}
+
+/// Mutants listed in `mutants.out/missed.txt`, one per line as `file:line:column: change`;
+/// older cargo-mutants releases leave out the column
+pub fn parse_missed(text: &str) -> Vec<Mutant> {
+    text.lines()
+        .filter_map(|line| {
+            let (location, description) = line.trim().split_once(": ")?;
+            let mut parts = location.split(':');
+            let file = parts.next()?;
+            let line = parts.next()?.parse().ok()?;
+            Some(Mutant {
+                file: file.to_string(),
+                line,
+                description: description.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Library and binary sources among `files`; mutating tests, examples or build scripts
+/// say nothing about the tests
+pub fn mutable_files(files: &[String]) -> Vec<String> {
+    let mut mutable: Vec<String> = files
+        .iter()
+        .filter(|file| file.ends_with(".rs") && file.as_str() != "build.rs")
+        .filter(|file| {
+            !Path::new(file.as_str()).components().any(|c| {
+                matches!(
+                    c.as_os_str().to_str(),
+                    Some("tests" | "benches" | "examples" | "target")
+                )
+            })
+        })
+        .cloned()
+        .collect();
+    mutable.sort();
+    mutable.dedup();
+    mutable
+}
+
+/// Run cargo-mutants on `files` of the Cargo project at `root` and return the mutants the
+/// tests missed
+pub async fn run(root: &Path, files: &[String]) -> AgentResult<Vec<Mutant>> {
+    if !root.join("Cargo.toml").is_file() {
+        return Err(AgentError::ToolExecution(
+            "Mutation testing needs a Cargo project".to_string(),
+        ));
+    }
+    let installed = tokio::process::Command::new("cargo")
+        .args(["mutants", "--version"])
+        .current_dir(root)
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success());
+    if !installed {
+        return Err(AgentError::ToolExecution(
+            "cargo-mutants is not installed; install it with `cargo install cargo-mutants`"
+                .to_string(),
+        ));
+    }
+
+    let mut command = tokio::process::Command::new("cargo");
+    command
+        .args(["mutants", "--no-shuffle", "--output", OUTPUT_DIR])
+        .current_dir(root);
+    for file in files {
+        command.args(["--file", file]);
+    }
+    let output = command
+        .output()
+        .await
+        .map_err(|e| AgentError::ToolExecution(format!("Failed to run cargo mutants: {}", e)))?;
+
+    // 2 means some mutants were missed and 3 that some timed out; both still report
+    match output.status.code() {
+        Some(0 | 2 | 3) => {}
+        Some(4) => {
+            return Err(AgentError::ToolExecution(
+                "The tests fail without any mutation; fix them before mutation testing".to_string(),
+            ))
+        }
+        _ => {
+            let stderr = tool_env::mask(&String::from_utf8_lossy(&output.stderr));
+            return Err(AgentError::ToolExecution(format!(
+                "cargo mutants failed:\n{}",
+                utils::truncate_string(stderr.trim(), 2_000)
+            )));
+        }
+    }
+
+    let missed = root.join(OUTPUT_DIR).join("mutants.out").join("missed.txt");
+    Ok(std::fs::read_to_string(missed)
+        .map(|text| parse_missed(&text))
+        .unwrap_or_default())
+}
+
+fn listing(mutants: &[Mutant]) -> String {
+    let mut lines: Vec<String> = mutants
+        .iter()
+        .take(MAX_LISTED_MUTANTS)
+        .map(|m| format!("- {}", m))
+        .collect();
+    if mutants.len() > MAX_LISTED_MUTANTS {
+        lines.push(format!(
+            "- ... and {} more",
+            mutants.len() - MAX_LISTED_MUTANTS
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Context fragment listing the surviving mutants
+pub fn fragment(mutants: &[Mutant]) -> MemoryFragment {
+    let mut files: Vec<&str> = mutants.iter().map(|m| m.file.as_str()).collect();
+    files.sort();
+    files.dedup();
+
+    MemoryFragment {
+        source: MUTANTS_SOURCE.to_string(),
+        provenance: Provenance::ToolOutput,
+        content: format!(
+            "cargo-mutants made {} changes to the code that every test still passed with:\n{}",
+            mutants.len(),
+            listing(mutants)
+        ),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("mutants".to_string()),
+            path: None,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            tags: files.iter().map(|file| format!("file:{}", file)).collect(),
+        }),
+    }
+}
+
+/// A task strengthening the tests so each surviving mutant would fail them
+pub fn strengthen_task(mutants: &[Mutant]) -> String {
+    format!(
+        "The tests pass, but they also pass with these changes to the code, so they do not \
+        check its behaviour. Strengthen the tests (add assertions on return values and effects, \
+        or new cases) so that each of these mutants would fail. Change only test code; the \
+        mutants are not bugs to fix.\n{}",
+        listing(mutants)
+    )
+}
@@ -0,0 +1,145 @@
+use crate::config::Settings;
+use crate::error::AgentResult;
+use crate::utils;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// What happened while the engine drained its subtask stack
+#[derive(Debug, Clone, Default)]
+pub struct RunStats {
+    pub operations: usize, // subtasks that were executed
+    pub failed: usize,     // executed subtasks whose tool reported a failure
+    pub declined: bool,    // the run stopped because an operation was declined
+    pub files_changed: Vec<String>,
+}
+
+impl RunStats {
+    /// Fold the stats of a later run into this one
+    pub fn merge(&mut self, other: RunStats) {
+        self.operations += other.operations;
+        self.failed += other.failed;
+        self.declined |= other.declined;
+        for file in other.files_changed {
+            if !self.files_changed.contains(&file) {
+                self.files_changed.push(file);
+            }
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        if self.declined {
+            "declined"
+        } else if self.failed > 0 {
+            "failed"
+        } else {
+            "completed"
+        }
+    }
+}
+
+/// JSON body posted to the notification webhook at the end of a turn or batch task
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub prompt: String,
+    pub status: String,
+    pub files_changed: Vec<String>,
+    pub operations: usize,
+    pub failed_operations: usize,
+    pub cost_usd: Option<f64>, // null when a model used has no known price
+    pub duration_seconds: f64,
+    pub session_id: String,
+    pub user: String,
+    pub timestamp: String,
+}
+
+impl RunSummary {
+    pub fn new(
+        prompt: &str,
+        stats: &RunStats,
+        started: Instant,
+        cost_usd: Option<f64>,
+        session_id: &str,
+        user: &str,
+    ) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            status: stats.status().to_string(),
+            files_changed: stats.files_changed.clone(),
+            operations: stats.operations,
+            failed_operations: stats.failed,
+            cost_usd,
+            duration_seconds: started.elapsed().as_secs_f64(),
+            session_id: session_id.to_string(),
+            user: user.to_string(),
+            timestamp: utils::current_timestamp(),
+        }
+    }
+}
+
+/// POST a run summary to `webhook_url`
+pub async fn post_run_summary(webhook_url: &str, summary: &RunSummary) -> AgentResult<()> {
+    reqwest::Client::new()
+        .post(webhook_url)
+        .timeout(Duration::from_secs(10))
+        .json(summary)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Report a finished run: post it to the webhook if one is configured, and show a
+/// desktop notification if it ran long enough for the user to have switched away.
+/// Failures are logged, never fatal.
+pub async fn notify_run(settings: &Settings, summary: RunSummary) {
+    if settings.desktop_notifications
+        && summary.duration_seconds >= settings.desktop_notify_after_seconds as f64
+    {
+        desktop_notify(
+            &format!("Agent run {}", summary.status),
+            &format!(
+                "{} ({} operations, {} files changed)",
+                utils::truncate_string(&summary.prompt, 80),
+                summary.operations,
+                summary.files_changed.len()
+            ),
+        );
+    }
+
+    let Some(webhook_url) = &settings.notification_webhook_url else {
+        return;
+    };
+
+    if let Err(e) = post_run_summary(webhook_url, &summary).await {
+        log::warn!("Failed to post run summary to webhook: {}", e);
+    }
+}
+
+/// Show a desktop notification without blocking the caller
+#[cfg(feature = "desktop-notifications")]
+pub fn desktop_notify(title: &str, body: &str) {
+    let title = title.to_string();
+    let body = body.to_string();
+
+    std::thread::spawn(move || {
+        if let Err(e) = notify_rust::Notification::new()
+            .appname("indiefuture")
+            .summary(&title)
+            .body(&body)
+            .show()
+        {
+            log::warn!("Failed to show desktop notification: {}", e);
+        }
+    });
+}
+
+/// Builds without desktop notification support only log them
+#[cfg(not(feature = "desktop-notifications"))]
+pub fn desktop_notify(title: &str, body: &str) {
+    log::debug!(
+        "Desktop notification (not supported in this build): {}: {}",
+        title,
+        body
+    );
+}
@@ -0,0 +1,330 @@
+use crate::theme;
+use cliclack::{StringCursor, Theme, ThemeState};
+use console::Style;
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--plain`: no emoji, box drawing or spinners, for screen readers and simple terminals
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Switch every prompt, log line and spinner to plain text for the rest of the process
+pub fn enable_plain() {
+    PLAIN.store(true, Ordering::Relaxed);
+    cliclack::set_theme(PlainTheme);
+    console::set_colors_enabled(false);
+    colored::control::set_override(false);
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Tool calls and other details of the agent's work are printed while set
+static VERBOSE: AtomicBool = AtomicBool::new(true);
+
+pub fn is_verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Flip verbose output, returning whether it is now on
+pub fn toggle_verbose() -> bool {
+    !VERBOSE.fetch_xor(true, Ordering::Relaxed)
+}
+
+/// `println!`, with emoji and box drawing removed in plain mode
+#[macro_export]
+macro_rules! say {
+    () => {
+        println!()
+    };
+    ($($arg:tt)*) => {
+        println!("{}", $crate::output::plain(&format!($($arg)*)))
+    };
+}
+
+/// `text` as printed in the current mode and color theme
+pub fn plain(text: &str) -> Cow<'_, str> {
+    if is_plain() {
+        Cow::Owned(plain_text(text))
+    } else if !theme::current().shows_emoji() {
+        Cow::Owned(without_emoji(text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Emoji and pictographs, which screen readers either skip or spell out
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF    // emoji and pictographs
+            | 0x2300..=0x23FF // technical symbols such as ⏸
+            | 0x2600..=0x27BF // miscellaneous symbols and dingbats, including ✓ and ✏
+            | 0x2B00..=0x2BFF // arrows and stars drawn as emoji
+            | 0xFE0F          // emoji presentation selector
+            | 0x200D          // joiner between the parts of one emoji
+            | 0x20E3 // keycap
+    )
+}
+
+/// Emoji, box drawing and the shapes prompts are framed with
+fn is_decoration(c: char) -> bool {
+    is_emoji(c) || matches!(c as u32, 0x2500..=0x25FF | 0x2A2F)
+}
+
+/// `text` without decorations and the space each one was followed by
+pub fn plain_text(text: &str) -> String {
+    strip(text, is_decoration)
+}
+
+/// `text` without emoji and the space each one was followed by, for the no-emoji theme
+pub fn without_emoji(text: &str) -> String {
+    strip(text, is_emoji)
+}
+
+fn strip(text: &str, removed_char: fn(char) -> bool) -> String {
+    let mut kept = String::with_capacity(text.len());
+    let mut removed = false;
+    for c in text.chars() {
+        if removed_char(c) {
+            removed = true;
+        } else if c == ' ' && removed {
+            removed = false;
+        } else {
+            removed = false;
+            kept.push(c);
+        }
+    }
+    kept
+}
+
+/// A spinner, or in plain mode one line when work starts and one when it ends
+pub enum Spinner {
+    Animated(cliclack::ProgressBar),
+    Plain,
+}
+
+pub fn spinner() -> Spinner {
+    if is_plain() {
+        Spinner::Plain
+    } else {
+        Spinner::Animated(cliclack::spinner())
+    }
+}
+
+impl Spinner {
+    pub fn start(&self, message: impl Display) {
+        match self {
+            Self::Animated(spin) => spin.start(message),
+            Self::Plain => labeled("Working:", message),
+        }
+    }
+
+    /// Replace the message; plain mode stays quiet rather than repeat a line per update
+    pub fn set_message(&self, message: impl Display) {
+        if let Self::Animated(spin) = self {
+            spin.set_message(message);
+        }
+    }
+
+    pub fn stop(&self, message: impl Display) {
+        match self {
+            Self::Animated(spin) => spin.stop(message),
+            Self::Plain => labeled("Done:", message),
+        }
+    }
+
+    pub fn error(&self, message: impl Display) {
+        match self {
+            Self::Animated(spin) => spin.error(message),
+            Self::Plain => labeled("Error:", message),
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Self::Animated(spin) = self {
+            spin.clear();
+        }
+    }
+}
+
+fn labeled(label: &str, message: impl Display) {
+    let message = plain_text(&message.to_string());
+    if !message.trim().is_empty() {
+        println!("{} {}", label, message.trim());
+    }
+}
+
+/// cliclack theme labelling each line in words instead of symbols and frame bars
+struct PlainTheme;
+
+impl Theme for PlainTheme {
+    fn bar_color(&self, _state: &ThemeState) -> Style {
+        Style::new()
+    }
+
+    fn state_symbol_color(&self, _state: &ThemeState) -> Style {
+        Style::new()
+    }
+
+    fn state_symbol(&self, _state: &ThemeState) -> String {
+        String::new()
+    }
+
+    fn radio_symbol(&self, state: &ThemeState, selected: bool) -> String {
+        match state {
+            ThemeState::Active | ThemeState::Error(_) if selected => ">".to_string(),
+            ThemeState::Active | ThemeState::Error(_) => " ".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn checkbox_symbol(&self, state: &ThemeState, selected: bool, active: bool) -> String {
+        let mark = if selected { "[x]" } else { "[ ]" };
+        match state {
+            ThemeState::Active | ThemeState::Error(_) if active => format!(">{}", mark),
+            ThemeState::Active | ThemeState::Error(_) => format!(" {}", mark),
+            _ => String::new(),
+        }
+    }
+
+    fn remark_symbol(&self) -> String {
+        "Note:".to_string()
+    }
+
+    fn info_symbol(&self) -> String {
+        "Info:".to_string()
+    }
+
+    fn warning_symbol(&self) -> String {
+        "Warning:".to_string()
+    }
+
+    fn error_symbol(&self) -> String {
+        "Error:".to_string()
+    }
+
+    fn active_symbol(&self) -> String {
+        "Done:".to_string()
+    }
+
+    fn submit_symbol(&self) -> String {
+        "Step:".to_string()
+    }
+
+    fn password_mask(&self) -> char {
+        '*'
+    }
+
+    fn format_intro(&self, title: &str) -> String {
+        format!("{}\n\n", plain_text(title))
+    }
+
+    fn format_outro(&self, message: &str) -> String {
+        format!("{}\n", plain_text(message))
+    }
+
+    fn format_outro_cancel(&self, message: &str) -> String {
+        format!("Cancelled: {}\n", plain_text(message))
+    }
+
+    fn format_header(&self, _state: &ThemeState, prompt: &str) -> String {
+        format!("{}\n", plain_text(prompt))
+    }
+
+    fn format_footer_with_message(&self, state: &ThemeState, message: &str) -> String {
+        match state {
+            ThemeState::Active if message.is_empty() => String::new(),
+            ThemeState::Active => format!("{}\n", plain_text(message)),
+            ThemeState::Cancel => "Cancelled.\n".to_string(),
+            ThemeState::Submit => "\n".to_string(),
+            ThemeState::Error(err) => format!("Error: {}\n", err),
+        }
+    }
+
+    fn format_input(&self, state: &ThemeState, cursor: &StringCursor) -> String {
+        let input = match state {
+            ThemeState::Active | ThemeState::Error(_) => {
+                self.cursor_with_style(cursor, &Style::new())
+            }
+            _ => cursor.to_string(),
+        };
+        format!("Answer: {}\n", input.trim_end_matches('\n'))
+    }
+
+    fn format_placeholder(&self, state: &ThemeState, cursor: &StringCursor) -> String {
+        match state {
+            ThemeState::Cancel => "Answer:\n".to_string(),
+            ThemeState::Active | ThemeState::Error(_) => format!(
+                "Answer: {}\n",
+                self.cursor_with_style(cursor, &Style::new())
+            ),
+            _ => format!("Answer: {}\n", cursor),
+        }
+    }
+
+    fn format_select_item(
+        &self,
+        state: &ThemeState,
+        selected: bool,
+        label: &str,
+        hint: &str,
+    ) -> String {
+        match state {
+            ThemeState::Cancel | ThemeState::Submit if !selected => String::new(),
+            ThemeState::Cancel | ThemeState::Submit => format!("Chosen: {}\n", plain_text(label)),
+            _ => format!(
+                "{}\n",
+                self.radio_item(state, selected, &plain_text(label), hint)
+            ),
+        }
+    }
+
+    fn format_multiselect_item(
+        &self,
+        state: &ThemeState,
+        selected: bool,
+        active: bool,
+        label: &str,
+        hint: &str,
+    ) -> String {
+        match state {
+            ThemeState::Cancel | ThemeState::Submit if !selected => String::new(),
+            ThemeState::Cancel | ThemeState::Submit => format!("Chosen: {}\n", plain_text(label)),
+            _ => format!(
+                "{}\n",
+                self.checkbox_item(state, selected, active, &plain_text(label), hint)
+            ),
+        }
+    }
+
+    fn format_confirm(&self, state: &ThemeState, confirm: bool) -> String {
+        match state {
+            ThemeState::Active | ThemeState::Error(_) => format!(
+                "{} / {}\n",
+                self.radio_item(state, confirm, "Yes", ""),
+                self.radio_item(state, !confirm, "No", "")
+            ),
+            _ => format!("Chosen: {}\n", if confirm { "Yes" } else { "No" }),
+        }
+    }
+
+    fn format_note_generic(&self, _is_outro: bool, prompt: &str, message: &str) -> String {
+        format!("{}:\n{}\n\n", plain_text(prompt), plain_text(message))
+    }
+
+    fn format_log_with_spacing(&self, text: &str, symbol: &str, spacing: bool) -> String {
+        let text = plain_text(text);
+        let mut lines = text.lines();
+        let mut formatted = format!("{} {}\n", symbol, lines.next().unwrap_or_default());
+        for line in lines {
+            formatted.push_str(line);
+            formatted.push('\n');
+        }
+        if spacing {
+            formatted.push('\n');
+        }
+        formatted
+    }
+}
@@ -0,0 +1,63 @@
+/// Results returned per page when the model does not ask for a different size
+pub const DEFAULT_MAX_RESULTS: usize = 100;
+
+/// One page of a result list, plus a trailer telling the model how to get more
+#[derive(Debug, Clone)]
+pub struct Page<'a, T> {
+    pub items: &'a [T],
+    pub trailer: Option<String>,
+}
+
+/// Slice `items` into 1-based `page` of `max_results` entries.
+/// `total_known` is false when the producer stopped early, so the total is a lower bound.
+pub fn paginate<T>(
+    items: &[T],
+    max_results: Option<usize>,
+    page: Option<usize>,
+    total_known: bool,
+) -> Page<'_, T> {
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1);
+    let page = page.unwrap_or(1).max(1);
+    let start = (page - 1).saturating_mul(max_results).min(items.len());
+    let end = start.saturating_add(max_results).min(items.len());
+    let total = if total_known {
+        items.len().to_string()
+    } else {
+        format!("at least {}", items.len())
+    };
+
+    let trailer = if start == items.len() && page > 1 {
+        Some(format!(
+            "Page {} is past the end; there are {} results.",
+            page, total
+        ))
+    } else if end < items.len() {
+        Some(format!(
+            "{}{} more results omitted; refine the pattern or request page {}.",
+            items.len() - end,
+            if total_known { "" } else { "+" },
+            page + 1
+        ))
+    } else if !total_known {
+        Some(
+            "Search stopped early; more results may exist, refine the pattern to see them."
+                .to_string(),
+        )
+    } else {
+        None
+    };
+
+    Page {
+        items: &items[start..end],
+        trailer,
+    }
+}
+
+/// Entries needed to fill `page` and tell whether a later page exists
+pub fn entries_needed(max_results: Option<usize>, page: Option<usize>) -> usize {
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1);
+    page.unwrap_or(1)
+        .max(1)
+        .saturating_mul(max_results)
+        .saturating_add(1)
+}
@@ -0,0 +1,206 @@
+use crate::error::{AgentError, AgentResult};
+use crate::memory::{MemoryFragment, MemoryMetadata, Provenance};
+use crate::tool_env::ToolEnv;
+use chrono::Utc;
+use std::path::Path;
+use std::sync::OnceLock;
+use tokio::process::Command;
+
+/// Source of the pinned fragment describing the machine commands run on
+pub const ENVIRONMENT_SOURCE: &str = "environment";
+
+/// Kind of shell, which decides how a command string is passed to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+    Cmd,
+}
+
+/// Shell that runs BashTool commands, workflow gates and verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shell {
+    pub kind: ShellKind,
+    pub program: String,
+    pub args: Vec<String>, // extra arguments from the configured command line, e.g. `-i`
+    pub login: bool,       // start as a login shell so the user's profile is sourced
+}
+
+static SHELL: OnceLock<Shell> = OnceLock::new();
+
+impl Shell {
+    /// `sh` everywhere but Windows, where PowerShell 7 is preferred over `cmd`
+    pub fn platform_default() -> Shell {
+        let kind = if !cfg!(windows) {
+            ShellKind::Sh
+        } else if on_path("pwsh.exe") {
+            ShellKind::PowerShell
+        } else {
+            ShellKind::Cmd
+        };
+        let program = match kind {
+            ShellKind::PowerShell => "pwsh",
+            ShellKind::Cmd => "cmd",
+            _ => "sh",
+        };
+
+        Shell {
+            kind,
+            program: program.to_string(),
+            args: Vec::new(),
+            login: false,
+        }
+    }
+
+    /// Parse a configured shell such as `bash -l`, `zsh` or `/opt/homebrew/bin/fish`.
+    /// Unknown programs are assumed to take `-c` like `sh`.
+    pub fn parse(command_line: &str, login: bool) -> AgentResult<Shell> {
+        let mut words = command_line.split_whitespace();
+        let program = words.next().ok_or_else(|| {
+            AgentError::Configuration("The configured shell is empty".to_string())
+        })?;
+        let args: Vec<String> = words.map(str::to_string).collect();
+
+        let name = Path::new(program)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(program)
+            .to_lowercase();
+        let kind = match name.as_str() {
+            "bash" => ShellKind::Bash,
+            "zsh" => ShellKind::Zsh,
+            "fish" => ShellKind::Fish,
+            "pwsh" | "powershell" => ShellKind::PowerShell,
+            "cmd" => ShellKind::Cmd,
+            _ => ShellKind::Sh,
+        };
+        let login = login || args.iter().any(|arg| arg == "-l" || arg == "--login");
+
+        Ok(Shell {
+            kind,
+            program: program.to_string(),
+            args: args
+                .into_iter()
+                .filter(|arg| arg != "-l" && arg != "--login")
+                .collect(),
+            login,
+        })
+    }
+
+    /// Use `shell` for every command this session; only the first call has an effect
+    pub fn configure(shell: Shell) {
+        let _ = SHELL.set(shell);
+    }
+
+    /// The configured shell, or the platform default when none was configured
+    pub fn current() -> &'static Shell {
+        SHELL.get_or_init(Shell::platform_default)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            ShellKind::Sh => "sh",
+            ShellKind::Bash => "bash",
+            ShellKind::Zsh => "zsh",
+            ShellKind::Fish => "fish",
+            ShellKind::PowerShell => "PowerShell",
+            ShellKind::Cmd => "cmd",
+        }
+    }
+
+    /// A command that runs `command` in this shell
+    pub fn command(&self, command: &str) -> Command {
+        let mut process = Command::new(&self.program);
+        match self.kind {
+            // PowerShell loads the user's profile unless told not to
+            ShellKind::PowerShell => {
+                if !self.login {
+                    process.arg("-NoProfile");
+                }
+                process
+                    .args(&self.args)
+                    .args(["-NonInteractive", "-Command"]);
+            }
+            ShellKind::Cmd => {
+                process.args(&self.args).arg("/C");
+            }
+            _ => {
+                if self.login {
+                    process.arg("-l");
+                }
+                process.args(&self.args).arg("-c");
+            }
+        }
+        process.arg(command);
+        process
+    }
+}
+
+/// Run `command` in the configured shell, with the project's environment variables
+pub fn shell_command(command: &str) -> Command {
+    let mut process = Shell::current().command(command);
+    ToolEnv::current().apply(&mut process);
+    process
+}
+
+/// Path as shown to the model: forward slashes on every platform, so outputs look alike and
+/// can be fed straight back into tool inputs (Windows accepts `/` as a separator)
+pub fn display_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+/// Pinned context telling the planner which OS and shell its commands will run on
+pub fn environment_fragment(workspace_root: &Path) -> MemoryFragment {
+    let shell = Shell::current();
+    let syntax = match shell.kind {
+        ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh => "POSIX shell syntax",
+        ShellKind::Fish => "fish syntax (e.g. `set -x NAME value`, `; and` between commands)",
+        ShellKind::PowerShell => {
+            "PowerShell syntax (e.g. Get-ChildItem, $env:NAME, `;` or `&&` between commands)"
+        }
+        ShellKind::Cmd => "cmd.exe syntax (e.g. dir, type, %NAME%, `&&` between commands)",
+    };
+
+    let mut content = format!(
+        "Environment: {} ({}). BashTool commands run in {}, so use {}. \
+         Workspace root: {}. Use forward slashes in file paths.",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        shell.name(),
+        syntax,
+        display_path(workspace_root)
+    );
+    let names: Vec<&str> = ToolEnv::current().names().collect();
+    if !names.is_empty() {
+        content.push_str(&format!(
+            " These project variables are already set for commands (values hidden): {}.",
+            names.join(", ")
+        ));
+    }
+
+    MemoryFragment {
+        source: ENVIRONMENT_SOURCE.to_string(),
+        provenance: Provenance::ToolOutput,
+        content,
+        metadata: Some(MemoryMetadata {
+            file_type: None,
+            path: None,
+            timestamp: Some(Utc::now().timestamp()),
+            tags: vec!["environment".to_string()],
+        }),
+    }
+}
+
+/// Whether an executable named `program` is in one of the PATH directories
+pub fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
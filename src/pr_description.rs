@@ -0,0 +1,161 @@
+use crate::ai::{AiClient, Message, MessageRole};
+use crate::error::{AgentError, AgentResult};
+use crate::git::{self, FileChange};
+use crate::utils;
+use std::path::Path;
+
+/// Per-file diffs are truncated so large branches still fit in one prompt
+const MAX_DIFF_CHARS_PER_FILE: usize = 4000;
+
+/// Whether a changed path is test code
+fn is_test_path(path: &str) -> bool {
+    path.starts_with("tests/")
+        || path.contains("/tests/")
+        || path.contains("_test.")
+        || path.contains(".test.")
+        || path.contains("test_")
+        || path.starts_with("benches/")
+}
+
+/// Name of the module a source or test file is about, e.g. `parser` for `src/parser/mod.rs`,
+/// `tests/parser.rs` and `parser_test.rs`
+fn module_name(path: &str) -> String {
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let stem = match stem {
+        "mod" | "lib" | "index" => path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|s| s.to_str())
+            .unwrap_or(stem),
+        _ => stem,
+    };
+    let stem = stem.split('.').next().unwrap_or(stem);
+    let stem = stem.strip_prefix("test_").unwrap_or(stem);
+    let stem = stem.strip_suffix("_test").unwrap_or(stem);
+    stem.strip_suffix("_tests").unwrap_or(stem).to_string()
+}
+
+/// Facts about test changes, so the model does not have to guess them from the diff
+pub fn coverage_notes(changes: &[FileChange]) -> String {
+    let (tests, sources): (Vec<&FileChange>, Vec<&FileChange>) =
+        changes.iter().partition(|c| is_test_path(&c.path));
+
+    let mut notes = String::new();
+
+    if tests.is_empty() {
+        notes.push_str("No test files were changed on this branch.\n");
+    } else {
+        notes.push_str("Test files changed:\n");
+        for change in &tests {
+            notes.push_str(&format!(
+                "- {} (+{} -{})\n",
+                change.path, change.added, change.removed
+            ));
+        }
+    }
+
+    // New code counts as tested when a test file for the same module changed too
+    let tested_modules: Vec<String> = tests.iter().map(|c| module_name(&c.path)).collect();
+    let untested: Vec<&str> = sources
+        .iter()
+        .filter(|c| c.path.ends_with(".rs") && c.added > 0)
+        .filter(|c| !tested_modules.contains(&module_name(&c.path)))
+        .map(|c| c.path.as_str())
+        .collect();
+
+    if !tests.is_empty() || !untested.is_empty() {
+        notes.push_str(&format!(
+            "Source files with new code and no matching test change: {}\n",
+            if untested.is_empty() {
+                "none".to_string()
+            } else {
+                untested.join(", ")
+            }
+        ));
+    }
+
+    notes
+}
+
+/// Generate a pull request description for the current branch against `base`
+pub async fn generate(
+    ai_client: &dyn AiClient,
+    workspace_root: &Path,
+    base: &str,
+) -> AgentResult<String> {
+    let changes = git::changed_files(workspace_root, base)?;
+
+    if changes.is_empty() {
+        return Err(AgentError::TaskExecution(format!(
+            "No changes between {} and HEAD",
+            base
+        )));
+    }
+
+    let mut diffs = String::new();
+    for change in &changes {
+        let diff = git::file_diff(workspace_root, base, &change.path)?;
+        diffs.push_str(&format!(
+            "=== {} (+{} -{}) ===\n{}\n\n",
+            change.path,
+            change.added,
+            change.removed,
+            utils::truncate_string(&diff, MAX_DIFF_CHARS_PER_FILE)
+        ));
+    }
+
+    let commits = git::run_git(
+        workspace_root,
+        &["log", "--format=- %s", &format!("{}..HEAD", base)],
+    )?;
+
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: "You write pull request descriptions in Markdown for code reviewers. \
+                Use these sections: `## Summary` (two or three sentences on what changed and why), \
+                `## Changes` (one bullet per file describing its change), \
+                `## Breaking changes` (public API, config, CLI or data format changes that \
+                affect users, or 'None'), and `## Testing` (based on the coverage notes; \
+                call out new code without tests). Do not invent details not in the diff."
+                .to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: format!(
+                "Commits:\n{}\n\nCoverage notes:\n{}\nDiff against {}:\n\n{}",
+                commits,
+                coverage_notes(&changes),
+                base,
+                diffs
+            ),
+            name: None,
+        },
+    ];
+
+    let description = ai_client.generate_text(messages).await?;
+    Ok(description.trim().to_string())
+}
+
+/// Replace the body of the pull request for the current branch using the GitHub CLI
+pub fn push_to_github(workspace_root: &Path, description: &str) -> AgentResult<()> {
+    let output = std::process::Command::new("gh")
+        .current_dir(workspace_root)
+        .args(["pr", "edit", "--body", description])
+        .output()
+        .map_err(|e| AgentError::ToolExecution(format!("Failed to run gh: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(AgentError::ToolExecution(format!(
+            "gh pr edit failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
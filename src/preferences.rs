@@ -0,0 +1,216 @@
+use crate::error::AgentResult;
+use crate::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance};
+use crate::storage::{Storage, StorageTree};
+use crate::utils;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tokio::sync::Mutex;
+
+/// Source name used for the pinned fragment holding user preferences
+pub const USER_PREFERENCES_SOURCE: &str = "user_preferences";
+
+/// How many times a correction must be seen before it is treated as a preference
+const RECURRING_THRESHOLD: u32 = 2;
+
+/// Share of keywords two corrections must exceed in common to count as the same one
+const SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// Words too common to tell corrections apart
+const STOP_WORDS: [&str; 24] = [
+    "a", "an", "and", "are", "be", "for", "i", "in", "instead", "is", "it", "of", "on", "or",
+    "please", "prefer", "should", "that", "the", "this", "to", "use", "using", "with",
+];
+
+/// Words that reverse a correction, so "use tabs" and "never use tabs" stay apart
+const NEGATIONS: [&str; 7] = ["avoid", "don't", "dont", "never", "no", "not", "without"];
+
+/// Keep the injected fragment compact
+const MAX_PINNED_PREFERENCES: usize = 10;
+
+/// A preference learned from user corrections, e.g. "use 2-space indentation"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserPreference {
+    pub text: String,
+    pub occurrences: u32,
+    pub explicit: bool, // set with /prefer rather than learned from corrections
+    pub last_seen: String,
+}
+
+impl UserPreference {
+    /// Whether this preference should be injected into prompts
+    pub fn is_active(&self) -> bool {
+        self.explicit || self.occurrences >= RECURRING_THRESHOLD
+    }
+}
+
+/// Preferences of the current user, shared by all their workspaces
+pub struct UserPreferences {
+    tree: StorageTree,
+}
+
+impl UserPreferences {
+    pub fn open(storage: &Storage) -> Self {
+        Self {
+            tree: storage.user_tree("user_preferences"),
+        }
+    }
+
+    /// Record a correction the user made, counting repeats of the same correction, also
+    /// when it is worded differently
+    pub async fn record_correction(&self, text: &str) -> AgentResult<UserPreference> {
+        self.upsert(text, false).await
+    }
+
+    /// Record an explicit preference, active immediately
+    pub async fn prefer(&self, text: &str) -> AgentResult<UserPreference> {
+        self.upsert(text, true).await
+    }
+
+    /// Remove preferences whose text contains `query`
+    pub async fn forget(&self, query: &str) -> AgentResult<Vec<UserPreference>> {
+        let query = normalize(query);
+        let mut removed = Vec::new();
+
+        if query.is_empty() {
+            return Ok(removed);
+        }
+
+        for preference in self.all().await? {
+            let key = normalize(&preference.text);
+            if key.contains(&query) {
+                self.tree.remove(&key).await?;
+                removed.push(preference);
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// All recorded preferences and corrections, most frequent first
+    pub async fn all(&self) -> AgentResult<Vec<UserPreference>> {
+        let mut preferences: Vec<UserPreference> = self.tree.values().await?;
+        preferences.sort_by(|a, b| {
+            b.explicit
+                .cmp(&a.explicit)
+                .then(b.occurrences.cmp(&a.occurrences))
+        });
+        Ok(preferences)
+    }
+
+    /// Preferences that are explicit or have recurred often enough
+    pub async fn active(&self) -> AgentResult<Vec<UserPreference>> {
+        Ok(self
+            .all()
+            .await?
+            .into_iter()
+            .filter(|p| p.is_active())
+            .collect())
+    }
+
+    /// Render active preferences as a compact fragment for planner and edit prompts
+    pub async fn to_memory_fragment(&self) -> AgentResult<Option<MemoryFragment>> {
+        let preferences = self.active().await?;
+        if preferences.is_empty() {
+            return Ok(None);
+        }
+
+        let mut content = String::from("User preferences (follow these unless told otherwise):\n");
+        for preference in preferences.iter().take(MAX_PINNED_PREFERENCES) {
+            content.push_str(&format!("- {}\n", preference.text));
+        }
+
+        Ok(Some(MemoryFragment {
+            source: USER_PREFERENCES_SOURCE.to_string(),
+            provenance: Provenance::UserProvided,
+            content,
+            metadata: Some(MemoryMetadata {
+                file_type: Some("user_preferences".to_string()),
+                path: None,
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec!["pinned".to_string(), "user_preferences".to_string()],
+            }),
+        }))
+    }
+
+    /// Refresh the pinned user preferences fragment in context memory
+    pub async fn pin_into(&self, context_memory: &Mutex<ContextMemory>) -> AgentResult<()> {
+        let fragment = self.to_memory_fragment().await?;
+        let mut memory = context_memory.lock().await;
+
+        match fragment {
+            Some(fragment) => memory.pin_frag(fragment),
+            None => memory.unpin(USER_PREFERENCES_SOURCE),
+        }
+        Ok(())
+    }
+
+    async fn upsert(&self, text: &str, explicit: bool) -> AgentResult<UserPreference> {
+        let existing = match self.tree.get::<UserPreference>(&normalize(text)).await? {
+            Some(existing) => Some(existing),
+            None => {
+                let words = keywords(text);
+                self.all()
+                    .await?
+                    .into_iter()
+                    .find(|p| same_request(&keywords(&p.text), &words))
+            }
+        };
+
+        let preference = match existing {
+            Some(existing) => UserPreference {
+                text: existing.text,
+                occurrences: existing.occurrences + 1,
+                explicit: existing.explicit || explicit,
+                last_seen: utils::current_timestamp(),
+            },
+            None => UserPreference {
+                text: text.trim().to_string(),
+                occurrences: 1,
+                explicit,
+                last_seen: utils::current_timestamp(),
+            },
+        };
+
+        self.tree
+            .put(&normalize(&preference.text), &preference)
+            .await?;
+        Ok(preference)
+    }
+}
+
+/// Lowercase and collapse whitespace so repeated corrections share one key
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The words of `text` that say what it asks for
+fn keywords(text: &str) -> BTreeSet<String> {
+    normalize(text)
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|word| !word.is_empty() && !STOP_WORDS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether two corrections ask for the same thing in other words: mostly the same keywords,
+/// both or neither negated, and the same numbers, so "2-space" and "4-space" stay apart
+fn same_request(a: &BTreeSet<String>, b: &BTreeSet<String>) -> bool {
+    let negated = |words: &BTreeSet<String>| words.iter().any(|w| NEGATIONS.contains(&w.as_str()));
+    let numbers = |words: &BTreeSet<String>| {
+        words
+            .iter()
+            .filter(|w| w.chars().any(|c| c.is_ascii_digit()))
+            .cloned()
+            .collect::<BTreeSet<_>>()
+    };
+    let all = a.union(b).count();
+
+    negated(a) == negated(b)
+        && numbers(a) == numbers(b)
+        && all > 0
+        && a.intersection(b).count() as f64 / all as f64 > SIMILARITY_THRESHOLD
+}
@@ -0,0 +1,231 @@
+use crate::ai::reasoning::ReasoningEffort;
+use crate::ai::{ChatCompletionResponse, GenerationProfile, Message, TextStream};
+use crate::error::AgentResult;
+use crate::AiClient;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Width of the longest bar in the breakdown
+const BAR_WIDTH: usize = 40;
+
+/// Where the wall time of one subtask went
+#[derive(Debug, Clone)]
+pub struct SubtaskProfile {
+    pub label: String,
+    pub kind: String, // tool name, for per-tool totals
+    pub ai: Duration,
+    pub tool: Duration,
+    pub user_wait: Duration,
+}
+
+impl SubtaskProfile {
+    pub fn total(&self) -> Duration {
+        self.ai + self.tool + self.user_wait
+    }
+}
+
+/// Collects per-subtask timings for `--profile-run`
+#[derive(Default)]
+pub struct RunProfiler {
+    ai_nanos: AtomicU64, // running total of time spent waiting on the model
+    ai_calls: AtomicU64,
+    subtasks: Mutex<Vec<SubtaskProfile>>,
+}
+
+impl RunProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total model latency so far; subtasks diff this before and after they run
+    pub fn ai_time(&self) -> Duration {
+        Duration::from_nanos(self.ai_nanos.load(Ordering::Relaxed))
+    }
+
+    fn record_ai_call(&self, elapsed: Duration) {
+        self.ai_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        self.ai_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, profile: SubtaskProfile) {
+        self.subtasks
+            .lock()
+            .expect("profiler poisoned")
+            .push(profile);
+    }
+
+    /// Every profiled subtask, in the order they ran
+    pub fn subtasks(&self) -> Vec<SubtaskProfile> {
+        self.subtasks.lock().expect("profiler poisoned").clone()
+    }
+
+    /// Flame-style breakdown: one bar per subtask split into AI, tool and wait time
+    pub fn report(&self) -> String {
+        let subtasks = self.subtasks.lock().expect("profiler poisoned");
+        if subtasks.is_empty() {
+            return "No subtasks were profiled".to_string();
+        }
+
+        let longest = subtasks
+            .iter()
+            .map(SubtaskProfile::total)
+            .max()
+            .unwrap_or_default()
+            .max(Duration::from_millis(1));
+        let scale = |d: Duration| {
+            (d.as_secs_f64() / longest.as_secs_f64() * BAR_WIDTH as f64).round() as usize
+        };
+
+        let mut report = String::from("Run profile (█ AI  ▓ tool  ░ waiting for you)\n\n");
+
+        for profile in subtasks.iter() {
+            report.push_str(&format!(
+                "{:<width$} {:>7.2}s  {}\n",
+                crate::utils::truncate_string(&profile.label, 40),
+                profile.total().as_secs_f64(),
+                "█".repeat(scale(profile.ai))
+                    + &"▓".repeat(scale(profile.tool))
+                    + &"░".repeat(scale(profile.user_wait)),
+                width = 40
+            ));
+        }
+
+        let mut by_kind: BTreeMap<&str, (usize, Duration, Duration, Duration)> = BTreeMap::new();
+        for profile in subtasks.iter() {
+            let entry = by_kind.entry(&profile.kind).or_default();
+            entry.0 += 1;
+            entry.1 += profile.ai;
+            entry.2 += profile.tool;
+            entry.3 += profile.user_wait;
+        }
+
+        report.push_str("\nBy tool:\n");
+        for (kind, (count, ai, tool, wait)) in &by_kind {
+            report.push_str(&format!(
+                "  {:<16} x{:<3} AI {:>7.2}s  tool {:>7.2}s  waiting {:>7.2}s\n",
+                kind,
+                count,
+                ai.as_secs_f64(),
+                tool.as_secs_f64(),
+                wait.as_secs_f64()
+            ));
+        }
+
+        let total =
+            |f: fn(&SubtaskProfile) -> Duration| -> Duration { subtasks.iter().map(f).sum() };
+        report.push_str(&format!(
+            "\nTotal: AI {:.2}s over {} calls, tools {:.2}s, waiting for you {:.2}s",
+            total(|p| p.ai).as_secs_f64(),
+            self.ai_calls.load(Ordering::Relaxed),
+            total(|p| p.tool).as_secs_f64(),
+            total(|p| p.user_wait).as_secs_f64()
+        ));
+
+        report
+    }
+}
+
+/// Wraps an AI client to attribute model latency to the profiler
+pub struct ProfilingAiClient {
+    inner: Box<dyn AiClient>,
+    profiler: Arc<RunProfiler>,
+}
+
+impl ProfilingAiClient {
+    pub fn new(inner: Box<dyn AiClient>, profiler: Arc<RunProfiler>) -> Self {
+        Self { inner, profiler }
+    }
+}
+
+#[async_trait]
+impl AiClient for ProfilingAiClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        let started = Instant::now();
+        let result = self.inner.generate_text(messages).await;
+        self.profiler.record_ai_call(started.elapsed());
+        result
+    }
+
+    /// The call lasts until the last token arrives; a stream dropped early is not recorded
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        let started = Instant::now();
+        let tokens = match self.inner.stream_text(messages).await {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                self.profiler.record_ai_call(started.elapsed());
+                return Err(e);
+            }
+        };
+
+        let profiler = self.profiler.clone();
+        let finished = stream::once(async move {
+            profiler.record_ai_call(started.elapsed());
+            None
+        });
+        Ok(tokens
+            .map(Some)
+            .chain(finished)
+            .filter_map(futures::future::ready)
+            .boxed())
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        messages: Vec<Message>,
+        functions: serde_json::Value,
+        force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        let started = Instant::now();
+        let result = self
+            .inner
+            .chat_completion_with_functions(messages, functions, force_message_only)
+            .await;
+        self.profiler.record_ai_call(started.elapsed());
+        result
+    }
+
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        let started = Instant::now();
+        let result = self.inner.generate_embeddings(text).await;
+        self.profiler.record_ai_call(started.elapsed());
+        result
+    }
+
+    fn provider_name(&self) -> String {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn embedding_model(&self) -> String {
+        self.inner.embedding_model()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(Self {
+            inner: self.inner.clone_box(),
+            profiler: self.profiler.clone(),
+        })
+    }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self {
+            inner: self.inner.with_profile(profile),
+            profiler: self.profiler.clone(),
+        })
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        Box::new(Self {
+            inner: self.inner.with_reasoning_effort(effort),
+            profiler: self.profiler.clone(),
+        })
+    }
+}
@@ -0,0 +1,301 @@
+use crate::chunking::{self, SourceLanguage};
+use crate::error::AgentError;
+use crate::file_rank::{self, FileRanking};
+use crate::memory::{MemoryFragment, MemoryMetadata, Provenance};
+use crate::utils;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Source files summarized in the repo map
+const MAX_MAP_FILES: usize = 150;
+
+/// Files larger than this are listed without their symbols
+const MAX_MAP_FILE_BYTES: u64 = 200_000;
+
+/// Top-level symbols listed per file
+const MAX_SYMBOLS_PER_FILE: usize = 12;
+
+/// README text included, in characters
+const MAX_README_CHARS: usize = 4000;
+
+/// AGENTS.md text pinned into every prompt, in characters
+const MAX_AGENT_INSTRUCTIONS_CHARS: usize = 8000;
+
+/// Source of the pinned AGENTS.md fragment
+pub const AGENT_INSTRUCTIONS_SOURCE: &str = "agents_md";
+
+/// When to gather project context at session start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoContext {
+    /// Only for projects the agent has no remembered facts about yet
+    New,
+    Always,
+    Off,
+}
+
+impl FromStr for AutoContext {
+    type Err = AgentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "new" => Ok(Self::New),
+            "always" => Ok(Self::Always),
+            "off" => Ok(Self::Off),
+            other => Err(AgentError::Configuration(format!(
+                "Unknown auto context setting '{}', expected one of: new, always, off",
+                other
+            ))),
+        }
+    }
+}
+
+/// A directory listing, a map of source files and their top-level symbols, and the README.
+/// Reads only local files and never calls the model, so the cost is bounded by the caps above.
+pub fn gather(root: &Path) -> Vec<MemoryFragment> {
+    gather_with(root, &index_symbols(root))
+}
+
+/// `gather`, with the repo map drawn from an already built symbol index
+pub fn gather_with(root: &Path, index: &SymbolIndex) -> Vec<MemoryFragment> {
+    [listing(root), repo_map(root, index), readme(root)]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn fragment(source: &str, root: &Path, content: String) -> MemoryFragment {
+    MemoryFragment {
+        source: source.to_string(),
+        provenance: Provenance::FileRead,
+        content,
+        metadata: Some(MemoryMetadata {
+            file_type: Some("project_overview".to_string()),
+            path: Some(utils::path_to_string(root)),
+            timestamp: Some(Utc::now().timestamp()),
+            tags: vec!["auto_context".to_string()],
+        }),
+    }
+}
+
+fn listing(root: &Path) -> Option<MemoryFragment> {
+    let mut entries: Vec<String> = std::fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() {
+                format!("{}/", name)
+            } else {
+                name
+            }
+        })
+        .filter(|name| name != ".git/")
+        .collect();
+    entries.sort();
+
+    Some(fragment(
+        "workspace_listing",
+        root,
+        format!(
+            "Top-level entries of the workspace:\n{}",
+            entries.join("\n")
+        ),
+    ))
+}
+
+/// A top-level definition in a source file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolEntry {
+    pub name: String,
+    pub start_line: usize, // 1-based, inclusive
+    pub end_line: usize,
+}
+
+/// The top-level definitions of one source file, in file order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSymbols {
+    pub path: String, // workspace-relative
+    pub symbols: Vec<SymbolEntry>,
+}
+
+/// Source files of a workspace and their top-level definitions, the basis of the repo map
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    pub files: Vec<FileSymbols>, // most important first
+    pub omitted_files: usize,    // less important source files past the cap, listed by count only
+    #[serde(default)]
+    pub ranking: FileRanking, // importance of every source file; empty in indexes cached before ranking
+}
+
+impl SymbolIndex {
+    /// Where `name` is defined, as workspace-relative paths and definitions
+    pub fn find(&self, name: &str) -> Vec<(&str, &SymbolEntry)> {
+        self.files
+            .iter()
+            .flat_map(|file| {
+                file.symbols
+                    .iter()
+                    .filter(|symbol| symbol.name == name)
+                    .map(|symbol| (file.path.as_str(), symbol))
+            })
+            .collect()
+    }
+}
+
+/// Index the top-level definitions of the workspace's most important source files. Walks,
+/// ranks and parses every file up to the cap, so sessions get it from the analysis cache
+/// where they can.
+pub fn index_symbols(root: &Path) -> SymbolIndex {
+    // Respects .gitignore so build output and dependencies stay out of the map
+    let walker = ignore::WalkBuilder::new(root)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    let mut paths: Vec<String> = walker
+        .flatten()
+        .filter(|entry| {
+            entry.file_type().is_some_and(|t| t.is_file())
+                && SourceLanguage::from_path(entry.path()).is_some()
+        })
+        .map(|entry| utils::path_to_string(entry.path().strip_prefix(root).unwrap_or(entry.path())))
+        .collect();
+
+    // Under the cap, the map keeps the files the planner most likely needs
+    let ranking = file_rank::rank_files(root, &paths);
+    ranking.sort_by_importance(&mut paths, |path| path);
+    let omitted_files = paths.len().saturating_sub(MAX_MAP_FILES);
+
+    let files = paths
+        .into_iter()
+        .take(MAX_MAP_FILES)
+        .map(|path| {
+            let full_path = root.join(&path);
+            let small_enough =
+                std::fs::metadata(&full_path).is_ok_and(|m| m.len() <= MAX_MAP_FILE_BYTES);
+            let symbols = if small_enough {
+                std::fs::read_to_string(&full_path)
+                    .map(|content| top_level_symbols(&full_path, &content))
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            FileSymbols { path, symbols }
+        })
+        .collect();
+
+    SymbolIndex {
+        files,
+        omitted_files,
+        ranking,
+    }
+}
+
+fn repo_map(root: &Path, index: &SymbolIndex) -> Option<MemoryFragment> {
+    if index.files.is_empty() {
+        return None;
+    }
+
+    let mut lines: Vec<String> = index
+        .files
+        .iter()
+        .map(|file| {
+            let mut names: Vec<&str> = file
+                .symbols
+                .iter()
+                .map(|symbol| symbol.name.as_str())
+                .collect();
+            if names.is_empty() {
+                return file.path.clone();
+            }
+
+            let more = names.len().saturating_sub(MAX_SYMBOLS_PER_FILE);
+            names.truncate(MAX_SYMBOLS_PER_FILE);
+            let mut listed = names.join(", ");
+            if more > 0 {
+                listed.push_str(&format!(", +{} more", more));
+            }
+            format!("{}: {}", file.path, listed)
+        })
+        .collect();
+    if index.omitted_files > 0 {
+        lines.push(format!("... and {} more source files", index.omitted_files));
+    }
+
+    Some(fragment(
+        "repo_map",
+        root,
+        format!(
+            "Source files, most important first, and their top-level definitions:\n{}",
+            lines.join("\n")
+        ),
+    ))
+}
+
+fn top_level_symbols(path: &Path, content: &str) -> Vec<SymbolEntry> {
+    let mut symbols: Vec<SymbolEntry> = chunking::chunk_source(path, content)
+        .into_iter()
+        .filter_map(|chunk| {
+            let name = chunk.symbol.filter(|symbol| !symbol.contains("::"))?;
+            Some(SymbolEntry {
+                name,
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+            })
+        })
+        .collect();
+
+    // Large definitions are split into several chunks; keep one entry spanning them
+    symbols.dedup_by(|next, previous| {
+        if next.name == previous.name {
+            previous.end_line = previous.end_line.max(next.end_line);
+            true
+        } else {
+            false
+        }
+    });
+    symbols
+}
+
+fn readme(root: &Path) -> Option<MemoryFragment> {
+    let name = ["README.md", "README", "README.txt", "readme.md"]
+        .into_iter()
+        .find(|name| root.join(name).is_file())?;
+    let content = std::fs::read_to_string(root.join(name)).ok()?;
+
+    let text: String = content.chars().take(MAX_README_CHARS).collect();
+    let truncated = if content.chars().count() > MAX_README_CHARS {
+        "\n... (truncated)"
+    } else {
+        ""
+    };
+
+    Some(fragment(
+        "readme",
+        root,
+        format!("{}:\n{}{}", name, text, truncated),
+    ))
+}
+
+/// The project's AGENTS.md, the instructions it keeps for coding agents, if it has one
+pub fn agent_instructions(root: &Path) -> Option<MemoryFragment> {
+    let content = std::fs::read_to_string(root.join("AGENTS.md")).ok()?;
+    if content.trim().is_empty() {
+        return None;
+    }
+
+    Some(MemoryFragment {
+        source: AGENT_INSTRUCTIONS_SOURCE.to_string(),
+        provenance: Provenance::FileRead,
+        content: format!(
+            "Project instructions (AGENTS.md):\n{}",
+            utils::truncate_string(&content, MAX_AGENT_INSTRUCTIONS_CHARS)
+        ),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("instructions".to_string()),
+            path: Some("AGENTS.md".to_string()),
+            timestamp: Some(Utc::now().timestamp()),
+            tags: vec!["pinned".to_string()],
+        }),
+    })
+}
@@ -0,0 +1,264 @@
+use crate::error::{AgentError, AgentResult};
+use crate::tool_env;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::pty::{openpty, Winsize};
+use nix::sys::termios::{self, LocalFlags, OutputFlags, SetArg};
+use std::io::{IsTerminal, Read, Write};
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+
+/// Size reported to programs on a non-interactive terminal; wide, so listings are not
+/// wrapped early
+const COLUMNS: u16 = 160;
+const ROWS: u16 = 48;
+
+/// A process running on a pseudo-terminal, which it sees as its controlling terminal
+pub struct PtyProcess {
+    pub child: Child,
+    master: OwnedFd,
+}
+
+impl PtyProcess {
+    /// Spawn `command` on a new PTY. An `interactive` terminal is sized like the user's and
+    /// behaves as usual; otherwise it neither echoes input nor rewrites line endings, so what
+    /// is read back is exactly what the process wrote.
+    pub fn spawn(mut command: Command, interactive: bool) -> AgentResult<Self> {
+        let (rows, columns) = console::Term::stdout()
+            .size_checked()
+            .filter(|_| interactive)
+            .unwrap_or((ROWS, COLUMNS));
+        let size = Winsize {
+            ws_row: rows,
+            ws_col: columns,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let pty = openpty(Some(&size), None).map_err(pty_error)?;
+
+        if !interactive {
+            let mut settings = termios::tcgetattr(&pty.slave).map_err(pty_error)?;
+            settings.local_flags.remove(LocalFlags::ECHO);
+            settings.output_flags.remove(OutputFlags::OPOST);
+            termios::tcsetattr(&pty.slave, SetArg::TCSANOW, &settings).map_err(pty_error)?;
+        }
+
+        command
+            .stdin(Stdio::from(pty.slave.try_clone()?))
+            .stdout(Stdio::from(pty.slave.try_clone()?))
+            .stderr(Stdio::from(pty.slave))
+            .kill_on_drop(true);
+        // SAFETY: only async-signal-safe calls run between fork and exec
+        unsafe {
+            command.pre_exec(|| {
+                // A new session whose controlling terminal is the PTY, so Ctrl+C and
+                // password prompts reach the process as they would in a terminal
+                if nix::libc::setsid() == -1
+                    || nix::libc::ioctl(0, nix::libc::TIOCSCTTY as _, 0) == -1
+                {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        Ok(Self {
+            child: command.spawn()?,
+            master: pty.master,
+        })
+    }
+
+    /// Chunks of terminal output as they arrive, read on a thread until the process exits
+    pub fn output(&self) -> AgentResult<mpsc::UnboundedReceiver<Vec<u8>>> {
+        let mut master = std::fs::File::from(self.master.try_clone()?);
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 4096];
+            // Reads fail with EIO once the process side is closed
+            while let Ok(read @ 1..) = master.read(&mut buffer) {
+                if sender.send(buffer[..read].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
+    /// Type `input` into the terminal
+    pub fn write(&self, input: &[u8]) -> AgentResult<()> {
+        let mut master = std::fs::File::from(self.master.try_clone()?);
+        master.write_all(input)?;
+        Ok(())
+    }
+}
+
+/// What an interactive command printed and how it exited
+#[derive(Debug, Clone)]
+pub struct InteractiveRun {
+    pub transcript: String, // plain text of the end of the session, masked
+    pub exit_code: Option<i32>,
+}
+
+/// Characters of an interactive session's output kept in its transcript
+const TRANSCRIPT_CHARS: usize = 8_000;
+
+/// Run `command` on a PTY connected to the user's terminal: its output is shown as is and,
+/// when stdin is a terminal, keystrokes are forwarded to it until it exits
+pub async fn run_interactive(command: Command) -> AgentResult<InteractiveRun> {
+    let mut process = PtyProcess::spawn(command, true)?;
+    let mut output = process.output()?;
+    let keystrokes = Keystrokes::forward(&process)?;
+
+    let mut captured = Vec::new();
+    let mut stdout = std::io::stdout();
+    let mut show = |chunk: Vec<u8>| {
+        let _ = stdout.write_all(&chunk);
+        let _ = stdout.flush();
+        captured.extend(chunk);
+    };
+
+    let mut status = None;
+    loop {
+        tokio::select! {
+            chunk = output.recv() => match chunk {
+                Some(chunk) => show(chunk),
+                None => break,
+            },
+            exited = process.child.wait() => {
+                status = Some(exited?);
+                break;
+            }
+        }
+    }
+    // Output the process wrote just before exiting may still be in flight
+    while let Ok(Some(chunk)) =
+        tokio::time::timeout(std::time::Duration::from_millis(100), output.recv()).await
+    {
+        show(chunk);
+    }
+    let status = match status {
+        Some(status) => status,
+        None => process.child.wait().await?,
+    };
+    drop(keystrokes);
+
+    let transcript = tool_env::mask(&strip_ansi(&String::from_utf8_lossy(&captured)));
+    let skip = transcript.chars().count().saturating_sub(TRANSCRIPT_CHARS);
+    Ok(InteractiveRun {
+        transcript: transcript.chars().skip(skip).collect(),
+        exit_code: status.code(),
+    })
+}
+
+/// Forwards the user's keystrokes to a PTY, with their terminal in raw mode so every key,
+/// Ctrl+C included, reaches the process. Dropping it stops forwarding and restores the terminal.
+struct Keystrokes {
+    stop: Arc<AtomicBool>,
+    forwarder: Option<std::thread::JoinHandle<()>>,
+    saved: Option<termios::Termios>,
+}
+
+impl Keystrokes {
+    fn forward(process: &PtyProcess) -> AgentResult<Self> {
+        let stdin = std::io::stdin();
+        let stop = Arc::new(AtomicBool::new(false));
+        if !stdin.is_terminal() {
+            return Ok(Self {
+                stop,
+                forwarder: None,
+                saved: None,
+            });
+        }
+
+        let saved = termios::tcgetattr(&stdin).map_err(pty_error)?;
+        let mut raw = saved.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(&stdin, SetArg::TCSANOW, &raw).map_err(pty_error)?;
+
+        let mut master = std::fs::File::from(process.master.try_clone()?);
+        let stopped = Arc::clone(&stop);
+        // Polls so the thread notices the process has exited without consuming a keystroke
+        // meant for the prompt that follows
+        let forwarder = std::thread::spawn(move || {
+            let stdin = std::io::stdin();
+            let mut buffer = [0u8; 1024];
+            while !stopped.load(Ordering::Relaxed) {
+                let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+                if !matches!(poll(&mut fds, PollTimeout::from(50u8)), Ok(1..)) {
+                    continue;
+                }
+                match nix::unistd::read(stdin.as_raw_fd(), &mut buffer) {
+                    Ok(read @ 1..) => {
+                        if master.write_all(&buffer[..read]).is_err() {
+                            break;
+                        }
+                    }
+                    _ => break,
+                }
+            }
+        });
+
+        Ok(Self {
+            stop,
+            forwarder: Some(forwarder),
+            saved: Some(saved),
+        })
+    }
+}
+
+impl Drop for Keystrokes {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(forwarder) = self.forwarder.take() {
+            let _ = forwarder.join();
+        }
+        if let Some(saved) = &self.saved {
+            let _ = termios::tcsetattr(std::io::stdin(), SetArg::TCSANOW, saved);
+        }
+    }
+}
+
+fn pty_error(e: nix::Error) -> AgentError {
+    AgentError::ToolExecution(format!("Failed to set up a pseudo-terminal: {}", e))
+}
+
+/// Remove terminal escape sequences (colors, cursor movement, titles) and carriage returns
+pub fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' => match chars.next() {
+                // CSI: parameters up to a final byte in @..~
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: up to BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\u{7}' {
+                            break;
+                        }
+                        if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\r' => {}
+            c => plain.push(c),
+        }
+    }
+    plain
+}
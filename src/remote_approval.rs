@@ -0,0 +1,291 @@
+use crate::error::{AgentError, AgentResult};
+use crate::utils;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, Mutex};
+
+/// A permission prompt waiting for an answer from another device
+struct PendingApproval {
+    description: String,
+    details: Option<String>,
+    answer: oneshot::Sender<bool>,
+}
+
+type PendingApprovals = Arc<Mutex<HashMap<String, PendingApproval>>>;
+
+/// Answers permission prompts over HTTP so unattended runs can be approved from a phone.
+/// Every URL carries a random session token, and only a POST answers a prompt, so link
+/// previews and other callers cannot approve anything. A notification with approve/deny
+/// links is sent to `notify_url` (an ntfy topic or any endpoint accepting a plain-text POST).
+pub struct RemoteApprover {
+    pending: PendingApprovals,
+    public_url: String, // base URL including the session token
+    notify_url: Option<String>,
+    timeout: Duration,
+    http: reqwest::Client,
+}
+
+impl RemoteApprover {
+    /// Start serving approval links on `bind`
+    pub async fn start(
+        bind: &str,
+        public_url: Option<String>,
+        notify_url: Option<String>,
+        timeout: Duration,
+    ) -> AgentResult<Self> {
+        let listener = TcpListener::bind(bind).await.map_err(|e| {
+            AgentError::Configuration(format!(
+                "Failed to start the remote approval server on {}: {}",
+                bind, e
+            ))
+        })?;
+
+        // Port 0 binds any free port, so links must name the one actually bound
+        let bound = listener
+            .local_addr()
+            .map_or_else(|_| bind.to_string(), |addr| addr.to_string());
+        let token: Arc<str> = utils::generate_id().replace('-', "").into();
+        // Forms post back through the public URL, which may sit behind a path prefix
+        let public_url: Arc<str> = format!(
+            "{}/{}",
+            public_url
+                .unwrap_or_else(|| format!("http://{}", bound))
+                .trim_end_matches('/'),
+            token
+        )
+        .into();
+
+        let pending: PendingApprovals = Arc::new(Mutex::new(HashMap::new()));
+        let server_pending = pending.clone();
+        let server_token = token.clone();
+        let server_url = public_url.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let pending = server_pending.clone();
+                let token = server_token.clone();
+                let url = server_url.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, pending, &token, &url).await {
+                        log::warn!("Remote approval request failed: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            pending,
+            public_url: public_url.to_string(),
+            notify_url,
+            timeout,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Page listing every pending request; the URL is the credential, so keep it private
+    pub fn url(&self) -> &str {
+        &self.public_url
+    }
+
+    /// Wait for a remote answer; an unanswered request is denied after the timeout
+    pub async fn request(&self, description: &str, details: Option<String>) -> bool {
+        let id = utils::generate_id();
+        let (sender, receiver) = oneshot::channel();
+
+        self.pending.lock().await.insert(
+            id.clone(),
+            PendingApproval {
+                description: description.to_string(),
+                details,
+                answer: sender,
+            },
+        );
+
+        if let Err(e) = self.notify(&id, description).await {
+            log::warn!("Failed to send approval notification: {}", e);
+        }
+
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(approved)) => approved,
+            _ => {
+                self.pending.lock().await.remove(&id);
+                false
+            }
+        }
+    }
+
+    async fn notify(&self, id: &str, description: &str) -> AgentResult<()> {
+        let Some(notify_url) = &self.notify_url else {
+            return Ok(());
+        };
+
+        let approve = format!("{}/approve/{}", self.public_url, id);
+        let deny = format!("{}/deny/{}", self.public_url, id);
+
+        // ntfy renders these headers as buttons; other endpoints can use the links in the body
+        self.http
+            .post(notify_url)
+            .header("Title", "Agent approval needed")
+            .header(
+                "Actions",
+                format!(
+                    "http, Approve, {}, method=POST, clear=true; http, Deny, {}, method=POST, clear=true",
+                    approve, deny
+                ),
+            )
+            .body(format!(
+                "{}\n\nApprove: {}\nDeny: {}",
+                description, approve, deny
+            ))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    pending: PendingApprovals,
+    token: &str,
+    public_url: &str,
+) -> AgentResult<()> {
+    let mut buffer = vec![0u8; 8192];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+
+    let mut request_line = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or("/");
+
+    // Without the token nothing is revealed, not even whether requests are pending
+    let not_found = ("404 Not Found", "<p>Not found.</p>".to_string());
+    let route = path
+        .trim_matches('/')
+        .split_once('/')
+        .map_or((path.trim_matches('/'), ""), |(first, rest)| (first, rest));
+    let (status, body) = match route {
+        (given, _) if !same_token(given, token) => not_found,
+        (_, "") => ("200 OK", render_pending(&*pending.lock().await, public_url)),
+        (_, answer) => match answer.split_once('/') {
+            // Opening a link only shows the button, so previews cannot answer for the user
+            Some(("approve" | "deny", id)) if method != "POST" => {
+                match pending.lock().await.get(id) {
+                    Some(approval) => ("200 OK", render_answer_form(public_url, answer, approval)),
+                    None => already_answered(),
+                }
+            }
+            Some((action @ ("approve" | "deny"), id)) => match pending.lock().await.remove(id) {
+                Some(approval) => {
+                    let approved = action == "approve";
+                    let _ = approval.answer.send(approved);
+                    (
+                        "200 OK",
+                        format!(
+                            "<p>{}: {}</p>",
+                            if approved { "Approved" } else { "Denied" },
+                            escape_html(&approval.description)
+                        ),
+                    )
+                }
+                None => already_answered(),
+            },
+            _ => not_found,
+        },
+    };
+
+    let page = format!(
+        "<!doctype html><html><head><meta name=\"viewport\" content=\"width=device-width\">\
+         <title>Agent approvals</title></head><body>{}</body></html>",
+        body
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        page.len(),
+        page
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+fn already_answered() -> (&'static str, String) {
+    (
+        "404 Not Found",
+        "<p>This request was already answered or has expired.</p>".to_string(),
+    )
+}
+
+/// Compare tokens in constant time, so response timing does not reveal a prefix
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// A single button answering the request at `answer`, e.g. `approve/<id>`
+fn render_answer_form(public_url: &str, answer: &str, approval: &PendingApproval) -> String {
+    let label = if answer.starts_with("approve") {
+        "Approve"
+    } else {
+        "Deny"
+    };
+    format!(
+        "<h3>{}</h3>{}<form method=\"post\" action=\"{}/{}\"><button>{}</button></form>",
+        escape_html(&approval.description),
+        render_details(approval),
+        escape_html(public_url),
+        answer,
+        label
+    )
+}
+
+fn render_details(approval: &PendingApproval) -> String {
+    approval
+        .details
+        .as_ref()
+        .map(|details| format!("<pre>{}</pre>", escape_html(details)))
+        .unwrap_or_default()
+}
+
+fn render_pending(pending: &HashMap<String, PendingApproval>, public_url: &str) -> String {
+    if pending.is_empty() {
+        return "<p>Nothing is waiting for approval.</p>".to_string();
+    }
+
+    pending
+        .iter()
+        .map(|(id, approval)| {
+            format!(
+                "<h3>{}</h3>{}<form method=\"post\" action=\"{}/approve/{}\" style=\"display:inline\">\
+                 <button>Approve</button></form> \
+                 <form method=\"post\" action=\"{}/deny/{}\" style=\"display:inline\">\
+                 <button>Deny</button></form>",
+                escape_html(&approval.description),
+                render_details(approval),
+                escape_html(public_url),
+                id,
+                escape_html(public_url),
+                id
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("<hr>")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
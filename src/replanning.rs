@@ -0,0 +1,163 @@
+use crate::agent_engine::{SubtaskFilter, SubtaskOutput};
+use crate::ai::{Message, MessageRole};
+use crate::error::{AgentError, AgentResult};
+use crate::subtasks::subtask::{ProtoSubtaskInner, SubTaskType};
+use crate::utils;
+use crate::AiClient;
+use serde_json::json;
+
+/// Characters of a step's result shown to the checkpoint model
+const OBSERVATION_LIMIT: usize = 3000;
+
+const CHECKPOINT_PROMPT: &str = r#"
+You review the plan of a command-line coding agent between steps.
+You are given the step that just ran, what it produced, and the steps still queued.
+
+Decide whether the queued steps still make sense given the result:
+- "continue" if they do. This is the usual answer; prefer it unless the result clearly
+  invalidates a queued step (a search found nothing, a file does not exist, a command failed
+  in a way later steps depend on).
+- "revise" if some queued steps would now fail or be pointless. Give the complete list of
+  steps to run instead, in order, reusing the queued steps that are still valid.
+- "abort" only if the task cannot be completed at all.
+
+Answer by calling the Checkpoint function.
+"#;
+
+/// What a checkpoint decided about the remaining plan
+#[derive(Debug)]
+pub enum Checkpoint {
+    Continue,
+    Revise(Vec<ProtoSubtaskInner>),
+    Abort(String),
+}
+
+impl Checkpoint {
+    /// The queue change this decision calls for, if any
+    pub fn into_output(self) -> Option<SubtaskOutput> {
+        match self {
+            Self::Continue => None,
+            Self::Revise(steps) => Some(SubtaskOutput::ReplaceQueue(
+                steps.into_iter().map(SubTaskType::ProtoSubtask).collect(),
+            )),
+            Self::Abort(reason) => Some(SubtaskOutput::CancelMatching(SubtaskFilter::new(
+                format!("all remaining steps ({})", reason),
+                |_| true,
+            ))),
+        }
+    }
+}
+
+/// Ask the model whether the result of `completed` invalidates the `remaining` steps,
+/// which are listed in the order they will run
+pub async fn checkpoint(
+    ai_client: &dyn AiClient,
+    completed: &SubTaskType,
+    observation: &str,
+    remaining: &[&SubTaskType],
+) -> AgentResult<Checkpoint> {
+    let remaining_list = remaining
+        .iter()
+        .enumerate()
+        .map(|(i, subtask)| format!("{}. {}", i + 1, describe(subtask)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: CHECKPOINT_PROMPT.to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: format!(
+                "STEP THAT JUST RAN: {}\n\nRESULT:\n{}\n\nQUEUED STEPS:\n{}",
+                describe(completed),
+                utils::truncate_string(observation, OBSERVATION_LIMIT),
+                remaining_list
+            ),
+            name: None,
+        },
+    ];
+
+    let response = ai_client
+        .chat_completion_with_functions(messages, checkpoint_function(), false)
+        .await?;
+    let Some(call) = response.tool_calls.into_iter().flatten().next() else {
+        return Ok(Checkpoint::Continue);
+    };
+
+    let arguments = match call.function.arguments {
+        serde_json::Value::String(raw) => serde_json::from_str(&raw)?,
+        arguments => arguments,
+    };
+    let reason = arguments["reason"]
+        .as_str()
+        .unwrap_or("no reason given")
+        .to_string();
+
+    match arguments["decision"].as_str() {
+        Some("revise") => {
+            let steps = ProtoSubtaskInner::parse_from_raw(arguments);
+            // An empty revision is more likely a malformed answer than a deliberate one
+            if steps.is_empty() {
+                return Err(AgentError::AiApi(
+                    "Checkpoint asked to revise the plan without giving any steps".to_string(),
+                ));
+            }
+            Ok(Checkpoint::Revise(steps))
+        }
+        Some("abort") => Ok(Checkpoint::Abort(reason)),
+        _ => Ok(Checkpoint::Continue),
+    }
+}
+
+/// Planned steps are shown as the tool and description the planner gave them
+fn describe(subtask: &SubTaskType) -> String {
+    match subtask {
+        SubTaskType::ProtoSubtask(step) => format!("[{}] {}", step.tool_name, step.description),
+        other => format!("[{}] {}", other.tool_name(), other.description()),
+    }
+}
+
+fn checkpoint_function() -> serde_json::Value {
+    json!([
+        {
+            "name": "Checkpoint",
+            "description": "Decide whether to continue, revise or abort the remaining plan",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "decision": {
+                        "type": "string",
+                        "enum": ["continue", "revise", "abort"]
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "One sentence explaining the decision"
+                    },
+                    "subtasks": {
+                        "type": "array",
+                        "description": "With \"revise\": every step to run instead of the queued ones, in order",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "tool_name": {
+                                    "type": "string",
+                                    "enum": [ "ExplainTool","TaskTool","BashTool","FileReadTool","FileEditTool","EditSymbolTool","InsertTool","EditSetTool","WriteFileTool","LSTool","GlobTool","GrepTool","MemoryGrepTool","SemanticSearchTool","HybridSearchTool","CrateSourceTool","CrateDocsTool","ErrorLookupTool","CoverageTool","TmuxPaneTool","RememberTool" ]
+                                },
+                                "description": {
+                                    "type": "string",
+                                    "description": "Description of the step"
+                                }
+                            },
+                            "required": ["tool_name", "description"]
+                        }
+                    }
+                },
+                "required": ["decision", "reason"]
+            }
+        }
+    ])
+}
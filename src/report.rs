@@ -0,0 +1,170 @@
+use crate::agent_engine::SubtaskOutput;
+use crate::git;
+use crate::subtasks::SubTaskType;
+use crate::tool_env;
+use std::path::Path;
+
+/// Lines of each tool's output kept in a report; the full text stays in context memory
+const OUTPUT_LINES: usize = 25;
+
+/// One executed step of a turn
+#[derive(Debug, Clone)]
+pub struct ReportStep {
+    pub icon: &'static str,
+    pub description: String,
+    pub output: Option<String>, // what the tool added to context memory, if anything
+    pub succeeded: bool,
+}
+
+/// Everything a turn did, kept so `/report` can write it up afterwards
+#[derive(Debug, Clone, Default)]
+pub struct TurnReport {
+    pub prompt: String,
+    pub started_at: String,
+    pub steps: Vec<ReportStep>,
+    pub files_changed: Vec<String>,
+    pub changes_summary: Option<String>, // what changed and why, written after the turn
+    pub explanation: Option<String>,     // final answer as markdown
+}
+
+impl TurnReport {
+    pub fn new(prompt: &str) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            started_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prompt.is_empty() && self.steps.is_empty()
+    }
+
+    /// Record an executed subtask and what it produced
+    pub fn record_step(&mut self, subtask: &SubTaskType, output: Option<&SubtaskOutput>) {
+        let text = match output {
+            Some(SubtaskOutput::AddToContextMemory(fragment)) => Some(fragment.content.clone()),
+            _ => None,
+        };
+
+        self.steps.push(ReportStep {
+            icon: subtask.icon(),
+            description: tool_env::mask(&subtask.description()),
+            output: text,
+            succeeded: output.is_some(),
+        });
+
+        for file in subtask.touched_files() {
+            if !self.files_changed.contains(&file) {
+                self.files_changed.push(file);
+            }
+        }
+    }
+
+    /// The turn as a markdown document: request, plan, trimmed tool outputs, the working-tree
+    /// diff of changed files, and the final explanation
+    pub fn to_markdown(&self, workspace_root: &Path) -> String {
+        let mut markdown = format!("# {}\n\n", self.title());
+        if !self.started_at.is_empty() {
+            markdown.push_str(&format!("_Agent session report, {}_\n\n", self.started_at));
+        }
+
+        if !self.prompt.is_empty() {
+            markdown.push_str("## Request\n\n");
+            for line in self.prompt.trim().lines() {
+                markdown.push_str(&format!("> {}\n", line));
+            }
+            markdown.push('\n');
+        }
+
+        if !self.steps.is_empty() {
+            markdown.push_str("## Plan\n\n");
+            for (i, step) in self.steps.iter().enumerate() {
+                markdown.push_str(&format!(
+                    "{}. {} {}{}\n",
+                    i + 1,
+                    step.icon,
+                    step.description,
+                    if step.succeeded { "" } else { " _(failed)_" }
+                ));
+            }
+            markdown.push('\n');
+        }
+
+        let outputs: Vec<(usize, &ReportStep, &String)> = self
+            .steps
+            .iter()
+            .enumerate()
+            .filter_map(|(i, step)| step.output.as_ref().map(|output| (i + 1, step, output)))
+            .collect();
+        if !outputs.is_empty() {
+            markdown.push_str("## Tool Outputs\n\n");
+            for (number, step, output) in outputs {
+                markdown.push_str(&format!(
+                    "<details>\n<summary>{}. {}</summary>\n\n````\n{}\n````\n\n</details>\n\n",
+                    number,
+                    step.description,
+                    trim_output(output)
+                ));
+            }
+        }
+
+        if !self.files_changed.is_empty() {
+            markdown.push_str("## Changes\n\n");
+            for file in &self.files_changed {
+                markdown.push_str(&format!("- `{}`\n", file));
+            }
+            markdown.push('\n');
+
+            if let Some(summary) = &self.changes_summary {
+                markdown.push_str(summary.trim());
+                markdown.push_str("\n\n");
+            }
+
+            let mut args = vec!["diff", "--"];
+            args.extend(self.files_changed.iter().map(String::as_str));
+            match git::run_git(workspace_root, &args) {
+                Ok(diff) if !diff.is_empty() => {
+                    markdown.push_str(&format!("```diff\n{}\n```\n\n", tool_env::mask(&diff)))
+                }
+                Ok(_) => markdown.push_str("_No uncommitted changes remain in these files._\n\n"),
+                Err(_) => markdown.push_str("_Diff unavailable outside a git repository._\n\n"),
+            }
+        }
+
+        if let Some(explanation) = &self.explanation {
+            markdown.push_str("## Explanation\n\n");
+            // Explanations carry their own heading; nest it under this section
+            let body = explanation
+                .strip_prefix("# ")
+                .and_then(|rest| rest.split_once('\n'))
+                .map(|(_, body)| body)
+                .unwrap_or(explanation);
+            markdown.push_str(body.trim());
+            markdown.push('\n');
+        }
+
+        markdown
+    }
+
+    fn title(&self) -> String {
+        match self.prompt.trim().lines().next() {
+            Some(line) => crate::utils::truncate_string(line, 80),
+            None => "Agent session report".to_string(),
+        }
+    }
+}
+
+/// Keep the first `OUTPUT_LINES` lines of a tool's output
+fn trim_output(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    if lines.len() <= OUTPUT_LINES {
+        return lines.join("\n");
+    }
+
+    format!(
+        "{}\n... ({} more lines)",
+        lines[..OUTPUT_LINES].join("\n"),
+        lines.len() - OUTPUT_LINES
+    )
+}
@@ -0,0 +1,262 @@
+use crate::ai::{AiClient, Message, MessageRole};
+use crate::error::{AgentError, AgentResult};
+use crate::utils;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Characters of a result shown to the judging model
+const JUDGED_LIMIT: usize = 6000;
+
+const JUDGE_PROMPT: &str = r#"
+You check the result of one step of a command-line coding agent before it is stored as
+context for later steps. You are given the tool that produced it, the criteria it must meet
+and the result itself.
+
+Judge only against the criteria. A result that is truncated or unusual but still meets them
+is valid. Answer by calling the Judge function.
+"#;
+
+/// One check a tool's results must pass before they are stored in context memory
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Validator {
+    Regex(String),     // found somewhere in the result
+    JsonSchema(Value), // the result, parsed as JSON, matches this schema
+    AiJudge(String),   // criteria the model checks the result against
+}
+
+/// Validators by the name of the tool whose results they check, such as `GrepTool`
+pub type Validators = BTreeMap<String, Vec<Validator>>;
+
+/// Validators from the user settings; none until configured
+static VALIDATORS: RwLock<Option<Validators>> = RwLock::new(None);
+
+pub fn configure(validators: Validators) -> AgentResult<()> {
+    check(&validators)?;
+    *VALIDATORS.write().unwrap_or_else(|e| e.into_inner()) = Some(validators);
+    Ok(())
+}
+
+/// An error naming the first validator that could never pass, such as an invalid regex
+pub fn check(validators: &Validators) -> AgentResult<()> {
+    for (tool, checks) in validators {
+        for validator in checks {
+            if let Validator::Regex(pattern) = validator {
+                Regex::new(pattern).map_err(|e| {
+                    AgentError::Configuration(format!(
+                        "Invalid regex validator for {}: {}",
+                        tool, e
+                    ))
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validators_for(tool: &str) -> Vec<Validator> {
+    VALIDATORS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|validators| validators.get(tool).cloned())
+        .unwrap_or_default()
+}
+
+/// Why `result` of `tool` fails its validators, or `None` when it passes them all
+pub async fn validate(tool: &str, result: &str, ai_client: &dyn AiClient) -> Option<String> {
+    for validator in validators_for(tool) {
+        let rejection = match &validator {
+            Validator::Regex(pattern) => Regex::new(pattern)
+                .ok()
+                .filter(|regex| !regex.is_match(result))
+                .map(|_| format!("it does not match /{}/", pattern)),
+            Validator::JsonSchema(schema) => match serde_json::from_str::<Value>(result.trim()) {
+                Ok(value) => schema_violation(schema, &value, "$"),
+                Err(e) => Some(format!("it is not JSON ({})", e)),
+            },
+            Validator::AiJudge(criteria) => match judge(ai_client, tool, criteria, result).await {
+                Ok(verdict) => verdict,
+                // A judge that cannot answer should not hold up the run
+                Err(e) => {
+                    log::warn!("Failed to judge the result of {}: {}", tool, e);
+                    None
+                }
+            },
+        };
+        if rejection.is_some() {
+            return rejection;
+        }
+    }
+    None
+}
+
+/// Where `value` breaks `schema`, for the common subset of JSON schema: `type`, `enum`,
+/// `required`, `properties`, `items`, `minItems`, `maxItems`, `minLength`, `minimum` and
+/// `maximum`. Other keywords are ignored.
+pub fn schema_violation(schema: &Value, value: &Value, path: &str) -> Option<String> {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            other => other.as_str().into_iter().collect(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            return Some(format!("{} is not of type {}", path, types.join(" or ")));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Some(format!(
+                "{} is not one of {}",
+                path,
+                Value::from(options.clone())
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            let required = schema.get("required").and_then(Value::as_array);
+            if let Some(missing) = required
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .find(|key| !object.contains_key(*key))
+            {
+                return Some(format!("{} is missing `{}`", path, missing));
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, property) in properties.into_iter().flatten() {
+                if let Some(field) = object.get(key) {
+                    let violation = schema_violation(property, field, &format!("{}.{}", path, key));
+                    if violation.is_some() {
+                        return violation;
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_u64);
+            if bound("minItems").is_some_and(|min| (items.len() as u64) < min) {
+                return Some(format!("{} has too few items ({})", path, items.len()));
+            }
+            if bound("maxItems").is_some_and(|max| (items.len() as u64) > max) {
+                return Some(format!("{} has too many items ({})", path, items.len()));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    let violation =
+                        schema_violation(item_schema, item, &format!("{}[{}]", path, index));
+                    if violation.is_some() {
+                        return violation;
+                    }
+                }
+            }
+        }
+        Value::String(text) => {
+            let min = schema.get("minLength").and_then(Value::as_u64);
+            if min.is_some_and(|min| (text.chars().count() as u64) < min) {
+                return Some(format!("{} is too short", path));
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+            if bound("minimum").is_some_and(|min| number < min)
+                || bound("maximum").is_some_and(|max| number > max)
+            {
+                return Some(format!("{} is out of range ({})", path, number));
+            }
+        }
+        _ => {}
+    }
+    None
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// The model's reason for rejecting `result`, or `None` when it meets `criteria`
+async fn judge(
+    ai_client: &dyn AiClient,
+    tool: &str,
+    criteria: &str,
+    result: &str,
+) -> AgentResult<Option<String>> {
+    let messages = vec![
+        Message {
+            role: MessageRole::System,
+            content: JUDGE_PROMPT.to_string(),
+            name: None,
+        },
+        Message {
+            role: MessageRole::User,
+            content: format!(
+                "TOOL: {}\n\nCRITERIA:\n{}\n\nRESULT:\n{}",
+                tool,
+                criteria,
+                utils::truncate_string(result, JUDGED_LIMIT)
+            ),
+            name: None,
+        },
+    ];
+
+    let response = ai_client
+        .chat_completion_with_functions(messages, judge_function(), false)
+        .await?;
+    let Some(call) = response.tool_calls.into_iter().flatten().next() else {
+        return Err(AgentError::AiApi(
+            "The judge answered without a verdict".to_string(),
+        ));
+    };
+
+    let arguments = match call.function.arguments {
+        Value::String(raw) => serde_json::from_str(&raw)?,
+        arguments => arguments,
+    };
+    Ok(match arguments["valid"].as_bool() {
+        Some(false) => Some(
+            arguments["reason"]
+                .as_str()
+                .unwrap_or("the judge gave no reason")
+                .to_string(),
+        ),
+        _ => None,
+    })
+}
+
+fn judge_function() -> Value {
+    json!([
+        {
+            "name": "Judge",
+            "description": "Say whether the result meets the criteria",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "valid": {
+                        "type": "boolean"
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "When invalid, one sentence on which criterion it misses"
+                    }
+                },
+                "required": ["valid"]
+            }
+        }
+    ])
+}
@@ -0,0 +1,189 @@
+use crate::error::{AgentError, AgentResult};
+use crate::memory::{MemoryFragment, MemoryMetadata, Provenance};
+use crate::tool_env;
+use std::fmt;
+use std::sync::RwLock;
+
+/// Source of fragments holding captured terminal output
+pub const SCROLLBACK_SOURCE: &str = "scrollback";
+/// Lines captured when `/capture` is given no count
+pub const DEFAULT_CAPTURE_LINES: usize = 200;
+/// Most lines one capture keeps
+pub const MAX_CAPTURE_LINES: usize = 2_000;
+/// Source of the pinned fragment naming the pane the agent may read
+pub const WATCHED_PANE_SOURCE: &str = "watched_pane";
+
+/// A tmux pane the user opted in to letting the agent read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedPane {
+    pub id: String,      // tmux pane id such as %3, stable while the pane lives
+    pub command: String, // what was running in it when it was watched
+}
+
+/// The pane set with `/watch`; the agent reads no pane unless one is set
+static WATCHED: RwLock<Option<WatchedPane>> = RwLock::new(None);
+
+/// A terminal multiplexer whose scrollback can be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// The multiplexer this process runs inside, from the variables each sets for its shells
+    pub fn detect() -> Option<Self> {
+        let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        if set("TMUX") {
+            Some(Self::Tmux)
+        } else if set("STY") {
+            Some(Self::Screen)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Multiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tmux => write!(f, "tmux"),
+            Self::Screen => write!(f, "screen"),
+        }
+    }
+}
+
+/// The last `lines` lines of scrollback, from the given tmux pane (e.g. `{last}` or `%3`)
+/// or the current one. Screen only captures the current window.
+pub async fn capture(
+    multiplexer: Multiplexer,
+    lines: usize,
+    pane: Option<&str>,
+) -> AgentResult<String> {
+    let lines = lines.clamp(1, MAX_CAPTURE_LINES);
+    let text = match multiplexer {
+        Multiplexer::Tmux => {
+            // -J joins wrapped lines; the prompt at the bottom is trimmed below
+            let start = format!("-{}", lines);
+            let mut args = vec!["capture-pane", "-p", "-J", "-S", start.as_str()];
+            if let Some(pane) = pane {
+                args.extend(["-t", pane]);
+            }
+            run("tmux", &args).await?
+        }
+        Multiplexer::Screen => {
+            if pane.is_some() {
+                return Err(AgentError::Cli(
+                    "screen can only capture the current window".to_string(),
+                ));
+            }
+            let path = std::env::temp_dir().join(format!(
+                "indiefuture-scrollback-{}.txt",
+                uuid::Uuid::new_v4()
+            ));
+            let hardcopy = path.to_string_lossy().to_string();
+            run("screen", &["-X", "hardcopy", "-h", hardcopy.as_str()]).await?;
+            let text = std::fs::read_to_string(&path);
+            let _ = std::fs::remove_file(&path);
+            text?
+        }
+    };
+    Ok(last_lines(&text, lines))
+}
+
+/// Let the agent read the tmux pane `target` (e.g. `{last}`, `%3` or `dev:1.0`). Only its
+/// output is read; keys are never sent to it.
+pub async fn watch(target: &str) -> AgentResult<WatchedPane> {
+    let description = run(
+        "tmux",
+        &[
+            "display-message",
+            "-p",
+            "-t",
+            target,
+            "#{pane_id} #{pane_current_command}",
+        ],
+    )
+    .await?;
+    let (id, command) = description
+        .trim()
+        .split_once(' ')
+        .unwrap_or((description.trim(), ""));
+    if !id.starts_with('%') {
+        return Err(AgentError::Cli(format!("No tmux pane {}", target)));
+    }
+
+    let pane = WatchedPane {
+        id: id.to_string(),
+        command: command.to_string(),
+    };
+    *WATCHED.write().unwrap_or_else(|e| e.into_inner()) = Some(pane.clone());
+    Ok(pane)
+}
+
+/// Stop reading the watched pane
+pub fn unwatch() -> Option<WatchedPane> {
+    WATCHED.write().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+pub fn watched() -> Option<WatchedPane> {
+    WATCHED.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Pinned context telling the planner it can read the watched pane, or None when there is none
+pub fn watched_fragment() -> Option<MemoryFragment> {
+    let pane = watched()?;
+    Some(MemoryFragment {
+        source: WATCHED_PANE_SOURCE.to_string(),
+        provenance: Provenance::UserProvided,
+        content: format!(
+            "The user runs `{}` in tmux pane {} and lets you read its output with \
+            TmuxPaneTool, e.g. to diagnose dev-server logs or a REPL session they are driving. \
+            You cannot type into it; ask the user to run anything there.",
+            pane.command, pane.id
+        ),
+        metadata: None,
+    })
+}
+
+async fn run(program: &str, args: &[&str]) -> AgentResult<String> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| AgentError::Cli(format!("Failed to run {}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(AgentError::Cli(format!(
+            "{} could not capture the scrollback: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The last `count` lines of `text`, ignoring the blank lines a pane has below its cursor
+pub fn last_lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().map(str::trim_end).collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+/// Context fragment with captured output, secrets from the tool environment masked
+pub fn fragment(text: &str, multiplexer: Multiplexer) -> MemoryFragment {
+    MemoryFragment {
+        source: SCROLLBACK_SOURCE.to_string(),
+        provenance: Provenance::UserProvided,
+        content: format!(
+            "The last lines of the user's terminal ({}), including commands they ran and \
+            their output:\n{}",
+            multiplexer,
+            tool_env::mask(text)
+        ),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("terminal".to_string()),
+            path: None,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            tags: vec!["scrollback".to_string()],
+        }),
+    }
+}
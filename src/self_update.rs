@@ -0,0 +1,307 @@
+use crate::config::Settings;
+use crate::error::{AgentError, AgentResult};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version of the running binary
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Name of the crate on crates.io
+const CRATE_NAME: &str = env!("CARGO_PKG_NAME");
+const USER_AGENT: &str = concat!("indiefuture-cli/", env!("CARGO_PKG_VERSION"));
+/// A release listing every binary with its sha256, as `sha256sum` prints them. It is
+/// published with the binaries, so it detects a corrupted download, not a tampered release.
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// How the running binary was installed, which decides how it is updated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Installation {
+    /// With `cargo install`; updated by running it again
+    Cargo,
+    /// A release binary, replaced in place
+    Binary(PathBuf),
+}
+
+impl Installation {
+    /// Binaries in cargo's `bin` directory were put there by `cargo install`
+    pub fn detect() -> AgentResult<Self> {
+        let exe = std::env::current_exe()?;
+        let exe = exe.canonicalize().unwrap_or(exe);
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+        let cargo_bin = cargo_home.map(|home| {
+            let bin = home.join("bin");
+            bin.canonicalize().unwrap_or(bin)
+        });
+
+        Ok(match cargo_bin {
+            Some(bin) if exe.starts_with(&bin) => Self::Cargo,
+            _ => Self::Binary(exe),
+        })
+    }
+}
+
+/// A file attached to a GitHub release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub url: String,
+}
+
+/// The latest published GitHub release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub version: String, // the tag without its leading `v`
+    pub assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    /// A release from the GitHub API's JSON
+    pub fn from_json(json: &serde_json::Value) -> Option<Self> {
+        let tag = json["tag_name"].as_str()?;
+        let assets = json["assets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|asset| {
+                Some(ReleaseAsset {
+                    name: asset["name"].as_str()?.to_string(),
+                    url: asset["browser_download_url"].as_str()?.to_string(),
+                })
+            })
+            .collect();
+        Some(Self {
+            version: tag.trim_start_matches('v').to_string(),
+            assets,
+        })
+    }
+
+    fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// `major.minor.patch` of a version; pre-release and build suffixes are ignored
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// Whether `candidate` is a later version than `current`
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    match (parse_version(candidate), parse_version(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+/// Release binaries are named after the platform they run on, e.g.
+/// `indiefuture-x86_64-linux` or `indiefuture-aarch64-macos`
+pub fn asset_name() -> String {
+    format!(
+        "indiefuture-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// The sha256 of `asset` in a checksum file: either `sha256sum` output listing several
+/// files, or a lone hash for a `<asset>.sha256` file
+pub fn parse_checksum(text: &str, asset: &str) -> Option<String> {
+    let is_hash = |word: &str| word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit());
+    let mut lone = None;
+
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        let (Some(hash), name) = (words.next(), words.next()) else {
+            continue;
+        };
+        if !is_hash(hash) {
+            continue;
+        }
+        // sha256sum marks binary-mode files with a `*` before the name
+        match name.map(|name| name.trim_start_matches('*')) {
+            Some(name) if name == asset => return Some(hash.to_lowercase()),
+            Some(_) => {}
+            None => lone = Some(hash.to_lowercase()),
+        }
+    }
+    lone
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn client(timeout: Duration) -> AgentResult<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(timeout)
+        .build()?)
+}
+
+async fn latest_release(repository: &str) -> AgentResult<Release> {
+    let json: serde_json::Value = client(Duration::from_secs(20))?
+        .get(format!(
+            "https://api.github.com/repos/{}/releases/latest",
+            repository
+        ))
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| AgentError::Cli(format!("No release found for {}: {}", repository, e)))?
+        .json()
+        .await?;
+    Release::from_json(&json)
+        .ok_or_else(|| AgentError::Cli(format!("The latest {} release has no tag", repository)))
+}
+
+async fn latest_crate_version() -> AgentResult<String> {
+    let json: serde_json::Value = client(Duration::from_secs(20))?
+        .get(format!("https://crates.io/api/v1/crates/{}", CRATE_NAME))
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| AgentError::Cli(format!("crates.io has no crate {}: {}", CRATE_NAME, e)))?
+        .json()
+        .await?;
+    json["crate"]["max_stable_version"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AgentError::Cli(format!("crates.io lists no versions of {}", CRATE_NAME)))
+}
+
+async fn download(url: &str) -> AgentResult<Vec<u8>> {
+    let bytes = client(Duration::from_secs(300))?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(bytes.to_vec())
+}
+
+/// Check for a newer release and, unless `check_only`, install it. Returns whether a newer
+/// version is available that is not installed yet.
+pub async fn run(settings: &Settings, check_only: bool) -> AgentResult<bool> {
+    let installation = Installation::detect()?;
+    let release = match installation {
+        Installation::Cargo => None,
+        Installation::Binary(_) => Some(latest_release(&settings.update_repository).await?),
+    };
+    let latest = match &release {
+        Some(release) => release.version.clone(),
+        None => latest_crate_version().await?,
+    };
+
+    if !is_newer(&latest, CURRENT_VERSION) {
+        println!("indiefuture {} is up to date", CURRENT_VERSION);
+        return Ok(false);
+    }
+    println!(
+        "indiefuture {} is available (installed: {})",
+        latest, CURRENT_VERSION
+    );
+    if check_only {
+        return Ok(true);
+    }
+
+    match (installation, release) {
+        (Installation::Binary(exe), Some(release)) => {
+            install(&release, &exe).await?;
+            println!("Updated {} to {}", exe.display(), latest);
+            println!(
+                "The download matched the checksum published with the release, which rules out \
+                 a corrupted download but not a tampered release"
+            );
+            Ok(false)
+        }
+        _ => {
+            println!(
+                "Installed with cargo; update with:\n  cargo install --locked {}",
+                CRATE_NAME
+            );
+            Ok(true)
+        }
+    }
+}
+
+/// Download this platform's binary from `release`, check it against the release's
+/// checksums, and put it in place of `exe`
+async fn install(release: &Release, exe: &Path) -> AgentResult<()> {
+    let name = asset_name();
+    let binary = release.asset(&name).ok_or_else(|| {
+        AgentError::Cli(format!(
+            "Release {} has no {} binary; build it from source with cargo",
+            release.version, name
+        ))
+    })?;
+    let checksum_asset = release
+        .asset(&format!("{}.sha256", name))
+        .or_else(|| release.asset(CHECKSUMS_ASSET))
+        .ok_or_else(|| {
+            AgentError::Cli(format!(
+                "Release {} publishes no checksum for {}; not installing an unverified binary",
+                release.version, name
+            ))
+        })?;
+
+    let checksums = String::from_utf8_lossy(&download(&checksum_asset.url).await?).to_string();
+    let expected = parse_checksum(&checksums, &name).ok_or_else(|| {
+        AgentError::Cli(format!(
+            "{} lists no checksum for {}",
+            checksum_asset.name, name
+        ))
+    })?;
+    let bytes = download(&binary.url).await?;
+    let actual = sha256_hex(&bytes);
+    if actual != expected {
+        return Err(AgentError::Cli(format!(
+            "Checksum mismatch for {} (expected {}, got {}); the binary was not replaced",
+            name, expected, actual
+        )));
+    }
+
+    replace_binary(exe, &bytes)
+}
+
+/// Swap `exe` for `bytes`. The running binary is renamed aside rather than overwritten,
+/// which every platform allows, and restored if the new one cannot be moved in.
+pub fn replace_binary(exe: &Path, bytes: &[u8]) -> AgentResult<()> {
+    let file_name = exe
+        .file_name()
+        .ok_or_else(|| AgentError::Cli(format!("{} is not a file", exe.display())))?
+        .to_string_lossy()
+        .to_string();
+    let staged = exe.with_file_name(format!("{}.new", file_name));
+    let previous = exe.with_file_name(format!("{}.old", file_name));
+    // Windows keeps the last replaced binary until the next update, while it may still run
+    let _ = std::fs::remove_file(&previous);
+
+    std::fs::write(&staged, bytes)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    std::fs::rename(exe, &previous)?;
+    if let Err(e) = std::fs::rename(&staged, exe) {
+        let _ = std::fs::rename(&previous, exe);
+        let _ = std::fs::remove_file(&staged);
+        return Err(e.into());
+    }
+    let _ = std::fs::remove_file(&previous);
+    Ok(())
+}
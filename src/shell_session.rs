@@ -0,0 +1,476 @@
+use crate::error::{AgentError, AgentResult};
+use crate::platform::{Shell, ShellKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc;
+
+#[cfg(unix)]
+use crate::pty::{self, PtyProcess};
+#[cfg(unix)]
+use crate::tool_env::{self, ToolEnv};
+#[cfg(unix)]
+use std::time::Duration;
+#[cfg(unix)]
+use tokio::process::Command;
+#[cfg(unix)]
+use tokio::sync::{oneshot, Mutex};
+#[cfg(unix)]
+use tokio::time::Instant;
+
+/// Commands still running after this are interrupted with Ctrl+C
+#[cfg(unix)]
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How long an interrupted command gets to return to the prompt before the shell is restarted
+#[cfg(unix)]
+const INTERRUPT_GRACE: Duration = Duration::from_secs(5);
+
+/// A command silent this long after printing a prompt is taken to be waiting for input
+#[cfg(unix)]
+const INPUT_IDLE: Duration = Duration::from_secs(5);
+
+/// Pause after Ctrl+C before typing anything else
+#[cfg(unix)]
+const INTERRUPT_SETTLE: Duration = Duration::from_millis(200);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ATTENDED: AtomicBool = AtomicBool::new(true);
+
+#[cfg(unix)]
+static SESSION: Mutex<Option<ShellSession>> = Mutex::const_new(None);
+
+/// Run BashTool commands in one long-lived shell from now on, if the configured shell allows it
+pub fn enable() {
+    if supported() {
+        ENABLED.store(true, Ordering::Relaxed);
+    } else {
+        log::warn!(
+            "A persistent shell session needs sh, bash or zsh on a Unix system; {} commands run one at a time",
+            Shell::current().name()
+        );
+    }
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Whether someone is at the terminal to answer commands waiting for input; when nobody
+/// is, such commands are interrupted instead of blocking the run
+pub fn set_attended(attended: bool) {
+    ATTENDED.store(attended, Ordering::Relaxed);
+}
+
+pub fn attended() -> bool {
+    ATTENDED.load(Ordering::Relaxed)
+}
+
+fn supported() -> bool {
+    cfg!(unix)
+        && matches!(
+            Shell::current().kind,
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh
+        )
+}
+
+/// Whether `line`, the last thing a silent command printed, reads like a request for input
+pub fn looks_like_prompt(line: &str) -> bool {
+    let line = line.trim().to_lowercase();
+    if line.is_empty() {
+        return false;
+    }
+
+    [
+        "y/n",
+        "yes/no",
+        "password",
+        "passphrase",
+        "press enter",
+        "press any key",
+    ]
+    .iter()
+    .any(|cue| line.contains(cue))
+        || line.ends_with([':', '?', '>', '»'])
+}
+
+/// Something the session reports while a command runs
+#[derive(Debug)]
+pub enum SessionEvent {
+    Line(String), // a complete line of output
+    /// The command has been silent for a while after `prompt`; it waits until `reply`
+    /// says what to do
+    AwaitingInput {
+        prompt: String,
+        reply: oneshot::Sender<InputChoice>,
+    },
+}
+
+/// What to do with a command waiting for input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputChoice {
+    Send(String), // type this line into it
+    Wait,         // leave it alone; it is asked about again after more output
+    Background,   // suspend it and resume it as a background job of the shell
+    Kill,
+}
+
+/// Why a command returned before finishing on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interruption {
+    TimedOut,
+    Killed,       // stopped while waiting for input
+    Backgrounded, // still running as a background job of the shell
+}
+
+/// What a command run in the session left behind
+#[derive(Debug, Clone)]
+pub struct SessionOutput {
+    pub output: String, // stdout and stderr interleaved as the terminal showed them, masked
+    pub exit_code: i32,
+    pub cwd: PathBuf, // the shell's working directory once the command finished
+    pub interruption: Option<Interruption>,
+}
+
+/// Run `command` in the session's shell, starting it in `workspace_root` on first use.
+/// Environment changes, activated virtualenvs and `cd` carry over to the next command.
+/// Complete output lines, and questions about a command that seems to wait for input, are
+/// sent to `events` as they come up.
+#[cfg(unix)]
+pub async fn run(
+    command: &str,
+    workspace_root: &Path,
+    events: mpsc::UnboundedSender<SessionEvent>,
+) -> AgentResult<SessionOutput> {
+    let mut session = SESSION.lock().await;
+    if session.is_none() {
+        *session = Some(ShellSession::start(workspace_root).await?);
+    }
+
+    let result = match session.as_mut() {
+        Some(shell) => shell.run(command, &events).await,
+        None => unreachable!(),
+    };
+    // A shell that exited or stopped responding is replaced on the next command
+    if result.is_err() {
+        *session = None;
+    }
+    result
+}
+
+#[cfg(not(unix))]
+pub async fn run(
+    _command: &str,
+    _workspace_root: &Path,
+    _events: mpsc::UnboundedSender<SessionEvent>,
+) -> AgentResult<SessionOutput> {
+    Err(AgentError::ToolExecution(
+        "Persistent shell sessions are only supported on Unix".to_string(),
+    ))
+}
+
+/// How reading up to a marker ended
+#[cfg(unix)]
+enum Read {
+    Done(SessionOutput),
+    TimedOut,
+    AwaitingInput(String), // the prompt the command went silent after
+}
+
+#[cfg(unix)]
+struct ShellSession {
+    process: PtyProcess,
+    output: mpsc::UnboundedReceiver<Vec<u8>>,
+    marker: String, // printed after each command, with its exit code and working directory
+    sequence: u64,  // numbers markers, so one left over from an interrupted command is ignored
+    pending: Vec<u8>, // output of the running command read so far
+    streamed: usize, // bytes of `pending` already sent as lines
+    last_line: String, // last complete line sent
+    last_output: Instant,
+    asked: bool, // whether the user was asked about the current silence
+}
+
+#[cfg(unix)]
+impl ShellSession {
+    async fn start(workspace_root: &Path) -> AgentResult<Self> {
+        let shell = Shell::current();
+        let mut command = Command::new(&shell.program);
+        // The user's rc files may set prompts or hooks that garble output; a login shell
+        // still reads the profile, as one-shot commands do
+        if shell.login {
+            command.arg("-l");
+        }
+        match shell.kind {
+            ShellKind::Bash if shell.login => {
+                command.arg("--noediting");
+            }
+            ShellKind::Bash => {
+                command.args(["--noprofile", "--norc", "--noediting"]);
+            }
+            ShellKind::Zsh if !shell.login => {
+                command.arg("-f");
+            }
+            _ => {}
+        }
+        command.args(&shell.args).current_dir(workspace_root);
+        ToolEnv::current().apply(&mut command);
+
+        let process = PtyProcess::spawn(command, false)?;
+        let output = process.output()?;
+        let mut session = Self {
+            process,
+            output,
+            marker: format!("__indiefuture_done_{}", crate::utils::generate_id()),
+            sequence: 0,
+            pending: Vec::new(),
+            streamed: 0,
+            last_line: String::new(),
+            last_output: Instant::now(),
+            asked: false,
+        };
+
+        // Silence prompts, then drop whatever the shell printed while starting
+        let quiet = match shell.kind {
+            ShellKind::Zsh => "unsetopt ZLE PROMPT_SP PROMPT_CR; PS1=''; PS2=''; RPROMPT=''",
+            ShellKind::Bash => "PS1=''; PS2=''; PROMPT_COMMAND=''; set +H",
+            _ => "PS1=''; PS2=''",
+        };
+        let ready = session.next_marker_command("$?");
+        session
+            .process
+            .write(format!("{}\n{}", quiet, ready).as_bytes())?;
+        let (sink, _) = mpsc::unbounded_channel();
+        match session
+            .read_until_marker(&sink, Instant::now() + INTERRUPT_GRACE, false)
+            .await?
+        {
+            Read::Done(_) => Ok(session),
+            _ => Err(AgentError::ToolExecution(format!(
+                "{} did not start in time",
+                shell.name()
+            ))),
+        }
+    }
+
+    /// A command printing the next marker line; split so the marker never appears in what
+    /// is typed
+    fn next_marker_command(&mut self, exit_code: &str) -> String {
+        self.sequence += 1;
+        let (head, tail) = self.marker.split_at(2);
+        format!(
+            "printf '\\n%s%s_%s %s %s\\n' '{}' '{}' '{}' \"{}\" \"$PWD\"\n",
+            head, tail, self.sequence, exit_code
+        )
+    }
+
+    async fn run(
+        &mut self,
+        command: &str,
+        events: &mpsc::UnboundedSender<SessionEvent>,
+    ) -> AgentResult<SessionOutput> {
+        // The braces make the shell read the whole command, marker included, before running
+        // it, so a program reading stdin cannot swallow the marker
+        let marker = self.next_marker_command("$__rc");
+        self.process
+            .write(format!("{{\n{}\n}}; __rc=$?; {}", command, marker).as_bytes())?;
+        self.last_line.clear();
+        self.last_output = Instant::now();
+        self.asked = false;
+
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        loop {
+            let prompt = match self.read_until_marker(events, deadline, true).await? {
+                Read::Done(output) => return Ok(output),
+                Read::TimedOut => {
+                    log::warn!(
+                        "Interrupting a command still running after {:?}",
+                        COMMAND_TIMEOUT
+                    );
+                    return self.interrupt(events, Interruption::TimedOut).await;
+                }
+                Read::AwaitingInput(prompt) => prompt,
+            };
+
+            let choice = if attended() {
+                let (reply, choice) = oneshot::channel();
+                let _ = events.send(SessionEvent::AwaitingInput { prompt, reply });
+                choice.await.unwrap_or(InputChoice::Kill)
+            } else {
+                log::warn!("Interrupting a command waiting for input with nobody to answer");
+                InputChoice::Kill
+            };
+            match choice {
+                InputChoice::Send(input) => {
+                    self.process.write(format!("{}\n", input).as_bytes())?;
+                    self.last_output = Instant::now();
+                    self.asked = false;
+                }
+                InputChoice::Wait => {}
+                InputChoice::Background => return self.background(events).await,
+                InputChoice::Kill => return self.interrupt(events, Interruption::Killed).await,
+            }
+        }
+    }
+
+    /// Interrupt the running command with Ctrl+C and ask for a fresh marker; the shell
+    /// keeps its state
+    async fn interrupt(
+        &mut self,
+        events: &mpsc::UnboundedSender<SessionEvent>,
+        why: Interruption,
+    ) -> AgentResult<SessionOutput> {
+        self.process.write(b"\x03")?;
+        // A builtin such as `read` would take a marker typed before the shell handled the
+        // interrupt as its input
+        tokio::time::sleep(INTERRUPT_SETTLE).await;
+        let marker = self.next_marker_command("130");
+        self.process.write(marker.as_bytes())?;
+
+        match self
+            .read_until_marker(events, Instant::now() + INTERRUPT_GRACE, false)
+            .await?
+        {
+            Read::Done(output) => Ok(SessionOutput {
+                interruption: Some(why),
+                ..output
+            }),
+            _ => Err(AgentError::ToolExecution(
+                "The shell session did not respond to Ctrl+C and was restarted".to_string(),
+            )),
+        }
+    }
+
+    /// Suspend the running command with Ctrl+Z and resume it as a background job. The rest
+    /// of the command line goes on without it.
+    async fn background(
+        &mut self,
+        events: &mpsc::UnboundedSender<SessionEvent>,
+    ) -> AgentResult<SessionOutput> {
+        self.process.write(b"\x1a")?;
+        let output = match self
+            .read_until_marker(events, Instant::now() + INTERRUPT_GRACE, false)
+            .await?
+        {
+            Read::Done(output) => output,
+            _ => {
+                log::warn!("The command could not be suspended, interrupting it instead");
+                return self.interrupt(events, Interruption::Killed).await;
+            }
+        };
+
+        // Job notices are not part of the command's output
+        let resume = format!("bg >/dev/null 2>&1; {}", self.next_marker_command("$?"));
+        self.process.write(resume.as_bytes())?;
+        let (sink, _) = mpsc::unbounded_channel();
+        self.read_until_marker(&sink, Instant::now() + INTERRUPT_GRACE, false)
+            .await?;
+
+        Ok(SessionOutput {
+            interruption: Some(Interruption::Backgrounded),
+            ..output
+        })
+    }
+
+    /// Read output until the current marker line, sending complete lines to `events` on
+    /// the way. With `watch`, a silence after a prompt-like line ends the read early.
+    async fn read_until_marker(
+        &mut self,
+        events: &mpsc::UnboundedSender<SessionEvent>,
+        deadline: Instant,
+        watch: bool,
+    ) -> AgentResult<Read> {
+        let needle = format!("{}_{} ", self.marker, self.sequence);
+        loop {
+            let marker_at = find(&self.pending, needle.as_bytes());
+            let end = marker_at.unwrap_or(self.pending.len());
+            if let Some(last_newline) = self.pending[self.streamed..end]
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+            {
+                let complete = self.streamed + last_newline + 1;
+                for line in clean(&self.pending[self.streamed..complete]).lines() {
+                    if !line.contains(&self.marker) {
+                        let _ = events.send(SessionEvent::Line(line.to_string()));
+                        self.last_line = line.to_string();
+                    }
+                }
+                self.streamed = complete;
+            }
+
+            if let Some(marker_at) = marker_at {
+                let status_start = marker_at + needle.len();
+                if let Some(status_len) = self.pending[status_start..]
+                    .iter()
+                    .position(|byte| *byte == b'\n')
+                {
+                    let status = clean(&self.pending[status_start..status_start + status_len]);
+                    let (exit_code, cwd) = status.split_once(' ').unwrap_or(("", ""));
+                    let output = clean(&self.pending[..marker_at])
+                        .lines()
+                        .filter(|line| !line.contains(&self.marker))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                        .trim_end_matches('\n')
+                        .to_string();
+                    self.pending.clear();
+                    self.streamed = 0;
+
+                    return Ok(Read::Done(SessionOutput {
+                        output,
+                        exit_code: exit_code.parse().unwrap_or(-1),
+                        cwd: PathBuf::from(cwd),
+                        interruption: None,
+                    }));
+                }
+            }
+
+            // A prompt usually has no line break after it; otherwise it is the last line
+            let waiting_for = if watch && !self.asked {
+                let partial = clean(&self.pending[self.streamed..]);
+                let prompt = match partial.trim() {
+                    "" => self.last_line.clone(),
+                    partial => partial.to_string(),
+                };
+                looks_like_prompt(&prompt).then_some(prompt)
+            } else {
+                None
+            };
+            let wake = match &waiting_for {
+                Some(_) => deadline.min(self.last_output + INPUT_IDLE),
+                None => deadline,
+            };
+
+            match tokio::time::timeout_at(wake, self.output.recv()).await {
+                Ok(Some(chunk)) => {
+                    self.pending.extend(chunk);
+                    self.last_output = Instant::now();
+                    self.asked = false;
+                }
+                Ok(None) => {
+                    return Err(AgentError::ToolExecution(
+                        "The shell session exited".to_string(),
+                    ))
+                }
+                Err(_) if Instant::now() >= deadline => return Ok(Read::TimedOut),
+                Err(_) => {
+                    self.asked = true;
+                    if let Some(prompt) = waiting_for {
+                        return Ok(Read::AwaitingInput(prompt.trim().to_string()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Terminal output as recorded: plain text with injected secrets masked
+#[cfg(unix)]
+fn clean(bytes: &[u8]) -> String {
+    tool_env::mask(&pty::strip_ansi(&String::from_utf8_lossy(bytes)))
+}
@@ -0,0 +1,316 @@
+use crate::config::Settings;
+use crate::error::{AgentError, AgentResult};
+use async_trait::async_trait;
+#[cfg(feature = "postgres")]
+use degen_sql::db::postgres::postgres_db::Database;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Raw key-value operations a storage backend must provide.
+/// Values are JSON strings; trees are flat namespaces of keys.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn put(&self, tree: &str, key: &str, value: String) -> AgentResult<()>;
+
+    async fn get(&self, tree: &str, key: &str) -> AgentResult<Option<String>>;
+
+    /// Remove a key, returning whether it existed
+    async fn remove(&self, tree: &str, key: &str) -> AgentResult<bool>;
+
+    /// All values in the tree, in key order
+    async fn values(&self, tree: &str) -> AgentResult<Vec<String>>;
+
+    /// Short name shown to the user, e.g. "local" or "postgres"
+    fn name(&self) -> &'static str;
+}
+
+/// Persistent storage for data that must outlive a session.
+/// Workspace trees are shared by everyone using the same backend; user trees are private.
+#[derive(Clone)]
+pub struct Storage {
+    backend: Arc<dyn StorageBackend>,
+    user: String,
+}
+
+impl Storage {
+    /// Open the local sled database at the given directory
+    pub fn open(path: &Path, user: &str) -> AgentResult<Self> {
+        Ok(Self {
+            backend: Arc::new(SledBackend::open(path)?),
+            user: user.to_string(),
+        })
+    }
+
+    /// Open a throwaway in-memory database, used when the on-disk one is unavailable
+    pub fn temporary(user: &str) -> AgentResult<Self> {
+        Ok(Self {
+            backend: Arc::new(SledBackend::temporary()?),
+            user: user.to_string(),
+        })
+    }
+
+    /// Connect to the backend selected in settings
+    pub async fn connect(settings: &Settings) -> AgentResult<Self> {
+        match settings.memory_backend.as_str() {
+            "local" => Self::open(&settings.sled_path, &settings.memory_user),
+            #[cfg(feature = "postgres")]
+            "postgres" => {
+                let url = settings.memory_backend_url.as_deref().ok_or_else(|| {
+                    AgentError::Configuration(
+                        "MEMORY_BACKEND_URL is required for the postgres memory backend"
+                            .to_string(),
+                    )
+                })?;
+
+                Ok(Self {
+                    backend: Arc::new(PostgresBackend::connect(url).await?),
+                    user: settings.memory_user.clone(),
+                })
+            }
+            #[cfg(not(feature = "postgres"))]
+            "postgres" => Err(AgentError::Configuration(
+                "This build does not include the postgres memory backend; rebuild with `--features postgres`"
+                    .to_string(),
+            )),
+            backend => Err(AgentError::Configuration(format!(
+                "Unsupported memory backend: {}",
+                backend
+            ))),
+        }
+    }
+
+    /// The user private trees belong to
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Open a named tree shared by all users and workspaces
+    pub fn tree(&self, name: &str) -> StorageTree {
+        StorageTree {
+            backend: Arc::clone(&self.backend),
+            name: name.to_string(),
+        }
+    }
+
+    /// Open a named tree scoped to a workspace, shared by all users of that workspace
+    pub fn workspace_tree(&self, name: &str, workspace_id: &str) -> StorageTree {
+        self.tree(&format!("{}:{}", name, workspace_id))
+    }
+
+    /// Open a named tree private to the current user
+    pub fn user_tree(&self, name: &str) -> StorageTree {
+        self.tree(&format!("{}@{}", name, self.user))
+    }
+}
+
+/// A single namespace storing JSON-encoded values under string keys
+#[derive(Clone)]
+pub struct StorageTree {
+    backend: Arc<dyn StorageBackend>,
+    name: String,
+}
+
+impl StorageTree {
+    pub async fn put<T: Serialize>(&self, key: &str, value: &T) -> AgentResult<()> {
+        let json = serde_json::to_string(value)?;
+        self.backend.put(&self.name, key, json).await
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> AgentResult<Option<T>> {
+        match self.backend.get(&self.name, key).await? {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a key, returning whether it existed
+    pub async fn remove(&self, key: &str) -> AgentResult<bool> {
+        self.backend.remove(&self.name, key).await
+    }
+
+    /// All values in key order, skipping entries that no longer deserialize
+    pub async fn values<T: DeserializeOwned>(&self) -> AgentResult<Vec<T>> {
+        let mut values = Vec::new();
+
+        for json in self.backend.values(&self.name).await? {
+            match serde_json::from_str(&json) {
+                Ok(value) => values.push(value),
+                Err(e) => log::warn!("Skipping unreadable storage entry: {}", e),
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+/// Local single-user storage in a sled database
+struct SledBackend {
+    db: sled::Db,
+}
+
+impl SledBackend {
+    fn open(path: &Path) -> AgentResult<Self> {
+        let db = sled::open(path).map_err(|e| {
+            AgentError::Storage(format!("Failed to open storage at {:?}: {}", path, e))
+        })?;
+
+        Ok(Self { db })
+    }
+
+    fn temporary() -> AgentResult<Self> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .map_err(|e| AgentError::Storage(format!("Failed to open temporary storage: {}", e)))?;
+
+        Ok(Self { db })
+    }
+
+    fn open_tree(&self, tree: &str) -> AgentResult<sled::Tree> {
+        self.db
+            .open_tree(tree)
+            .map_err(|e| AgentError::Storage(format!("Failed to open tree {}: {}", tree, e)))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for SledBackend {
+    async fn put(&self, tree: &str, key: &str, value: String) -> AgentResult<()> {
+        let tree = self.open_tree(tree)?;
+        tree.insert(key, value.into_bytes())
+            .map_err(|e| AgentError::Storage(format!("Failed to write {}: {}", key, e)))?;
+        tree.flush()
+            .map_err(|e| AgentError::Storage(format!("Failed to flush storage: {}", e)))?;
+        Ok(())
+    }
+
+    async fn get(&self, tree: &str, key: &str) -> AgentResult<Option<String>> {
+        let bytes = self
+            .open_tree(tree)?
+            .get(key)
+            .map_err(|e| AgentError::Storage(format!("Failed to read {}: {}", key, e)))?;
+
+        Ok(bytes.map(|b| String::from_utf8_lossy(&b).to_string()))
+    }
+
+    async fn remove(&self, tree: &str, key: &str) -> AgentResult<bool> {
+        let tree = self.open_tree(tree)?;
+        let removed = tree
+            .remove(key)
+            .map_err(|e| AgentError::Storage(format!("Failed to remove {}: {}", key, e)))?;
+        tree.flush()
+            .map_err(|e| AgentError::Storage(format!("Failed to flush storage: {}", e)))?;
+
+        Ok(removed.is_some())
+    }
+
+    async fn values(&self, tree: &str) -> AgentResult<Vec<String>> {
+        let mut values = Vec::new();
+
+        for entry in self.open_tree(tree)?.iter() {
+            let (_, bytes) =
+                entry.map_err(|e| AgentError::Storage(format!("Failed to iterate tree: {}", e)))?;
+            values.push(String::from_utf8_lossy(&bytes).to_string());
+        }
+
+        Ok(values)
+    }
+
+    fn name(&self) -> &'static str {
+        "local"
+    }
+}
+
+/// Shared team storage in a Postgres table
+#[cfg(feature = "postgres")]
+struct PostgresBackend {
+    db: Database,
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresBackend {
+    async fn connect(url: &str) -> AgentResult<Self> {
+        let db = Database::connect(url.to_string(), None)
+            .await
+            .map_err(|e| AgentError::Storage(format!("Failed to connect to postgres: {}", e)))?;
+
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS indiefuture_storage (
+                tree TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (tree, key)
+            )",
+            &[],
+        )
+        .await
+        .map_err(|e| AgentError::Storage(format!("Failed to create storage table: {}", e)))?;
+
+        Ok(Self { db })
+    }
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl StorageBackend for PostgresBackend {
+    async fn put(&self, tree: &str, key: &str, value: String) -> AgentResult<()> {
+        self.db
+            .execute(
+                "INSERT INTO indiefuture_storage (tree, key, value) VALUES ($1, $2, $3)
+                 ON CONFLICT (tree, key) DO UPDATE SET value = EXCLUDED.value",
+                &[&tree, &key, &value],
+            )
+            .await
+            .map_err(|e| AgentError::Storage(format!("Failed to write {}: {}", key, e)))?;
+        Ok(())
+    }
+
+    async fn get(&self, tree: &str, key: &str) -> AgentResult<Option<String>> {
+        let rows = self
+            .db
+            .query(
+                "SELECT value FROM indiefuture_storage WHERE tree = $1 AND key = $2",
+                &[&tree, &key],
+            )
+            .await
+            .map_err(|e| AgentError::Storage(format!("Failed to read {}: {}", key, e)))?;
+
+        Ok(rows.first().map(|row| row.get(0)))
+    }
+
+    async fn remove(&self, tree: &str, key: &str) -> AgentResult<bool> {
+        let removed = self
+            .db
+            .execute(
+                "DELETE FROM indiefuture_storage WHERE tree = $1 AND key = $2",
+                &[&tree, &key],
+            )
+            .await
+            .map_err(|e| AgentError::Storage(format!("Failed to remove {}: {}", key, e)))?;
+
+        Ok(removed > 0)
+    }
+
+    async fn values(&self, tree: &str) -> AgentResult<Vec<String>> {
+        let rows = self
+            .db
+            .query(
+                "SELECT value FROM indiefuture_storage WHERE tree = $1 ORDER BY key",
+                &[&tree],
+            )
+            .await
+            .map_err(|e| AgentError::Storage(format!("Failed to iterate tree: {}", e)))?;
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A kind of access a tool needs; approval policy is derived from these
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    /// Reads files in the workspace
+    ReadFs,
+    /// Creates or modifies files in the workspace
+    WriteFs,
+    /// Runs arbitrary commands
+    Exec,
+    /// Stores knowledge that later sessions load, outside the workspace
+    WriteKnowledge,
+    /// Talks to services outside the workspace, including the model when planning
+    Network,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Capability::ReadFs,
+        Capability::WriteFs,
+        Capability::Exec,
+        Capability::WriteKnowledge,
+        Capability::Network,
+    ];
+
+    /// Whether using this capability can change the workspace
+    pub fn is_mutating(&self) -> bool {
+        matches!(self, Self::WriteFs | Self::Exec)
+    }
+
+    /// Whether using this capability leaves anything changed, in the workspace or elsewhere
+    pub fn is_write(&self) -> bool {
+        self.is_mutating() || matches!(self, Self::WriteKnowledge)
+    }
+
+    /// Whether a tool using this capability should be confirmed by default
+    pub fn requires_permission(&self) -> bool {
+        !matches!(self, Self::ReadFs)
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadFs => write!(f, "read files"),
+            Self::WriteFs => write!(f, "write files"),
+            Self::Exec => write!(f, "run commands"),
+            Self::WriteKnowledge => write!(f, "store knowledge"),
+            Self::Network => write!(f, "network"),
+        }
+    }
+}
@@ -1,4 +1,7 @@
+pub mod capability;
+pub mod schema;
 pub mod subtask;
 
 // Re-export SubTaskType for use outside this module
+pub use capability::Capability;
 pub use subtask::SubTaskType;
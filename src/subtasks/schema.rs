@@ -0,0 +1,56 @@
+use crate::error::AgentResult;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// A function definition for the model whose parameters are generated from `T`,
+/// so the schema it sees always matches what `parse::<T>` accepts
+pub fn function<T: JsonSchema>(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "description": description,
+        "parameters": parameters::<T>(),
+    })
+}
+
+/// The JSON schema of `T` as function parameters: self-contained, with optional fields
+/// simply left out of `required` rather than made nullable
+pub fn parameters<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings.option_add_null_type = false;
+            settings.meta_schema = None;
+        })
+        .into_generator();
+
+    let mut schema =
+        serde_json::to_value(generator.into_root_schema_for::<T>()).unwrap_or_default();
+    strip_titles(&mut schema);
+    if let Some(root) = schema.as_object_mut() {
+        // The struct's own doc comment is for contributors; the function describes itself
+        root.remove("description");
+        root.entry("required").or_insert_with(|| json!([]));
+    }
+    schema
+}
+
+/// Parse function call arguments the model sent for `T`'s function
+pub fn parse<T: DeserializeOwned>(args: Value) -> AgentResult<T> {
+    Ok(serde_json::from_value(args)?)
+}
+
+/// Titles are just the Rust type names and only cost prompt tokens
+fn strip_titles(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if object.get("title").is_some_and(Value::is_string) {
+                object.remove("title");
+            }
+            object.values_mut().for_each(strip_titles);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_titles),
+        _ => {}
+    }
+}
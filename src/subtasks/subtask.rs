@@ -1,31 +1,60 @@
 use crate::ai::openai::GptToolCall;
-use crate::memory::ContextMemory;
+use crate::ai::salvage;
+use crate::memory::{self, ContextMemory, Provenance, TRUST_GUIDANCE};
 use tokio::sync::Mutex;
 
+use crate::ai::GenerationProfile;
 use crate::ai::Message;
 use crate::ai::MessageRole;
 use crate::AiClient;
-use cliclack::input;
-use cliclack::log as cliclack_log;
 use serde_json::json;
 
-use cliclack::log;
-
-use crate::agent_engine::SubtaskOutput;
-use crate::AgentError;
+use crate::agent_engine::{SubtaskChoice, SubtaskOutput};
+use crate::answers;
+use crate::clarification::{self, Clarification};
+use crate::command_preview::{self, CommandPreview};
+use crate::editorconfig::EditorStyle;
+use crate::edits::{self, EditSet, FileEdit, InsertPosition};
+use crate::error::{AgentError, AgentResult};
+use crate::experiments;
+use crate::explanation::{ExplanationStream, ASSESSMENT_INSTRUCTIONS};
+use crate::file_rank;
+use crate::fs_guard::{self, PathAccess};
+use crate::model_switch;
+use crate::output;
+use crate::platform;
+use crate::say;
+use crate::shell_session::{self, InputChoice, Interruption, SessionEvent};
+use crate::subtasks::{schema, Capability};
+use crate::text_file::{self, TextFormat};
+use crate::theme::{self, Role};
+use crate::tokens;
+use crate::tool_env;
+use crate::utils;
+use crate::workspaces;
 use async_trait::async_trait;
+use futures::StreamExt;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
-use std::fmt;
+use std::io::Write;
+use std::path::Path;
 use std::sync::Arc;
 
 #[async_trait]
 pub trait SubtaskTool {
     async fn handle_subtask(
         &self,
-        ai_client: &Box<dyn AiClient>,
+        ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput>;
+
+    /// What this tool needs access to. Tools that do not declare anything are
+    /// treated as needing everything, so they are always confirmed.
+    fn capabilities(&self) -> &'static [Capability] {
+        &Capability::ALL
+    }
 }
 
 /// Represents the type of subtask to perform
@@ -35,17 +64,42 @@ pub enum SubTaskType {
 
     Task(String),
 
-    Bash(String),
+    Bash(BashToolInputs),
 
     FileReadTool(FileReadToolInputs),
 
     FileEditTool(FileEditToolInputs),
 
+    EditSymbolTool(EditSymbolToolInputs), // replace a whole definition located by name
+
+    InsertTool(InsertToolInputs), // add lines relative to an anchor or at the end of a file
+
+    EditSetTool(EditSetToolInputs), // edits across several files applied all-or-nothing
+
+    WriteFileTool(WriteFileToolInputs), // create a file, or replace all of one's content
     LSTool(LSToolInputs),
     GlobTool(GlobToolInputs),
     GrepTool(GrepToolInputs),
 
-    ExplainTool(String), // Takes a string query to explain using accumulated context
+    ExplainTool(ExplainToolInputs), // A query to explain using accumulated context
+
+    RememberTool(String), // A durable project fact to keep across sessions
+
+    MemoryGrepTool(MemoryGrepToolInputs), // search text already gathered into context memory
+
+    SemanticSearchTool(SemanticSearchToolInputs), // find code by meaning in the embedding index
+
+    HybridSearchTool(HybridSearchToolInputs), // keyword grep and semantic search, fused by rank
+
+    CrateSourceTool(CrateSourceToolInputs), // read or search a Cargo dependency's source
+
+    CrateDocsTool(CrateDocsToolInputs), // crates.io metadata and README of a crate version
+
+    ErrorLookupTool(ErrorLookupToolInputs), // explain a compiler error code or message
+
+    CoverageTool(CoverageToolInputs), // run the tests under coverage, report uncovered functions
+
+    TmuxPaneTool(TmuxPaneToolInputs), // read the output of the tmux pane the user lets it watch
 }
 
 impl SubTaskType {
@@ -59,7 +113,15 @@ impl SubTaskType {
 
             Self::FileEditTool(input) => Arc::new(FileEditTool(input.clone())),
 
-            Self::Bash(input) => Arc::new(BashTool(input.to_string())),
+            Self::EditSymbolTool(input) => Arc::new(EditSymbolTool(input.clone())),
+
+            Self::InsertTool(input) => Arc::new(InsertTool(input.clone())),
+
+            Self::EditSetTool(input) => Arc::new(EditSetTool(input.clone())),
+
+            Self::WriteFileTool(input) => Arc::new(WriteFileTool(input.clone())),
+
+            Self::Bash(input) => Arc::new(BashTool(input.clone())),
 
             Self::LSTool(input) => Arc::new(LSTool(input.clone())),
 
@@ -69,211 +131,170 @@ impl SubTaskType {
 
             Self::GrepTool(input) => Arc::new(GrepTool(input.clone())),
 
-            Self::ExplainTool(query) => Arc::new(ExplainTool(query.to_string())),
-        }
-    }
+            Self::ExplainTool(input) => Arc::new(ExplainTool(input.clone())),
 
-    /// Returns the tool schema for a specific subtask type
-    pub fn get_ai_tool_schema(&self) -> serde_json::Value {
-        match self {
-            Self::ExplainTool(_) => json!({
-                "name": "ExplainTool",
-                "description": "Provides an explanation to the user using accumulated context. Can be used directly without prior tools when specific information is requested.",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "query": {
-                      "type": "string",
-                      "description": "The specific question or topic to explain based on the gathered context"
-                    }
-                  },
-                  "required": []
-                }
-            }),
+            Self::RememberTool(fact) => Arc::new(RememberTool(fact.to_string())),
 
-            Self::Task(_) => json!({
-                "name": "TaskTool",
-                "description": "Create a multiple subtask for general AI assistance, analysis, or processing. DO not select this tool, ever",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "description": {
-                      "type": "string",
-                      "description": "The task description or query for the AI to process"
-                    }
-                  },
-                  "required": ["description"]
-                }
-            }),
+            Self::MemoryGrepTool(input) => Arc::new(MemoryGrepTool(input.clone())),
 
-            Self::Bash(_) => json!({
-                "name": "BashTool",
-                "description": "Execute a bash command on the system",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "command": {
-                      "type": "string",
-                      "description": "The bash command to execute"
-                    }
-                  },
-                  "required": ["command"]
-                }
-            }),
+            Self::SemanticSearchTool(input) => Arc::new(SemanticSearchTool(input.clone())),
 
-            Self::FileReadTool(_) => json!({
-                "name": "FileReadTool",
-                "description": "Read the contents of a file",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "file_description": {
-                      "type": "string",
-                      "description": "Information about the file or where to find the file"
-                    },
-
-                    "file_path": {
-                      "type": "string",
-                      "description": "The absolute path to the file to read"
-                    },
-                    "limit": {
-                      "type": "number",
-                      "description": "Optional: The maximum number of lines to read"
-                    },
-                    "offset": {
-                      "type": "number",
-                      "description": "Optional: The line number to start reading from (0-based)"
-                    }
-                  },
-                  "required": ["file_path"]
-                }
-            }),
+            Self::HybridSearchTool(input) => Arc::new(HybridSearchTool(input.clone())),
 
-            Self::FileEditTool(_) => json!({
-                "name": "FileEditTool",
-                "description": "Edit the contents of a file by replacing text",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "file_path": {
-                      "type": "string",
-                      "description": "The absolute path to the file to edit"
-                    },
-                    "old_string": {
-                      "type": "string",
-                      "description": "The text to replace. Must be exact including whitespace"
-                    },
-                    "new_string": {
-                      "type": "string",
-                      "description": "The new text to insert in place of old_string"
-                    }
-                  },
-                  "required": ["file_path", "old_string", "new_string"]
-                }
-            }),
+            Self::CrateSourceTool(input) => Arc::new(CrateSourceTool(input.clone())),
 
-            Self::LSTool(_) => json!({
-                "name": "LSTool",
-                "description": "List files and directories at a specified path",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "path": {
-                      "type": "string",
-                      "description": "The absolute path to the directory to list"
-                    },
-                    "ignore": {
-                      "type": "array",
-                      "description": "Optional: List of glob patterns to ignore (e.g. [\"*.tmp\", \"node_modules\"])",
-                      "items": {
-                        "type": "string"
-                      }
-                    }
-                  },
-                  "required": ["path"]
-                }
-            }),
+            Self::CrateDocsTool(input) => Arc::new(CrateDocsTool(input.clone())),
 
-            Self::GlobTool(_) => json!({
-                "name": "GlobTool",
-                "description": "Find files matching a glob pattern. Use only when searching for files is the specific intent.",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "pattern": {
-                      "type": "string",
-                      "description": "The glob pattern to match (e.g., '**/*.rs', 'src/**/*.json', '**/*.{js,ts}')"
-                    },
-                    "path": {
-                      "type": "string",
-                      "description": "Optional: The directory to search in"
-                    }
-                  },
-                  "required": ["pattern"]
-                }
-            }),
+            Self::ErrorLookupTool(input) => Arc::new(ErrorLookupTool(input.clone())),
 
-            Self::GrepTool(_) => json!({
-                "name": "GrepTool",
-                "description": "Search for content in files using regular expressions",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "pattern": {
-                      "type": "string",
-                      "description": "The regex pattern to search for in file contents (e.g., 'function\\s+main', 'import\\s+React')"
-                    },
-                    "include": {
-                      "type": "string",
-                      "description": "Optional: File pattern to include (e.g., '*.rs', '*.{ts,tsx}')"
-                    },
-                    "path": {
-                      "type": "string",
-                      "description": "Optional: The directory to search in"
-                    }
-                  },
-                  "required": ["pattern"]
-                }
-            }),
+            Self::CoverageTool(input) => Arc::new(CoverageTool(input.clone())),
 
-            Self::ProtoSubtask(_) => json!({
-                "name": "ProtoSubtask",
-                "description": "A subtask that will be processed to determine which actual tool to use",
-                "parameters": {
-                  "type": "object",
-                  "properties": {
-                    "description": {
-                      "type": "string",
-                      "description": "Description of what needs to be done"
-                    },
-                    "priority": {
-                      "type": "string",
-                      "description": "Optional priority level (high, medium, low)"
-                    }
-                  },
-                  "required": ["description"]
-                }
-            }),
+            Self::TmuxPaneTool(input) => Arc::new(TmuxPaneTool(input.clone())),
         }
     }
 
-    /// Returns all tool schemas as a JSON array
-    pub fn get_all_tool_schemas() -> serde_json::Value {
-        let task_types = vec![
-            SubTaskType::ExplainTool(String::new()),
+    /// Returns the tool schema for a specific subtask type, with parameters generated from
+    /// the same struct its arguments are parsed into
+    pub fn get_ai_tool_schema(&self) -> serde_json::Value {
+        let name = self.tool_name();
+        match self {
+            Self::ExplainTool(_) => schema::function::<ExplainToolInputs>(
+                name,
+                "Provides an explanation to the user using accumulated context. Can be used directly without prior tools when specific information is requested.",
+            ),
+            Self::RememberTool(_) => schema::function::<RememberToolInputs>(
+                name,
+                "Record a durable fact about this project (e.g. how tests are run, where a subsystem lives) so it is available in future sessions",
+            ),
+            Self::MemoryGrepTool(_) => schema::function::<MemoryGrepToolInputs>(
+                name,
+                "Search text already gathered in this session (file contents read earlier, search results, command output) instead of reading files from disk again",
+            ),
+            Self::SemanticSearchTool(_) => schema::function::<SemanticSearchToolInputs>(
+                name,
+                "Find code by what it does rather than by exact text, e.g. 'where retries are scheduled'. Each result cites a file, line range and similarity; read more around a result with FileReadTool's offset and limit",
+            ),
+            Self::HybridSearchTool(_) => schema::function::<HybridSearchToolInputs>(
+                name,
+                "Search the workspace for a natural-language query by its keywords and by meaning at once, returning one ranked list of file line ranges that either search found. Use it when a query may or may not use the code's own names",
+            ),
+            Self::CrateSourceTool(_) => schema::function::<CrateSourceToolInputs>(
+                name,
+                "List, read or search the source of a dependency declared in Cargo.toml, at the version the project uses. Use it to check how a library API actually behaves instead of guessing",
+            ),
+            Self::CrateDocsTool(_) => schema::function::<CrateDocsToolInputs>(
+                name,
+                "Look up a crate on crates.io: description, features, minimum Rust version, latest release and README, for the version the project uses. Use it for crate APIs that may be newer than your training data",
+            ),
+            Self::ErrorLookupTool(_) => schema::function::<ErrorLookupToolInputs>(
+                name,
+                "Explain a compiler error before planning a fix: rustc error codes (e.g. E0507) with `rustc --explain`, other error messages with a web search",
+            ),
+            Self::CoverageTool(_) => schema::function::<CoverageToolInputs>(
+                name,
+                "Run the Rust test suite under cargo-llvm-cov or cargo-tarpaulin and report line coverage, the least covered files and their functions no test runs. Use it to choose what to write tests for",
+            ),
+            Self::TmuxPaneTool(_) => schema::function::<TmuxPaneToolInputs>(
+                name,
+                "Read the latest output of the tmux pane the user is watching with you (a dev server, REPL or test watcher they run themselves). Only available after the user ran /watch; it cannot type into the pane",
+            ),
+            Self::Task(_) => schema::function::<TaskToolInputs>(
+                name,
+                "Create a multiple subtask for general AI assistance, analysis, or processing. DO not select this tool, ever",
+            ),
+            Self::Bash(_) => schema::function::<BashToolInputs>(
+                name,
+                &format!(
+                    "Execute a shell command on the system ({})",
+                    platform::Shell::current().name()
+                ),
+            ),
+            Self::FileReadTool(_) => {
+                schema::function::<FileReadToolInputs>(name, "Read the contents of a file")
+            }
+            Self::FileEditTool(_) => schema::function::<FileEditToolInputs>(
+                name,
+                "Edit the contents of a file by replacing text",
+            ),
+            Self::EditSymbolTool(_) => schema::function::<EditSymbolToolInputs>(
+                name,
+                "Replace an entire function, method, type or class definition, located by name. Prefer this over FileEditTool when rewriting a whole definition",
+            ),
+            Self::InsertTool(_) => schema::function::<InsertToolArguments>(
+                name,
+                "Insert new lines into a file without replacing anything: after or before the line containing an anchor, or at the end of the file. Prefer this over FileEditTool for adding imports, functions or config entries",
+            ),
+            Self::EditSetTool(_) => schema::function::<EditSetToolInputs>(
+                name,
+                "Apply related text replacements across several files together, e.g. a rename or refactor. All edits are reviewed at once and either all land or none do",
+            ),
+            Self::WriteFileTool(_) => schema::function::<WriteFileToolInputs>(
+                name,
+                "Write a whole file: create a new one, or replace all of an existing file's content when overwrite is true. Prefer FileEditTool, EditSymbolTool or InsertTool for changing part of a file",
+            ),
+            Self::LSTool(_) => schema::function::<LSToolInputs>(
+                name,
+                "List files and directories at a specified path",
+            ),
+            Self::GlobTool(_) => schema::function::<GlobToolInputs>(
+                name,
+                "Find files matching a glob pattern. Use only when searching for files is the specific intent.",
+            ),
+            Self::GrepTool(_) => schema::function::<GrepToolInputs>(
+                name,
+                "Search for content in files using regular expressions",
+            ),
+            Self::ProtoSubtask(_) => schema::function::<ProtoSubtaskInner>(
+                name,
+                "A subtask that will be processed to determine which actual tool to use",
+            ),
+        }
+    }
+
+    /// A placeholder of every tool the model can call, in the order they are offered.
+    /// Schemas and name lookups are derived from this list so they cannot drift apart.
+    pub fn all_tools() -> Vec<SubTaskType> {
+        vec![
+            SubTaskType::ExplainTool(ExplainToolInputs::default()),
+            SubTaskType::RememberTool(String::new()),
+            SubTaskType::MemoryGrepTool(MemoryGrepToolInputs::default()),
+            SubTaskType::SemanticSearchTool(SemanticSearchToolInputs::default()),
+            SubTaskType::HybridSearchTool(HybridSearchToolInputs::default()),
+            SubTaskType::CrateSourceTool(CrateSourceToolInputs::default()),
+            SubTaskType::CrateDocsTool(CrateDocsToolInputs::default()),
+            SubTaskType::ErrorLookupTool(ErrorLookupToolInputs::default()),
+            SubTaskType::CoverageTool(CoverageToolInputs::default()),
+            SubTaskType::TmuxPaneTool(TmuxPaneToolInputs::default()),
             SubTaskType::Task(String::new()),
-            SubTaskType::Bash(String::new()),
+            SubTaskType::Bash(BashToolInputs::default()),
             SubTaskType::FileReadTool(FileReadToolInputs {
-                file_description: None, 
+                file_description: None,
                 file_path: String::new(),
                 limit: None,
                 offset: None,
+                around_symbol: None,
             }),
             SubTaskType::FileEditTool(FileEditToolInputs {
                 file_path: String::new(),
                 old_string: String::new(),
                 new_string: String::new(),
             }),
+            SubTaskType::EditSymbolTool(EditSymbolToolInputs {
+                file_path: String::new(),
+                symbol: String::new(),
+                new_body: String::new(),
+            }),
+            SubTaskType::InsertTool(InsertToolInputs {
+                file_path: String::new(),
+                position: InsertPosition::End,
+                content: String::new(),
+            }),
+            SubTaskType::EditSetTool(EditSetToolInputs { edits: Vec::new() }),
+            SubTaskType::WriteFileTool(WriteFileToolInputs {
+                file_path: String::new(),
+                content: String::new(),
+                overwrite: false,
+            }),
             SubTaskType::LSTool(LSToolInputs {
                 file_path: String::new(),
                 ignore: None,
@@ -281,57 +302,37 @@ impl SubTaskType {
             SubTaskType::GlobTool(GlobToolInputs {
                 pattern: String::new(),
                 path: None,
+                max_matches: None,
+                max_results: None,
+                page: None,
             }),
             SubTaskType::GrepTool(GrepToolInputs {
                 pattern: String::new(),
                 include: None,
                 path: None,
+                case_insensitive: false,
+                fixed_string: false,
+                word_boundary: false,
+                max_matches_per_file: None,
+                max_results: None,
+                page: None,
             }),
-        ];
+        ]
+    }
 
-        json!(task_types
+    /// Returns all tool schemas as a JSON array
+    pub fn get_all_tool_schemas() -> serde_json::Value {
+        json!(Self::all_tools()
             .iter()
             .map(|tt| tt.get_ai_tool_schema())
             .collect::<Vec<_>>())
     }
 
+    /// The schema of the tool called `tool_name`, as a one-element array, or an empty array
     pub fn get_tool_schema_for_tool(tool_name: String) -> serde_json::Value {
-        let mut task_types = Vec::new();
-
-        // Check if the tool name matches any tool, add that tool to task_types
-        match tool_name.as_str() {
-            "ExplainTool" => task_types.push(SubTaskType::ExplainTool(String::new())),
-            "TaskTool" => task_types.push(SubTaskType::Task(String::new())),
-            "BashTool" => task_types.push(SubTaskType::Bash(String::new())),
-            "FileReadTool" => task_types.push(SubTaskType::FileReadTool(FileReadToolInputs {
-                file_path: String::new(),
-                file_description: None,
-                limit: None,
-                offset: None,
-            })),
-            "FileEditTool" => task_types.push(SubTaskType::FileEditTool(FileEditToolInputs {
-                file_path: String::new(),
-                old_string: String::new(),
-                new_string: String::new(),
-            })),
-            "LSTool" => task_types.push(SubTaskType::LSTool(LSToolInputs {
-                file_path: String::new(),
-                ignore: None,
-            })),
-            "GlobTool" => task_types.push(SubTaskType::GlobTool(GlobToolInputs {
-                pattern: String::new(),
-                path: None,
-            })),
-            "GrepTool" => task_types.push(SubTaskType::GrepTool(GrepToolInputs {
-                pattern: String::new(),
-                include: None,
-                path: None,
-            })),
-            _ => {} // Tool name not found
-        }
-
-        json!(task_types
+        json!(Self::all_tools()
             .iter()
+            .filter(|tt| tt.tool_name() == tool_name)
             .map(|tt| tt.get_ai_tool_schema())
             .collect::<Vec<_>>())
     }
@@ -346,144 +347,73 @@ impl SubTaskType {
         // Log that we got a function call
         let _ = cliclack::log::info(format!("Processing function: {} {:?}", function_name, args));
 
-        // Create the appropriate subtask based on the function call
-        let subtask = match function_name {
-            "TaskTool" => {
-                let description = match args["description"].as_str() {
-                    Some(desc) => desc.to_string(),
-                    None => return None,
-                };
-
-                let _ = cliclack::log::info(format!("Adding task: {}", description));
-                SubTaskType::Task(description)
-            }
-
-            "BashTool" => {
-                let command = match args["command"].as_str() {
-                    Some(cmd) => cmd.to_string(),
-                    None => return None,
-                };
-
-                let _ = cliclack::log::info(format!("Adding bash subtask: {}", command));
-                SubTaskType::Bash(command)
-            }
-
-            "FileReadTool" => {
-                let file_path = match args["file_path"].as_str() {
-                    Some(path) => path.to_string(),
-                    None => return None,
-                };
-
-                let limit = args["limit"].as_u64().map(|v| v as u32);
-                let offset = args["offset"].as_u64().map(|v| v as u32);
-
-                let _ = cliclack::log::info(format!("Adding file read subtask: {}", file_path));
-
-                SubTaskType::FileReadTool(FileReadToolInputs {
-                    file_path,
-                    file_description: None,
-                    limit,
-                    offset,
-                })
-            }
-
-            "FileEditTool" => {
-                let file_path = match args["file_path"].as_str() {
-                    Some(path) => path.to_string(),
-                    None => return None,
-                };
-
-                let old_string = match args["old_string"].as_str() {
-                    Some(s) => s.to_string(),
-                    None => return None,
-                };
-
-                let new_string = match args["new_string"].as_str() {
-                    Some(s) => s.to_string(),
-                    None => return None,
-                };
-
-                let _ = cliclack::log::info(format!("Adding file edit subtask: {}", file_path));
-
-                SubTaskType::FileEditTool(FileEditToolInputs {
-                    file_path,
-                    old_string,
-                    new_string,
-                })
-            }
-
-            "LSTool" => {
-                let file_path = match args["path"].as_str() {
-                    Some(path) => path.to_string(),
-                    None => return None,
-                };
-
-                // Parse ignore patterns if present
-                let ignore = if let Some(ignore_arr) = args["ignore"].as_array() {
-                    Some(
-                        ignore_arr
-                            .iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect(),
-                    )
-                } else {
-                    None
-                };
-
-                let _ = cliclack::log::info(format!("Adding LS subtask: {}", file_path));
-
-                SubTaskType::LSTool(LSToolInputs { file_path, ignore })
+        match Self::parse_arguments(function_name, args) {
+            Ok(Some(subtask)) => {
+                let _ = cliclack::log::info(format!("Adding subtask: {}", subtask.description()));
+                Some(subtask)
             }
-
-            "GlobTool" => {
-                let pattern = match args["pattern"].as_str() {
-                    Some(p) => p.to_string(),
-                    None => return None,
-                };
-
-                let path = args["path"].as_str().map(|s| s.to_string());
-
-                let _ = cliclack::log::info(format!("Adding glob search subtask: {}", pattern));
-
-                SubTaskType::GlobTool(GlobToolInputs { pattern, path })
+            Ok(None) => {
+                let _ = cliclack::log::info(format!("Unknown function: {}", function_name));
+                None
             }
-
-            "GrepTool" => {
-                let pattern = match args["pattern"].as_str() {
-                    Some(p) => p.to_string(),
-                    None => return None,
-                };
-
-                let include = args["include"].as_str().map(|s| s.to_string());
-                let path = args["path"].as_str().map(|s| s.to_string());
-
-                let _ = cliclack::log::info(format!("Adding grep search subtask: {}", pattern));
-
-                SubTaskType::GrepTool(GrepToolInputs {
-                    pattern,
-                    include,
-                    path,
-                })
+            Err(e) => {
+                let _ = cliclack::log::warning(format!(
+                    "Invalid arguments for {}: {}",
+                    function_name, e
+                ));
+                None
             }
+        }
+    }
 
-            "ExplainTool" => {
-                let query = match args["query"].as_str() {
-                    Some(q) => q.to_string(),
-                    None => "".into(),
-                };
-
-                let _ = cliclack::log::info(format!("Adding explain subtask: {}", query));
-
-                SubTaskType::ExplainTool(query)
+    /// Build the subtask for a call to `function_name`, parsing `args` into the struct its
+    /// schema was generated from. `Ok(None)` means no tool has that name.
+    fn parse_arguments(function_name: &str, args: serde_json::Value) -> AgentResult<Option<Self>> {
+        let subtask = match function_name {
+            "TaskTool" => SubTaskType::Task(schema::parse::<TaskToolInputs>(args)?.description),
+            "BashTool" => SubTaskType::Bash(schema::parse(args)?),
+            "FileReadTool" => SubTaskType::FileReadTool(schema::parse(args)?),
+            "FileEditTool" => SubTaskType::FileEditTool(schema::parse(args)?),
+            "EditSymbolTool" => SubTaskType::EditSymbolTool(schema::parse(args)?),
+            "InsertTool" => SubTaskType::InsertTool(
+                schema::parse::<InsertToolArguments>(args)?
+                    .into_inputs()
+                    .ok_or_else(|| {
+                        AgentError::ToolExecution(
+                            "one of insert_after, insert_before or append_to_file is required"
+                                .to_string(),
+                        )
+                    })?,
+            ),
+            "EditSetTool" => {
+                let inputs: EditSetToolInputs = schema::parse(args)?;
+                if inputs.edits.is_empty() {
+                    return Err(AgentError::ToolExecution(
+                        "edits must not be empty".to_string(),
+                    ));
+                }
+                SubTaskType::EditSetTool(inputs)
             }
-
-            _ => {
-                let _ = cliclack::log::info(format!("Unknown function: {}", function_name));
-                return None;
+            "WriteFileTool" => SubTaskType::WriteFileTool(schema::parse(args)?),
+            "LSTool" => SubTaskType::LSTool(schema::parse(args)?),
+            "GlobTool" => SubTaskType::GlobTool(schema::parse(args)?),
+            "GrepTool" => SubTaskType::GrepTool(schema::parse(args)?),
+            "ExplainTool" => SubTaskType::ExplainTool(schema::parse(args)?),
+            "RememberTool" => {
+                SubTaskType::RememberTool(schema::parse::<RememberToolInputs>(args)?.fact)
             }
+            "MemoryGrepTool" => SubTaskType::MemoryGrepTool(schema::parse(args)?),
+            "SemanticSearchTool" => SubTaskType::SemanticSearchTool(schema::parse(args)?),
+            "HybridSearchTool" => SubTaskType::HybridSearchTool(schema::parse(args)?),
+            "CrateSourceTool" => SubTaskType::CrateSourceTool(schema::parse(args)?),
+            "CrateDocsTool" => SubTaskType::CrateDocsTool(schema::parse(args)?),
+            "ErrorLookupTool" => SubTaskType::ErrorLookupTool(schema::parse(args)?),
+            "CoverageTool" => SubTaskType::CoverageTool(schema::parse(args)?),
+            "TmuxPaneTool" => SubTaskType::TmuxPaneTool(schema::parse(args)?),
+            _ => return Ok(None),
         };
 
-        return Some(subtask);
+        Ok(Some(subtask))
     }
 }
 
@@ -497,43 +427,224 @@ impl SubTaskType {
     pub fn description(&self) -> String {
         match self {
             SubTaskType::Task(desc) => format!("Task: {}", desc),
-            SubTaskType::Bash(cmd) => format!("Execute: {}", cmd),
+            SubTaskType::Bash(inputs) => format!("Execute: {}", inputs.command),
             SubTaskType::FileReadTool(inputs) => format!("Read File: {}", inputs.file_path),
             SubTaskType::FileEditTool(inputs) => format!("Edit File: {}", inputs.file_path),
+            SubTaskType::EditSetTool(inputs) => {
+                let mut files: Vec<&str> =
+                    inputs.edits.iter().map(|e| e.file_path.as_str()).collect();
+                files.dedup();
+                format!(
+                    "Edit Set: {} edits to {}",
+                    inputs.edits.len(),
+                    files.join(", ")
+                )
+            }
+            SubTaskType::WriteFileTool(inputs) if inputs.overwrite => {
+                format!("Overwrite File: {}", inputs.file_path)
+            }
+            SubTaskType::WriteFileTool(inputs) => format!("Create File: {}", inputs.file_path),
+            SubTaskType::InsertTool(inputs) => match &inputs.position {
+                InsertPosition::After(anchor) => {
+                    format!("Insert into {} after: {}", inputs.file_path, anchor)
+                }
+                InsertPosition::Before(anchor) => {
+                    format!("Insert into {} before: {}", inputs.file_path, anchor)
+                }
+                InsertPosition::End => format!("Append to File: {}", inputs.file_path),
+            },
+            SubTaskType::EditSymbolTool(inputs) => {
+                format!("Edit Symbol: {} in {}", inputs.symbol, inputs.file_path)
+            }
             SubTaskType::LSTool(inputs) => format!("List Directory: {}", inputs.file_path),
             SubTaskType::GlobTool(inputs) => format!("Glob Search: {}", inputs.pattern),
             SubTaskType::GrepTool(inputs) => format!("Grep Search: {}", inputs.pattern),
-            SubTaskType::ExplainTool(query) => format!("Explain: {}", query),
+            SubTaskType::ExplainTool(inputs) => format!("Explain: {}", inputs.query),
+            SubTaskType::RememberTool(fact) => format!("Remember: {}", fact),
+            SubTaskType::MemoryGrepTool(inputs) => format!("Search Memory: {}", inputs.pattern),
+            SubTaskType::SemanticSearchTool(inputs) => format!("Semantic Search: {}", inputs.query),
+            SubTaskType::HybridSearchTool(inputs) => format!("Hybrid Search: {}", inputs.query),
+            SubTaskType::CrateSourceTool(inputs) => match (&inputs.pattern, &inputs.file_path) {
+                (Some(pattern), _) => format!("Search {}: {}", inputs.crate_name, pattern),
+                (None, Some(file_path)) => format!("Read {}: {}", inputs.crate_name, file_path),
+                (None, None) => format!("List {} Source", inputs.crate_name),
+            },
+            SubTaskType::CrateDocsTool(inputs) => format!("Crate Docs: {}", inputs.crate_name),
+            SubTaskType::ErrorLookupTool(inputs) => format!("Look Up Error: {}", inputs.error),
+            SubTaskType::CoverageTool(_) => "Measure Test Coverage".to_string(),
+            SubTaskType::TmuxPaneTool(_) => "Read Watched Pane".to_string(),
             SubTaskType::ProtoSubtask(query) => format!("Proto: {:?}", query),
         }
     }
 
+    /// The tool name the model uses to call this subtask
+    pub fn tool_name(&self) -> &'static str {
+        match self {
+            SubTaskType::Task(_) => "TaskTool",
+            SubTaskType::Bash(_) => "BashTool",
+            SubTaskType::FileReadTool(_) => "FileReadTool",
+            SubTaskType::FileEditTool(_) => "FileEditTool",
+            SubTaskType::EditSymbolTool(_) => "EditSymbolTool",
+            SubTaskType::InsertTool(_) => "InsertTool",
+            SubTaskType::EditSetTool(_) => "EditSetTool",
+            SubTaskType::WriteFileTool(_) => "WriteFileTool",
+            SubTaskType::LSTool(_) => "LSTool",
+            SubTaskType::GlobTool(_) => "GlobTool",
+            SubTaskType::GrepTool(_) => "GrepTool",
+            SubTaskType::ExplainTool(_) => "ExplainTool",
+            SubTaskType::RememberTool(_) => "RememberTool",
+            SubTaskType::MemoryGrepTool(_) => "MemoryGrepTool",
+            SubTaskType::SemanticSearchTool(_) => "SemanticSearchTool",
+            SubTaskType::HybridSearchTool(_) => "HybridSearchTool",
+            SubTaskType::CrateSourceTool(_) => "CrateSourceTool",
+            SubTaskType::CrateDocsTool(_) => "CrateDocsTool",
+            SubTaskType::ErrorLookupTool(_) => "ErrorLookupTool",
+            SubTaskType::CoverageTool(_) => "CoverageTool",
+            SubTaskType::TmuxPaneTool(_) => "TmuxPaneTool",
+            SubTaskType::ProtoSubtask(_) => "ProtoSubtask",
+        }
+    }
+
     pub fn icon(&self) -> &'static str {
         match self {
             SubTaskType::Task(_) => "🧠",
             SubTaskType::Bash(_) => "🖥️",
             SubTaskType::FileReadTool(_) => "📄",
             SubTaskType::FileEditTool(_) => "✏️",
+            SubTaskType::EditSymbolTool(_) => "🧩",
+            SubTaskType::InsertTool(_) => "➕",
+            SubTaskType::EditSetTool(_) => "🗂️",
+            SubTaskType::WriteFileTool(_) => "📝",
             SubTaskType::LSTool(_) => "📁",
             SubTaskType::GlobTool(_) => "🔍",
             SubTaskType::GrepTool(_) => "🔎",
             SubTaskType::ExplainTool(_) => "💡",
+            SubTaskType::RememberTool(_) => "📌",
+            SubTaskType::MemoryGrepTool(_) => "🧾",
+            SubTaskType::SemanticSearchTool(_) => "🧭",
+            SubTaskType::HybridSearchTool(_) => "🔦",
+            SubTaskType::CrateSourceTool(_) => "📦",
+            SubTaskType::CrateDocsTool(_) => "📚",
+            SubTaskType::ErrorLookupTool(_) => "🩺",
+            SubTaskType::CoverageTool(_) => "📊",
+            SubTaskType::TmuxPaneTool(_) => "👀",
             SubTaskType::ProtoSubtask(_) => "🔄",
         }
     }
 
-    pub fn requires_user_permission(&self) -> bool {
-        match self {
-            SubTaskType::Task( _ ) => true , 
-            SubTaskType::Bash(_) => true,
+    /// What the tool behind this subtask needs access to
+    pub fn capabilities(&self) -> &'static [Capability] {
+        self.get_tool().capabilities()
+    }
+
+    /// Whether this subtask changes the workspace or runs arbitrary commands
+    pub fn is_mutating(&self) -> bool {
+        self.capabilities().iter().any(Capability::is_mutating)
+    }
+
+    /// Planning and the tools that only look at the workspace, gathered context or outside
+    /// services, the only ones a read-only turn runs
+    pub fn is_read_only(&self) -> bool {
+        !self.capabilities().iter().any(Capability::is_write)
+    }
 
-            SubTaskType::FileEditTool(_) => true,
+    /// Read-only tools that need nothing earlier steps add to context memory, so a run of
+    /// them can execute at the same time
+    pub fn runs_in_parallel(&self) -> bool {
+        self.is_read_only()
+            && !matches!(
+                self,
+                SubTaskType::Task(_)
+                    | SubTaskType::ProtoSubtask(_)
+                    | SubTaskType::MemoryGrepTool(_)
+                    | SubTaskType::ExplainTool(_)
+                    | SubTaskType::TmuxPaneTool(_)
+            )
+    }
+
+    /// Whether this subtask takes over the terminal while it runs; session commands stream
+    /// their output and may ask what to do with a command waiting for input
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, SubTaskType::Bash(inputs) if inputs.interactive || shell_session::enabled())
+    }
+
+    /// Text describing exactly what a mutating subtask changes, used for journal hashes
+    pub fn change_text(&self) -> Option<String> {
+        match self {
+            SubTaskType::Bash(inputs) => Some(inputs.command.clone()),
+            SubTaskType::FileEditTool(inputs) => Some(format!(
+                "{}\n---\n{}\n+++\n{}",
+                inputs.file_path, inputs.old_string, inputs.new_string
+            )),
+            SubTaskType::EditSymbolTool(inputs) => Some(format!(
+                "{}\n{}\n{}",
+                inputs.file_path, inputs.symbol, inputs.new_body
+            )),
+            SubTaskType::InsertTool(inputs) => Some(format!(
+                "{}\n{:?}\n{}",
+                inputs.file_path, inputs.position, inputs.content
+            )),
+            SubTaskType::EditSetTool(inputs) => serde_json::to_string(&inputs.edits).ok(),
+            SubTaskType::WriteFileTool(inputs) => Some(format!(
+                "{}\n{}\n{}",
+                inputs.file_path, inputs.overwrite, inputs.content
+            )),
+            _ => None,
+        }
+    }
 
-            SubTaskType::ProtoSubtask(_) => true,
+    /// Files this subtask writes to, used to detect overlapping edits between sessions
+    pub fn touched_files(&self) -> Vec<String> {
+        match self {
+            SubTaskType::FileEditTool(inputs) => vec![inputs.file_path.clone()],
+            SubTaskType::EditSymbolTool(inputs) => vec![inputs.file_path.clone()],
+            SubTaskType::InsertTool(inputs) => vec![inputs.file_path.clone()],
+            SubTaskType::WriteFileTool(inputs) => vec![inputs.file_path.clone()],
+            SubTaskType::EditSetTool(inputs) => {
+                let mut files: Vec<String> =
+                    inputs.edits.iter().map(|e| e.file_path.clone()).collect();
+                files.sort();
+                files.dedup();
+                files
+            }
+            _ => Vec::new(),
+        }
+    }
 
-            _ => false,
+    /// Details to show alongside the approval prompt, such as a diff of pending edits or a
+    /// command's risk
+    pub fn preview(&self) -> Option<String> {
+        match self {
+            // Approval happens in the workspace, so the current directory is its root
+            SubTaskType::EditSetTool(inputs) => Some(
+                std::env::current_dir()
+                    .map_err(Into::into)
+                    .and_then(|root| inputs.to_edit_set(&root).review())
+                    .unwrap_or_else(|e| format!("⚠️ These edits cannot be applied: {}", e)),
+            ),
+            SubTaskType::WriteFileTool(inputs) => Some(
+                std::env::current_dir()
+                    .map_err(Into::into)
+                    .and_then(|root| inputs.to_edit_set(&root)?.review())
+                    .unwrap_or_else(|e| format!("⚠️ This file cannot be written: {}", e)),
+            ),
+            SubTaskType::Bash(inputs) => Some(
+                CommandPreview {
+                    risk: command_preview::assess(&inputs.command),
+                    explanation: None,
+                }
+                .render(),
+            ),
+            _ => None,
         }
     }
+
+    /// Anything beyond reading files needs confirmation under the default policy
+    pub fn requires_user_permission(&self) -> bool {
+        self.capabilities()
+            .iter()
+            .any(Capability::requires_permission)
+    }
 }
 
 /// Represents a subtask to be executed
@@ -551,11 +662,17 @@ impl SubTask {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ProtoSubtaskInner {
+    /// The tool expected to carry out this step
     pub tool_name: String,
+    /// Description of what needs to be done
     pub description: String,
+    /// Priority level (high, medium, low)
     pub priority: Option<String>,
+    /// Sampling preset for the model call that carries out this step
+    #[serde(default)]
+    pub generation_profile: Option<GenerationProfile>,
 }
 
 impl ProtoSubtaskInner {
@@ -566,9 +683,10 @@ impl ProtoSubtaskInner {
             match serde_json::from_str::<serde_json::Value>(args_str) {
                 Ok(parsed) => parsed,
                 Err(e) => {
-                    println!(
+                    say!(
                         "WARN: could not parse string arguments: {:?}, error: {}",
-                        args_str, e
+                        args_str,
+                        e
                     );
                     return Vec::new();
                 }
@@ -614,10 +732,16 @@ impl ProtoSubtaskInner {
                 .and_then(|p| p.as_str())
                 .map(|s| s.to_string());
 
+            // Unknown profiles fall back to the provider's defaults
+            let generation_profile = subtask
+                .get("generation_profile")
+                .and_then(|p| serde_json::from_value(p.clone()).ok());
+
             result.push(ProtoSubtaskInner {
                 tool_name,
                 description,
                 priority,
+                generation_profile,
             });
         }
 
@@ -625,18 +749,32 @@ impl ProtoSubtaskInner {
     }
 }
 
+/// TaskTool arguments as the model sends them
+#[derive(Deserialize, JsonSchema)]
+pub struct TaskToolInputs {
+    /// The task description or query for the AI to process
+    pub description: String,
+}
+
 pub struct TaskTool(String); // agent tool
 
 #[async_trait]
 impl SubtaskTool for TaskTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Network]
+    }
+
     async fn handle_subtask(
         &self,
-        ai_client: &Box<dyn AiClient>,
-        _context_memory: Arc<Mutex<ContextMemory>>,
+        ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
         let input = &self.0;
 
-        let system_prompt = r#"
+        let system_prompt = experiments::prompt(
+            "TaskTool",
+            r#"
 You are an expert AI assistant for a command-line tool that helps with software development tasks of a local codebase.
 Your job is to analyze user requests and determine what operations the command-line tool should  perform.
 
@@ -654,13 +792,68 @@ For example, if asked to "explain the logging system in this codebase", you shou
 3. Use FileReadTool to examine the most relevant files in detail
 4. Finally use ExplainTool to provide a comprehensive explanation based on all gathered information
 
+To rewrite a whole function, method or type, prefer EditSymbolTool over FileEditTool.
+To add imports, new functions or config entries, prefer InsertTool over FileEditTool.
+To create a new file or rewrite one entirely, use WriteFileTool.
+When a change spans several files (renames, refactors), use a single EditSetTool so the edits land together.
+
+To look something up in text already gathered this session (a file read earlier, previous search results),
+use MemoryGrepTool instead of reading the file again.
+
+To find code by what it does when you do not know the names to grep for, use SemanticSearchTool. Its results
+cite a file and line range; read around one with FileReadTool's offset and limit rather than the whole file.
+When a query might or might not use the code's own names, HybridSearchTool runs both searches at once and
+returns one list ranked by both.
+
+If you learn a durable fact about the project that would help in future sessions (how to build or test it,
+where a subsystem lives), use RememberTool to record it.
+
 
 Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer based on all gathered information.
-"#;
+"#,
+        );
 
         // Use the get_all_tool_schemas method to get function schemas
         let functions = SubTaskType::get_all_tool_schemas();
 
+        // Pinned fragments carry project knowledge and user preferences into planning
+        let pinned_context = {
+            let memory = context_memory.lock().await;
+            format_memory_fragments(memory.get_pinned_fragments())
+        };
+
+        // Ambiguous requests get a few clarifying questions, one per run, before any planning
+        let recorded = Clarification::recorded(&*context_memory.lock().await, input);
+        let clarification = match recorded {
+            Some(clarification) => clarification,
+            None => {
+                let clarification =
+                    match clarification::clarify(ai_client, input, &pinned_context).await {
+                        Ok(clarification) => clarification,
+                        Err(e) => {
+                            say!("WARN: Failed to check the request for ambiguity: {:?}", e);
+                            Clarification::default()
+                        }
+                    };
+                clarification.record(&mut *context_memory.lock().await, input);
+                clarification
+            }
+        };
+        let (question, answers) = {
+            let memory = context_memory.lock().await;
+            (
+                clarification.next_unanswered(&memory).cloned(),
+                clarification.answers(&memory),
+            )
+        };
+        if let Some(question) = question {
+            return Some(SubtaskOutput::AskUser(question.question, question.options));
+        }
+        let request = match answers {
+            Some(answers) => format!("{}\n\nCLARIFICATIONS FROM THE USER:\n{}", input, answers),
+            None => input.to_string(),
+        };
+
         // Create messages for the AI
         let messages = vec![
             Message {
@@ -668,9 +861,14 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
                 content: system_prompt.to_string(),
                 name: None,
             },
+            Message {
+                role: MessageRole::System,
+                content: format!("PINNED CONTEXT:\n\n{}", pinned_context),
+                name: None,
+            },
             Message {
                 role: MessageRole::User,
-                content: input.to_string(),
+                content: request,
                 name: None,
             },
         ];
@@ -682,14 +880,12 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
         {
             Ok(resp) => resp,
             Err(e) => {
-                println!("WARN: Failed to get chat completion: {:?}", e);
+                say!("WARN: Failed to get chat completion: {:?}", e);
                 return None;
             }
         };
 
-        let Some(first_content) = message_only_response.content else {
-            return None;
-        };
+        let first_content = message_only_response.content?;
 
         let _ = cliclack::log::info(format!(" My plan : {}", first_content));
 
@@ -726,7 +922,7 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
                            "tool_name": {
                              "type": "string",
                              "description": "name of the subtask",
-                             "enum": [ "ExplainTool","TaskTool","BashTool","FileReadTool","FileEditTool","LSTool","GlobTool","GrepTool" ]
+                             "enum": [ "ExplainTool","TaskTool","BashTool","FileReadTool","FileEditTool","EditSymbolTool","InsertTool","EditSetTool","WriteFileTool","LSTool","GlobTool","GrepTool","MemoryGrepTool","SemanticSearchTool","HybridSearchTool","CrateSourceTool","CrateDocsTool","ErrorLookupTool","CoverageTool","TmuxPaneTool","RememberTool" ]
 
                            },
 
@@ -738,6 +934,11 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
                              "type": "string",
                              "enum": ["high", "medium", "low"],
                              "description": "Priority level of the subtask"
+                           },
+                           "generation_profile": {
+                             "type": "string",
+                             "enum": ["deterministic", "balanced", "creative"],
+                             "description": "How the model should generate this step: deterministic for code edits, commands and exact arguments, balanced for explanations, creative for brainstorming alternatives. Omit for the default"
                            }
                          },
                          "required": ["tool_name","description"]
@@ -752,7 +953,9 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
 
         );
 
-        println!("secondary input messages {:?}", secondary_input_messages);
+        if output::is_verbose() {
+            say!("secondary input messages {:?}", secondary_input_messages);
+        }
 
         let secondary_response = match ai_client
             .chat_completion_with_functions(
@@ -764,7 +967,7 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
         {
             Ok(resp) => resp,
             Err(e) => {
-                println!("WARN: Failed to get chat completion: {:?}", e);
+                say!("WARN: Failed to get chat completion: {:?}", e);
                 return None;
             }
         };
@@ -776,12 +979,12 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
 
         // Process function calls
         let Some(tool_calls) = secondary_response.tool_calls else {
-            println!("WARN: No tool calls chosen by AI");
+            say!("WARN: No tool calls chosen by AI");
             return None;
         };
 
         if tool_calls.is_empty() {
-            println!("WARN: Empty tool calls list");
+            say!("WARN: Empty tool calls list");
             return None;
         }
 
@@ -790,8 +993,8 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
             let subtask_inners =
                 ProtoSubtaskInner::parse_from_raw(tool_call.function.arguments.clone());
 
-            if subtask_inners.len() == 0 {
-                println!("WARN: could not parse {:?}", tool_call.function.arguments);
+            if subtask_inners.is_empty() {
+                say!("WARN: could not parse {:?}", tool_call.function.arguments);
             }
             proto_subtasks.extend(subtask_inners);
         }
@@ -809,7 +1012,9 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
         // Convert tool calls to subtasks
         /* let mut built_sub_tasks = Vec::new();
         for tool_call in &tool_calls {
-            println!("Processing tool call: {:?}", tool_call);
+            if output::is_verbose() {
+                say!("Processing tool call: {:?}", tool_call);
+            }
 
             if let Some(sub_task_type) = SubTaskType::from_tool_call(tool_call.clone()) {
                 built_sub_tasks.push(sub_task_type);
@@ -818,33 +1023,52 @@ Remember to ALWAYS conclude with ExplainTool to provide a comprehensive answer b
 
         // Check if we have multiple subtasks and need to process them in sequence
         if built_sub_tasks.len() > 1 {
-            println!("✅ Received multiple tool calls: {} tools", built_sub_tasks.len());
+            if output::is_verbose() {
+                say!("✅ Received multiple tool calls: {} tools", built_sub_tasks.len());
+            }
             // Push subtasks to the queue with depth increment for proper execution flow
             Some(SubtaskOutput::PushSubtasks(built_sub_tasks))
         } else if built_sub_tasks.len() == 1 {
             // Just a single subtask - use the simpler form
             Some(SubtaskOutput::PushSubtasks(built_sub_tasks))
         } else {
-            println!("WARN: No valid subtasks created from tool calls");
+            say!("WARN: No valid subtasks created from tool calls");
             None
         }
         */
     }
 }
 
+/// The client to use for a step, sampling with its requested profile if it has one
+fn with_profile(ai_client: &dyn AiClient, profile: Option<GenerationProfile>) -> Box<dyn AiClient> {
+    match profile {
+        Some(profile) => ai_client.with_profile(profile),
+        None => ai_client.clone_box(),
+    }
+}
+
 //use ai and context to convert this to a 'hard type' subtask
 
 pub struct ProtoSubtask(ProtoSubtaskInner); //query
 
 #[async_trait]
 impl SubtaskTool for ProtoSubtask {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Network]
+    }
+
     async fn handle_subtask(
         &self,
-        ai_client: &Box<dyn AiClient>,
+        ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
         let input = &self.0;
 
+        // The planner can ask for deterministic edits or creative brainstorming per step
+        let ai_client = with_profile(ai_client, input.generation_profile);
+        let ai_client = ai_client.as_ref();
+
         // Check if the description explicitly mentions a tool to use
         let desc_lower = input.description.to_lowercase();
 
@@ -856,14 +1080,21 @@ impl SubtaskTool for ProtoSubtask {
             || desc_lower.contains("with explaintool")
             || desc_lower.contains("clear memory")
         {
-            println!("Explicit tool request detected: ExplainTool");
+            if output::is_verbose() {
+                say!("Explicit tool request detected: ExplainTool");
+            }
             return Some(SubtaskOutput::PushSubtasks(vec![SubTaskType::ExplainTool(
-                input.description.clone(),
+                ExplainToolInputs {
+                    query: input.description.clone(),
+                    generation_profile: input.generation_profile,
+                },
             )]));
         }
 
         // Create a system prompt that instructs the AI on how to use the context effectively
-        let system_prompt = r#"
+        let system_prompt = experiments::prompt(
+            "ProtoSubtask",
+            r#"
 You are an expert AI assistant for a command-line tool that helps with software development tasks.
 Your task is to use the provided context along with user instructions to determine the appropriate tool to use.
 
@@ -874,83 +1105,18 @@ Follow these guidelines:
 
 If the context contains relevant file paths, code patterns, or specific information,
 use those details to populate the tool parameters precisely.
-"#;
+"#,
+        );
 
         // Extract and filter memory fragments based on relevance to the current subtask
-        let filtered_memory_context = {
-            let memory = context_memory.lock().await;
-            let all_fragments = memory.get_fragments();
-            
-            // If we have too many fragments, select the most relevant ones
-            let mut filtered_fragments = Vec::new();
-            
-            // Extract key terms from the subtask description
-            let desc_lowercase = input.description.to_lowercase();
-            let desc_terms: Vec<&str> = desc_lowercase
-                .split_whitespace()
-                .collect();
-            
-            // Score each fragment based on relevance to the task description
-            for fragment in all_fragments {
-                let content_lower = fragment.content.to_lowercase();
-                
-                // Calculate a simple relevance score based on matching terms
-                let mut relevance_score = 0;
-                for term in &desc_terms {
-                    if content_lower.contains(term) {
-                        relevance_score += 1;
-                    }
-                }
-                
-                // Also check metadata for relevance
-                if let Some(meta) = &fragment.metadata {
-                    // Check path
-                    if let Some(path) = &meta.path {
-                        let path_lower = path.to_lowercase();
-                        for term in &desc_terms {
-                            if path_lower.contains(term) {
-                                relevance_score += 2; // Paths are more important
-                            }
-                        }
-                    }
-                    
-                    // Check tags
-                    for tag in &meta.tags {
-                        let tag_lower = tag.to_lowercase();
-                        for term in &desc_terms {
-                            if tag_lower.contains(term) {
-                                relevance_score += 2; // Tags are also important
-                            }
-                        }
-                    }
-                }
-                
-                // Include fragments with any relevance or select tools results
-                if relevance_score > 0 || 
-                   fragment.source.contains("search") || 
-                   fragment.source.contains("file_read") ||
-                   fragment.source.contains("ls_tool") {
-                    filtered_fragments.push((fragment.clone(), relevance_score));
-                }
-            }
-            
-            // Sort by relevance score (highest first)
-            filtered_fragments.sort_by(|a, b| b.1.cmp(&a.1));
-            
-            // Limit to most relevant fragments (max 5)
-            let max_fragments = 5.min(filtered_fragments.len());
-            let selected_fragments: Vec<_> = filtered_fragments
-                .into_iter()
-                .take(max_fragments)
-                .map(|(frag, _)| frag)
-                .collect();
-            
-            // Format the selected fragments for the AI
-            format_memory_fragments(&selected_fragments)
-        };
-        
+        let filtered_memory_context = format_memory_fragments(
+            &memory::semantic_fragments(ai_client, &context_memory, &input.description, 5).await,
+        );
+
         // Log how many memory fragments we're using
-        println!("Using filtered memory context for ProtoSubtask");
+        if output::is_verbose() {
+            say!("Using filtered memory context for ProtoSubtask");
+        }
 
         // Get function schemas using our static method
         let functions = SubTaskType::get_tool_schema_for_tool(subtask_name.clone());
@@ -981,52 +1147,112 @@ use those details to populate the tool parameters precisely.
         {
             Ok(resp) => resp,
             Err(e) => {
-                println!("WARN: Failed to get chat completion: {:?}", e);
+                say!("WARN: Failed to get chat completion: {:?}", e);
                 return None;
             }
         };
 
         let Some(tool_calls) = best_function_response.tool_calls else {
-            println!("WARN: No tool calls chosen by AI");
+            say!("WARN: No tool calls chosen by AI");
             return None;
         };
 
         if tool_calls.is_empty() {
-            println!("WARN: Empty tool calls list");
+            say!("WARN: Empty tool calls list");
             return None;
         }
 
-        let mut built_sub_tasks = Vec::new();
+        let mut choices = Vec::new();
 
         for tool_call in &tool_calls {
-            println!("Processing tool call: {:?}", tool_call);
+            if output::is_verbose() {
+                say!("Processing tool call: {:?}", tool_call);
+            }
 
-            if let Some(sub_task_type) = SubTaskType::from_tool_call(tool_call.clone()) {
-                built_sub_tasks.push(sub_task_type);
+            if let Some(mut sub_task_type) = SubTaskType::from_tool_call(tool_call.clone()) {
+                // Explanations are written by a later call, which keeps this step's profile
+                if let SubTaskType::ExplainTool(inputs) = &mut sub_task_type {
+                    inputs.generation_profile =
+                        inputs.generation_profile.or(input.generation_profile);
+                }
+                let parameters = match &tool_call.function.arguments {
+                    serde_json::Value::String(raw) => raw.clone(),
+                    arguments => arguments.to_string(),
+                };
+                choices.push(SubtaskChoice {
+                    subtask: sub_task_type,
+                    parameters: utils::truncate_string(&parameters, 120),
+                });
             }
         }
 
-        Some(SubtaskOutput::PushSubtasks(built_sub_tasks))
+        // One step resolving to several calls is ambiguous, so the user picks
+        if choices.len() > 1 {
+            return Some(SubtaskOutput::ChooseSubtask(
+                format!(
+                    "Several ways to \"{}\" - which should run?",
+                    input.description
+                ),
+                choices,
+            ));
+        }
+
+        Some(SubtaskOutput::PushSubtasks(
+            choices.into_iter().map(|choice| choice.subtask).collect(),
+        ))
     }
 }
 
-pub struct BashTool(String); //query
+/// BashTool arguments as the model sends them
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct BashToolInputs {
+    /// The bash command to execute
+    pub command: String,
+    /// Run on a terminal the user can type into, for commands that need a TTY (password
+    /// prompts, `git rebase -i`, watch modes)
+    #[serde(default)]
+    pub interactive: bool,
+}
+
+pub struct BashTool(BashToolInputs);
 
 #[async_trait]
 impl SubtaskTool for BashTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Exec]
+    }
+
     async fn handle_subtask(
         &self,
-        _ai_client: &Box<dyn AiClient>,
-        _context_memory: Arc<Mutex<ContextMemory>>,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
-        let command = &self.0;
+        let command = &self.0.command;
 
         // Execute Bash command
-        let _ = cliclack::log::info(format!("🔧 Executing command: {}", command));
+        let _ = cliclack::log::info(format!("🔧 Executing command: {}", tool_env::mask(command)));
+
+        // Interactive commands get a terminal of their own, outside the shell session
+        if self.0.interactive {
+            #[cfg(unix)]
+            return run_interactive(command, context_memory, workspace_root).await;
+            #[cfg(not(unix))]
+            let _ = cliclack::log::warning(
+                "Interactive commands need a Unix terminal; running without one",
+            );
+        }
 
-        // Use tokio::process::Command to execute the command
-        use tokio::process::Command;
-        let output = match Command::new("sh").arg("-c").arg(command).output().await {
+        if shell_session::enabled() {
+            return run_in_session(command, context_memory, workspace_root).await;
+        }
+
+        // Run the command in the platform's shell, from the workspace root
+        let output = match platform::shell_command(command)
+            .current_dir(workspace_root)
+            .output()
+            .await
+        {
             Ok(out) => out,
             Err(e) => {
                 let _ = cliclack::log::info(format!("Failed to execute command: {}", e));
@@ -1034,19 +1260,14 @@ impl SubtaskTool for BashTool {
             }
         };
 
-        // Process the output
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        // Process the output, hiding any injected secrets it echoes
+        let stdout = tool_env::mask(&String::from_utf8_lossy(&output.stdout));
+        let stderr = tool_env::mask(&String::from_utf8_lossy(&output.stderr));
 
         // Print the results in a nice format
         if !stdout.is_empty() {
-            println!();
-            let _ = cliclack::log::info(format!("📄 Command output:"));
-
-            // Print the output with some formatting
-            let width = 80;
-            let separator = "─".repeat(width);
-            println!("┌{}┐", separator);
+            say!();
+            let _ = cliclack::log::info("📄 Command output:".to_string());
 
             // Split and limit output lines if too long
             let max_lines = 20;
@@ -1059,16 +1280,27 @@ impl SubtaskTool for BashTool {
                 lines
             };
 
-            for line in display_lines {
-                println!("│ {:<width$} │", line, width = width - 2);
+            if output::is_plain() {
+                // A labelled end instead of a frame, which screen readers read out per character
+                for line in display_lines {
+                    println!("{}", line);
+                }
+                println!("End of command output");
+            } else {
+                // Print the output with some formatting
+                let width = 80;
+                let separator = "─".repeat(width);
+                println!("┌{}┐", separator);
+                for line in display_lines {
+                    println!("│ {:<width$} │", line, width = width - 2);
+                }
+                println!("└{}┘", separator);
             }
-
-            println!("└{}┘", separator);
         }
 
         if !stderr.is_empty() {
-            println!();
-            let _ = cliclack::log::info(format!("⚠️ Command errors:"));
+            say!();
+            let _ = cliclack::log::info("⚠️ Command errors:".to_string());
             println!("{}", stderr);
         }
 
@@ -1076,19 +1308,189 @@ impl SubtaskTool for BashTool {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Output of a session command is fed to memory in chunks of about this many characters
+const SESSION_CHUNK_CHARS: usize = 8_000;
+
+/// Run `command` in the persistent shell session, showing and remembering its output as it
+/// arrives, so long-running commands are not lost if the run is interrupted
+async fn run_in_session(
+    command: &str,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    workspace_root: &Path,
+) -> Option<SubtaskOutput> {
+    let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let run = shell_session::run(command, workspace_root, sender);
+    let feed = async {
+        let mut chunk = String::new();
+        while let Some(event) = events.recv().await {
+            let line = match event {
+                SessionEvent::Line(line) => line,
+                SessionEvent::AwaitingInput { prompt, reply } => {
+                    let _ = reply.send(ask_about_waiting_command(&prompt));
+                    continue;
+                }
+            };
+            if output::is_plain() {
+                println!("{}", line);
+            } else {
+                println!("│ {}", line);
+            }
+            chunk.push_str(&line);
+            chunk.push('\n');
+            if chunk.len() >= SESSION_CHUNK_CHARS {
+                let fragment = command_fragment(
+                    "bash_session",
+                    command,
+                    format!("Output so far:\n{}", chunk),
+                );
+                context_memory.lock().await.add_frag(fragment);
+                chunk.clear();
+            }
+        }
+        chunk
+    };
+    let (result, rest) = tokio::join!(run, feed);
+
+    let output = match result {
+        Ok(output) => output,
+        Err(e) => {
+            let _ = cliclack::log::info(format!("Failed to execute command: {}", e));
+            return None;
+        }
+    };
+    let interruption = match output.interruption {
+        Some(Interruption::TimedOut) => " (timed out and interrupted)",
+        Some(Interruption::Killed) => " (killed while waiting for input)",
+        Some(Interruption::Backgrounded) => " (moved to the background, still running)",
+        None => "",
+    };
+    if !interruption.is_empty() {
+        let _ = cliclack::log::warning(format!("Command ended early{}", interruption));
+    }
+
+    let fragment = command_fragment(
+        "bash_session",
+        command,
+        format!(
+            "Exit code: {}{}\nWorking directory: {}\nOutput:\n{}",
+            output.exit_code,
+            interruption,
+            platform::display_path(&output.cwd),
+            rest.trim_end()
+        ),
+    );
+    context_memory.lock().await.add_frag(fragment.clone());
+    Some(SubtaskOutput::AddToContextMemory(fragment))
+}
+
+/// Ask what to do with a session command that seems to wait for input after `prompt`
+fn ask_about_waiting_command(prompt: &str) -> InputChoice {
+    let choice = cliclack::select(format!(
+        "The command seems to be waiting for input: {}",
+        prompt
+    ))
+    .item("send", "Send input", "type a line into it")
+    .item("wait", "Keep waiting", "ask again if it prints more")
+    .item(
+        "background",
+        "Move it to the background",
+        "the plan goes on while it runs",
+    )
+    .item("kill", "Kill it", "interrupt it with Ctrl+C")
+    .interact()
+    .unwrap_or("kill");
+
+    match choice {
+        "send" => {
+            let prompt = prompt.to_lowercase();
+            let input = if prompt.contains("password") || prompt.contains("passphrase") {
+                cliclack::password("Input (hidden)").interact()
+            } else {
+                cliclack::input("Input").required(false).interact()
+            };
+            input.map_or(InputChoice::Kill, InputChoice::Send)
+        }
+        "wait" => InputChoice::Wait,
+        "background" => InputChoice::Background,
+        _ => InputChoice::Kill,
+    }
+}
+
+/// Run `command` on a terminal the user can type into, then remember how it ended
+#[cfg(unix)]
+async fn run_interactive(
+    command: &str,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    workspace_root: &Path,
+) -> Option<SubtaskOutput> {
+    let mut process = platform::shell_command(command);
+    process.current_dir(workspace_root);
+
+    let _ = cliclack::log::info("⌨️ Interactive command; keystrokes go to it until it exits");
+    let run = match crate::pty::run_interactive(process).await {
+        Ok(run) => run,
+        Err(e) => {
+            let _ = cliclack::log::info(format!("Failed to execute command: {}", e));
+            return None;
+        }
+    };
+    say!();
+
+    let exit_code = run.exit_code.map_or_else(
+        || "none (killed by a signal)".to_string(),
+        |code| code.to_string(),
+    );
+    let fragment = command_fragment(
+        "bash_interactive",
+        command,
+        format!(
+            "Exit code: {}\nEnd of the terminal session:\n{}",
+            exit_code,
+            run.transcript.trim_end()
+        ),
+    );
+    context_memory.lock().await.add_frag(fragment.clone());
+    Some(SubtaskOutput::AddToContextMemory(fragment))
+}
+
+fn command_fragment(source: &str, command: &str, content: String) -> crate::memory::MemoryFragment {
+    use crate::memory::{MemoryFragment, MemoryMetadata};
+    use chrono::Utc;
+
+    MemoryFragment {
+        source: source.to_string(),
+        provenance: Provenance::ToolOutput,
+        content: format!("$ {}\n{}", tool_env::mask(command), content),
+        metadata: Some(MemoryMetadata {
+            file_type: Some("command_output".to_string()),
+            path: None,
+            timestamp: Some(Utc::now().timestamp()),
+            tags: vec!["bash".to_string(), "command_output".to_string()],
+        }),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct LSToolInputs {
+    /// The absolute path to the directory to list
+    #[serde(rename = "path")]
     pub file_path: String,
+    /// Glob patterns to ignore (e.g. ["*.tmp", "node_modules"])
     pub ignore: Option<Vec<String>>,
 }
 pub struct LSTool(LSToolInputs);
 
 #[async_trait]
 impl SubtaskTool for LSTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs]
+    }
+
     async fn handle_subtask(
         &self,
-        _ai_client: &Box<dyn AiClient>,
+        _ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
         use crate::memory::{MemoryFragment, MemoryMetadata};
         use chrono::Utc;
@@ -1101,17 +1503,26 @@ impl SubtaskTool for LSTool {
         let ignore_patterns = self.0.ignore.clone().unwrap_or_default();
 
         // Log the operation
-        println!("📁 Listing directory: {}", dir_path);
+        say!("📁 Listing directory: {}", dir_path);
+
+        // Never follow symlinks out of the workspace or touch special files
+        let path = match fs_guard::resolve(workspace_root, Path::new(dir_path), PathAccess::List) {
+            Ok(path) => path,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                return None;
+            }
+        };
+        let path = path.as_path();
 
         // Check if path exists and is a directory
-        let path = Path::new(dir_path);
         if !path.exists() {
-            println!("⚠️ Error: Directory does not exist: {}", dir_path);
+            say!("⚠️ Error: Directory does not exist: {}", dir_path);
             return None;
         }
 
         if !path.is_dir() {
-            println!("⚠️ Error: Path is not a directory: {}", dir_path);
+            say!("⚠️ Error: Path is not a directory: {}", dir_path);
             return None;
         }
 
@@ -1119,7 +1530,7 @@ impl SubtaskTool for LSTool {
         let entries = match fs::read_dir(path) {
             Ok(entries) => entries,
             Err(e) => {
-                println!("⚠️ Error reading directory: {}", e);
+                say!("⚠️ Error reading directory: {}", e);
                 return None;
             }
         };
@@ -1161,13 +1572,29 @@ impl SubtaskTool for LSTool {
                     };
 
                     let is_dir = metadata.is_dir();
-                    let path_str = entry_path.to_string_lossy().to_string();
-
-                    // Format entry with type indicator for display
-                    let type_indicator = if is_dir { "📁" } else { "📄" };
+                    let path_str = workspaces::display(&entry_path);
+
+                    // Symlinks and special files are listed as such, never as regular files
+                    let special = fs_guard::special_kind(&metadata);
+                    let type_indicator = if is_dir {
+                        "📁"
+                    } else if metadata.is_symlink() {
+                        "🔗"
+                    } else if special.is_some() {
+                        "⚙️"
+                    } else {
+                        "📄"
+                    };
 
                     // Format size for files
-                    let size_str = if !is_dir {
+                    let size_str = if let Some(kind) = special {
+                        format!(" ({})", kind)
+                    } else if metadata.is_symlink() {
+                        match fs::read_link(&entry_path) {
+                            Ok(target) => format!(" -> {}", target.display()),
+                            Err(_) => " -> ?".to_string(),
+                        }
+                    } else if !is_dir {
                         let size = metadata.len();
                         if size < 1024 {
                             format!(" ({} bytes)", size)
@@ -1201,7 +1628,7 @@ impl SubtaskTool for LSTool {
                     ));
                 }
                 Err(e) => {
-                    println!("⚠️ Error reading entry: {}", e);
+                    say!("⚠️ Error reading entry: {}", e);
                 }
             }
         }
@@ -1227,7 +1654,7 @@ impl SubtaskTool for LSTool {
         };
 
         // Log the results
-        println!("{}", output);
+        say!("{}", output);
 
         // Create a memory fragment from the results
         let memory_fragment = if !detailed_results.is_empty() {
@@ -1249,7 +1676,7 @@ impl SubtaskTool for LSTool {
                         .to_string_lossy();
                     mem_content.push_str(&format!("- {}\n", name));
                 }
-                mem_content.push_str("\n");
+                mem_content.push('\n');
             }
 
             // Add file entries
@@ -1279,6 +1706,7 @@ impl SubtaskTool for LSTool {
 
             MemoryFragment {
                 source: "ls_tool".to_string(),
+                provenance: Provenance::FileRead,
                 content: mem_content,
                 metadata: Some(memory_metadata),
             }
@@ -1286,6 +1714,7 @@ impl SubtaskTool for LSTool {
             // Create a memory fragment for empty directory
             MemoryFragment {
                 source: "ls_tool".to_string(),
+                provenance: Provenance::FileRead,
                 content: format!("Directory is empty: {}", dir_path),
                 metadata: Some(MemoryMetadata {
                     file_type: Some("directory_listing".to_string()),
@@ -1311,10 +1740,21 @@ impl SubtaskTool for LSTool {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct GlobToolInputs {
+    /// The glob pattern to match (e.g., '**/*.rs', 'src/**/*.json', '**/*.{js,ts}')
     pub pattern: String,
+    /// The directory to search in
     pub path: Option<String>,
+    /// Stop after this many matches (default 5000)
+    #[serde(default)]
+    pub max_matches: Option<usize>,
+    /// Results per page (default 100)
+    #[serde(default)]
+    pub max_results: Option<usize>,
+    /// Page of results to return, starting at 1. Only request later pages when the first was not enough
+    #[serde(default)]
+    pub page: Option<usize>,
 }
 
 pub struct GlobTool {
@@ -1323,121 +1763,115 @@ pub struct GlobTool {
 
 #[async_trait]
 impl SubtaskTool for GlobTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs]
+    }
+
     async fn handle_subtask(
         &self,
-        _ai_client: &Box<dyn AiClient>,
+        _ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
+        use crate::glob_walk::{self, DEFAULT_MATCH_CAP};
         use crate::memory::{MemoryFragment, MemoryMetadata};
+        use crate::pagination;
         use chrono::Utc;
-        use glob::glob;
-        use std::fs;
-        use std::path::PathBuf;
 
         // Get the pattern and base path
         let pattern = &self.inputs.pattern;
         let base_path = match &self.inputs.path {
-            Some(path) => PathBuf::from(path),
-            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
-        };
-
-        // Construct the full pattern with the base path
-        let search_pattern = if base_path.to_string_lossy().ends_with('/') {
-            format!("{}{}", base_path.display(), pattern)
-        } else {
-            format!("{}/{}", base_path.display(), pattern)
+            Some(path) => workspaces::locate(workspace_root, Path::new(path)),
+            None => workspace_root.to_path_buf(),
         };
 
-        // Log the search
-        println!("🔍 Searching for files with pattern: {}", search_pattern);
+        if let Err(e) = fs_guard::resolve(workspace_root, &base_path, PathAccess::List) {
+            say!("⚠️ Error: {}", e);
+            return None;
+        }
 
-        // Collect results and detailed path information
-        let mut results = Vec::new();
-        let mut detailed_results = Vec::new();
+        let search_pattern = workspaces::display(&base_path.join(pattern));
 
-        // Use the glob crate to perform the pattern matching
-        match glob(&search_pattern) {
-            Ok(paths) => {
-                for entry in paths {
-                    match entry {
-                        Ok(path) => {
-                            // Get additional file metadata when possible
-                            let metadata = fs::metadata(&path).ok();
-                            let is_dir = metadata.as_ref().map_or(false, |m| m.is_dir());
-                            let size = metadata.as_ref().map_or(0, |m| m.len());
-                            let path_str = path.to_string_lossy().to_string();
-
-                            // Format path with type indicator for display
-                            let type_indicator = if is_dir { "📁" } else { "📄" };
-                            let size_str = if !is_dir {
-                                format!(" ({} bytes)", size)
-                            } else {
-                                String::new()
-                            };
-                            let formatted_path =
-                                format!("{} {}{}", type_indicator, path_str, size_str);
-
-                            results.push(formatted_path);
-
-                            // Add detailed information for memory
-                            detailed_results.push((
-                                path_str.clone(),
-                                if is_dir {
-                                    "directory".to_string()
-                                } else {
-                                    "file".to_string()
-                                },
-                                size,
-                            ));
-                        }
-                        Err(e) => {
-                            println!("⚠️ Error: {:?}", e);
-                        }
-                    }
-                }
-            }
+        // Log the search
+        say!("🔍 Searching for files with pattern: {}", search_pattern);
+
+        // Walk at least far enough to fill the requested page and know whether another exists
+        let max_matches =
+            self.inputs
+                .max_matches
+                .unwrap_or(DEFAULT_MATCH_CAP)
+                .max(pagination::entries_needed(
+                    self.inputs.max_results,
+                    self.inputs.page,
+                ));
+        let mut matches = match glob_walk::glob_paths(&base_path, pattern, max_matches) {
+            Ok(matches) => matches,
             Err(e) => {
-                println!("⚠️ Invalid glob pattern: {:?}", e);
+                say!("⚠️ {}", e);
                 return None;
             }
-        }
-
-        // Sort results (directories first, then files)
-        let mut sorted_indexes = (0..results.len()).collect::<Vec<_>>();
-        sorted_indexes.sort_by(|&a, &b| {
-            let a_is_dir = results[a].starts_with("📁");
-            let b_is_dir = results[b].starts_with("📁");
+        };
 
-            if a_is_dir && !b_is_dir {
-                std::cmp::Ordering::Less
-            } else if !a_is_dir && b_is_dir {
-                std::cmp::Ordering::Greater
-            } else {
-                results[a].cmp(&results[b])
-            }
+        // Matches reached through symlinks out of the workspace, or special files, are dropped
+        matches.entries.retain(|entry| {
+            fs_guard::resolve(workspace_root, &entry.path, PathAccess::Read).is_ok()
         });
 
-        // Reorder both results based on the sorted indexes
-        let sorted_results: Vec<String> =
-            sorted_indexes.iter().map(|&i| results[i].clone()).collect();
-        let sorted_detailed: Vec<(String, String, u64)> = sorted_indexes
+        // Already sorted directories first, then by path, so pages are stable
+        let page = pagination::paginate(
+            &matches.entries,
+            self.inputs.max_results,
+            self.inputs.page,
+            !matches.truncated,
+        );
+        let sorted_results: Vec<String> = page
+            .items
+            .iter()
+            .map(|entry| {
+                if entry.is_dir {
+                    format!("📁 {}", workspaces::display(&entry.path))
+                } else {
+                    format!(
+                        "📄 {} ({} bytes)",
+                        workspaces::display(&entry.path),
+                        entry.size
+                    )
+                }
+            })
+            .collect();
+        let sorted_detailed: Vec<(String, String, u64)> = page
+            .items
             .iter()
-            .map(|&i| detailed_results[i].clone())
+            .map(|entry| {
+                (
+                    workspaces::display(&entry.path),
+                    if entry.is_dir { "directory" } else { "file" }.to_string(),
+                    entry.size,
+                )
+            })
             .collect();
+        let truncation_note = page
+            .trailer
+            .as_ref()
+            .map(|trailer| format!("\n{}", trailer))
+            .unwrap_or_default();
 
         // Format the output for display
-        let output = if sorted_results.is_empty() {
+        let output = if matches.entries.is_empty() {
             "No files found matching the pattern.".to_string()
         } else {
             format!(
-                "Found {} file(s):\n{}",
+                "Found {}{} file(s), showing {}:\n{}{}",
+                matches.entries.len(),
+                if matches.truncated { "+" } else { "" },
                 sorted_results.len(),
-                sorted_results.join("\n")
+                sorted_results.join("\n"),
+                truncation_note
             )
         };
 
         // Log the results
-        println!("{}", output);
+        say!("{}", output);
 
         // Create a memory fragment from the results
         let memory_fragment = if !sorted_detailed.is_empty() {
@@ -1451,17 +1885,19 @@ impl SubtaskTool for GlobTool {
                     mem_content.push_str(&format!("- File: {} ({} bytes)\n", path, size));
                 }
             }
+            mem_content.push_str(&truncation_note);
 
             // Create memory metadata
             let memory_metadata = MemoryMetadata {
                 file_type: Some("search_results".to_string()),
-                path: Some(base_path.to_string_lossy().to_string()),
+                path: Some(workspaces::display(&base_path)),
                 timestamp: Some(Utc::now().timestamp()),
                 tags: vec!["glob_search".to_string(), format!("pattern:{}", pattern)],
             };
 
             MemoryFragment {
                 source: "glob_search".to_string(),
+                provenance: Provenance::FileRead,
                 content: mem_content,
                 metadata: Some(memory_metadata),
             }
@@ -1469,10 +1905,13 @@ impl SubtaskTool for GlobTool {
             // Create a memory fragment for empty results
             MemoryFragment {
                 source: "glob_search".to_string(),
-                content: format!("No files found matching glob pattern: {}", search_pattern),
+                provenance: Provenance::FileRead,
+                content: page.trailer.clone().unwrap_or_else(|| {
+                    format!("No files found matching glob pattern: {}", search_pattern)
+                }),
                 metadata: Some(MemoryMetadata {
                     file_type: Some("search_results".to_string()),
-                    path: Some(base_path.to_string_lossy().to_string()),
+                    path: Some(workspaces::display(&base_path)),
                     timestamp: Some(Utc::now().timestamp()),
                     tags: vec![
                         "glob_search".to_string(),
@@ -1494,66 +1933,124 @@ impl SubtaskTool for GlobTool {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct GrepToolInputs {
+    /// The regex pattern to search for in file contents (e.g., 'function\s+main', 'import\s+React')
     pub pattern: String,
+    /// File pattern to include (e.g., '*.rs', '*.{ts,tsx}')
     pub include: Option<String>,
+    /// The directory to search in
     pub path: Option<String>,
+    /// Ignore letter case when matching
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Search for the pattern as literal text instead of a regex. Prefer this for plain identifiers and strings
+    #[serde(default)]
+    pub fixed_string: bool,
+    /// Only match whole words
+    #[serde(default)]
+    pub word_boundary: bool,
+    /// Stop reading a file after this many matching lines
+    #[serde(default)]
+    pub max_matches_per_file: Option<usize>,
+    /// Matching lines per page (default 100)
+    #[serde(default)]
+    pub max_results: Option<usize>,
+    /// Page of matches to return, starting at 1. Only request later pages when the first was not enough
+    #[serde(default)]
+    pub page: Option<usize>,
+}
+
+impl GrepToolInputs {
+    /// Compile the pattern with the requested matching options
+    pub fn regex(&self) -> Result<regex::Regex, regex::Error> {
+        search_regex(
+            &self.pattern,
+            self.case_insensitive,
+            self.fixed_string,
+            self.word_boundary,
+        )
+    }
+}
+
+/// Build the regex used by the search tools from a pattern and matching options
+fn search_regex(
+    pattern: &str,
+    case_insensitive: bool,
+    fixed_string: bool,
+    word_boundary: bool,
+) -> Result<regex::Regex, regex::Error> {
+    let pattern = if fixed_string {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    let pattern = if word_boundary {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern
+    };
+
+    regex::RegexBuilder::new(&pattern)
+        .case_insensitive(case_insensitive)
+        .build()
 }
 
 pub struct GrepTool(GrepToolInputs);
 
 #[async_trait]
 impl SubtaskTool for GrepTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs]
+    }
+
     async fn handle_subtask(
         &self,
-        _ai_client: &Box<dyn AiClient>,
+        _ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
         use crate::memory::{MemoryFragment, MemoryMetadata};
+        use crate::pagination;
         use chrono::Utc;
         use glob::glob;
-        use regex::Regex;
         use std::fs::File;
         use std::io::{BufRead, BufReader};
-        use std::path::PathBuf;
 
         // Extract input parameters
         let pattern = &self.0.pattern;
         let include_pattern = self.0.include.as_deref();
         let base_path = match &self.0.path {
-            Some(path) => PathBuf::from(path),
-            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            Some(path) => workspaces::locate(workspace_root, Path::new(path)),
+            None => workspace_root.to_path_buf(),
         };
 
+        if let Err(e) = fs_guard::resolve(workspace_root, &base_path, PathAccess::List) {
+            say!("⚠️ Error: {}", e);
+            return None;
+        }
+
         // Compile regex pattern
-        let regex = match Regex::new(pattern) {
+        let regex = match self.0.regex() {
             Ok(re) => re,
             Err(e) => {
-                println!("⚠️ Invalid regex pattern: {:?}", e);
+                say!("⚠️ Invalid regex pattern: {:?}", e);
                 return None;
             }
         };
 
         // Log the search
-        println!("🔎 Searching for content matching: {}", pattern);
+        say!("🔎 Searching for content matching: {}", pattern);
         if let Some(include) = include_pattern {
-            println!("   In files matching: {}", include);
+            say!("   In files matching: {}", include);
         }
-        println!("   In directory: {}", base_path.display());
+        say!("   In directory: {}", base_path.display());
 
         // Generate the file pattern to search
-        let file_pattern = if let Some(include) = include_pattern {
-            if base_path.to_string_lossy().ends_with('/') {
-                format!("{}{}", base_path.display(), include)
-            } else {
-                format!("{}/{}", base_path.display(), include)
-            }
-        } else if base_path.to_string_lossy().ends_with('/') {
-            format!("{}**/*", base_path.display())
-        } else {
-            format!("{}/**/*", base_path.display())
-        };
+        let file_pattern = base_path
+            .join(include_pattern.unwrap_or("**/*"))
+            .to_string_lossy()
+            .to_string();
 
         // Collect search results
         let mut matches = Vec::new();
@@ -1566,8 +2063,11 @@ impl SubtaskTool for GrepTool {
                 for entry in paths {
                     match entry {
                         Ok(path) => {
-                            // Skip directories and non-regular files
-                            if path.is_dir() {
+                            // Skip directories, special files and symlinks out of the workspace
+                            if path.is_dir()
+                                || fs_guard::resolve(workspace_root, &path, PathAccess::Read)
+                                    .is_err()
+                            {
                                 continue;
                             }
 
@@ -1592,37 +2092,81 @@ impl SubtaskTool for GrepTool {
                                 if regex.is_match(&line) {
                                     match_count += 1;
                                     file_matches.push((line_num + 1, line.clone()));
+
+                                    if self
+                                        .0
+                                        .max_matches_per_file
+                                        .is_some_and(|max| file_matches.len() >= max)
+                                    {
+                                        break;
+                                    }
                                 }
                             }
 
                             // If we found matches in this file, add them to the results
                             if !file_matches.is_empty() {
-                                let file_name = path.to_string_lossy().to_string();
-                                matches.push((file_name, file_matches));
+                                let file_name = workspaces::display(&path);
+                                let relative = utils::path_to_string(
+                                    path.strip_prefix(workspace_root).unwrap_or(&path),
+                                );
+                                matches.push((file_name, file_matches, relative));
                             }
                         }
                         Err(e) => {
-                            println!("⚠️ Error: {:?}", e);
+                            say!("⚠️ Error: {:?}", e);
                         }
                     }
                 }
             }
             Err(e) => {
-                println!("⚠️ Error with file pattern: {:?}", e);
+                say!("⚠️ Error with file pattern: {:?}", e);
                 return None;
             }
         }
 
+        // The most important files' matches come first, so the first page holds them
+        file_rank::current().sort_by_importance(&mut matches, |(_, _, relative)| relative);
+
+        // Page over matching lines, then regroup the page by file
+        let all_lines: Vec<(&String, usize, &String)> = matches
+            .iter()
+            .flat_map(|(file, file_matches, _)| {
+                file_matches
+                    .iter()
+                    .map(move |(line_num, line)| (file, *line_num, line))
+            })
+            .collect();
+        let page = pagination::paginate(&all_lines, self.0.max_results, self.0.page, true);
+
+        let mut page_matches: Vec<(String, Vec<(usize, String)>)> = Vec::new();
+        for (file, line_num, line) in page.items {
+            match page_matches.last_mut() {
+                Some((last_file, lines)) if last_file == *file => {
+                    lines.push((*line_num, (*line).clone()))
+                }
+                _ => page_matches.push(((*file).clone(), vec![(*line_num, (*line).clone())])),
+            }
+        }
+        let matches = page_matches;
+        let trailer = page
+            .trailer
+            .as_ref()
+            .map(|trailer| format!("\n{}\n", trailer))
+            .unwrap_or_default();
+
         // Format the output for display
         let output = if matches.is_empty() {
-            format!("No matches found for '{}' in {} files", pattern, file_count)
+            format!(
+                "No matches found for '{}' in {} files{}",
+                pattern, file_count, trailer
+            )
         } else {
             let mut result = format!(
-                "Found {} matches for '{}' across {} files (searched {} files total):\n",
+                "Found {} matches for '{}' (searched {} files total), showing {}:\n",
                 match_count,
                 pattern,
-                matches.len(),
-                file_count
+                file_count,
+                page.items.len()
             );
 
             // Format each match
@@ -1657,11 +2201,12 @@ impl SubtaskTool for GrepTool {
                 }
             }
 
+            result.push_str(&trailer);
             result
         };
 
         // Log the formatted output
-        println!("{}", output);
+        say!("{}", output);
 
         // Create a memory fragment from the results
         let memory_fragment = if !matches.is_empty() {
@@ -1676,11 +2221,12 @@ impl SubtaskTool for GrepTool {
                 }
                 mem_content.push('\n');
             }
+            mem_content.push_str(&trailer);
 
             // Create memory metadata
             let memory_metadata = MemoryMetadata {
                 file_type: Some("search_results".to_string()),
-                path: Some(base_path.to_string_lossy().to_string()),
+                path: Some(workspaces::display(&base_path)),
                 timestamp: Some(Utc::now().timestamp()),
                 tags: vec![
                     "grep_search".to_string(),
@@ -1691,6 +2237,7 @@ impl SubtaskTool for GrepTool {
 
             MemoryFragment {
                 source: "grep_search".to_string(),
+                provenance: Provenance::FileRead,
                 content: mem_content,
                 metadata: Some(memory_metadata),
             }
@@ -1698,10 +2245,14 @@ impl SubtaskTool for GrepTool {
             // Create a memory fragment for empty results
             MemoryFragment {
                 source: "grep_search".to_string(),
-                content: format!("No matches found for '{}' in {} files", pattern, file_count),
+                provenance: Provenance::FileRead,
+                content: format!(
+                    "No matches found for '{}' in {} files{}",
+                    pattern, file_count, trailer
+                ),
                 metadata: Some(MemoryMetadata {
                     file_type: Some("search_results".to_string()),
-                    path: Some(base_path.to_string_lossy().to_string()),
+                    path: Some(workspaces::display(&base_path)),
                     timestamp: Some(Utc::now().timestamp()),
                     tags: vec![
                         "grep_search".to_string(),
@@ -1723,94 +2274,125 @@ impl SubtaskTool for GrepTool {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct FileReadToolInputs {
+    /// The absolute path to the file to read
     pub file_path: String,
-    pub file_description:Option<String> ,
+    /// Information about the file or where to find the file
+    pub file_description: Option<String>,
+    /// The maximum number of lines to read
     pub limit: Option<u32>,
+    /// The line number to start reading from (0-based)
     pub offset: Option<u32>,
+    /// Read only the definition of this function, type or class (e.g. `parse_args` or `Storage::open`)
+    pub around_symbol: Option<String>,
 }
 
 pub struct FileReadTool(FileReadToolInputs); //query
 
 #[async_trait]
 impl SubtaskTool for FileReadTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs]
+    }
+
     async fn handle_subtask(
         &self,
-        _ai_client: &Box<dyn AiClient>,
+        _ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
         use crate::memory::{MemoryFragment, MemoryMetadata};
         use chrono::Utc;
-        use std::fs::File;
-        use std::io::{self, BufRead, BufReader};
         use std::path::Path;
 
         // Extract input parameters
         let file_path = &self.0.file_path;
-        let limit = self.0.limit;
-        let offset = self.0.offset.unwrap_or(0);
+        let mut limit = self.0.limit;
+        let mut offset = self.0.offset.unwrap_or(0);
+
+        // Narrow the read to the requested symbol's definition
+        if let Some(symbol) = &self.0.around_symbol {
+            let path = Path::new(file_path);
+            let found = fs_guard::resolve(workspace_root, path, PathAccess::Read)
+                .and_then(|resolved| text_file::read_text(&resolved))
+                .ok()
+                .and_then(|(source, _)| crate::chunking::find_symbol(path, &source, symbol));
+
+            match found {
+                Some(chunk) => {
+                    say!(
+                        "🎯 Found {} at lines {}-{}",
+                        symbol,
+                        chunk.start_line,
+                        chunk.end_line
+                    );
+                    offset = (chunk.start_line - 1) as u32;
+                    limit = Some((chunk.end_line - chunk.start_line + 1) as u32);
+                }
+                None => say!("⚠️ Symbol {} not found, reading the file instead", symbol),
+            }
+        }
 
         // Log the operation
-        println!("📄 Reading file: {}", file_path);
+        say!("📄 Reading file: {}", file_path);
         if let Some(lim) = limit {
-            println!("   With limit: {} lines", lim);
+            say!("   With limit: {} lines", lim);
         }
         if offset > 0 {
-            println!("   Starting at line: {}", offset);
+            say!("   Starting at line: {}", offset);
         }
 
+        // Never follow symlinks out of the workspace or touch special files
+        let path = match fs_guard::resolve(workspace_root, Path::new(file_path), PathAccess::Read) {
+            Ok(path) => path,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                return None;
+            }
+        };
+        let path = path.as_path();
+
         // Check if file exists and is readable
-        let path = Path::new(file_path);
         if !path.exists() {
-            println!("⚠️ Error: File does not exist: {}", file_path);
+            say!("⚠️ Error: File does not exist: {}", file_path);
             return None;
         }
 
-        // Open and read the file
-        let file = match File::open(path) {
-            Ok(f) => f,
+        // Decode the file so the model sees UTF-8 whatever the encoding on disk
+        let text = match text_file::read_text(path) {
+            Ok((text, _)) => text,
             Err(e) => {
-                println!("⚠️ Error opening file: {}", e);
+                say!("⚠️ Error reading file: {}", e);
                 return None;
             }
         };
 
-        // Use BufReader for efficient line-by-line reading
-        let reader = BufReader::new(file);
         let mut content = String::new();
         let mut line_count = 0;
         let mut total_lines = 0;
 
-        // Read file line by line, respecting offset and limit
-        for (i, line_result) in reader.lines().enumerate() {
+        // Walk the lines, respecting offset and limit
+        for (i, line) in text.lines().enumerate() {
             // Skip lines before the offset
             if i < offset as usize {
                 continue;
             }
 
-            match line_result {
-                Ok(line) => {
-                    // Check if we've reached the limit
-                    if let Some(lim) = limit {
-                        if line_count >= lim {
-                            content.push_str("\n... (more lines not shown due to limit) ...");
-                            break;
-                        }
-                    }
-
-                    // Add the line to our content
-                    if line_count > 0 {
-                        content.push('\n');
-                    }
-                    content.push_str(&line);
-                    line_count += 1;
-                }
-                Err(e) => {
-                    println!("⚠️ Error reading line {}: {}", i, e);
-                    // Continue reading despite errors
+            // Check if we've reached the limit
+            if let Some(lim) = limit {
+                if line_count >= lim {
+                    content.push_str("\n... (more lines not shown due to limit) ...");
+                    break;
                 }
             }
+
+            // Add the line to our content
+            if line_count > 0 {
+                content.push('\n');
+            }
+            content.push_str(line);
+            line_count += 1;
             total_lines = i + 1;
         }
 
@@ -1830,20 +2412,26 @@ impl SubtaskTool for FileReadTool {
         };
 
         // Create memory metadata
+        let mut tags = vec![
+            "file_content".to_string(),
+            format!("file:{}", file_name),
+            format!("ext:{}", extension),
+        ];
+        // A window of the file is cited by its lines
+        if (offset > 0 || limit.is_some()) && line_count > 0 {
+            tags.push(crate::memory::lines_tag(offset as usize + 1, total_lines));
+        }
         let memory_metadata = MemoryMetadata {
             file_type: Some(file_type.to_string()),
-            path: Some(file_path.clone()),
+            path: Some(workspaces::display(Path::new(file_path))),
             timestamp: Some(Utc::now().timestamp()),
-            tags: vec![
-                "file_content".to_string(),
-                format!("file:{}", file_name),
-                format!("ext:{}", extension),
-            ],
+            tags,
         };
 
         // Create memory fragment
         let memory_fragment = MemoryFragment {
             source: "file_read".to_string(),
+            provenance: Provenance::FileRead,
             content,
             metadata: Some(memory_metadata),
         };
@@ -1855,9 +2443,10 @@ impl SubtaskTool for FileReadTool {
         }
 
         // Log reading status
-        println!(
+        say!(
             "✅ Read {} of {} total lines from file",
-            line_count, total_lines
+            line_count,
+            total_lines
         );
 
         // Return memory fragment as output
@@ -1865,11 +2454,13 @@ impl SubtaskTool for FileReadTool {
     }
 }
 
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct FileEditToolInputs {
+    /// The absolute path to the file to edit
     pub file_path: String,
+    /// The text to replace. Must be exact including whitespace; empty to create a new file
     pub old_string: String,
+    /// The new text to insert in place of old_string
     pub new_string: String,
 }
 
@@ -1877,36 +2468,57 @@ pub struct FileEditTool(FileEditToolInputs);
 
 #[async_trait]
 impl SubtaskTool for FileEditTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs, Capability::WriteFs]
+    }
+
     async fn handle_subtask(
         &self,
-        _ai_client: &Box<dyn AiClient>,
+        _ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
         use crate::memory::{MemoryFragment, MemoryMetadata};
         use chrono::Utc;
         use std::fs;
-        use std::io::{self, Read, Write};
         use std::path::Path;
 
         // Extract input parameters
         let file_path = &self.0.file_path;
         let old_string = &self.0.old_string;
-        let new_string = &self.0.new_string;
+        let style = EditorStyle::for_path(Path::new(file_path));
+        let new_string = &if old_string.is_empty() {
+            style.whole_file(&self.0.new_string)
+        } else {
+            style.indent_block(&self.0.new_string)
+        };
 
         // Log the operation
-        println!("✏️ Editing file: {}", file_path);
+        say!("✏️ Editing file: {}", file_path);
 
         // Check if we're creating a new file (old_string is empty)
         let creating_new_file = old_string.is_empty();
-        let path = Path::new(file_path);
+        // Never follow symlinks out of the workspace or touch special files
+        let path = match fs_guard::resolve(workspace_root, Path::new(file_path), PathAccess::Write)
+        {
+            Ok(path) => path,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                return None;
+            }
+        };
+        let path = path.as_path();
 
         if creating_new_file {
-            println!("🆕 Creating new file: {}", file_path);
+            say!("🆕 Creating new file: {}", file_path);
 
             // Make sure parent directory exists
             if let Some(parent) = path.parent() {
                 if !parent.exists() {
-                    println!("⚠️ Error: Parent directory does not exist: {}", parent.display());
+                    say!(
+                        "⚠️ Error: Parent directory does not exist: {}",
+                        parent.display()
+                    );
                     return None;
                 }
             }
@@ -1914,43 +2526,42 @@ impl SubtaskTool for FileEditTool {
             // Create file and write content
             let write_result = fs::write(path, new_string);
             if let Err(e) = write_result {
-                println!("⚠️ Error creating file: {}", e);
+                say!("⚠️ Error creating file: {}", e);
                 return None;
             }
 
-            println!("✅ Successfully created file: {}", file_path);
+            say!("✅ Successfully created file: {}", file_path);
         } else {
             // Editing existing file
             if !path.exists() {
-                println!("⚠️ Error: File does not exist: {}", file_path);
+                say!("⚠️ Error: File does not exist: {}", file_path);
                 return None;
             }
 
-            // Read file content
-            let mut file_content = String::new();
-            let read_result = fs::read_to_string(path);
-            match read_result {
-                Ok(content) => {
-                    file_content = content;
-                },
+            // Read file content, remembering its line endings and encoding
+            let (file_content, format) = match text_file::read_text(path) {
+                Ok(decoded) => decoded,
                 Err(e) => {
-                    println!("⚠️ Error reading file: {}", e);
+                    say!("⚠️ Error reading file: {}", e);
                     return None;
                 }
-            }
+            };
 
             // Check if the old_string exists exactly
             if !file_content.contains(old_string) {
-                println!("⚠️ Error: Could not find the exact text to replace in the file");
-                println!("   Please ensure the text to replace matches exactly, including whitespace and indentation");
+                say!("⚠️ Error: Could not find the exact text to replace in the file");
+                say!("   Please ensure the text to replace matches exactly, including whitespace and indentation");
                 return None;
             }
 
             // Count occurrences to check for uniqueness
             let occurrences = file_content.matches(old_string).count();
             if occurrences > 1 {
-                println!("⚠️ Error: The text to replace appears {} times in the file", occurrences);
-                println!("   Please provide more context to make the match unique");
+                say!(
+                    "⚠️ Error: The text to replace appears {} times in the file",
+                    occurrences
+                );
+                say!("   Please provide more context to make the match unique");
                 return None;
             }
 
@@ -1958,13 +2569,13 @@ impl SubtaskTool for FileEditTool {
             let updated_content = file_content.replace(old_string, new_string);
 
             // Write the updated content back to the file
-            let write_result = fs::write(path, updated_content);
+            let write_result = format.write(path, &updated_content);
             if let Err(e) = write_result {
-                println!("⚠️ Error writing to file: {}", e);
+                say!("⚠️ Error writing to file: {}", e);
                 return None;
             }
 
-            println!("✅ Successfully updated file: {}", file_path);
+            say!("✅ Successfully updated file: {}", file_path);
         }
 
         // Create memory fragment to record the edit
@@ -1980,13 +2591,14 @@ impl SubtaskTool for FileEditTool {
 
         // Create memory content
         let memory_content = if creating_new_file {
-            format!("Created new file: {}\n\nContent:\n{}", file_path, new_string)
+            format!(
+                "Created new file: {}\n\nContent:\n{}",
+                file_path, new_string
+            )
         } else {
             format!(
-                "Edited file: {}\n\nReplaced:\n{}\n\nWith:\n{}", 
-                file_path, 
-                old_string, 
-                new_string
+                "Edited file: {}\n\nReplaced:\n{}\n\nWith:\n{}",
+                file_path, old_string, new_string
             )
         };
 
@@ -2005,6 +2617,7 @@ impl SubtaskTool for FileEditTool {
         // Create memory fragment
         let memory_fragment = MemoryFragment {
             source: "file_edit".to_string(),
+            provenance: Provenance::ToolOutput,
             content: memory_content,
             metadata: Some(memory_metadata),
         };
@@ -2020,130 +2633,654 @@ impl SubtaskTool for FileEditTool {
     }
 }
 
-// --------------
-
-// Helper function to format memory fragments in a structured way for the AI
-fn format_memory_fragments(fragments: &[crate::memory::MemoryFragment]) -> String {
-    if fragments.is_empty() {
-        return "No relevant context available.".to_string();
-    }
-
-    let mut formatted_context = format!("=== RELEVANT CONTEXT ({} ITEMS) ===\n\n", fragments.len());
-
-    for (i, fragment) in fragments.iter().enumerate() {
-        formatted_context.push_str(&format!("--- CONTEXT ITEM {} ---\n", i + 1));
-        formatted_context.push_str(&format!("Source: {}\n", fragment.source));
-        
-        // Add metadata if present
-        if let Some(meta) = &fragment.metadata {
-            if let Some(file_type) = &meta.file_type {
-                formatted_context.push_str(&format!("Type: {}\n", file_type));
-            }
-            if let Some(path) = &meta.path {
-                formatted_context.push_str(&format!("Path: {}\n", path));
-            }
-            if !meta.tags.is_empty() {
-                formatted_context.push_str(&format!("Tags: {}\n", meta.tags.join(", ")));
-            }
-        }
-        
-        // Add content with a separator
-        formatted_context.push_str("Content:\n");
-        formatted_context.push_str("```\n");
-        
-        // Truncate very long content
-        let content = if fragment.content.len() > 2000 {
-            format!("{}...(truncated)", &fragment.content[0..2000])
-        } else {
-            fragment.content.clone()
-        };
-        
-        formatted_context.push_str(&content);
-        formatted_context.push_str("\n```\n\n");
-    }
-
-    formatted_context
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct EditSymbolToolInputs {
+    /// The absolute path to the file containing the symbol
+    pub file_path: String,
+    /// Name of the definition, qualified with its container if ambiguous (e.g. `Storage::open`)
+    pub symbol: String,
+    /// The complete new definition, including its signature, replacing the old one
+    pub new_body: String,
 }
 
-pub struct ExplainTool(String); // Query string
+pub struct EditSymbolTool(EditSymbolToolInputs);
 
 #[async_trait]
-impl SubtaskTool for ExplainTool {
+impl SubtaskTool for EditSymbolTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs, Capability::WriteFs]
+    }
+
     async fn handle_subtask(
         &self,
-        ai_client: &Box<dyn AiClient>,
+        _ai_client: &dyn AiClient,
         context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
     ) -> Option<SubtaskOutput> {
-        use colored::Colorize;
-        use std::time::Instant;
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use chrono::Utc;
+        use std::path::Path;
 
-        // Get the query to explain
-        let query = &self.0;
+        let file_path = &self.0.file_path;
+        let symbol = &self.0.symbol;
+        // Never follow symlinks out of the workspace or touch special files
+        let path = match fs_guard::resolve(workspace_root, Path::new(file_path), PathAccess::Write)
+        {
+            Ok(path) => path,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                return None;
+            }
+        };
+        let path = path.as_path();
 
-        println!(
-            "\n{} {}\n",
-            "💡".bold(),
-            "Generating explanation based on context...".cyan().bold()
-        );
+        say!("🧩 Editing {} in {}", symbol, file_path);
 
-        // Create a system prompt that instructs the AI to use the context
-        let system_prompt = r#"
-You are an expert AI assistant explaining a software codebase with context.
-Use the provided context to thoroughly answer the user's question.
-Focus only on the information present in the context and the specific query.
-If the context doesn't contain relevant information, acknowledge the limitations 
-of what you can explain based on the available context.
+        let (file_content, format) = match text_file::read_text(path) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                say!("⚠️ Error reading file: {}", e);
+                return None;
+            }
+        };
 
-Format your response clearly using markdown when appropriate:
-- Use bullet points for lists
-- Use code blocks for code examples or file paths
-- Use headings to organize longer responses
-"#;
+        let ranges = crate::chunking::symbol_byte_ranges(path, &file_content, symbol);
+        let range = match ranges.as_slice() {
+            [range] => range.clone(),
+            [] => {
+                say!("⚠️ Error: Could not find a definition named {}", symbol);
+                return None;
+            }
+            _ => {
+                say!(
+                    "⚠️ Error: {} definitions are named {}",
+                    ranges.len(),
+                    symbol
+                );
+                say!("   Qualify the symbol with its container, e.g. Type::method");
+                return None;
+            }
+        };
 
-        // Collect all context data from memory fragments
-        let context_data = {
-            let memory = context_memory.lock().await;
-            let fragments = memory.get_fragments();
+        let old_body = file_content[range.clone()].to_string();
 
-            if fragments.is_empty() {
-                "No context information has been collected yet.".to_string()
-            } else {
-                // Format all fragments as context
-                let mut context_str = format!(
-                    "Local {} relevant context of information:\n\n",
-                    fragments.len()
-                );
+        // The definition starts mid-line, after its indentation, so drop any on the new body
+        let new_body = EditorStyle::for_path(path).indent_block(&self.0.new_body);
+        let new_body = new_body.trim_start().trim_end();
 
-                for (i, fragment) in fragments.iter().enumerate() {
-                    context_str.push_str(&format!(
-                        "=== CONTEXT ITEM {} (from {}) ===\n",
-                        i + 1,
-                        fragment.source
-                    ));
-                    context_str.push_str(&fragment.content);
-                    context_str.push_str("\n\n");
+        let updated_content = format!(
+            "{}{}{}",
+            &file_content[..range.start],
+            new_body,
+            &file_content[range.end..]
+        );
 
-                    // Add metadata if present
-                    if let Some(meta) = &fragment.metadata {
-                        if let Some(path) = &meta.path {
-                            context_str.push_str(&format!("Path: {}\n", path));
-                        }
-                        if !meta.tags.is_empty() {
-                            context_str.push_str(&format!("Tags: {}\n", meta.tags.join(", ")));
-                        }
-                        context_str.push_str("\n");
-                    }
-                }
+        if let Err(e) = format.write(path, &updated_content) {
+            say!("⚠️ Error writing to file: {}", e);
+            return None;
+        }
 
-                context_str
-            }
-        };
+        say!("✅ Successfully replaced {} in {}", symbol, file_path);
 
-        // Build messages with context and query
-        let messages = vec![
-            crate::ai::Message {
+        let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or("");
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+        let memory_fragment = MemoryFragment {
+            source: "file_edit".to_string(),
+            provenance: Provenance::ToolOutput,
+            content: format!(
+                "Edited {} in file: {}\n\nReplaced:\n{}\n\nWith:\n{}",
+                symbol, file_path, old_body, new_body
+            ),
+            metadata: Some(MemoryMetadata {
+                file_type: Some(format!("{}_file", extension)),
+                path: Some(file_path.clone()),
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec![
+                    "file_edit".to_string(),
+                    format!("file:{}", file_name),
+                    format!("symbol:{}", symbol),
+                    "operation:edited".to_string(),
+                ],
+            }),
+        };
+
+        {
+            let mut memory = context_memory.lock().await;
+            memory.add_frag(memory_fragment.clone());
+        }
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InsertToolInputs {
+    pub file_path: String,
+    pub position: InsertPosition,
+    pub content: String,
+}
+
+/// InsertTool arguments as the model sends them, with the position as separate options
+#[derive(Deserialize, JsonSchema)]
+pub struct InsertToolArguments {
+    /// The absolute path to the file to insert into
+    pub file_path: String,
+    /// The lines to insert
+    pub content: String,
+    /// Text that appears exactly once; content goes on the lines after the line containing it (e.g. `fn main() {`)
+    pub insert_after: Option<String>,
+    /// Text that appears exactly once; content goes on the lines before the line containing it
+    pub insert_before: Option<String>,
+    /// Set to true to add content at the end of the file, creating it if needed
+    #[serde(default)]
+    pub append_to_file: bool,
+}
+
+impl InsertToolArguments {
+    /// The inputs, or None when no position was given
+    pub fn into_inputs(self) -> Option<InsertToolInputs> {
+        let position = if let Some(anchor) = self.insert_after {
+            InsertPosition::After(anchor)
+        } else if let Some(anchor) = self.insert_before {
+            InsertPosition::Before(anchor)
+        } else if self.append_to_file {
+            InsertPosition::End
+        } else {
+            return None;
+        };
+
+        Some(InsertToolInputs {
+            file_path: self.file_path,
+            position,
+            content: self.content,
+        })
+    }
+}
+
+pub struct InsertTool(InsertToolInputs);
+
+#[async_trait]
+impl SubtaskTool for InsertTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs, Capability::WriteFs]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use chrono::Utc;
+        use std::path::Path;
+
+        let file_path = &self.0.file_path;
+        // Never follow symlinks out of the workspace or touch special files
+        let path = match fs_guard::resolve(workspace_root, Path::new(file_path), PathAccess::Write)
+        {
+            Ok(path) => path,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                return None;
+            }
+        };
+        let path = path.as_path();
+
+        say!("➕ Inserting into file: {}", file_path);
+
+        // Appending may create the file; anchored inserts need existing content
+        let (file_content, format) = match text_file::read_text(path) {
+            Ok(decoded) => decoded,
+            Err(_) if self.0.position == InsertPosition::End && !path.exists() => {
+                (String::new(), TextFormat::default())
+            }
+            Err(e) => {
+                say!("⚠️ Error reading file: {}", e);
+                return None;
+            }
+        };
+
+        let content = EditorStyle::for_path(path).indent_block(&self.0.content);
+        let updated_content = match edits::apply_insert(&file_content, &self.0.position, &content) {
+            Ok(content) => content,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                return None;
+            }
+        };
+
+        if let Err(e) = format.write(path, &updated_content) {
+            say!("⚠️ Error writing to file: {}", e);
+            return None;
+        }
+
+        say!("✅ Successfully inserted into file: {}", file_path);
+
+        let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or("");
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+        let location = match &self.0.position {
+            InsertPosition::After(anchor) => format!("after `{}`", anchor),
+            InsertPosition::Before(anchor) => format!("before `{}`", anchor),
+            InsertPosition::End => "at end of file".to_string(),
+        };
+
+        let memory_fragment = MemoryFragment {
+            source: "file_edit".to_string(),
+            provenance: Provenance::ToolOutput,
+            content: format!(
+                "Inserted into file: {} {}\n\nContent:\n{}",
+                file_path, location, self.0.content
+            ),
+            metadata: Some(MemoryMetadata {
+                file_type: Some(format!("{}_file", extension)),
+                path: Some(file_path.clone()),
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec![
+                    "file_edit".to_string(),
+                    format!("file:{}", file_name),
+                    "operation:inserted".to_string(),
+                ],
+            }),
+        };
+
+        {
+            let mut memory = context_memory.lock().await;
+            memory.add_frag(memory_fragment.clone());
+        }
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct WriteFileToolInputs {
+    /// The absolute path to the file to write
+    pub file_path: String,
+    /// The complete content of the file
+    pub content: String,
+    /// Set to true to replace the content of a file that already exists
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+impl WriteFileToolInputs {
+    /// The write as an edit set, so it is reviewed and applied like other edits. Fails for
+    /// an existing file unless `overwrite` is set.
+    pub fn to_edit_set(&self, workspace_root: &Path) -> AgentResult<EditSet> {
+        let path = fs_guard::resolve(
+            workspace_root,
+            Path::new(&self.file_path),
+            PathAccess::Write,
+        )?;
+        if path.exists() && !self.overwrite {
+            return Err(AgentError::ToolExecution(format!(
+                "File already exists: {}; set overwrite to replace it",
+                self.file_path
+            )));
+        }
+
+        let mut edit_set = EditSet::new(workspace_root);
+        edit_set.stage(
+            &self.file_path,
+            FileEdit::Replace {
+                old_string: String::new(),
+                new_string: self.content.clone(),
+            },
+        );
+        Ok(edit_set)
+    }
+}
+
+pub struct WriteFileTool(WriteFileToolInputs);
+
+#[async_trait]
+impl SubtaskTool for WriteFileTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs, Capability::WriteFs]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use chrono::Utc;
+
+        let file_path = &self.0.file_path;
+        say!("📝 Writing file: {}", file_path);
+
+        let written = match self
+            .0
+            .to_edit_set(workspace_root)
+            .and_then(|set| set.apply())
+        {
+            Ok(mut written) if !written.is_empty() => written.remove(0),
+            Ok(_) => return None,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                return None;
+            }
+        };
+
+        let operation = if written.original.is_some() {
+            "overwritten"
+        } else {
+            "created"
+        };
+        say!("✅ Successfully {} file: {}", operation, file_path);
+
+        let file_name = written
+            .path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or("");
+        let extension = written
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+
+        // A new file's diff is its whole content; a rewrite only shows what changed
+        let memory_fragment = MemoryFragment {
+            source: "file_edit".to_string(),
+            provenance: Provenance::ToolOutput,
+            content: format!(
+                "File {}: {} ({} lines)\n\n{}",
+                operation,
+                file_path,
+                written.updated.lines().count(),
+                written.diff()
+            ),
+            metadata: Some(MemoryMetadata {
+                file_type: Some(format!("{}_file", extension)),
+                path: Some(file_path.clone()),
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec![
+                    "file_edit".to_string(),
+                    format!("file:{}", file_name),
+                    format!("operation:{}", operation),
+                ],
+            }),
+        };
+
+        {
+            let mut memory = context_memory.lock().await;
+            memory.add_frag(memory_fragment.clone());
+        }
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct EditSetToolInputs {
+    /// The edits to apply, in order
+    pub edits: Vec<FileEditToolInputs>,
+}
+
+impl EditSetToolInputs {
+    pub fn to_edit_set(&self, workspace_root: &Path) -> EditSet {
+        let mut edit_set = EditSet::new(workspace_root);
+        for edit in &self.edits {
+            edit_set.stage(
+                &edit.file_path,
+                FileEdit::Replace {
+                    old_string: edit.old_string.clone(),
+                    new_string: edit.new_string.clone(),
+                },
+            );
+        }
+        edit_set
+    }
+}
+
+pub struct EditSetTool(EditSetToolInputs);
+
+#[async_trait]
+impl SubtaskTool for EditSetTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs, Capability::WriteFs]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use chrono::Utc;
+
+        let edit_set = self.0.to_edit_set(workspace_root);
+
+        say!("🗂️ Applying {} edits", edit_set.len());
+
+        let applied = match edit_set.apply() {
+            Ok(applied) => applied,
+            Err(e) => {
+                say!("⚠️ Error: {}", e);
+                say!("   No files were changed");
+                return None;
+            }
+        };
+
+        let paths: Vec<String> = applied
+            .iter()
+            .map(|file| file.path.display().to_string())
+            .collect();
+
+        say!("✅ Successfully updated {} files", applied.len());
+
+        let memory_fragment = MemoryFragment {
+            source: "file_edit".to_string(),
+            provenance: Provenance::ToolOutput,
+            content: format!(
+                "Edited {} files together:\n\n{}",
+                applied.len(),
+                applied
+                    .iter()
+                    .map(|file| file.diff())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            metadata: Some(MemoryMetadata {
+                file_type: None,
+                path: None,
+                timestamp: Some(Utc::now().timestamp()),
+                tags: std::iter::once("file_edit".to_string())
+                    .chain(std::iter::once("operation:edit_set".to_string()))
+                    .chain(paths.iter().map(|path| format!("file:{}", path)))
+                    .collect(),
+            }),
+        };
+
+        {
+            let mut memory = context_memory.lock().await;
+            memory.add_frag(memory_fragment.clone());
+        }
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+// --------------
+
+// Helper function to format memory fragments in a structured way for the AI
+fn format_memory_fragments(fragments: &[crate::memory::MemoryFragment]) -> String {
+    if fragments.is_empty() {
+        return "No relevant context available.".to_string();
+    }
+
+    let mut formatted_context = format!(
+        "=== RELEVANT CONTEXT ({} ITEMS) ===\n{}\n\n",
+        fragments.len(),
+        TRUST_GUIDANCE
+    );
+
+    for (i, fragment) in fragments.iter().enumerate() {
+        formatted_context.push_str(&format!("--- CONTEXT ITEM {} ---\n", i + 1));
+        formatted_context.push_str(&format!("Source: {}\n", fragment.source));
+        formatted_context.push_str(&format!("Provenance: {}\n", fragment.provenance_label()));
+
+        // Add metadata if present
+        if let Some(meta) = &fragment.metadata {
+            if let Some(file_type) = &meta.file_type {
+                formatted_context.push_str(&format!("Type: {}\n", file_type));
+            }
+            if let Some(path) = &meta.path {
+                formatted_context.push_str(&format!("Path: {}\n", path));
+            }
+            if !meta.tags.is_empty() {
+                formatted_context.push_str(&format!("Tags: {}\n", meta.tags.join(", ")));
+            }
+        }
+
+        // Add content with a separator
+        formatted_context.push_str("Content:\n");
+        formatted_context.push_str("```\n");
+
+        // Truncate very long content
+        let content = if fragment.content.len() > 2000 {
+            format!("{}...(truncated)", &fragment.content[0..2000])
+        } else {
+            fragment.content.clone()
+        };
+
+        formatted_context.push_str(&content);
+        formatted_context.push_str("\n```\n\n");
+    }
+
+    formatted_context
+}
+
+/// ExplainTool arguments as the model sends them
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct ExplainToolInputs {
+    /// The specific question or topic to explain based on the gathered context
+    #[serde(default)]
+    pub query: String,
+    /// How to write the explanation: creative to brainstorm alternatives, otherwise omit
+    #[serde(default)]
+    pub generation_profile: Option<GenerationProfile>,
+}
+
+pub struct ExplainTool(ExplainToolInputs);
+
+/// Tokens of the context window kept free for the explanation itself
+const EXPLANATION_RESERVE_TOKENS: usize = 4096;
+
+#[async_trait]
+impl SubtaskTool for ExplainTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[]
+    }
+
+    async fn handle_subtask(
+        &self,
+        ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use std::time::Instant;
+
+        // Get the query to explain
+        let query = &self.0.query;
+
+        let ai_client = with_profile(ai_client, self.0.generation_profile);
+        let ai_client = ai_client.as_ref();
+
+        say!(
+            "\n{} {}\n",
+            "💡",
+            theme::paint("Generating explanation based on context...", Role::Accent)
+        );
+
+        // Create a system prompt that instructs the AI to use the context
+        let system_prompt = r#"
+You are an expert AI assistant explaining a software codebase with context.
+Use the provided context to thoroughly answer the user's question.
+Focus only on the information present in the context and the specific query.
+If the context doesn't contain relevant information, acknowledge the limitations 
+of what you can explain based on the available context.
+
+When a context item has a citation such as `src/parser.rs:40-62`, cite it in that form
+after the statements it supports, so the user can jump to exactly those lines.
+
+Format your response clearly using markdown when appropriate:
+- Use bullet points for lists
+- Use code blocks for code examples or file paths
+- Use headings to organize longer responses
+"#
+        .to_string()
+            + TRUST_GUIDANCE
+            + ASSESSMENT_INSTRUCTIONS;
+
+        // Collect the context that fits the model's window, most relevant first
+        let budget = model_switch::context_window(&ai_client.model_name()).saturating_sub(
+            tokens::count(&system_prompt) + tokens::count(query) + EXPLANATION_RESERVE_TOKENS,
+        );
+        let (fragments, omitted) = context_memory.lock().await.fragments_within(query, budget);
+        if omitted > 0 {
+            say!(
+                "{}",
+                theme::paint(
+                    &format!(
+                        "Left out {} less relevant context fragments to fit the context window",
+                        omitted
+                    ),
+                    Role::Muted
+                )
+            );
+        }
+        let context_data = if fragments.is_empty() {
+            "No context information has been collected yet.".to_string()
+        } else {
+            // Format all fragments as context
+            let mut context_str = format!(
+                "Local {} relevant context of information:\n\n",
+                fragments.len()
+            );
+
+            for (i, fragment) in fragments.iter().enumerate() {
+                let citation = fragment
+                    .citation()
+                    .map(|citation| format!("; cite as {}", citation))
+                    .unwrap_or_default();
+                context_str.push_str(&format!(
+                    "=== CONTEXT ITEM {} (from {}; {}{}) ===\n",
+                    i + 1,
+                    fragment.source,
+                    fragment.provenance_label(),
+                    citation
+                ));
+                context_str.push_str(&fragment.content);
+                context_str.push_str("\n\n");
+
+                // Add metadata if present
+                if let Some(meta) = &fragment.metadata {
+                    if let Some(path) = &meta.path {
+                        context_str.push_str(&format!("Path: {}\n", path));
+                    }
+                    if !meta.tags.is_empty() {
+                        context_str.push_str(&format!("Tags: {}\n", meta.tags.join(", ")));
+                    }
+                    context_str.push('\n');
+                }
+            }
+
+            context_str
+        };
+
+        // Build messages with context and query
+        let messages = vec![
+            crate::ai::Message {
                 role: crate::ai::MessageRole::System,
-                content: system_prompt.to_string(),
+                content: system_prompt,
                 name: None,
             },
             crate::ai::Message {
@@ -2153,35 +3290,665 @@ Format your response clearly using markdown when appropriate:
             },
         ];
 
-        // Start timer for measuring explanation generation time
-        let start_time = Instant::now();
+        // Start timer for measuring explanation generation time
+        let start_time = Instant::now();
+
+        // Show the explanation line by line as the model writes it
+        let separator = theme::paint(&"=".repeat(80), Role::Accent);
+        say!("\n{}\n", separator);
+        let mut shown = ExplanationStream::default();
+        let streamed: AgentResult<()> = async {
+            // A connection that drops midway is resumed rather than losing the text so far
+            let mut tokens = salvage::stream_text(ai_client, messages).await?;
+            while let Some(token) = tokens.next().await {
+                print!("{}", output::plain(&shown.push(&token?)));
+                let _ = std::io::stdout().flush();
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(e) = streamed {
+            say!(
+                "{} {}: {}",
+                "❌",
+                theme::paint("Error generating explanation", Role::Error),
+                e
+            );
+            return Some(SubtaskOutput::SubtaskComplete());
+        }
+        let (rest, explanation) = shown.finish();
+        print!("{}", output::plain(&rest));
+
+        // Calculate and format elapsed time
+        let elapsed = start_time.elapsed();
+        let elapsed_secs = elapsed.as_secs_f32();
+        let timing_msg = format!("Generated in {:.2}s", elapsed_secs);
+
+        let assessment = explanation.render_assessment();
+        if !assessment.is_empty() {
+            say!("\n{}", assessment);
+        }
+        say!("\n{}\n", separator);
+        say!("{}\n", theme::paint(&timing_msg, Role::Muted));
+
+        context_memory
+            .lock()
+            .await
+            .record_explanation(explanation.to_markdown(query));
+
+        // Long answers scroll away; keep the full text for `/last`
+        match answers::save(workspace_root, query, &explanation) {
+            Ok(Some(path)) => say!(
+                "{}\n",
+                theme::paint(
+                    &format!(
+                        "Saved to {} (/last to reopen)",
+                        platform::display_path(&path)
+                    ),
+                    Role::Muted
+                )
+            ),
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to save the explanation: {}", e),
+        }
+
+        // Unconfirmed claims can be checked by a follow-up plan that searches further
+        match explanation.verification_task(query) {
+            Some(task) => Some(SubtaskOutput::OfferFollowUp(
+                "Verify with more searching?".to_string(),
+                vec![SubTaskType::Task(task)],
+            )),
+            None => Some(SubtaskOutput::SubtaskComplete()),
+        }
+    }
+}
+
+/// RememberTool arguments as the model sends them
+#[derive(Deserialize, JsonSchema)]
+pub struct RememberToolInputs {
+    /// A short, self-contained statement of the fact to remember
+    pub fact: String,
+}
+
+pub struct RememberTool(String); // Fact to persist
+
+#[async_trait]
+impl SubtaskTool for RememberTool {
+    // The fact reaches every later planner prompt, so it needs approval, but the workspace
+    // itself is left alone
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::WriteKnowledge]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        _context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        let fact = self.0.trim();
+
+        if fact.is_empty() {
+            say!("⚠️ Error: Nothing to remember");
+            return None;
+        }
+
+        // Persisting needs storage, which the engine owns
+        Some(SubtaskOutput::RememberFact(fact.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct MemoryGrepToolInputs {
+    /// The regex pattern to search for
+    pub pattern: String,
+    /// Only search fragments whose source or path contains this text (e.g. a file name)
+    pub source: Option<String>,
+    /// Ignore letter case when matching
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Search for the pattern as literal text instead of a regex
+    #[serde(default)]
+    pub fixed_string: bool,
+}
+
+pub struct MemoryGrepTool(MemoryGrepToolInputs);
+
+/// Matching lines shown per fragment, so one huge fragment cannot crowd out the rest
+const MEMORY_GREP_LINES_PER_FRAGMENT: usize = 20;
+
+#[async_trait]
+impl SubtaskTool for MemoryGrepTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use chrono::Utc;
+
+        let inputs = &self.0;
+        let regex = match search_regex(
+            &inputs.pattern,
+            inputs.case_insensitive,
+            inputs.fixed_string,
+            false,
+        ) {
+            Ok(re) => re,
+            Err(e) => {
+                say!("⚠️ Invalid regex pattern: {:?}", e);
+                return None;
+            }
+        };
+
+        let mut result = String::new();
+        let mut match_count = 0;
+        let mut searched = 0;
+
+        {
+            let memory = context_memory.lock().await;
+            let fragments = memory
+                .get_pinned_fragments()
+                .iter()
+                .chain(memory.get_fragments().iter());
+
+            for fragment in fragments {
+                let path = fragment.metadata.as_ref().and_then(|m| m.path.as_deref());
+                let label = match path {
+                    Some(path) => format!("{} ({})", fragment.source, path),
+                    None => fragment.source.clone(),
+                };
+
+                if let Some(filter) = &inputs.source {
+                    if !label.contains(filter.as_str()) {
+                        continue;
+                    }
+                }
+                searched += 1;
+
+                let lines: Vec<(usize, &str)> = fragment
+                    .content
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| regex.is_match(line))
+                    .map(|(i, line)| (i + 1, line))
+                    .collect();
+                if lines.is_empty() {
+                    continue;
+                }
+
+                match_count += lines.len();
+                result.push_str(&format!("\n{}:\n", label));
+                for (line_num, line) in lines.iter().take(MEMORY_GREP_LINES_PER_FRAGMENT) {
+                    result.push_str(&format!("  Line {}: {}\n", line_num, line));
+                }
+                if lines.len() > MEMORY_GREP_LINES_PER_FRAGMENT {
+                    result.push_str(&format!(
+                        "  ... and {} more matches\n",
+                        lines.len() - MEMORY_GREP_LINES_PER_FRAGMENT
+                    ));
+                }
+            }
+        }
+
+        let content = if match_count == 0 {
+            format!(
+                "No matches for '{}' in {} gathered fragments",
+                inputs.pattern, searched
+            )
+        } else {
+            format!(
+                "Found {} matches for '{}' in gathered context:\n{}",
+                match_count, inputs.pattern, result
+            )
+        };
+        println!("{}", content);
+
+        let memory_fragment = MemoryFragment {
+            source: "memory_search".to_string(),
+            provenance: Provenance::ToolOutput,
+            content,
+            metadata: Some(MemoryMetadata {
+                file_type: Some("search_results".to_string()),
+                path: None,
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec![
+                    "memory_search".to_string(),
+                    format!("pattern:{}", inputs.pattern),
+                ],
+            }),
+        };
+
+        context_memory
+            .lock()
+            .await
+            .add_frag(memory_fragment.clone());
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct SemanticSearchToolInputs {
+    /// What the code you are looking for does, in plain words
+    pub query: String,
+    /// The most chunks to return (default 8)
+    pub max_results: Option<usize>,
+}
+
+pub struct SemanticSearchTool(SemanticSearchToolInputs);
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct HybridSearchToolInputs {
+    /// What you are looking for, in plain words; its distinctive words are also grepped for
+    pub query: String,
+    /// The most results to return (default 10)
+    pub max_results: Option<usize>,
+}
+
+pub struct HybridSearchTool(HybridSearchToolInputs);
+
+/// Results returned when the model does not ask for a number
+const HYBRID_SEARCH_DEFAULT_RESULTS: usize = 10;
+
+#[async_trait]
+impl SubtaskTool for HybridSearchTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs, Capability::Network]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        _context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        let query = self.0.query.trim();
+        if query.is_empty() {
+            say!("⚠️ Error: Nothing to search for");
+            return None;
+        }
+
+        // The semantic half needs the index, which lives in storage the engine owns
+        Some(SubtaskOutput::HybridSearch(
+            query.to_string(),
+            self.0
+                .max_results
+                .unwrap_or(HYBRID_SEARCH_DEFAULT_RESULTS)
+                .max(1),
+        ))
+    }
+}
+
+/// Chunks returned when the model does not ask for a number
+const SEMANTIC_SEARCH_DEFAULT_RESULTS: usize = 8;
+
+#[async_trait]
+impl SubtaskTool for SemanticSearchTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs, Capability::Network]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        _context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        let query = self.0.query.trim();
+        if query.is_empty() {
+            say!("⚠️ Error: Nothing to search for");
+            return None;
+        }
+
+        // The index lives in storage, which the engine owns
+        Some(SubtaskOutput::SemanticSearch(
+            query.to_string(),
+            self.0
+                .max_results
+                .unwrap_or(SEMANTIC_SEARCH_DEFAULT_RESULTS)
+                .max(1),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct CrateSourceToolInputs {
+    /// The dependency's name as written in Cargo.toml
+    pub crate_name: String,
+    /// A file to read, relative to the dependency's root (e.g. `src/lib.rs`)
+    pub file_path: Option<String>,
+    /// Line to start reading file_path from, counting from 0
+    pub offset: Option<usize>,
+    /// A regex to search the dependency's source for, instead of reading a file. Leave both
+    /// out to list the dependency's files
+    pub pattern: Option<String>,
+}
+
+pub struct CrateSourceTool(CrateSourceToolInputs);
+
+#[async_trait]
+impl SubtaskTool for CrateSourceTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::ReadFs]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::crate_source;
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use chrono::Utc;
+
+        let inputs = &self.0;
+        let source = match crate_source::locate(workspace_root, &inputs.crate_name) {
+            Ok(source) => source,
+            Err(e) => {
+                say!("⚠️ {}", e);
+                return None;
+            }
+        };
+        say!(
+            "📦 Using the source of {} in {}",
+            source.label(),
+            platform::display_path(&source.root)
+        );
+
+        let (file_type, path, content) = match (&inputs.pattern, &inputs.file_path) {
+            (Some(pattern), _) => {
+                let regex = match search_regex(pattern, false, false, false) {
+                    Ok(re) => re,
+                    Err(e) => {
+                        say!("⚠️ Invalid regex pattern: {:?}", e);
+                        return None;
+                    }
+                };
+                let matches = crate_source::search(&source, &regex);
+                let content = if matches.is_empty() {
+                    format!("No matches for '{}' in {}", pattern, source.label())
+                } else {
+                    format!(
+                        "Matches for '{}' in {}:\n{}",
+                        pattern,
+                        source.label(),
+                        matches.join("\n")
+                    )
+                };
+                ("search_results", source.label(), content)
+            }
+            (None, Some(file_path)) => {
+                match crate_source::read(&source, file_path, inputs.offset.unwrap_or(0)) {
+                    Ok(content) => (
+                        "rust_source",
+                        format!("{}/{}", source.label(), file_path),
+                        content,
+                    ),
+                    Err(e) => {
+                        say!("⚠️ Error: {}", e);
+                        return None;
+                    }
+                }
+            }
+            (None, None) => (
+                "file_list",
+                source.label(),
+                format!(
+                    "Files of {}:\n{}",
+                    source.label(),
+                    crate_source::list_files(&source).join("\n")
+                ),
+            ),
+        };
+        println!("{}", utils::truncate_string(&content, 2_000));
+
+        // Dependency code is read like workspace files, but is not part of the project
+        let memory_fragment = MemoryFragment {
+            source: "crate_source".to_string(),
+            provenance: Provenance::FileRead,
+            content,
+            metadata: Some(MemoryMetadata {
+                file_type: Some(file_type.to_string()),
+                path: Some(path),
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec!["crate_source".to_string(), format!("crate:{}", source.name)],
+            }),
+        };
+
+        context_memory
+            .lock()
+            .await
+            .add_frag(memory_fragment.clone());
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct CrateDocsToolInputs {
+    /// The crate's name on crates.io
+    pub crate_name: String,
+    /// The version to describe; defaults to the one Cargo.lock pins, else the latest release
+    pub version: Option<String>,
+    /// Only show README paragraphs mentioning this (e.g. a type or function name)
+    pub query: Option<String>,
+}
+
+pub struct CrateDocsTool(CrateDocsToolInputs);
+
+#[async_trait]
+impl SubtaskTool for CrateDocsTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Network]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::crate_docs;
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use chrono::Utc;
+
+        let inputs = &self.0;
+        let docs = match crate_docs::lookup(
+            workspace_root,
+            &inputs.crate_name,
+            inputs.version.as_deref(),
+        )
+        .await
+        {
+            Ok(docs) => docs,
+            Err(e) => {
+                say!("⚠️ Failed to look up {}: {}", inputs.crate_name, e);
+                return None;
+            }
+        };
+        let content = docs.render(inputs.query.as_deref());
+        println!("{}", utils::truncate_string(&content, 2_000));
+
+        let memory_fragment = MemoryFragment {
+            source: "crate_docs".to_string(),
+            provenance: Provenance::Web,
+            content,
+            metadata: Some(MemoryMetadata {
+                file_type: Some("crate_docs".to_string()),
+                path: Some(docs.documentation.clone()),
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec![
+                    "crate_docs".to_string(),
+                    format!("crate:{}", docs.name),
+                    format!("version:{}", docs.version),
+                ],
+            }),
+        };
+
+        context_memory
+            .lock()
+            .await
+            .add_frag(memory_fragment.clone());
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct ErrorLookupToolInputs {
+    /// A rustc error code such as E0507, or a distinctive part of an error message
+    pub error: String,
+}
+
+pub struct ErrorLookupTool(ErrorLookupToolInputs);
+
+#[async_trait]
+impl SubtaskTool for ErrorLookupTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Network]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::error_lookup::{self, ErrorQuery};
+
+        let query = ErrorQuery::parse(&self.0.error);
+        let memory_fragment = match error_lookup::lookup(&query).await {
+            Ok(fragment) => fragment,
+            Err(e) => {
+                say!("⚠️ Failed to look up the error: {}", e);
+                return None;
+            }
+        };
+        say!(
+            "{}",
+            utils::truncate_string(&memory_fragment.content, 2_000)
+        );
+
+        context_memory
+            .lock()
+            .await
+            .add_frag(memory_fragment.clone());
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct CoverageToolInputs {
+    /// Least covered files to list uncovered functions for (default 5)
+    pub max_files: Option<usize>,
+}
+
+pub struct CoverageTool(CoverageToolInputs);
+
+#[async_trait]
+impl SubtaskTool for CoverageTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        // Runs the project's test suite
+        &[Capability::Exec]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::coverage;
+
+        let files = match coverage::measure(workspace_root).await {
+            Ok(files) => files,
+            Err(e) => {
+                say!("⚠️ Failed to measure coverage: {}", e);
+                return None;
+            }
+        };
+        let memory_fragment =
+            coverage::fragment(workspace_root, &files, self.0.max_files.unwrap_or(5));
+        say!("{}", memory_fragment.content);
+
+        context_memory
+            .lock()
+            .await
+            .add_frag(memory_fragment.clone());
+
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct TmuxPaneToolInputs {
+    /// Lines of output to read, counting back from the latest (default 200)
+    pub lines: Option<usize>,
+}
+
+pub struct TmuxPaneTool(TmuxPaneToolInputs);
+
+#[async_trait]
+impl SubtaskTool for TmuxPaneTool {
+    fn capabilities(&self) -> &'static [Capability] {
+        // Only reads what the user already sees; keys are never sent to the pane
+        &[Capability::ReadFs]
+    }
+
+    async fn handle_subtask(
+        &self,
+        _ai_client: &dyn AiClient,
+        context_memory: Arc<Mutex<ContextMemory>>,
+        _workspace_root: &Path,
+    ) -> Option<SubtaskOutput> {
+        use crate::memory::{MemoryFragment, MemoryMetadata};
+        use crate::scrollback::{self, Multiplexer};
+        use chrono::Utc;
 
-        // Generate explanation using the AI client
-        let explanation = match ai_client.generate_text(messages).await {
+        let Some(pane) = scrollback::watched() else {
+            say!("⚠️ No pane is watched; the user can share one with /watch <pane>");
+            return None;
+        };
+        let lines = self.0.lines.unwrap_or(scrollback::DEFAULT_CAPTURE_LINES);
+        let text = match scrollback::capture(Multiplexer::Tmux, lines, Some(&pane.id)).await {
             Ok(text) => text,
             Err(e) => {
-                println!(
-                    "{} {}: {}",
-                    "❌".red().bold(),
-                    "Error generating explanation".red().bold(),
-                    e
-                );
-                return Some(SubtaskOutput::SubtaskComplete());
+                say!("⚠️ Failed to read pane {}: {}", pane.id, e);
+                return None;
             }
         };
+        println!("{}", utils::truncate_string(&text, 2_000));
 
-        // Calculate and format elapsed time
-        let elapsed = start_time.elapsed();
-        let elapsed_secs = elapsed.as_secs_f32();
-        let timing_msg = format!("Generated in {:.2}s", elapsed_secs);
+        let memory_fragment = MemoryFragment {
+            source: format!("tmux:{}", pane.id),
+            provenance: Provenance::ToolOutput,
+            content: format!(
+                "Latest output of `{}` in tmux pane {}:\n{}",
+                pane.command,
+                pane.id,
+                tool_env::mask(&text)
+            ),
+            metadata: Some(MemoryMetadata {
+                file_type: Some("terminal".to_string()),
+                path: None,
+                timestamp: Some(Utc::now().timestamp()),
+                tags: vec!["scrollback".to_string(), format!("pane:{}", pane.id)],
+            }),
+        };
 
-        // Print the generated explanation
-        println!("\n{}\n", "=".repeat(80).cyan());
-        println!("{}\n", explanation);
-        println!("{}\n", "=".repeat(80).cyan());
-        println!("{}\n", timing_msg.dimmed());
+        context_memory
+            .lock()
+            .await
+            .add_frag(memory_fragment.clone());
 
-        // Return complete signal
-        Some(SubtaskOutput::SubtaskComplete())
+        Some(SubtaskOutput::AddToContextMemory(memory_fragment))
     }
 }
@@ -0,0 +1,360 @@
+//! Deterministic stand-ins for the model and the workspace, so tool and planner flows can be
+//! exercised without network access or touching the real project. Only built for tests and
+//! with the `testing` feature, which the integration tests enable as a dev-dependency.
+
+use crate::agent_engine::{AgentEngine, SharedState, SubtaskOutput};
+use crate::ai::openai::{GptFunctionCall, GptToolCall};
+use crate::ai::{ChatCompletionResponse, GenerationProfile, Message};
+use crate::approval::ApprovalMode;
+use crate::error::{AgentError, AgentResult};
+use crate::memory::ContextMemory;
+use crate::notifications::RunStats;
+use crate::profiling::{RunProfiler, SubtaskProfile};
+use crate::storage::Storage;
+use crate::subtasks::SubTaskType;
+use crate::{AiClient, Settings};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::Mutex;
+
+/// Dimensions of the embeddings the fake client returns
+pub const FAKE_EMBEDDING_DIMENSIONS: usize = 8;
+
+/// One recorded model reply: text, function calls, or both
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<RecordedCall>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+/// An `AiClient` that answers from a script, in order, and records every request.
+/// Clones share the script and the recorded requests.
+#[derive(Clone, Default)]
+pub struct FakeAiClient {
+    script: Arc<StdMutex<VecDeque<ChatCompletionResponse>>>,
+    requests: Arc<StdMutex<Vec<Vec<Message>>>>,
+    profiles: Arc<StdMutex<Vec<Option<GenerationProfile>>>>, // the profile of each request
+    profile: Option<GenerationProfile>,
+}
+
+impl FakeAiClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a plain text reply
+    pub fn text(self, content: impl Into<String>) -> Self {
+        self.respond(ChatCompletionResponse {
+            content: Some(content.into()),
+            tool_calls: None,
+            usage: None,
+        })
+    }
+
+    /// Queue a reply calling one function with `arguments`
+    pub fn tool_call(self, name: &str, arguments: serde_json::Value) -> Self {
+        self.tool_calls(vec![(name, arguments)])
+    }
+
+    /// Queue a reply calling several functions at once
+    pub fn tool_calls(self, calls: Vec<(&str, serde_json::Value)>) -> Self {
+        self.respond(ChatCompletionResponse {
+            content: None,
+            tool_calls: Some(
+                calls
+                    .into_iter()
+                    .map(|(name, arguments)| api_tool_call(name, &arguments))
+                    .collect(),
+            ),
+            usage: None,
+        })
+    }
+
+    /// Script the replies of a recorded transcript
+    pub fn from_transcript(responses: &[RecordedResponse]) -> Self {
+        responses.iter().fold(Self::new(), |client, response| {
+            client.respond(ChatCompletionResponse {
+                content: response.text.clone(),
+                tool_calls: (!response.tool_calls.is_empty()).then(|| {
+                    response
+                        .tool_calls
+                        .iter()
+                        .map(|call| api_tool_call(&call.name, &call.arguments))
+                        .collect()
+                }),
+                usage: None,
+            })
+        })
+    }
+
+    /// Queue a raw response
+    pub fn respond(self, response: ChatCompletionResponse) -> Self {
+        self.script.lock().unwrap().push_back(response);
+        self
+    }
+
+    /// Every request made so far, oldest first
+    pub fn requests(&self) -> Vec<Vec<Message>> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The generation profile each request was made with, oldest first
+    pub fn profiles(&self) -> Vec<Option<GenerationProfile>> {
+        self.profiles.lock().unwrap().clone()
+    }
+
+    /// Scripted responses not yet consumed
+    pub fn remaining(&self) -> usize {
+        self.script.lock().unwrap().len()
+    }
+
+    fn next(&self, messages: Vec<Message>) -> AgentResult<ChatCompletionResponse> {
+        self.requests.lock().unwrap().push(messages);
+        self.profiles.lock().unwrap().push(self.profile);
+        self.script.lock().unwrap().pop_front().ok_or_else(|| {
+            AgentError::AiApi("FakeAiClient has no scripted response left".to_string())
+        })
+    }
+}
+
+/// A function call as the API returns it, with the arguments as a JSON string
+fn api_tool_call(name: &str, arguments: &serde_json::Value) -> GptToolCall {
+    GptToolCall {
+        r#type: "function".to_string(),
+        function: GptFunctionCall {
+            name: name.to_string(),
+            arguments: serde_json::Value::String(arguments.to_string()),
+        },
+    }
+}
+
+#[async_trait]
+impl AiClient for FakeAiClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        Ok(self.next(messages)?.content.unwrap_or_default())
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        messages: Vec<Message>,
+        _functions: serde_json::Value,
+        _force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        self.next(messages)
+    }
+
+    /// Embeddings derived from the text's bytes, so equal texts embed equally
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        let mut embedding = vec![0.0; FAKE_EMBEDDING_DIMENSIONS];
+        for (i, byte) in text.bytes().enumerate() {
+            embedding[i % FAKE_EMBEDDING_DIMENSIONS] += byte as f32 / 255.0;
+        }
+        Ok(embedding)
+    }
+
+    fn provider_name(&self) -> String {
+        "fake".to_string()
+    }
+
+    fn model_name(&self) -> String {
+        "fake-model".to_string()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(self.clone())
+    }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self {
+            profile: Some(profile),
+            ..self.clone()
+        })
+    }
+}
+
+/// What a whole turn left behind
+pub struct TurnOutcome {
+    pub stats: RunStats,
+    pub steps: Vec<SubtaskProfile>, // executed subtasks, in order
+    pub context_memory: ContextMemory,
+}
+
+/// A scratch workspace directory, removed when dropped
+pub struct TempWorkspace {
+    root: PathBuf,
+}
+
+impl TempWorkspace {
+    pub fn new() -> AgentResult<Self> {
+        let root = std::env::temp_dir().join(format!("indiefuture-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root)?;
+        // Tools canonicalize paths, so compare against the canonical root
+        Ok(Self {
+            root: root.canonicalize()?,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Absolute path of `relative` inside the workspace
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.root.join(relative)
+    }
+
+    /// Write a file, creating parent directories as needed
+    pub fn write(&self, relative: &str, contents: &str) -> AgentResult<PathBuf> {
+        let path = self.path(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    pub fn read(&self, relative: &str) -> AgentResult<String> {
+        Ok(std::fs::read_to_string(self.path(relative))?)
+    }
+
+    /// Make the workspace a git repository with everything committed, like a real checkout
+    pub fn init_git(&self) -> AgentResult<()> {
+        for args in [
+            &["init", "--quiet"][..],
+            &["add", "--all"],
+            &[
+                "-c",
+                "user.name=Test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "--allow-empty",
+                "-m",
+                "Fixture",
+            ],
+        ] {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(&self.root)
+                .status()?;
+            if !status.success() {
+                return Err(AgentError::Unknown(format!(
+                    "git {} failed in the test workspace",
+                    args.join(" ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every file outside `.git`, by workspace-relative path with forward slashes
+    pub fn files(&self) -> AgentResult<BTreeMap<String, String>> {
+        let mut files = BTreeMap::new();
+        for entry in walkdir::WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git")
+        {
+            let entry = entry.map_err(|e| AgentError::Unknown(e.to_string()))?;
+            if entry.file_type().is_file() {
+                let relative = entry
+                    .path()
+                    .strip_prefix(&self.root)
+                    .unwrap_or(entry.path());
+                files.insert(
+                    relative.to_string_lossy().replace('\\', "/"),
+                    std::fs::read_to_string(entry.path())?,
+                );
+            }
+        }
+        Ok(files)
+    }
+
+    /// Session state for this workspace on `ai_client`, with temporary storage
+    pub fn shared_state(&self, ai_client: &FakeAiClient) -> AgentResult<SharedState> {
+        Ok(SharedState::new(
+            ai_client.clone_box(),
+            Storage::temporary("test")?,
+            &self.root,
+        ))
+    }
+
+    /// Run a whole turn for `task` in this workspace, the way the CLI does, in `auto` mode so
+    /// nothing waits for input
+    pub async fn run_turn(
+        &self,
+        task: &str,
+        ai_client: &FakeAiClient,
+        settings: Settings,
+    ) -> AgentResult<TurnOutcome> {
+        self.run_turn_with_memory(task, ai_client, settings, ContextMemory::default())
+            .await
+    }
+
+    /// Run a whole turn like `run_turn`, starting from `context_memory` instead of an empty one
+    pub async fn run_turn_with_memory(
+        &self,
+        task: &str,
+        ai_client: &FakeAiClient,
+        settings: Settings,
+        context_memory: ContextMemory,
+    ) -> AgentResult<TurnOutcome> {
+        let profiler = Arc::new(RunProfiler::new());
+        let shared_state = Arc::new(self.shared_state(ai_client)?);
+        let context_memory = Arc::new(Mutex::new(context_memory));
+
+        let mut engine = AgentEngine {
+            approval_mode: ApprovalMode::Auto,
+            profiler: Some(Arc::clone(&profiler)),
+            ..AgentEngine::default()
+        };
+        engine.push_subtask(SubTaskType::Task(task.to_string()));
+        let stats = engine
+            .handle_subtasks(
+                shared_state,
+                Arc::clone(&context_memory),
+                Arc::new(settings),
+            )
+            .await;
+
+        let context_memory = std::mem::take(&mut *context_memory.lock().await);
+        Ok(TurnOutcome {
+            stats,
+            steps: profiler.subtasks(),
+            context_memory,
+        })
+    }
+
+    /// Run one subtask's tool in this workspace with fresh context memory, returning its
+    /// output and the memory it left behind
+    pub async fn run(
+        &self,
+        subtask: SubTaskType,
+        ai_client: &dyn AiClient,
+    ) -> (Option<SubtaskOutput>, ContextMemory) {
+        let context_memory = Arc::new(Mutex::new(ContextMemory::default()));
+        let output = subtask
+            .get_tool()
+            .handle_subtask(ai_client, Arc::clone(&context_memory), &self.root)
+            .await;
+        let memory = std::mem::take(&mut *context_memory.lock().await);
+        (output, memory)
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::error::{AgentError, AgentResult};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::path::Path;
+
+/// Bytes checked for NUL when deciding whether a file is binary
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+/// How a text file is stored on disk, so edits can be written back the same way.
+/// The model only ever sees UTF-8 with `\n` line endings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFormat {
+    pub encoding: &'static Encoding,
+    pub line_ending: LineEnding,
+    pub bom: bool,
+}
+
+impl Default for TextFormat {
+    /// Format for files the agent creates
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            line_ending: LineEnding::Lf,
+            bom: false,
+        }
+    }
+}
+
+impl TextFormat {
+    /// Decode file bytes into `\n`-terminated text and the format they were stored in
+    pub fn decode(bytes: &[u8]) -> AgentResult<(String, TextFormat)> {
+        let (encoding, bom_len) = match Encoding::for_bom(bytes) {
+            Some((encoding, bom_len)) => (encoding, bom_len),
+            None => {
+                let sniffed = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES)];
+                if sniffed.contains(&0) {
+                    return Err(AgentError::ToolExecution(
+                        "binary file, refusing to treat it as text".to_string(),
+                    ));
+                }
+                let encoding = if std::str::from_utf8(bytes).is_ok() {
+                    UTF_8
+                } else {
+                    let mut detector = chardetng::EncodingDetector::new();
+                    detector.feed(bytes, true);
+                    detector.guess(None, true)
+                };
+                (encoding, 0)
+            }
+        };
+
+        let text = encoding
+            .decode_without_bom_handling_and_without_replacement(&bytes[bom_len..])
+            .ok_or_else(|| {
+                AgentError::ToolExecution(format!("not valid {} text", encoding.name()))
+            })?;
+
+        // Mixed files keep whatever endings they have; only consistent CRLF is converted
+        let crlf = text.matches("\r\n").count();
+        let line_ending = if crlf > 0 && crlf == text.matches('\n').count() {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+        let text = match line_ending {
+            LineEnding::CrLf => text.replace("\r\n", "\n"),
+            LineEnding::Lf => text.into_owned(),
+        };
+
+        Ok((
+            text,
+            TextFormat {
+                encoding,
+                line_ending,
+                bom: bom_len > 0,
+            },
+        ))
+    }
+
+    /// Encode `\n`-terminated text back into this format
+    pub fn encode(&self, text: &str) -> AgentResult<Vec<u8>> {
+        // The BOM is written below; text for a new file may carry its own
+        let text = if self.bom {
+            text.strip_prefix('\u{feff}').unwrap_or(text)
+        } else {
+            text
+        };
+        let text = match self.line_ending {
+            LineEnding::CrLf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+            LineEnding::Lf => text.to_string(),
+        };
+
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            // encoding_rs only decodes UTF-16, so encode it by hand
+            let little_endian = self.encoding == UTF_16LE;
+            for unit in std::iter::once(0xfeff)
+                .filter(|_| self.bom)
+                .chain(text.encode_utf16())
+            {
+                bytes.extend(if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                });
+            }
+            return Ok(bytes);
+        }
+
+        if self.bom && self.encoding == UTF_8 {
+            bytes.extend([0xef, 0xbb, 0xbf]);
+        }
+        let (encoded, _, unmappable) = self.encoding.encode(&text);
+        if unmappable {
+            return Err(AgentError::ToolExecution(format!(
+                "text contains characters that cannot be written as {}",
+                self.encoding.name()
+            )));
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+
+    /// Encode `text` in this format and write it to `path`
+    pub fn write(&self, path: &Path, text: &str) -> AgentResult<()> {
+        let bytes = self.encode(text).map_err(|e| with_path(path, e))?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+/// Read a text file as UTF-8 with `\n` line endings, along with its on-disk format.
+/// Binary files and undecodable text are errors naming the file.
+pub fn read_text(path: &Path) -> AgentResult<(String, TextFormat)> {
+    let bytes = std::fs::read(path)?;
+    TextFormat::decode(&bytes).map_err(|e| with_path(path, e))
+}
+
+fn with_path(path: &Path, error: AgentError) -> AgentError {
+    match error {
+        AgentError::ToolExecution(message) => {
+            AgentError::ToolExecution(format!("{}: {}", path.display(), message))
+        }
+        other => other,
+    }
+}
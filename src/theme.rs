@@ -0,0 +1,314 @@
+use crate::error::AgentError;
+use crate::output;
+use cliclack::ThemeState;
+use colored::{ColoredString, Colorize};
+use console::Style;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// Colors and symbols of diffs, explanations, prompts and status lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorTheme {
+    /// Colors for dark backgrounds
+    #[default]
+    Default,
+    /// Darker colors, without yellow or dimmed text, for light backgrounds
+    LightTerminal,
+    /// Bright, bold colors and no dimmed text
+    HighContrast,
+    /// The default colors without emoji
+    NoEmoji,
+}
+
+/// The theme in use; set at startup and with `/theme`
+static THEME: RwLock<ColorTheme> = RwLock::new(ColorTheme::Default);
+
+/// What a piece of text is, so each theme can pick its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Added,      // diff line added
+    Removed,    // diff line removed
+    DiffHeader, // file names and hunk ranges
+    Heading,
+    Code,
+    Accent, // list markers and step counters
+    Muted,  // secondary details such as timings
+    Success,
+    Warning,
+    Error,
+}
+
+impl ColorTheme {
+    pub const ALL: [ColorTheme; 4] = [
+        ColorTheme::Default,
+        ColorTheme::LightTerminal,
+        ColorTheme::HighContrast,
+        ColorTheme::NoEmoji,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Default => "colors for dark backgrounds",
+            Self::LightTerminal => "darker colors for light backgrounds",
+            Self::HighContrast => "bright, bold colors without dimmed text",
+            Self::NoEmoji => "default colors without emoji",
+        }
+    }
+
+    pub fn shows_emoji(&self) -> bool {
+        *self != Self::NoEmoji
+    }
+
+    pub fn paint(&self, text: &str, role: Role) -> ColoredString {
+        match (self, role) {
+            (Self::LightTerminal, Role::Added | Role::Success) => text.green(),
+            (Self::LightTerminal, Role::Removed | Role::Error) => text.red(),
+            (Self::LightTerminal, Role::DiffHeader | Role::Accent) => text.blue(),
+            (Self::LightTerminal, Role::Heading) => text.blue().bold(),
+            (Self::LightTerminal, Role::Code | Role::Warning) => text.magenta(),
+            (Self::LightTerminal, Role::Muted) => text.normal(),
+
+            (Self::HighContrast, Role::Added | Role::Success) => text.bright_green().bold(),
+            (Self::HighContrast, Role::Removed | Role::Error) => text.bright_red().bold(),
+            (Self::HighContrast, Role::DiffHeader | Role::Accent) => text.bright_cyan().bold(),
+            (Self::HighContrast, Role::Heading) => text.bright_white().bold().underline(),
+            (Self::HighContrast, Role::Code) => text.bright_yellow(),
+            (Self::HighContrast, Role::Warning) => text.bright_yellow().bold(),
+            (Self::HighContrast, Role::Muted) => text.normal(),
+
+            (_, Role::Added | Role::Success) => text.green(),
+            (_, Role::Removed) => text.red(),
+            (_, Role::Error) => text.red().bold(),
+            (_, Role::DiffHeader | Role::Accent) => text.cyan(),
+            (_, Role::Heading) => text.cyan().bold(),
+            (_, Role::Code | Role::Warning) => text.yellow(),
+            (_, Role::Muted) => text.dimmed(),
+        }
+    }
+}
+
+impl fmt::Display for ColorTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Default => "default",
+            Self::LightTerminal => "light-terminal",
+            Self::HighContrast => "high-contrast",
+            Self::NoEmoji => "no-emoji",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ColorTheme {
+    type Err = AgentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|theme| theme.to_string() == s.trim())
+            .ok_or_else(|| {
+                AgentError::Configuration(format!(
+                    "Unknown color theme '{}', expected one of: {}",
+                    s,
+                    Self::ALL
+                        .iter()
+                        .map(|theme| theme.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+/// Use `theme` for everything printed from now on. Plain output keeps its own prompts.
+pub fn configure(theme: ColorTheme) {
+    *THEME.write().unwrap_or_else(|e| e.into_inner()) = theme;
+    if !output::is_plain() {
+        cliclack::set_theme(PromptTheme(theme));
+    }
+}
+
+pub fn current() -> ColorTheme {
+    *THEME.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `text` in the current theme's color for `role`
+pub fn paint(text: &str, role: Role) -> String {
+    current().paint(text, role).to_string()
+}
+
+/// A unified diff with added, removed and header lines colored
+pub fn diff(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let role = if ["+++", "---", "@@"].iter().any(|p| line.starts_with(p)) {
+                Role::DiffHeader
+            } else if line.starts_with('+') {
+                Role::Added
+            } else if line.starts_with('-') {
+                Role::Removed
+            } else {
+                return line.to_string();
+            };
+            paint(line, role)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Markdown with headings, list markers, quotes and code colored; the markup itself is kept
+/// so the text reads the same without colors
+pub fn markdown(text: &str) -> String {
+    let mut lines = MarkdownLines::default();
+    text.lines()
+        .map(|line| lines.line(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Colors markdown one line at a time, as it streams in, remembering open code fences
+#[derive(Debug, Default)]
+pub struct MarkdownLines {
+    in_fence: bool,
+}
+
+impl MarkdownLines {
+    pub fn in_fence(&self) -> bool {
+        self.in_fence
+    }
+
+    pub fn line(&mut self, line: &str) -> String {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            self.in_fence = !self.in_fence;
+            return paint(line, Role::Muted);
+        }
+        if self.in_fence {
+            return paint(line, Role::Code);
+        }
+        if trimmed.starts_with('#') {
+            return paint(line, Role::Heading);
+        }
+        if trimmed.starts_with('>') {
+            return paint(line, Role::Muted);
+        }
+
+        let indent = &line[..line.len() - trimmed.len()];
+        match list_marker(trimmed) {
+            Some(marker) => format!(
+                "{}{}{}",
+                indent,
+                paint(marker, Role::Accent),
+                inline_code(&trimmed[marker.len()..])
+            ),
+            None => format!("{}{}", indent, inline_code(trimmed)),
+        }
+    }
+}
+
+/// `- `, `* ` or `1. ` at the start of a list item
+fn list_marker(line: &str) -> Option<&str> {
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return Some(&line[..2]);
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    (digits > 0 && line[digits..].starts_with(". ")).then(|| &line[..digits + 2])
+}
+
+/// Color `code` spans; an unclosed backtick leaves the rest of the line as it is
+fn inline_code(line: &str) -> String {
+    if line.matches('`').count() < 2 {
+        return line.to_string();
+    }
+    let mut styled = String::new();
+    let mut parts = line.split('`').peekable();
+    let mut code = false;
+    while let Some(part) = parts.next() {
+        if code && parts.peek().is_some() {
+            styled.push_str(&paint(&format!("`{}`", part), Role::Code));
+        } else if code {
+            // The last span has no closing backtick
+            styled.push('`');
+            styled.push_str(part);
+        } else {
+            styled.push_str(part);
+        }
+        code = !code;
+    }
+    styled
+}
+
+/// cliclack prompts and log lines in the colors of a theme
+struct PromptTheme(ColorTheme);
+
+impl cliclack::Theme for PromptTheme {
+    fn bar_color(&self, state: &ThemeState) -> Style {
+        match (self.0, state) {
+            (ColorTheme::LightTerminal, ThemeState::Active) => Style::new().blue(),
+            (ColorTheme::LightTerminal, ThemeState::Error(_)) => Style::new().magenta(),
+            (ColorTheme::LightTerminal, ThemeState::Submit) => Style::new().black(),
+            (ColorTheme::HighContrast, ThemeState::Active) => Style::new().cyan().bright().bold(),
+            (ColorTheme::HighContrast, ThemeState::Error(_)) => {
+                Style::new().yellow().bright().bold()
+            }
+            (ColorTheme::HighContrast, ThemeState::Submit) => Style::new().white().bright(),
+            (_, ThemeState::Active) => Style::new().cyan(),
+            (_, ThemeState::Cancel) => Style::new().red(),
+            (_, ThemeState::Submit) => Style::new().bright().black(),
+            (_, ThemeState::Error(_)) => Style::new().yellow(),
+        }
+    }
+
+    fn warning_symbol(&self) -> String {
+        let style = match self.0 {
+            ColorTheme::LightTerminal => Style::new().magenta(),
+            ColorTheme::HighContrast => Style::new().yellow().bright().bold(),
+            _ => Style::new().yellow(),
+        };
+        style.apply_to("▲").to_string()
+    }
+
+    fn input_style(&self, state: &ThemeState) -> Style {
+        match state {
+            ThemeState::Cancel => Style::new().dim().strikethrough(),
+            ThemeState::Submit if self.dims() => Style::new().dim(),
+            _ => Style::new(),
+        }
+    }
+
+    fn placeholder_style(&self, state: &ThemeState) -> Style {
+        match state {
+            ThemeState::Cancel => Style::new().hidden(),
+            _ if self.dims() => Style::new().dim(),
+            _ => Style::new().italic(),
+        }
+    }
+
+    fn format_note(&self, prompt: &str, message: &str) -> String {
+        self.format_note_generic(false, &self.text(prompt), &self.text(message))
+    }
+
+    fn format_outro_note(&self, prompt: &str, message: &str) -> String {
+        self.format_note_generic(true, &self.text(prompt), &self.text(message))
+    }
+
+    fn format_log(&self, text: &str, symbol: &str) -> String {
+        self.format_log_with_spacing(&self.text(text), symbol, true)
+    }
+}
+
+impl PromptTheme {
+    /// Dimmed text is hard to read on light backgrounds and in high contrast
+    fn dims(&self) -> bool {
+        matches!(self.0, ColorTheme::Default | ColorTheme::NoEmoji)
+    }
+
+    fn text(&self, text: &str) -> String {
+        if self.0.shows_emoji() {
+            text.to_string()
+        } else {
+            output::without_emoji(text)
+        }
+    }
+}
@@ -0,0 +1,113 @@
+/// Letters of a word that usually stay in one token
+const LETTERS_PER_TOKEN: usize = 6;
+
+/// Digits the tokenizer groups into one number token
+const DIGITS_PER_TOKEN: usize = 3;
+
+/// Punctuation such as `::`, `->` and `()` is mostly merged in pairs
+const SYMBOLS_PER_TOKEN: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Piece {
+    Letters,
+    Digits,
+    Symbols,
+    Space,
+}
+
+fn piece(c: char) -> Piece {
+    if c.is_alphabetic() {
+        Piece::Letters
+    } else if c.is_numeric() {
+        Piece::Digits
+    } else if c.is_whitespace() {
+        Piece::Space
+    } else {
+        Piece::Symbols
+    }
+}
+
+/// The estimated number of tokens in `text`. Text is cut into the pieces BPE tokenizers such
+/// as cl100k pre-split it into (words with their leading space, numbers, punctuation runs,
+/// whitespace) and each piece is costed by its length, so no vocabulary is needed.
+/// Estimates err on the high side.
+pub fn count(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(first) = chars.next() {
+        let kind = piece(first);
+        let mut ascii = usize::from(first.is_ascii());
+        let mut other = usize::from(!first.is_ascii());
+        while let Some(&next) = chars.peek() {
+            if piece(next) != kind {
+                break;
+            }
+            ascii += usize::from(next.is_ascii());
+            other += usize::from(!next.is_ascii());
+            chars.next();
+        }
+
+        let length = ascii + other;
+        tokens += match kind {
+            // Scripts outside ASCII are mostly split per character or byte pair
+            Piece::Letters => ascii.div_ceil(LETTERS_PER_TOKEN) + other,
+            Piece::Digits => length.div_ceil(DIGITS_PER_TOKEN),
+            Piece::Symbols => ascii.div_ceil(SYMBOLS_PER_TOKEN) + other,
+            // A single space joins the word after it; other runs are one token
+            Piece::Space if first == ' ' && length == 1 => 0,
+            Piece::Space => 1,
+        };
+    }
+    tokens
+}
+
+/// `text` cut to about `max_tokens`, keeping its start and end with a note of what was left
+/// out in between, at line breaks where there are any
+pub fn truncate(text: &str, max_tokens: usize) -> String {
+    let total = count(text);
+    if total <= max_tokens {
+        return text.to_string();
+    }
+
+    // Token density varies along a text, so shrink the kept share until it fits
+    let mut share = max_tokens as f64 / total as f64;
+    loop {
+        let keep = (text.len() as f64 * share) as usize;
+        let head = line_boundary(text, keep * 2 / 3, false);
+        let tail = line_boundary(text, text.len() - keep / 3, true).max(head);
+        let left_out = count(&text[head..tail]);
+        let truncated = format!(
+            "{}\n[... {} tokens left out ...]\n{}",
+            &text[..head],
+            left_out,
+            &text[tail..]
+        );
+        if count(&truncated) <= max_tokens || head == 0 {
+            return truncated;
+        }
+        share *= 0.9;
+    }
+}
+
+/// The char boundary at or before `index`, moved back to a line break within the last fifth
+/// of the way there. `after` moves forward to the next line instead.
+fn line_boundary(text: &str, index: usize, after: bool) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    let slack = if after { text.len() - index } else { index } / 5;
+    if after {
+        text[index..]
+            .find('\n')
+            .filter(|offset| *offset <= slack)
+            .map_or(index, |offset| index + offset + 1)
+    } else {
+        text[..index]
+            .rfind('\n')
+            .filter(|line| index - line <= slack)
+            .map_or(index, |line| line + 1)
+    }
+}
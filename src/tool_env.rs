@@ -0,0 +1,103 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+use tokio::process::Command;
+
+/// Values shorter than this are masked only when they are secrets, as masking short values
+/// such as `true` or `80` would garble file contents and output
+const MIN_MASKED_LEN: usize = 8;
+
+static TOOL_ENV: OnceLock<ToolEnv> = OnceLock::new();
+
+/// Environment variables injected into commands the agent runs, whose values must never
+/// appear in context fragments, logs or the journal
+#[derive(Debug, Clone, Default)]
+pub struct ToolEnv {
+    vars: BTreeMap<String, String>,
+    secrets: BTreeSet<String>, // names whose values are masked however short they are
+}
+
+impl ToolEnv {
+    pub fn new(vars: BTreeMap<String, String>) -> Self {
+        Self {
+            vars,
+            secrets: BTreeSet::new(),
+        }
+    }
+
+    /// Mark the variables named in `names` as secrets
+    pub fn with_secrets(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.secrets.extend(names);
+        self
+    }
+
+    /// Use `env` for every command this session; only the first call has an effect
+    pub fn configure(env: ToolEnv) {
+        let _ = TOOL_ENV.set(env);
+    }
+
+    pub fn current() -> &'static ToolEnv {
+        TOOL_ENV.get_or_init(ToolEnv::default)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.vars.keys().map(String::as_str)
+    }
+
+    pub fn apply(&self, command: &mut Command) {
+        command.envs(&self.vars);
+    }
+
+    /// Replace injected values in `text` with a placeholder naming their variable: secrets
+    /// and values of at least `MIN_MASKED_LEN` characters, where they stand as a whole token
+    pub fn mask(&self, text: &str) -> String {
+        // Longest first, so a value containing another is masked whole
+        let mut vars: Vec<(&String, &String)> = self
+            .vars
+            .iter()
+            .filter(|(name, value)| {
+                !value.is_empty() && (self.secrets.contains(*name) || value.len() >= MIN_MASKED_LEN)
+            })
+            .collect();
+        vars.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+
+        let mut masked = text.to_string();
+        for (name, value) in vars {
+            if masked.contains(value.as_str()) {
+                masked = replace_tokens(&masked, value, &format!("[masked {}]", name));
+            }
+        }
+        masked
+    }
+}
+
+/// Replace the occurrences of `value` in `text` that are not part of a longer word, so a
+/// value like `80` leaves `8080` alone
+fn replace_tokens(text: &str, value: &str, placeholder: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut replaced = String::with_capacity(text.len());
+    let mut rest = 0;
+
+    for (start, _) in text.match_indices(value) {
+        let end = start + value.len();
+        if start < rest {
+            continue;
+        }
+        let joined_before = text[..start].chars().next_back().is_some_and(is_word)
+            && value.chars().next().is_some_and(is_word);
+        let joined_after = text[end..].chars().next().is_some_and(is_word)
+            && value.chars().next_back().is_some_and(is_word);
+        if joined_before || joined_after {
+            continue;
+        }
+        replaced.push_str(&text[rest..start]);
+        replaced.push_str(placeholder);
+        rest = end;
+    }
+    replaced.push_str(&text[rest..]);
+    replaced
+}
+
+/// Mask injected values in `text` using the session's environment
+pub fn mask(text: &str) -> String {
+    ToolEnv::current().mask(text)
+}
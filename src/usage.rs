@@ -0,0 +1,294 @@
+use crate::ai::reasoning::ReasoningEffort;
+use crate::ai::{ChatCompletionResponse, GenerationProfile, Message, TextStream, TokenUsage};
+use crate::error::AgentResult;
+use crate::tokens;
+use crate::AiClient;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// List prices in USD per million prompt and completion tokens, by model name prefix. The
+/// longest matching prefix wins, so `gpt-4o-mini` is not priced as `gpt-4o`.
+const PRICES: [(&str, f64, f64); 24] = [
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-4", 30.00, 60.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o1", 15.00, 60.00),
+    ("o1-mini", 1.10, 4.40),
+    ("o3", 2.00, 8.00),
+    ("o3-mini", 1.10, 4.40),
+    ("o4-mini", 1.10, 4.40),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-3-opus", 15.00, 75.00),
+    ("claude-opus-4", 15.00, 75.00),
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.5-pro", 1.25, 10.00),
+];
+
+/// Prices in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+/// The price of `model` of `provider`, if known. Local models are free.
+pub fn price(provider: &str, model: &str) -> Option<ModelPrice> {
+    if provider == "ollama" {
+        return Some(ModelPrice {
+            prompt: 0.0,
+            completion: 0.0,
+        });
+    }
+    PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|&(_, prompt, completion)| ModelPrice { prompt, completion })
+}
+
+/// The requests made to one model and the tokens they took
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelUsage {
+    pub requests: usize,
+    pub estimated_requests: usize, // requests whose usage the provider did not report
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl ModelUsage {
+    /// The cost in USD at `price`
+    pub fn cost_usd(&self, price: ModelPrice) -> f64 {
+        (self.prompt_tokens as f64 * price.prompt
+            + self.completion_tokens as f64 * price.completion)
+            / 1_000_000.0
+    }
+}
+
+/// Adds up the tokens of a session's model requests, by `provider:model`
+#[derive(Default)]
+pub struct UsageTracker {
+    models: Mutex<BTreeMap<(String, String), ModelUsage>>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, provider: &str, model: &str, usage: TokenUsage, estimated: bool) {
+        let mut models = self.models.lock().expect("usage tracker poisoned");
+        let entry = models
+            .entry((provider.to_string(), model.to_string()))
+            .or_default();
+        entry.requests += 1;
+        entry.estimated_requests += usize::from(estimated);
+        entry.prompt_tokens += usage.prompt_tokens;
+        entry.completion_tokens += usage.completion_tokens;
+    }
+
+    /// Usage so far by provider and model
+    pub fn by_model(&self) -> BTreeMap<(String, String), ModelUsage> {
+        self.models.lock().expect("usage tracker poisoned").clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models
+            .lock()
+            .expect("usage tracker poisoned")
+            .is_empty()
+    }
+
+    /// The cost so far in USD, or `None` if a model that was used has no known price
+    pub fn cost_usd(&self) -> Option<f64> {
+        self.by_model()
+            .iter()
+            .map(|((provider, model), usage)| Some(usage.cost_usd(price(provider, model)?)))
+            .sum()
+    }
+
+    /// A table of the tokens and cost of each model, and their total
+    pub fn summary(&self) -> String {
+        let models = self.by_model();
+        if models.is_empty() {
+            return "No model requests yet".to_string();
+        }
+
+        let mut table = format!(
+            "{:<32} {:>8} {:>12} {:>12} {:>10}",
+            "model", "requests", "prompt", "completion", "cost"
+        );
+        for ((provider, model), usage) in &models {
+            let cost = match price(provider, model) {
+                Some(price) => format!("${:.4}", usage.cost_usd(price)),
+                None => "unknown".to_string(),
+            };
+            table.push_str(&format!(
+                "\n{:<32} {:>8} {:>12} {:>12} {:>10}",
+                crate::utils::truncate_string(&format!("{}:{}", provider, model), 32),
+                usage.requests,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                cost
+            ));
+        }
+
+        let total = match self.cost_usd() {
+            Some(cost) => format!("${:.4}", cost),
+            None => "unknown (a model has no known price)".to_string(),
+        };
+        table.push_str(&format!("\nTotal cost: {}", total));
+        let estimated: usize = models.values().map(|usage| usage.estimated_requests).sum();
+        if estimated > 0 {
+            table.push_str(&format!(
+                " ({} request(s) without reported usage were estimated)",
+                estimated
+            ));
+        }
+        table
+    }
+}
+
+/// The estimated usage of a request the provider did not report on
+fn estimate(messages: &[Message], answer: &str) -> TokenUsage {
+    TokenUsage {
+        prompt_tokens: messages
+            .iter()
+            .map(|message| tokens::count(&message.content) as u64)
+            .sum(),
+        completion_tokens: tokens::count(answer) as u64,
+    }
+}
+
+/// Wraps an AI client to add the tokens of its requests to a tracker. Usage the provider
+/// reports is used as is; text requests are estimated. Embeddings are not counted.
+pub struct UsageTrackingAiClient {
+    inner: Box<dyn AiClient>,
+    tracker: Arc<UsageTracker>,
+}
+
+impl UsageTrackingAiClient {
+    pub fn new(inner: Box<dyn AiClient>, tracker: Arc<UsageTracker>) -> Self {
+        Self { inner, tracker }
+    }
+
+    fn record(&self, usage: TokenUsage, estimated: bool) {
+        self.tracker.record(
+            &self.inner.provider_name(),
+            &self.inner.model_name(),
+            usage,
+            estimated,
+        );
+    }
+}
+
+#[async_trait]
+impl AiClient for UsageTrackingAiClient {
+    async fn generate_text(&self, messages: Vec<Message>) -> AgentResult<String> {
+        let prompt = messages.clone();
+        let text = self.inner.generate_text(messages).await?;
+        self.record(estimate(&prompt, &text), true);
+        Ok(text)
+    }
+
+    /// The answer is counted once it has fully arrived; a stream dropped early is not counted
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        let prompt = messages.clone();
+        let tokens = self.inner.stream_text(messages).await?;
+        let tracker = self.tracker.clone();
+        let model = (self.inner.provider_name(), self.inner.model_name());
+        Ok(stream::unfold(
+            (tokens, String::new(), Some((tracker, model, prompt))),
+            |(mut tokens, mut text, record)| async move {
+                match tokens.next().await {
+                    Some(Ok(token)) => {
+                        text.push_str(&token);
+                        Some((Ok(token), (tokens, text, record)))
+                    }
+                    Some(Err(e)) => Some((Err(e), (tokens, text, record))),
+                    None => {
+                        if let Some((tracker, (provider, model), prompt)) = record {
+                            tracker.record(&provider, &model, estimate(&prompt, &text), true);
+                        }
+                        None
+                    }
+                }
+            },
+        )
+        .boxed())
+    }
+
+    async fn chat_completion_with_functions(
+        &self,
+        messages: Vec<Message>,
+        functions: serde_json::Value,
+        force_message_only: bool,
+    ) -> AgentResult<ChatCompletionResponse> {
+        let prompt = messages.clone();
+        let response = self
+            .inner
+            .chat_completion_with_functions(messages, functions, force_message_only)
+            .await?;
+        match response.usage {
+            Some(usage) => self.record(usage, false),
+            None => {
+                let answer = format!(
+                    "{}{}",
+                    response.content.as_deref().unwrap_or_default(),
+                    serde_json::to_string(&response.tool_calls).unwrap_or_default()
+                );
+                self.record(estimate(&prompt, &answer), true);
+            }
+        }
+        Ok(response)
+    }
+
+    async fn generate_embeddings(&self, text: &str) -> AgentResult<Vec<f32>> {
+        self.inner.generate_embeddings(text).await
+    }
+
+    fn provider_name(&self) -> String {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn embedding_model(&self) -> String {
+        self.inner.embedding_model()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(Self {
+            inner: self.inner.clone_box(),
+            tracker: self.tracker.clone(),
+        })
+    }
+
+    fn with_profile(&self, profile: GenerationProfile) -> Box<dyn AiClient> {
+        Box::new(Self {
+            inner: self.inner.with_profile(profile),
+            tracker: self.tracker.clone(),
+        })
+    }
+
+    fn with_reasoning_effort(&self, effort: ReasoningEffort) -> Box<dyn AiClient> {
+        Box::new(Self {
+            inner: self.inner.with_reasoning_effort(effort),
+            tracker: self.tracker.clone(),
+        })
+    }
+}
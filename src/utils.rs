@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use uuid::Uuid;
 
 /// Generate a unique ID for tasks, entries, etc.
@@ -24,13 +24,90 @@ pub fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[0..max_len - 3])
+        let mut end = max_len.saturating_sub(3);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}...", &s[..end])
     }
 }
 
 /// Convert a file path to a string, handling error cases
 pub fn path_to_string(path: &std::path::Path) -> String {
-    path.to_string_lossy().to_string()
+    crate::platform::display_path(path)
+}
+
+/// Identify this checkout of a workspace, so separate clones of one repository keep their
+/// own journal, index and caches
+pub fn workspace_id(root: &std::path::Path) -> String {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    hashed_id(&path_to_string(&root))
+}
+
+/// Identify a project independently of where it is checked out, so teammates share what
+/// is known about it. Derived from the git origin's host and path, or the checkout's id when
+/// there is no origin.
+pub fn project_id(root: &std::path::Path) -> String {
+    let origin = std::process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["config", "--get", "remote.origin.url"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|url| !url.is_empty());
+
+    match origin {
+        Some(url) => hashed_id(&remote_path(&url)),
+        None => workspace_id(root),
+    }
+}
+
+/// `url` without a `user:token@` part, which must not end up in storage names
+pub fn without_credentials(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let host_end = rest.find('/').unwrap_or(rest.len());
+    let rest = match rest[..host_end].rfind('@') {
+        Some(at) => &rest[at + 1..],
+        None => rest,
+    };
+    format!("{}://{}", scheme, rest)
+}
+
+/// `host/path` of a git remote, so the SSH and HTTPS forms of a URL, with or without
+/// credentials or a trailing `.git`, name the same repository
+pub fn remote_path(url: &str) -> String {
+    let url = without_credentials(url.trim());
+    let location = match url.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        // scp-like `git@host:org/repo`
+        None => url
+            .split_once(':')
+            .map(|(host, path)| format!("{}/{}", host, path))
+            .unwrap_or(url.clone()),
+    };
+    let location = location
+        .rsplit_once('@')
+        .map_or(location.as_str(), |(_, rest)| rest);
+
+    let (host, path) = location.split_once('/').unwrap_or((location, ""));
+    let host = host.split(':').next().unwrap_or(host).to_lowercase(); // drop any port
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    format!("{}/{}", host, path)
+}
+
+/// Short hex digest of `text`, usable in storage names
+fn hashed_id(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(text.as_bytes())[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
 }
 
 /// Check if a string is likely to be binary content
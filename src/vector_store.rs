@@ -0,0 +1,56 @@
+use crate::embeddings::cosine_similarity;
+use std::collections::HashMap;
+
+/// Embedding vectors by key, searched exhaustively. It holds context memory, at most a few
+/// hundred fragments, where an exact scan is as fast as an approximate index and never
+/// misses a neighbour.
+#[derive(Debug, Clone, Default)]
+pub struct VectorStore {
+    embedding_model: String, // vectors of one model only; another model's mean nothing here
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl VectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep vectors of `embedding_model` from now on, dropping any of another model
+    pub fn use_model(&mut self, embedding_model: &str) {
+        if self.embedding_model != embedding_model {
+            self.vectors.clear();
+            self.embedding_model = embedding_model.to_string();
+        }
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, vector: Vec<f32>) {
+        self.vectors.insert(key.into(), vector);
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.vectors.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Drop the vectors whose key `keep` rejects
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.vectors.retain(|key, _| keep(key));
+    }
+
+    pub fn clear(&mut self) {
+        self.vectors.clear();
+    }
+
+    /// Cosine similarity of the vector under `key` to `query`, if there is one of its length
+    pub fn similarity(&self, key: &str, query: &[f32]) -> Option<f32> {
+        let vector = self.vectors.get(key)?;
+        (vector.len() == query.len()).then(|| cosine_similarity(vector, query))
+    }
+}
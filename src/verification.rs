@@ -0,0 +1,81 @@
+use crate::error::{AgentError, AgentResult};
+use crate::platform;
+use crate::tool_env;
+use std::path::Path;
+
+/// Characters of failure output handed to the fix loop
+const MAX_FAILURE_CHARS: usize = 6000;
+
+/// Command that checks the workspace still builds and passes its tests
+#[derive(Debug, Clone)]
+pub struct VerifyCommand {
+    pub toolchain: &'static str,
+    pub command: String,
+}
+
+/// Result of running the verification command
+#[derive(Debug, Clone)]
+pub struct VerifyOutcome {
+    pub success: bool,
+    pub output: String,
+}
+
+/// Pick a compile/typecheck/test command from the project files at `root`.
+/// `configured` overrides detection.
+pub fn detect(root: &Path, configured: Option<&str>) -> Option<VerifyCommand> {
+    if let Some(command) = configured {
+        return Some(VerifyCommand {
+            toolchain: "custom",
+            command: command.to_string(),
+        });
+    }
+
+    let has = |name: &str| root.join(name).is_file();
+    let (toolchain, command) = if has("Cargo.toml") {
+        ("cargo", "cargo check --all-targets && cargo test --quiet")
+    } else if has("tsconfig.json") {
+        (
+            "typescript",
+            "npx tsc --noEmit && npm test --if-present --silent",
+        )
+    } else if has("package.json") {
+        ("npm", "npm test --if-present --silent")
+    } else if has("go.mod") {
+        ("go", "go build ./... && go test ./...")
+    } else if has("pyproject.toml") || has("setup.py") {
+        ("python", "python -m compileall -q . && python -m pytest -q")
+    } else {
+        return None;
+    };
+
+    Some(VerifyCommand {
+        toolchain,
+        command: command.to_string(),
+    })
+}
+
+/// Run the verification command in `root`, keeping the tail of its output on failure
+pub async fn run(root: &Path, verify: &VerifyCommand) -> AgentResult<VerifyOutcome> {
+    let output = platform::shell_command(&verify.command)
+        .current_dir(root)
+        .output()
+        .await
+        .map_err(|e| {
+            AgentError::TaskExecution(format!("Failed to run '{}': {}", verify.command, e))
+        })?;
+
+    let combined = tool_env::mask(&format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ));
+
+    // Errors usually sit at the end of the output
+    let skip = combined.chars().count().saturating_sub(MAX_FAILURE_CHARS);
+    let output_tail: String = combined.chars().skip(skip).collect();
+
+    Ok(VerifyOutcome {
+        success: output.status.success(),
+        output: output_tail,
+    })
+}
@@ -0,0 +1,216 @@
+use crate::config::Settings;
+use crate::error::{AgentError, AgentResult};
+use crate::platform;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+/// Placeholder for the audio file in recorder and transcriber commands
+pub const FILE_PLACEHOLDER: &str = "{file}";
+const TRANSCRIPTIONS_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
+
+/// Recorders tried in order when none is configured, each writing 16 kHz mono WAV for at
+/// most two minutes
+const RECORDERS: &[&str] = &[
+    "rec -q -c 1 -r 16000 {file} trim 0 120",
+    "arecord -q -f S16_LE -c 1 -r 16000 -d 120 {file}",
+    "ffmpeg -loglevel error -f avfoundation -i :0 -ac 1 -ar 16000 -t 120 {file}",
+];
+
+/// Split a command template into its program and arguments, with `{file}` replaced by
+/// `file`. Arguments are separated by whitespace; there is no shell quoting.
+pub fn command_args(template: &str, file: &Path) -> Vec<String> {
+    let file = file.to_string_lossy();
+    template
+        .split_whitespace()
+        .map(|arg| arg.replace(FILE_PLACEHOLDER, &file))
+        .collect()
+}
+
+/// The configured recorder, or the first of sox, arecord and ffmpeg (macOS) installed
+fn recorder(settings: &Settings) -> AgentResult<String> {
+    if let Some(command) = &settings.voice_record_command {
+        return Ok(command.clone());
+    }
+    RECORDERS
+        .iter()
+        .find(|template| {
+            let program = template.split_whitespace().next().unwrap_or_default();
+            let usable = program != "ffmpeg" || cfg!(target_os = "macos");
+            usable && platform::on_path(program)
+        })
+        .map(|template| template.to_string())
+        .ok_or_else(|| {
+            AgentError::Cli(
+                "No audio recorder found; install sox or set VOICE_RECORD_COMMAND".to_string(),
+            )
+        })
+}
+
+/// Record from the microphone until the user presses Enter, then transcribe the recording
+pub async fn listen(settings: &Settings) -> AgentResult<String> {
+    let recorder = recorder(settings)?;
+    let file_name = format!("indiefuture-voice-{}.wav", uuid::Uuid::new_v4());
+    let file = std::env::temp_dir().join(file_name);
+
+    let result = async {
+        record(&recorder, &file).await?;
+        transcribe(settings, &file).await
+    }
+    .await;
+    let _ = std::fs::remove_file(&file);
+    result
+}
+
+async fn record(recorder: &str, file: &Path) -> AgentResult<()> {
+    let args = command_args(recorder, file);
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| AgentError::Configuration("VOICE_RECORD_COMMAND is empty".to_string()))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| AgentError::Cli(format!("Failed to start {}: {}", program, e)))?;
+
+    cliclack::log::info("🎙️ Recording... press Enter to stop")?;
+    tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)
+    })
+    .await
+    .map_err(|e| AgentError::Cli(format!("Failed to wait for Enter: {}", e)))??;
+    stop(&mut child).await;
+
+    if !file.is_file() {
+        return Err(AgentError::Cli(format!(
+            "{} recorded nothing; check the microphone",
+            program
+        )));
+    }
+    Ok(())
+}
+
+/// Stop a recorder so it finishes the file; recorders write the WAV header on SIGINT, but
+/// not when killed
+async fn stop(child: &mut Child) {
+    if let Ok(Some(_)) = child.try_wait() {
+        return;
+    }
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        let _ = Command::new("kill")
+            .args(["-INT", &pid.to_string()])
+            .status()
+            .await;
+        if tokio::time::timeout(Duration::from_secs(3), child.wait())
+            .await
+            .is_ok()
+        {
+            return;
+        }
+    }
+    let _ = child.kill().await;
+}
+
+/// Text of the recording, from the local whisper command if one is configured, otherwise
+/// from the OpenAI transcription API
+pub async fn transcribe(settings: &Settings, file: &Path) -> AgentResult<String> {
+    let text = match &settings.voice_transcribe_command {
+        Some(command) => transcribe_locally(command, file).await?,
+        None => transcribe_with_api(settings, file).await?,
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return Err(AgentError::Cli(
+            "Nothing was heard in the recording".to_string(),
+        ));
+    }
+    Ok(text)
+}
+
+async fn transcribe_locally(command: &str, file: &Path) -> AgentResult<String> {
+    let args = command_args(command, file);
+    let (program, args) = args.split_first().ok_or_else(|| {
+        AgentError::Configuration("VOICE_TRANSCRIBE_COMMAND is empty".to_string())
+    })?;
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| AgentError::Cli(format!("Failed to run {}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(AgentError::Cli(format!(
+            "{} could not transcribe the recording: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+async fn transcribe_with_api(settings: &Settings, file: &Path) -> AgentResult<String> {
+    let api_key = settings.openai_api_key.as_deref().ok_or_else(|| {
+        AgentError::Configuration(
+            "Voice input needs OPENAI_API_KEY, or a local VOICE_TRANSCRIBE_COMMAND".to_string(),
+        )
+    })?;
+    let audio = std::fs::read(file)?;
+
+    let mut fields = vec![("model", settings.voice_model.as_str())];
+    if let Some(language) = &settings.voice_language {
+        fields.push(("language", language.as_str()));
+    }
+    let boundary = format!("indiefuture-{}", uuid::Uuid::new_v4().simple());
+    let body = multipart_body(&boundary, &fields, "speech.wav", &audio);
+
+    let response: serde_json::Value = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()?
+        .post(TRANSCRIPTIONS_URL)
+        .bearer_auth(api_key)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    response["text"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AgentError::Cli("The transcription response has no text".to_string()))
+}
+
+/// A multipart/form-data body with text `fields` and the WAV `audio` as the `file` field
+pub fn multipart_body(
+    boundary: &str,
+    fields: &[(&str, &str)],
+    file_name: &str,
+    audio: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(audio.len() + 512);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+            Content-Type: audio/wav\r\n\r\n",
+            boundary, file_name
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(audio);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
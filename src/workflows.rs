@@ -0,0 +1,348 @@
+use crate::agent_engine::{AgentEngine, SharedState};
+use crate::ai::openai::{GptFunctionCall, GptToolCall};
+use crate::ai::AiClient;
+use crate::config::Settings;
+use crate::error::{AgentError, AgentResult};
+use crate::memory::ContextMemory;
+use crate::notifications::{self, RunStats, RunSummary};
+use crate::platform;
+use crate::subtasks::subtask::BashToolInputs;
+use crate::subtasks::SubTaskType;
+use crate::tool_env;
+use cliclack::confirm;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+/// Directory, relative to the workspace root, holding workflow definitions
+pub const WORKFLOWS_DIR: &str = "workflows";
+
+/// A parameterized sequence of steps loaded from `workflows/<name>.yaml`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Workflow {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub params: Vec<WorkflowParam>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+/// A named input, passed on the command line as `--name value`
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkflowParam {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// One step: either a tool invocation with arguments or a prompt handed to the planner
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkflowStep {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub tool: Option<String>,
+    #[serde(default)]
+    pub args: HashMap<String, Value>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub gate: Option<WorkflowGate>,
+}
+
+/// A check that must pass before a step runs; a failed gate stops the workflow
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkflowGate {
+    #[serde(default)]
+    pub confirm: Option<String>, // ask the user before continuing
+    #[serde(default)]
+    pub command: Option<String>, // shell command that must exit successfully
+}
+
+impl Workflow {
+    /// Load `workflows/<name>.yaml` (or `.yml`) from the workspace
+    pub fn load(workspace_root: &Path, name: &str) -> AgentResult<Self> {
+        let dir = workspace_root.join(WORKFLOWS_DIR);
+
+        let path = ["yaml", "yml"]
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name, ext)))
+            .find(|path| path.exists())
+            .ok_or_else(|| {
+                AgentError::Workflow(format!("No workflow named '{}' in {:?}", name, dir))
+            })?;
+
+        Self::from_file(&path)
+    }
+
+    pub fn from_file(path: &Path) -> AgentResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+            .map_err(|e| AgentError::Workflow(format!("Invalid workflow {:?}: {}", path, e)))
+    }
+
+    pub fn parse(contents: &str) -> AgentResult<Self> {
+        let workflow: Workflow =
+            serde_yaml::from_str(contents).map_err(|e| AgentError::Workflow(e.to_string()))?;
+
+        for (index, step) in workflow.steps.iter().enumerate() {
+            if step.tool.is_none() && step.prompt.is_none() {
+                return Err(AgentError::Workflow(format!(
+                    "Step {} needs either a `tool` or a `prompt`",
+                    index + 1
+                )));
+            }
+        }
+
+        Ok(workflow)
+    }
+
+    /// Fill in defaults and check that every parameter has a value
+    pub fn resolve_params(
+        &self,
+        provided: &HashMap<String, String>,
+    ) -> AgentResult<HashMap<String, String>> {
+        let mut resolved = HashMap::new();
+
+        for param in &self.params {
+            let value = provided
+                .get(&param.name)
+                .cloned()
+                .or_else(|| param.default.clone())
+                .ok_or_else(|| {
+                    AgentError::Workflow(format!(
+                        "Missing parameter --{}{}",
+                        param.name,
+                        param
+                            .description
+                            .as_ref()
+                            .map(|d| format!(" ({})", d))
+                            .unwrap_or_default()
+                    ))
+                })?;
+            resolved.insert(param.name.clone(), value);
+        }
+
+        for name in provided.keys() {
+            if !resolved.contains_key(name) {
+                return Err(AgentError::Workflow(format!(
+                    "Unknown parameter --{} for workflow '{}'",
+                    name, self.name
+                )));
+            }
+        }
+
+        Ok(resolved)
+    }
+}
+
+impl WorkflowStep {
+    /// Build the subtask for this step with `{{param}}` placeholders filled in
+    pub fn to_subtask(&self, params: &HashMap<String, String>) -> AgentResult<SubTaskType> {
+        if let Some(tool) = &self.tool {
+            let args: serde_json::Map<String, Value> = self
+                .args
+                .iter()
+                .map(|(key, value)| (key.clone(), render_value(value, params)))
+                .collect();
+
+            let tool_call = GptToolCall {
+                r#type: "function".to_string(),
+                function: GptFunctionCall {
+                    name: tool.clone(),
+                    arguments: Value::String(Value::Object(args).to_string()),
+                },
+            };
+
+            return SubTaskType::from_tool_call(tool_call).ok_or_else(|| {
+                AgentError::Workflow(format!("Invalid arguments for tool {}", tool))
+            });
+        }
+
+        let prompt = self.prompt.as_deref().unwrap_or_default();
+        Ok(SubTaskType::Task(render_template(prompt, params)))
+    }
+
+    fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .or_else(|| self.tool.clone())
+            .unwrap_or_else(|| format!("step {}", index + 1))
+    }
+}
+
+impl WorkflowGate {
+    /// Returns whether the workflow may continue past this gate. The command comes from the
+    /// repository, so it is approved like any shell command the agent runs.
+    pub async fn check(
+        &self,
+        params: &HashMap<String, String>,
+        engine: &AgentEngine,
+        ai_client: &dyn AiClient,
+    ) -> AgentResult<bool> {
+        if let Some(command) = &self.command {
+            let command = render_template(command, params);
+            let subtask = SubTaskType::Bash(BashToolInputs {
+                command: command.clone(),
+                interactive: false,
+            });
+            // A gate runs before its step, so it cannot wait for the batched plan approval
+            let ask = engine.approval_mode.requires_confirmation(&subtask)
+                || engine.approval_mode.defers(&subtask);
+            if ask
+                && engine
+                    .ask_user_confirmation(subtask, ai_client)
+                    .await
+                    .is_none()
+            {
+                cliclack::log::warning(format!(
+                    "Gate command declined: {}",
+                    tool_env::mask(&command)
+                ))?;
+                return Ok(false);
+            }
+
+            let status = platform::shell_command(&command).status().await?;
+
+            if !status.success() {
+                cliclack::log::warning(format!(
+                    "Gate command failed: {}",
+                    tool_env::mask(&command)
+                ))?;
+                return Ok(false);
+            }
+        }
+
+        if let Some(message) = &self.confirm {
+            return Ok(confirm(render_template(message, params))
+                .initial_value(true)
+                .interact()
+                .unwrap_or(false));
+        }
+
+        Ok(true)
+    }
+}
+
+/// Run a workflow step by step, draining the engine's subtask stack after each one
+pub async fn run_workflow(
+    workflow: &Workflow,
+    provided_params: &HashMap<String, String>,
+    shared_state: Arc<SharedState>,
+    context_memory: Arc<Mutex<ContextMemory>>,
+    settings: Arc<Settings>,
+    agent_engine: Arc<Mutex<AgentEngine>>,
+) -> AgentResult<()> {
+    let params = workflow.resolve_params(provided_params)?;
+    let started = Instant::now();
+    let mut stats = RunStats::default();
+
+    cliclack::log::info(format!(
+        "▶ Running workflow {}{}",
+        workflow.name,
+        workflow
+            .description
+            .as_ref()
+            .map(|d| format!(": {}", d))
+            .unwrap_or_default()
+    ))?;
+
+    for (index, step) in workflow.steps.iter().enumerate() {
+        let label = step.label(index);
+
+        if let Some(gate) = &step.gate {
+            let passed = {
+                let engine = agent_engine.lock().await;
+                gate.check(&params, &engine, shared_state.ai_client.as_ref())
+                    .await?
+            };
+            if !passed {
+                stats.declined = true;
+                notify_workflow(workflow, &stats, started, &shared_state, &settings).await;
+                return Err(AgentError::Workflow(format!(
+                    "Stopped at gate before '{}'",
+                    label
+                )));
+            }
+        }
+
+        cliclack::log::step(format!(
+            "[{}/{}] {}",
+            index + 1,
+            workflow.steps.len(),
+            label
+        ))?;
+
+        let subtask = step.to_subtask(&params)?;
+
+        let mut engine = agent_engine.lock().await;
+        engine.push_subtask(subtask);
+        stats.merge(
+            engine
+                .handle_subtasks(
+                    Arc::clone(&shared_state),
+                    Arc::clone(&context_memory),
+                    Arc::clone(&settings),
+                )
+                .await,
+        );
+    }
+
+    notify_workflow(workflow, &stats, started, &shared_state, &settings).await;
+
+    cliclack::log::success(format!("Workflow {} finished", workflow.name))?;
+    Ok(())
+}
+
+async fn notify_workflow(
+    workflow: &Workflow,
+    stats: &RunStats,
+    started: Instant,
+    shared_state: &SharedState,
+    settings: &Settings,
+) {
+    notifications::notify_run(
+        settings,
+        RunSummary::new(
+            &format!("workflow {}", workflow.name),
+            stats,
+            started,
+            shared_state.usage.cost_usd(),
+            &shared_state.session_id,
+            shared_state.storage.user(),
+        ),
+    )
+    .await;
+}
+
+/// Replace `{{name}}` placeholders with parameter values
+pub fn render_template(template: &str, params: &HashMap<String, String>) -> String {
+    params
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{{{}}}}}", name), value)
+                .replace(&format!("{{{{ {} }}}}", name), value)
+        })
+}
+
+fn render_value(value: &Value, params: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(s) => Value::String(render_template(s, params)),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|v| render_value(v, params)).collect())
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), render_value(v, params)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,197 @@
+use crate::error::{AgentError, AgentResult};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What a running session records about itself in its lockfile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub pid: u32,
+    pub user: String,
+    pub started_at: String,
+    #[serde(default)]
+    pub touched_files: Vec<String>,
+}
+
+impl SessionInfo {
+    pub fn short_id(&self) -> &str {
+        &self.session_id[..8.min(self.session_id.len())]
+    }
+}
+
+/// A file this session edited that another live session has also edited
+#[derive(Debug, Clone)]
+pub struct FileOverlap {
+    pub file: String,
+    pub other: SessionInfo,
+}
+
+/// Marks this session as active in a workspace so concurrent sessions notice each other.
+/// Each session owns one lockfile; the file is removed when the lock is dropped.
+pub struct WorkspaceLock {
+    dir: PathBuf,
+    info: Mutex<SessionInfo>,
+}
+
+impl WorkspaceLock {
+    /// Register this session for `workspace_root`.
+    /// Fails if another live session holds the workspace, unless `force` is set.
+    pub fn acquire(
+        locks_root: &Path,
+        workspace_root: &Path,
+        session_id: &str,
+        user: &str,
+        force: bool,
+    ) -> AgentResult<Self> {
+        let dir = locks_root.join(lock_dir_name(workspace_root));
+        std::fs::create_dir_all(&dir)?;
+
+        let lock = Self {
+            dir,
+            info: Mutex::new(SessionInfo {
+                session_id: session_id.to_string(),
+                pid: std::process::id(),
+                user: user.to_string(),
+                started_at: utils::current_timestamp(),
+                touched_files: Vec::new(),
+            }),
+        };
+
+        let others = lock.other_sessions();
+        if !others.is_empty() && !force {
+            let listing = others
+                .iter()
+                .map(|s| {
+                    format!(
+                        "session {} (pid {}, {}) since {}",
+                        s.short_id(),
+                        s.pid,
+                        s.user,
+                        s.started_at
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            return Err(AgentError::WorkingContext(format!(
+                "Another agent session is active in this workspace: {}. Use --force to start anyway",
+                listing
+            )));
+        }
+
+        lock.write()?;
+        Ok(lock)
+    }
+
+    pub fn session_id(&self) -> String {
+        self.info().session_id
+    }
+
+    /// Other live sessions in this workspace; lockfiles of dead processes are cleaned up
+    pub fn other_sessions(&self) -> Vec<SessionInfo> {
+        let own_id = self.session_id();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "lock"))
+            .filter_map(|path| {
+                let info: SessionInfo =
+                    serde_json::from_str(&std::fs::read_to_string(&path).ok()?).ok()?;
+
+                if info.session_id == own_id {
+                    return None;
+                }
+                if !process_alive(info.pid) {
+                    let _ = std::fs::remove_file(&path);
+                    return None;
+                }
+                Some(info)
+            })
+            .collect()
+    }
+
+    /// Record files this session modified and report any another live session also modified
+    pub fn record_touched(&self, files: &[String]) -> AgentResult<Vec<FileOverlap>> {
+        {
+            let mut info = self.info.lock().expect("workspace lock poisoned");
+            for file in files {
+                if !info.touched_files.contains(file) {
+                    info.touched_files.push(file.clone());
+                }
+            }
+        }
+        self.write()?;
+
+        Ok(self
+            .other_sessions()
+            .into_iter()
+            .flat_map(|other| {
+                files
+                    .iter()
+                    .filter(|file| other.touched_files.contains(file))
+                    .map(|file| FileOverlap {
+                        file: file.clone(),
+                        other: other.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+
+    fn info(&self) -> SessionInfo {
+        self.info.lock().expect("workspace lock poisoned").clone()
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(format!("{}.lock", self.session_id()))
+    }
+
+    fn write(&self) -> AgentResult<()> {
+        let json = serde_json::to_string_pretty(&self.info())?;
+        std::fs::write(self.path(), json)?;
+        Ok(())
+    }
+}
+
+impl Drop for WorkspaceLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(self.path());
+    }
+}
+
+/// One directory per workspace, named after its absolute path
+fn lock_dir_name(workspace_root: &Path) -> String {
+    let root = workspace_root
+        .canonicalize()
+        .unwrap_or_else(|_| workspace_root.to_path_buf());
+
+    utils::path_to_string(&root)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(true)
+}
+
+#[cfg(not(unix))]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(true)
+}
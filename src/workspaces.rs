@@ -0,0 +1,161 @@
+use crate::error::{AgentError, AgentResult};
+use crate::memory::{MemoryFragment, Provenance};
+use crate::platform;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Source of the pinned fragment listing the workspaces added to the session
+pub const WORKSPACES_SOURCE: &str = "workspaces";
+
+/// A repository added to the session next to the one it started in. Its files are named
+/// `name:path`, and it can only be edited once edits are allowed for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    pub name: String,
+    pub root: PathBuf, // canonical
+    pub editable: bool,
+}
+
+/// Workspaces added with `/workspace add`; the session's own workspace is not listed
+static ADDED: RwLock<Vec<Workspace>> = RwLock::new(Vec::new());
+
+/// Add the directory at `path`, relative to `workspace_root`, named after its last component
+pub fn add(workspace_root: &Path, path: &Path, editable: bool) -> AgentResult<Workspace> {
+    let root = workspace_root
+        .join(path)
+        .canonicalize()
+        .map_err(|e| AgentError::Cli(format!("Cannot add {}: {}", path.display(), e)))?;
+    if !root.is_dir() {
+        return Err(AgentError::Cli(format!(
+            "Cannot add {}: not a directory",
+            path.display()
+        )));
+    }
+    let primary = workspace_root.canonicalize()?;
+    if root.starts_with(&primary) || primary.starts_with(&root) {
+        return Err(AgentError::Cli(format!(
+            "{} overlaps the current workspace",
+            root.display()
+        )));
+    }
+
+    let mut added = ADDED.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(existing) = added.iter().find(|w| w.root == root) {
+        return Err(AgentError::Cli(format!(
+            "{} is already added as {}",
+            root.display(),
+            existing.name
+        )));
+    }
+
+    // Two repositories with the same directory name get numbered names
+    let base = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "workspace".to_string());
+    let mut name = base.clone();
+    let mut suffix = 2;
+    while added.iter().any(|w| w.name == name) {
+        name = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+
+    let workspace = Workspace {
+        name,
+        root,
+        editable,
+    };
+    added.push(workspace.clone());
+    Ok(workspace)
+}
+
+/// Remove the workspace called `name`
+pub fn remove(name: &str) -> Option<Workspace> {
+    let mut added = ADDED.write().unwrap_or_else(|e| e.into_inner());
+    let index = added.iter().position(|w| w.name == name)?;
+    Some(added.remove(index))
+}
+
+/// Allow or forbid edits in the workspace called `name`
+pub fn set_editable(name: &str, editable: bool) -> Option<Workspace> {
+    let mut added = ADDED.write().unwrap_or_else(|e| e.into_inner());
+    let workspace = added.iter_mut().find(|w| w.name == name)?;
+    workspace.editable = editable;
+    Some(workspace.clone())
+}
+
+pub fn list() -> Vec<Workspace> {
+    ADDED.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The added workspace `path` belongs to, and `path` within it. Paths are either qualified as
+/// `name:path` or absolute paths under the workspace's root.
+pub fn find(path: &Path) -> Option<(Workspace, PathBuf)> {
+    let added = ADDED.read().unwrap_or_else(|e| e.into_inner());
+    let text = path.to_string_lossy();
+    if let Some((name, rest)) = text.split_once(':') {
+        if let Some(workspace) = added.iter().find(|w| w.name == name) {
+            let rest = rest.trim_start_matches(['/', '\\']);
+            return Some((workspace.clone(), PathBuf::from(rest)));
+        }
+    }
+
+    added.iter().find_map(|workspace| {
+        let relative = path.strip_prefix(&workspace.root).ok()?;
+        Some((workspace.clone(), relative.to_path_buf()))
+    })
+}
+
+/// Where `path` is on disk: files of added workspaces under their root, anything else
+/// relative to `workspace_root`
+pub fn locate(workspace_root: &Path, path: &Path) -> PathBuf {
+    match find(path) {
+        Some((workspace, relative)) => workspace.root.join(relative),
+        None => workspace_root.join(path),
+    }
+}
+
+/// `path` as shown to the model: qualified with the workspace name when it is in an added
+/// workspace, so it reads the same in every fragment and can be passed back to any tool
+pub fn display(path: &Path) -> String {
+    match find(path) {
+        Some((workspace, relative)) => {
+            format!("{}:{}", workspace.name, platform::display_path(&relative))
+        }
+        None => platform::display_path(path),
+    }
+}
+
+/// Pinned context telling the planner which other workspaces it can use, or None when
+/// there are none
+pub fn fragment() -> Option<MemoryFragment> {
+    let added = list();
+    if added.is_empty() {
+        return None;
+    }
+
+    let listing = added
+        .iter()
+        .map(|w| {
+            let access = if w.editable { "editable" } else { "read-only" };
+            format!(
+                "- {} ({}, {})",
+                w.name,
+                platform::display_path(&w.root),
+                access
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(MemoryFragment {
+        source: WORKSPACES_SOURCE.to_string(),
+        provenance: Provenance::UserProvided,
+        content: format!(
+            "Other workspaces in this session. Name their files as `name:path` (e.g. \
+            `{}:README.md`) in every tool; paths without a name are in the current workspace. \
+            Read-only workspaces cannot be edited.\n{}",
+            added[0].name, listing
+        ),
+        metadata: None,
+    })
+}
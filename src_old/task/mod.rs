@@ -1,15 +0,0 @@
-//mod subtask;
-//mod queue_manager;
-//mod execution;
-
-/*
-pub use subtask::{
-    SubTask, SubTaskType, SubTaskQueue,
-
-};
-
-//pub use execution::SubTaskExecutor;
-
-// Re-export for backward compatibility
-pub use subtask::{Task, TaskStatus, OperationType, TaskExecutor,  };
-*/
@@ -0,0 +1,47 @@
+use indiefuture_cli::analysis_cache::AnalysisCache;
+use indiefuture_cli::git;
+use indiefuture_cli::storage::Storage;
+use indiefuture_cli::testing::TempWorkspace;
+
+#[tokio::test]
+async fn symbol_index_is_cached_per_commit_and_invalidated_when_head_moves() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write("src/lib.rs", "pub fn one() {}\n\npub struct Two;\n")
+        .unwrap();
+    workspace.init_git().unwrap();
+    let root = workspace.root();
+    let head = || git::run_git(root, &["rev-parse", "HEAD"]).unwrap();
+
+    let cache = AnalysisCache::open(&Storage::temporary("test").unwrap(), "workspace");
+    let first = head();
+    let index = cache.symbol_index(root).await.unwrap();
+    assert_eq!(index.find("one")[0].0, "src/lib.rs");
+    assert!(cache.get(&first).await.unwrap().is_some());
+
+    // Uncommitted source changes are indexed but match no commit, so nothing is cached
+    workspace
+        .write("src/lib.rs", "pub fn one() {}\n\npub fn three() {}\n")
+        .unwrap();
+    let index = cache.symbol_index(root).await.unwrap();
+    assert_eq!(index.find("three").len(), 1);
+    assert!(index.find("Two").is_empty());
+
+    git::run_git(
+        root,
+        &[
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "-am",
+            "Add three",
+        ],
+    )
+    .unwrap();
+    cache.symbol_index(root).await.unwrap();
+    assert!(cache.get(&head()).await.unwrap().is_some());
+    assert!(cache.get(&first).await.unwrap().is_none());
+}
@@ -0,0 +1,44 @@
+use indiefuture_cli::answers;
+use indiefuture_cli::subtasks::subtask::ExplainToolInputs;
+use indiefuture_cli::subtasks::SubTaskType;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn explanations_are_saved_in_full_and_the_latest_is_found() {
+    answers::configure(PathBuf::from(".answers"));
+    let workspace = TempWorkspace::new().unwrap();
+    let text = "Line one.\n\n".repeat(200);
+    let ai = FakeAiClient::new()
+        .text(format!(
+            "{}```json\n{{\"confidence\": \"medium\", \"assumptions\": [\"config is TOML\"]}}\n```",
+            text
+        ))
+        .text("A second, shorter answer.");
+
+    let explain = SubTaskType::ExplainTool(ExplainToolInputs {
+        query: "How is the config parsed?".to_string(),
+        ..Default::default()
+    });
+    workspace.run(explain, &ai).await;
+
+    let first = answers::latest(workspace.root()).unwrap().unwrap();
+    assert!(first.starts_with(workspace.path(".answers")));
+    let saved = std::fs::read_to_string(&first).unwrap();
+    assert!(saved.starts_with("# How is the config parsed?\n\n"));
+    assert!(saved.contains(text.trim()));
+    assert!(saved.contains("**Confidence:** medium"));
+    assert!(saved.contains("- config is TOML"));
+
+    let explain = SubTaskType::ExplainTool(ExplainToolInputs {
+        query: "And then?".to_string(),
+        ..Default::default()
+    });
+    workspace.run(explain, &ai).await;
+
+    let second = answers::latest(workspace.root()).unwrap().unwrap();
+    assert_ne!(second, first);
+    assert!(std::fs::read_to_string(second)
+        .unwrap()
+        .contains("A second, shorter answer."));
+}
@@ -0,0 +1,75 @@
+use indiefuture_cli::ai::openai::{self, OpenAiClient, OpenAiConfig};
+use indiefuture_cli::ai::AiClient;
+
+fn azure() -> OpenAiConfig {
+    OpenAiConfig {
+        base_url: Some("https://my-resource.openai.azure.com/".to_string()),
+        api_version: Some("2024-10-21".to_string()),
+        deployment: Some("team-gpt-4o".to_string()),
+        embedding_deployment: Some("team-embeddings".to_string()),
+    }
+}
+
+#[test]
+fn openai_urls_are_unchanged_by_default() {
+    let config = OpenAiConfig::default();
+    assert!(!config.is_azure());
+    assert_eq!(
+        config.chat_url("gpt-4o"),
+        "https://api.openai.com/v1/chat/completions"
+    );
+    assert_eq!(
+        config.embeddings_url(),
+        "https://api.openai.com/v1/embeddings"
+    );
+    assert_eq!(config.embedding_model(), "text-embedding-ada-002");
+
+    // A compatible API keeps OpenAI's paths under its own base
+    let proxy = OpenAiConfig {
+        base_url: Some("http://localhost:8000/v1".to_string()),
+        ..OpenAiConfig::default()
+    };
+    assert_eq!(
+        proxy.chat_url("gpt-4o"),
+        "http://localhost:8000/v1/chat/completions"
+    );
+}
+
+#[test]
+fn azure_addresses_deployments() {
+    let config = azure();
+    assert!(config.is_azure());
+    assert_eq!(
+        config.chat_url("gpt-4o"),
+        "https://my-resource.openai.azure.com/openai/deployments/team-gpt-4o/chat/completions?api-version=2024-10-21"
+    );
+    assert_eq!(
+        config.embeddings_url(),
+        "https://my-resource.openai.azure.com/openai/deployments/team-embeddings/embeddings?api-version=2024-10-21"
+    );
+
+    // Deployments named after their models need not be configured
+    let unnamed = OpenAiConfig {
+        deployment: None,
+        embedding_deployment: None,
+        ..azure()
+    };
+    assert!(unnamed.chat_url("gpt-4o").contains("/deployments/gpt-4o/"));
+    assert!(unnamed
+        .embeddings_url()
+        .contains("/deployments/text-embedding-ada-002/"));
+}
+
+#[test]
+fn the_client_follows_the_configured_endpoint() {
+    openai::configure(OpenAiConfig {
+        base_url: None,
+        ..azure()
+    });
+    assert!(OpenAiClient::new("key", "gpt-4o").is_err());
+
+    openai::configure(azure());
+    let client = OpenAiClient::new("key", "gpt-4o").unwrap();
+    assert_eq!(client.model_name(), "gpt-4o");
+    assert_eq!(client.embedding_model(), "openai:team-embeddings");
+}
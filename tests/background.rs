@@ -0,0 +1,45 @@
+use indiefuture_cli::background::{BackgroundJobs, Job};
+use indiefuture_cli::memory::ContextMemory;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+#[tokio::test]
+async fn queued_jobs_embed_changed_files_once() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write("src/lib.rs", "pub fn one() {}\n\npub fn two() {}\n")
+        .unwrap();
+
+    let shared_state = Arc::new(workspace.shared_state(&FakeAiClient::new()).unwrap());
+    let jobs = BackgroundJobs::new(
+        Arc::clone(&shared_state),
+        Arc::new(Mutex::new(ContextMemory::default())),
+        1,
+        600,
+    );
+
+    jobs.enqueue(Job::EmbedFile("src/lib.rs".to_string())).await;
+    jobs.enqueue(Job::EmbedFile("src/lib.rs".to_string())).await;
+    assert_eq!(jobs.pending().len(), 1);
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !jobs.pending().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("queued jobs did not finish");
+
+    let embedded = shared_state
+        .embedding_index()
+        .get("src/lib.rs")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!embedded.chunks.is_empty());
+
+    // Nothing is left for the next session to resume
+    assert_eq!(jobs.resume().await.unwrap(), 0);
+}
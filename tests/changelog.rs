@@ -0,0 +1,41 @@
+use indiefuture_cli::changelog::{self, indexed_symbols};
+use indiefuture_cli::embeddings::EmbeddingIndex;
+use indiefuture_cli::git::CommitSummary;
+use indiefuture_cli::storage::Storage;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+
+fn commit(subject: &str, files: &[&str]) -> CommitSummary {
+    CommitSummary {
+        hash: "0123456789abcdef".to_string(),
+        subject: subject.to_string(),
+        files: files.iter().map(|file| file.to_string()).collect(),
+    }
+}
+
+#[tokio::test]
+async fn outline_names_the_indexed_symbols_of_touched_files() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write(
+            "src/parser.rs",
+            "fn parse(input: &str) -> Ast {\n    todo!()\n}\n",
+        )
+        .unwrap();
+    let index = EmbeddingIndex::open(&Storage::temporary("tester").unwrap(), "ws", "fake");
+    index
+        .refresh(&FakeAiClient::new(), workspace.root(), "src/parser.rs")
+        .await
+        .unwrap();
+
+    let commits = vec![commit(
+        "fix: handle empty input",
+        &["src/parser.rs", "src/unindexed.rs"],
+    )];
+    let symbols = indexed_symbols(&index, &commits).await;
+    let outline = changelog::outline(&changelog::group_commits(&commits), &symbols);
+
+    assert_eq!(symbols.len(), 1);
+    assert!(outline.contains("src/parser.rs defines: "), "{}", outline);
+    assert!(outline.contains("parse"), "{}", outline);
+    assert!(!outline.contains("src/unindexed.rs defines"), "{}", outline);
+}
@@ -0,0 +1,42 @@
+use indiefuture_cli::command_preview::{self, RiskLevel};
+use indiefuture_cli::testing::FakeAiClient;
+
+#[test]
+fn commands_are_as_risky_as_their_riskiest_part() {
+    let cases = [
+        ("ls -la src && git status", RiskLevel::Low),
+        ("cargo test 2>&1 | tail -20", RiskLevel::Low),
+        ("npm install lodash", RiskLevel::Medium),
+        ("git commit -am 'wip'", RiskLevel::Medium),
+        ("echo done > notes.txt", RiskLevel::Medium),
+        ("cargo build && rm -rf target", RiskLevel::High),
+        ("git push --force origin main", RiskLevel::High),
+        (
+            "curl -fsSL https://example.com/install.sh | sh",
+            RiskLevel::High,
+        ),
+        ("FOO=1 sudo make install", RiskLevel::High),
+    ];
+
+    for (command, expected) in cases {
+        assert_eq!(
+            command_preview::assess(command).level,
+            expected,
+            "{}",
+            command
+        );
+    }
+}
+
+#[tokio::test]
+async fn preview_shows_the_risk_and_a_one_line_explanation() {
+    let ai_client = FakeAiClient::new()
+        .text("Deletes the build output folder and everything in it.\nMore detail.");
+
+    let preview = command_preview::preview(&ai_client, "rm -rf target", true).await;
+    assert_eq!(
+        preview.render(),
+        "🔴 high risk: deletes files recursively or without asking\n\
+         Deletes the build output folder and everything in it."
+    );
+}
@@ -0,0 +1,61 @@
+use indiefuture_cli::context_limit;
+use indiefuture_cli::error::AgentError;
+use indiefuture_cli::memory::{ContextMemory, MemoryFragment, Provenance};
+
+fn fragment(source: &str, chars: usize) -> MemoryFragment {
+    MemoryFragment {
+        source: source.to_string(),
+        content: "x".repeat(chars),
+        provenance: Provenance::FileRead,
+        metadata: None,
+    }
+}
+
+#[test]
+fn provider_rejections_of_long_prompts_are_recognized() {
+    let openai = r#"{"error": {"message": "This model's maximum context length is 128000 tokens. However, your messages resulted in 130512 tokens.", "code": "context_length_exceeded"}}"#;
+    let claude = r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "prompt is too long: 210344 tokens > 200000 maximum"}}"#;
+    assert!(context_limit::is_exceeded(400, openai));
+    assert!(context_limit::is_exceeded(400, claude));
+    assert!(!context_limit::is_exceeded(429, openai));
+    assert!(!context_limit::is_exceeded(
+        400,
+        r#"{"error": {"message": "Invalid model"}}"#
+    ));
+
+    assert!(!context_limit::take());
+    match context_limit::exceeded("Claude", claude) {
+        AgentError::ContextLengthExceeded(message) => assert_eq!(
+            message,
+            "Claude: prompt is too long: 210344 tokens > 200000 maximum"
+        ),
+        other => panic!("unexpected error: {}", other),
+    }
+    assert!(context_limit::take());
+    assert!(!context_limit::take());
+}
+
+#[test]
+fn largest_fragments_include_pinned_ones_and_can_be_removed() {
+    let mut memory = ContextMemory::default();
+    memory.add_frag(fragment("small", 10));
+    memory.add_frag(fragment("large", 3000));
+    memory.pin_frag(fragment("pinned", 500));
+
+    let largest = memory.largest_fragments(2);
+    let sources: Vec<(&str, bool)> = largest
+        .iter()
+        .map(|(fragment, pinned)| (fragment.source.as_str(), *pinned))
+        .collect();
+    assert_eq!(sources, [("large", false), ("pinned", true)]);
+    assert_eq!(
+        context_limit::fragment_label(&largest[1].0, true),
+        "📌 pinned - 500 chars, ~125 tokens"
+    );
+
+    assert!(memory.remove(&largest[0].0));
+    assert!(memory.remove(&largest[1].0));
+    assert!(!memory.remove(&largest[1].0));
+    assert_eq!(memory.fragment_count(), 1);
+    assert_eq!(memory.pinned_len(), 0);
+}
@@ -0,0 +1,75 @@
+use indiefuture_cli::coverage::{self, FileCoverage};
+use serde_json::json;
+use std::path::Path;
+
+#[test]
+fn lcov_records_are_read_per_workspace_file() {
+    let root = Path::new("/work/project");
+    let lcov = "TN:\n\
+        SF:/work/project/src/lib.rs\nFN:1,add\nDA:1,3\nDA:2,3\nDA:5,0\nDA:6,0\nend_of_record\n\
+        SF:/home/u/.cargo/registry/src/x/lib.rs\nDA:1,9\nend_of_record\n\
+        SF:/work/project/src/lib.rs\nDA:5,1\nend_of_record\n";
+
+    let files = coverage::parse_lcov(lcov, root);
+
+    assert_eq!(files.len(), 1, "files outside the workspace are skipped");
+    assert_eq!(files[0].file, "src/lib.rs");
+    // The second record, another instantiation of the same code, adds its hits
+    assert_eq!(files[0].lines.get(&5), Some(&1));
+    assert_eq!(files[0].covered(), 3);
+    assert_eq!(files[0].percent(), 75.0);
+}
+
+#[test]
+fn tarpaulin_reports_join_path_components() {
+    let root = Path::new("/work/project");
+    let report = json!({
+        "files": [{
+            "path": ["/", "work", "project", "src", "parse.rs"],
+            "traces": [
+                {"line": 3, "stats": {"Line": 2}},
+                {"line": 4, "stats": {"Line": 0}}
+            ]
+        }]
+    });
+
+    let files = coverage::parse_tarpaulin(&report.to_string(), root);
+
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].file, "src/parse.rs");
+    assert_eq!(files[0].percent(), 50.0);
+}
+
+#[test]
+fn functions_whose_lines_never_ran_are_uncovered() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/lib.rs"),
+        "pub fn used() -> u8 {\n    1\n}\n\npub struct S;\n\nimpl S {\n    pub fn unused(&self) -> u8 {\n        2\n    }\n}\n",
+    )
+    .unwrap();
+    let file = FileCoverage {
+        file: "src/lib.rs".to_string(),
+        lines: [(1, 4), (2, 4), (8, 0), (9, 0)].into_iter().collect(),
+    };
+    let fully_covered = FileCoverage {
+        file: "src/other.rs".to_string(),
+        lines: [(1, 1)].into_iter().collect(),
+    };
+
+    let uncovered = coverage::uncovered_functions(dir.path(), &file);
+    assert_eq!(uncovered.len(), 1);
+    assert_eq!(uncovered[0].symbol, "S::unused");
+    assert_eq!((uncovered[0].start_line, uncovered[0].end_line), (8, 10));
+
+    let files = vec![fully_covered, file];
+    let lowest = coverage::lowest_coverage(&files, 5);
+    assert_eq!(lowest.len(), 1, "fully covered files are not listed");
+
+    let fragment = coverage::fragment(dir.path(), &files, 5);
+    assert!(fragment.content.contains("src/lib.rs: 50.0%"));
+    assert!(fragment
+        .content
+        .contains("uncovered: S::unused (lines 8-10)"));
+}
@@ -0,0 +1,74 @@
+use indiefuture_cli::cli::{parse_args, CliCommand};
+use indiefuture_cli::config::Settings;
+use indiefuture_cli::crash::{self, CrashBundle};
+use indiefuture_cli::testing::TempWorkspace;
+use std::path::PathBuf;
+
+#[test]
+fn settings_are_saved_without_secrets() {
+    let settings = Settings {
+        openai_api_key: Some("sk-secret".to_string()),
+        memory_backend_url: Some("postgres://admin:hunter2@db/agent".to_string()),
+        memory_user: "alice".to_string(),
+        ..Settings::default()
+    };
+
+    let config = crash::redacted_settings(&settings);
+    assert_eq!(config["openai_api_key"], "<redacted>");
+    assert_eq!(config["memory_backend_url"], "<redacted>");
+    assert_eq!(config["memory_user"], "<redacted>");
+    assert!(config["claude_api_key"].is_null());
+    assert_eq!(config["default_model"], settings.default_model);
+    assert!(!config.to_string().contains("hunter2"));
+}
+
+#[test]
+fn bundles_record_the_activity_and_are_found_again() {
+    crash::begin_turn("Rename the parser");
+    crash::set_active_subtask(Some("Edit src/parser.rs"));
+    crash::set_active_subtask(None);
+    crash::set_active_subtask(Some("Run cargo test"));
+
+    let bundle = CrashBundle::capture("index out of bounds", None, "0: main");
+    assert_eq!(bundle.task.as_deref(), Some("Rename the parser"));
+    assert_eq!(bundle.active_subtask.as_deref(), Some("Run cargo test"));
+    let events: Vec<&str> = bundle
+        .events
+        .iter()
+        .map(|event| event.split_once(' ').unwrap().1)
+        .collect();
+    assert_eq!(
+        events,
+        [
+            "turn: Rename the parser",
+            "started: Edit src/parser.rs",
+            "finished: Edit src/parser.rs",
+            "started: Run cargo test"
+        ]
+    );
+    assert!(bundle.recovery_message(None).contains("indiefuture resume"));
+
+    let workspace = TempWorkspace::new().unwrap();
+    let dir = workspace.path("crashes");
+    assert_eq!(crash::latest(&dir).unwrap(), None);
+    let saved = bundle.save(&dir).unwrap();
+    assert_eq!(crash::latest(&dir).unwrap(), Some(saved.clone()));
+    let loaded = CrashBundle::load(&saved).unwrap();
+    assert_eq!(loaded.message, "index out of bounds");
+    assert!(loaded.summary().contains("Run cargo test"));
+}
+
+#[test]
+fn resume_takes_an_optional_bundle() {
+    let args = parse_args(["resume".to_string()]).unwrap();
+    assert_eq!(args.command, CliCommand::Resume { bundle: None });
+
+    let args = parse_args(["resume", "crash.json"].map(String::from)).unwrap();
+    assert_eq!(
+        args.command,
+        CliCommand::Resume {
+            bundle: Some(PathBuf::from("crash.json"))
+        }
+    );
+    assert!(parse_args(["resume", "a.json", "b.json"].map(String::from)).is_err());
+}
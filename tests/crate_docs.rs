@@ -0,0 +1,60 @@
+use indiefuture_cli::crate_docs::{self, CrateDocs};
+use tempfile::TempDir;
+
+const README: &str = r#"<h1>tinyjson</h1>
+<p>A small JSON parser &amp; printer.</p>
+<h2>Usage</h2>
+<pre><code>let value = tinyjson::parse("[1, 2]")?;
+</code></pre>
+<h2>Streaming</h2>
+<p>Use <code>Parser::stream</code> for documents larger than memory.</p>
+<ul><li>no allocations</li><li>no_std</li></ul>"#;
+
+#[test]
+fn readmes_are_reduced_to_the_paragraphs_asked_about() {
+    let text = crate_docs::html_to_text(README);
+    assert!(text.starts_with("tinyjson\n\nA small JSON parser & printer."));
+    assert!(text.contains("let value = tinyjson::parse(\"[1, 2]\")?;"));
+    assert!(text.contains("- no allocations\n- no_std"));
+
+    assert_eq!(
+        crate_docs::readme_snippets(&text, "parser::STREAM"),
+        "Streaming\nUse Parser::stream for documents larger than memory."
+    );
+}
+
+#[tokio::test]
+async fn cached_versions_are_not_fetched_again() {
+    let dir = TempDir::new().unwrap();
+    crate_docs::configure(dir.path().join("cache"));
+    std::fs::write(
+        dir.path().join("Cargo.lock"),
+        "[[package]]\nname = \"tinyjson\"\nversion = \"2.5.1\"\n",
+    )
+    .unwrap();
+
+    let docs = CrateDocs {
+        name: "tinyjson".to_string(),
+        version: "2.5.1".to_string(),
+        latest_version: Some("2.6.0".to_string()),
+        description: Some("A small JSON parser".to_string()),
+        license: Some("MIT".to_string()),
+        rust_version: None,
+        yanked: false,
+        features: vec!["std".to_string()],
+        repository: None,
+        documentation: "https://docs.rs/tinyjson/2.5.1".to_string(),
+        readme: Some(crate_docs::html_to_text(README)),
+        fetched_at: "2026-01-01T00:00:00Z".to_string(),
+    };
+    crate_docs::store(&docs).unwrap();
+
+    // The pinned version is read from the cache, so this never reaches the network
+    let found = crate_docs::lookup(dir.path(), "tinyjson", None)
+        .await
+        .unwrap();
+    assert_eq!(found, docs);
+    let rendered = found.render(Some("allocations"));
+    assert!(rendered.contains("Latest stable version: 2.6.0"));
+    assert!(rendered.ends_with("- no allocations\n- no_std"));
+}
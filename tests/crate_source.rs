@@ -0,0 +1,71 @@
+use indiefuture_cli::crate_source::{self, Dependency};
+use tempfile::TempDir;
+
+const MANIFEST: &str = r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] } # inline table
+regex = "1"
+json = { package = "serde_json", version = "1" }
+local = { path = "../local" }
+
+[target.'cfg(unix)'.dependencies]
+nix = "0.29"
+
+[dev-dependencies.tempfile]
+version = "3"
+"#;
+
+#[test]
+fn dependencies_are_read_from_every_table() {
+    let names: Vec<String> = crate_source::dependencies(MANIFEST)
+        .into_iter()
+        .map(|d| d.name)
+        .collect();
+    assert_eq!(
+        names,
+        ["serde", "regex", "json", "local", "nix", "tempfile"]
+    );
+
+    let renamed = crate_source::dependencies(MANIFEST)
+        .into_iter()
+        .find(|d| d.name == "json")
+        .unwrap();
+    assert_eq!(
+        renamed,
+        Dependency {
+            name: "json".to_string(),
+            package: "serde_json".to_string(),
+            path: None,
+        }
+    );
+}
+
+#[test]
+fn vendored_sources_are_found_at_the_locked_version() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    std::fs::write(root.join("Cargo.toml"), MANIFEST).unwrap();
+    std::fs::write(
+        root.join("Cargo.lock"),
+        "[[package]]\nname = \"regex\"\nversion = \"1.9.6\"\n\n\
+         [[package]]\nname = \"regex\"\nversion = \"1.10.3\"\n",
+    )
+    .unwrap();
+    let vendored = root.join("vendor/regex-1.10.3/src");
+    std::fs::create_dir_all(&vendored).unwrap();
+    std::fs::write(vendored.join("lib.rs"), "pub fn is_match() {}\n").unwrap();
+
+    let source = crate_source::locate(root, "regex").unwrap();
+    assert_eq!(source.label(), "regex@1.10.3");
+    assert_eq!(crate_source::list_files(&source), ["src/lib.rs"]);
+
+    let matches = crate_source::search(&source, &regex::Regex::new("is_match").unwrap());
+    assert_eq!(matches, ["src/lib.rs:1: pub fn is_match() {}"]);
+    assert!(crate_source::read(&source, "../../Cargo.toml", 0).is_err());
+    assert!(crate_source::locate(root, "tokio").is_err());
+    assert!(crate_source::resolve(&source, "src/lib.rs").is_ok());
+}
@@ -0,0 +1,80 @@
+use indiefuture_cli::diagnostics::{self, Diagnostic, Severity};
+use serde_json::json;
+use std::path::Path;
+
+#[test]
+fn cargo_messages_become_diagnostics_with_their_suggestions() {
+    let error = json!({
+        "reason": "compiler-message",
+        "message": {
+            "level": "error",
+            "message": "mismatched types",
+            "code": {"code": "E0308"},
+            "spans": [{"file_name": "src/main.rs", "line_start": 4, "column_start": 18,
+                       "is_primary": true, "suggested_replacement": null}],
+            "children": [{"spans": [{"file_name": "src/main.rs", "line_start": 4,
+                          "column_start": 18, "is_primary": true,
+                          "suggested_replacement": "value.to_string()"}]}]
+        }
+    });
+    let in_dependency = json!({
+        "reason": "compiler-message",
+        "message": {
+            "level": "warning", "message": "unused import", "code": null,
+            "spans": [{"file_name": "/home/u/.cargo/registry/src/x/lib.rs", "line_start": 1,
+                       "column_start": 1, "is_primary": true}],
+            "children": []
+        }
+    });
+    let output = format!(
+        "{}\n{}\n{}\n",
+        error,
+        in_dependency,
+        json!({"reason": "build-finished", "success": false})
+    );
+
+    assert_eq!(
+        diagnostics::parse_cargo(&output),
+        [Diagnostic {
+            severity: Severity::Error,
+            code: Some("E0308".to_string()),
+            message: "mismatched types".to_string(),
+            file: "src/main.rs".to_string(),
+            line: 4,
+            column: 18,
+            suggestion: Some("value.to_string()".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn tsc_and_eslint_output_is_parsed() {
+    let tsc = "src/a.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.\n\
+               Found 1 error in src/a.ts:3";
+    let parsed = diagnostics::parse_tsc(tsc);
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(
+        parsed[0].to_string(),
+        "src/a.ts:3:7 error[TS2322]: Type 'string' is not assignable to type 'number'."
+    );
+
+    let eslint = json!([{
+        "filePath": "/repo/src/b.js",
+        "messages": [
+            {"ruleId": "prefer-const", "severity": 2, "message": "Use const.", "line": 2,
+             "column": 5, "fix": {"range": [10, 13], "text": "const"}},
+            {"ruleId": "no-console", "severity": 1, "message": "Unexpected console.",
+             "line": 9, "column": 1}
+        ]
+    }]);
+    let parsed = diagnostics::parse_eslint(&eslint.to_string(), Path::new("/repo"));
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed[0].file, "src/b.js");
+    assert_eq!(parsed[0].suggestion.as_deref(), Some("const"));
+    assert_eq!(parsed[1].severity, Severity::Warning);
+
+    let task = diagnostics::fix_task("src/b.js", &parsed.iter().collect::<Vec<_>>());
+    assert!(task.contains(
+        "- src/b.js:2:5 error[prefer-const]: Use const. (suggested replacement: `const`)"
+    ));
+}
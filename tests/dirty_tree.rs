@@ -0,0 +1,41 @@
+use indiefuture_cli::dirty_tree::{self, AutoStash};
+use indiefuture_cli::testing::TempWorkspace;
+use std::process::Command;
+
+#[test]
+fn auto_stash_sets_changes_aside_and_restores_them() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("src/lib.rs", "pub fn one() {}\n").unwrap();
+    workspace.init_git().unwrap();
+    for (key, value) in [("user.name", "Test"), ("user.email", "test@example.com")] {
+        Command::new("git")
+            .args(["config", key, value])
+            .current_dir(workspace.root())
+            .status()
+            .unwrap();
+    }
+
+    workspace.write("src/lib.rs", "pub fn two() {}\n").unwrap();
+    workspace.write("notes.txt", "draft\n").unwrap();
+    let mut dirty = dirty_tree::uncommitted_files(workspace.root()).unwrap();
+    dirty.sort();
+    assert_eq!(dirty, ["notes.txt", "src/lib.rs"]);
+
+    let stash = AutoStash::push(workspace.root()).unwrap().unwrap();
+    assert!(dirty_tree::uncommitted_files(workspace.root())
+        .unwrap()
+        .is_empty());
+    assert_eq!(workspace.read("src/lib.rs").unwrap(), "pub fn one() {}\n");
+
+    stash.restore().unwrap();
+    assert_eq!(workspace.read("src/lib.rs").unwrap(), "pub fn two() {}\n");
+    assert_eq!(workspace.read("notes.txt").unwrap(), "draft\n");
+
+    // A clean tree has nothing to stash
+    Command::new("git")
+        .args(["stash", "--include-untracked"])
+        .current_dir(workspace.root())
+        .status()
+        .unwrap();
+    assert!(AutoStash::push(workspace.root()).unwrap().is_none());
+}
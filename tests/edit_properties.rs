@@ -0,0 +1,161 @@
+//! Invariants of edit application over generated file contents. Needles are drawn from
+//! letters the surrounding text never uses, so uniqueness is known by construction.
+
+use indiefuture_cli::edits::{self, EditSet, FileEdit, InsertPosition};
+use indiefuture_cli::testing::TempWorkspace;
+use proptest::prelude::*;
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Text that never contains `x` or `y`
+fn text() -> impl Strategy<Value = String> {
+    "[ab \n]{0,40}"
+}
+
+/// Text that can be matched exactly once inside `text()`
+fn needle() -> impl Strategy<Value = String> {
+    "[xy]{1,4}"
+}
+
+/// `text` as stored on disk, optionally with CRLF endings and a UTF-8 BOM
+fn stored(text: &str, crlf: bool, bom: bool) -> Vec<u8> {
+    let mut bytes = if bom { BOM.to_vec() } else { Vec::new() };
+    if crlf {
+        bytes.extend_from_slice(text.replace('\n', "\r\n").as_bytes());
+    } else {
+        bytes.extend_from_slice(text.as_bytes());
+    }
+    bytes
+}
+
+fn replace(old: &str, new: &str) -> FileEdit {
+    FileEdit::Replace {
+        old_string: old.to_string(),
+        new_string: new.to_string(),
+    }
+}
+
+fn apply_one(workspace: &TempWorkspace, path: &str, edit: FileEdit) -> bool {
+    let mut set = EditSet::new(workspace.root());
+    set.stage(path, edit);
+    set.apply().is_ok()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn reapplying_a_replace_fails_and_leaves_the_file_alone(
+        head in text(),
+        old in needle(),
+        tail in text(),
+        new in text(),
+    ) {
+        let workspace = TempWorkspace::new().unwrap();
+        workspace.write("file.txt", &format!("{}{}{}", head, old, tail)).unwrap();
+
+        prop_assert!(apply_one(&workspace, "file.txt", replace(&old, &new)));
+        let applied = std::fs::read(workspace.path("file.txt")).unwrap();
+        prop_assert_eq!(&applied, &format!("{}{}{}", head, new, tail).into_bytes());
+
+        prop_assert!(!apply_one(&workspace, "file.txt", replace(&old, &new)));
+        prop_assert_eq!(std::fs::read(workspace.path("file.txt")).unwrap(), applied);
+    }
+
+    #[test]
+    fn non_unique_matches_are_always_rejected(
+        parts in prop::collection::vec("[abxy \n]{0,20}", 3),
+        needle in needle(),
+        new in text(),
+        crlf in any::<bool>(),
+        bom in any::<bool>(),
+    ) {
+        let workspace = TempWorkspace::new().unwrap();
+        let content = parts.join(&needle);
+        let original = stored(&content, crlf, bom);
+        std::fs::write(workspace.path("file.txt"), &original).unwrap();
+        workspace.write("other.txt", "keep x\n").unwrap();
+
+        prop_assert!(edits::apply_insert(&content, &InsertPosition::After(needle.clone()), &new).is_err());
+        prop_assert!(edits::apply_insert(&content, &InsertPosition::Before(needle.clone()), &new).is_err());
+
+        for edit in [
+            replace(&needle, &new),
+            FileEdit::Insert { position: InsertPosition::After(needle.clone()), content: new.clone() },
+            FileEdit::Insert { position: InsertPosition::Before(needle.clone()), content: new.clone() },
+        ] {
+            // A valid edit earlier in the set must not land either
+            let mut set = EditSet::new(workspace.root());
+            set.stage("other.txt", replace("x", "changed"));
+            set.stage("file.txt", edit);
+            prop_assert!(set.apply().is_err());
+            prop_assert_eq!(std::fs::read(workspace.path("file.txt")).unwrap(), original.clone());
+            prop_assert_eq!(workspace.read("other.txt").unwrap(), "keep x\n");
+        }
+    }
+
+    #[test]
+    fn restoring_the_original_gives_back_the_same_bytes(
+        head in text(),
+        old in needle(),
+        tail in text(),
+        new in text(),
+        crlf in any::<bool>(),
+        bom in any::<bool>(),
+    ) {
+        let workspace = TempWorkspace::new().unwrap();
+        let original = stored(&format!("{}{}{}", head, old, tail), crlf, bom);
+        std::fs::write(workspace.path("file.txt"), &original).unwrap();
+
+        let mut set = EditSet::new(workspace.root());
+        set.stage("file.txt", replace(&old, &new));
+        let planned = set.apply().unwrap();
+
+        // The edit keeps the file's BOM and, when it had any, its CRLF endings
+        let written = std::fs::read(workspace.path("file.txt")).unwrap();
+        prop_assert_eq!(written.starts_with(BOM), bom);
+        let crlf_on_disk = crlf && original.contains(&b'\n');
+        if crlf_on_disk {
+            prop_assert_eq!(
+                written.windows(2).filter(|w| w == b"\r\n").count(),
+                written.iter().filter(|&&b| b == b'\n').count()
+            );
+        }
+
+        // Undo writes the recorded original back in the recorded format
+        let file = &planned[0];
+        file.format
+            .write(&file.resolved, file.original.as_deref().unwrap())
+            .unwrap();
+        prop_assert_eq!(std::fs::read(workspace.path("file.txt")).unwrap(), original);
+    }
+
+    #[test]
+    fn hunks_reassemble_the_planned_content(
+        head in text(),
+        old in needle(),
+        new in needle(),
+        tail in text(),
+    ) {
+        let workspace = TempWorkspace::new().unwrap();
+        let original = format!("{}{}{}", head, old, tail);
+        std::fs::write(workspace.path("file.txt"), &original).unwrap();
+
+        let mut set = EditSet::new(workspace.root());
+        set.stage("file.txt", replace(&old, &new));
+        let planned = set.plan().unwrap();
+        let file = &planned[0];
+
+        // Accepting every hunk, split or not, gives the planned content; none gives the original
+        let hunks = file.hunks();
+        let whole: Vec<_> = hunks.iter().flat_map(|hunk| hunk.changes.clone()).collect();
+        let split: Vec<_> = hunks
+            .iter()
+            .flat_map(|hunk| hunk.split())
+            .flat_map(|hunk| hunk.changes)
+            .collect();
+        prop_assert_eq!(edits::apply_changes(&original, &whole), file.updated.clone());
+        prop_assert_eq!(edits::apply_changes(&original, &split), file.updated.clone());
+        prop_assert_eq!(edits::apply_changes(&original, &[]), original);
+    }
+}
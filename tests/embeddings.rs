@@ -0,0 +1,103 @@
+use indiefuture_cli::embeddings::{self, EmbeddingIndex, MigrationPolicy, SearchHit};
+use indiefuture_cli::storage::Storage;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace, FAKE_EMBEDDING_DIMENSIONS};
+
+fn hit(path: &str, model: &str) -> SearchHit {
+    SearchHit {
+        path: path.to_string(),
+        start_line: 1,
+        end_line: 10,
+        symbol: None,
+        similarity: 0.5,
+        embedding_model: model.to_string(),
+    }
+}
+
+#[test]
+fn migration_policies_parse_by_name() {
+    assert_eq!(
+        "reembed".parse::<MigrationPolicy>().unwrap(),
+        MigrationPolicy::Reembed
+    );
+    assert_eq!(
+        " dual ".parse::<MigrationPolicy>().unwrap(),
+        MigrationPolicy::Dual
+    );
+    assert_eq!(MigrationPolicy::default().to_string(), "ask");
+    assert!("both".parse::<MigrationPolicy>().is_err());
+}
+
+#[tokio::test]
+async fn indexes_record_their_model_and_find_older_ones() {
+    let storage = Storage::temporary("tester").unwrap();
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write(
+            "src/parser.rs",
+            "fn parse(input: &str) -> Ast {\n    todo!()\n}\n",
+        )
+        .unwrap();
+    let fake = FakeAiClient::new();
+
+    let index = EmbeddingIndex::open(&storage, "ws", "fake");
+    assert!(index
+        .refresh(&fake, workspace.root(), "src/parser.rs")
+        .await
+        .unwrap());
+    let header = index.header().await.unwrap().unwrap();
+    assert_eq!(header.embedding_model, "fake");
+    assert_eq!(header.dimensions, FAKE_EMBEDDING_DIMENSIONS);
+    assert!(index.other_indexes().await.unwrap().is_empty());
+
+    // After switching models the old index is reported, and can be dropped
+    let switched = EmbeddingIndex::open(&storage, "ws", "openai:text-embedding-3-small");
+    assert!(switched
+        .refresh(&fake, workspace.root(), "src/parser.rs")
+        .await
+        .is_err());
+    let others = switched.other_indexes().await.unwrap();
+    assert_eq!(others, vec![header]);
+    assert_eq!(
+        switched.indexed_paths("fake").await.unwrap(),
+        vec!["src/parser.rs".to_string()]
+    );
+    assert_eq!(switched.drop_index("fake").await.unwrap(), 1);
+    assert!(switched.other_indexes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn search_ranks_the_closest_chunks_first() {
+    let storage = Storage::temporary("tester").unwrap();
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("a.txt", "zzzzzzzz").unwrap();
+    workspace.write("b.txt", "aaaaaaab").unwrap();
+    let fake = FakeAiClient::new();
+
+    let index = EmbeddingIndex::open(&storage, "ws", "fake");
+    for path in ["a.txt", "b.txt"] {
+        index.refresh(&fake, workspace.root(), path).await.unwrap();
+    }
+    let hits = index.search(&fake, "aaaaaaab", 2).await.unwrap();
+    assert_eq!(hits[0].path, "b.txt");
+    assert_eq!(hits[0].embedding_model, "fake");
+}
+
+#[test]
+fn hits_of_several_indexes_merge_by_rank() {
+    let merged = embeddings::merge_by_rank(
+        vec![
+            vec![hit("a.rs", "new"), hit("b.rs", "new")],
+            vec![hit("b.rs", "old"), hit("c.rs", "old")],
+        ],
+        10,
+    );
+    let paths: Vec<&str> = merged.iter().map(|hit| hit.path.as_str()).collect();
+    assert_eq!(paths, vec!["b.rs", "a.rs", "c.rs"]);
+}
+
+#[test]
+fn cosine_similarity_of_vectors() {
+    assert!((embeddings::cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+    assert_eq!(embeddings::cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+    assert_eq!(embeddings::cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+}
@@ -0,0 +1,37 @@
+use indiefuture_cli::error_lookup::{self, ErrorQuery};
+
+#[test]
+fn codes_are_looked_up_instead_of_the_messages_quoting_them() {
+    assert_eq!(
+        ErrorQuery::parse("error[E0507]: cannot move out of `self.name`"),
+        ErrorQuery::Code("E0507".to_string())
+    );
+    assert_eq!(
+        ErrorQuery::parse("e0382"),
+        ErrorQuery::Code("E0382".to_string())
+    );
+    assert_eq!(
+        ErrorQuery::parse("future cannot be sent between threads safely"),
+        ErrorQuery::Message("future cannot be sent between threads safely".to_string())
+    );
+
+    let output = "error[E0507]: cannot move\nerror[E0382]: use of moved value\n\
+                  error[E0507]: cannot move\nwarning: unused variable `E1234`";
+    assert_eq!(error_lookup::reported_codes(output), ["E0507", "E0382"]);
+}
+
+#[test]
+fn search_results_are_read_from_the_results_page() {
+    let html = r#"
+<a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fstackoverflow.com%2Fq%2F1&amp;rut=x">Cannot move out of <b>borrowed</b> content</a>
+<a class="result__snippet" href="x">You are trying to move a value &amp; keep the borrow.</a>
+"#;
+    let results = error_lookup::parse_search_results(html);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].title, "Cannot move out of borrowed content");
+    assert_eq!(results[0].url, "https://stackoverflow.com/q/1");
+    assert_eq!(
+        results[0].snippet,
+        "You are trying to move a value & keep the borrow."
+    );
+}
@@ -0,0 +1,142 @@
+use indiefuture_cli::cli::{parse_args, CliCommand};
+use indiefuture_cli::eval::{self, CaseResult, CheckResult, EvalReport, EvalSuite};
+use indiefuture_cli::report::{ReportStep, TurnReport};
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use std::path::PathBuf;
+
+const SUITE: &str = r#"
+name: basics
+fixture: fixtures/calculator
+cases:
+  - name: find-parser
+    prompt: Where are expressions parsed?
+    expect:
+      files_found: [src/parser.rs]
+      answer_contains: [Parse_Expression]
+  - name: add-modulo
+    prompt: Add a modulo operator
+    expect:
+      files_changed: [src/parser.rs]
+      command: cargo check
+      judge: Modulo is parsed
+"#;
+
+fn check(score: f64) -> CheckResult {
+    CheckResult {
+        check: "check".to_string(),
+        score,
+        detail: String::new(),
+    }
+}
+
+#[test]
+fn suites_are_loaded_from_the_evals_directory() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("evals/basics.yaml", SUITE).unwrap();
+
+    let (suite, fixture) = EvalSuite::load(workspace.root(), "basics").unwrap();
+    assert_eq!(suite.cases.len(), 2);
+    assert_eq!(suite.cases[0].expect.files_found, ["src/parser.rs"]);
+    assert_eq!(
+        suite.cases[1].expect.command.as_deref(),
+        Some("cargo check")
+    );
+    assert_eq!(fixture, workspace.path("evals/fixtures/calculator"));
+
+    assert!(EvalSuite::load(workspace.root(), "missing").is_err());
+    assert!(EvalSuite::parse("name: empty\nfixture: .\ncases: []\n").is_err());
+}
+
+#[test]
+fn deterministic_checks_score_the_turn_report() {
+    let suite = EvalSuite::parse(SUITE).unwrap();
+    let report = TurnReport {
+        steps: vec![ReportStep {
+            icon: "🔍",
+            description: "Search for parse".to_string(),
+            output: Some("src/parser.rs:12: fn parse_expression".to_string()),
+            succeeded: true,
+        }],
+        files_changed: vec!["/tmp/eval/src/lexer.rs".to_string()],
+        explanation: Some("Expressions are parsed by parse_expression.".to_string()),
+        ..TurnReport::default()
+    };
+
+    let found = eval::deterministic_checks(&suite.cases[0].expect, &report);
+    assert!(found.iter().all(|check| check.score == 1.0));
+
+    let changed = eval::deterministic_checks(&suite.cases[1].expect, &report);
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0].score, 0.0);
+
+    let report = TurnReport {
+        files_changed: vec!["/tmp/eval/src/parser.rs".to_string()],
+        ..report
+    };
+    let changed = eval::deterministic_checks(&suite.cases[1].expect, &report);
+    assert_eq!(changed[0].score, 1.0);
+}
+
+#[test]
+fn fixtures_are_copied_without_build_output() {
+    let fixture = TempWorkspace::new().unwrap();
+    fixture.write("src/main.rs", "fn main() {}").unwrap();
+    fixture.write("target/debug/main", "binary").unwrap();
+    let copy = TempWorkspace::new().unwrap();
+
+    eval::copy_fixture(fixture.root(), copy.root()).unwrap();
+    assert!(copy.path("src/main.rs").is_file());
+    assert!(!copy.path("target").exists());
+}
+
+#[test]
+fn reports_compare_against_a_baseline() {
+    let client = FakeAiClient::new();
+    let before = EvalReport::new(
+        "basics",
+        &client,
+        vec![CaseResult::new("find-parser", vec![check(0.0), check(1.0)])],
+    );
+    let after = EvalReport::new(
+        "basics",
+        &client,
+        vec![CaseResult::new("find-parser", vec![check(1.0), check(1.0)])],
+    );
+    assert_eq!(before.score, 50.0);
+    assert_eq!(after.score, 100.0);
+
+    let table = after.render(Some(&before));
+    assert!(table.contains("find-parser"));
+    assert!(table.contains("(+50.0)"));
+
+    let workspace = TempWorkspace::new().unwrap();
+    let path = after.save(workspace.root()).unwrap();
+    assert!(path.starts_with(workspace.path("evals/results")));
+    assert_eq!(EvalReport::load(&path).unwrap(), after);
+}
+
+#[test]
+fn eval_arguments_are_parsed() {
+    let args = parse_args(
+        [
+            "eval",
+            "basics",
+            "--case",
+            "add-modulo",
+            "--compare=old.json",
+        ]
+        .map(String::from),
+    )
+    .unwrap();
+    assert_eq!(
+        args.command,
+        CliCommand::Eval {
+            suite: "basics".to_string(),
+            case: Some("add-modulo".to_string()),
+            compare: Some(PathBuf::from("old.json")),
+        }
+    );
+
+    assert!(parse_args(["eval"].map(String::from)).is_err());
+    assert!(parse_args(["eval", "basics", "--verbose", "x"].map(String::from)).is_err());
+}
@@ -0,0 +1,85 @@
+use indiefuture_cli::config::UserSettings;
+use indiefuture_cli::experiments::{self, PromptVariants, TurnOutcome, CONTROL};
+use indiefuture_cli::testing::TempWorkspace;
+use std::collections::BTreeMap;
+
+fn variants() -> PromptVariants {
+    UserSettings::parse(
+        r#"
+prompt_variants:
+  TaskTool:
+    terse: Plan in as few steps as possible.
+    search-first: Always search before reading.
+"#,
+    )
+    .unwrap()
+    .prompt_variants
+}
+
+fn outcome(variant: &str, status: &str, operations: usize) -> TurnOutcome {
+    TurnOutcome {
+        timestamp: "2026-10-17T12:00:00Z".to_string(),
+        session_id: "session".to_string(),
+        model: "gpt-4o".to_string(),
+        variants: BTreeMap::from([("TaskTool".to_string(), variant.to_string())]),
+        status: status.to_string(),
+        operations,
+        failed_operations: usize::from(status == "failed"),
+        files_changed: 0,
+        duration_seconds: 10.0,
+    }
+}
+
+#[test]
+fn variants_are_checked_when_the_user_settings_load() {
+    assert_eq!(variants()["TaskTool"].len(), 2);
+
+    assert!(UserSettings::parse("prompt_variants:\n  ExplainTool:\n    a: Explain\n").is_err());
+    assert!(UserSettings::parse("prompt_variants:\n  TaskTool:\n    control: Plan\n").is_err());
+    assert!(UserSettings::parse("prompt_variants:\n  TaskTool:\n    empty: ''\n").is_err());
+}
+
+#[test]
+fn sessions_keep_their_variant_and_split_between_all_of_them() {
+    let variants = variants();
+    assert_eq!(
+        experiments::assign(&variants, "session-1"),
+        experiments::assign(&variants, "session-1")
+    );
+
+    let used: Vec<String> = (0..60)
+        .map(|i| experiments::assign(&variants, &format!("session-{}", i))["TaskTool"].clone())
+        .collect();
+    for variant in [CONTROL, "terse", "search-first"] {
+        assert!(used.iter().any(|used| used == variant), "{}", variant);
+    }
+}
+
+#[test]
+fn outcomes_are_recorded_and_summarized_per_variant() {
+    let workspace = TempWorkspace::new().unwrap();
+    let path = workspace.path("experiments.jsonl");
+    for outcome in [
+        outcome("terse", "completed", 3),
+        outcome(CONTROL, "failed", 6),
+        outcome("terse", "failed", 5),
+    ] {
+        experiments::record(&path, &outcome).unwrap();
+    }
+    std::fs::write(
+        &path,
+        std::fs::read_to_string(&path).unwrap() + "not json\n",
+    )
+    .unwrap();
+
+    let outcomes = experiments::load(&path).unwrap();
+    assert_eq!(outcomes.len(), 3);
+
+    let summaries = experiments::summarize(&outcomes);
+    assert_eq!(summaries[0].variant, CONTROL);
+    assert_eq!(summaries[1].variant, "terse");
+    assert_eq!(summaries[1].turns, 2);
+    assert_eq!(summaries[1].completed, 1);
+    assert_eq!(summaries[1].mean_operations, 4.0);
+    assert!(experiments::render(&summaries).contains("50%"));
+}
@@ -0,0 +1,77 @@
+use indiefuture_cli::file_rank::{self, FileRanking};
+use indiefuture_cli::project_context;
+use indiefuture_cli::testing::TempWorkspace;
+
+#[test]
+fn paths_are_weighted_by_where_they_live() {
+    let weight = file_rank::path_weight;
+    assert!(weight("src/main.rs") > weight("src/parser.rs"));
+    assert!(weight("src/parser.rs") > weight("examples/demo.rs"));
+    assert!(weight("examples/demo.rs") > weight("tests/parser.rs"));
+    assert_eq!(weight("src/parser_test.go"), weight("tests/parser.rs"));
+    assert_eq!(weight("web/app.spec.ts"), weight("tests/parser.rs"));
+    assert!(weight("tests/parser.rs") > weight("vendor/lib/parser.rs"));
+}
+
+#[test]
+fn files_referred_to_by_others_rank_higher() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write("src/a_unused.rs", "fn lonely() {}\n")
+        .unwrap();
+    workspace
+        .write("src/parser.rs", "pub fn parse() -> Ast { todo!() }\n")
+        .unwrap();
+    workspace
+        .write(
+            "src/cli.rs",
+            "use crate::parser;\nfn run() { parser::parse(); }\n",
+        )
+        .unwrap();
+    workspace
+        .write("src/check.rs", "use crate::parser::parse;\n")
+        .unwrap();
+    workspace
+        .write("tests/parser.rs", "use app::parser;\n")
+        .unwrap();
+
+    let paths: Vec<String> = [
+        "src/a_unused.rs",
+        "src/check.rs",
+        "src/cli.rs",
+        "src/parser.rs",
+        "tests/parser.rs",
+    ]
+    .map(String::from)
+    .to_vec();
+    let ranking = file_rank::rank_files(workspace.root(), &paths);
+    assert!(ranking.score("src/parser.rs") > ranking.score("src/a_unused.rs"));
+    assert!(ranking.score("src/a_unused.rs") > ranking.score("tests/parser.rs"));
+
+    let mut ordered = paths.clone();
+    ranking.sort_by_importance(&mut ordered, |path| path);
+    assert_eq!(ordered[0], "src/parser.rs");
+    assert_eq!(ordered[4], "tests/parser.rs");
+
+    // Unranked paths fall back to their weight
+    assert_eq!(FileRanking::default().score("src/lib.rs"), 1.5);
+}
+
+#[test]
+fn the_repo_map_lists_important_files_first() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write("src/a_unused.rs", "pub fn lonely() {}\n")
+        .unwrap();
+    workspace
+        .write("src/parser.rs", "pub fn parse() {}\n")
+        .unwrap();
+    workspace
+        .write("src/cli.rs", "use crate::parser;\n")
+        .unwrap();
+
+    let index = project_context::index_symbols(workspace.root());
+    assert_eq!(index.files[0].path, "src/parser.rs");
+    assert_eq!(index.files[0].symbols[0].name, "parse");
+    assert!(!index.ranking.is_empty());
+}
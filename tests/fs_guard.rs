@@ -0,0 +1,177 @@
+#![cfg(unix)]
+
+use indiefuture_cli::fs_guard::{resolve, PathAccess};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A workspace and a sibling directory outside it holding a secret file
+fn setup() -> (TempDir, PathBuf, PathBuf) {
+    let dir = TempDir::new().unwrap();
+    let workspace = dir.path().join("workspace");
+    let outside = dir.path().join("outside");
+    std::fs::create_dir_all(workspace.join("src")).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(workspace.join("src/lib.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(outside.join("secret.txt"), "secret\n").unwrap();
+    (dir, workspace, outside)
+}
+
+fn refused(root: &Path, path: &str, access: PathAccess) -> bool {
+    resolve(root, Path::new(path), access).is_err()
+}
+
+#[test]
+fn regular_paths_inside_the_workspace_resolve() {
+    let (_dir, workspace, _) = setup();
+
+    let resolved = resolve(&workspace, Path::new("src/lib.rs"), PathAccess::Read).unwrap();
+    assert_eq!(
+        resolved,
+        workspace.canonicalize().unwrap().join("src/lib.rs")
+    );
+
+    // Files about to be created resolve through their existing parent
+    assert!(resolve(&workspace, Path::new("src/new.rs"), PathAccess::Write).is_ok());
+    assert!(resolve(&workspace, Path::new("src/../src/lib.rs"), PathAccess::Read).is_ok());
+}
+
+#[test]
+fn symlinked_file_escaping_the_workspace_is_refused() {
+    let (_dir, workspace, outside) = setup();
+    symlink(outside.join("secret.txt"), workspace.join("leak.txt")).unwrap();
+
+    assert!(refused(&workspace, "leak.txt", PathAccess::Read));
+    assert!(refused(&workspace, "leak.txt", PathAccess::Write));
+}
+
+#[test]
+fn symlinked_directory_escaping_the_workspace_is_refused() {
+    let (_dir, workspace, outside) = setup();
+    symlink(&outside, workspace.join("linked")).unwrap();
+
+    assert!(refused(&workspace, "linked", PathAccess::List));
+    assert!(refused(&workspace, "linked/secret.txt", PathAccess::Read));
+    assert!(refused(&workspace, "linked/new.txt", PathAccess::Write));
+    assert!(refused(
+        &workspace,
+        "linked/missing/new.txt",
+        PathAccess::Write
+    ));
+}
+
+#[test]
+fn parent_components_after_a_symlink_cannot_escape() {
+    let (_dir, workspace, outside) = setup();
+    std::fs::create_dir_all(outside.join("nested")).unwrap();
+    symlink(outside.join("nested"), workspace.join("nested")).unwrap();
+
+    // `nested/..` is the outside directory, even though it reads like the workspace root
+    assert!(refused(
+        &workspace,
+        "nested/../secret.txt",
+        PathAccess::Read
+    ));
+}
+
+#[test]
+fn dangling_symlinks_are_not_written_through() {
+    let (_dir, workspace, outside) = setup();
+    symlink(outside.join("created.txt"), workspace.join("dangling.txt")).unwrap();
+
+    assert!(refused(&workspace, "dangling.txt", PathAccess::Write));
+    assert!(!outside.join("created.txt").exists());
+}
+
+#[test]
+fn symlinks_within_the_workspace_are_followed() {
+    let (_dir, workspace, _) = setup();
+    symlink(workspace.join("src"), workspace.join("source")).unwrap();
+
+    let resolved = resolve(&workspace, Path::new("source/lib.rs"), PathAccess::Read).unwrap();
+    assert_eq!(
+        resolved,
+        workspace.canonicalize().unwrap().join("src/lib.rs")
+    );
+}
+
+#[test]
+fn absolute_symlink_path_into_the_workspace_is_checked_too() {
+    let (_dir, workspace, outside) = setup();
+    symlink(outside.join("secret.txt"), workspace.join("leak.txt")).unwrap();
+
+    let absolute = workspace.canonicalize().unwrap().join("leak.txt");
+    assert!(refused(
+        &workspace,
+        absolute.to_str().unwrap(),
+        PathAccess::Read
+    ));
+}
+
+#[test]
+fn paths_outside_the_workspace_are_read_but_never_written() {
+    let (_dir, workspace, outside) = setup();
+    let absolute = outside.join("new.txt");
+
+    assert!(!refused(
+        &workspace,
+        "../outside/secret.txt",
+        PathAccess::Read
+    ));
+    assert!(refused(&workspace, "../outside.txt", PathAccess::Write));
+    assert!(refused(
+        &workspace,
+        "src/../../outside.txt",
+        PathAccess::Write
+    ));
+    assert!(refused(
+        &workspace,
+        absolute.to_str().unwrap(),
+        PathAccess::Write
+    ));
+    assert!(refused(&workspace, "/tmp/outside.txt", PathAccess::Write));
+}
+
+#[test]
+fn fifos_are_refused() {
+    let (_dir, workspace, _) = setup();
+    let fifo = workspace.join("pipe");
+    let status = std::process::Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert!(refused(&workspace, "pipe", PathAccess::Read));
+    assert!(refused(&workspace, "pipe", PathAccess::Write));
+
+    // A link to the FIFO is no way around it
+    symlink(&fifo, workspace.join("pipe_link")).unwrap();
+    assert!(refused(&workspace, "pipe_link", PathAccess::Read));
+}
+
+#[test]
+fn character_devices_are_refused() {
+    let (_dir, workspace, _) = setup();
+
+    assert!(refused(&workspace, "/dev/null", PathAccess::Read));
+    assert!(refused(&workspace, "/dev/null", PathAccess::Write));
+}
+
+#[test]
+fn added_workspaces_are_written_only_when_editable() {
+    use indiefuture_cli::workspaces;
+
+    let (_dir, workspace, outside) = setup();
+    let added = workspaces::add(&workspace, Path::new("../outside"), false).unwrap();
+    let qualified = format!("{}:secret.txt", added.name);
+
+    let resolved = resolve(&workspace, Path::new(&qualified), PathAccess::Read).unwrap();
+    assert_eq!(resolved, outside.canonicalize().unwrap().join("secret.txt"));
+    assert_eq!(workspaces::display(&resolved), qualified);
+    assert!(refused(&workspace, &qualified, PathAccess::Write));
+
+    workspaces::set_editable(&added.name, true);
+    assert!(!refused(&workspace, &qualified, PathAccess::Write));
+    workspaces::remove(&added.name);
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "gemini")]
+
+use indiefuture_cli::ai::gemini;
+use indiefuture_cli::ai::{Message, MessageRole};
+use indiefuture_cli::config::Settings;
+use serde_json::json;
+
+fn message(role: MessageRole, content: &str) -> Message {
+    Message {
+        role,
+        content: content.to_string(),
+        name: None,
+    }
+}
+
+#[test]
+fn messages_become_alternating_contents() {
+    let request = gemini::contents(vec![
+        message(MessageRole::System, "You are a coding agent."),
+        message(MessageRole::User, "Context"),
+        message(MessageRole::User, "Rename the parser"),
+        message(MessageRole::Assistant, "Done"),
+        message(MessageRole::Developer, "Be brief."),
+    ]);
+
+    assert_eq!(
+        request["systemInstruction"]["parts"][0]["text"],
+        "You are a coding agent.\n\nBe brief."
+    );
+    assert_eq!(
+        request["contents"],
+        json!([
+            {"role": "user", "parts": [{"text": "Context\n\nRename the parser"}]},
+            {"role": "model", "parts": [{"text": "Done"}]}
+        ])
+    );
+}
+
+#[test]
+fn function_schemas_are_cut_down_to_what_gemini_accepts() {
+    let functions = json!([
+        {
+            "name": "read_file",
+            "description": "Read a file",
+            "parameters": {
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "path": {"type": "string", "default": "."},
+                    "lines": {"type": ["integer", "null"], "minimum": 1},
+                    "tags": {"type": "array", "items": {"type": "string", "examples": ["a"]}},
+                    "mode": {"oneOf": [{"type": "string", "enum": ["fast"]}, {"type": "string", "enum": ["full"]}]}
+                },
+                "required": ["path"]
+            }
+        },
+        {"name": "finish", "description": "Finish", "parameters": {"type": "object", "properties": {}}}
+    ]);
+
+    let declarations = gemini::function_declarations(&functions);
+    assert_eq!(
+        declarations[0]["parameters"],
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "lines": {"type": "integer", "nullable": true, "minimum": 1},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "mode": {"anyOf": [{"type": "string", "enum": ["fast"]}, {"type": "string", "enum": ["full"]}]}
+            },
+            "required": ["path"]
+        })
+    );
+    assert_eq!(
+        declarations[1],
+        json!({"name": "finish", "description": "Finish"})
+    );
+}
+
+#[test]
+fn responses_yield_text_and_tool_calls() {
+    let response = json!({"candidates": [{"content": {"role": "model", "parts": [
+        {"text": "Planning the edit", "thought": true},
+        {"text": "Reading it first."},
+        {"functionCall": {"name": "read_file", "args": {"path": "src/main.rs"}}}
+    ]}}]});
+
+    assert_eq!(gemini::answer_text(&response), "Reading it first.");
+    let calls = gemini::tool_calls(&response);
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].function.name, "read_file");
+    assert_eq!(
+        calls[0].function.arguments,
+        json!(r#"{"path":"src/main.rs"}"#)
+    );
+
+    let chunk = r#"{"candidates": [{"content": {"parts": [{"text": "Hel"}]}}]}"#;
+    assert_eq!(gemini::stream_token(chunk).unwrap().as_deref(), Some("Hel"));
+    assert_eq!(
+        gemini::stream_token(r#"{"candidates": [{"finishReason": "STOP"}]}"#).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn gemini_is_selected_with_its_own_key() {
+    let mut settings = Settings {
+        default_ai_provider: "gemini".to_string(),
+        openai_api_key: Some("sk-openai".to_string()),
+        ..Settings::default()
+    };
+    assert!(settings.validate().is_err());
+
+    settings.gemini_api_key = Some("gemini-key".to_string());
+    assert!(settings.validate().is_ok());
+    assert_eq!(settings.api_key(), "gemini-key");
+}
@@ -0,0 +1,132 @@
+//! Whole turns replayed from recorded transcripts in `tests/golden/`. Each case lists the
+//! workspace files, the task, the model's replies in order, and the expected outcome: files
+//! on disk, executed steps, context fragments and run stats.
+//!
+//! After an intended behaviour change, regenerate the expectations with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use indiefuture_cli::testing::{FakeAiClient, RecordedResponse, TempWorkspace};
+use indiefuture_cli::Settings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GoldenCase {
+    task: String,
+    #[serde(default)]
+    replan_checkpoints: bool,
+    files: BTreeMap<String, String>,
+    transcript: Vec<RecordedResponse>,
+    #[serde(default)]
+    expect: Option<Expected>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Expected {
+    files: BTreeMap<String, String>,
+    steps: Vec<String>,
+    fragments: Vec<Fragment>,
+    stats: Stats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Fragment {
+    source: String,
+    content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Stats {
+    operations: usize,
+    failed: usize,
+    declined: bool,
+    files_changed: Vec<String>,
+}
+
+async fn run_case(path: &Path) {
+    let case: GoldenCase = serde_yaml::from_str(&std::fs::read_to_string(path).unwrap())
+        .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+    let workspace = TempWorkspace::new().unwrap();
+    for (file, contents) in &case.files {
+        workspace.write(file, contents).unwrap();
+    }
+    workspace.init_git().unwrap();
+
+    let settings = Settings {
+        auto_format: false,
+        verify_after_edits: false,
+        replan_checkpoints: case.replan_checkpoints,
+        ..Settings::default()
+    };
+    let ai = FakeAiClient::from_transcript(&case.transcript);
+    let outcome = workspace.run_turn(&case.task, &ai, settings).await.unwrap();
+
+    // Temp paths differ between runs, so fragments refer to the workspace by name
+    let root = workspace.root().to_string_lossy().replace('\\', "/");
+    let actual = Expected {
+        files: workspace.files().unwrap(),
+        steps: outcome.steps.iter().map(|step| step.kind.clone()).collect(),
+        fragments: outcome
+            .context_memory
+            .get_fragments()
+            .iter()
+            .map(|fragment| Fragment {
+                source: fragment.source.clone(),
+                content: fragment.content.replace(&root, "<workspace>"),
+            })
+            .collect(),
+        stats: Stats {
+            operations: outcome.stats.operations,
+            failed: outcome.stats.failed,
+            declined: outcome.stats.declined,
+            files_changed: outcome.stats.files_changed.clone(),
+        },
+    };
+
+    assert_eq!(
+        ai.remaining(),
+        0,
+        "{}: the turn ended before using the whole transcript",
+        path.display()
+    );
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let updated = GoldenCase {
+            expect: Some(actual),
+            ..case
+        };
+        std::fs::write(path, serde_yaml::to_string(&updated).unwrap()).unwrap();
+        return;
+    }
+
+    let expected = case.expect.unwrap_or_else(|| {
+        panic!(
+            "{}: no expectations; run with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    });
+    assert_eq!(
+        actual,
+        expected,
+        "{}: outcome differs from the golden file",
+        path.display()
+    );
+}
+
+#[tokio::test]
+async fn golden_turns() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut cases: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no golden cases in {}", dir.display());
+
+    for case in cases {
+        run_case(&case).await;
+    }
+}
@@ -0,0 +1,135 @@
+use indiefuture_cli::agent_engine::SubtaskOutput;
+use indiefuture_cli::embeddings::SearchHit;
+use indiefuture_cli::hybrid_search::{self, HYBRID_SEARCH_SOURCE};
+use indiefuture_cli::memory::ContextMemory;
+use indiefuture_cli::subtasks::SubTaskType;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+const RETRY: &str = "use std::time::Duration;\n\n/// Wait longer after each failure\nfn backoff(attempt: u32) -> Duration {\n    Duration::from_millis(500 << attempt)\n}\n\nfn schedule_retry(attempt: u32) {\n    sleep(backoff(attempt));\n}\n";
+
+const CONFIG: &str = "fn load_config() {\n    // retry reading once\n}\n";
+
+fn chunk(path: &str, start_line: usize, end_line: usize) -> SearchHit {
+    SearchHit {
+        path: path.to_string(),
+        start_line,
+        end_line,
+        symbol: Some("backoff".to_string()),
+        similarity: 0.82,
+        embedding_model: "fake".to_string(),
+    }
+}
+
+#[test]
+fn queries_are_reduced_to_distinctive_keywords() {
+    assert_eq!(
+        hybrid_search::keywords("Where is the retry backoff scheduled? Retry!"),
+        vec!["retry", "backoff", "scheduled"]
+    );
+    assert!(hybrid_search::keywords("how is it").is_empty());
+}
+
+#[test]
+fn regions_matching_more_keywords_rank_first() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("src/config.rs", CONFIG).unwrap();
+    workspace.write("src/retry.rs", RETRY).unwrap();
+
+    let keywords = hybrid_search::keywords("retry backoff");
+    let hits = hybrid_search::keyword_hits(workspace.root(), &keywords, 5);
+    assert_eq!(hits.len(), 3);
+    assert_eq!(hits[0].path, "src/retry.rs");
+    assert_eq!((hits[0].start_line, hits[0].end_line), (5, 10));
+
+    // Equally good regions keep path order
+    assert_eq!(hits[1].path, "src/config.rs");
+    assert_eq!(
+        (hits[2].path.as_str(), hits[2].start_line),
+        ("src/retry.rs", 1)
+    );
+}
+
+#[test]
+fn a_place_both_searches_found_is_listed_once_and_first() {
+    let keyword_hits = vec![
+        SearchHit {
+            path: "src/config.rs".to_string(),
+            start_line: 1,
+            end_line: 3,
+            symbol: None,
+            similarity: 0.0,
+            embedding_model: String::new(),
+        },
+        SearchHit {
+            path: "src/retry.rs".to_string(),
+            start_line: 2,
+            end_line: 6,
+            symbol: None,
+            similarity: 0.0,
+            embedding_model: String::new(),
+        },
+    ];
+    let semantic_hits = vec![chunk("src/http.rs", 10, 30), chunk("src/retry.rs", 3, 10)];
+
+    let fused = hybrid_search::fuse(keyword_hits, semantic_hits, 10);
+    assert_eq!(fused.len(), 3);
+    assert_eq!(fused[0].hit.path, "src/retry.rs");
+    assert_eq!(fused[0].hit.start_line, 3);
+    assert!(fused[0].by_keywords && fused[0].by_meaning);
+    assert!(fused.iter().any(|hit| hit.by_meaning && !hit.by_keywords));
+    assert!(fused.iter().any(|hit| hit.by_keywords && !hit.by_meaning));
+}
+
+#[tokio::test]
+async fn search_adds_one_ranked_fragment() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("src/config.rs", CONFIG).unwrap();
+    workspace.write("src/retry.rs", RETRY).unwrap();
+    let shared_state = workspace.shared_state(&FakeAiClient::new()).unwrap();
+    shared_state
+        .embedding_index()
+        .refresh(
+            shared_state.ai_client.as_ref(),
+            workspace.root(),
+            "src/retry.rs",
+        )
+        .await
+        .unwrap();
+
+    let context_memory = Mutex::new(ContextMemory::default());
+    let found = shared_state
+        .hybrid_search("when is a retry scheduled", 5, &context_memory)
+        .await
+        .unwrap();
+    assert!(found >= 2);
+
+    let memory = context_memory.lock().await;
+    assert_eq!(memory.get_fragments().len(), 1);
+    let fragment = &memory.get_fragments()[0];
+    assert_eq!(fragment.source, HYBRID_SEARCH_SOURCE);
+    assert!(fragment.content.contains("(keywords: retry, scheduled)"));
+    assert!(fragment.content.contains("1. src/retry.rs:"));
+    assert!(fragment
+        .content
+        .contains("src/config.rs:1-3 (found by keywords;"));
+}
+
+#[tokio::test]
+async fn the_tool_hands_its_query_to_the_engine() {
+    let workspace = TempWorkspace::new().unwrap();
+    let search = SubTaskType::HybridSearchTool(
+        serde_json::from_value(json!({ "query": "retry backoff", "max_results": 3 })).unwrap(),
+    );
+    assert!(search.is_read_only());
+
+    let (output, _) = workspace.run(search, &FakeAiClient::new()).await;
+    match output {
+        Some(SubtaskOutput::HybridSearch(query, limit)) => {
+            assert_eq!(query, "retry backoff");
+            assert_eq!(limit, 3);
+        }
+        other => panic!("expected a hybrid search, got {:?}", other),
+    }
+}
@@ -0,0 +1,41 @@
+use console::Key;
+use indiefuture_cli::config::UserSettings;
+use indiefuture_cli::keys::{self, Action, KeyBindings};
+
+#[test]
+fn key_names_parse_to_the_keys_read_in_raw_mode() {
+    assert_eq!(keys::parse_key("y").unwrap(), Key::Char('y'));
+    assert_eq!(keys::parse_key("Y").unwrap(), Key::Char('Y'));
+    assert_eq!(keys::parse_key("ctrl-g").unwrap(), Key::Char('\u{7}'));
+    assert_eq!(keys::parse_key("Esc").unwrap(), Key::Escape);
+    assert_eq!(keys::parse_key("ctrl-c").unwrap(), Key::CtrlC);
+    assert!(keys::parse_key("ctrl-1").is_err());
+    assert!(keys::parse_key("yes").is_err());
+}
+
+#[test]
+fn keys_table_overrides_the_defaults() {
+    let settings = UserSettings::parse("keys:\n  approve: a\n  interrupt: ctrl-g\n").unwrap();
+    let bindings = settings.keys;
+
+    assert_eq!(bindings.action(&Key::Char('a')), Some(Action::Approve));
+    assert_eq!(bindings.action(&Key::Char('y')), None);
+    assert_eq!(bindings.action(&Key::Char('n')), Some(Action::Reject));
+    assert_eq!(
+        bindings.action(&Key::Char('\u{7}')),
+        Some(Action::Interrupt)
+    );
+    // Ctrl+C interrupts even when another key is bound to it
+    assert_eq!(bindings.action(&Key::CtrlC), Some(Action::Interrupt));
+}
+
+#[test]
+fn conflicting_or_unknown_bindings_are_rejected() {
+    let conflict = KeyBindings {
+        open_editor: "n".to_string(),
+        ..KeyBindings::default()
+    };
+    assert!(conflict.validate().is_err());
+    assert!(UserSettings::parse("keys:\n  approve: maybe\n").is_err());
+    assert!(UserSettings::parse("keys:\n  accept: y\n").is_err());
+}
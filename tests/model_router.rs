@@ -0,0 +1,86 @@
+use indiefuture_cli::model_router::{self, ModelRoute, ModelRouter};
+use indiefuture_cli::subtasks::SubTaskType;
+use indiefuture_cli::testing::FakeAiClient;
+use indiefuture_cli::usage::{UsageTracker, UsageTrackingAiClient};
+use std::sync::Arc;
+
+#[test]
+fn routes_name_a_tool_and_a_model() {
+    let routes = model_router::parse_routes(
+        " TaskTool=gpt-4o-mini, ProtoSubtask=ollama:qwen2.5-coder:7b,ExplainTool=llama3:8b,",
+    )
+    .unwrap();
+    assert_eq!(
+        routes,
+        vec![
+            ModelRoute {
+                tool: "TaskTool".to_string(),
+                provider: None,
+                model: "gpt-4o-mini".to_string(),
+            },
+            ModelRoute {
+                tool: "ProtoSubtask".to_string(),
+                provider: Some("ollama".to_string()),
+                model: "qwen2.5-coder:7b".to_string(),
+            },
+            // Only known providers are split off the model
+            ModelRoute {
+                tool: "ExplainTool".to_string(),
+                provider: None,
+                model: "llama3:8b".to_string(),
+            },
+        ]
+    );
+
+    assert!(model_router::parse_routes("").unwrap().is_empty());
+    assert!(model_router::parse_routes("PlannerTool=gpt-4o-mini").is_err());
+    assert!(model_router::parse_routes("TaskTool").is_err());
+    assert!(model_router::parse_routes("TaskTool=").is_err());
+}
+
+#[tokio::test]
+async fn routed_subtasks_run_on_their_model() {
+    let planner = FakeAiClient::new().text("plan");
+    let default = FakeAiClient::new().text("explanation");
+    let router = ModelRouter::default()
+        .route("TaskTool", Box::new(planner.clone()))
+        .route("ProtoSubtask", Box::new(planner.clone()));
+
+    let task = SubTaskType::Task("Add logging".to_string());
+    let answer = router
+        .client_for(&task, &default)
+        .generate_text(Vec::new())
+        .await
+        .unwrap();
+    assert_eq!(answer, "plan");
+
+    let explain = SubTaskType::ExplainTool(Default::default());
+    let answer = router
+        .client_for(&explain, &default)
+        .generate_text(Vec::new())
+        .await
+        .unwrap();
+    assert_eq!(answer, "explanation");
+    assert_eq!(planner.requests().len(), 1);
+    assert_eq!(default.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn wrapping_keeps_the_routes() {
+    let tracker = Arc::new(UsageTracker::new());
+    let router = ModelRouter::default()
+        .route("TaskTool", Box::new(FakeAiClient::new().text("plan")))
+        .map(|client| Box::new(UsageTrackingAiClient::new(client, tracker.clone())));
+    assert_eq!(
+        router.routes(),
+        vec![("TaskTool".to_string(), "fake:fake-model".to_string())]
+    );
+
+    let default = FakeAiClient::new();
+    router
+        .client_for(&SubTaskType::Task(String::new()), &default)
+        .generate_text(Vec::new())
+        .await
+        .unwrap();
+    assert!(!tracker.is_empty());
+}
@@ -0,0 +1,55 @@
+use indiefuture_cli::background::COMPACTED_SOURCE;
+use indiefuture_cli::memory::{ContextMemory, MemoryFragment, Provenance, USER_ANSWER_SOURCE};
+use indiefuture_cli::model_switch::{self, CURRENT_PLAN_SOURCE};
+use indiefuture_cli::testing::FakeAiClient;
+use tokio::sync::Mutex;
+
+fn fragment(source: &str, chars: usize) -> MemoryFragment {
+    MemoryFragment {
+        source: source.to_string(),
+        content: "x".repeat(chars),
+        provenance: Provenance::FileRead,
+        metadata: None,
+    }
+}
+
+#[tokio::test]
+async fn handoff_compacts_then_drops_to_fit_a_smaller_window() {
+    let mut memory = ContextMemory::default();
+    memory.pin_frag(model_switch::plan_fragment(&["Edit src/lib.rs".to_string()]).unwrap());
+    memory.add_frag(fragment(USER_ANSWER_SOURCE, 10));
+    for i in 0..12 {
+        memory.add_frag(fragment(&format!("file_read_{}", i), 1000));
+    }
+    let context_memory = Mutex::new(memory);
+
+    let ai_client = FakeAiClient::new().text("short summary");
+    let budget = model_switch::memory_budget("gpt-4o") / 40;
+    let handoff = model_switch::fit_memory(&ai_client, &context_memory, budget)
+        .await
+        .unwrap();
+
+    // The four oldest reads are summarized; the eight recent ones overflow and the oldest go
+    assert_eq!(handoff.compacted, 4);
+    assert!(!handoff.dropped.is_empty());
+    assert!(!handoff.pinned_over_budget);
+
+    let memory = context_memory.lock().await;
+    assert!(memory.content_len() + memory.pinned_len() <= budget);
+    let sources: Vec<_> = memory
+        .get_fragments()
+        .iter()
+        .map(|f| f.source.as_str())
+        .collect();
+    assert!(sources.contains(&COMPACTED_SOURCE));
+    assert!(sources.contains(&USER_ANSWER_SOURCE));
+    assert!(sources.contains(&"file_read_11"));
+    assert_eq!(memory.get_pinned_fragments()[0].source, CURRENT_PLAN_SOURCE);
+}
+
+#[test]
+fn smaller_models_have_smaller_windows() {
+    assert!(model_switch::context_window("gpt-4") < model_switch::context_window("gpt-4o"));
+    assert_eq!(model_switch::context_window("o3-mini"), 200_000);
+    assert_eq!(model_switch::context_window("some-new-model"), 128_000);
+}
@@ -0,0 +1,44 @@
+use indiefuture_cli::mutants;
+
+#[test]
+fn missed_mutants_are_read_with_or_without_a_column() {
+    let missed = "src/lib.rs:12:5: replace add -> i32 with 0\n\
+        src/parse.rs:40: replace == with != in Parser::accepts\n\
+        not a mutant\n";
+
+    let mutants = mutants::parse_missed(missed);
+
+    assert_eq!(mutants.len(), 2);
+    assert_eq!(mutants[0].file, "src/lib.rs");
+    assert_eq!(mutants[0].line, 12);
+    assert_eq!(mutants[0].description, "replace add -> i32 with 0");
+    assert_eq!(
+        mutants[1].to_string(),
+        "src/parse.rs:40: replace == with != in Parser::accepts"
+    );
+
+    let task = mutants::strengthen_task(&mutants);
+    assert!(task.contains("- src/lib.rs:12: replace add -> i32 with 0"));
+}
+
+#[test]
+fn only_library_and_binary_sources_are_mutated() {
+    let changed: Vec<String> = [
+        "src/lib.rs",
+        "tests/api.rs",
+        "crates/core/tests/it.rs",
+        "examples/demo.rs",
+        "build.rs",
+        "README.md",
+        "crates/core/src/lib.rs",
+        "src/lib.rs",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+
+    assert_eq!(
+        mutants::mutable_files(&changed),
+        vec!["crates/core/src/lib.rs", "src/lib.rs"]
+    );
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "ollama")]
+
+use indiefuture_cli::ai::ollama;
+use indiefuture_cli::ai::sse::Lines;
+use serde_json::json;
+
+fn functions() -> serde_json::Value {
+    json!([
+        {"name": "read_file", "description": "Read a file", "parameters": {"type": "object"}},
+        {"name": "run_command", "description": "Run a command", "parameters": {"type": "object"}}
+    ])
+}
+
+#[test]
+fn json_mode_answers_become_tool_calls() {
+    let answer = r#"{"tool_calls": [{"name": "read_file", "arguments": {"path": "src/main.rs"}}]}"#;
+    let calls = ollama::parse_tool_calls(answer, &functions()).unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].function.name, "read_file");
+    let arguments: serde_json::Value =
+        serde_json::from_str(calls[0].function.arguments.as_str().unwrap()).unwrap();
+    assert_eq!(arguments["path"], "src/main.rs");
+
+    // A bare call, with its arguments as a string
+    let answer = r#"{"name": "run_command", "arguments": "{\"command\": \"ls\"}"}"#;
+    let calls = ollama::parse_tool_calls(answer, &functions()).unwrap();
+    assert_eq!(calls[0].function.arguments, json!("{\"command\": \"ls\"}"));
+
+    // A list of calls nested the way OpenAI returns them
+    let answer =
+        r#"[{"function": {"name": "read_file", "arguments": {}}}, {"name": "run_command"}]"#;
+    let calls = ollama::parse_tool_calls(answer, &functions()).unwrap();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[1].function.arguments, json!("{}"));
+
+    assert!(ollama::parse_tool_calls(r#"{"name": "delete_repo"}"#, &functions()).is_none());
+    assert!(ollama::parse_tool_calls("I would read the file first.", &functions()).is_none());
+}
+
+#[test]
+fn streamed_lines_carry_message_tokens() {
+    let mut lines = Lines::default();
+    assert!(lines
+        .push(br#"{"message": {"role": "assistant", "content": "Hel"#)
+        .is_empty());
+    let complete = lines.push(b"lo\"}}\n{\"done\": true}\n");
+    assert_eq!(complete.len(), 2);
+
+    assert_eq!(
+        ollama::stream_token(&complete[0]).unwrap().as_deref(),
+        Some("Hello")
+    );
+    assert_eq!(ollama::stream_token(&complete[1]).unwrap(), None);
+    assert!(ollama::stream_token(r#"{"error": "model not found"}"#).is_err());
+}
@@ -0,0 +1,34 @@
+use indiefuture_cli::cli::parse_args;
+use indiefuture_cli::output;
+
+#[test]
+fn plain_text_drops_emoji_and_box_drawing() {
+    assert_eq!(
+        output::plain_text("⚠️ Error: File does not exist"),
+        "Error: File does not exist"
+    );
+    assert_eq!(
+        output::plain_text("✓ Verification passed"),
+        "Verification passed"
+    );
+    assert_eq!(output::plain_text("Task analyzed ✓"), "Task analyzed ");
+    assert_eq!(output::plain_text("│ cargo build"), "cargo build");
+    assert_eq!(
+        output::plain_text("Step 2/5: 🖥️ Run tests"),
+        "Step 2/5: Run tests"
+    );
+    // Text a reader can speak is kept, including punctuation and non-Latin letters
+    assert_eq!(
+        output::plain_text("naïve café — 日本 (50%)"),
+        "naïve café — 日本 (50%)"
+    );
+}
+
+#[test]
+fn plain_flag_is_parsed_before_the_subcommand() {
+    let args = parse_args(["--plain", "ask", "why?"].map(String::from)).unwrap();
+    assert!(args.plain);
+
+    let args = parse_args(Vec::<String>::new()).unwrap();
+    assert!(!args.plain);
+}
@@ -0,0 +1,203 @@
+use indiefuture_cli::memory::{ContextMemory, MemoryFragment, Provenance};
+use indiefuture_cli::subtasks::SubTaskType;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace, TurnOutcome};
+use indiefuture_cli::Settings;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+#[test]
+fn only_steps_independent_of_gathered_context_run_in_parallel() {
+    assert!(!SubTaskType::Task("Find the parser".to_string()).runs_in_parallel());
+    assert!(!SubTaskType::ExplainTool(Default::default()).runs_in_parallel());
+    assert!(SubTaskType::CrateDocsTool(Default::default()).runs_in_parallel());
+    assert!(SubTaskType::ErrorLookupTool(Default::default()).runs_in_parallel());
+    for subtask in SubTaskType::all_tools() {
+        if subtask.runs_in_parallel() {
+            assert!(subtask.is_read_only(), "{}", subtask.tool_name());
+        }
+    }
+}
+
+fn settings(parallel_tools: usize) -> Settings {
+    Settings {
+        auto_format: false,
+        verify_after_edits: false,
+        replan_checkpoints: false,
+        parallel_tools,
+        ..Settings::default()
+    }
+}
+
+/// A planner that turns the task into one step, which resolves to all of `calls`
+fn one_step_resolving_to(tool_name: &str, calls: Vec<(&str, serde_json::Value)>) -> FakeAiClient {
+    FakeAiClient::new()
+        .tool_call("Clarify", json!({ "questions": [] }))
+        .text("Look it up.")
+        .tool_call(
+            "AddSubtasks",
+            json!({ "subtasks": [
+                { "description": "Look it up", "tool_name": tool_name },
+            ] }),
+        )
+        .tool_calls(calls)
+}
+
+/// A turn whose one step resolves to three lookups, which auto mode runs all of
+async fn lookup_turn(
+    workspace: &TempWorkspace,
+    parallel_tools: usize,
+    context_memory: ContextMemory,
+) -> TurnOutcome {
+    let ai = one_step_resolving_to(
+        "GrepTool",
+        vec![
+            ("GrepTool", json!({ "pattern": "fn parse" })),
+            (
+                "FileReadTool",
+                json!({ "file_path": workspace.path("src/parser.rs").to_string_lossy() }),
+            ),
+            ("GlobTool", json!({ "pattern": "src/**/*.rs" })),
+        ],
+    );
+    let outcome = workspace
+        .run_turn_with_memory(
+            "Where is the parser?",
+            &ai,
+            settings(parallel_tools),
+            context_memory,
+        )
+        .await
+        .unwrap();
+    assert_eq!(ai.remaining(), 0);
+    outcome
+}
+
+fn parser_workspace() -> TempWorkspace {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write("src/parser.rs", "pub fn parse(text: &str) {}\n")
+        .unwrap();
+    workspace
+        .write("src/main.rs", "fn main() { parser::parse(\"\"); }\n")
+        .unwrap();
+    workspace
+}
+
+/// Fragments as stored, without the timestamps that differ between two runs
+fn stored(outcome: &TurnOutcome) -> Vec<MemoryFragment> {
+    let mut fragments = outcome.context_memory.get_fragments().to_vec();
+    for fragment in &mut fragments {
+        if let Some(metadata) = &mut fragment.metadata {
+            metadata.timestamp = None;
+        }
+    }
+    fragments
+}
+
+fn note(source: &str, content: &str) -> MemoryFragment {
+    MemoryFragment {
+        source: source.to_string(),
+        provenance: Provenance::UserProvided,
+        content: content.to_string(),
+        metadata: None,
+    }
+}
+
+#[tokio::test]
+async fn parallel_lookups_are_stored_in_planned_order() {
+    let workspace = parser_workspace();
+
+    let sequential = lookup_turn(&workspace, 1, ContextMemory::default()).await;
+    let parallel = lookup_turn(&workspace, 4, ContextMemory::default()).await;
+
+    let kinds = |outcome: &TurnOutcome| -> Vec<String> {
+        outcome.steps.iter().map(|step| step.kind.clone()).collect()
+    };
+    assert_eq!(kinds(&parallel), kinds(&sequential));
+    assert_eq!(kinds(&parallel).len(), 5);
+    assert_eq!(parallel.stats.operations, 5);
+    assert_eq!(parallel.stats.failed, 0);
+    assert_eq!(stored(&parallel), stored(&sequential));
+}
+
+#[tokio::test]
+async fn parallel_steps_keep_the_context_they_started_with() {
+    let workspace = parser_workspace();
+    let memory = || {
+        let mut memory = ContextMemory::default();
+        memory.pin_frag(note("conventions", "Parsers live in src/parser.rs."));
+        memory.add_frag(note("earlier_turn", "The user asked about parsing before."));
+        memory
+    };
+
+    let sequential = lookup_turn(&workspace, 1, memory()).await;
+    let parallel = lookup_turn(&workspace, 4, memory()).await;
+
+    // Pinned context is still there, and what the steps started with is not stored twice
+    let pinned = parallel.context_memory.get_pinned_fragments();
+    assert_eq!(
+        pinned,
+        &[note("conventions", "Parsers live in src/parser.rs.")]
+    );
+    let fragments = parallel.context_memory.get_fragments();
+    assert_eq!(
+        fragments
+            .iter()
+            .filter(|fragment| fragment.source == "earlier_turn")
+            .count(),
+        1
+    );
+    assert_eq!(stored(&parallel), stored(&sequential));
+}
+
+/// Three error lookups against a `rustc` that takes a second to explain each code finish in
+/// well under the three seconds they take one after another
+#[cfg(unix)]
+#[tokio::test]
+async fn independent_steps_run_at_the_same_time() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let workspace = TempWorkspace::new().unwrap();
+    let rustc = workspace
+        .write(
+            "bin/rustc",
+            "#!/bin/sh\nsleep 1\necho \"Explanation of $2\"\n",
+        )
+        .unwrap();
+    std::fs::set_permissions(&rustc, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var(
+        "PATH",
+        format!("{}:{}", workspace.path("bin").display(), path),
+    );
+
+    let codes = ["E0308", "E0382", "E0499"];
+    let ai = one_step_resolving_to(
+        "ErrorLookupTool",
+        codes
+            .iter()
+            .map(|code| ("ErrorLookupTool", json!({ "error": code })))
+            .collect(),
+    );
+    let started = Instant::now();
+    let outcome = workspace
+        .run_turn("What do these errors mean?", &ai, settings(3))
+        .await
+        .unwrap();
+
+    assert!(
+        started.elapsed() < Duration::from_millis(2_500),
+        "took {:?}",
+        started.elapsed()
+    );
+    let fragments = outcome.context_memory.get_fragments();
+    for code in codes {
+        assert!(
+            fragments.iter().any(|fragment| fragment
+                .content
+                .contains(&format!("Explanation of {}", code))),
+            "{} was not looked up",
+            code
+        );
+    }
+}
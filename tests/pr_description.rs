@@ -0,0 +1,35 @@
+use indiefuture_cli::git::FileChange;
+use indiefuture_cli::pr_description::coverage_notes;
+
+fn change(path: &str, added: usize) -> FileChange {
+    FileChange {
+        path: path.to_string(),
+        added,
+        removed: 0,
+    }
+}
+
+#[test]
+fn only_source_files_without_a_matching_test_change_are_untested() {
+    let notes = coverage_notes(&[
+        change("src/parser.rs", 12),
+        change("tests/parser.rs", 30),
+        change("src/lexer/mod.rs", 4),
+        change("src/lexer/lexer_test.rs", 8),
+        change("src/config.rs", 5),
+        change("src/cleanup.rs", 0),
+    ]);
+
+    assert!(
+        notes.contains("Source files with new code and no matching test change: src/config.rs\n"),
+        "{}",
+        notes
+    );
+}
+
+#[test]
+fn fully_tested_branches_report_none() {
+    let notes = coverage_notes(&[change("src/parser.rs", 12), change("tests/parser.rs", 30)]);
+
+    assert!(notes.contains("no matching test change: none"), "{}", notes);
+}
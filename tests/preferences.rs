@@ -0,0 +1,67 @@
+use indiefuture_cli::preferences::UserPreferences;
+use indiefuture_cli::storage::Storage;
+
+fn preferences() -> UserPreferences {
+    UserPreferences::open(&Storage::temporary("test").unwrap())
+}
+
+#[tokio::test]
+async fn reworded_corrections_recur() {
+    let preferences = preferences();
+
+    let first = preferences
+        .record_correction("Use 2-space indentation")
+        .await
+        .unwrap();
+    assert!(!first.is_active());
+
+    let second = preferences
+        .record_correction("please prefer 2 space indentation")
+        .await
+        .unwrap();
+    assert_eq!(second.occurrences, 2);
+    assert_eq!(second.text, "Use 2-space indentation");
+    assert!(second.is_active());
+    assert_eq!(preferences.all().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn opposite_and_unrelated_corrections_stay_apart() {
+    let preferences = preferences();
+
+    preferences.record_correction("use tabs").await.unwrap();
+    let negated = preferences
+        .record_correction("never use tabs")
+        .await
+        .unwrap();
+    let unrelated = preferences
+        .record_correction("use anyhow for errors")
+        .await
+        .unwrap();
+
+    assert_eq!(negated.occurrences, 1);
+    assert_eq!(unrelated.occurrences, 1);
+    assert_eq!(preferences.all().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn conflicting_corrections_stay_apart() {
+    let preferences = preferences();
+
+    preferences
+        .record_correction("Use 2-space indentation")
+        .await
+        .unwrap();
+    let conflicting = preferences
+        .record_correction("Use 4-space indentation")
+        .await
+        .unwrap();
+    let half_shared = preferences
+        .record_correction("Use 2-space tabs")
+        .await
+        .unwrap();
+
+    assert_eq!(conflicting.occurrences, 1);
+    assert_eq!(half_shared.occurrences, 1);
+    assert_eq!(preferences.all().await.unwrap().len(), 3);
+}
@@ -0,0 +1,25 @@
+use indiefuture_cli::ai::{self, create_ai_client};
+use indiefuture_cli::Settings;
+
+#[test]
+fn providers_left_out_of_the_build_say_so() {
+    for provider in ["claude", "gemini", "ollama"] {
+        let settings = Settings {
+            default_ai_provider: provider.to_string(),
+            claude_api_key: Some("claude-key".to_string()),
+            gemini_api_key: Some("gemini-key".to_string()),
+            ..Settings::default()
+        };
+        let client = create_ai_client(provider, "some-model", "key");
+
+        if ai::compiled_in(provider) {
+            assert!(client.is_ok(), "{}", provider);
+            assert!(settings.validate().is_ok(), "{}", provider);
+        } else {
+            let error = client.err().unwrap().to_string();
+            assert!(error.contains("not compiled into this build"), "{}", error);
+            assert!(error.contains(&format!("--features {}", provider)));
+            assert!(settings.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,17 @@
+#![cfg(unix)]
+
+use indiefuture_cli::pty;
+use tokio::process::Command;
+
+#[tokio::test]
+async fn interactive_commands_run_on_a_terminal() {
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        "test -t 0 && test -t 1 && printf 'on a \\033[1mtty\\033[0m\\n'; exit 3",
+    ]);
+
+    let run = pty::run_interactive(command).await.unwrap();
+    assert_eq!(run.transcript.trim_end(), "on a tty");
+    assert_eq!(run.exit_code, Some(3));
+}
@@ -0,0 +1,30 @@
+use indiefuture_cli::ai::reasoning::{self, ReasoningEffort};
+
+#[test]
+fn thinking_blocks_are_stripped_from_answers() {
+    assert_eq!(
+        reasoning::strip_thinking("<think>\nthe user wants a list\n</think>\n\n- one\n- two"),
+        "- one\n- two"
+    );
+    assert_eq!(
+        reasoning::strip_thinking("Answer <thinking>aside</thinking>here"),
+        "Answer here"
+    );
+    assert_eq!(reasoning::strip_thinking("<think>never closed"), "");
+    assert_eq!(reasoning::strip_thinking("  untouched  "), "  untouched  ");
+}
+
+#[test]
+fn o_series_models_are_reasoning_models() {
+    for model in ["o1", "o3-mini", "o4-mini"] {
+        assert!(reasoning::is_reasoning_model(model), "{}", model);
+    }
+    for model in ["gpt-4o", "omni", "claude-3-7-sonnet"] {
+        assert!(!reasoning::is_reasoning_model(model), "{}", model);
+    }
+    assert_eq!(
+        "high".parse::<ReasoningEffort>().unwrap(),
+        ReasoningEffort::High
+    );
+    assert!("max".parse::<ReasoningEffort>().is_err());
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use indiefuture_cli::remote_approval::RemoteApprover;
+
+async fn approver() -> Arc<RemoteApprover> {
+    Arc::new(
+        RemoteApprover::start("127.0.0.1:0", None, None, Duration::from_secs(30))
+            .await
+            .unwrap(),
+    )
+}
+
+/// Id of the one pending request, read from the token-protected listing
+async fn pending_id(remote: &RemoteApprover) -> String {
+    for _ in 0..50 {
+        let page = reqwest::get(remote.url())
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        if let Some((_, rest)) = page.split_once("/approve/") {
+            return rest.split('"').next().unwrap().to_string();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("no pending request listed");
+}
+
+#[tokio::test]
+async fn links_need_the_session_token_and_a_post() {
+    let remote = approver().await;
+    let waiting = tokio::spawn({
+        let remote = remote.clone();
+        async move { remote.request("Run rm -rf target", None).await }
+    });
+    let id = pending_id(&remote).await;
+
+    // Without the token nothing is listed or answered
+    let (base, _token) = remote.url().rsplit_once('/').unwrap();
+    let client = reqwest::Client::new();
+    let listing = client.get(format!("{}/", base)).send().await.unwrap();
+    assert_eq!(listing.status(), 404);
+    assert!(!listing.text().await.unwrap().contains(&id));
+    let guessed = client
+        .post(format!("{}/approve/{}", base, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(guessed.status(), 404);
+
+    // Opening the link, as a link preview would, only shows the button
+    let approve = format!("{}/approve/{}", remote.url(), id);
+    let opened = client.get(&approve).send().await.unwrap();
+    assert!(opened
+        .text()
+        .await
+        .unwrap()
+        .contains("<button>Approve</button>"));
+    assert!(!waiting.is_finished());
+
+    let answered = client.post(&approve).send().await.unwrap();
+    assert_eq!(answered.status(), 200);
+    assert!(waiting.await.unwrap());
+}
+
+#[tokio::test]
+async fn deny_link_declines() {
+    let remote = approver().await;
+    let waiting = tokio::spawn({
+        let remote = remote.clone();
+        async move { remote.request("Push to main", None).await }
+    });
+    let id = pending_id(&remote).await;
+
+    reqwest::Client::new()
+        .post(format!("{}/deny/{}", remote.url(), id))
+        .send()
+        .await
+        .unwrap();
+    assert!(!waiting.await.unwrap());
+}
+
+#[tokio::test]
+async fn forms_post_back_through_the_public_url() {
+    // Behind a proxy that serves the approver under a path prefix
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let remote = Arc::new(
+        RemoteApprover::start(
+            &format!("127.0.0.1:{}", port),
+            Some("https://example.test/approvals/".to_string()),
+            None,
+            Duration::from_secs(30),
+        )
+        .await
+        .unwrap(),
+    );
+    let waiting = tokio::spawn({
+        let remote = remote.clone();
+        async move { remote.request("Deploy", None).await }
+    });
+    let (_, token) = remote.url().rsplit_once('/').unwrap();
+
+    // The proxy strips the prefix before forwarding
+    let mut page = String::new();
+    for _ in 0..50 {
+        page = reqwest::get(format!("http://127.0.0.1:{}/{}", port, token))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        if page.contains("<form") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    assert!(
+        page.contains(&format!(
+            "action=\"https://example.test/approvals/{}/approve/",
+            token
+        )),
+        "{}",
+        page
+    );
+    waiting.abort();
+}
@@ -0,0 +1,117 @@
+use futures::StreamExt;
+use indiefuture_cli::ai::cache::CachingAiClient;
+use indiefuture_cli::ai::{AiClient, GenerationProfile, Message, MessageRole};
+use indiefuture_cli::storage::Storage;
+use indiefuture_cli::testing::FakeAiClient;
+use serde_json::json;
+use std::time::Duration;
+
+fn question(text: &str) -> Vec<Message> {
+    vec![Message {
+        role: MessageRole::User,
+        content: text.to_string(),
+        name: None,
+    }]
+}
+
+fn cached(fake: &FakeAiClient, storage: &Storage, ttl: Duration) -> CachingAiClient {
+    CachingAiClient::new(Box::new(fake.clone()), storage, ttl)
+}
+
+const DAY: Duration = Duration::from_secs(24 * 3600);
+
+#[tokio::test]
+async fn identical_requests_are_answered_from_storage() {
+    let storage = Storage::temporary("tester").unwrap();
+    let fake = FakeAiClient::new()
+        .text("The parser is in src/parser.rs")
+        .text("Something else");
+    let client = cached(&fake, &storage, DAY);
+
+    let first = client
+        .generate_text(question("Where is the parser?"))
+        .await
+        .unwrap();
+    let again = client
+        .generate_text(question("Where is the parser?"))
+        .await
+        .unwrap();
+    assert_eq!(first, again);
+    assert_eq!(fake.remaining(), 1);
+
+    // A later session over the same storage still has it
+    let later = cached(&FakeAiClient::new(), &storage, DAY);
+    assert_eq!(
+        later
+            .generate_text(question("Where is the parser?"))
+            .await
+            .unwrap(),
+        first
+    );
+
+    client
+        .generate_text(question("Where is the lexer?"))
+        .await
+        .unwrap();
+    assert_eq!(fake.remaining(), 0);
+}
+
+#[tokio::test]
+async fn function_calls_are_keyed_by_their_functions_and_options() {
+    let storage = Storage::temporary("tester").unwrap();
+    let fake = FakeAiClient::new()
+        .tool_call("GrepTool", json!({"pattern": "parse"}))
+        .tool_call("GlobTool", json!({"pattern": "*.rs"}))
+        .tool_call("GrepTool", json!({"pattern": "lex"}));
+    let client = cached(&fake, &storage, DAY);
+
+    let functions = json!([{"name": "GrepTool"}]);
+    for _ in 0..2 {
+        client
+            .chat_completion_with_functions(question("Find it"), functions.clone(), false)
+            .await
+            .unwrap();
+    }
+    assert_eq!(fake.remaining(), 2);
+
+    client
+        .chat_completion_with_functions(question("Find it"), json!([{"name": "GlobTool"}]), false)
+        .await
+        .unwrap();
+    client
+        .with_profile(GenerationProfile::Creative)
+        .chat_completion_with_functions(question("Find it"), functions, false)
+        .await
+        .unwrap();
+    assert_eq!(fake.remaining(), 0);
+}
+
+#[tokio::test]
+async fn streamed_answers_are_stored_once_complete() {
+    let storage = Storage::temporary("tester").unwrap();
+    let fake = FakeAiClient::new().text("Streamed answer");
+    let client = cached(&fake, &storage, DAY);
+
+    let tokens: Vec<_> = client
+        .stream_text(question("Explain"))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(tokens.len(), 1);
+
+    let replayed = client.generate_text(question("Explain")).await.unwrap();
+    assert_eq!(replayed, "Streamed answer");
+    assert_eq!(fake.remaining(), 0);
+}
+
+#[tokio::test]
+async fn expired_responses_are_requested_again() {
+    let storage = Storage::temporary("tester").unwrap();
+    let fake = FakeAiClient::new().text("old").text("new");
+    let client = cached(&fake, &storage, Duration::ZERO);
+
+    client.generate_text(question("Now?")).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(client.generate_text(question("Now?")).await.unwrap(), "new");
+}
@@ -0,0 +1,105 @@
+use indiefuture_cli::config::UserSettings;
+use indiefuture_cli::result_validation::{self, Validator};
+use indiefuture_cli::testing::FakeAiClient;
+use serde_json::json;
+
+#[test]
+fn validators_are_read_from_the_user_settings() {
+    let settings = UserSettings::parse(
+        r#"
+validators:
+  GrepTool:
+    - regex: '\S+:\d+:'
+  BashTool:
+    - ai_judge: The output is not a usage message
+    - json_schema: { type: object, required: [status] }
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        settings.validators["GrepTool"],
+        [Validator::Regex(r"\S+:\d+:".to_string())]
+    );
+    assert_eq!(
+        settings.validators["BashTool"][1],
+        Validator::JsonSchema(json!({"type": "object", "required": ["status"]}))
+    );
+
+    assert!(UserSettings::parse("validators:\n  GrepTool:\n    - regex: '(unclosed'\n").is_err());
+}
+
+#[test]
+fn json_results_are_checked_against_the_schema() {
+    let schema = json!({
+        "type": "object",
+        "required": ["files"],
+        "properties": {
+            "files": {"type": "array", "minItems": 1, "items": {"type": "string"}},
+            "status": {"enum": ["ok", "partial"]}
+        }
+    });
+
+    let check = |value| result_validation::schema_violation(&schema, &value, "$");
+    assert_eq!(
+        check(json!({"files": ["src/lib.rs"], "status": "ok"})),
+        None
+    );
+    assert_eq!(
+        check(json!({"status": "ok"})).as_deref(),
+        Some("$ is missing `files`")
+    );
+    assert_eq!(
+        check(json!({"files": []})).as_deref(),
+        Some("$.files has too few items (0)")
+    );
+    assert_eq!(
+        check(json!({"files": [3]})).as_deref(),
+        Some("$.files[0] is not of type string")
+    );
+    assert!(check(json!({"files": ["a"], "status": "done"})).is_some());
+}
+
+#[tokio::test]
+async fn results_failing_a_validator_are_rejected_with_a_reason() {
+    result_validation::configure(
+        [
+            (
+                "GrepTool".to_string(),
+                vec![Validator::Regex(r"\S+:\d+:".to_string())],
+            ),
+            (
+                "BashTool".to_string(),
+                vec![Validator::AiJudge("Not a usage message".to_string())],
+            ),
+        ]
+        .into(),
+    )
+    .unwrap();
+
+    let judge = FakeAiClient::new().tool_call(
+        "Judge",
+        json!({"valid": false, "reason": "It is the usage text of cargo"}),
+    );
+    assert_eq!(
+        result_validation::validate("GrepTool", "src/main.rs:12: fn main()", &judge).await,
+        None
+    );
+    assert_eq!(
+        result_validation::validate("GrepTool", "No matches", &judge)
+            .await
+            .as_deref(),
+        Some(r"it does not match /\S+:\d+:/")
+    );
+    assert_eq!(
+        result_validation::validate("BashTool", "Usage: cargo <command>", &judge)
+            .await
+            .as_deref(),
+        Some("It is the usage text of cargo")
+    );
+    // Tools without validators always pass
+    assert_eq!(
+        result_validation::validate("LSTool", "", &judge).await,
+        None
+    );
+}
@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use indiefuture_cli::ai::retry::{self, RetryPolicy, RetryingAiClient};
+use indiefuture_cli::ai::{AiClient, Message, MessageRole};
+use indiefuture_cli::error::{AgentError, AgentResult};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Answers with scripted results, one per call
+#[derive(Clone, Default)]
+struct ScriptedResults {
+    script: Arc<Mutex<VecDeque<AgentResult<String>>>>,
+    calls: Arc<Mutex<usize>>,
+}
+
+impl ScriptedResults {
+    fn new(script: Vec<AgentResult<String>>) -> Self {
+        Self {
+            script: Arc::new(Mutex::new(script.into())),
+            ..Self::default()
+        }
+    }
+
+    fn calls(&self) -> usize {
+        *self.calls.lock().unwrap()
+    }
+}
+
+#[async_trait]
+impl AiClient for ScriptedResults {
+    async fn generate_text(&self, _messages: Vec<Message>) -> AgentResult<String> {
+        *self.calls.lock().unwrap() += 1;
+        self.script
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| Ok("done".to_string()))
+    }
+
+    async fn generate_embeddings(&self, _text: &str) -> AgentResult<Vec<f32>> {
+        unimplemented!()
+    }
+
+    fn provider_name(&self) -> String {
+        "scripted".to_string()
+    }
+
+    fn model_name(&self) -> String {
+        "scripted".to_string()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(self.clone())
+    }
+}
+
+fn status(code: &str) -> AgentResult<String> {
+    Err(AgentError::AiApi(format!(
+        "OpenAI API returned error status: {}, body: {{}}",
+        code
+    )))
+}
+
+fn question() -> Vec<Message> {
+    vec![Message {
+        role: MessageRole::User,
+        content: "Explain the parser".to_string(),
+        name: None,
+    }]
+}
+
+fn configure_without_delays() {
+    retry::configure(RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::ZERO,
+        max_delay: Duration::ZERO,
+        jitter: false,
+    });
+}
+
+#[test]
+fn only_rate_limits_and_server_errors_are_transient() {
+    for code in ["429 Too Many Requests", "500 Internal Server Error", "503"] {
+        assert!(retry::is_transient(&status(code).unwrap_err()), "{}", code);
+    }
+    for code in ["400 Bad Request", "401 Unauthorized", "404 Not Found"] {
+        assert!(!retry::is_transient(&status(code).unwrap_err()), "{}", code);
+    }
+    assert!(retry::is_transient(&AgentError::AiApi(
+        "Claude API request failed: connection reset".to_string()
+    )));
+    assert!(!retry::is_transient(&AgentError::ContextLengthExceeded(
+        "openai".to_string()
+    )));
+}
+
+#[test]
+fn delays_double_up_to_the_maximum() {
+    let policy = RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(3),
+        jitter: false,
+    };
+    let delays: Vec<_> = (1..=4).map(|retry| policy.delay(retry)).collect();
+    assert_eq!(delays, [500, 1000, 2000, 3000].map(Duration::from_millis));
+
+    let jittered = RetryPolicy {
+        jitter: true,
+        ..policy
+    };
+    for _ in 0..20 {
+        let delay = jittered.delay(2);
+        assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+    }
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_until_the_attempts_run_out() {
+    configure_without_delays();
+
+    let inner = ScriptedResults::new(vec![status("429"), status("502 Bad Gateway")]);
+    let client = RetryingAiClient::new(Box::new(inner.clone()));
+    assert_eq!(client.generate_text(question()).await.unwrap(), "done");
+    assert_eq!(inner.calls(), 3);
+
+    let inner = ScriptedResults::new(vec![status("500"), status("500"), status("500")]);
+    let client = RetryingAiClient::new(Box::new(inner.clone()));
+    assert!(client.generate_text(question()).await.is_err());
+    assert_eq!(inner.calls(), 3);
+
+    let inner = ScriptedResults::new(vec![status("401 Unauthorized")]);
+    let client = RetryingAiClient::new(Box::new(inner.clone()));
+    assert!(client.generate_text(question()).await.is_err());
+    assert_eq!(inner.calls(), 1);
+}
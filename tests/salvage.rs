@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use indiefuture_cli::ai::salvage;
+use indiefuture_cli::ai::{AiClient, Message, MessageRole, TextStream};
+use indiefuture_cli::error::{AgentError, AgentResult};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Streams scripted tokens, one script per request
+#[derive(Clone, Default)]
+struct ScriptedStreams {
+    scripts: Arc<Mutex<VecDeque<Vec<AgentResult<String>>>>>,
+    requests: Arc<Mutex<Vec<Vec<Message>>>>,
+}
+
+impl ScriptedStreams {
+    fn new(scripts: Vec<Vec<AgentResult<String>>>) -> Self {
+        Self {
+            scripts: Arc::new(Mutex::new(scripts.into())),
+            ..Self::default()
+        }
+    }
+}
+
+#[async_trait]
+impl AiClient for ScriptedStreams {
+    async fn generate_text(&self, _messages: Vec<Message>) -> AgentResult<String> {
+        unimplemented!()
+    }
+
+    async fn stream_text(&self, messages: Vec<Message>) -> AgentResult<TextStream> {
+        self.requests.lock().unwrap().push(messages);
+        let script = self.scripts.lock().unwrap().pop_front().unwrap_or_default();
+        Ok(stream::iter(script).boxed())
+    }
+
+    async fn generate_embeddings(&self, _text: &str) -> AgentResult<Vec<f32>> {
+        unimplemented!()
+    }
+
+    fn provider_name(&self) -> String {
+        "scripted".to_string()
+    }
+
+    fn model_name(&self) -> String {
+        "scripted".to_string()
+    }
+
+    fn clone_box(&self) -> Box<dyn AiClient> {
+        Box::new(self.clone())
+    }
+}
+
+fn broken() -> AgentResult<String> {
+    Err(AgentError::AiApi(
+        "The response stream broke off".to_string(),
+    ))
+}
+
+fn question() -> Vec<Message> {
+    vec![Message {
+        role: MessageRole::User,
+        content: "Explain the parser".to_string(),
+        name: None,
+    }]
+}
+
+async fn collect(client: &ScriptedStreams) -> (String, Option<AgentError>) {
+    let mut tokens = salvage::stream_text(client, question()).await.unwrap();
+    let mut text = String::new();
+    while let Some(token) = tokens.next().await {
+        match token {
+            Ok(token) => text.push_str(&token),
+            Err(e) => return (text, Some(e)),
+        }
+    }
+    (text, None)
+}
+
+#[tokio::test]
+async fn broken_streams_are_continued_without_repeats() {
+    let client = ScriptedStreams::new(vec![
+        vec![
+            Ok("The parser reads tokens ".to_string()),
+            Ok("one at a time and builds".to_string()),
+            broken(),
+        ],
+        vec![
+            Ok("one at a time and builds an AST.".to_string()),
+            Ok(" Errors carry spans.".to_string()),
+        ],
+    ]);
+
+    let (text, error) = collect(&client).await;
+    assert!(error.is_none());
+    assert_eq!(
+        text,
+        "The parser reads tokens one at a time and builds an AST. Errors carry spans."
+    );
+
+    let requests = client.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let continuation = &requests[1];
+    assert_eq!(continuation[1].role, MessageRole::Assistant);
+    assert_eq!(
+        continuation[1].content,
+        "The parser reads tokens one at a time and builds"
+    );
+    assert!(continuation[2].content.contains("cut off"));
+}
+
+#[tokio::test]
+async fn streams_fail_once_the_attempts_are_used_up() {
+    let client = ScriptedStreams::new(vec![
+        vec![Ok("Partial".to_string()), broken()],
+        vec![broken()],
+        vec![broken()],
+        vec![Ok("never requested".to_string())],
+    ]);
+
+    let (text, error) = collect(&client).await;
+    assert_eq!(text, "Partial");
+    assert!(matches!(error, Some(AgentError::AiApi(_))));
+    assert_eq!(
+        client.requests.lock().unwrap().len(),
+        1 + salvage::attempts()
+    );
+}
+
+#[test]
+fn repeated_text_is_trimmed_only_when_long_enough() {
+    let written = "The parser reads tokens one at a time";
+    assert_eq!(
+        salvage::trim_overlap(written, "tokens one at a time, then parses"),
+        ", then parses"
+    );
+    assert_eq!(
+        salvage::trim_overlap(written, "time to parse"),
+        "time to parse"
+    );
+    assert!(!salvage::is_salvageable(
+        &AgentError::ContextLengthExceeded("too long".to_string())
+    ));
+}
@@ -0,0 +1,25 @@
+use indiefuture_cli::scrollback::{self, Multiplexer};
+
+#[test]
+fn capture_keeps_the_last_lines_above_the_blank_pane_bottom() {
+    let pane =
+        "$ cargo build\n   Compiling app v0.1.0   \nerror[E0425]: cannot find value `x`\n$ \n\n\n";
+
+    assert_eq!(
+        scrollback::last_lines(pane, 2),
+        "error[E0425]: cannot find value `x`\n$"
+    );
+    assert_eq!(scrollback::last_lines(pane, 50).lines().count(), 4);
+
+    let fragment = scrollback::fragment(&scrollback::last_lines(pane, 2), Multiplexer::Tmux);
+    assert_eq!(fragment.source, scrollback::SCROLLBACK_SOURCE);
+    assert!(fragment.content.contains("(tmux)"));
+    assert!(fragment.content.ends_with("cannot find value `x`\n$"));
+}
+
+#[test]
+fn no_pane_is_read_until_the_user_watches_one() {
+    assert_eq!(scrollback::watched(), None);
+    assert!(scrollback::watched_fragment().is_none());
+    assert_eq!(scrollback::unwatch(), None);
+}
@@ -0,0 +1,70 @@
+use indiefuture_cli::cli::{parse_args, CliCommand};
+use indiefuture_cli::self_update::{self, Release};
+use serde_json::json;
+
+#[test]
+fn versions_compare_numerically() {
+    assert!(self_update::is_newer("0.10.0", "0.9.3"));
+    assert!(self_update::is_newer("v1.0.0", "0.9.0"));
+    assert!(!self_update::is_newer("1.0.0-rc.1", "1.0.0"));
+    assert!(!self_update::is_newer("nightly", "0.1.0"));
+    assert_eq!(self_update::parse_version("2.1"), Some((2, 1, 0)));
+}
+
+#[test]
+fn checksums_are_found_for_the_platform_binary() {
+    let hash = self_update::sha256_hex(b"binary");
+    let other = "0".repeat(64);
+    let sums = format!(
+        "{}  indiefuture-aarch64-macos\n{} *indiefuture-x86_64-linux\n",
+        other, hash
+    );
+
+    assert_eq!(
+        self_update::parse_checksum(&sums, "indiefuture-x86_64-linux"),
+        Some(hash.clone())
+    );
+    assert_eq!(
+        self_update::parse_checksum(&sums, "indiefuture-x86_64-windows.exe"),
+        None
+    );
+    // A per-binary `.sha256` file may hold the hash alone
+    assert_eq!(
+        self_update::parse_checksum(&hash.to_uppercase(), "anything"),
+        Some(hash)
+    );
+}
+
+#[test]
+fn releases_are_read_from_the_github_api() {
+    let release = Release::from_json(&json!({
+        "tag_name": "v0.2.0",
+        "assets": [{
+            "name": "SHA256SUMS",
+            "browser_download_url": "https://example.com/SHA256SUMS"
+        }]
+    }))
+    .unwrap();
+
+    assert_eq!(release.version, "0.2.0");
+    assert_eq!(release.assets[0].name, "SHA256SUMS");
+}
+
+#[test]
+fn replacing_the_binary_leaves_no_staged_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let exe = dir.path().join("indiefuture");
+    std::fs::write(&exe, "old").unwrap();
+
+    self_update::replace_binary(&exe, b"new").unwrap();
+
+    assert_eq!(std::fs::read_to_string(&exe).unwrap(), "new");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn update_takes_a_check_flag() {
+    let args = parse_args(["update", "--check"].map(String::from)).unwrap();
+    assert_eq!(args.command, CliCommand::Update { check: true });
+    assert!(parse_args(["update", "--force-now"].map(String::from)).is_err());
+}
@@ -0,0 +1,108 @@
+use indiefuture_cli::agent_engine::SubtaskOutput;
+use indiefuture_cli::embeddings::{self, SearchHit, SEMANTIC_SEARCH_SOURCE};
+use indiefuture_cli::memory::ContextMemory;
+use indiefuture_cli::subtasks::SubTaskType;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+const PARSER: &str = "use std::io;\n\nfn parse(input: &str) -> Ast {\n    todo!()\n}\n";
+
+fn hit(path: &str, start_line: usize, end_line: usize) -> SearchHit {
+    SearchHit {
+        path: path.to_string(),
+        start_line,
+        end_line,
+        symbol: Some("parse".to_string()),
+        similarity: 0.87,
+        embedding_model: "fake".to_string(),
+    }
+}
+
+#[test]
+fn hits_become_fragments_citing_their_lines() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("src/parser.rs", PARSER).unwrap();
+
+    let fragment = embeddings::hit_fragment(workspace.root(), &hit("src/parser.rs", 3, 5)).unwrap();
+    assert_eq!(fragment.source, SEMANTIC_SEARCH_SOURCE);
+    assert_eq!(fragment.citation().unwrap(), "src/parser.rs:3-5");
+    assert!(fragment.content.starts_with(
+        "src/parser.rs:3-5 (similarity 0.87, in parse; FileReadTool offset 2 limit 3"
+    ));
+    assert!(fragment.content.contains("fn parse(input: &str) -> Ast {"));
+    assert!(!fragment.content.contains("use std::io;"));
+
+    // Lines past the end of a file that shrank are cited as they are now
+    let shrunk = embeddings::hit_fragment(workspace.root(), &hit("src/parser.rs", 4, 40)).unwrap();
+    assert_eq!(shrunk.citation().unwrap(), "src/parser.rs:4-5");
+
+    assert!(embeddings::hit_fragment(workspace.root(), &hit("src/gone.rs", 1, 2)).is_none());
+    assert!(embeddings::hit_fragment(workspace.root(), &hit("src/parser.rs", 90, 95)).is_none());
+}
+
+#[tokio::test]
+async fn search_adds_a_cited_fragment_per_chunk() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("src/parser.rs", PARSER).unwrap();
+    let shared_state = workspace.shared_state(&FakeAiClient::new()).unwrap();
+    shared_state
+        .embedding_index()
+        .refresh(
+            shared_state.ai_client.as_ref(),
+            workspace.root(),
+            "src/parser.rs",
+        )
+        .await
+        .unwrap();
+
+    let context_memory = Mutex::new(ContextMemory::default());
+    let found = shared_state
+        .semantic_search("parse the input", 5, &context_memory)
+        .await
+        .unwrap();
+    assert!(found > 0);
+
+    let memory = context_memory.lock().await;
+    let fragment = &memory.get_fragments()[0];
+    assert_eq!(fragment.source, SEMANTIC_SEARCH_SOURCE);
+    assert!(fragment.citation().unwrap().starts_with("src/parser.rs:"));
+}
+
+#[tokio::test]
+async fn the_tool_hands_its_query_to_the_engine() {
+    let workspace = TempWorkspace::new().unwrap();
+    let search = SubTaskType::SemanticSearchTool(
+        serde_json::from_value(json!({ "query": " where input is parsed " })).unwrap(),
+    );
+    assert!(search.is_read_only());
+
+    let (output, _) = workspace.run(search, &FakeAiClient::new()).await;
+    match output {
+        Some(SubtaskOutput::SemanticSearch(query, limit)) => {
+            assert_eq!(query, "where input is parsed");
+            assert_eq!(limit, 8);
+        }
+        other => panic!("expected a semantic search, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn reading_a_window_of_a_file_cites_its_lines() {
+    let workspace = TempWorkspace::new().unwrap();
+    let path = workspace.write("src/parser.rs", PARSER).unwrap();
+    let read = SubTaskType::FileReadTool(
+        serde_json::from_value(json!({
+            "file_path": path.to_string_lossy(),
+            "offset": 2,
+            "limit": 3,
+        }))
+        .unwrap(),
+    );
+
+    let (output, _) = workspace.run(read, &FakeAiClient::new()).await;
+    let Some(SubtaskOutput::AddToContextMemory(fragment)) = output else {
+        panic!("expected a memory fragment");
+    };
+    assert!(fragment.citation().unwrap().ends_with("parser.rs:3-5"));
+}
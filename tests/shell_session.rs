@@ -0,0 +1,89 @@
+#![cfg(unix)]
+
+use indiefuture_cli::shell_session::{self, InputChoice, SessionEvent};
+use indiefuture_cli::testing::TempWorkspace;
+use tokio::sync::mpsc;
+
+// Tests share the session's shell, so none relies on where it started
+
+#[tokio::test]
+async fn directory_and_exports_persist_between_commands() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace.write("nested/marker.txt", "found\n").unwrap();
+    let nested = workspace.root().join("nested");
+
+    let (events, _) = mpsc::unbounded_channel();
+    let first = shell_session::run(
+        &format!("cd '{}' && export GREETING=hello", nested.display()),
+        workspace.root(),
+        events,
+    )
+    .await
+    .unwrap();
+    assert_eq!(first.exit_code, 0);
+    assert!(first.cwd.ends_with("nested"));
+
+    let (events, mut received) = mpsc::unbounded_channel();
+    let second = shell_session::run(
+        "cat marker.txt; echo \"$GREETING\"; false",
+        workspace.root(),
+        events,
+    )
+    .await
+    .unwrap();
+    assert_eq!(second.output, "found\nhello");
+    assert_eq!(second.exit_code, 1);
+    assert_eq!(second.interruption, None);
+    for expected in ["found", "hello"] {
+        match received.recv().await {
+            Some(SessionEvent::Line(line)) => assert_eq!(line, expected),
+            other => panic!("expected a line, got {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn commands_waiting_for_input_are_asked_about() {
+    let workspace = TempWorkspace::new().unwrap();
+    let (events, mut received) = mpsc::unbounded_channel();
+
+    let answer = async {
+        while let Some(event) = received.recv().await {
+            if let SessionEvent::AwaitingInput { prompt, reply } = event {
+                reply.send(InputChoice::Send("world".to_string())).unwrap();
+                return prompt;
+            }
+        }
+        String::new()
+    };
+    let (output, prompt) = tokio::join!(
+        shell_session::run(
+            "printf 'Name: '; read name; echo \"hello $name\"",
+            workspace.root(),
+            events,
+        ),
+        answer
+    );
+
+    let output = output.unwrap();
+    assert_eq!(prompt, "Name:");
+    assert_eq!(output.output, "Name: hello world");
+    assert_eq!(output.exit_code, 0);
+    assert_eq!(output.interruption, None);
+}
+
+#[test]
+fn prompt_like_lines_are_recognized() {
+    for prompt in [
+        "Password:",
+        "Continue? [Y/n]",
+        "Overwrite (yes/no)",
+        "> ",
+        "Username: ",
+    ] {
+        assert!(shell_session::looks_like_prompt(prompt), "{}", prompt);
+    }
+    for line in ["Compiling crate v0.1.0", "Listening on port 3000", ""] {
+        assert!(!shell_session::looks_like_prompt(line), "{}", line);
+    }
+}
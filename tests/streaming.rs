@@ -0,0 +1,77 @@
+use futures::StreamExt;
+use indiefuture_cli::ai::sse::SseEvents;
+use indiefuture_cli::ai::{openai, AiClient, Message, MessageRole};
+use indiefuture_cli::explanation::ExplanationStream;
+use indiefuture_cli::testing::FakeAiClient;
+
+#[test]
+fn events_are_split_across_chunks() {
+    let mut events = SseEvents::default();
+    let body = "data: {\"a\": \"é\"}\r\n\r\n: keep-alive\n\nevent: done\ndata: one\ndata: two\n\n";
+    let bytes = body.as_bytes();
+    // Split inside the two-byte `é`
+    let split = body.find('é').unwrap() + 1;
+
+    assert!(events.push(&bytes[..split]).is_empty());
+    assert_eq!(events.push(&bytes[split..]), ["{\"a\": \"é\"}", "one\ntwo"]);
+}
+
+#[test]
+fn openai_events_yield_their_content() {
+    let token = r#"{"choices":[{"delta":{"content":"Hel"}}]}"#;
+    assert_eq!(openai::stream_token(token).unwrap().as_deref(), Some("Hel"));
+    let role_only = r#"{"choices":[{"delta":{"role":"assistant"}}]}"#;
+    assert_eq!(openai::stream_token(role_only).unwrap(), None);
+    assert_eq!(openai::stream_token("[DONE]").unwrap(), None);
+    assert!(openai::stream_token(r#"{"error":{"message":"overloaded"}}"#).is_err());
+}
+
+#[test]
+fn explanation_streams_by_line_without_the_assessment() {
+    colored::control::set_override(false);
+    let mut stream = ExplanationStream::default();
+
+    assert_eq!(stream.push("First li"), "");
+    assert_eq!(stream.push("ne.\nSecond"), "First line.\n");
+    assert_eq!(
+        stream.push(" line.\n```json\n{\"confidence\": "),
+        "Second line.\n"
+    );
+    assert_eq!(stream.push("\"low\"}\n```"), "");
+
+    let (rest, explanation) = stream.finish();
+    assert_eq!(rest, "");
+    assert_eq!(explanation.text, "First line.\nSecond line.");
+    assert_eq!(explanation.render_assessment(), "Confidence: low");
+}
+
+#[test]
+fn json_examples_are_shown_once_their_fence_closes() {
+    colored::control::set_override(false);
+    let mut stream = ExplanationStream::default();
+
+    assert_eq!(stream.push("```json\n{\"port\": 8080}\n"), "");
+    assert_eq!(stream.push("```\n"), "```json\n{\"port\": 8080}\n```\n");
+    let (rest, explanation) = stream.finish();
+    assert_eq!(rest, "");
+    assert!(explanation.assessment.is_none());
+}
+
+#[tokio::test]
+async fn clients_without_streaming_yield_the_whole_response() {
+    let ai = FakeAiClient::new().text("The whole answer.");
+    let messages = vec![Message {
+        role: MessageRole::User,
+        content: "Explain".to_string(),
+        name: None,
+    }];
+
+    let tokens: Vec<String> = ai
+        .stream_text(messages)
+        .await
+        .unwrap()
+        .map(|token| token.unwrap())
+        .collect()
+        .await;
+    assert_eq!(tokens, ["The whole answer."]);
+}
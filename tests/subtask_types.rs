@@ -0,0 +1,198 @@
+//! Every tool variant must be offered to the model with a schema that its arguments are parsed
+//! by, and have an icon and declared capabilities. `variant_index` has no wildcard arm, so adding
+//! a variant does not compile until it is listed here, and the tests then check the rest.
+
+use indiefuture_cli::ai::openai::{GptFunctionCall, GptToolCall};
+use indiefuture_cli::approval::ApprovalMode;
+use indiefuture_cli::subtasks::{Capability, SubTaskType};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Variants the model can call directly; `ProtoSubtask` is only produced by planning
+const CALLABLE_VARIANTS: usize = 21;
+
+fn variant_index(subtask: &SubTaskType) -> Option<usize> {
+    Some(match subtask {
+        SubTaskType::ProtoSubtask(_) => return None,
+        SubTaskType::Task(_) => 0,
+        SubTaskType::Bash(_) => 1,
+        SubTaskType::FileReadTool(_) => 2,
+        SubTaskType::FileEditTool(_) => 3,
+        SubTaskType::EditSymbolTool(_) => 4,
+        SubTaskType::InsertTool(_) => 5,
+        SubTaskType::EditSetTool(_) => 6,
+        SubTaskType::LSTool(_) => 7,
+        SubTaskType::GlobTool(_) => 8,
+        SubTaskType::GrepTool(_) => 9,
+        SubTaskType::ExplainTool(_) => 10,
+        SubTaskType::RememberTool(_) => 11,
+        SubTaskType::MemoryGrepTool(_) => 12,
+        SubTaskType::CrateSourceTool(_) => 13,
+        SubTaskType::CrateDocsTool(_) => 14,
+        SubTaskType::ErrorLookupTool(_) => 15,
+        SubTaskType::CoverageTool(_) => 16,
+        SubTaskType::TmuxPaneTool(_) => 17,
+        SubTaskType::SemanticSearchTool(_) => 18,
+        SubTaskType::HybridSearchTool(_) => 19,
+        SubTaskType::WriteFileTool(_) => 20,
+    })
+}
+
+#[test]
+fn every_callable_variant_is_offered_once() {
+    let offered: Vec<usize> = SubTaskType::all_tools()
+        .iter()
+        .map(|tool| {
+            variant_index(tool)
+                .unwrap_or_else(|| panic!("{} cannot be called directly", tool.tool_name()))
+        })
+        .collect();
+
+    let unique: HashSet<usize> = offered.iter().copied().collect();
+    assert_eq!(unique.len(), offered.len(), "a tool is offered twice");
+    assert_eq!(unique, (0..CALLABLE_VARIANTS).collect());
+}
+
+#[test]
+fn every_tool_has_a_schema_under_its_own_name() {
+    let schemas = SubTaskType::get_all_tool_schemas();
+    let names: Vec<&str> = schemas
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|schema| schema["name"].as_str().unwrap())
+        .collect();
+    let tools: Vec<&str> = SubTaskType::all_tools()
+        .iter()
+        .map(|tool| tool.tool_name())
+        .collect();
+    assert_eq!(names, tools);
+
+    for tool in SubTaskType::all_tools() {
+        let schema = SubTaskType::get_tool_schema_for_tool(tool.tool_name().to_string());
+        assert_eq!(schema.as_array().unwrap().len(), 1, "{}", tool.tool_name());
+        assert!(
+            schema[0]["parameters"]["properties"].is_object(),
+            "{} has no parameters",
+            tool.tool_name()
+        );
+    }
+}
+
+#[test]
+fn every_tool_has_an_icon_and_declared_capabilities() {
+    let mut icons = HashSet::new();
+    for tool in SubTaskType::all_tools() {
+        assert!(!tool.icon().is_empty(), "{} has no icon", tool.tool_name());
+        assert!(
+            icons.insert(tool.icon()),
+            "{} reuses an icon",
+            tool.tool_name()
+        );
+
+        // Falling back to every capability means the tool never declared its own
+        assert_ne!(
+            tool.capabilities(),
+            &Capability::ALL[..],
+            "{} does not declare its capabilities",
+            tool.tool_name()
+        );
+    }
+}
+
+/// A value for every property of `schema`, optional ones included
+fn sample(schema: &Value) -> Value {
+    if let Some(values) = schema["enum"].as_array() {
+        return values[0].clone();
+    }
+
+    match schema["type"].as_str() {
+        Some("object") => Value::Object(
+            schema["properties"]
+                .as_object()
+                .unwrap()
+                .iter()
+                .map(|(name, property)| (name.clone(), sample(property)))
+                .collect(),
+        ),
+        Some("array") => json!([sample(&schema["items"])]),
+        Some("string") => json!("src/lib.rs"),
+        Some("integer") => json!(1),
+        Some("boolean") => json!(true),
+        other => panic!("no sample for {:?} in {}", other, schema),
+    }
+}
+
+fn call(name: &str, arguments: Value) -> GptToolCall {
+    GptToolCall {
+        r#type: "function".to_string(),
+        function: GptFunctionCall {
+            name: name.to_string(),
+            arguments: Value::String(arguments.to_string()),
+        },
+    }
+}
+
+#[test]
+fn every_schema_property_is_parsed_by_its_tool() {
+    for tool in SubTaskType::all_tools() {
+        let schema = tool.get_ai_tool_schema();
+        let parameters = &schema["parameters"];
+        for required in parameters["required"].as_array().unwrap() {
+            assert!(
+                parameters["properties"][required.as_str().unwrap()].is_object(),
+                "{} requires the undeclared {}",
+                tool.tool_name(),
+                required
+            );
+        }
+
+        let parsed = SubTaskType::from_tool_call(call(tool.tool_name(), sample(parameters)))
+            .unwrap_or_else(|| {
+                panic!(
+                    "{} rejected arguments matching its schema",
+                    tool.tool_name()
+                )
+            });
+        assert_eq!(parsed.tool_name(), tool.tool_name());
+    }
+}
+
+#[test]
+fn arguments_of_the_wrong_type_are_rejected_not_ignored() {
+    let arguments = json!({ "file_path": "src/lib.rs", "limit": "ten" });
+    assert!(SubTaskType::from_tool_call(call("FileReadTool", arguments)).is_none());
+
+    let arguments = json!({ "path": "src", "ignore": "target" });
+    assert!(SubTaskType::from_tool_call(call("LSTool", arguments)).is_none());
+}
+
+#[test]
+fn read_only_tools_cannot_change_the_workspace() {
+    for tool in SubTaskType::all_tools() {
+        if tool.is_read_only() {
+            assert!(!tool.is_mutating(), "{} is not read-only", tool.tool_name());
+        }
+    }
+    let read_only: Vec<&str> = SubTaskType::all_tools()
+        .iter()
+        .filter(|tool| tool.is_read_only())
+        .map(|tool| tool.tool_name())
+        .collect();
+    assert!(read_only.contains(&"ExplainTool"));
+    assert!(read_only.contains(&"CrateDocsTool"));
+    assert!(read_only.contains(&"ErrorLookupTool"));
+    assert!(!read_only.contains(&"Bash"));
+    assert!(!read_only.contains(&"CoverageTool"));
+    assert!(!read_only.contains(&"RememberTool"));
+}
+
+#[test]
+fn remembered_facts_need_approval_without_touching_the_workspace() {
+    let remember = SubTaskType::RememberTool("Tests run with make check".to_string());
+
+    assert!(!remember.is_mutating());
+    assert!(!remember.is_read_only());
+    assert!(ApprovalMode::ConfirmWrites.requires_confirmation(&remember));
+    assert!(ApprovalMode::Plan.defers(&remember));
+}
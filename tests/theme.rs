@@ -0,0 +1,28 @@
+use indiefuture_cli::output;
+use indiefuture_cli::theme::{self, ColorTheme};
+
+#[test]
+fn themes_parse_by_name() {
+    for theme in ColorTheme::ALL {
+        assert_eq!(theme.to_string().parse::<ColorTheme>().unwrap(), theme);
+    }
+    assert!("solarized".parse::<ColorTheme>().is_err());
+    assert!(!ColorTheme::NoEmoji.shows_emoji());
+}
+
+#[test]
+fn diffs_and_markdown_keep_their_text() {
+    colored::control::set_override(false);
+
+    let diff = "--- a.rs\n+++ a.rs\n@@ -1 +1 @@\n-old\n+new\n same";
+    assert_eq!(theme::diff(diff), diff);
+
+    let markdown = "# Title\n- item with `code`\n```rust\nfn main() {}\n```";
+    assert_eq!(theme::markdown(markdown), markdown);
+}
+
+#[test]
+fn no_emoji_keeps_box_drawing() {
+    assert_eq!(output::without_emoji("✅ Done │ ok"), "Done │ ok");
+    assert_eq!(output::plain_text("✅ Done │ ok"), "Done ok");
+}
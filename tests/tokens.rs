@@ -0,0 +1,62 @@
+use indiefuture_cli::memory::{ContextMemory, MemoryFragment, MemoryMetadata, Provenance};
+use indiefuture_cli::tokens;
+
+fn fragment(source: &str, path: &str, content: String) -> MemoryFragment {
+    MemoryFragment {
+        source: source.to_string(),
+        content,
+        provenance: Provenance::FileRead,
+        metadata: Some(MemoryMetadata {
+            file_type: Some("file".to_string()),
+            path: Some(path.to_string()),
+            timestamp: None,
+            tags: Vec::new(),
+        }),
+    }
+}
+
+#[test]
+fn counts_follow_the_tokenizer_pieces() {
+    assert_eq!(tokens::count(""), 0);
+    assert_eq!(tokens::count("fn main() {}"), 4);
+    assert_eq!(tokens::count("Hello, world!"), 4);
+    assert_eq!(tokens::count("12345"), 2);
+    assert_eq!(tokens::count("    let x"), 3);
+    assert_eq!(tokens::count("日本語"), 3);
+}
+
+#[test]
+fn truncation_keeps_both_ends_within_the_budget() {
+    let text: String = (1..=400).map(|n| format!("line number {}\n", n)).collect();
+    let truncated = tokens::truncate(&text, 300);
+
+    assert!(tokens::count(&truncated) <= 300);
+    assert!(truncated.starts_with("line number 1\n"));
+    assert!(truncated.ends_with("line number 400\n"));
+    assert!(truncated.contains("tokens left out ...]\nline number"));
+    assert_eq!(tokens::truncate("short", 300), "short");
+}
+
+#[test]
+fn the_most_relevant_fragments_fill_the_budget() {
+    let mut memory = ContextMemory::default();
+    memory.pin_frag(fragment("knowledge", "NOTES.md", "Use tokio.".to_string()));
+    memory.add_frag(fragment("file_read", "src/lexer.rs", "lexer ".repeat(600)));
+    memory.add_frag(fragment(
+        "file_read",
+        "src/parser.rs",
+        "parser ".repeat(600),
+    ));
+    memory.add_frag(fragment("file_read", "src/cli.rs", "cli ".repeat(2000)));
+
+    let (fragments, omitted) = memory.fragments_within("how does the parser work", 1000);
+    let paths: Vec<&str> = fragments
+        .iter()
+        .map(|f| f.metadata.as_ref().unwrap().path.as_deref().unwrap())
+        .collect();
+    // The parser fits whole; the most recent of the rest is cut down to what is left
+    assert_eq!(paths, ["NOTES.md", "src/parser.rs", "src/cli.rs"]);
+    assert_eq!(omitted, 1);
+    assert_eq!(fragments[1].content, "parser ".repeat(600));
+    assert!(fragments[2].content.contains("tokens left out"));
+}
@@ -0,0 +1,54 @@
+use indiefuture_cli::config::ProjectSettings;
+use indiefuture_cli::testing::TempWorkspace;
+use indiefuture_cli::tool_env::ToolEnv;
+use std::collections::BTreeMap;
+
+#[test]
+fn long_values_and_secrets_are_masked() {
+    let env = ToolEnv::new(BTreeMap::from([
+        (
+            "DATABASE_URL".to_string(),
+            "postgres://app:pw@db/app".to_string(),
+        ),
+        ("PIN".to_string(), "731".to_string()),
+        ("EMPTY".to_string(), String::new()),
+    ]))
+    .with_secrets(["PIN".to_string()]);
+
+    assert_eq!(
+        env.mask("connecting to postgres://app:pw@db/app with pin 731, not 17310"),
+        "connecting to [masked DATABASE_URL] with pin [masked PIN], not 17310"
+    );
+}
+
+#[test]
+fn short_values_leave_file_contents_alone() {
+    let workspace = TempWorkspace::new().unwrap();
+    let config = "ci: true\nport: 80\nbacktrace: 1\n";
+    workspace.write("deploy.yaml", config).unwrap();
+    let env = ToolEnv::new(BTreeMap::from([
+        ("CI".to_string(), "true".to_string()),
+        ("PORT".to_string(), "80".to_string()),
+        ("RUST_BACKTRACE".to_string(), "1".to_string()),
+    ]));
+
+    assert_eq!(env.mask(&workspace.read("deploy.yaml").unwrap()), config);
+}
+
+#[test]
+fn passed_through_variables_come_from_the_agents_environment() {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write(
+            ".indiefuture/project.yaml",
+            "env:\n  RUST_LOG: debug\n  API_TOKEN: placeholder\npass_env:\n  - API_TOKEN\n  - TOOL_ENV_TEST_UNSET\n",
+        )
+        .unwrap();
+    std::env::set_var("API_TOKEN", "tok-from-env");
+
+    let vars = ProjectSettings::load(workspace.root()).unwrap().env_vars();
+
+    assert_eq!(vars["RUST_LOG"], "debug");
+    assert_eq!(vars["API_TOKEN"], "tok-from-env");
+    assert!(!vars.contains_key("TOOL_ENV_TEST_UNSET"));
+}
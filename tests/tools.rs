@@ -0,0 +1,298 @@
+use indiefuture_cli::agent_engine::SubtaskOutput;
+use indiefuture_cli::ai::GenerationProfile;
+use indiefuture_cli::subtasks::subtask::WriteFileToolInputs;
+use indiefuture_cli::subtasks::SubTaskType;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use serde_json::json;
+
+fn workspace() -> TempWorkspace {
+    let workspace = TempWorkspace::new().unwrap();
+    workspace
+        .write(
+            "src/lib.rs",
+            "pub fn parse_config() -> Config {\n    Config::default()\n}\n",
+        )
+        .unwrap();
+    workspace
+        .write("src/main.rs", "fn main() {\n    run();\n}\n")
+        .unwrap();
+    workspace.write("README.md", "# Demo\n").unwrap();
+    workspace
+}
+
+fn memory_content(output: Option<SubtaskOutput>) -> String {
+    match output {
+        Some(SubtaskOutput::AddToContextMemory(fragment)) => fragment.content,
+        other => panic!("expected a memory fragment, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn grep_finds_matching_lines() {
+    let workspace = workspace();
+    let grep = SubTaskType::GrepTool(
+        serde_json::from_value(json!({ "pattern": "parse_config", "include": "**/*.rs" })).unwrap(),
+    );
+
+    let (output, memory) = workspace.run(grep, &FakeAiClient::new()).await;
+
+    let content = memory_content(output);
+    assert!(content.contains("lib.rs"));
+    assert!(content.contains("pub fn parse_config() -> Config {"));
+    assert!(!content.contains("main.rs"));
+    assert_eq!(memory.fragment_count(), 1);
+}
+
+#[tokio::test]
+async fn glob_lists_only_matching_files_in_the_workspace() {
+    let workspace = workspace();
+    let glob =
+        SubTaskType::GlobTool(serde_json::from_value(json!({ "pattern": "src/*.rs" })).unwrap());
+
+    let content = memory_content(workspace.run(glob, &FakeAiClient::new()).await.0);
+
+    assert!(content.contains("src/lib.rs"));
+    assert!(content.contains("src/main.rs"));
+    assert!(!content.contains("README.md"));
+}
+
+#[tokio::test]
+async fn file_edit_rewrites_the_file_on_disk() {
+    let workspace = workspace();
+    let edit = SubTaskType::FileEditTool(
+        serde_json::from_value(json!({
+            "file_path": "src/main.rs",
+            "old_string": "    run();",
+            "new_string": "    run().unwrap();"
+        }))
+        .unwrap(),
+    );
+
+    let (output, _) = workspace.run(edit, &FakeAiClient::new()).await;
+
+    assert!(output.is_some());
+    assert_eq!(
+        workspace.read("src/main.rs").unwrap(),
+        "fn main() {\n    run().unwrap();\n}\n"
+    );
+}
+
+#[tokio::test]
+async fn edit_set_lands_all_or_nothing() {
+    let workspace = workspace();
+    let edits = SubTaskType::EditSetTool(
+        serde_json::from_value(json!({ "edits": [
+            { "file_path": "src/lib.rs", "old_string": "parse_config", "new_string": "load_config" },
+            { "file_path": "src/main.rs", "old_string": "not in the file", "new_string": "x" }
+        ]}))
+        .unwrap(),
+    );
+
+    let (output, _) = workspace.run(edits, &FakeAiClient::new()).await;
+
+    assert!(output.is_none());
+    assert!(workspace
+        .read("src/lib.rs")
+        .unwrap()
+        .contains("parse_config"));
+}
+
+#[tokio::test]
+async fn write_file_creates_files_and_overwrites_only_when_asked() {
+    let workspace = workspace();
+    let write = |file_path: &str, overwrite: bool| {
+        SubTaskType::WriteFileTool(
+            serde_json::from_value(json!({
+                "file_path": file_path,
+                "content": "pub fn run() {}\n",
+                "overwrite": overwrite
+            }))
+            .unwrap(),
+        )
+    };
+
+    let content = memory_content(
+        workspace
+            .run(write("src/run.rs", false), &FakeAiClient::new())
+            .await
+            .0,
+    );
+    assert!(content.contains("File created: src/run.rs"));
+    assert!(content.contains("+pub fn run() {}"));
+    assert_eq!(workspace.read("src/run.rs").unwrap(), "pub fn run() {}\n");
+
+    let (output, _) = workspace
+        .run(write("src/main.rs", false), &FakeAiClient::new())
+        .await;
+    assert!(output.is_none());
+    assert_eq!(
+        workspace.read("src/main.rs").unwrap(),
+        "fn main() {\n    run();\n}\n"
+    );
+
+    let content = memory_content(
+        workspace
+            .run(write("src/main.rs", true), &FakeAiClient::new())
+            .await
+            .0,
+    );
+    assert!(content.contains("File overwritten: src/main.rs"));
+    assert!(content.contains("-fn main() {"));
+    assert_eq!(workspace.read("src/main.rs").unwrap(), "pub fn run() {}\n");
+}
+
+#[test]
+fn write_file_previews_its_diff() {
+    let workspace = workspace();
+    let inputs: WriteFileToolInputs = serde_json::from_value(json!({
+        "file_path": "README.md",
+        "content": "# Demo\n\nUsage\n",
+        "overwrite": true
+    }))
+    .unwrap();
+
+    let review = inputs
+        .to_edit_set(workspace.root())
+        .unwrap()
+        .review()
+        .unwrap();
+    assert!(review.contains(" # Demo"));
+    assert!(review.contains("+Usage"));
+
+    let refused = WriteFileToolInputs {
+        overwrite: false,
+        ..inputs
+    };
+    assert!(refused.to_edit_set(workspace.root()).is_err());
+}
+
+#[tokio::test]
+async fn planner_turns_the_scripted_plan_into_proto_subtasks() {
+    let workspace = workspace();
+    let ai = FakeAiClient::new()
+        .tool_call("Clarify", json!({ "questions": [] }))
+        .text("Find the config parser, then explain it.")
+        .tool_call(
+            "AddSubtasks",
+            json!({ "subtasks": [
+                { "tool_name": "GrepTool", "description": "Search for parse_config" },
+                {
+                    "tool_name": "ExplainTool",
+                    "description": "Explain the config parser",
+                    "generation_profile": "balanced"
+                }
+            ]}),
+        );
+
+    let task = SubTaskType::Task("How is the config parsed?".to_string());
+    let (output, _) = workspace.run(task, &ai).await;
+
+    // The stack pops from the end, so the first step is pushed last
+    let Some(SubtaskOutput::PushSubtasks(subtasks)) = output else {
+        panic!("expected subtasks, got {:?}", output);
+    };
+    let steps: Vec<_> = subtasks
+        .iter()
+        .map(|subtask| match subtask {
+            SubTaskType::ProtoSubtask(step) => (step.tool_name.clone(), step.generation_profile),
+            other => panic!("expected a proto subtask, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            ("ExplainTool".to_string(), Some(GenerationProfile::Balanced)),
+            ("GrepTool".to_string(), None)
+        ]
+    );
+    assert_eq!(ai.remaining(), 0);
+    assert_eq!(ai.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn planner_asks_before_planning_an_ambiguous_request() {
+    let workspace = workspace();
+    let ai = FakeAiClient::new().tool_call(
+        "Clarify",
+        json!({ "questions": [
+            { "question": "Which files?", "options": ["src/lib.rs", "everything"] }
+        ]}),
+    );
+
+    let task = SubTaskType::Task("clean this up".to_string());
+    let (output, memory) = workspace.run(task, &ai).await;
+
+    match output {
+        Some(SubtaskOutput::AskUser(question, options)) => {
+            assert_eq!(question, "Which files?");
+            assert_eq!(options, ["src/lib.rs", "everything"]);
+        }
+        other => panic!("expected a question, got {:?}", other),
+    }
+    assert!(memory.answer_to("Which files?").is_none());
+}
+
+#[tokio::test]
+async fn proto_subtask_with_several_calls_offers_a_choice() {
+    let workspace = workspace();
+    let ai = FakeAiClient::new().tool_calls(vec![
+        ("GrepTool", json!({ "pattern": "parse_config" })),
+        ("GrepTool", json!({ "pattern": "Config" })),
+    ]);
+
+    let proto = SubTaskType::ProtoSubtask(
+        serde_json::from_value(json!({
+            "tool_name": "GrepTool",
+            "description": "Search for the config parser"
+        }))
+        .unwrap(),
+    );
+    let (output, _) = workspace.run(proto, &ai).await;
+
+    match output {
+        Some(SubtaskOutput::ChooseSubtask(_, choices)) => {
+            assert_eq!(choices.len(), 2);
+            assert!(choices[0].parameters.contains("parse_config"));
+        }
+        other => panic!("expected a choice, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn proto_subtask_generates_with_its_profile() {
+    let workspace = workspace();
+    let ai = FakeAiClient::new().tool_call("ExplainTool", json!({ "query": "Naming ideas" }));
+
+    let proto = SubTaskType::ProtoSubtask(
+        serde_json::from_value(json!({
+            "tool_name": "ExplainTool",
+            "description": "Brainstorm names for the config parser",
+            "generation_profile": "creative"
+        }))
+        .unwrap(),
+    );
+    let (output, _) = workspace.run(proto, &ai).await;
+
+    // The explanation written later keeps the profile the planner asked for
+    match output {
+        Some(SubtaskOutput::PushSubtasks(subtasks)) => match &subtasks[..] {
+            [SubTaskType::ExplainTool(inputs)] => {
+                assert_eq!(inputs.generation_profile, Some(GenerationProfile::Creative))
+            }
+            other => panic!("expected an explanation, got {:?}", other),
+        },
+        other => panic!("expected subtasks, got {:?}", other),
+    }
+    assert_eq!(ai.profiles(), [Some(GenerationProfile::Creative)]);
+}
+
+#[tokio::test]
+async fn exhausted_script_is_an_error_not_a_hang() {
+    let workspace = workspace();
+    let task = SubTaskType::Task("How is the config parsed?".to_string());
+
+    // Clarification falls back to planning, and planning fails without a response
+    let (output, _) = workspace.run(task, &FakeAiClient::new()).await;
+
+    assert!(output.is_none());
+}
@@ -0,0 +1,92 @@
+use indiefuture_cli::ai::{AiClient, ChatCompletionResponse, Message, MessageRole, TokenUsage};
+use indiefuture_cli::testing::FakeAiClient;
+use indiefuture_cli::usage::{self, UsageTracker, UsageTrackingAiClient};
+use serde_json::json;
+use std::sync::Arc;
+
+fn question(text: &str) -> Vec<Message> {
+    vec![Message {
+        role: MessageRole::User,
+        content: text.to_string(),
+        name: None,
+    }]
+}
+
+fn reported(prompt_tokens: u64, completion_tokens: u64) -> ChatCompletionResponse {
+    ChatCompletionResponse {
+        content: Some("Done".to_string()),
+        tool_calls: None,
+        usage: Some(TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+        }),
+    }
+}
+
+#[test]
+fn models_are_priced_by_their_longest_prefix() {
+    let mini = usage::price("openai", "gpt-4o-mini-2024-07-18").unwrap();
+    assert_eq!(mini.prompt, 0.15);
+    assert_eq!(usage::price("openai", "gpt-4o").unwrap().prompt, 2.50);
+    assert_eq!(usage::price("ollama", "llama3").unwrap().completion, 0.0);
+    assert!(usage::price("openai", "some-new-model").is_none());
+}
+
+#[tokio::test]
+async fn reported_usage_is_counted_and_text_is_estimated() {
+    let tracker = Arc::new(UsageTracker::new());
+    let fake = FakeAiClient::new()
+        .respond(reported(1200, 300))
+        .respond(reported(800, 100))
+        .text("A short answer");
+    let client = UsageTrackingAiClient::new(Box::new(fake), tracker.clone());
+
+    for _ in 0..2 {
+        client
+            .chat_completion_with_functions(question("Plan it"), json!([]), false)
+            .await
+            .unwrap();
+    }
+    client.generate_text(question("Explain")).await.unwrap();
+
+    let models = tracker.by_model();
+    let fake_usage = &models[&("fake".to_string(), "fake-model".to_string())];
+    assert_eq!(fake_usage.requests, 3);
+    assert_eq!(fake_usage.estimated_requests, 1);
+    assert!(fake_usage.prompt_tokens > 2000);
+    assert!(fake_usage.completion_tokens > 400);
+
+    // The fake model has no price, so neither has the session
+    assert_eq!(tracker.cost_usd(), None);
+    assert!(tracker.summary().contains("unknown"));
+}
+
+#[test]
+fn cost_adds_up_across_models() {
+    let tracker = UsageTracker::new();
+    assert!(tracker.is_empty());
+    tracker.record(
+        "openai",
+        "gpt-4o",
+        TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+        },
+        false,
+    );
+    tracker.record(
+        "ollama",
+        "llama3",
+        TokenUsage {
+            prompt_tokens: 5000,
+            completion_tokens: 5000,
+        },
+        true,
+    );
+
+    let cost = tracker.cost_usd().unwrap();
+    assert!((cost - 3.5).abs() < 1e-9);
+    let summary = tracker.summary();
+    assert!(summary.contains("$3.5000"));
+    assert!(summary.contains("1 request(s) without reported usage were estimated"));
+}
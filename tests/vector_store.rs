@@ -0,0 +1,65 @@
+use indiefuture_cli::memory::{self, ContextMemory, MemoryFragment, Provenance};
+use indiefuture_cli::testing::FakeAiClient;
+use indiefuture_cli::vector_store::VectorStore;
+use tokio::sync::Mutex;
+
+fn fragment(source: &str, content: &str) -> MemoryFragment {
+    MemoryFragment {
+        source: source.to_string(),
+        content: content.to_string(),
+        provenance: Provenance::FileRead,
+        metadata: None,
+    }
+}
+
+#[test]
+fn vectors_of_another_model_are_dropped() {
+    let mut store = VectorStore::new();
+    store.use_model("small");
+    store.insert("a", vec![1.0, 0.0]);
+    store.insert("b", vec![0.0, 1.0]);
+    assert!((store.similarity("a", &[1.0, 0.0]).unwrap() - 1.0).abs() < 1e-6);
+    assert!(store.similarity("a", &[1.0, 0.0, 0.0]).is_none());
+    assert!(store.similarity("c", &[1.0, 0.0]).is_none());
+
+    store.use_model("small");
+    assert_eq!(store.len(), 2);
+    store.use_model("large");
+    assert!(store.is_empty());
+}
+
+#[tokio::test]
+async fn fragments_are_ranked_by_similarity_to_the_step() {
+    let ai_client = FakeAiClient::new();
+    let context_memory = Mutex::new(ContextMemory::default());
+    {
+        let mut memory = context_memory.lock().await;
+        memory.add_frag(fragment("notes", "parse the config file"));
+        memory.add_frag(fragment("notes", "back off when rate limited"));
+        memory.add_frag(fragment("notes", "render the status bar"));
+    }
+
+    // Without embeddings, a fragment sharing no word with the step is left out
+    let by_keywords = context_memory
+        .lock()
+        .await
+        .relevant_fragments("back off when rate limited", 3);
+    assert_eq!(by_keywords.len(), 1);
+
+    memory::embed_fragments(&ai_client, &context_memory)
+        .await
+        .unwrap();
+    assert_eq!(context_memory.lock().await.embedded_count(), 3);
+    assert!(context_memory.lock().await.unembedded().is_empty());
+
+    memory::enable_semantic_ranking();
+    let ranked =
+        memory::semantic_fragments(&ai_client, &context_memory, "back off when rate limited", 3)
+            .await;
+    assert_eq!(ranked.len(), 3);
+    assert_eq!(ranked[0].content, "back off when rate limited");
+
+    // Embeddings of removed fragments are forgotten
+    context_memory.lock().await.clear();
+    assert_eq!(context_memory.lock().await.embedded_count(), 0);
+}
@@ -0,0 +1,35 @@
+use indiefuture_cli::voice;
+use std::path::Path;
+
+#[test]
+fn commands_get_the_audio_file_in_place_of_the_placeholder() {
+    let args = voice::command_args(
+        "whisper-cli -nt -m model.bin -f {file}",
+        Path::new("/tmp/my recording.wav"),
+    );
+
+    assert_eq!(
+        args,
+        vec![
+            "whisper-cli",
+            "-nt",
+            "-m",
+            "model.bin",
+            "-f",
+            "/tmp/my recording.wav"
+        ]
+    );
+}
+
+#[test]
+fn transcription_uploads_are_multipart_forms() {
+    let body = voice::multipart_body("b0", &[("model", "whisper-1")], "speech.wav", b"RIFF");
+    let body = String::from_utf8(body).unwrap();
+
+    assert_eq!(
+        body,
+        "--b0\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n\
+         --b0\r\nContent-Disposition: form-data; name=\"file\"; filename=\"speech.wav\"\r\n\
+         Content-Type: audio/wav\r\n\r\nRIFF\r\n--b0--\r\n"
+    );
+}
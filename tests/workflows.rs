@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use indiefuture_cli::agent_engine::AgentEngine;
+use indiefuture_cli::approval::ApprovalMode;
+use indiefuture_cli::remote_approval::RemoteApprover;
+use indiefuture_cli::testing::{FakeAiClient, TempWorkspace};
+use indiefuture_cli::workflows::Workflow;
+
+fn gated_workflow(marker: &std::path::Path) -> Workflow {
+    Workflow::parse(&format!(
+        "name: release\nsteps:\n  - prompt: Tag the release\n    gate:\n      command: touch {}\n",
+        marker.display()
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn confirm_writes_asks_before_running_a_gate_command() {
+    let workspace = TempWorkspace::new().unwrap();
+    let marker = workspace.path("gate-ran");
+    let workflow = gated_workflow(&marker);
+    let gate = workflow.steps[0].gate.as_ref().unwrap();
+    let remote = Arc::new(
+        RemoteApprover::start("127.0.0.1:0", None, None, Duration::from_secs(30))
+            .await
+            .unwrap(),
+    );
+    let engine = AgentEngine {
+        approval_mode: ApprovalMode::ConfirmWrites,
+        remote_approver: Some(Arc::clone(&remote)),
+        ..AgentEngine::default()
+    };
+
+    let deny = tokio::spawn({
+        let remote = Arc::clone(&remote);
+        async move {
+            for _ in 0..50 {
+                let page = reqwest::get(remote.url())
+                    .await
+                    .unwrap()
+                    .text()
+                    .await
+                    .unwrap();
+                if let Some((_, rest)) = page.split_once("/deny/") {
+                    let id = rest.split('"').next().unwrap();
+                    reqwest::Client::new()
+                        .post(format!("{}/deny/{}", remote.url(), id))
+                        .send()
+                        .await
+                        .unwrap();
+                    return true;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            false
+        }
+    });
+    let passed = gate
+        .check(&HashMap::new(), &engine, &FakeAiClient::new())
+        .await
+        .unwrap();
+
+    assert!(
+        deny.await.unwrap(),
+        "the gate command was never put up for approval"
+    );
+    assert!(!passed);
+    assert!(!marker.exists());
+}
+
+#[tokio::test]
+async fn auto_runs_gate_commands_without_asking() {
+    let workspace = TempWorkspace::new().unwrap();
+    let marker = workspace.path("gate-ran");
+    let workflow = gated_workflow(&marker);
+    let engine = AgentEngine {
+        approval_mode: ApprovalMode::Auto,
+        ..AgentEngine::default()
+    };
+
+    let passed = workflow.steps[0]
+        .gate
+        .as_ref()
+        .unwrap()
+        .check(&HashMap::new(), &engine, &FakeAiClient::new())
+        .await
+        .unwrap();
+
+    assert!(passed);
+    assert!(marker.exists());
+}